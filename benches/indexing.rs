@@ -0,0 +1,140 @@
+#![feature(test)]
+
+extern crate futures;
+#[macro_use]
+extern crate tantivy;
+extern crate test;
+
+use futures::Future;
+use tantivy::{Document, Index};
+use tantivy::merge_policy::NoMergePolicy;
+use tantivy::schema::{Schema, SchemaBuilder, FAST, STRING, TEXT};
+use tantivy::test_utils::CorpusGenerator;
+use test::Bencher;
+
+const NUM_DOCS: usize = 5_000;
+const NUM_SEGMENTS: usize = 4;
+
+// Indexes the documents in `NUM_SEGMENTS` segments.
+fn create_segments(corpus_generator: &CorpusGenerator) -> Index {
+    let index = Index::create_in_ram(corpus_generator.schema());
+    let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+    index_writer.set_merge_policy(Box::new(NoMergePolicy::default()));
+    for (doc_id, doc) in corpus_generator.documents(NUM_DOCS).enumerate() {
+        index_writer.add_document(doc).unwrap();
+        if (doc_id + 1) % (NUM_DOCS / NUM_SEGMENTS) == 0 {
+            index_writer.commit().unwrap();
+        }
+    }
+    index_writer.commit().unwrap();
+    index_writer.wait_merging_threads().unwrap();
+    index
+}
+
+#[bench]
+fn bench_indexing(b: &mut Bencher) {
+    let corpus_generator = CorpusGenerator::new(42);
+    let docs: Vec<_> = corpus_generator.documents(NUM_DOCS).collect();
+    b.iter(|| {
+        let index = Index::create_in_ram(corpus_generator.schema());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for doc in docs.iter().cloned() {
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+    });
+}
+
+/// Indexes documents with a unique id each, starting from a tiny
+/// term hash table.
+#[bench]
+fn bench_indexing_unique_terms(b: &mut Bencher) {
+    let mut schema_builder = SchemaBuilder::default();
+    let id_field = schema_builder.add_text_field("id", STRING);
+    let schema = schema_builder.build();
+    b.iter(|| {
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_term_table_num_bits(4).unwrap();
+        for i in 0..100_000 {
+            index_writer
+                .add_document(doc!(id_field => format!("id{}", i)))
+                .unwrap();
+        }
+        index_writer.commit().unwrap();
+        // 100k terms fit below the load factor in 2^18 buckets.
+        assert_eq!(index_writer.num_term_rehashes(), 14);
+        assert!(index_writer.max_term_probe_len() < 100);
+    });
+}
+
+/// Indexes and merges the segments.
+///
+/// The merge itself costs the difference with `bench_create_segments`.
+#[bench]
+fn bench_merge(b: &mut Bencher) {
+    let corpus_generator = CorpusGenerator::new(42);
+    b.iter(|| {
+        let index = create_segments(&corpus_generator);
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index_writer.wait_merging_threads().unwrap();
+    });
+}
+
+#[bench]
+fn bench_create_segments(b: &mut Bencher) {
+    let corpus_generator = CorpusGenerator::new(42);
+    b.iter(|| create_segments(&corpus_generator));
+}
+
+const NUM_FAST_FIELDS: usize = 50;
+
+// A schema with a text field and many fast fields, with its documents.
+fn many_fast_fields_corpus() -> (Schema, Vec<Document>) {
+    let mut schema_builder = SchemaBuilder::default();
+    let text_field = schema_builder.add_text_field("text", TEXT);
+    let fast_fields: Vec<_> = (0..NUM_FAST_FIELDS)
+        .map(|i| schema_builder.add_u64_field(&format!("fast{}", i), FAST))
+        .collect();
+    let docs = (0..20_000u64)
+        .map(|i| {
+            let mut doc = Document::default();
+            doc.add_text(text_field, &format!("w{} w{}", i % 17, i % 101));
+            for (k, &fast_field) in fast_fields.iter().enumerate() {
+                doc.add_u64(fast_field, i * (k as u64 + 1));
+            }
+            doc
+        })
+        .collect();
+    (schema_builder.build(), docs)
+}
+
+fn commit_many_fast_fields(b: &mut Bencher, num_serialization_threads: usize) {
+    let (schema, docs) = many_fast_fields_corpus();
+    b.iter(|| {
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 100_000_000).unwrap();
+        index_writer
+            .set_num_serialization_threads(num_serialization_threads)
+            .unwrap();
+        for doc in docs.iter().cloned() {
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+    });
+}
+
+/// Indexes documents with many fast fields, serialized by the indexing thread.
+#[bench]
+fn bench_commit_many_fast_fields(b: &mut Bencher) {
+    commit_many_fast_fields(b, 1);
+}
+
+/// Same as `bench_commit_many_fast_fields`, serializing the
+/// fast fields in 4 threads.
+#[bench]
+fn bench_commit_many_fast_fields_parallel(b: &mut Bencher) {
+    commit_many_fast_fields(b, 4);
+}
@@ -0,0 +1,100 @@
+#![feature(test)]
+
+extern crate tantivy;
+extern crate test;
+
+use tantivy::collector::{CountCollector, FacetCollector, TopCustomCollector};
+use tantivy::query::{AllQuery, BooleanQuery, Occur, PhraseQuery, Query, TermQuery};
+use tantivy::schema::{Facet, IndexRecordOption, Term};
+use tantivy::test_utils::{term_of_rank, CorpusFields, CorpusGenerator};
+use tantivy::{DocId, Index, Score, SegmentReader};
+use test::Bencher;
+
+const NUM_DOCS: usize = 20_000;
+
+fn corpus_index() -> (Index, CorpusFields) {
+    let corpus_generator = CorpusGenerator::new(42);
+    let index = corpus_generator.create_index_in_ram(NUM_DOCS).unwrap();
+    index.load_searchers().unwrap();
+    (index, corpus_generator.fields())
+}
+
+fn term_query(fields: &CorpusFields, rank: usize) -> Box<Query> {
+    Box::new(TermQuery::new(
+        Term::from_field_text(fields.text, &term_of_rank(rank)),
+        IndexRecordOption::WithFreqs,
+    ))
+}
+
+fn boolean_query(fields: &CorpusFields, occur: Occur, ranks: &[usize]) -> BooleanQuery {
+    let subqueries: Vec<(Occur, Box<Query>)> = ranks
+        .iter()
+        .map(|&rank| (occur, term_query(fields, rank)))
+        .collect();
+    BooleanQuery::from(subqueries)
+}
+
+fn bench_count(b: &mut Bencher, index: &Index, query: &Query) {
+    let searcher = index.searcher();
+    b.iter(|| {
+        let mut count_collector = CountCollector::default();
+        query.search(&*searcher, &mut count_collector).unwrap();
+        count_collector.count()
+    });
+}
+
+#[bench]
+fn bench_term_query(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    bench_count(b, &index, &*term_query(&fields, 10));
+}
+
+#[bench]
+fn bench_intersection_of_3_terms(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    bench_count(b, &index, &boolean_query(&fields, Occur::Must, &[1, 10, 100]));
+}
+
+#[bench]
+fn bench_union_of_3_terms(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    bench_count(b, &index, &boolean_query(&fields, Occur::Should, &[1, 10, 100]));
+}
+
+#[bench]
+fn bench_phrase_query(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    let terms: Vec<Term> = [0, 1]
+        .iter()
+        .map(|&rank| Term::from_field_text(fields.text, &term_of_rank(rank)))
+        .collect();
+    bench_count(b, &index, &PhraseQuery::from(terms));
+}
+
+#[bench]
+fn bench_facet_collector(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    let searcher = index.searcher();
+    b.iter(|| {
+        let mut facet_collector = FacetCollector::for_field(fields.category);
+        facet_collector.add_facet(Facet::root());
+        AllQuery.search(&*searcher, &mut facet_collector).unwrap();
+        facet_collector.harvest()
+    });
+}
+
+#[bench]
+fn bench_top_k_by_fast_field(b: &mut Bencher) {
+    let (index, fields) = corpus_index();
+    let searcher = index.searcher();
+    let query = term_query(&fields, 1);
+    b.iter(|| {
+        let segment_key_fn = |segment_reader: &SegmentReader| {
+            let skewed_reader = segment_reader.fast_field_reader::<u64>(fields.skewed).unwrap();
+            move |doc: DocId, _score: Score| skewed_reader.get(doc)
+        };
+        let mut top_collector = TopCustomCollector::with_key_fn(10, segment_key_fn);
+        query.search(&*searcher, &mut top_collector).unwrap();
+        top_collector.harvest()
+    });
+}
@@ -0,0 +1,147 @@
+extern crate tantivy;
+extern crate tempdir;
+
+#[macro_use]
+extern crate serde_json;
+
+use std::path::Path;
+use tempdir::TempDir;
+use tantivy::Index;
+use tantivy::schema::*;
+use tantivy::collector::{CountCollector, FacetCollector};
+use tantivy::query::{AllQuery, QueryParser};
+
+fn main() {
+    // Let's create a temporary directory for the
+    // sake of this example
+    if let Ok(dir) = TempDir::new("tantivy_facet_example_dir") {
+        run_example(dir.path()).unwrap();
+        dir.close().unwrap();
+    }
+}
+
+fn run_example(index_path: &Path) -> tantivy::Result<()> {
+    // # Defining the schema
+    //
+    // Facets are hierarchical categories, that look like
+    // file paths: `/category/fiction/fantasy`.
+    //
+    // A document can have any number of facets, and it
+    // also belongs to all of their ancestors: a book with the
+    // facet `/category/fiction/fantasy` also belongs
+    // to `/category/fiction` and `/category`.
+    let mut schema_builder = SchemaBuilder::default();
+    schema_builder.add_text_field("title", TEXT | STORED);
+
+    // A facet field is always indexed and stored.
+    schema_builder.add_facet_field("facet");
+    let schema = schema_builder.build();
+
+    // # Indexing documents
+    let index = Index::create(index_path, schema.clone())?;
+    let mut index_writer = index.writer(50_000_000)?;
+
+    let title = schema.get_field("title").unwrap();
+    let facet = schema.get_field("facet").unwrap();
+
+    // ### Adding facets to a document
+    //
+    // Facets are given by their text representation.
+    // A facet can also carry a display label, which is
+    // returned along with the facet counts.
+    let mut wind_doc = Document::default();
+    wind_doc.add_text(title, "The Name of the Wind");
+    wind_doc.add_facet(facet, "/category/fiction/fantasy");
+    wind_doc.add_facet_with_label(facet, "/lang/en", "English");
+    index_writer.add_document(wind_doc)?;
+
+    let mut dune_doc = Document::default();
+    dune_doc.add_text(title, "Dune");
+    dune_doc.add_facet(facet, "/category/fiction/scifi");
+    dune_doc.add_facet(facet, "/lang/en");
+    index_writer.add_document(dune_doc)?;
+
+    // Facets can also be expressed in JSON.
+    let json = json!({
+        "title": "Cien años de soledad",
+        "facet": ["/category/fiction/fantasy", "/lang/es"]
+    });
+    index_writer.add_document(schema.parse_document(&json.to_string())?)?;
+
+    let json = json!({
+        "title": "Historia de España",
+        "facet": ["/category/history", "/lang/es"]
+    });
+    index_writer.add_document(schema.parse_document(&json.to_string())?)?;
+
+    index_writer.commit()?;
+
+    // # Counting facets
+    index.load_searchers()?;
+    let searcher = index.searcher();
+
+    // The `FacetCollector` counts the children of the
+    // facets it is given, among the documents matching the query.
+    //
+    // Here, we count the direct children of `/category`
+    // and of `/lang`, over all of the documents.
+    let mut facet_collector = FacetCollector::for_field(facet);
+    facet_collector.add_facet("/category");
+    facet_collector.add_facet("/lang");
+    searcher.search(&AllQuery, &mut facet_collector)?;
+    let facet_counts = facet_collector.harvest();
+
+    // The counts are sorted by facet.
+    //
+    // /category/fiction: 3
+    // /category/history: 1
+    for (counted_facet, count) in facet_counts.get("/category") {
+        println!("{}: {}", counted_facet, count);
+    }
+
+    // The labels given at indexing time are available as well.
+    //
+    // /lang/en (English): 2
+    // /lang/es: 2
+    for (counted_facet, label_opt, count) in facet_counts.get_with_labels("/lang") {
+        match label_opt {
+            Some(label) => println!("{} ({}): {}", counted_facet, label, count),
+            None => println!("{}: {}", counted_facet, count),
+        }
+    }
+
+    // `top_k` only returns the facets with the highest counts.
+    //
+    // /category/fiction: 3
+    for (counted_facet, count) in facet_counts.top_k("/category", 1) {
+        println!("{}: {}", counted_facet, count);
+    }
+
+    // # Drilling down
+    //
+    // The query parser searches a facet, and all of its
+    // descendants, with a quoted facet path.
+    //
+    // Let's count the fiction subcategories
+    // of the spanish books.
+    let query_parser = QueryParser::for_index(&index, vec![title]);
+    let query = query_parser.parse_query("facet:\"/lang/es\"")?;
+    let mut facet_collector = FacetCollector::for_field(facet);
+    facet_collector.add_facet("/category/fiction");
+    searcher.search(&*query, &mut facet_collector)?;
+
+    // /category/fiction/fantasy: 1
+    for (counted_facet, count) in facet_collector.harvest().get("/category/fiction") {
+        println!("{}: {}", counted_facet, count);
+    }
+
+    // Facet queries can be combined with other queries.
+    let query = query_parser.parse_query("+facet:\"/category/fiction\" +title:dune")?;
+    let mut count_collector = CountCollector::default();
+    searcher.search(&*query, &mut count_collector)?;
+
+    // 1 fiction book matches `dune`.
+    println!("{} fiction book matches `dune`.", count_collector.count());
+
+    Ok(())
+}
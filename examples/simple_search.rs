@@ -94,7 +94,7 @@ fn run_example(index_path: &Path) -> tantivy::Result<()> {
     );
 
     // ... and add it to the `IndexWriter`.
-    index_writer.add_document(old_man_doc);
+    index_writer.add_document(old_man_doc)?;
 
     // ### Create a document directly from json.
     //
@@ -115,7 +115,7 @@ fn run_example(index_path: &Path) -> tantivy::Result<()> {
     });
     let mice_and_men_doc = schema.parse_document(&json.to_string())?;
 
-    index_writer.add_document(mice_and_men_doc);
+    index_writer.add_document(mice_and_men_doc)?;
 
     // Multi-valued field are allowed, they are
     // expressed in JSON by an array.
@@ -129,7 +129,7 @@ fn run_example(index_path: &Path) -> tantivy::Result<()> {
     });
     let frankenstein_doc = schema.parse_document(&json.to_string())?;
 
-    index_writer.add_document(frankenstein_doc);
+    index_writer.add_document(frankenstein_doc)?;
 
     // This is an example, so we will only index 3 documents
     // here. You can check out tantivy's tutorial to index
@@ -2,6 +2,7 @@ use Result;
 use collector::Collector;
 use SegmentLocalId;
 use SegmentReader;
+use Searcher;
 use DocId;
 use Score;
 
@@ -41,6 +42,12 @@ impl<Left: Collector, Right: Collector> ChainedCollector<Left, Right> {
 }
 
 impl<Left: Collector, Right: Collector> Collector for ChainedCollector<Left, Right> {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        self.left.prepare(searcher)?;
+        self.right.prepare(searcher)?;
+        Ok(())
+    }
+
     fn set_segment(
         &mut self,
         segment_local_id: SegmentLocalId,
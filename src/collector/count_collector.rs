@@ -43,10 +43,10 @@ mod tests {
     fn test_count_collector() {
         let mut count_collector = CountCollector::default();
         assert_eq!(count_collector.count(), 0);
-        count_collector.collect(0u32, 1f32);
+        count_collector.collect(0u32, 1.0);
         assert_eq!(count_collector.count(), 1);
         assert_eq!(count_collector.count(), 1);
-        count_collector.collect(1u32, 1f32);
+        count_collector.collect(1u32, 1.0);
         assert_eq!(count_collector.count(), 2);
         assert!(!count_collector.requires_scoring());
     }
@@ -1,7 +1,7 @@
 use std::mem;
 use collector::Collector;
 use fastfield::FacetReader;
-use schema::Field;
+use schema::{Field, FieldType, Schema};
 use std::cell::UnsafeCell;
 use schema::Facet;
 use std::collections::BTreeMap;
@@ -13,7 +13,7 @@ use termdict::TermStreamerBuilder;
 use std::collections::BTreeSet;
 use termdict::TermMerger;
 use docset::SkipResult;
-use std::{usize, u64};
+use std::{u32, u64, usize};
 use std::iter::Peekable;
 
 use DocId;
@@ -21,7 +21,10 @@ use Result;
 use Score;
 use SegmentReader;
 use SegmentLocalId;
+use Searcher;
+use error::ErrorKind;
 use std::cmp::Ordering;
+use serde::{Serialize, Serializer};
 
 struct Hit<'a> {
     count: u64,
@@ -48,13 +51,101 @@ impl<'a> Ord for Hit<'a> {
     }
 }
 
+// With `FacetCounterWidth::Auto`, segments with at least this number
+// of facet counters use 32 bits counters.
+const AUTO_U32_MIN_NUM_COUNTERS: usize = 1 << 16;
+
+/// Width of the per-segment facet counters of the `FacetCollector`.
+///
+/// The counters of every segment are kept until the collector is harvested,
+/// so 32 bits counters halve the memory usage of the collection when
+/// a large number of facets are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacetCounterWidth {
+    /// Uses 32 bits counters for the segments with a large
+    /// number of facets to count, and 64 bits counters otherwise.
+    Auto,
+    /// Uses 32 bits counters.
+    ///
+    /// Counts remain exact : if one of its counters overflows,
+    /// the counters of the segment are promoted to 64 bits.
+    U32,
+    /// Uses 64 bits counters.
+    U64,
+}
+
+#[derive(Debug)]
+enum FacetCounters {
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+}
+
+impl Default for FacetCounters {
+    fn default() -> FacetCounters {
+        FacetCounters::U64(Vec::new())
+    }
+}
+
+impl FacetCounters {
+    fn with_width(counter_width: FacetCounterWidth, num_counters: usize) -> FacetCounters {
+        let use_u32 = match counter_width {
+            FacetCounterWidth::Auto => num_counters >= AUTO_U32_MIN_NUM_COUNTERS,
+            FacetCounterWidth::U32 => true,
+            FacetCounterWidth::U64 => false,
+        };
+        if use_u32 {
+            FacetCounters::U32(vec![0u32; num_counters])
+        } else {
+            FacetCounters::U64(vec![0u64; num_counters])
+        }
+    }
+
+    fn increment(&mut self, counter_id: usize) {
+        let overflow = match *self {
+            FacetCounters::U32(ref mut counts) => {
+                if counts[counter_id] == u32::MAX {
+                    true
+                } else {
+                    counts[counter_id] += 1;
+                    false
+                }
+            }
+            FacetCounters::U64(ref mut counts) => {
+                counts[counter_id] += 1;
+                false
+            }
+        };
+        if overflow {
+            self.promote();
+            self.increment(counter_id);
+        }
+    }
+
+    fn promote(&mut self) {
+        let promoted_counts = match *self {
+            FacetCounters::U32(ref counts) => counts.iter().cloned().map(u64::from).collect(),
+            FacetCounters::U64(_) => {
+                return;
+            }
+        };
+        *self = FacetCounters::U64(promoted_counts);
+    }
+
+    fn get(&self, counter_id: usize) -> u64 {
+        match *self {
+            FacetCounters::U32(ref counts) => u64::from(counts[counter_id]),
+            FacetCounters::U64(ref counts) => counts[counter_id],
+        }
+    }
+}
+
 struct SegmentFacetCounter {
     pub facet_reader: FacetReader,
     pub facet_ords: Vec<u64>,
-    pub facet_counts: Vec<u64>,
+    pub facet_counts: FacetCounters,
 }
 
-fn facet_depth(facet_bytes: &[u8]) -> usize {
+pub(crate) fn facet_depth(facet_bytes: &[u8]) -> usize {
     if facet_bytes.is_empty() {
         0
     } else {
@@ -84,11 +175,15 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 /// (e.g. `/category/fiction`, `/category/biography`, `/category/personal_development`).
 ///
 /// Once collection is finished, you can harvest its results in the form
-/// of a `FacetCounts` object, and extract your face                t counts from it.
+/// of a `FacetCounts` object, and extract your facet counts from it.
 ///
 /// This implementation assumes you are working with a number of facets that
 /// is much hundreds of time lower than your number of documents.
 ///
+/// The counters of each segment are kept until the collector
+/// is harvested. Their width can be configured with
+/// [`.set_counter_width(...)`](#method.set_counter_width).
+///
 ///
 /// ```rust
 /// #[macro_use]
@@ -116,23 +211,23 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 ///             title => "The Name of the Wind",
 ///             facet => Facet::from("/lang/en"),
 ///             facet => Facet::from("/category/fiction/fantasy")
-///         ));
+///         ))?;
 ///         index_writer.add_document(doc!(
 ///             title => "Dune",
 ///             facet => Facet::from("/lang/en"),
 ///             facet => Facet::from("/category/fiction/sci-fi")
-///         ));
+///         ))?;
 ///         index_writer.add_document(doc!(
 ///             title => "La Vénus d'Ille",
 ///             facet => Facet::from("/lang/fr"),
 ///             facet => Facet::from("/category/fiction/fantasy"),
 ///             facet => Facet::from("/category/fiction/horror")
-///         ));
+///         ))?;
 ///         index_writer.add_document(doc!(
 ///             title => "The Diary of a Young Girl",
 ///             facet => Facet::from("/lang/en"),
 ///             facet => Facet::from("/category/biography")
-///         ));
+///         ))?;
 ///         index_writer.commit().unwrap();
 ///     }
 ///
@@ -140,7 +235,7 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 ///     let searcher = index.searcher();
 ///
 ///     {
-///			let mut facet_collector = FacetCollector::for_field(facet);
+///         let mut facet_collector = FacetCollector::for_field(facet);
 ///         facet_collector.add_facet("/lang");
 ///         facet_collector.add_facet("/category");
 ///         searcher.search(&AllQuery, &mut facet_collector).unwrap();
@@ -159,7 +254,7 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 ///     }
 ///
 ///     {
-///			let mut facet_collector = FacetCollector::for_field(facet);
+///         let mut facet_collector = FacetCollector::for_field(facet);
 ///         facet_collector.add_facet("/category/fiction");
 ///         searcher.search(&AllQuery, &mut facet_collector).unwrap();
 ///
@@ -177,8 +272,8 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 ///         ]);
 ///     }
 ///
-///    {
-///			let mut facet_collector = FacetCollector::for_field(facet);
+///     {
+///         let mut facet_collector = FacetCollector::for_field(facet);
 ///         facet_collector.add_facet("/category/fiction");
 ///         searcher.search(&AllQuery, &mut facet_collector).unwrap();
 ///
@@ -204,7 +299,8 @@ pub struct FacetCollector {
     // facet_ord -> collapse facet_id
     current_segment_collapse_mapping: Vec<usize>,
     // collapse facet_id -> count
-    current_segment_counts: Vec<u64>,
+    current_segment_counts: FacetCounters,
+    counter_width: FacetCounterWidth,
     // collapse facet_id -> facet_ord
     current_collapse_facet_ords: Vec<u64>,
 
@@ -234,12 +330,42 @@ fn skip<'a, I: Iterator<Item = &'a Facet>>(
     }
 }
 
+/// Adds a facet to a set of non-overlapping facets.
+///
+/// # Panics
+/// If the facet is the ancestor or the descendant
+/// of one of the facets of the set.
+pub(crate) fn add_facet(facets: &mut BTreeSet<Facet>, facet: Facet) {
+    for old_facet in facets.iter() {
+        assert!(
+            !old_facet.is_prefix_of(&facet),
+            "Tried to add a facet which is a descendant of an already added facet."
+        );
+        assert!(
+            !facet.is_prefix_of(old_facet),
+            "Tried to add a facet which is an ancestor of an already added facet."
+        );
+    }
+    facets.insert(facet);
+}
+
+/// Returns a `NotAFacetField` error if the field is not
+/// a hierarchical facet field.
+pub(crate) fn check_facet_field(schema: &Schema, field: Field) -> Result<()> {
+    let field_entry = schema.get_field_entry(field);
+    if *field_entry.field_type() != FieldType::HierarchicalFacet {
+        bail!(ErrorKind::NotAFacetField(field_entry.name().to_string()));
+    }
+    Ok(())
+}
+
 impl FacetCollector {
     /// Create a facet collector to collect the facets
     /// from a specific facet `Field`.
     ///
     /// This function does not check whether the field
-    /// is of the proper type.
+    /// is of the proper type: if it is not a facet field, searching
+    /// with the collector returns a `NotAFacetField` error.
     pub fn for_field(field: Field) -> FacetCollector {
         FacetCollector {
             facet_ords: Vec::with_capacity(255),
@@ -250,10 +376,18 @@ impl FacetCollector {
 
             current_segment_collapse_mapping: Vec::new(),
             current_collapse_facet_ords: Vec::new(),
-            current_segment_counts: Vec::new(),
+            current_segment_counts: FacetCounters::default(),
+            counter_width: FacetCounterWidth::Auto,
         }
     }
 
+    /// Sets the width of the per-segment counters.
+    ///
+    /// Defaults to `FacetCounterWidth::Auto`.
+    pub fn set_counter_width(&mut self, counter_width: FacetCounterWidth) {
+        self.counter_width = counter_width;
+    }
+
     /// Adds a facet that we want to record counts
     ///
     /// Adding facet `Facet::from("/country")` for instance,
@@ -267,24 +401,12 @@ impl FacetCollector {
     where
         Facet: From<T>,
     {
-        let facet = Facet::from(facet_from);
-        for old_facet in &self.facets {
-            assert!(
-                !old_facet.is_prefix_of(&facet),
-                "Tried to add a facet which is a descendant of an already added facet."
-            );
-            assert!(
-                !facet.is_prefix_of(old_facet),
-                "Tried to add a facet which is an ancestor of an already added facet."
-            );
-        }
-        self.facets.insert(facet);
+        add_facet(&mut self.facets, Facet::from(facet_from));
     }
 
     fn set_collapse_mapping(&mut self, facet_reader: &FacetReader) {
         self.current_segment_collapse_mapping.clear();
         self.current_collapse_facet_ords.clear();
-        self.current_segment_counts.clear();
         let mut collapse_facet_it = self.facets.iter().peekable();
         self.current_collapse_facet_ords.push(0);
         let mut facet_streamer = facet_reader.facet_dict().range().into_stream();
@@ -332,7 +454,10 @@ impl FacetCollector {
             self.segment_counters.push(SegmentFacetCounter {
                 facet_reader: self.ff_reader.take().unwrap().into_inner(),
                 facet_ords: mem::replace(&mut self.current_collapse_facet_ords, Vec::new()),
-                facet_counts: mem::replace(&mut self.current_segment_counts, Vec::new()),
+                facet_counts: mem::replace(
+                    &mut self.current_segment_counts,
+                    FacetCounters::default(),
+                ),
             });
         }
     }
@@ -348,9 +473,9 @@ impl FacetCollector {
             .iter()
             .map(|segment_counter| &segment_counter.facet_ords[..])
             .collect();
-        let collapsed_facet_counts: Vec<&[u64]> = self.segment_counters
+        let collapsed_facet_counts: Vec<&FacetCounters> = self.segment_counters
             .iter()
-            .map(|segment_counter| &segment_counter.facet_counts[..])
+            .map(|segment_counter| &segment_counter.facet_counts)
             .collect();
 
         let facet_streams = self.segment_counters
@@ -360,6 +485,7 @@ impl FacetCollector {
 
         let mut facet_merger = TermMerger::new(facet_streams);
         let mut facet_counts = BTreeMap::new();
+        let mut facet_labels = BTreeMap::new();
 
         while facet_merger.advance() {
             let count = facet_merger
@@ -374,7 +500,7 @@ impl FacetCollector {
                             if collapsed_term_id == 0 {
                                 0
                             } else {
-                                collapsed_facet_counts[seg_ord][collapsed_term_id]
+                                collapsed_facet_counts[seg_ord].get(collapsed_term_id)
                             }
                         })
                         .unwrap_or(0)
@@ -382,57 +508,109 @@ impl FacetCollector {
                 .sum();
             if count > 0u64 {
                 let bytes = facet_merger.key().to_owned();
-                facet_counts.insert(Facet::from_encoded(bytes), count);
+                let facet = Facet::from_encoded(bytes);
+                // the label of the first segment wins.
+                let label_opt = facet_merger
+                    .current_kvs()
+                    .iter()
+                    .filter_map(|it| {
+                        let facet_reader = &self.segment_counters[it.segment_ord].facet_reader;
+                        facet_reader
+                            .label_from_ord(it.streamer.term_ord())
+                            .map(|label| (it.segment_ord, label))
+                    })
+                    .min_by_key(|&(segment_ord, _)| segment_ord);
+                if let Some((_, label)) = label_opt {
+                    facet_labels.insert(facet.clone(), label.to_string());
+                }
+                facet_counts.insert(facet, count);
             }
         }
-        FacetCounts { facet_counts }
+        FacetCounts {
+            facet_counts,
+            facet_labels,
+        }
     }
 }
 
 impl Collector for FacetCollector {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        check_facet_field(searcher.schema(), self.field)?;
+        self.segment_counters
+            .reserve(searcher.segment_readers().len());
+        Ok(())
+    }
+
     fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
         self.finalize_segment();
         let facet_reader = reader.facet_reader(self.field)?;
         self.set_collapse_mapping(&facet_reader);
-        self.current_segment_counts
-            .resize(self.current_collapse_facet_ords.len(), 0);
+        self.current_segment_counts = FacetCounters::with_width(
+            self.counter_width,
+            self.current_collapse_facet_ords.len(),
+        );
         self.ff_reader = Some(UnsafeCell::new(facet_reader));
         Ok(())
     }
 
     fn collect(&mut self, doc: DocId, _: Score) {
+        let mut facet_ords = mem::replace(&mut self.facet_ords, Vec::new());
+        self.collect_with_buffer(doc, &mut facet_ords);
+        self.facet_ords = facet_ords;
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+impl FacetCollector {
+    /// Returns true iff at least one facet was added.
+    pub(crate) fn has_facets(&self) -> bool {
+        !self.facets.is_empty()
+    }
+
+    /// Counts the facets of a document, using `facet_ords`
+    /// as a buffer for its facet ordinals.
+    pub(crate) fn collect_with_buffer(&mut self, doc: DocId, facet_ords: &mut Vec<u64>) {
         let facet_reader: &mut FacetReader = unsafe {
             &mut *self.ff_reader
                 .as_ref()
                 .expect("collect() was called before set_segment. This should never happen.")
                 .get()
         };
-        facet_reader.facet_ords(doc, &mut self.facet_ords);
+        facet_reader.facet_ords(doc, facet_ords);
         let mut previous_collapsed_ord: usize = usize::MAX;
-        for &facet_ord in &self.facet_ords {
+        for &facet_ord in facet_ords.iter() {
             let collapsed_ord = self.current_segment_collapse_mapping[facet_ord as usize];
-            self.current_segment_counts[collapsed_ord] += if collapsed_ord == previous_collapsed_ord
-            {
-                0
-            } else {
-                1
-            };
+            if collapsed_ord != previous_collapsed_ord {
+                self.current_segment_counts.increment(collapsed_ord);
+            }
             previous_collapsed_ord = collapsed_ord;
         }
     }
-
-    fn requires_scoring(&self) -> bool {
-        false
-    }
 }
 
 /// Intermediary result of the `FacetCollector` that stores
 /// the facet counts for all the segments.
+///
+/// It also holds the display labels of the counted facets that were indexed
+/// with [`Document::add_facet_with_label`][add_facet_with_label].
+/// When a facet has different labels in different segments,
+/// the label of the first segment is kept.
+///
+/// [add_facet_with_label]: ../schema/struct.Document.html#method.add_facet_with_label
 pub struct FacetCounts {
-    facet_counts: BTreeMap<Facet, u64>,
+    pub(crate) facet_counts: BTreeMap<Facet, u64>,
+    pub(crate) facet_labels: BTreeMap<Facet, String>,
 }
 
 impl FacetCounts {
+    /// Returns an iterator over all of the facet counts
+    /// that are descendants of the given facet.
+    ///
+    /// Passing the root facet (`"/"` or `Facet::root()`) does not
+    /// restrict the returned facets at all.
     #[allow(needless_lifetimes)] //< compiler fails if we remove the lifetime
     pub fn get<'a, T>(&'a self, facet_from: T) -> impl Iterator<Item = (&'a Facet, u64)>
     where
@@ -454,30 +632,220 @@ impl FacetCounts {
             .map(|(facet, count)| (facet, *count))
     }
 
-    pub fn top_k<T>(&self, facet: T, k: usize) -> Vec<(&Facet, u64)>
+    /// Returns the display label of a counted facet, if it was
+    /// indexed with a label.
+    pub fn label<T>(&self, facet_from: T) -> Option<&str>
     where
         Facet: From<T>,
     {
-        let mut heap = BinaryHeap::with_capacity(k);
-        let mut it = self.get(facet);
+        self.facet_labels
+            .get(&Facet::from(facet_from))
+            .map(|label| label.as_str())
+    }
 
-        for (facet, count) in (&mut it).take(k) {
-            heap.push(Hit { count, facet });
-        }
+    /// Returns an iterator over all of the facet counts
+    /// that are descendants of the given facet, along with
+    /// the display label of the facets.
+    ///
+    /// See [`.get(...)`](#method.get).
+    #[allow(needless_lifetimes)] //< compiler fails if we remove the lifetime
+    pub fn get_with_labels<'a, T>(
+        &'a self,
+        facet_from: T,
+    ) -> impl Iterator<Item = (&'a Facet, Option<&'a str>, u64)>
+    where
+        Facet: From<T>,
+    {
+        self.get(facet_from).map(move |(facet, count)| {
+            let label_opt = self.facet_labels.get(facet).map(|label| label.as_str());
+            (facet, label_opt, count)
+        })
+    }
+
+    /// Returns the `k` descendants of the given facet with
+    /// the highest counts.
+    pub fn top_k<T>(&self, facet: T, k: usize) -> Vec<(&Facet, u64)>
+    where
+        Facet: From<T>,
+    {
+        top_k(self.get(facet), k)
+    }
 
-        let mut lowest_count: u64 = heap.peek().map(|hit| hit.count).unwrap_or(u64::MIN);
-        for (facet, count) in it {
-            if count > lowest_count {
-                lowest_count = count;
-                if let Some(mut head) = heap.peek_mut() {
-                    *head = Hit { count, facet };
+    /// Converts the facet counts into an `OwnedFacetCounts`.
+    ///
+    /// See [`OwnedFacetCounts`](./struct.OwnedFacetCounts.html).
+    pub fn into_owned(self) -> OwnedFacetCounts {
+        let mut facet_labels = self.facet_labels;
+        let facet_counts = self.facet_counts
+            .into_iter()
+            .map(|(facet, count)| {
+                let label = facet_labels.remove(&facet);
+                FacetCount {
+                    facet,
+                    count,
+                    label,
                 }
+            })
+            .collect();
+        OwnedFacetCounts { facet_counts }
+    }
+}
+
+fn top_k<'a, I>(mut it: I, k: usize) -> Vec<(&'a Facet, u64)>
+where
+    I: Iterator<Item = (&'a Facet, u64)>,
+{
+    let mut heap = BinaryHeap::with_capacity(k);
+
+    for (facet, count) in (&mut it).take(k) {
+        heap.push(Hit { count, facet });
+    }
+
+    let mut lowest_count: u64 = heap.peek().map(|hit| hit.count).unwrap_or(u64::MIN);
+    for (facet, count) in it {
+        if count > lowest_count {
+            if let Some(mut head) = heap.peek_mut() {
+                *head = Hit { count, facet };
             }
+            lowest_count = heap.peek().map(|hit| hit.count).unwrap_or(u64::MIN);
         }
-        heap.into_sorted_vec()
-            .into_iter()
-            .map(|hit| (hit.facet, hit.count))
-            .collect::<Vec<_>>()
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|hit| (hit.facet, hit.count))
+        .collect::<Vec<_>>()
+}
+
+/// Count of a facet, as stored in the `OwnedFacetCounts`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FacetCount {
+    /// The counted facet.
+    pub facet: Facet,
+    /// The number of documents belonging to the facet.
+    pub count: u64,
+    /// The display label of the facet, if it was indexed with a label.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Facet counts stored as a plain vector, sorted by facet.
+///
+/// It is obtained with [`FacetCounts::into_owned`](./struct.FacetCounts.html#method.into_owned),
+/// and offers the same API as the `FacetCounts`.
+///
+/// Unlike the `FacetCounts`, it does not rely on a tree, which
+/// makes it more compact and cheaper to move around, for instance
+/// to store it in a cache or to return it from a request handler.
+/// The `FacetCounts` do not borrow the searcher either, so the
+/// conversion only makes sense when the counts outlive the search :
+/// it costs a pass over all of the counted facets, and lookups
+/// become binary searches.
+///
+/// It serializes into a JSON array of facet counts, such as
+/// `[{"facet":"/lang/en","count":3,"label":"English"}]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OwnedFacetCounts {
+    facet_counts: Vec<FacetCount>,
+}
+
+impl OwnedFacetCounts {
+    /// Returns all of the facet counts, sorted by facet.
+    pub fn facet_counts(&self) -> &[FacetCount] {
+        &self.facet_counts[..]
+    }
+
+    /// Returns the facet counts that are descendants of the given facet.
+    ///
+    /// Passing the root facet (`"/"` or `Facet::root()`) does not
+    /// restrict the returned facets at all.
+    pub fn get_facet_counts<T>(&self, facet_from: T) -> &[FacetCount]
+    where
+        Facet: From<T>,
+    {
+        let facet = Facet::from(facet_from);
+        let start = match self.search(&facet) {
+            Ok(ord) => ord + 1,
+            Err(ord) => ord,
+        };
+        let stop = if facet.is_root() {
+            self.facet_counts.len()
+        } else {
+            let mut facet_after_bytes = facet.encoded_bytes().to_owned();
+            facet_after_bytes.push(1u8);
+            let facet_after = Facet::from_encoded(facet_after_bytes);
+            match self.search(&facet_after) {
+                Ok(ord) | Err(ord) => ord,
+            }
+        };
+        &self.facet_counts[start..stop]
+    }
+
+    fn search(&self, facet: &Facet) -> ::std::result::Result<usize, usize> {
+        self.facet_counts
+            .binary_search_by(|facet_count| facet_count.facet.cmp(facet))
+    }
+
+    /// Returns an iterator over all of the facet counts
+    /// that are descendants of the given facet.
+    ///
+    /// See [`FacetCounts::get`](./struct.FacetCounts.html#method.get).
+    #[allow(needless_lifetimes)] //< compiler fails if we remove the lifetime
+    pub fn get<'a, T>(&'a self, facet_from: T) -> impl Iterator<Item = (&'a Facet, u64)>
+    where
+        Facet: From<T>,
+    {
+        self.get_facet_counts(facet_from)
+            .iter()
+            .map(|facet_count| (&facet_count.facet, facet_count.count))
+    }
+
+    /// Returns the display label of a counted facet, if it was
+    /// indexed with a label.
+    pub fn label<T>(&self, facet_from: T) -> Option<&str>
+    where
+        Facet: From<T>,
+    {
+        self.search(&Facet::from(facet_from))
+            .ok()
+            .and_then(|ord| self.facet_counts[ord].label.as_ref())
+            .map(|label| label.as_str())
+    }
+
+    /// Returns an iterator over all of the facet counts
+    /// that are descendants of the given facet, along with
+    /// the display label of the facets.
+    ///
+    /// See [`.get(...)`](#method.get).
+    #[allow(needless_lifetimes)] //< compiler fails if we remove the lifetime
+    pub fn get_with_labels<'a, T>(
+        &'a self,
+        facet_from: T,
+    ) -> impl Iterator<Item = (&'a Facet, Option<&'a str>, u64)>
+    where
+        Facet: From<T>,
+    {
+        self.get_facet_counts(facet_from).iter().map(|facet_count| {
+            let label_opt = facet_count.label.as_ref().map(|label| label.as_str());
+            (&facet_count.facet, label_opt, facet_count.count)
+        })
+    }
+
+    /// Returns the `k` descendants of the given facet with
+    /// the highest counts.
+    pub fn top_k<T>(&self, facet: T, k: usize) -> Vec<(&Facet, u64)>
+    where
+        Facet: From<T>,
+    {
+        top_k(self.get(facet), k)
+    }
+}
+
+impl Serialize for OwnedFacetCounts {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.facet_counts.serialize(serializer)
     }
 }
 
@@ -485,13 +853,46 @@ impl FacetCounts {
 mod tests {
     use test::Bencher;
     use core::Index;
-    use schema::{Document, Facet, SchemaBuilder};
+    use schema::{Document, Facet, SchemaBuilder, TEXT};
     use query::AllQuery;
-    use super::{FacetCollector, FacetCounts};
+    use error::ErrorKind;
+    use super::{FacetCollector, FacetCount, FacetCounterWidth, FacetCounters, FacetCounts,
+                OwnedFacetCounts};
+    use serde_json;
     use std::iter;
     use schema::Field;
     use rand::{thread_rng, Rng};
 
+    #[test]
+    fn test_facet_collector_on_text_field() {
+        let mut schema_builder = SchemaBuilder::new();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let mut doc = Document::new();
+        doc.add_facet(title, "/a/b");
+        let error = index_writer.add_document(doc).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the document does not match the schema: 'Invalid value for the field 'title': \
+             expected a text, got the facet /a/b.'"
+        );
+        index_writer.add_document(doc!(title => "/a/b")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut facet_collector = FacetCollector::for_field(title);
+        facet_collector.add_facet(Facet::from("/a"));
+        let error = searcher
+            .search(&AllQuery, &mut facet_collector)
+            .err()
+            .unwrap();
+        match *error.kind() {
+            ErrorKind::NotAFacetField(ref field_name) => assert_eq!(field_name, "title"),
+            _ => panic!("Unexpected error {:?}", error),
+        }
+    }
+
     #[test]
     fn test_facet_collector_drilldown() {
         let mut schema_builder = SchemaBuilder::new();
@@ -514,7 +915,7 @@ mod tests {
         for i in 0..num_facets * 10 {
             let mut doc = Document::new();
             doc.add_facet(facet_field, facets[i % num_facets].clone());
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
@@ -526,24 +927,219 @@ mod tests {
 
         let counts: FacetCounts = facet_collector.harvest();
         {
-            let facets: Vec<(String, u64)> = counts
-                .get("/top1")
-                .map(|(facet, count)| (facet.to_string(), count))
-                .collect();
+            let facets: Vec<(&Facet, u64)> = counts.get("/top1").collect();
             assert_eq!(
                 facets,
-                [
+                vec![
                     ("/top1/mid0", 50),
                     ("/top1/mid1", 50),
                     ("/top1/mid2", 50),
                     ("/top1/mid3", 50),
-                ].iter()
-                    .map(|&(facet_str, count)| (String::from(facet_str), count))
-                    .collect::<Vec<_>>()
+                ]
             );
         }
     }
 
+    fn facet_counts_for(facets: &[&str], collected_facet: &str) -> FacetCounts {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
+            for facet in facets {
+                index_writer.add_document(doc!(facet_field => Facet::from(*facet))).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet(collected_facet);
+        searcher.search(&AllQuery, &mut facet_collector).unwrap();
+        facet_collector.harvest()
+    }
+
+    #[test]
+    fn test_facet_collector_labels() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
+            let mut doc = Document::new();
+            doc.add_facet_with_label(facet_field, "/brand/apple", "Apple Inc.");
+            doc.add_facet(facet_field, "/color/red");
+            index_writer.add_document(doc).unwrap();
+            let mut doc = Document::new();
+            // the first label of the segment wins.
+            doc.add_facet_with_label(facet_field, "/brand/apple", "Apple");
+            doc.add_facet_with_label(facet_field, "/brand", "Brands");
+            index_writer.add_document(doc).unwrap();
+            let mut doc = Document::new();
+            doc.add_facet(facet_field, "/brand/samsung");
+            index_writer.add_document(doc).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let facet_reader = segment_reader.facet_reader(facet_field).unwrap();
+        let labels: Vec<(String, &str)> = facet_reader
+            .labels()
+            .map(|(facet_ord, label)| {
+                let mut facet = Facet::root();
+                facet_reader.facet_from_ord(facet_ord, &mut facet);
+                (facet.to_string(), label)
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("/brand".to_string(), "Brands"),
+                ("/brand/apple".to_string(), "Apple Inc."),
+            ]
+        );
+
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/brand");
+        searcher.search(&AllQuery, &mut facet_collector).unwrap();
+        let counts = facet_collector.harvest();
+        let labeled_counts: Vec<(String, Option<&str>, u64)> = counts
+            .get_with_labels("/brand")
+            .map(|(facet, label_opt, count)| (facet.to_string(), label_opt, count))
+            .collect();
+        assert_eq!(
+            labeled_counts,
+            vec![
+                ("/brand/apple".to_string(), Some("Apple Inc."), 2),
+                ("/brand/samsung".to_string(), None, 1),
+            ]
+        );
+        assert_eq!(counts.label("/brand/apple"), Some("Apple Inc."));
+        assert_eq!(counts.label("/brand/samsung"), None);
+        // only the labels of the counted facets are harvested.
+        assert_eq!(counts.label("/brand"), None);
+    }
+
+    #[test]
+    fn test_facet_collector_into_owned() {
+        let docs = ["/a", "/a/b", "/a/b/", "/a/c", "/ab/d", "/c/d/e"];
+        let owned_counts = {
+            let counts = facet_counts_for(&docs, "/a/");
+            let owned_counts = facet_counts_for(&docs, "/a/").into_owned();
+            for facet in &["/", "/a", "/a/b", "/ab", "/c", "/z"] {
+                assert_eq!(
+                    owned_counts.get(*facet).collect::<Vec<_>>(),
+                    counts.get(*facet).collect::<Vec<_>>()
+                );
+                assert_eq!(owned_counts.top_k(*facet, 1), counts.top_k(*facet, 1));
+                assert_eq!(owned_counts.top_k(*facet, 5), counts.top_k(*facet, 5));
+            }
+            owned_counts
+        };
+        // the index and its searcher are dropped at this point.
+        assert_eq!(
+            owned_counts.get("/a").collect::<Vec<_>>(),
+            vec![("/a/b", 2), ("/a/c", 1)]
+        );
+        assert_eq!(owned_counts.top_k("/", 1), vec![(&Facet::from("/a/b"), 2)]);
+        assert_eq!(owned_counts.facet_counts().len(), 2);
+        assert!(owned_counts.get("/a/b").next().is_none());
+        assert!(OwnedFacetCounts::default().get("/").next().is_none());
+    }
+
+    #[test]
+    fn test_owned_facet_counts_labels() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
+            let mut doc = Document::new();
+            doc.add_facet_with_label(facet_field, "/lang/en", "English");
+            index_writer.add_document(doc).unwrap();
+            index_writer.add_document(doc!(facet_field => Facet::from("/lang/en"))).unwrap();
+            index_writer.add_document(doc!(facet_field => Facet::from("/lang/fr"))).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/lang");
+        searcher.search(&AllQuery, &mut facet_collector).unwrap();
+        let owned_counts = facet_collector.harvest().into_owned();
+        drop(searcher);
+        drop(index);
+        assert_eq!(owned_counts.label("/lang/en"), Some("English"));
+        assert_eq!(owned_counts.label("/lang/fr"), None);
+        assert_eq!(owned_counts.label("/lang"), None);
+        assert_eq!(
+            owned_counts.get_with_labels("/lang").collect::<Vec<_>>(),
+            vec![
+                (&Facet::from("/lang/en"), Some("English"), 2),
+                (&Facet::from("/lang/fr"), None, 1),
+            ]
+        );
+        assert_eq!(
+            owned_counts.facet_counts()[1],
+            FacetCount {
+                facet: Facet::from("/lang/fr"),
+                count: 1,
+                label: None,
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&owned_counts).unwrap(),
+            "[{\"facet\":\"/lang/en\",\"count\":2,\"label\":\"English\"},\
+             {\"facet\":\"/lang/fr\",\"count\":1}]"
+        );
+    }
+
+    #[test]
+    fn test_facet_collector_root() {
+        let docs = ["/a", "/a/b", "/c/", "/", "/c/d/e", "/a/x"];
+        let counts = facet_counts_for(&docs, "/");
+        let expected = vec![("/a", 3), ("/c", 2)];
+        assert_eq!(counts.get("/").collect::<Vec<_>>(), expected);
+        assert_eq!(counts.get("").collect::<Vec<_>>(), expected);
+        assert_eq!(counts.get(Facet::root()).collect::<Vec<_>>(), expected);
+        assert_eq!(counts.top_k(Facet::root(), 10), expected);
+    }
+
+    #[test]
+    fn test_facet_collector_single_step() {
+        let docs = ["/a", "/a/b", "/a/b/", "/a/c", "/ab/d"];
+        let counts = facet_counts_for(&docs, "/a/");
+        assert_eq!(
+            counts.get("/a").collect::<Vec<_>>(),
+            vec![("/a/b", 2), ("/a/c", 1)]
+        );
+        assert_eq!(
+            counts.get("/a/").collect::<Vec<_>>(),
+            counts.get("/a").collect::<Vec<_>>()
+        );
+        assert!(counts.get("/ab").next().is_none());
+        assert!(counts.get("/a/b").next().is_none());
+    }
+
+    #[test]
+    fn test_facet_collector_root_only_docs() {
+        let docs = ["/", "/", ""];
+        let counts = facet_counts_for(&docs, "/");
+        assert!(counts.get("/").next().is_none());
+        assert!(counts.top_k("/", 3).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tried to add a facet which is an ancestor of \
+                               an already added facet.")]
+    fn test_root_facet_collector_is_ancestor() {
+        let mut facet_collector = FacetCollector::for_field(Field(0));
+        facet_collector.add_facet(Facet::from("/country"));
+        facet_collector.add_facet(Facet::root());
+    }
+
     #[test]
     #[should_panic(expected = "Tried to add a facet which is a descendant of \
                                an already added facet.")]
@@ -579,7 +1175,7 @@ mod tests {
 
         let mut index_writer = index.writer(3_000_000).unwrap();
         for doc in docs {
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
@@ -595,15 +1191,87 @@ mod tests {
             let facets: Vec<(&Facet, u64)> = counts.top_k("/", 3);
             assert_eq!(
                 facets,
-                vec![
-                    (&Facet::from("/facet_b"), 100),
-                    (&Facet::from("/facet_e"), 21),
-                    (&Facet::from("/facet_d"), 12),
-                ]
+                vec![("/facet_b", 100), ("/facet_e", 21), ("/facet_d", 12)]
             );
         }
     }
 
+    #[test]
+    fn test_facet_counters_u32_overflow() {
+        let mut counters = FacetCounters::U32(vec![0u32, u32::max_value() - 1, 7u32]);
+        counters.increment(1);
+        assert_matches!(counters, FacetCounters::U32(_));
+        assert_eq!(counters.get(1), u64::from(u32::max_value()));
+        // this increment overflows, and promotes the counters to u64.
+        counters.increment(1);
+        counters.increment(1);
+        counters.increment(2);
+        assert_matches!(counters, FacetCounters::U64(_));
+        assert_eq!(counters.get(0), 0);
+        assert_eq!(counters.get(1), u64::from(u32::max_value()) + 2);
+        assert_eq!(counters.get(2), 8);
+    }
+
+    #[test]
+    fn test_facet_counters_with_width() {
+        assert_matches!(
+            FacetCounters::with_width(FacetCounterWidth::Auto, 10),
+            FacetCounters::U64(_)
+        );
+        assert_matches!(
+            FacetCounters::with_width(FacetCounterWidth::Auto, 1 << 20),
+            FacetCounters::U32(_)
+        );
+        assert_matches!(
+            FacetCounters::with_width(FacetCounterWidth::U32, 10),
+            FacetCounters::U32(_)
+        );
+        assert_matches!(
+            FacetCounters::with_width(FacetCounterWidth::U64, 1 << 20),
+            FacetCounters::U64(_)
+        );
+    }
+
+    #[test]
+    fn test_facet_collector_counter_width() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
+            for i in 0..1_000 {
+                let mut doc = Document::new();
+                for j in 0..(i % 7) {
+                    let facet = Facet::from(&format!("/a/{}/{}", (i + j) % 13, j));
+                    doc.add_facet(facet_field, facet);
+                }
+                index_writer.add_document(doc).unwrap();
+                if i % 300 == 0 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |counter_width: FacetCounterWidth| {
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.set_counter_width(counter_width);
+            facet_collector.add_facet("/a");
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            facet_collector
+                .harvest()
+                .get("/a")
+                .map(|(facet, count)| (facet.to_string(), count))
+                .collect::<Vec<_>>()
+        };
+        let counts_u64 = count(FacetCounterWidth::U64);
+        assert_eq!(counts_u64.len(), 13);
+        assert_eq!(count(FacetCounterWidth::U32), counts_u64);
+        assert_eq!(count(FacetCounterWidth::Auto), counts_u64);
+    }
+
     #[bench]
     fn bench_facet_collector(b: &mut Bencher) {
         let mut schema_builder = SchemaBuilder::new();
@@ -623,7 +1291,7 @@ mod tests {
 
         let mut index_writer = index.writer(3_000_000).unwrap();
         for doc in docs {
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
@@ -0,0 +1,363 @@
+use std::sync::Arc;
+use std::usize;
+use std::collections::{BTreeMap, BTreeSet};
+use collector::Collector;
+use collector::facet_collector::{add_facet, check_facet_field, facet_depth, FacetCounts};
+use fastfield::FacetReader;
+use schema::{Facet, Field, FACET_SEP_BYTE};
+use termdict::{TermOrdinal, TermOrdinalMapping};
+use DocId;
+use Result;
+use Score;
+use Searcher;
+use SegmentReader;
+use SegmentLocalId;
+
+/// Collector for faceting, relying on global facet ordinals.
+///
+/// It is configured and returns the same results as the
+/// [`FacetCollector`](./struct.FacetCollector.html), but
+/// it translates the facet ordinals of each segment into global
+/// ordinals (see [`TermOrdinalMapping`](../termdict/struct.TermOrdinalMapping.html))
+/// and accumulates all counts into a single array.
+/// Harvesting the results then does not require any work
+/// on the term dictionaries.
+///
+/// The `TermOrdinalMapping` is cached by the `Searcher`, so that
+/// its cost is only paid once per searcher generation.
+///
+/// The collector is bound to the `Searcher` it was created with,
+/// and must not be used with another one.
+pub struct FastFacetCollector {
+    field: Field,
+    term_ordinal_mapping: Arc<TermOrdinalMapping>,
+    facets: BTreeSet<Facet>,
+    facet_ords: Vec<u64>,
+    facet_reader: Option<FacetReader>,
+    segment_ord: usize,
+
+    // global facet ord -> collapse facet id
+    collapse_mapping: Vec<usize>,
+    // collapse facet id -> global facet ord
+    collapse_facet_ords: Vec<TermOrdinal>,
+    // collapse facet id -> count
+    counts: Vec<u64>,
+    // collapse facet id -> label of the first segment having one.
+    labels: Vec<Option<String>>,
+}
+
+impl FastFacetCollector {
+    /// Create a facet collector to collect the facets
+    /// from a specific facet `Field`, for the given `Searcher`.
+    ///
+    /// This function does not check whether the field
+    /// is of the proper type: if it is not a facet field, searching
+    /// with the collector returns a `NotAFacetField` error.
+    pub fn for_field(searcher: &Searcher, field: Field) -> FastFacetCollector {
+        FastFacetCollector {
+            field,
+            term_ordinal_mapping: searcher.facet_ordinal_mapping(field),
+            facets: BTreeSet::new(),
+            facet_ords: Vec::with_capacity(255),
+            facet_reader: None,
+            segment_ord: 0,
+            collapse_mapping: Vec::new(),
+            collapse_facet_ords: Vec::new(),
+            counts: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Adds a facet that we want to record counts
+    ///
+    /// See [`FacetCollector::add_facet`](./struct.FacetCollector.html#method.add_facet).
+    /// All facets must be added before running the search.
+    /// The mapping of the facet ordinals to the counted facets is built
+    /// when the search starts (see [`Collector::prepare`](./trait.Collector.html#method.prepare)).
+    pub fn add_facet<T>(&mut self, facet_from: T)
+    where
+        Facet: From<T>,
+    {
+        add_facet(&mut self.facets, Facet::from(facet_from));
+    }
+
+    /// Builds the mapping from global facet ordinals to collapsed ids.
+    ///
+    /// The descendants of a facet form a contiguous range of
+    /// ordinals, starting right after the facet itself.
+    /// Within that range, each child of the facet receives a new
+    /// collapsed id that is shared with all of its own descendants.
+    /// The collapsed id `0` is associated to the facets that are not counted.
+    fn compute_collapse_mapping(&mut self) {
+        let term_ordinal_mapping = &self.term_ordinal_mapping;
+        let num_terms = term_ordinal_mapping.num_terms();
+        self.collapse_mapping = vec![0; num_terms];
+        self.collapse_facet_ords = vec![0];
+        for facet in &self.facets {
+            let facet_bytes = facet.encoded_bytes();
+            let (start, stop) = if facet.is_root() {
+                (0, num_terms as TermOrdinal)
+            } else {
+                let mut first_child_bytes = facet_bytes.to_owned();
+                first_child_bytes.push(FACET_SEP_BYTE);
+                let mut facet_after_bytes = facet_bytes.to_owned();
+                facet_after_bytes.push(1u8);
+                (
+                    term_ordinal_mapping.lower_bound(&first_child_bytes),
+                    term_ordinal_mapping.lower_bound(&facet_after_bytes),
+                )
+            };
+            let child_depth = facet_depth(facet_bytes) + 1;
+            let mut collapsed_id = 0;
+            for global_ord in start..stop {
+                if facet_depth(term_ordinal_mapping.term(global_ord)) == child_depth {
+                    collapsed_id = self.collapse_facet_ords.len();
+                    self.collapse_facet_ords.push(global_ord);
+                }
+                self.collapse_mapping[global_ord as usize] = collapsed_id;
+            }
+        }
+        self.counts = vec![0; self.collapse_facet_ords.len()];
+        self.labels = vec![None; self.collapse_facet_ords.len()];
+    }
+
+    fn record_labels(&mut self, segment_ord: usize, facet_reader: &FacetReader) {
+        let segment_mapping = self.term_ordinal_mapping.segment_mapping(segment_ord);
+        for (facet_ord, label) in facet_reader.labels() {
+            let global_ord = segment_mapping[facet_ord as usize];
+            let collapsed_id = self.collapse_mapping[global_ord as usize];
+            // only the labels of the counted facets are kept.
+            if collapsed_id != 0 && self.collapse_facet_ords[collapsed_id] == global_ord {
+                let label_opt = &mut self.labels[collapsed_id];
+                if label_opt.is_none() {
+                    *label_opt = Some(label.to_string());
+                }
+            }
+        }
+    }
+
+    /// Returns the results of the collection.
+    pub fn harvest(self) -> FacetCounts {
+        let mut facet_counts = BTreeMap::new();
+        let mut facet_labels = BTreeMap::new();
+        for (collapsed_id, &count) in self.counts.iter().enumerate().skip(1) {
+            if count > 0u64 {
+                let global_ord = self.collapse_facet_ords[collapsed_id];
+                let bytes = self.term_ordinal_mapping.term(global_ord).to_owned();
+                let facet = Facet::from_encoded(bytes);
+                if let Some(ref label) = self.labels[collapsed_id] {
+                    facet_labels.insert(facet.clone(), label.clone());
+                }
+                facet_counts.insert(facet, count);
+            }
+        }
+        FacetCounts {
+            facet_counts,
+            facet_labels,
+        }
+    }
+}
+
+impl Collector for FastFacetCollector {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        check_facet_field(searcher.schema(), self.field)?;
+        if self.counts.is_empty() {
+            self.compute_collapse_mapping();
+        }
+        Ok(())
+    }
+
+    fn set_segment(&mut self, segment_ord: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        if self.counts.is_empty() {
+            self.compute_collapse_mapping();
+        }
+        let facet_reader = reader.facet_reader(self.field)?;
+        self.record_labels(segment_ord as usize, &facet_reader);
+        self.facet_reader = Some(facet_reader);
+        self.segment_ord = segment_ord as usize;
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        let facet_reader: &mut FacetReader = self.facet_reader
+            .as_mut()
+            .expect("collect() was called before set_segment. This should never happen.");
+        facet_reader.facet_ords(doc, &mut self.facet_ords);
+        let segment_mapping = self.term_ordinal_mapping.segment_mapping(self.segment_ord);
+        let mut previous_collapsed_ord: usize = usize::MAX;
+        for &facet_ord in &self.facet_ords {
+            let global_ord = segment_mapping[facet_ord as usize];
+            let collapsed_ord = self.collapse_mapping[global_ord as usize];
+            if collapsed_ord != previous_collapsed_ord {
+                self.counts[collapsed_ord] += 1;
+            }
+            previous_collapsed_ord = collapsed_ord;
+        }
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test::Bencher;
+    use core::Index;
+    use schema::{Document, Facet, Field, SchemaBuilder, TEXT};
+    use query::AllQuery;
+    use collector::{FacetCollector, FastFacetCollector};
+    use collector::facet_collector::FacetCounts;
+    use indexer::NoMergePolicy;
+    use rand::{thread_rng, Rng};
+
+    const NUM_SEGMENTS: usize = 20;
+
+    #[test]
+    fn test_fast_facet_collector_on_text_field() {
+        let mut schema_builder = SchemaBuilder::new();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "/a/b")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut facet_collector = FastFacetCollector::for_field(&searcher, title);
+        facet_collector.add_facet("/a");
+        let error = searcher
+            .search(&AllQuery, &mut facet_collector)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the field 'title' is not a hierarchical facet field"
+        );
+    }
+
+    fn build_index(num_docs_per_segment: usize) -> (Index, Field) {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut rng = thread_rng();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for _ in 0..NUM_SEGMENTS {
+                for _ in 0..num_docs_per_segment {
+                    let mut doc = Document::new();
+                    let num_facets = rng.gen_range(0, 3);
+                    for _ in 0..num_facets {
+                        let leaf = rng.gen_range(0, 20);
+                        let facet = Facet::from(&format!(
+                            "/top{}/mid{}/leaf{}",
+                            rng.gen_range(0, 3),
+                            rng.gen_range(0, 5),
+                            leaf
+                        ));
+                        // leaves are labeled, while their ancestors are not.
+                        doc.add_facet_with_label(facet_field, facet, &format!("Leaf {}", leaf));
+                    }
+                    index_writer.add_document(doc).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        (index, facet_field)
+    }
+
+    #[test]
+    fn test_fast_facet_collector_same_as_facet_collector() {
+        let (index, facet_field) = build_index(50);
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), NUM_SEGMENTS);
+        let facet_sets: Vec<Vec<&str>> = vec![
+            vec!["/"],
+            vec!["/top0"],
+            vec!["/top1", "/top2/mid3"],
+            vec!["/top0/mid1/leaf2"],
+            vec!["/unknown"],
+        ];
+        for facets in facet_sets {
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            let mut fast_facet_collector = FastFacetCollector::for_field(&searcher, facet_field);
+            for &facet in &facets {
+                facet_collector.add_facet(facet);
+                fast_facet_collector.add_facet(facet);
+            }
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            searcher
+                .search(&AllQuery, &mut fast_facet_collector)
+                .unwrap();
+            let fast_facet_counts: FacetCounts = fast_facet_collector.harvest();
+            let facet_counts: FacetCounts = facet_collector.harvest();
+            assert_eq!(
+                fast_facet_counts.get("/").collect::<Vec<_>>(),
+                facet_counts.get("/").collect::<Vec<_>>()
+            );
+            assert_eq!(
+                fast_facet_counts.get_with_labels("/").collect::<Vec<_>>(),
+                facet_counts.get_with_labels("/").collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fast_facet_collector_labels() {
+        let (index, facet_field) = build_index(10);
+        let searcher = index.searcher();
+        let mut fast_facet_collector = FastFacetCollector::for_field(&searcher, facet_field);
+        fast_facet_collector.add_facet("/top1/mid2");
+        fast_facet_collector.add_facet("/top2");
+        searcher
+            .search(&AllQuery, &mut fast_facet_collector)
+            .unwrap();
+        let facet_counts = fast_facet_collector.harvest();
+        for (facet, label_opt, _) in facet_counts.get_with_labels("/top1/mid2") {
+            let facet_str = facet.to_string();
+            let leaf = facet_str.trim_left_matches("/top1/mid2/leaf");
+            assert_eq!(label_opt, Some(format!("Leaf {}", leaf).as_str()));
+        }
+        for (_, label_opt, _) in facet_counts.get_with_labels("/top2") {
+            assert_eq!(label_opt, None);
+        }
+    }
+
+    #[test]
+    fn test_facet_ordinal_mapping_is_cached() {
+        let (index, facet_field) = build_index(10);
+        let searcher = index.searcher();
+        let mapping = searcher.facet_ordinal_mapping(facet_field);
+        assert!(::std::sync::Arc::ptr_eq(
+            &mapping,
+            &searcher.facet_ordinal_mapping(facet_field)
+        ));
+    }
+
+    #[bench]
+    fn bench_facet_collector_20_segments(b: &mut Bencher) {
+        let (index, facet_field) = build_index(2_000);
+        b.iter(|| {
+            let searcher = index.searcher();
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.add_facet("/top0");
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            facet_collector.harvest()
+        });
+    }
+
+    #[bench]
+    fn bench_fast_facet_collector_20_segments(b: &mut Bencher) {
+        let (index, facet_field) = build_index(2_000);
+        b.iter(|| {
+            let searcher = index.searcher();
+            let mut facet_collector = FastFacetCollector::for_field(&searcher, facet_field);
+            facet_collector.add_facet("/top0");
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            facet_collector.harvest()
+        });
+    }
+}
@@ -7,6 +7,7 @@ use SegmentLocalId;
 use DocId;
 use Score;
 use Result;
+use Searcher;
 
 mod count_collector;
 pub use self::count_collector::CountCollector;
@@ -17,8 +18,18 @@ pub use self::multi_collector::MultiCollector;
 mod top_collector;
 pub use self::top_collector::TopCollector;
 
+mod top_custom_collector;
+pub use self::top_custom_collector::TopCustomCollector;
+
 mod facet_collector;
-pub use self::facet_collector::FacetCollector;
+pub use self::facet_collector::{FacetCollector, FacetCount, FacetCounterWidth, FacetCounts,
+                                OwnedFacetCounts};
+
+mod fast_facet_collector;
+pub use self::fast_facet_collector::FastFacetCollector;
+
+mod multi_facet_collector;
+pub use self::multi_facet_collector::MultiFacetCollector;
 
 mod chained_collector;
 pub use self::chained_collector::chain;
@@ -40,6 +51,7 @@ pub use self::chained_collector::chain;
 /// call the `collect` method to push the document to the collector.
 ///
 /// Temporally, our collector will receive calls
+/// - `.prepare(searcher)`
 /// - `.set_segment(0, segment_reader_0)`
 /// - `.collect(doc0_of_segment_0)`
 /// - `.collect(...)`
@@ -53,8 +65,23 @@ pub use self::chained_collector::chain;
 ///
 /// Segments are not guaranteed to be visited in any specific order.
 pub trait Collector {
+    /// `prepare` is called once per search, before any call to `set_segment`.
+    ///
+    /// The searcher gives access to the number of segments,
+    /// the number of documents and the schema of the searched index,
+    /// making it possible to size the buffers of the collector up front.
+    ///
+    /// The default implementation does nothing.
+    fn prepare(&mut self, _searcher: &Searcher) -> Result<()> {
+        Ok(())
+    }
+
     /// `set_segment` is called before beginning to enumerate
     /// on this segment.
+    ///
+    /// It is called for all of the segments of the searcher,
+    /// including the segments that the query skips because none of
+    /// their documents can match: `collect` is then not called for them.
     fn set_segment(
         &mut self,
         segment_local_id: SegmentLocalId,
@@ -68,6 +95,10 @@ pub trait Collector {
 }
 
 impl<'a, C: Collector> Collector for &'a mut C {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        (*self).prepare(searcher)
+    }
+
     fn set_segment(
         &mut self,
         segment_local_id: SegmentLocalId,
@@ -95,7 +126,10 @@ pub mod tests {
     use core::SegmentReader;
     use SegmentLocalId;
     use fastfield::FastFieldReader;
-    use schema::Field;
+    use indexer::NoMergePolicy;
+    use query::{AllQuery, RangeQuery, TermQuery};
+    use schema::{Field, IndexRecordOption, SchemaBuilder, Term, FAST, INT_INDEXED, TEXT};
+    use {Index, ScopedSearcher};
 
     /// Stores all of the doc ids.
     /// This collector is only used for tests.
@@ -179,13 +213,170 @@ pub mod tests {
         }
     }
 
+    /// Records the calls it receives.
+    #[derive(Default)]
+    struct RecordingCollector {
+        calls: Vec<String>,
+    }
+
+    impl Collector for RecordingCollector {
+        fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+            self.calls
+                .push(format!("prepare({})", searcher.segment_readers().len()));
+            Ok(())
+        }
+
+        fn set_segment(&mut self, segment_ord: SegmentLocalId, _: &SegmentReader) -> Result<()> {
+            self.calls.push(format!("set_segment({})", segment_ord));
+            Ok(())
+        }
+
+        fn collect(&mut self, doc: DocId, _score: Score) {
+            self.calls.push(format!("collect({})", doc));
+        }
+
+        fn requires_scoring(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_collector_call_order() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(text => "a")).unwrap();
+            index_writer.add_document(doc!(text => "b")).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text => "a")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "a"),
+            IndexRecordOption::Basic,
+        );
+        let check_calls = |calls: Vec<String>| {
+            assert_eq!(calls.len(), 5);
+            assert_eq!(calls[0], "prepare(2)");
+            let mut segment_calls = vec![calls[1..3].to_vec(), calls[3..5].to_vec()];
+            segment_calls.sort();
+            assert_eq!(
+                segment_calls,
+                vec![
+                    vec!["set_segment(0)".to_string(), "collect(0)".to_string()],
+                    vec!["set_segment(1)".to_string(), "collect(0)".to_string()],
+                ]
+            );
+        };
+        {
+            let mut recording_collector = RecordingCollector::default();
+            searcher.search(&query, &mut recording_collector).unwrap();
+            check_calls(recording_collector.calls);
+        }
+        {
+            let mut recording_collector = RecordingCollector::default();
+            searcher
+                .search_profiled(&query, &mut recording_collector)
+                .unwrap();
+            check_calls(recording_collector.calls);
+        }
+        {
+            let scoped_searcher = ScopedSearcher::new(&*searcher, &AllQuery).unwrap();
+            let mut recording_collector = RecordingCollector::default();
+            scoped_searcher
+                .search(&query, &mut recording_collector)
+                .unwrap();
+            check_calls(recording_collector.calls);
+        }
+        {
+            let mut left_collector = RecordingCollector::default();
+            let mut right_collector = RecordingCollector::default();
+            {
+                let mut multi_collector =
+                    MultiCollector::from(vec![&mut left_collector, &mut right_collector]);
+                searcher.search(&query, &mut multi_collector).unwrap();
+            }
+            check_calls(left_collector.calls);
+            check_calls(right_collector.calls);
+        }
+        {
+            let mut left_collector = RecordingCollector::default();
+            let mut right_collector = RecordingCollector::default();
+            {
+                let mut chained_collector = chain()
+                    .push(&mut left_collector)
+                    .push(&mut right_collector);
+                searcher.search(&query, &mut chained_collector).unwrap();
+            }
+            check_calls(left_collector.calls);
+            check_calls(right_collector.calls);
+        }
+    }
+
+    #[test]
+    fn test_collector_set_segment_on_skipped_segments() {
+        let mut schema_builder = SchemaBuilder::default();
+        let timestamp = schema_builder.add_u64_field("timestamp", INT_INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(timestamp => 1u64)).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(timestamp => 100u64)).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        // the segment holding the timestamp 1 cannot match.
+        let query = RangeQuery::new_u64(timestamp, 50..150);
+        let check_calls = |mut calls: Vec<String>| {
+            assert_eq!(calls.len(), 4);
+            assert_eq!(calls[0], "prepare(2)");
+            calls[1..].sort();
+            assert_eq!(
+                &calls[1..],
+                &[
+                    "collect(0)".to_string(),
+                    "set_segment(0)".to_string(),
+                    "set_segment(1)".to_string(),
+                ]
+            );
+        };
+        {
+            let mut recording_collector = RecordingCollector::default();
+            searcher.search(&query, &mut recording_collector).unwrap();
+            check_calls(recording_collector.calls);
+        }
+        {
+            let mut recording_collector = RecordingCollector::default();
+            searcher
+                .search_profiled(&query, &mut recording_collector)
+                .unwrap();
+            check_calls(recording_collector.calls);
+        }
+        {
+            let scoped_searcher = ScopedSearcher::new(&*searcher, &AllQuery).unwrap();
+            let mut recording_collector = RecordingCollector::default();
+            scoped_searcher
+                .search(&query, &mut recording_collector)
+                .unwrap();
+            check_calls(recording_collector.calls);
+        }
+    }
+
     #[bench]
     fn build_collector(b: &mut Bencher) {
         b.iter(|| {
             let mut count_collector = CountCollector::default();
             let docs: Vec<u32> = (0..1_000_000).collect();
             for doc in docs {
-                count_collector.collect(doc, 1f32);
+                count_collector.collect(doc, 1.0);
             }
             count_collector.count()
         });
@@ -4,6 +4,7 @@ use Score;
 use Result;
 use SegmentReader;
 use SegmentLocalId;
+use Searcher;
 
 /// Multicollector makes it possible to collect on more than one collector.
 /// It should only be used for use cases where the Collector types is unknown
@@ -21,6 +22,13 @@ impl<'a> MultiCollector<'a> {
 }
 
 impl<'a> Collector for MultiCollector<'a> {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        for collector in &mut self.collectors {
+            collector.prepare(searcher)?;
+        }
+        Ok(())
+    }
+
     fn set_segment(
         &mut self,
         segment_local_id: SegmentLocalId,
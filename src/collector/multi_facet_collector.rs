@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use collector::{Collector, FacetCollector, FacetCounterWidth, FacetCounts};
+use schema::{Facet, Field};
+use DocId;
+use Result;
+use Score;
+use Searcher;
+use SegmentReader;
+use SegmentLocalId;
+
+/// Collector for faceting on several facet fields at once.
+///
+/// Each field is counted as with a [`FacetCollector`](./struct.FacetCollector.html),
+/// but the query only runs once : the facet readers of all of the fields
+/// are opened for each segment, and the counters of all of the fields are
+/// updated for each matching document, sharing the buffer of facet ordinals.
+///
+/// A field that is added without any facet counts the direct
+/// children of the root facet.
+///
+/// The collector can run alongside other collectors, e.g. a `TopCollector`,
+/// with a [`MultiCollector`](./struct.MultiCollector.html).
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{Facet, SchemaBuilder};
+/// use tantivy::{Index, Result};
+/// use tantivy::collector::MultiFacetCollector;
+/// use tantivy::query::AllQuery;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let category = schema_builder.add_facet_field("category");
+///     let lang = schema_builder.add_facet_field("lang");
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///         index_writer.add_document(doc!(
+///             category => Facet::from("/fiction/fantasy"),
+///             lang => Facet::from("/en")
+///         ))?;
+///         index_writer.add_document(doc!(
+///             category => Facet::from("/fiction/horror"),
+///             lang => Facet::from("/fr")
+///         ))?;
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///
+///     let mut multi_facet_collector = MultiFacetCollector::default();
+///     multi_facet_collector.add_facet(category, "/fiction");
+///     multi_facet_collector.add_field(lang);
+///     searcher.search(&AllQuery, &mut multi_facet_collector)?;
+///
+///     let facet_counts = multi_facet_collector.harvest();
+///     let category_counts: Vec<(&Facet, u64)> = facet_counts[&category].get("/").collect();
+///     assert_eq!(category_counts, vec![
+///         (&Facet::from("/fiction/fantasy"), 1),
+///         (&Facet::from("/fiction/horror"), 1),
+///     ]);
+///     let lang_counts: Vec<(&Facet, u64)> = facet_counts[&lang].get("/").collect();
+///     assert_eq!(lang_counts, vec![
+///         (&Facet::from("/en"), 1),
+///         (&Facet::from("/fr"), 1),
+///     ]);
+///     Ok(())
+/// }
+/// ```
+pub struct MultiFacetCollector {
+    field_collectors: Vec<(Field, FacetCollector)>,
+    counter_width: FacetCounterWidth,
+    // facet ordinals of the document being collected, shared by the fields.
+    facet_ords: Vec<u64>,
+}
+
+impl Default for MultiFacetCollector {
+    fn default() -> MultiFacetCollector {
+        MultiFacetCollector {
+            field_collectors: Vec::new(),
+            counter_width: FacetCounterWidth::Auto,
+            facet_ords: Vec::with_capacity(255),
+        }
+    }
+}
+
+impl MultiFacetCollector {
+    /// Sets the width of the per-segment counters of all of the fields.
+    ///
+    /// See [`FacetCollector::set_counter_width`][set_counter_width].
+    ///
+    /// [set_counter_width]: ./struct.FacetCollector.html#method.set_counter_width
+    pub fn set_counter_width(&mut self, counter_width: FacetCounterWidth) {
+        self.counter_width = counter_width;
+        for &mut (_, ref mut facet_collector) in &mut self.field_collectors {
+            facet_collector.set_counter_width(counter_width);
+        }
+    }
+
+    fn field_collector(&mut self, field: Field) -> &mut FacetCollector {
+        let pos_opt = self.field_collectors
+            .iter()
+            .position(|&(collector_field, _)| collector_field == field);
+        let pos = match pos_opt {
+            Some(pos) => pos,
+            None => {
+                let mut facet_collector = FacetCollector::for_field(field);
+                facet_collector.set_counter_width(self.counter_width);
+                self.field_collectors.push((field, facet_collector));
+                self.field_collectors.len() - 1
+            }
+        };
+        &mut self.field_collectors[pos].1
+    }
+
+    /// Adds a facet field to collect.
+    ///
+    /// Unless facets are added for this field with
+    /// [`.add_facet(...)`](#method.add_facet), the direct children
+    /// of the root facet are counted.
+    ///
+    /// This function does not check whether the field
+    /// is of the proper type: if it is not a facet field, searching
+    /// with the collector returns a `NotAFacetField` error.
+    pub fn add_field(&mut self, field: Field) {
+        self.field_collector(field);
+    }
+
+    /// Adds a facet whose direct children are counted, for the given field.
+    ///
+    /// The field is added if needed. As with
+    /// [`FacetCollector::add_facet`](./struct.FacetCollector.html#method.add_facet),
+    /// adding two facets of the same field within which one is
+    /// the prefix of the other is forbidden.
+    pub fn add_facet<T>(&mut self, field: Field, facet_from: T)
+    where
+        Facet: From<T>,
+    {
+        self.field_collector(field).add_facet(facet_from);
+    }
+
+    /// Returns the results of the collection, for each of the fields.
+    pub fn harvest(self) -> HashMap<Field, FacetCounts> {
+        self.field_collectors
+            .into_iter()
+            .map(|(field, facet_collector)| (field, facet_collector.harvest()))
+            .collect()
+    }
+}
+
+impl Collector for MultiFacetCollector {
+    fn prepare(&mut self, searcher: &Searcher) -> Result<()> {
+        for &mut (_, ref mut facet_collector) in &mut self.field_collectors {
+            if !facet_collector.has_facets() {
+                facet_collector.add_facet(Facet::root());
+            }
+            facet_collector.prepare(searcher)?;
+        }
+        Ok(())
+    }
+
+    fn set_segment(&mut self, segment_ord: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        for &mut (_, ref mut facet_collector) in &mut self.field_collectors {
+            facet_collector.set_segment(segment_ord, reader)?;
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        for &mut (_, ref mut facet_collector) in &mut self.field_collectors {
+            facet_collector.collect_with_buffer(doc, &mut self.facet_ords);
+        }
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test::Bencher;
+    use core::Index;
+    use schema::{Document, Facet, Field, SchemaBuilder, Term, STRING, TEXT};
+    use query::{AllQuery, BooleanQuery, Occur, Query, TermQuery};
+    use collector::{Collector, FacetCollector, FacetCounts, MultiCollector,
+                    MultiFacetCollector, TopCollector};
+    use indexer::NoMergePolicy;
+    use schema::IndexRecordOption;
+    use test_utils::SeededRng;
+
+    const NUM_SEGMENTS: usize = 5;
+
+    struct Fields {
+        id: Field,
+        text: Field,
+        facets: Vec<Field>,
+    }
+
+    /// Builds an index of `NUM_SEGMENTS` segments, with three facet fields
+    /// and a text field holding `a` and/or `b`, and deletes some documents.
+    fn build_index(num_docs_per_segment: usize) -> (Index, Fields) {
+        let mut schema_builder = SchemaBuilder::new();
+        let id = schema_builder.add_text_field("id", STRING);
+        let text = schema_builder.add_text_field("text", TEXT);
+        let facets: Vec<Field> = (0..3)
+            .map(|field_ord| schema_builder.add_facet_field(&format!("facet{}", field_ord)))
+            .collect();
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut rng = SeededRng::new(7);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment_ord in 0..NUM_SEGMENTS {
+                for doc_ord in 0..num_docs_per_segment {
+                    let mut doc = Document::new();
+                    doc.add_text(id, &format!("{}-{}", segment_ord, doc_ord));
+                    let words = ["a", "b", "a b"][rng.gen_range(0, 3) as usize];
+                    doc.add_text(text, words);
+                    for &facet_field in &facets {
+                        for _ in 0..rng.gen_range(0, 3) {
+                            let facet = Facet::from(&format!(
+                                "/top{}/mid{}/leaf{}",
+                                rng.gen_range(0, 3),
+                                rng.gen_range(0, 4),
+                                rng.gen_range(0, 10),
+                            ));
+                            doc.add_facet(facet_field, facet);
+                        }
+                    }
+                    index_writer.add_document(doc).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+            for segment_ord in 0..NUM_SEGMENTS {
+                for doc_ord in (0..num_docs_per_segment).filter(|doc_ord| doc_ord % 7 == 3) {
+                    let doc_id = format!("{}-{}", segment_ord, doc_ord);
+                    index_writer.delete_term(Term::from_field_text(id, &doc_id));
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        (index, Fields { id, text, facets })
+    }
+
+    fn term_query(fields: &Fields, word: &str) -> Box<Query> {
+        box TermQuery::new(
+            Term::from_field_text(fields.text, word),
+            IndexRecordOption::Basic,
+        )
+    }
+
+    fn facet_counts_vec(facet_counts: &FacetCounts, facets: &[&str]) -> Vec<(String, u64)> {
+        let facets = if facets.is_empty() { &["/"][..] } else { facets };
+        facets
+            .iter()
+            .flat_map(|&facet| facet_counts.get(facet))
+            .map(|(facet, count)| (facet.to_string(), count))
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_facet_collector_same_as_facet_collectors() {
+        let (index, fields) = build_index(100);
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), NUM_SEGMENTS);
+        assert!(searcher.segment_readers()[0].num_deleted_docs() > 0);
+        let queries: Vec<Box<Query>> = vec![box AllQuery as Box<Query>, term_query(&fields, "a")];
+        let restrictions: Vec<Vec<&str>> = vec![vec![], vec!["/top1"], vec!["/top0", "/top2/mid1"]];
+        for query in &queries {
+            let mut multi_facet_collector = MultiFacetCollector::default();
+            for (&field, facets) in fields.facets.iter().zip(restrictions.iter()) {
+                multi_facet_collector.add_field(field);
+                for &facet in facets {
+                    multi_facet_collector.add_facet(field, facet);
+                }
+            }
+            query.search(&*searcher, &mut multi_facet_collector).unwrap();
+            let mut multi_facet_counts = multi_facet_collector.harvest();
+            assert_eq!(multi_facet_counts.len(), fields.facets.len());
+            for (field, facets) in fields.facets.iter().zip(restrictions.iter()) {
+                let mut facet_collector = FacetCollector::for_field(*field);
+                if facets.is_empty() {
+                    facet_collector.add_facet("/");
+                }
+                for &facet in facets {
+                    facet_collector.add_facet(facet);
+                }
+                query.search(&*searcher, &mut facet_collector).unwrap();
+                let expected_facet_counts = facet_collector.harvest();
+                let facet_counts = multi_facet_counts.remove(field).unwrap();
+                assert!(!facet_counts_vec(&facet_counts, facets).is_empty());
+                assert_eq!(
+                    facet_counts_vec(&facet_counts, facets),
+                    facet_counts_vec(&expected_facet_counts, facets)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_facet_collector_with_multi_collector() {
+        let (index, fields) = build_index(20);
+        let searcher = index.searcher();
+        let query = term_query(&fields, "b");
+        let mut top_collector = TopCollector::with_limit(5);
+        let mut multi_facet_collector = MultiFacetCollector::default();
+        multi_facet_collector.add_field(fields.facets[0]);
+        multi_facet_collector.add_field(fields.facets[2]);
+        {
+            let mut multi_collector =
+                MultiCollector::from(vec![&mut top_collector, &mut multi_facet_collector]);
+            query.search(&*searcher, &mut multi_collector).unwrap();
+        }
+        assert_eq!(top_collector.docs().len(), 5);
+        let facet_counts = multi_facet_collector.harvest();
+        assert_eq!(facet_counts.len(), 2);
+        let mut facet_collector = FacetCollector::for_field(fields.facets[2]);
+        facet_collector.add_facet("/");
+        query.search(&*searcher, &mut facet_collector).unwrap();
+        assert_eq!(
+            facet_counts_vec(&facet_counts[&fields.facets[2]], &[]),
+            facet_counts_vec(&facet_collector.harvest(), &[])
+        );
+    }
+
+    #[test]
+    fn test_multi_facet_collector_not_a_facet_field() {
+        let (index, fields) = build_index(1);
+        let searcher = index.searcher();
+        let mut multi_facet_collector = MultiFacetCollector::default();
+        multi_facet_collector.add_field(fields.facets[0]);
+        multi_facet_collector.add_field(fields.id);
+        assert!(multi_facet_collector.prepare(&*searcher).is_err());
+    }
+
+    fn bench_query(fields: &Fields) -> BooleanQuery {
+        BooleanQuery::from(vec![
+            (Occur::Should, term_query(fields, "a")),
+            (Occur::Should, term_query(fields, "b")),
+        ])
+    }
+
+    #[bench]
+    fn bench_facet_collectors_3_fields(b: &mut Bencher) {
+        let (index, fields) = build_index(4_000);
+        let query = bench_query(&fields);
+        b.iter(|| {
+            let searcher = index.searcher();
+            fields
+                .facets
+                .iter()
+                .map(|&field| {
+                    let mut facet_collector = FacetCollector::for_field(field);
+                    facet_collector.add_facet("/");
+                    query.search(&*searcher, &mut facet_collector).unwrap();
+                    facet_collector.harvest()
+                })
+                .collect::<Vec<_>>()
+        });
+    }
+
+    #[bench]
+    fn bench_multi_facet_collector_3_fields(b: &mut Bencher) {
+        let (index, fields) = build_index(4_000);
+        let query = bench_query(&fields);
+        b.iter(|| {
+            let searcher = index.searcher();
+            let mut multi_facet_collector = MultiFacetCollector::default();
+            for &field in &fields.facets {
+                multi_facet_collector.add_field(field);
+            }
+            query.search(&*searcher, &mut multi_facet_collector).unwrap();
+            multi_facet_collector.harvest()
+        });
+    }
+}
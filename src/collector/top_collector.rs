@@ -7,6 +7,7 @@ use std::collections::BinaryHeap;
 use std::cmp::Ordering;
 use DocId;
 use Score;
+use query::score_combiner::sanitize_score;
 
 // Rust heap is a max-heap and we need a min heap.
 #[derive(Clone, Copy)]
@@ -105,6 +106,7 @@ impl Collector for TopCollector {
     }
 
     fn collect(&mut self, doc: DocId, score: Score) {
+        let score = sanitize_score(score, "the scorer collected by the TopCollector");
         if self.at_capacity() {
             // It's ok to unwrap as long as a limit of 0 is forbidden.
             let limit_doc: GlobalScoredDoc = *self.heap
@@ -181,6 +183,35 @@ mod tests {
         }
     }
 
+    fn nan_score() -> Score {
+        let zero: Score = 0.0;
+        zero / zero
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN score returned by the scorer collected by the TopCollector")]
+    fn test_top_collector_nan() {
+        let mut top_collector = TopCollector::with_limit(4);
+        top_collector.collect(1, 0.8);
+        top_collector.collect(3, nan_score());
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_top_collector_nan_sanitized() {
+        let mut top_collector = TopCollector::with_limit(2);
+        top_collector.collect(1, 0.8);
+        top_collector.collect(3, nan_score());
+        top_collector.collect(5, 0.3);
+        let docs: Vec<DocId> = top_collector
+            .docs()
+            .into_iter()
+            .map(|doc_address| doc_address.doc())
+            .collect();
+        assert_eq!(docs, vec![1, 5]);
+    }
+
     #[test]
     #[should_panic]
     fn test_top_0() {
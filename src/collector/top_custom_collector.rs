@@ -0,0 +1,271 @@
+use super::Collector;
+use SegmentReader;
+use SegmentLocalId;
+use DocAddress;
+use Result;
+use std::collections::BinaryHeap;
+use std::cmp::Ordering;
+use DocId;
+use Score;
+
+// Rust heap is a max-heap and we need a min heap.
+struct GlobalKeyedDoc<K> {
+    key: K,
+    doc_address: DocAddress,
+}
+
+impl<K: PartialOrd> PartialOrd for GlobalKeyedDoc<K> {
+    fn partial_cmp(&self, other: &GlobalKeyedDoc<K>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: PartialOrd> Ord for GlobalKeyedDoc<K> {
+    #[inline]
+    fn cmp(&self, other: &GlobalKeyedDoc<K>) -> Ordering {
+        other
+            .key
+            .partial_cmp(&self.key)
+            .unwrap_or_else(|| other.doc_address.cmp(&self.doc_address))
+    }
+}
+
+impl<K: PartialOrd> PartialEq for GlobalKeyedDoc<K> {
+    fn eq(&self, other: &GlobalKeyedDoc<K>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: PartialOrd> Eq for GlobalKeyedDoc<K> {}
+
+/// The `TopCustomCollector` keeps track of the K documents
+/// with the largest keys, the key of a document being computed
+/// by a user-defined function.
+///
+/// The key function is built once per segment, by calling
+/// `segment_key_fn` with the `SegmentReader` of the segment.
+/// This is the place to open the fast field readers the key
+/// depends on. The returned function is then called with the
+/// doc id and the score of each of the collected documents.
+///
+/// Keys that cannot be compared with each other (`NaN` for instance)
+/// are not supported: the documents that hold them are kept
+/// or discarded in an unspecified manner.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::{DocId, Index, Score, SegmentReader};
+/// use tantivy::collector::TopCustomCollector;
+/// use tantivy::query::{AllQuery, Query};
+/// use tantivy::schema::{SchemaBuilder, FAST};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let recency = schema_builder.add_u64_field("recency", FAST);
+/// let popularity = schema_builder.add_u64_field("popularity", FAST);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(recency => 10u64, popularity => 1u64))?;
+///     index_writer.add_document(doc!(recency => 1u64, popularity => 30u64))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let segment_key_fn = |segment_reader: &SegmentReader| {
+///     let recency_reader = segment_reader.fast_field_reader::<u64>(recency).unwrap();
+///     let popularity_reader = segment_reader.fast_field_reader::<u64>(popularity).unwrap();
+///     move |doc: DocId, _score: Score| 2 * recency_reader.get(doc) + popularity_reader.get(doc)
+/// };
+/// let mut top_collector = TopCustomCollector::with_key_fn(10, segment_key_fn);
+/// AllQuery.search(&*searcher, &mut top_collector)?;
+/// let keys: Vec<u64> = top_collector.harvest().into_iter().map(|(key, _)| key).collect();
+/// assert_eq!(keys, vec![32, 21]);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+pub struct TopCustomCollector<K, TSegmentKeyFn, TKeyFn>
+where
+    K: PartialOrd + Clone,
+    TSegmentKeyFn: FnMut(&SegmentReader) -> TKeyFn,
+    TKeyFn: Fn(DocId, Score) -> K,
+{
+    limit: usize,
+    heap: BinaryHeap<GlobalKeyedDoc<K>>,
+    segment_id: u32,
+    segment_key_fn: TSegmentKeyFn,
+    key_fn: Option<TKeyFn>,
+}
+
+impl<K, TSegmentKeyFn, TKeyFn> TopCustomCollector<K, TSegmentKeyFn, TKeyFn>
+where
+    K: PartialOrd + Clone,
+    TSegmentKeyFn: FnMut(&SegmentReader) -> TKeyFn,
+    TKeyFn: Fn(DocId, Score) -> K,
+{
+    /// Creates a top collector, with a number of documents equal to "limit",
+    /// ranking the documents with the key function built by `segment_key_fn`.
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn with_key_fn(
+        limit: usize,
+        segment_key_fn: TSegmentKeyFn,
+    ) -> TopCustomCollector<K, TSegmentKeyFn, TKeyFn> {
+        if limit < 1 {
+            panic!("Limit must be strictly greater than 0.");
+        }
+        TopCustomCollector {
+            limit,
+            heap: BinaryHeap::with_capacity(limit),
+            segment_id: 0,
+            segment_key_fn,
+            key_fn: None,
+        }
+    }
+
+    /// Returns the K documents with the largest keys,
+    /// sorted by decreasing key.
+    ///
+    /// Calling this method triggers the sort.
+    /// The result of the sort is not cached.
+    pub fn harvest(&self) -> Vec<(K, DocAddress)> {
+        let mut keyed_docs: Vec<&GlobalKeyedDoc<K>> = self.heap.iter().collect();
+        keyed_docs.sort();
+        keyed_docs
+            .into_iter()
+            .map(|keyed_doc| (keyed_doc.key.clone(), keyed_doc.doc_address))
+            .collect()
+    }
+
+    /// Return true iff at least K documents have gone through
+    /// the collector.
+    #[inline]
+    pub fn at_capacity(&self) -> bool {
+        self.heap.len() >= self.limit
+    }
+}
+
+impl<K, TSegmentKeyFn, TKeyFn> Collector for TopCustomCollector<K, TSegmentKeyFn, TKeyFn>
+where
+    K: PartialOrd + Clone,
+    TSegmentKeyFn: FnMut(&SegmentReader) -> TKeyFn,
+    TKeyFn: Fn(DocId, Score) -> K,
+{
+    fn set_segment(&mut self, segment_id: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.segment_id = segment_id;
+        self.key_fn = Some((self.segment_key_fn)(reader));
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let key = {
+            let key_fn = self.key_fn
+                .as_ref()
+                .expect("collect called before set_segment");
+            key_fn(doc, score)
+        };
+        let doc_address = DocAddress(self.segment_id, doc);
+        if self.at_capacity() {
+            // It's ok to unwrap as long as a limit of 0 is forbidden.
+            let mut head = self.heap
+                .peek_mut()
+                .expect("Top collector with size 0 is forbidden");
+            if head.key < key {
+                head.key = key;
+                head.doc_address = doc_address;
+            }
+        } else {
+            self.heap.push(GlobalKeyedDoc { key, doc_address });
+        }
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::TopCustomCollector;
+    use Index;
+    use DocId;
+    use Score;
+    use SegmentReader;
+    use query::{AllQuery, Query};
+    use schema::{SchemaBuilder, FAST, STORED};
+    use indexer::NoMergePolicy;
+
+    fn key(recency_val: u64, popularity_val: u64) -> f64 {
+        0.7 * recency_val as f64 + 0.3 * popularity_val as f64
+    }
+
+    #[test]
+    fn test_top_custom_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_u64_field("id", STORED);
+        let recency = schema_builder.add_u64_field("recency", FAST);
+        let popularity = schema_builder.add_u64_field("popularity", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut expected: Vec<(f64, u64)> = vec![];
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for id_val in 0..100u64 {
+                let recency_val = (id_val * 37) % 101;
+                let popularity_val = (id_val * 53) % 97;
+                index_writer.add_document(doc!(
+                    id => id_val,
+                    recency => recency_val,
+                    popularity => popularity_val
+                )).unwrap();
+                expected.push((key(recency_val, popularity_val), id_val));
+                if id_val == 49 {
+                    index_writer.commit().unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let segment_key_fn = |segment_reader: &SegmentReader| {
+            let recency_reader = segment_reader.fast_field_reader::<u64>(recency).unwrap();
+            let popularity_reader = segment_reader.fast_field_reader::<u64>(popularity).unwrap();
+            move |doc: DocId, _score: Score| {
+                key(recency_reader.get(doc), popularity_reader.get(doc))
+            }
+        };
+        let mut top_collector = TopCustomCollector::with_key_fn(10, segment_key_fn);
+        AllQuery.search(&*searcher, &mut top_collector).unwrap();
+        assert!(top_collector.at_capacity());
+        let top_docs: Vec<(f64, u64)> = top_collector
+            .harvest()
+            .into_iter()
+            .map(|(key_val, doc_address)| {
+                let doc = searcher.doc(&doc_address).unwrap();
+                (key_val, doc.get_first(id).unwrap().u64_value())
+            })
+            .collect();
+
+        expected.sort_by(|left, right| right.partial_cmp(left).unwrap());
+        expected.truncate(10);
+        assert_eq!(top_docs, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_top_custom_collector_0() {
+        TopCustomCollector::with_key_fn(0, |_: &SegmentReader| |_: DocId, score: Score| score);
+    }
+}
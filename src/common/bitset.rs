@@ -160,6 +160,7 @@ impl BitSet {
         for tinyset in self.tinysets.iter_mut() {
             *tinyset = TinySet::empty();
         }
+        self.len = 0;
     }
 
     /// Returns the number of elements in the `BitSet`.
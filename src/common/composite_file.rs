@@ -67,6 +67,21 @@ impl<W: Write> CompositeWrite<W> {
         &mut self.write
     }
 
+    /// Appends the fields written in a `CompositeWrite` buffered in memory,
+    /// in the order they were written.
+    ///
+    /// The result is the same as if these fields had been written
+    /// directly in this `CompositeWrite`.
+    pub fn append(&mut self, buffer: CompositeWrite<Vec<u8>>) -> io::Result<()> {
+        let (data, _) = buffer.write.finish()?;
+        let start = self.write.written_bytes();
+        for (file_addr, offset) in buffer.offsets {
+            assert!(!self.offsets.contains_key(&file_addr));
+            self.offsets.insert(file_addr, start + offset);
+        }
+        self.write.write_all(&data)
+    }
+
     /// Close the composite file.
     ///
     /// An index of the different field offsets
@@ -106,15 +121,32 @@ impl<W: Write> CompositeWrite<W> {
 pub struct CompositeFile {
     data: ReadOnlySource,
     offsets_index: HashMap<FileAddr, (usize, usize)>,
+    // files that were copied into anonymous memory.
+    anonymous_files: HashMap<FileAddr, ReadOnlySource>,
 }
 
 impl CompositeFile {
     /// Opens a composite file stored in a given
     /// `ReadOnlySource`.
+    ///
+    /// Returns an error if the footer is not consistent
+    /// with the length of the data, e.g. if the file was truncated.
     pub fn open(data: &ReadOnlySource) -> io::Result<CompositeFile> {
         let end = data.len();
+        if end < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "composite file is too short to contain a footer",
+            ));
+        }
         let footer_len_data = data.slice_from(end - 4);
         let footer_len = u32::deserialize(&mut footer_len_data.as_slice())? as usize;
+        if footer_len > end - 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "composite file footer is longer than the file",
+            ));
+        }
 
         let footer_start = end - 4 - footer_len;
         let footer_data = data.slice(footer_start, footer_start + footer_len);
@@ -133,6 +165,12 @@ impl CompositeFile {
             offsets.push(offset);
             file_addrs.push(file_addr);
         }
+        if !footer_buffer.is_empty() || offset > footer_start {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "composite file footer is corrupted",
+            ));
+        }
         offsets.push(footer_start);
         for i in 0..num_fields {
             let file_addr = file_addrs[i];
@@ -144,6 +182,7 @@ impl CompositeFile {
         Ok(CompositeFile {
             data: data.slice_to(footer_start),
             offsets_index: field_index,
+            anonymous_files: HashMap::new(),
         })
     }
 
@@ -153,9 +192,38 @@ impl CompositeFile {
         CompositeFile {
             offsets_index: HashMap::new(),
             data: ReadOnlySource::empty(),
+            anonymous_files: HashMap::new(),
         }
     }
 
+    /// Copies the files that are smaller than `max_num_bytes`
+    /// into anonymous memory.
+    ///
+    /// Small files are then not subject to page faults when they are
+    /// accessed, while larger files remain backed by the original data.
+    pub fn load_small_files(&mut self, max_num_bytes: usize) {
+        for (&file_addr, &(from, to)) in &self.offsets_index {
+            if to - from < max_num_bytes {
+                let source = self.data.slice(from, to).to_anonymous();
+                self.anonymous_files.insert(file_addr, source);
+            }
+        }
+    }
+
+    /// Returns all of the files of the composite file,
+    /// as `(field, idx, source)` triplets sorted by field and idx.
+    pub fn files(&self) -> Vec<(Field, usize, ReadOnlySource)> {
+        let mut file_addrs: Vec<FileAddr> = self.offsets_index.keys().cloned().collect();
+        file_addrs.sort();
+        file_addrs
+            .into_iter()
+            .filter_map(|file_addr| {
+                self.open_read_with_idx(file_addr.field, file_addr.idx)
+                    .map(|source| (file_addr.field, file_addr.idx, source))
+            })
+            .collect()
+    }
+
     /// Returns the `ReadOnlySource` associated
     /// to a given `Field` and stored in a `CompositeFile`.
     pub fn open_read(&self, field: Field) -> Option<ReadOnlySource> {
@@ -165,8 +233,12 @@ impl CompositeFile {
     /// Returns the `ReadOnlySource` associated
     /// to a given `Field` and stored in a `CompositeFile`.
     pub fn open_read_with_idx(&self, field: Field, idx: usize) -> Option<ReadOnlySource> {
+        let file_addr = FileAddr { field, idx, };
+        if let Some(source) = self.anonymous_files.get(&file_addr) {
+            return Some(source.clone());
+        }
         self.offsets_index
-            .get(&FileAddr { field, idx, })
+            .get(&file_addr)
             .map(|&(from, to)| self.data.slice(from, to))
     }
 }
@@ -176,7 +248,7 @@ mod test {
 
     use std::io::Write;
     use super::{CompositeFile, CompositeWrite};
-    use directory::{Directory, RAMDirectory};
+    use directory::{Directory, RAMDirectory, ReadOnlySource};
     use schema::Field;
     use common::VInt;
     use common::BinarySerializable;
@@ -222,4 +294,46 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_composite_file_load_small_files() {
+        let path = Path::new("test_path");
+        let mut directory = RAMDirectory::create();
+        {
+            let w = directory.open_write(path).unwrap();
+            let mut composite_write = CompositeWrite::wrap(w);
+            composite_write.for_field(Field(0u32)).write_all(&[1u8; 10]).unwrap();
+            composite_write.for_field(Field(1u32)).write_all(&[2u8; 3]).unwrap();
+            composite_write.close().unwrap();
+        }
+        let r = directory.open_read(path).unwrap();
+        let mut composite_file = CompositeFile::open(&r).unwrap();
+        composite_file.load_small_files(5);
+        assert_eq!(composite_file.open_read(Field(0u32)).unwrap().as_slice(), &[1u8; 10]);
+        assert_eq!(composite_file.open_read(Field(1u32)).unwrap().as_slice(), &[2u8; 3]);
+        let files: Vec<(Field, usize)> = composite_file
+            .files()
+            .into_iter()
+            .map(|(field, idx, _)| (field, idx))
+            .collect();
+        assert_eq!(files, vec![(Field(0u32), 0), (Field(1u32), 0)]);
+    }
+
+    #[test]
+    fn test_composite_file_truncated() {
+        let path = Path::new("test_path");
+        let mut directory = RAMDirectory::create();
+        {
+            let w = directory.open_write(path).unwrap();
+            let mut composite_write = CompositeWrite::wrap(w);
+            composite_write.for_field(Field(0u32)).write_all(&[1u8; 10]).unwrap();
+            composite_write.for_field(Field(1u32)).write_all(&[2u8; 3]).unwrap();
+            composite_write.close().unwrap();
+        }
+        let data = directory.open_read(path).unwrap().as_slice().to_vec();
+        assert!(CompositeFile::open(&ReadOnlySource::from(data.clone())).is_ok());
+        for num_bytes in 0..data.len() {
+            let truncated_source = ReadOnlySource::from(data[..num_bytes].to_vec());
+            assert!(CompositeFile::open(&truncated_source).is_err());
+        }
+    }
 }
@@ -1,8 +1,17 @@
-use std::io::Write;
+use std::io::{Seek, SeekFrom, Write};
 use std::io;
+use std::cmp;
 
+/// Wraps a writer and keeps track of the number of bytes written.
+///
+/// If the underlying writer implements `Seek`, so does the `CountingWriter`.
+/// Seeking is passed through to the underlying writer, assuming the
+/// counting started at its position 0. Seeking back and overwriting
+/// bytes does not change `written_bytes`, which is the largest position
+/// written so far, in other words the length of the written data.
 pub struct CountingWriter<W> {
     underlying: W,
+    position: usize,
     written_bytes: usize,
 }
 
@@ -10,14 +19,35 @@ impl<W: Write> CountingWriter<W> {
     pub fn wrap(underlying: W) -> CountingWriter<W> {
         CountingWriter {
             underlying,
+            position: 0,
             written_bytes: 0,
         }
     }
 
+    /// Returns the largest position written so far.
     pub fn written_bytes(&self) -> usize {
         self.written_bytes
     }
 
+    /// Returns the position the next byte will be written at.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.underlying
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly is not accounted for.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.underlying
+    }
+
+    /// Flushes the writer, and returns the underlying writer
+    /// together with the number of bytes written.
     pub fn finish(mut self) -> io::Result<(W, usize)> {
         self.flush()?;
         Ok((self.underlying, self.written_bytes))
@@ -27,7 +57,8 @@ impl<W: Write> CountingWriter<W> {
 impl<W: Write> Write for CountingWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let written_size = self.underlying.write(buf)?;
-        self.written_bytes += written_size;
+        self.position += written_size;
+        self.written_bytes = cmp::max(self.written_bytes, self.position);
         Ok(written_size)
     }
 
@@ -36,11 +67,19 @@ impl<W: Write> Write for CountingWriter<W> {
     }
 }
 
+impl<W: Write + Seek> Seek for CountingWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = self.underlying.seek(pos)?;
+        self.position = new_position as usize;
+        Ok(new_position)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::CountingWriter;
-    use std::io::Write;
+    use std::io::{self, Cursor, Seek, SeekFrom, Write};
 
     #[test]
     fn test_counting_writer() {
@@ -52,4 +91,47 @@ mod test {
         assert_eq!(len, 10);
         assert_eq!(w.len(), 10);
     }
+
+    #[test]
+    fn test_counting_writer_seek() {
+        let mut counting_writer = CountingWriter::wrap(Cursor::new(Vec::<u8>::new()));
+        counting_writer.write_all(&[0u8; 10]).unwrap();
+        assert_eq!(counting_writer.seek(SeekFrom::Start(2)).unwrap(), 2);
+        assert_eq!(counting_writer.position(), 2);
+        counting_writer.write_all(&[1u8; 3]).unwrap();
+        assert_eq!(counting_writer.position(), 5);
+        assert_eq!(counting_writer.written_bytes(), 10);
+        assert_eq!(counting_writer.get_ref().position(), 5);
+        assert_eq!(counting_writer.seek(SeekFrom::End(0)).unwrap(), 10);
+        counting_writer.write_all(&[2u8; 2]).unwrap();
+        assert_eq!(counting_writer.written_bytes(), 12);
+        assert_eq!(counting_writer.seek(SeekFrom::Current(-1)).unwrap(), 11);
+        let (w, len) = counting_writer.finish().unwrap();
+        assert_eq!(len, 12);
+        assert_eq!(w.into_inner(), vec![0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 2, 2]);
+    }
+
+    #[test]
+    fn test_counting_writer_failed_seek() {
+        struct NonSeekable(Vec<u8>);
+        impl Write for NonSeekable {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        impl Seek for NonSeekable {
+            fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+                Err(io::Error::new(io::ErrorKind::Other, "not seekable"))
+            }
+        }
+        let mut counting_writer = CountingWriter::wrap(NonSeekable(vec![]));
+        counting_writer.write_all(&[0u8; 4]).unwrap();
+        assert!(counting_writer.seek(SeekFrom::Start(0)).is_err());
+        assert_eq!(counting_writer.position(), 4);
+        assert_eq!(counting_writer.written_bytes(), 4);
+        assert_eq!(counting_writer.get_ref().0.len(), 4);
+    }
 }
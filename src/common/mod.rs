@@ -17,6 +17,7 @@ pub use self::bitset::BitSet;
 pub(crate) use self::bitset::TinySet;
 pub use byteorder::LittleEndian as Endianness;
 
+use std::ascii;
 use std::io;
 
 /// Computes the number of bits that will be used for bitpacking.
@@ -61,6 +62,20 @@ pub(crate) fn make_io_err(msg: String) -> io::Error {
     io::Error::new(io::ErrorKind::Other, msg)
 }
 
+/// Returns a printable representation of arbitrary bytes,
+/// as a byte string literal (e.g. `b"ab\xff"`).
+///
+/// Printable ASCII characters are kept as is, and the other bytes are
+/// hex escaped, so that the bytes can be recovered from their representation.
+pub(crate) fn escape_bytes(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|&b| ascii::escape_default(b))
+        .map(char::from)
+        .collect();
+    format!("b\"{}\"", escaped)
+}
+
 /// Has length trait
 pub trait HasLen {
     /// Return length
@@ -108,8 +123,8 @@ pub fn u64_to_i64(val: u64) -> i64 {
 #[cfg(test)]
 pub(crate) mod test {
 
-    use super::{compute_num_bits, i64_to_u64, u64_to_i64};
-    pub use super::serialize::test::fixed_size_test;
+    use super::{compute_num_bits, escape_bytes, i64_to_u64, u64_to_i64};
+    pub use super::serialize::test::{fixed_size_test, serialize_test};
 
     fn test_i64_converter_helper(val: i64) {
         assert_eq!(u64_to_i64(i64_to_u64(val)), val);
@@ -127,6 +142,15 @@ pub(crate) mod test {
         }
     }
 
+    #[test]
+    fn test_escape_bytes() {
+        assert_eq!(escape_bytes(b""), "b\"\"");
+        assert_eq!(escape_bytes(b"abc"), "b\"abc\"");
+        assert_eq!(escape_bytes(b"\x00a\xff"), "b\"\\x00a\\xff\"");
+        assert_eq!(escape_bytes(b"\"\\"), "b\"\\\"\\\\\"");
+        assert_eq!(escape_bytes("é".as_bytes()), "b\"\\xc3\\xa9\"");
+    }
+
     #[test]
     fn test_compute_num_bits() {
         assert_eq!(compute_num_bits(1), 1u8);
@@ -144,7 +144,7 @@ pub mod test {
         assert_eq!(buffer.len(), O::SIZE_IN_BYTES);
     }
 
-    fn serialize_test<T: BinarySerializable + Eq>(v: T) -> usize {
+    pub fn serialize_test<T: BinarySerializable + Eq>(v: T) -> usize {
         let mut buffer: Vec<u8> = Vec::new();
         v.serialize(&mut buffer).unwrap();
         let num_bytes = buffer.len();
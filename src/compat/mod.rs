@@ -0,0 +1,327 @@
+/*!
+Compatibility with the indexes written by upstream tantivy.
+
+This fork started diverging from the upstream index format.
+Each segment records the version of the format it was written in,
+in its `SegmentMeta`. The segments that do not record it were written
+- by this fork before the version was recorded. Their meta records the
+statistics that only this fork writes (see `SegmentMeta::total_num_tokens`),
+and their store starts with a header, unless they were written before the
+header was introduced. They are in the `INDEX_FORMAT_VERSION` format.
+- by upstream tantivy, in which case neither their meta records these
+statistics, nor their store starts with a header
+(`UPSTREAM_FORMAT_VERSION`).
+
+The store of the upstream segments does not start with a header.
+The store reader detects it, as it does for the stores this fork wrote
+before the header was introduced.
+
+Reading segments in the upstream format requires the `compat-upstream`
+feature. Without it, or when a segment was written in a newer format,
+opening the segment fails with an
+[`IncompatibleIndex`](../enum.ErrorKind.html#variant.IncompatibleIndex) error.
+*/
+
+use core::SegmentMeta;
+use directory::ReadOnlySource;
+use store::{StoreReader, STORE_FORMAT_VERSION};
+use Result;
+use ErrorKind;
+
+/// Version of the format of the segments written by upstream tantivy.
+pub const UPSTREAM_FORMAT_VERSION: u32 = 0;
+
+/// Version of the format of the segments written by this version of tantivy.
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Returns the format version of a segment, given its meta and
+/// the data of its store.
+///
+/// Returns an `IncompatibleIndex` error if the segment cannot be read.
+pub(crate) fn segment_format_version(
+    segment_meta: &SegmentMeta,
+    store_data: &ReadOnlySource,
+) -> Result<u32> {
+    let store_format_version_opt = StoreReader::read_format_version(store_data);
+    if let Some(store_format_version) = store_format_version_opt {
+        if store_format_version > STORE_FORMAT_VERSION {
+            bail!(ErrorKind::IncompatibleIndex(
+                format!("store of the segment {}", segment_meta.id().short_uuid_string()),
+                store_format_version,
+                STORE_FORMAT_VERSION,
+            ));
+        }
+    }
+    let format_version = match segment_meta.format_version() {
+        Some(format_version) => format_version,
+        // written by this fork before the format version was recorded.
+        None if store_format_version_opt.is_some() => INDEX_FORMAT_VERSION,
+        // written by this fork before the store header was introduced.
+        None if segment_meta.has_fork_statistics() => INDEX_FORMAT_VERSION,
+        None => UPSTREAM_FORMAT_VERSION,
+    };
+    let readable = if format_version == UPSTREAM_FORMAT_VERSION {
+        cfg!(feature = "compat-upstream")
+    } else {
+        format_version <= INDEX_FORMAT_VERSION
+    };
+    if !readable {
+        bail!(ErrorKind::IncompatibleIndex(
+            format!("segment {}", segment_meta.id().short_uuid_string()),
+            format_version,
+            INDEX_FORMAT_VERSION,
+        ));
+    }
+    Ok(format_version)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::INDEX_FORMAT_VERSION;
+    use Index;
+    use ErrorKind;
+    use collector::TopCollector;
+    use common::BinarySerializable;
+    use query::{AllQuery, Query, TermQuery};
+    use schema::{IndexRecordOption, Schema, SchemaBuilder, Term, STORED, STRING, TEXT};
+    use serde_json::{self, Map, Value};
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::Path;
+    use store::STORE_FORMAT_VERSION;
+    use tempdir::TempDir;
+
+    // Store of a segment holding the two documents of `write_legacy_index`,
+    // in the layout written before the store header was introduced: the
+    // LZ4 frames of the blocks of documents, the skip list of the blocks,
+    // and a footer with the offset of the skip list and the number of documents.
+    //
+    // Upstream tantivy writes its stores in the same layout.
+    // The block is stored uncompressed in its LZ4 frame.
+    const HEADERLESS_STORE: [u8; 120] = [
+        0x5d, 0x00, 0x00, 0x00, 0x04, 0x22, 0x4d, 0x18, 0x60, 0x40, 0x82, 0x4e,
+        0x00, 0x00, 0x80, 0xad, 0x82, 0x00, 0x00, 0x00, 0x00, 0x00, 0x97, 0x74,
+        0x68, 0x65, 0x20, 0x6f, 0x6c, 0x64, 0x20, 0x6d, 0x61, 0x6e, 0x20, 0x61,
+        0x6e, 0x64, 0x20, 0x74, 0x68, 0x65, 0x20, 0x73, 0x65, 0x61, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x89, 0x68, 0x65, 0x6d, 0x69, 0x6e, 0x67, 0x77, 0x61,
+        0x79, 0x9f, 0x82, 0x00, 0x00, 0x00, 0x00, 0x00, 0x90, 0x61, 0x20, 0x74,
+        0x65, 0x6c, 0x65, 0x76, 0x69, 0x73, 0x69, 0x6f, 0x6e, 0x20, 0x73, 0x65,
+        0x74, 0x01, 0x00, 0x00, 0x00, 0x00, 0x82, 0x74, 0x76, 0x00, 0x00, 0x00,
+        0x00, 0x81, 0x89, 0x82, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+    ];
+
+    fn legacy_schema() -> Schema {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.add_text_field("id", STRING | STORED);
+        schema_builder.build()
+    }
+
+    fn read_file(path: &Path) -> Vec<u8> {
+        let mut data = Vec::new();
+        fs::File::open(path).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    fn write_file(path: &Path, data: &[u8]) {
+        fs::File::create(path).unwrap().write_all(data).unwrap();
+    }
+
+    fn edit_segment_metas<F>(index_path: &Path, mut edit: F)
+    where
+        F: FnMut(&mut Map<String, Value>),
+    {
+        let meta_path = index_path.join("meta.json");
+        let mut meta: Value = serde_json::from_slice(&read_file(&meta_path)).unwrap();
+        for segment in meta["segments"].as_array_mut().unwrap() {
+            edit(segment.as_object_mut().unwrap());
+        }
+        write_file(&meta_path, &serde_json::to_vec_pretty(&meta).unwrap());
+    }
+
+    fn set_segment_format_versions(index_path: &Path, format_version_opt: Option<u32>) {
+        edit_segment_metas(index_path, |segment| match format_version_opt {
+            Some(format_version) => {
+                segment.insert("format_version".to_string(), Value::from(format_version));
+            }
+            None => {
+                segment.remove("format_version");
+            }
+        });
+    }
+
+    // Writes an index of a single segment, whose store is replaced by
+    // `HEADERLESS_STORE`, and whose meta does not record its format version.
+    // The other files of the segment are written in the same layout
+    // by upstream tantivy and all of the versions of this fork.
+    //
+    // If `upstream` is true, the statistics that only this fork
+    // writes are removed from the meta as well.
+    fn write_legacy_index(index_path: &Path, upstream: bool) {
+        let schema = legacy_schema();
+        let title = schema.get_field("title").unwrap();
+        let id = schema.get_field("id").unwrap();
+        let index = Index::create(index_path, schema).unwrap();
+        let segment_ids = {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "the old man and the sea", id => "hemingway"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "a television set", id => "tv"))
+                .unwrap();
+            index_writer.commit().unwrap();
+            index.searchable_segment_ids().unwrap()
+        };
+        assert_eq!(segment_ids.len(), 1);
+        let store_path = index_path.join(format!("{}.store", segment_ids[0].uuid_string()));
+        write_file(&store_path, &HEADERLESS_STORE);
+        edit_segment_metas(index_path, |segment| {
+            segment.remove("format_version");
+            if upstream {
+                segment.remove("total_num_tokens");
+                segment.remove("index_record_options");
+                segment.remove("int_value_ranges");
+                segment.remove("fast_field_value_ranges");
+            }
+        });
+    }
+
+    fn is_incompatible_index(index_path: &Path) -> bool {
+        match Index::open(index_path) {
+            Err(error) => match *error.kind() {
+                ErrorKind::IncompatibleIndex(_, _, _) => true,
+                _ => false,
+            },
+            Ok(_) => false,
+        }
+    }
+
+    // Searches a term, reads the stored document,
+    // and adds a segment to the index.
+    fn check_legacy_index(index: &Index) {
+        let schema = index.schema();
+        let title = schema.get_field("title").unwrap();
+        let id = schema.get_field("id").unwrap();
+        let searcher = index.searcher();
+        assert_eq!(AllQuery.count(&*searcher).unwrap(), 2);
+        let term_query = TermQuery::new(
+            Term::from_field_text(title, "television"),
+            IndexRecordOption::WithFreqs,
+        );
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&term_query, &mut top_collector).unwrap();
+        let doc_addresses = top_collector.docs();
+        assert_eq!(doc_addresses.len(), 1);
+        let doc = searcher.doc(&doc_addresses[0]).unwrap();
+        assert_eq!(doc.get_first(id).unwrap().text(), "tv");
+        assert_eq!(doc.get_first(title).unwrap().text(), "a television set");
+
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title => "a radio")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(AllQuery.count(&*index.searcher()).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_segments_record_their_format_version() {
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        let index = Index::create(temp_dir.path(), legacy_schema()).unwrap();
+        let title = index.schema().get_field("title").unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title => "a")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.format_version(), INDEX_FORMAT_VERSION);
+        for segment_meta in index.searchable_segment_metas().unwrap() {
+            assert_eq!(segment_meta.format_version(), Some(INDEX_FORMAT_VERSION));
+        }
+        // segments written before the format version was recorded
+        // are detected thanks to the header of their store.
+        drop(index_writer);
+        set_segment_format_versions(temp_dir.path(), None);
+        let index = Index::open(temp_dir.path()).unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_reader(0).format_version(), INDEX_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn test_read_segment_without_store_header() {
+        // segments written before the store header was introduced
+        // are detected thanks to the statistics recorded in their meta,
+        // and do not require the `compat-upstream` feature.
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        write_legacy_index(temp_dir.path(), false);
+        let index = Index::open(temp_dir.path()).unwrap();
+        for segment_meta in index.searchable_segment_metas().unwrap() {
+            assert_eq!(segment_meta.format_version(), None);
+        }
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_reader(0).format_version(), INDEX_FORMAT_VERSION);
+        check_legacy_index(&index);
+    }
+
+    #[test]
+    fn test_newer_format_version_is_incompatible() {
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        {
+            let index = Index::create(temp_dir.path(), legacy_schema()).unwrap();
+            let title = index.schema().get_field("title").unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "a")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        set_segment_format_versions(temp_dir.path(), Some(INDEX_FORMAT_VERSION + 1));
+        assert!(is_incompatible_index(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_newer_store_format_version_is_incompatible() {
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        let segment_ids = {
+            let index = Index::create(temp_dir.path(), legacy_schema()).unwrap();
+            let title = index.schema().get_field("title").unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "a")).unwrap();
+            index_writer.commit().unwrap();
+            index.searchable_segment_ids().unwrap()
+        };
+        let store_path = temp_dir
+            .path()
+            .join(format!("{}.store", segment_ids[0].uuid_string()));
+        let mut data = read_file(&store_path);
+        // the format version follows the magic number.
+        let mut format_version_bytes = &mut data[4..8];
+        (STORE_FORMAT_VERSION + 1)
+            .serialize(&mut format_version_bytes)
+            .unwrap();
+        write_file(&store_path, &data);
+        assert!(is_incompatible_index(temp_dir.path()));
+    }
+
+    #[cfg(not(feature = "compat-upstream"))]
+    #[test]
+    fn test_upstream_index_requires_compat_feature() {
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        write_legacy_index(temp_dir.path(), true);
+        assert!(is_incompatible_index(temp_dir.path()));
+    }
+
+    #[cfg(feature = "compat-upstream")]
+    #[test]
+    fn test_read_upstream_index() {
+        use super::UPSTREAM_FORMAT_VERSION;
+
+        let temp_dir = TempDir::new("tantivy_compat_test").unwrap();
+        write_legacy_index(temp_dir.path(), true);
+        let index = Index::open(temp_dir.path()).unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_reader(0).format_version(), UPSTREAM_FORMAT_VERSION);
+        check_legacy_index(&index);
+    }
+}
@@ -2,7 +2,9 @@ use Result;
 use error::{ErrorKind, ResultExt};
 use serde_json;
 use schema::Schema;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
 use std::borrow::BorrowMut;
 use std::fmt;
 use core::SegmentId;
@@ -17,32 +19,49 @@ use super::pool::Pool;
 use core::SegmentMeta;
 use super::pool::LeasedItem;
 use std::path::Path;
-use core::IndexMeta;
-use indexer::DirectoryLock;
+use core::{IndexMeta, IndexSettings};
+use core::IndexBuilder;
+use indexer::{DirectoryLock, Opstamp};
 use IndexWriter;
 use directory::ManagedDirectory;
 use core::META_FILEPATH;
 use super::segment::create_segment;
 use indexer::segment_updater::save_new_metas;
 use tokenizer::TokenizerManager;
+use super::index_recovery::{recover_meta, RecoveryReport};
 
 const NUM_SEARCHERS: usize = 12;
 
-fn load_metas(directory: &Directory) -> Result<IndexMeta> {
+pub(crate) fn load_metas(directory: &Directory) -> Result<IndexMeta> {
     let meta_data = directory.atomic_read(&META_FILEPATH)?;
     let meta_string = String::from_utf8_lossy(&meta_data);
     serde_json::from_str(&meta_string).chain_err(|| ErrorKind::CorruptedFile(META_FILEPATH.clone()))
 }
 
+/// Identifies the state of the searchable segments:
+/// their ids and the opstamp of their deletes.
+type SearchableSegmentsKey = Vec<(SegmentId, Option<u64>)>;
+
 /// Search Index
 pub struct Index {
     directory: ManagedDirectory,
     schema: Schema,
     searcher_pool: Arc<Pool<Searcher>>,
+    // segments the current generation of searchers was built on.
+    // The lock also serializes the calls to `load_searchers`.
+    loaded_segments: Arc<Mutex<Option<SearchableSegmentsKey>>>,
+    eager_loading_threshold: Arc<AtomicUsize>,
     tokenizers: TokenizerManager,
+    settings: IndexSettings,
 }
 
 impl Index {
+    /// Returns an `IndexBuilder`, to create or open an index
+    /// with its schema, settings and tokenizers configured at once.
+    pub fn builder() -> IndexBuilder {
+        IndexBuilder::new()
+    }
+
     /// Creates a new index using the `RAMDirectory`.
     ///
     /// The index will be allocated in anonymous memory.
@@ -63,7 +82,17 @@ impl Index {
     /// If a previous index was in this directory, then its meta file will be destroyed.
     pub fn create<P: AsRef<Path>>(directory_path: P, schema: Schema) -> Result<Index> {
         let mmap_directory = MmapDirectory::open(directory_path)?;
-        let directory = ManagedDirectory::new(mmap_directory)?;
+        Index::create_in_directory(mmap_directory, schema)
+    }
+
+    /// Creates a new index in the given directory.
+    ///
+    /// This makes it possible to use a configured `MmapDirectory`,
+    /// or any other implementation of `Directory`.
+    ///
+    /// If a previous index was in this directory, then its meta file will be destroyed.
+    pub fn create_in_directory<Dir: Directory>(directory: Dir, schema: Schema) -> Result<Index> {
+        let directory = ManagedDirectory::new(directory)?;
         Index::from_directory(directory, schema)
     }
 
@@ -87,31 +116,125 @@ impl Index {
     }
 
     /// Creates a new index given a directory and an `IndexMeta`.
-    fn create_from_metas(directory: ManagedDirectory, metas: &IndexMeta) -> Result<Index> {
+    pub(crate) fn create_from_metas(
+        directory: ManagedDirectory,
+        metas: &IndexMeta,
+        tokenizers: TokenizerManager,
+    ) -> Result<Index> {
         let schema = metas.schema.clone();
+        let settings = metas.settings.clone();
         let index = Index {
             directory,
             schema,
             searcher_pool: Arc::new(Pool::new()),
-            tokenizers: TokenizerManager::default(),
+            loaded_segments: Arc::new(Mutex::new(None)),
+            eager_loading_threshold: Arc::new(AtomicUsize::new(settings.eager_loading_threshold)),
+            tokenizers,
+            settings,
         };
         index.load_searchers()?;
         Ok(index)
     }
 
     /// Create a new index from a directory.
-    pub fn from_directory(mut directory: ManagedDirectory, schema: Schema) -> Result<Index> {
-        save_new_metas(schema.clone(), 0, directory.borrow_mut())?;
-        let metas = IndexMeta::with_schema(schema);
-        Index::create_from_metas(directory, &metas)
+    pub fn from_directory(directory: ManagedDirectory, schema: Schema) -> Result<Index> {
+        Index::create_with(
+            directory,
+            schema,
+            IndexSettings::default(),
+            TokenizerManager::default(),
+        )
+    }
+
+    /// Writes the `meta.json` of a new index, and opens it.
+    pub(crate) fn create_with(
+        mut directory: ManagedDirectory,
+        schema: Schema,
+        settings: IndexSettings,
+        tokenizers: TokenizerManager,
+    ) -> Result<Index> {
+        save_new_metas(
+            schema.clone(),
+            settings.clone(),
+            Opstamp(0),
+            directory.borrow_mut(),
+        )?;
+        let mut metas = IndexMeta::with_schema(schema);
+        metas.settings = settings;
+        Index::create_from_metas(directory, &metas, tokenizers)
     }
 
     /// Opens a new directory from an index path.
     pub fn open<P: AsRef<Path>>(directory_path: P) -> Result<Index> {
         let mmap_directory = MmapDirectory::open(directory_path)?;
-        let directory = ManagedDirectory::new(mmap_directory)?;
+        Index::open_directory(mmap_directory)
+    }
+
+    /// Opens the index stored in the given directory.
+    pub fn open_directory<Dir: Directory>(directory: Dir) -> Result<Index> {
+        let directory = ManagedDirectory::new(directory)?;
         let metas = load_metas(&directory)?;
-        Index::create_from_metas(directory, &metas)
+        Index::create_from_metas(directory, &metas, TokenizerManager::default())
+    }
+
+    /// Rebuilds the `meta.json` file of an index from the segment files
+    /// found in its directory, for instance after it got deleted or corrupted.
+    ///
+    /// The segment files are listed with `Directory::list_files`,
+    /// and grouped by segment. Segments missing a file, or with a corrupted
+    /// file, are skipped. The meta of each segment is read from its `META`
+    /// file, its number of documents is checked against the footer of its
+    /// store, and its deletes are read from its most recent delete file.
+    ///
+    /// A merge leaves the segments it was merged from on disk until they
+    /// get garbage collected. These segments are skipped, as their documents
+    /// belong to the merged segment.
+    ///
+    /// The following information only lived in the lost `meta.json`
+    /// and cannot be recovered:
+    /// - the opstamp and the payload of the last commit,
+    /// - the settings of the index, which are reset to their defaults,
+    /// - which of the segment files were actually part of the last commit,
+    /// - the statistics of the segments written before the `META` file
+    ///   was introduced (see `SegmentMeta::total_num_tokens`,
+    ///   `SegmentMeta::index_record_option` and `SegmentMeta::int_value_range`).
+    ///
+    /// Segments that were flushed but not committed yet, or merges that
+    /// completed but were not committed yet, are recovered as well, and
+    /// a delete file that was written for a commit that did not complete
+    /// is applied.
+    ///
+    /// No `IndexWriter` may be opened on the directory during the recovery.
+    /// Once the recovery is done, the index can be opened as usual.
+    pub fn recover_meta<Dir: Directory>(directory: Dir, schema: Schema) -> Result<RecoveryReport> {
+        let directory = ManagedDirectory::new(directory)?;
+        recover_meta(directory, schema)
+    }
+
+    /// Sets the size (in bytes) under which the per-field term dictionaries
+    /// and fast fields are copied into anonymous memory when the segments
+    /// are loaded, instead of being accessed through mmap.
+    ///
+    /// This avoids paying for page faults when small dictionaries are first
+    /// accessed. Defaults to `0`: everything remains mmapped.
+    ///
+    /// The setting is shared by all of the clones of the `Index`,
+    /// and applies from the next call to `load_searchers`.
+    ///
+    /// It is not persisted. Use
+    /// [`IndexSettings`](./struct.IndexSettings.html) to persist it.
+    pub fn set_eager_loading_threshold(&self, num_bytes: usize) {
+        let mut loaded_segments = self.loaded_segments
+            .lock()
+            .expect("Loaded segments lock poisoned. This should never happen.");
+        self.eager_loading_threshold.store(num_bytes, Ordering::SeqCst);
+        // force the next call to `load_searchers` to reopen the segments.
+        *loaded_segments = None;
+    }
+
+    /// Accessor to the settings of the index.
+    pub fn settings(&self) -> &IndexSettings {
+        &self.settings
     }
 
     /// Reads the index meta file from the directory.
@@ -119,6 +242,23 @@ impl Index {
         load_metas(self.directory())
     }
 
+    /// Reads the index meta file of the index stored in the given directory,
+    /// without opening the index nor any of its segments.
+    ///
+    /// This is useful to inspect an index, for instance to list its segments
+    /// and their number of documents, from a process that does not search it.
+    pub fn load_metas_from_directory(directory: &Directory) -> Result<IndexMeta> {
+        load_metas(directory)
+    }
+
+    /// Returns the opstamp of the last commit, as read from the index meta file.
+    ///
+    /// It is `Opstamp(0)` if nothing was committed yet.
+    /// See [`Opstamp`](./struct.Opstamp.html) for the guarantees on opstamps.
+    pub fn last_committed_opstamp(&self) -> Result<Opstamp> {
+        Ok(self.load_metas()?.opstamp)
+    }
+
     /// Open a new index writer. Attempts to acquire a lockfile.
     ///
     /// The lockfile should be deleted on drop, but it is possible
@@ -205,21 +345,56 @@ impl Index {
     }
 
     /// Creates a new generation of searchers after
-
     /// a change of the set of searchable indexes.
     ///
     /// This needs to be called when a new segment has been
     /// published or after a merge.
+    ///
+    /// The searchers are built on the segments of the last `meta.json`
+    /// written to the directory. As a result, all of the commits
+    /// that have returned before the call to `load_searchers` (from any
+    /// thread) are visible to the searchers acquired after it returns.
+    /// See also [`IndexWriter::commit_and_reload`](./struct.IndexWriter.html#method.commit_and_reload).
+    ///
+    /// Calling this method is cheap if the searchable segments did not
+    /// change since the last call: no new generation of searchers
+    /// is created.
+    /// Concurrent calls are serialized, so that a generation of
+    /// searchers can never be replaced by an older one.
     pub fn load_searchers(&self) -> Result<()> {
-        let searchable_segments = self.searchable_segments()?;
-        let segment_readers: Vec<SegmentReader> = searchable_segments
+        let mut loaded_segments = self.loaded_segments
+            .lock()
+            .expect("Loaded segments lock poisoned. This should never happen.");
+        let searchable_segment_metas = self.searchable_segment_metas()?;
+        let searchable_segments_key: SearchableSegmentsKey = searchable_segment_metas
             .iter()
-            .map(SegmentReader::open)
+            .map(|segment_meta| (segment_meta.id(), segment_meta.delete_opstamp()))
+            .collect();
+        if loaded_segments.as_ref() == Some(&searchable_segments_key) {
+            return Ok(());
+        }
+        let eager_loading_threshold = self.eager_loading_threshold.load(Ordering::SeqCst);
+        let segment_readers: Vec<SegmentReader> = searchable_segment_metas
+            .into_iter()
+            .map(|segment_meta| {
+                SegmentReader::open_with_eager_loading(
+                    &self.segment(segment_meta),
+                    eager_loading_threshold,
+                )
+            })
             .collect::<Result<_>>()?;
+        let term_ordinal_mappings = Arc::new(Mutex::new(HashMap::new()));
         let searchers = (0..NUM_SEARCHERS)
-            .map(|_| Searcher::from(segment_readers.clone()))
+            .map(|_| {
+                Searcher::new(
+                    self.schema(),
+                    segment_readers.clone(),
+                    Arc::clone(&term_ordinal_mappings),
+                )
+            })
             .collect();
         self.searcher_pool.publish_new_generation(searchers);
+        *loaded_segments = Some(searchable_segments_key);
         Ok(())
     }
 
@@ -250,7 +425,10 @@ impl Clone for Index {
             directory: self.directory.clone(),
             schema: self.schema.clone(),
             searcher_pool: Arc::clone(&self.searcher_pool),
+            loaded_segments: Arc::clone(&self.loaded_segments),
+            eager_loading_threshold: Arc::clone(&self.eager_loading_threshold),
             tokenizers: self.tokenizers.clone(),
+            settings: self.settings.clone(),
         }
     }
 }
@@ -0,0 +1,348 @@
+use Result;
+use error::ErrorKind;
+use serde_json;
+use schema::{FieldType, Schema};
+use directory::{Directory, ManagedDirectory, MmapDirectory, RAMDirectory};
+use tokenizer::{Tokenizer, TokenizerManager};
+use core::{Index, IndexSettings, META_FILEPATH};
+use core::index::load_metas;
+use std::path::Path;
+
+/// `IndexBuilder` creates or opens an `Index` with its schema,
+/// its settings and its tokenizers, all configured at once.
+///
+/// The configuration is checked before anything gets written:
+/// for instance, all of the tokenizers the schema refers to must be
+/// either built in, or registered with `.tokenizer(...)`.
+///
+/// ```rust
+/// use tantivy::{Index, IndexSettings};
+/// use tantivy::schema::{SchemaBuilder, TextFieldIndexing, TextOptions};
+/// use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, Tokenizer};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title_options = TextOptions::default()
+///     .set_indexing_options(TextFieldIndexing::default().set_tokenizer("custom"));
+/// schema_builder.add_text_field("title", title_options);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::builder()
+///     .schema(schema)
+///     .settings(IndexSettings {
+///         eager_loading_threshold: 1_000_000,
+///     })
+///     .tokenizer("custom", SimpleTokenizer.filter(LowerCaser))
+///     .create_in_ram()?;
+/// assert!(index.tokenizers().get("custom").is_some());
+/// assert_eq!(index.settings().eager_loading_threshold, 1_000_000);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+pub struct IndexBuilder {
+    schema: Option<Schema>,
+    settings: Option<IndexSettings>,
+    tokenizers: TokenizerManager,
+}
+
+impl IndexBuilder {
+    /// Creates a new `IndexBuilder`, with the default settings
+    /// and the built-in tokenizers.
+    ///
+    /// See also [`Index::builder`](./struct.Index.html#method.builder).
+    pub fn new() -> IndexBuilder {
+        IndexBuilder {
+            schema: None,
+            settings: None,
+            tokenizers: TokenizerManager::default(),
+        }
+    }
+
+    /// Sets the schema of the index.
+    pub fn schema(mut self, schema: Schema) -> IndexBuilder {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Sets the settings of the index.
+    ///
+    /// They are persisted in the `meta.json` file of the index.
+    pub fn settings(mut self, settings: IndexSettings) -> IndexBuilder {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Registers a tokenizer under the given name.
+    ///
+    /// Tokenizers are not persisted: they need to be registered
+    /// every time the index is opened.
+    pub fn tokenizer<A>(self, tokenizer_name: &str, tokenizer: A) -> IndexBuilder
+    where
+        A: 'static + Send + Sync + for<'a> Tokenizer<'a>,
+    {
+        self.tokenizers.register(tokenizer_name, tokenizer);
+        self
+    }
+
+    /// Creates a new index using the `RAMDirectory`.
+    pub fn create_in_ram(self) -> Result<Index> {
+        self.create(RAMDirectory::create())
+    }
+
+    /// Creates a new index in a given filepath, using the `MmapDirectory`.
+    ///
+    /// If a previous index was in this directory, then its meta file will be destroyed.
+    pub fn create_in_dir<P: AsRef<Path>>(self, directory_path: P) -> Result<Index> {
+        let mmap_directory = MmapDirectory::open(directory_path)?;
+        self.create(mmap_directory)
+    }
+
+    /// Creates a new index in the given directory.
+    ///
+    /// If a previous index was in this directory, then its meta file will be destroyed.
+    pub fn create<Dir: Directory>(self, directory: Dir) -> Result<Index> {
+        let schema = self.validate()?;
+        let directory = ManagedDirectory::new(directory)?;
+        let settings = self.settings.unwrap_or_default();
+        Index::create_with(directory, schema, settings, self.tokenizers)
+    }
+
+    /// Opens the index of the given directory, or creates it
+    /// if the directory does not contain any index.
+    ///
+    /// # Errors
+    /// If the directory contains an index, its schema must be the one
+    /// of the builder, and its settings must be the ones of the builder
+    /// if they were set. Otherwise, `ErrorKind::SchemaMismatch` or
+    /// `ErrorKind::InvalidArgument` is returned.
+    pub fn open_or_create<Dir: Directory>(self, directory: Dir) -> Result<Index> {
+        let schema = self.validate()?;
+        let directory = ManagedDirectory::new(directory)?;
+        if !directory.exists(&META_FILEPATH) {
+            let settings = self.settings.unwrap_or_default();
+            return Index::create_with(directory, schema, settings, self.tokenizers);
+        }
+        let metas = load_metas(&directory)?;
+        check_schema_matches(&metas.schema, &schema)?;
+        if let Some(ref settings) = self.settings {
+            if *settings != metas.settings {
+                bail!(ErrorKind::InvalidArgument(format!(
+                    "the settings of the index are {:?}, not {:?}",
+                    metas.settings, settings
+                )));
+            }
+        }
+        Index::create_from_metas(directory, &metas, self.tokenizers)
+    }
+
+    /// Checks that the schema is set, and that all of
+    /// the tokenizers it refers to are registered.
+    fn validate(&self) -> Result<Schema> {
+        let schema = self.schema
+            .clone()
+            .ok_or_else(|| ErrorKind::InvalidArgument("the schema is not set".to_string()))?;
+        for field_entry in schema.fields() {
+            if let FieldType::Str(ref text_options) = *field_entry.field_type() {
+                if let Some(indexing_options) = text_options.get_indexing_options() {
+                    let tokenizer_name = indexing_options.tokenizer();
+                    if self.tokenizers.get(tokenizer_name).is_none() {
+                        bail!(ErrorKind::InvalidArgument(format!(
+                            "the field {:?} uses the unknown tokenizer {:?}",
+                            field_entry.name(),
+                            tokenizer_name
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(schema)
+    }
+}
+
+impl Default for IndexBuilder {
+    fn default() -> IndexBuilder {
+        IndexBuilder::new()
+    }
+}
+
+/// Returns an error describing the first difference
+/// between the schema of an index and the expected one.
+fn check_schema_matches(index_schema: &Schema, expected_schema: &Schema) -> Result<()> {
+    for (field_id, (index_entry, expected_entry)) in index_schema
+        .fields()
+        .iter()
+        .zip(expected_schema.fields())
+        .enumerate()
+    {
+        let index_json = serde_json::to_string(index_entry)?;
+        let expected_json = serde_json::to_string(expected_entry)?;
+        if index_json != expected_json {
+            bail!(ErrorKind::SchemaMismatch(format!(
+                "field #{} is {} in the index, but {} was expected",
+                field_id, index_json, expected_json
+            )));
+        }
+    }
+    let num_index_fields = index_schema.fields().len();
+    let num_expected_fields = expected_schema.fields().len();
+    if num_index_fields != num_expected_fields {
+        bail!(ErrorKind::SchemaMismatch(format!(
+            "the index has {} fields, but {} were expected",
+            num_index_fields, num_expected_fields
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use IndexSettings;
+    use error::ErrorKind;
+    use directory::{MmapDirectory, RAMDirectory};
+    use schema::{Schema, SchemaBuilder, TextFieldIndexing, TextOptions, INT_STORED, TEXT};
+    use tokenizer::{LowerCaser, SimpleTokenizer, Tokenizer};
+    use tempdir::TempDir;
+
+    fn custom_schema() -> Schema {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default()
+            .set_indexing_options(TextFieldIndexing::default().set_tokenizer("custom"));
+        schema_builder.add_text_field("text", text_options);
+        schema_builder.build()
+    }
+
+    fn settings() -> IndexSettings {
+        IndexSettings {
+            eager_loading_threshold: 10_000,
+        }
+    }
+
+    fn add_document(index: &Index, text: &str) {
+        let text_field = index.schema().get_field("text").unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => text)).unwrap();
+        index_writer.commit().unwrap();
+    }
+
+    #[test]
+    fn test_index_builder_create_in_ram() {
+        let index = Index::builder()
+            .schema(custom_schema())
+            .settings(settings())
+            .tokenizer("custom", SimpleTokenizer.filter(LowerCaser))
+            .create_in_ram()
+            .unwrap();
+        assert_eq!(index.settings(), &settings());
+        assert_eq!(index.load_metas().unwrap().settings, settings());
+        add_document(&index, "Hello");
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_index_builder_unknown_tokenizer() {
+        let result = Index::builder().schema(custom_schema()).create_in_ram();
+        assert_matches!(result.err().unwrap().kind(), &ErrorKind::InvalidArgument(_));
+        let result = Index::builder().create_in_ram();
+        assert_matches!(result.err().unwrap().kind(), &ErrorKind::InvalidArgument(_));
+    }
+
+    #[test]
+    fn test_index_builder_create_in_dir() {
+        let temp_dir = TempDir::new("tantivy_index_builder_test").unwrap();
+        {
+            let index = Index::builder()
+                .schema(custom_schema())
+                .settings(settings())
+                .tokenizer("custom", SimpleTokenizer)
+                .create_in_dir(temp_dir.path())
+                .unwrap();
+            add_document(&index, "hello");
+        }
+        // the settings are persisted, and survive commits.
+        let index = Index::open(temp_dir.path()).unwrap();
+        assert_eq!(index.settings(), &settings());
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_index_builder_open_or_create() {
+        let temp_dir = TempDir::new("tantivy_index_builder_test").unwrap();
+        let open_or_create = || {
+            Index::builder()
+                .schema(custom_schema())
+                .tokenizer("custom", SimpleTokenizer)
+                .open_or_create(MmapDirectory::open(temp_dir.path()).unwrap())
+        };
+        {
+            let index = open_or_create().unwrap();
+            assert_eq!(index.settings(), &IndexSettings::default());
+            add_document(&index, "hello");
+        }
+        {
+            let index = open_or_create().unwrap();
+            assert_eq!(index.searcher().num_docs(), 1);
+            add_document(&index, "happy tax payer");
+        }
+        let index = open_or_create().unwrap();
+        assert_eq!(index.searcher().num_docs(), 2);
+        // settings differing from the persisted ones are rejected.
+        let result = Index::builder()
+            .schema(custom_schema())
+            .settings(settings())
+            .tokenizer("custom", SimpleTokenizer)
+            .open_or_create(MmapDirectory::open(temp_dir.path()).unwrap());
+        assert_matches!(result.err().unwrap().kind(), &ErrorKind::InvalidArgument(_));
+    }
+
+    #[test]
+    fn test_index_builder_open_or_create_schema_mismatch() {
+        let directory = RAMDirectory::create();
+        Index::builder()
+            .schema(custom_schema())
+            .tokenizer("custom", SimpleTokenizer)
+            .open_or_create(directory.clone())
+            .unwrap();
+        let other_schema = {
+            let mut schema_builder = SchemaBuilder::default();
+            schema_builder.add_text_field("text", TEXT);
+            schema_builder.build()
+        };
+        let err = Index::builder()
+            .schema(other_schema)
+            .open_or_create(directory.clone())
+            .err()
+            .unwrap();
+        match *err.kind() {
+            ErrorKind::SchemaMismatch(ref msg) => {
+                assert!(msg.starts_with("field #0 is"), "{}", msg);
+            }
+            _ => panic!("expected a schema mismatch, got {:?}", err),
+        }
+        let larger_schema = {
+            let mut schema_builder = SchemaBuilder::default();
+            let text_options = TextOptions::default()
+                .set_indexing_options(TextFieldIndexing::default().set_tokenizer("custom"));
+            schema_builder.add_text_field("text", text_options);
+            schema_builder.add_u64_field("num", INT_STORED);
+            schema_builder.build()
+        };
+        let err = Index::builder()
+            .schema(larger_schema)
+            .tokenizer("custom", SimpleTokenizer)
+            .open_or_create(directory)
+            .err()
+            .unwrap();
+        match *err.kind() {
+            ErrorKind::SchemaMismatch(ref msg) => {
+                assert_eq!(msg, "the index has 1 fields, but 2 were expected");
+            }
+            _ => panic!("expected a schema mismatch, got {:?}", err),
+        }
+    }
+}
@@ -1,34 +1,120 @@
 use schema::Schema;
 use core::SegmentMeta;
+use indexer::Opstamp;
 use std::fmt;
 use serde_json;
 
+/// Settings of an `Index`, persisted in its `meta.json` file.
+///
+/// They are set once and for all when the index is created,
+/// see [`IndexBuilder`](./struct.IndexBuilder.html).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexSettings {
+    /// Size (in bytes) under which the per-field term dictionaries and
+    /// fast fields are copied into anonymous memory when the segments
+    /// are loaded.
+    ///
+    /// See [`Index::set_eager_loading_threshold`][set_eager_loading_threshold].
+    ///
+    /// [set_eager_loading_threshold]: ./struct.Index.html#method.set_eager_loading_threshold
+    pub eager_loading_threshold: usize,
+}
+
+impl IndexSettings {
+    fn is_default(&self) -> bool {
+        *self == IndexSettings::default()
+    }
+}
+
 /// Meta information about the `Index`.
 ///
 /// This object is serialized on disk in the `meta.json` file.
 /// It keeps information about
 /// * the searchable segments,
-/// * the index `docstamp`
+/// * the index `opstamp`, and the last opstamp assigned before the commit
 /// * the schema
+/// * the settings
+///
+/// It can be read without opening the index, using
+/// [`Index::load_metas_from_directory`][load_metas_from_directory].
+///
+/// # Format stability
 ///
+/// The JSON shape of `meta.json` is stable within a major version:
+/// fields may be added, but are never renamed or removed. Unknown
+/// fields are ignored on read, so that a `meta.json` written by a newer
+/// version can still be read by an older one.
+///
+/// [load_metas_from_directory]: ./struct.Index.html#method.load_metas_from_directory
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IndexMeta {
+    /// Metas of the searchable segments.
     pub segments: Vec<SegmentMeta>,
+    /// Schema of the index.
     pub schema: Schema,
-    pub opstamp: u64,
+    /// Opstamp of the last commit.
+    pub opstamp: Opstamp,
+    /// Last opstamp assigned before the last commit was saved.
+    ///
+    /// It may be greater than the opstamp of the commit, as merges
+    /// also consume opstamps. `meta.json` files written before it was
+    /// recorded do not have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opstamp: Option<Opstamp>,
+    /// Payload of the last commit, if any.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub payload: Option<String>,
+    /// Settings of the index.
+    #[serde(default, skip_serializing_if = "IndexSettings::is_default")]
+    pub settings: IndexSettings,
 }
 
 impl IndexMeta {
+    /// Creates the metas of an empty index with the given schema.
     pub fn with_schema(schema: Schema) -> IndexMeta {
         IndexMeta {
             segments: vec![],
             schema,
-            opstamp: 0u64,
+            opstamp: Opstamp(0),
+            last_opstamp: None,
             payload: None,
+            settings: IndexSettings::default(),
         }
     }
+
+    /// Returns the metas of the searchable segments.
+    pub fn segments(&self) -> &[SegmentMeta] {
+        &self.segments
+    }
+
+    /// Returns the schema of the index.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Returns the opstamp of the last commit.
+    pub fn opstamp(&self) -> Opstamp {
+        self.opstamp
+    }
+
+    /// Returns the last opstamp assigned before the last commit.
+    ///
+    /// An `IndexWriter` opened on this index assigns opstamps
+    /// strictly after this one.
+    pub fn last_opstamp(&self) -> Opstamp {
+        self.last_opstamp.unwrap_or(self.opstamp)
+    }
+
+    /// Returns the payload of the last commit, if any.
+    pub fn payload(&self) -> Option<&str> {
+        self.payload.as_ref().map(|payload| payload.as_str())
+    }
+
+    /// Returns the settings of the index.
+    pub fn settings(&self) -> &IndexSettings {
+        &self.settings
+    }
 }
 
 impl fmt::Debug for IndexMeta {
@@ -46,8 +132,12 @@ impl fmt::Debug for IndexMeta {
 mod tests {
 
     use serde_json;
-    use super::IndexMeta;
-    use schema::{SchemaBuilder, TEXT};
+    use serde_json::Value;
+    use super::{IndexMeta, IndexSettings};
+    use indexer::Opstamp;
+    use schema::{Field, IndexRecordOption, SchemaBuilder, TEXT};
+
+    const META_FIXTURE: &str = include_str!("test_data/meta.json");
 
     #[test]
     fn test_serialize_metas() {
@@ -59,10 +149,104 @@ mod tests {
         let index_metas = IndexMeta {
             segments: Vec::new(),
             schema: schema,
-            opstamp: 0u64,
+            opstamp: Opstamp(0),
+            last_opstamp: None,
             payload: None,
+            settings: IndexSettings::default(),
         };
         let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
         assert_eq!(json, r#"{"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#);
     }
+
+    #[test]
+    fn test_serialize_metas_with_settings() {
+        let schema = SchemaBuilder::new().build();
+        let mut index_metas = IndexMeta::with_schema(schema);
+        index_metas.settings.eager_loading_threshold = 1_000;
+        let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
+        assert_eq!(
+            json,
+            r#"{"segments":[],"schema":[],"opstamp":0,"settings":{"eager_loading_threshold":1000}}"#
+        );
+        let deserialized: IndexMeta = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.settings.eager_loading_threshold, 1_000);
+    }
+
+    #[test]
+    fn test_serialize_metas_with_last_opstamp() {
+        let schema = SchemaBuilder::new().build();
+        let mut index_metas = IndexMeta::with_schema(schema);
+        index_metas.opstamp = Opstamp(3);
+        index_metas.last_opstamp = Some(Opstamp(5));
+        let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
+        assert_eq!(
+            json,
+            r#"{"segments":[],"schema":[],"opstamp":3,"last_opstamp":5}"#
+        );
+        let deserialized: IndexMeta = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.opstamp(), Opstamp(3));
+        assert_eq!(deserialized.last_opstamp(), Opstamp(5));
+    }
+
+    #[test]
+    fn test_deserialize_metas_fixture() {
+        let metas: IndexMeta = serde_json::from_str(META_FIXTURE).unwrap();
+        assert_eq!(metas.opstamp(), Opstamp(9));
+        // the fixture predates the recording of the last opstamp.
+        assert_eq!(metas.last_opstamp(), Opstamp(9));
+        assert_eq!(metas.payload(), Some("nightly import"));
+        assert_eq!(metas.settings().eager_loading_threshold, 4096);
+        let schema = metas.schema();
+        assert_eq!(schema.fields().len(), 4);
+        assert_eq!(schema.get_field("year"), Some(Field(1)));
+        assert!(schema.get_field_entry(Field(3)).is_unique_key());
+        let segments = metas.segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[0].id().uuid_string(),
+            "5b7e1b408c864d3f9d430e47f2a1c2b1"
+        );
+        assert_eq!(segments[0].max_doc(), 3);
+        assert_eq!(segments[0].num_docs(), 2);
+        assert_eq!(segments[0].delete_opstamp(), Some(Opstamp(7)));
+        assert_eq!(segments[0].total_num_tokens(Field(0)), Some(12));
+        assert_eq!(
+            segments[0].index_record_option(Field(1)),
+            Some(IndexRecordOption::Basic)
+        );
+        assert_eq!(segments[0].int_value_range(Field(1)), Some((1990, 2012)));
+        assert_eq!(segments[0].format_version(), Some(1));
+        assert_eq!(segments[1].num_docs(), 2);
+        assert!(!segments[1].has_deletes());
+        assert_eq!(segments[1].format_version(), None);
+    }
+
+    #[test]
+    fn test_serialize_metas_fixture() {
+        // Serializing the metas must give back the fixture. Any change in this
+        // test is a change of the `meta.json` format.
+        let metas: IndexMeta = serde_json::from_str(META_FIXTURE).unwrap();
+        let json = serde_json::to_string_pretty(&metas).unwrap();
+        let expected: Value = serde_json::from_str(META_FIXTURE).unwrap();
+        let serialized: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(serialized, expected);
+        assert_eq!(json.trim(), META_FIXTURE.trim());
+    }
+
+    #[test]
+    fn test_deserialize_metas_with_unknown_fields() {
+        let mut json: Value = serde_json::from_str(META_FIXTURE).unwrap();
+        json["added_later"] = serde_json::from_str(r#"{"some": "value"}"#).unwrap();
+        json["segments"][0]["added_later"] = Value::from(3u64);
+        json["schema"][0]["added_later"] = Value::Bool(true);
+        json["schema"][0]["options"]["added_later"] = Value::Array(Vec::new());
+        json["schema"][0]["options"]["indexing"]["added_later"] = Value::Null;
+        json["schema"][1]["options"]["added_later"] = Value::from("a");
+        json["settings"]["added_later"] = Value::from(1u64);
+        let metas: IndexMeta = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(metas.opstamp(), Opstamp(9));
+        assert_eq!(metas.segments().len(), 2);
+        assert_eq!(metas.schema().fields().len(), 4);
+        assert_eq!(metas.settings().eager_loading_threshold, 4096);
+    }
 }
@@ -0,0 +1,508 @@
+use Result;
+use DocId;
+use core::{IndexSettings, SegmentComponent, SegmentId, SegmentMeta, SegmentMetaFile};
+use common::CompositeFile;
+use directory::{Directory, ManagedDirectory, ReadOnlySource};
+use fastfield::DeleteBitSet;
+use common::HasLen;
+use indexer::{DirectoryLock, Opstamp};
+use indexer::segment_updater::save_metas;
+use schema::Schema;
+use serde_json;
+use store::StoreReader;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::result;
+
+/// Outcome of [`Index::recover_meta`](./struct.Index.html#method.recover_meta).
+#[derive(Debug)]
+pub struct RecoveryReport {
+    recovered_segments: Vec<SegmentMeta>,
+    skipped_segments: Vec<(SegmentId, String)>,
+    opstamp: Opstamp,
+}
+
+impl RecoveryReport {
+    /// Returns the meta of the segments written in the new `meta.json`.
+    pub fn recovered_segments(&self) -> &[SegmentMeta] {
+        &self.recovered_segments
+    }
+
+    /// Returns the segments that could not be recovered,
+    /// together with the reason why.
+    pub fn skipped_segments(&self) -> &[(SegmentId, String)] {
+        &self.skipped_segments
+    }
+
+    /// Returns the opstamp written in the new `meta.json`.
+    ///
+    /// The opstamp of the lost commit is unknown. It is replaced by the
+    /// largest opstamp of the recovered deletes, or 0 if there are none.
+    pub fn opstamp(&self) -> Opstamp {
+        self.opstamp
+    }
+}
+
+/// Returns the segment id and the delete opstamp (if any)
+/// of a segment file, given its path.
+fn parse_segment_file(path: &Path) -> Option<(SegmentId, Option<Opstamp>)> {
+    let filename = path.file_name()?.to_str()?;
+    let parts: Vec<&str> = filename.split('.').collect();
+    let segment_id = SegmentId::from_uuid_string(parts[0])?;
+    match parts.len() {
+        2 => Some((segment_id, None)),
+        3 if parts[2] == "del" => parts[1]
+            .parse::<u64>()
+            .ok()
+            .map(|opstamp| (segment_id, Some(Opstamp(opstamp)))),
+        _ => None,
+    }
+}
+
+fn open_component(
+    directory: &ManagedDirectory,
+    segment_meta: &SegmentMeta,
+    component: SegmentComponent,
+) -> result::Result<ReadOnlySource, String> {
+    let path = segment_meta.relative_path(component);
+    directory
+        .open_read(&path)
+        .map_err(|_| format!("Missing file {:?}", path))
+}
+
+fn check_composite_file(
+    directory: &ManagedDirectory,
+    segment_meta: &SegmentMeta,
+    component: SegmentComponent,
+) -> result::Result<(), String> {
+    let source = open_component(directory, segment_meta, component)?;
+    CompositeFile::open(&source)
+        .map(|_| ())
+        .map_err(|err| format!("Corrupted {:?} file: {}", component, err))
+}
+
+fn read_meta_file(
+    directory: &ManagedDirectory,
+    segment_id: SegmentId,
+) -> result::Result<Option<SegmentMetaFile>, String> {
+    let path = SegmentMeta::new(segment_id).relative_path(SegmentComponent::META);
+    if !directory.exists(&path) {
+        return Ok(None);
+    }
+    let source = directory
+        .open_read(&path)
+        .map_err(|_| format!("Missing file {:?}", path))?;
+    serde_json::from_slice(source.as_slice())
+        .map(Some)
+        .map_err(|err| format!("Corrupted META file: {}", err))
+}
+
+/// Rebuilds the `SegmentMeta` of a segment from its files, and returns
+/// it with the ids of the segments it was merged from.
+///
+/// `delete_opstamps` lists the opstamps of the delete files found for this segment.
+fn recover_segment_meta(
+    directory: &ManagedDirectory,
+    segment_id: SegmentId,
+    delete_opstamps: &[Opstamp],
+) -> result::Result<(SegmentMeta, Vec<SegmentId>), String> {
+    // segments written before the `META` component was introduced
+    // only have their number of documents recovered.
+    let (mut segment_meta, merged_segment_ids) = match read_meta_file(directory, segment_id)? {
+        Some(meta_file) => (
+            meta_file.segment_meta(segment_id),
+            meta_file.merged_segment_ids().to_vec(),
+        ),
+        None => (SegmentMeta::new(segment_id), Vec::new()),
+    };
+
+    let store_source = open_component(directory, &segment_meta, SegmentComponent::STORE)?;
+    let max_doc: DocId = StoreReader::read_max_doc(&store_source)
+        .map_err(|err| format!("Corrupted store: {}", err))?;
+    if max_doc == 0 {
+        return Err("The segment does not contain any document".to_string());
+    }
+    if segment_meta.max_doc() != 0 && segment_meta.max_doc() != max_doc {
+        return Err(format!(
+            "The store holds {} documents instead of {}",
+            max_doc,
+            segment_meta.max_doc()
+        ));
+    }
+    segment_meta.set_max_doc(max_doc);
+
+    for &component in &[
+        SegmentComponent::POSTINGS,
+        SegmentComponent::TERMS,
+        SegmentComponent::FASTFIELDS,
+        SegmentComponent::FIELDNORMS,
+    ] {
+        check_composite_file(directory, &segment_meta, component)?;
+    }
+    // positions, bloom filters, field presence and facet labels are optional.
+    for &component in &[
+        SegmentComponent::POSITIONS,
+        SegmentComponent::BLOOMFILTERS,
+        SegmentComponent::FIELDPRESENCE,
+        SegmentComponent::FACETLABELS,
+    ] {
+        if directory.exists(&segment_meta.relative_path(component)) {
+            check_composite_file(directory, &segment_meta, component)?;
+        }
+    }
+
+    // Delete files that were not garbage collected yet are superseded
+    // by the one with the highest opstamp.
+    if let Some(&delete_opstamp) = delete_opstamps.iter().max() {
+        segment_meta.set_delete_meta(0, delete_opstamp);
+        let delete_source = open_component(directory, &segment_meta, SegmentComponent::DELETE)?;
+        if delete_source.len() * 8 < max_doc as usize {
+            return Err(format!(
+                "Delete file with opstamp {} is too short",
+                delete_opstamp
+            ));
+        }
+        let num_deleted_docs = DeleteBitSet::open(delete_source).len() as u32;
+        if num_deleted_docs > max_doc {
+            return Err(format!(
+                "Delete file with opstamp {} is corrupted",
+                delete_opstamp
+            ));
+        }
+        segment_meta.set_delete_meta(num_deleted_docs, delete_opstamp);
+    }
+    Ok((segment_meta, merged_segment_ids))
+}
+
+/// Returns the segments whose documents were merged into one of
+/// the complete segments, associated to the id of that segment.
+///
+/// A merge leaves the segments it was merged from on disk until they
+/// get garbage collected, and these may themselves result from a merge.
+fn superseded_segments(
+    directory: &ManagedDirectory,
+    complete_segments: &[(SegmentMeta, Vec<SegmentId>)],
+) -> BTreeMap<SegmentId, SegmentId> {
+    let mut superseded_segments = BTreeMap::new();
+    let mut stack: Vec<(SegmentId, SegmentId)> = vec![];
+    for &(ref segment_meta, ref merged_segment_ids) in complete_segments {
+        for &merged_segment_id in merged_segment_ids {
+            stack.push((merged_segment_id, segment_meta.id()));
+        }
+    }
+    while let Some((segment_id, merged_segment_id)) = stack.pop() {
+        if superseded_segments.contains_key(&segment_id) {
+            continue;
+        }
+        superseded_segments.insert(segment_id, merged_segment_id);
+        // the files of a superseded segment may be partially
+        // garbage collected: its META file is only used if it is readable.
+        if let Ok(Some(meta_file)) = read_meta_file(directory, segment_id) {
+            for &source_segment_id in meta_file.merged_segment_ids() {
+                stack.push((source_segment_id, merged_segment_id));
+            }
+        }
+    }
+    superseded_segments
+}
+
+pub(crate) fn recover_meta(
+    mut directory: ManagedDirectory,
+    schema: Schema,
+) -> Result<RecoveryReport> {
+    let _directory_lock = DirectoryLock::lock(directory.box_clone())?;
+
+    let mut delete_opstamps_per_segment: BTreeMap<SegmentId, Vec<Opstamp>> = BTreeMap::new();
+    for path in directory.list_files()? {
+        if let Some((segment_id, delete_opstamp_opt)) = parse_segment_file(&path) {
+            let delete_opstamps = delete_opstamps_per_segment
+                .entry(segment_id)
+                .or_insert_with(Vec::new);
+            if let Some(delete_opstamp) = delete_opstamp_opt {
+                delete_opstamps.push(delete_opstamp);
+            }
+        }
+    }
+
+    let mut complete_segments = vec![];
+    let mut skipped_segments = vec![];
+    for (segment_id, delete_opstamps) in delete_opstamps_per_segment {
+        match recover_segment_meta(&directory, segment_id, &delete_opstamps) {
+            Ok(complete_segment) => {
+                complete_segments.push(complete_segment);
+            }
+            Err(reason) => {
+                warn!("Skipping segment {:?}: {}", segment_id, reason);
+                skipped_segments.push((segment_id, reason));
+            }
+        }
+    }
+
+    let superseded_segments = superseded_segments(&directory, &complete_segments);
+    let mut recovered_segments = vec![];
+    for (segment_meta, _) in complete_segments {
+        match superseded_segments.get(&segment_meta.id()) {
+            Some(merged_segment_id) => {
+                let reason = format!(
+                    "Superseded by the merged segment {}",
+                    merged_segment_id.uuid_string()
+                );
+                info!("Skipping segment {:?}: {}", segment_meta.id(), reason);
+                skipped_segments.push((segment_meta.id(), reason));
+            }
+            None => {
+                recovered_segments.push(segment_meta);
+            }
+        }
+    }
+
+    let opstamp = recovered_segments
+        .iter()
+        .flat_map(|segment_meta| segment_meta.delete_opstamp())
+        .max()
+        .unwrap_or_default();
+    save_metas(
+        recovered_segments.clone(),
+        schema,
+        IndexSettings::default(),
+        opstamp,
+        None,
+        None,
+        &mut directory,
+    )?;
+    Ok(RecoveryReport {
+        recovered_segments,
+        skipped_segments,
+        opstamp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use core::{SegmentComponent, SegmentMeta};
+    use directory::MmapDirectory;
+    use futures::Future;
+    use indexer::NoMergePolicy;
+    use query::{AllQuery, Query, TermQuery};
+    use schema::{Field, IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, STORED, TEXT};
+    use std::fs;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    fn assert_same_segment_meta(
+        recovered: &SegmentMeta,
+        original: &SegmentMeta,
+        text_field: Field,
+        num_field: Field,
+    ) {
+        assert_eq!(recovered.id(), original.id());
+        assert_eq!(recovered.max_doc(), original.max_doc());
+        assert_eq!(recovered.num_deleted_docs(), original.num_deleted_docs());
+        assert_eq!(recovered.delete_opstamp(), original.delete_opstamp());
+        assert!(original.total_num_tokens(text_field).is_some());
+        assert_eq!(
+            recovered.total_num_tokens(text_field),
+            original.total_num_tokens(text_field)
+        );
+        assert!(original.index_record_option(text_field).is_some());
+        assert_eq!(
+            recovered.index_record_option(text_field),
+            original.index_record_option(text_field)
+        );
+        assert!(original.int_value_range(num_field).is_some());
+        assert_eq!(
+            recovered.int_value_range(num_field),
+            original.int_value_range(num_field)
+        );
+        assert_eq!(recovered.format_version(), original.format_version());
+    }
+
+    #[test]
+    fn test_recover_meta() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", INT_INDEXED);
+        let schema = schema_builder.build();
+        let temp_dir = TempDir::new("tantivy_recovery_test").unwrap();
+        let index_path = temp_dir.path().to_path_buf();
+        let segment_metas = {
+            let index = Index::create(&index_path, schema.clone()).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for text in &["a", "b", "c"] {
+                for i in 0..10u64 {
+                    index_writer
+                        .add_document(doc!(text_field => *text, num_field => i))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+            index_writer.delete_term(Term::from_field_text(text_field, "b"));
+            index_writer.commit().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+            let mut segment_metas = index.searchable_segment_metas().unwrap();
+            segment_metas.sort_by_key(|segment_meta| segment_meta.id());
+            segment_metas
+        };
+        assert_eq!(segment_metas.len(), 3);
+        let segment_ids: Vec<_> = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.id())
+            .collect();
+
+        fs::remove_file(index_path.join("meta.json")).unwrap();
+        assert!(Index::open(&index_path).is_err());
+
+        {
+            let directory = MmapDirectory::open(&index_path).unwrap();
+            let report = Index::recover_meta(directory, schema.clone()).unwrap();
+            let mut recovered_segment_ids: Vec<_> = report
+                .recovered_segments()
+                .iter()
+                .map(|segment_meta| segment_meta.id())
+                .collect();
+            recovered_segment_ids.sort();
+            assert_eq!(recovered_segment_ids, segment_ids);
+            assert!(report.skipped_segments().is_empty());
+            let max_delete_opstamp = report
+                .recovered_segments()
+                .iter()
+                .flat_map(|segment_meta| segment_meta.delete_opstamp())
+                .max();
+            assert_eq!(Some(report.opstamp()), max_delete_opstamp);
+            let num_deleted_docs: u32 = report
+                .recovered_segments()
+                .iter()
+                .map(|segment_meta| segment_meta.num_deleted_docs())
+                .sum();
+            assert_eq!(num_deleted_docs, 10);
+            for recovered_segment_meta in report.recovered_segments() {
+                let segment_meta = segment_metas
+                    .iter()
+                    .find(|segment_meta| segment_meta.id() == recovered_segment_meta.id())
+                    .unwrap();
+                assert_same_segment_meta(
+                    recovered_segment_meta,
+                    segment_meta,
+                    text_field,
+                    num_field,
+                );
+            }
+        }
+        {
+            let index = Index::open(&index_path).unwrap();
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_docs(), 20);
+            assert_eq!(AllQuery.count(&*searcher).unwrap(), 20);
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, "c"),
+                IndexRecordOption::Basic,
+            );
+            assert_eq!(term_query.count(&*searcher).unwrap(), 10);
+            // the recovered index can be written again.
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "d")).unwrap();
+            index_writer.commit().unwrap();
+        }
+
+        // a segment with a corrupted file is skipped.
+        let corrupted_segment_id = segment_ids[0];
+        {
+            let store_filename = format!("{}.store", corrupted_segment_id.uuid_string());
+            let store_path = index_path.join(store_filename);
+            let mut store_file = fs::File::create(store_path).unwrap();
+            store_file.write_all(b"corrupted").unwrap();
+        }
+        fs::remove_file(index_path.join("meta.json")).unwrap();
+        let directory = MmapDirectory::open(&index_path).unwrap();
+        let report = Index::recover_meta(directory, schema).unwrap();
+        assert_eq!(report.recovered_segments().len(), 3);
+        assert_eq!(report.skipped_segments().len(), 1);
+        assert_eq!(report.skipped_segments()[0].0, corrupted_segment_id);
+        let index = Index::open(&index_path).unwrap();
+        assert_eq!(index.searcher().num_docs(), 11);
+    }
+
+    #[test]
+    fn test_recover_meta_after_merge() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", INT_INDEXED);
+        let schema = schema_builder.build();
+        let temp_dir = TempDir::new("tantivy_recovery_test").unwrap();
+        let index_path = temp_dir.path().to_path_buf();
+        let backup_dir = TempDir::new("tantivy_recovery_backup").unwrap();
+        let (source_segment_ids, merged_segment_meta) = {
+            let index = Index::create(&index_path, schema.clone()).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for text in &["a", "b"] {
+                for i in 0..10u64 {
+                    index_writer
+                        .add_document(doc!(text_field => *text, num_field => i))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+            index_writer.delete_term(Term::from_field_u64(num_field, 3));
+            index_writer.commit().unwrap();
+            let mut source_segment_ids = index.searchable_segment_ids().unwrap();
+            source_segment_ids.sort();
+            assert_eq!(source_segment_ids.len(), 2);
+            // the files of the source segments are garbage collected
+            // once the merge is committed. They are restored afterwards,
+            // as if the process had crashed before they were deleted.
+            for entry in fs::read_dir(&index_path).unwrap() {
+                let path = entry.unwrap().path();
+                let filename = path.file_name().unwrap().to_str().unwrap().to_string();
+                let is_source_segment_file = source_segment_ids
+                    .iter()
+                    .any(|segment_id| filename.starts_with(&segment_id.uuid_string()));
+                if is_source_segment_file {
+                    fs::copy(&path, backup_dir.path().join(&filename)).unwrap();
+                }
+            }
+            let merged_segment_meta = index_writer.merge(&source_segment_ids).wait().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+            (source_segment_ids, merged_segment_meta)
+        };
+        for &source_segment_id in &source_segment_ids {
+            let meta_filename = format!("{}.meta", source_segment_id.uuid_string());
+            assert!(!index_path.join(&meta_filename).exists());
+        }
+        for entry in fs::read_dir(backup_dir.path()).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, index_path.join(path.file_name().unwrap())).unwrap();
+        }
+
+        fs::remove_file(index_path.join("meta.json")).unwrap();
+        let directory = MmapDirectory::open(&index_path).unwrap();
+        let report = Index::recover_meta(directory, schema).unwrap();
+        assert_eq!(report.recovered_segments().len(), 1);
+        assert_same_segment_meta(
+            &report.recovered_segments()[0],
+            &merged_segment_meta,
+            text_field,
+            num_field,
+        );
+        let mut skipped_segment_ids: Vec<_> = report
+            .skipped_segments()
+            .iter()
+            .map(|&(segment_id, ref reason)| {
+                assert!(reason.starts_with("Superseded"), "{}", reason);
+                segment_id
+            })
+            .collect();
+        skipped_segment_ids.sort();
+        assert_eq!(skipped_segment_ids, source_segment_ids);
+
+        let index = Index::open(&index_path).unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 18);
+        let merged_segment = index.segment(report.recovered_segments()[0].clone());
+        assert!(merged_segment.open_read(SegmentComponent::META).is_ok());
+    }
+}
@@ -8,6 +8,11 @@ use fastfield::DeleteBitSet;
 use compression::CompressedIntStream;
 use postings::FreqReadingOption;
 use schema::FieldType;
+use datastruct::BloomFilter;
+use query::QueryContext;
+use query::query_context::Pooled;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// The inverted index reader is in charge of accessing
 /// the inverted index associated to a specific field.
@@ -28,6 +33,10 @@ pub struct InvertedIndexReader {
     positions_source: ReadOnlySource,
     delete_bitset: DeleteBitSet,
     record_option: IndexRecordOption,
+    bloom_filter_opt: Option<BloomFilter>,
+    #[cfg(test)]
+    num_termdict_lookups: AtomicUsize,
+    total_num_tokens: u64,
 }
 
 impl InvertedIndexReader {
@@ -37,6 +46,8 @@ impl InvertedIndexReader {
         positions_source: ReadOnlySource,
         delete_bitset: DeleteBitSet,
         record_option: IndexRecordOption,
+        bloom_filter_opt: Option<BloomFilter>,
+        total_num_tokens: u64,
     ) -> InvertedIndexReader {
         InvertedIndexReader {
             termdict,
@@ -44,6 +55,10 @@ impl InvertedIndexReader {
             positions_source,
             delete_bitset,
             record_option,
+            bloom_filter_opt,
+            #[cfg(test)]
+            num_termdict_lookups: AtomicUsize::new(0),
+            total_num_tokens,
         }
     }
 
@@ -59,12 +74,55 @@ impl InvertedIndexReader {
             ReadOnlySource::empty(),
             DeleteBitSet::empty(),
             record_option,
+            None,
+            0u64,
         )
     }
 
     /// Returns the term info associated with the term.
+    ///
+    /// If the field has a bloom filter, it is checked first, and the
+    /// term dictionary is only looked up if the term may be in the segment.
     pub fn get_term_info(&self, term: &Term) -> Option<TermInfo> {
-        self.termdict.get(term.value_bytes())
+        let term_bytes = term.value_bytes();
+        if let Some(ref bloom_filter) = self.bloom_filter_opt {
+            if !bloom_filter.may_contain(term_bytes) {
+                return None;
+            }
+        }
+        #[cfg(test)]
+        self.num_termdict_lookups.fetch_add(1, Ordering::Relaxed);
+        self.termdict.get(term_bytes)
+    }
+
+    /// Returns true iff the field has a bloom filter in this segment.
+    pub fn has_bloom_filter(&self) -> bool {
+        self.bloom_filter_opt.is_some()
+    }
+
+    /// Returns the total number of tokens of the field, over all
+    /// of the documents of the segment.
+    ///
+    /// Deleted documents are counted until they are
+    /// expunged by a merge.
+    pub fn total_num_tokens(&self) -> u64 {
+        self.total_num_tokens
+    }
+
+    /// Returns the `IndexRecordOption` the postings of the field
+    /// were written with in this segment.
+    pub fn record_option(&self) -> IndexRecordOption {
+        self.record_option
+    }
+
+    /// Returns the number of times the term dictionary was looked up
+    /// by `get_term_info` since this reader was opened.
+    ///
+    /// Lookups skipped thanks to the bloom filter are not counted.
+    /// The lookups are only counted in tests.
+    #[cfg(test)]
+    pub fn num_termdict_lookups(&self) -> usize {
+        self.num_termdict_lookups.load(Ordering::Relaxed)
     }
 
     /// Return the term dictionary datastructure.
@@ -103,20 +161,34 @@ impl InvertedIndexReader {
         term_info: &TermInfo,
         requested_option: IndexRecordOption,
     ) -> BlockSegmentPostings {
-        let offset = term_info.postings_offset as usize;
-        let postings_data = self.postings_source.slice_from(offset);
-        let freq_reading_option = match (self.record_option, requested_option) {
-            (IndexRecordOption::Basic, _) => FreqReadingOption::NoFreq,
-            (_, IndexRecordOption::Basic) => FreqReadingOption::SkipFreq,
-            (_, _) => FreqReadingOption::ReadFreq,
-        };
         BlockSegmentPostings::from_data(
             term_info.doc_freq as usize,
-            SourceRead::from(postings_data),
-            freq_reading_option,
+            self.postings_data(term_info),
+            self.freq_reading_option(requested_option),
         )
     }
 
+    fn postings_data(&self, term_info: &TermInfo) -> SourceRead {
+        let offset = term_info.postings_offset as usize;
+        SourceRead::from(self.postings_source.slice_from(offset))
+    }
+
+    fn freq_reading_option(&self, requested_option: IndexRecordOption) -> FreqReadingOption {
+        // the term frequencies are also needed to find the positions.
+        let with_positions = self.record_option.has_positions() && requested_option.has_positions();
+        if !self.record_option.has_freq() {
+            if with_positions {
+                FreqReadingOption::ReadPositionCounts
+            } else {
+                FreqReadingOption::NoFreq
+            }
+        } else if requested_option.has_freq() || with_positions {
+            FreqReadingOption::ReadFreq
+        } else {
+            FreqReadingOption::SkipFreq
+        }
+    }
+
     /// Returns a posting object given a `term_info`.
     /// This method is for an advanced usage only.
     ///
@@ -127,9 +199,50 @@ impl InvertedIndexReader {
         option: IndexRecordOption,
     ) -> SegmentPostings {
         let block_postings = self.read_block_postings_from_terminfo(term_info, option);
+        self.segment_postings(term_info, option, Pooled::unpooled(box block_postings))
+    }
+
+    /// Returns a posting object given a `term_info`, whose
+    /// `BlockSegmentPostings` is taken from the `QueryContext`.
+    ///
+    /// It is given back to the context when the posting object is dropped.
+    pub(crate) fn read_postings_from_terminfo_with_context(
+        &self,
+        term_info: &TermInfo,
+        option: IndexRecordOption,
+        context: &QueryContext,
+    ) -> SegmentPostings {
+        let doc_freq = term_info.doc_freq as usize;
+        let freq_reading_option = self.freq_reading_option(option);
+        let block_postings = context.take_buffer(
+            || {
+                box BlockSegmentPostings::from_data(
+                    doc_freq,
+                    self.postings_data(term_info),
+                    freq_reading_option,
+                )
+            },
+            |block_postings| {
+                block_postings.reset_with_option(
+                    doc_freq,
+                    self.postings_data(term_info),
+                    freq_reading_option,
+                )
+            },
+        );
+        self.segment_postings(term_info, option, block_postings)
+    }
+
+    fn segment_postings(
+        &self,
+        term_info: &TermInfo,
+        option: IndexRecordOption,
+        block_postings: Pooled<BlockSegmentPostings>,
+    ) -> SegmentPostings {
         let delete_bitset = self.delete_bitset.clone();
         let position_stream = {
-            if option.has_positions() {
+            // positions cannot be read if they were not recorded.
+            if option.has_positions() && self.record_option.has_positions() {
                 let position_offset = term_info.positions_offset;
                 let positions_source = self.positions_source.slice_from(position_offset as usize);
                 let mut stream = CompressedIntStream::wrap(positions_source);
@@ -139,7 +252,7 @@ impl InvertedIndexReader {
                 None
             }
         };
-        SegmentPostings::from_block_postings(block_postings, delete_bitset, position_stream)
+        SegmentPostings::from_pooled_block_postings(block_postings, delete_bitset, position_stream)
     }
 
     /// Returns the segment postings associated with the term, and with the given option,
@@ -152,11 +265,33 @@ impl InvertedIndexReader {
     /// For instance, requesting `IndexRecordOption::Freq` for a
     /// `TextIndexingOptions` that does not index position will return a `SegmentPostings`
     /// with `DocId`s and frequencies.
+    ///
+    /// More precisely, depending on the option the segment was written with
+    /// (see [`.record_option()`](#method.record_option)):
+    ///
+    /// * missing term frequencies are all equal to 1,
+    /// * missing positions are empty.
+    ///
+    /// If the positions are read, the term frequencies are always their number,
+    /// even if they were not requested, or if the segment was written
+    /// with `IndexRecordOption::WithPositions`.
     pub fn read_postings(&self, term: &Term, option: IndexRecordOption) -> Option<SegmentPostings> {
         let term_info = get!(self.get_term_info(term));
         Some(self.read_postings_from_terminfo(&term_info, option))
     }
 
+    /// Same as [`.read_postings(...)`](#method.read_postings), except that
+    /// the buffers of the posting object are taken from the `QueryContext`.
+    pub(crate) fn read_postings_with_context(
+        &self,
+        term: &Term,
+        option: IndexRecordOption,
+        context: &QueryContext,
+    ) -> Option<SegmentPostings> {
+        let term_info = get!(self.get_term_info(term));
+        Some(self.read_postings_from_terminfo_with_context(&term_info, option, context))
+    }
+
     /// Returns the number of documents containing the term.
     pub fn doc_freq(&self, term: &Term) -> u32 {
         self.get_term_info(term)
@@ -164,3 +299,143 @@ impl InvertedIndexReader {
             .unwrap_or(0u32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, Term, INT_INDEXED, STORED, STRING};
+    use indexer::NoMergePolicy;
+    use query::{Query, TermQuery};
+    use schema::IndexRecordOption;
+    use schema::{TextFieldIndexing, TextOptions};
+    use collector::CountCollector;
+    use docset::DocSet;
+    use postings::Postings;
+
+    const NUM_SEGMENTS: u64 = 20;
+    const NUM_DOCS_PER_SEGMENT: u64 = 5;
+
+    // Returns, for each lookup, the number of hits, as well as
+    // the total number of term dictionary lookups.
+    fn lookup_ids(bloom_filter: bool) -> (Vec<usize>, usize) {
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", STRING | STORED);
+        let num_field = schema_builder.add_u64_field("num", INT_INDEXED);
+        if bloom_filter {
+            schema_builder.set_bloom_filter(id_field, 10);
+        }
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment in 0..NUM_SEGMENTS {
+                for i in 0..NUM_DOCS_PER_SEGMENT {
+                    let id = segment * NUM_DOCS_PER_SEGMENT + i;
+                    index_writer.add_document(doc!(
+                        id_field => format!("id{}", id),
+                        num_field => id % 3
+                    )).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), NUM_SEGMENTS as usize);
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(id_field);
+            assert_eq!(inverted_index.has_bloom_filter(), bloom_filter);
+            assert!(!segment_reader.inverted_index(num_field).has_bloom_filter());
+        }
+        let num_hits: Vec<usize> = (0..NUM_SEGMENTS * NUM_DOCS_PER_SEGMENT + 50)
+            .map(|id| {
+                let term = Term::from_field_text(id_field, &format!("id{}", id));
+                let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+                let mut count_collector = CountCollector::default();
+                term_query
+                    .search(&*searcher, &mut count_collector)
+                    .unwrap();
+                count_collector.count()
+            })
+            .collect();
+        let num_termdict_lookups = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(id_field).num_termdict_lookups())
+            .sum();
+        (num_hits, num_termdict_lookups)
+    }
+
+    #[test]
+    fn test_bloom_filter_lookups() {
+        let (num_hits_without_bloom, num_lookups_without_bloom) = lookup_ids(false);
+        let (num_hits_with_bloom, num_lookups_with_bloom) = lookup_ids(true);
+        assert_eq!(num_hits_without_bloom, num_hits_with_bloom);
+        let num_existing_ids = (NUM_SEGMENTS * NUM_DOCS_PER_SEGMENT) as usize;
+        assert_eq!(
+            num_hits_with_bloom.iter().cloned().sum::<usize>(),
+            num_existing_ids
+        );
+        // without bloom filter, each query looks up the term dictionary
+        // of every segment the same number of times.
+        let num_queries = num_hits_with_bloom.len();
+        assert_eq!(
+            num_lookups_without_bloom % (num_queries * NUM_SEGMENTS as usize),
+            0
+        );
+        let lookups_per_segment = num_lookups_without_bloom / (num_queries * NUM_SEGMENTS as usize);
+        assert!(lookups_per_segment > 0);
+        // with the bloom filter, each existing id is only looked up in its segment,
+        // on top of a small number of false positives.
+        assert!(num_lookups_with_bloom >= lookups_per_segment * num_existing_ids);
+        assert!(num_lookups_with_bloom < lookups_per_segment * (num_existing_ids + 50));
+    }
+
+    #[test]
+    fn test_read_postings_record_options() {
+        let record_options = [
+            IndexRecordOption::Basic,
+            IndexRecordOption::WithFreqs,
+            IndexRecordOption::WithPositions,
+            IndexRecordOption::WithFreqsAndPositions,
+        ];
+        for &stored_option in &record_options {
+            let mut schema_builder = SchemaBuilder::default();
+            let text_field = schema_builder.add_text_field(
+                "text",
+                TextOptions::default().set_indexing_options(
+                    TextFieldIndexing::default().set_index_option(stored_option),
+                ),
+            );
+            let index = Index::create_in_ram(schema_builder.build());
+            {
+                let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+                index_writer.add_document(doc!(text_field => "a b a")).unwrap();
+                index_writer.commit().unwrap();
+            }
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let inverted_index = searcher.segment_reader(0).inverted_index(text_field);
+            assert_eq!(inverted_index.record_option(), stored_option);
+            let term = Term::from_field_text(text_field, "a");
+            for &requested_option in &record_options {
+                let mut postings = inverted_index.read_postings(&term, requested_option).unwrap();
+                assert!(postings.advance());
+                // the postings contain the information that was both
+                // recorded and requested. The term frequency is
+                // the number of positions if they are read.
+                let (expected_term_freq, expected_positions): (u32, &[u32]) =
+                    match stored_option.intersection(requested_option) {
+                        IndexRecordOption::Basic => (1, &[]),
+                        IndexRecordOption::WithFreqs => (2, &[]),
+                        IndexRecordOption::WithPositions
+                        | IndexRecordOption::WithFreqsAndPositions => (2, &[0, 2]),
+                    };
+                assert_eq!(postings.term_freq(), expected_term_freq);
+                assert_eq!(postings.positions(), expected_positions);
+                assert!(!postings.advance());
+            }
+        }
+    }
+}
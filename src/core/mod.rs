@@ -8,17 +8,30 @@ mod index_meta;
 mod pool;
 mod segment_meta;
 mod inverted_index_reader;
+mod search_profile;
+mod suggest;
+mod scoped_searcher;
+mod space_usage;
+mod index_recovery;
+mod index_builder;
 
 pub use self::inverted_index_reader::InvertedIndexReader;
 pub use self::searcher::Searcher;
+pub use self::scoped_searcher::ScopedSearcher;
+pub use self::search_profile::{SearchProfile, SegmentSearchProfile};
+pub use self::suggest::Suggestion;
+pub use self::space_usage::{ComponentSpaceUsage, FieldSpaceUsage, Residency, SegmentSpaceUsage};
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
 pub use self::segment::Segment;
 pub use self::segment::SerializableSegment;
 pub use self::index::Index;
+pub use self::index_recovery::RecoveryReport;
 pub use self::segment_meta::SegmentMeta;
-pub use self::index_meta::IndexMeta;
+pub(crate) use self::segment_meta::SegmentMetaFile;
+pub use self::index_meta::{IndexMeta, IndexSettings};
+pub use self::index_builder::IndexBuilder;
 
 use std::path::PathBuf;
 
@@ -0,0 +1,283 @@
+use Result;
+use DocId;
+use DocAddress;
+use Score;
+use SegmentLocalId;
+use collector::Collector;
+use common::BitSet;
+use core::Searcher;
+use docset::DocSet;
+use query::{Query, QueryContext, Scorer};
+use schema::Document;
+use std::sync::Arc;
+
+/// Scorer only emitting the documents of the
+/// underlying scorer that pass the filter.
+struct FilteredScorer {
+    scorer: Box<Scorer>,
+    filter: Arc<BitSet>,
+}
+
+impl DocSet for FilteredScorer {
+    fn advance(&mut self) -> bool {
+        while self.scorer.advance() {
+            if self.filter.contains(self.scorer.doc()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+}
+
+impl Scorer for FilteredScorer {
+    fn score(&mut self) -> Score {
+        self.scorer.score()
+    }
+
+    fn name(&self) -> &'static str {
+        "FilteredScorer"
+    }
+}
+
+/// Wraps a `Searcher` so that all of its searches are restricted
+/// to the documents matching a filter query, typically the documents
+/// of one tenant of a multi-tenant index.
+///
+/// The filter is evaluated once, when the `ScopedSearcher` is created.
+/// The set of documents it matches in each segment is then reused
+/// by all of the searches. As a `Searcher` never changes, a `ScopedSearcher`
+/// can be kept and reused for as long as its `Searcher`, that is
+/// for a whole generation of segments.
+///
+/// The filter does not contribute to the scores.
+///
+/// # Warning
+///
+/// Only the searches are scoped. The statistics, like
+/// [`Searcher::num_docs`](./struct.Searcher.html#method.num_docs),
+/// [`Searcher::doc_freq`](./struct.Searcher.html#method.doc_freq),
+/// or [`Query::count`](./query/trait.Query.html#method.count)
+/// called with the underlying `Searcher`, account for all of the documents
+/// of the index. In particular, the inverse document frequencies used
+/// in scoring are computed over all of the documents.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::{Index, ScopedSearcher};
+/// use tantivy::collector::CountCollector;
+/// use tantivy::query::{AllQuery, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let tenant_id = schema_builder.add_u64_field("tenant_id", INT_INDEXED);
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(tenant_id => 1u64, title => "first tenant"))?;
+///     index_writer.add_document(doc!(tenant_id => 2u64, title => "second tenant"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let tenant_filter = TermQuery::new(
+///     Term::from_field_u64(tenant_id, 1u64),
+///     IndexRecordOption::Basic,
+/// );
+/// let scoped_searcher = ScopedSearcher::new(&*searcher, &tenant_filter)?;
+/// let mut count_collector = CountCollector::default();
+/// scoped_searcher.search(&AllQuery, &mut count_collector)?;
+/// assert_eq!(count_collector.count(), 1);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+pub struct ScopedSearcher<'a> {
+    searcher: &'a Searcher,
+    // documents matching the filter, for each segment.
+    filters: Vec<Arc<BitSet>>,
+}
+
+impl<'a> ScopedSearcher<'a> {
+    /// Creates a new `ScopedSearcher`, evaluating the filter
+    /// on all of the segments of the searcher.
+    pub fn new(searcher: &'a Searcher, filter: &Query) -> Result<ScopedSearcher<'a>> {
+        let weight = filter.weight(searcher, false)?;
+        let mut filters = Vec::with_capacity(searcher.segment_readers().len());
+        for segment_reader in searcher.segment_readers() {
+            let mut filter_bitset = BitSet::with_max_value(segment_reader.max_doc());
+            if weight.can_match(segment_reader.segment_meta()) {
+                let mut scorer = weight.scorer(segment_reader)?;
+                while scorer.advance() {
+                    filter_bitset.insert(scorer.doc());
+                }
+            }
+            filters.push(Arc::new(filter_bitset));
+        }
+        Ok(ScopedSearcher { searcher, filters })
+    }
+
+    /// Returns the underlying, unscoped, `Searcher`.
+    pub fn searcher(&self) -> &'a Searcher {
+        self.searcher
+    }
+
+    /// Returns the number of non-deleted documents matching the filter.
+    pub fn num_docs(&self) -> u64 {
+        self.filters
+            .iter()
+            .map(|filter_bitset| filter_bitset.len() as u64)
+            .sum()
+    }
+
+    /// Fetches a document from the store.
+    ///
+    /// Documents are not checked against the filter, so that
+    /// this is equivalent to [`Searcher::doc`](./struct.Searcher.html#method.doc).
+    pub fn doc(&self, doc_address: &DocAddress) -> Result<Document> {
+        self.searcher.doc(doc_address)
+    }
+
+    /// Runs a query on the documents matching the filter.
+    ///
+    /// See [`Query::search`](./query/trait.Query.html#method.search).
+    pub fn search(&self, query: &Query, collector: &mut Collector) -> Result<()> {
+        let scoring_enabled = collector.requires_scoring();
+        let weight = query.weight(self.searcher, scoring_enabled)?;
+        collector.prepare(self.searcher)?;
+        let mut context = QueryContext::new();
+        for (segment_ord, segment_reader) in self.searcher.segment_readers().iter().enumerate() {
+            collector.set_segment(segment_ord as SegmentLocalId, segment_reader)?;
+            let filter = &self.filters[segment_ord];
+            if filter.len() == 0 || !weight.can_match(segment_reader.segment_meta()) {
+                continue;
+            }
+            let scorer = weight.scorer_with_context(segment_reader, &mut context)?;
+            let mut filtered_scorer = FilteredScorer {
+                scorer,
+                filter: Arc::clone(filter),
+            };
+            if scoring_enabled {
+                filtered_scorer.collect(collector);
+            } else {
+                filtered_scorer.collect_doc_ids(collector);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::ScopedSearcher;
+    use Index;
+    use collector::{CountCollector, FacetCollector, TopCollector};
+    use indexer::NoMergePolicy;
+    use query::{AllQuery, Query, QueryParser, TermQuery};
+    use schema::{Facet, IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, STORED, TEXT};
+
+    #[test]
+    fn test_scoped_searcher() {
+        let mut schema_builder = SchemaBuilder::default();
+        let tenant_id = schema_builder.add_u64_field("tenant_id", INT_INDEXED | STORED);
+        let title = schema_builder.add_text_field("title", TEXT);
+        let category = schema_builder.add_facet_field("category");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for &(tenant, title_text, category_path) in &[
+                (1u64, "red apple", "/fruits/apple"),
+                (2u64, "green apple", "/fruits/apple"),
+                (2u64, "yellow banana", "/fruits/banana"),
+            ] {
+                let mut doc = doc!(tenant_id => tenant, title => title_text);
+                doc.add_facet(category, category_path);
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+            let mut doc = doc!(tenant_id => 2u64, title => "red cherry");
+            doc.add_facet(category, "/fruits/cherry");
+            index_writer.add_document(doc).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let tenant_filter = |tenant: u64| {
+            TermQuery::new(
+                Term::from_field_u64(tenant_id, tenant),
+                IndexRecordOption::Basic,
+            )
+        };
+        let first_tenant_searcher = ScopedSearcher::new(&*searcher, &tenant_filter(1)).unwrap();
+        let second_tenant_searcher = ScopedSearcher::new(&*searcher, &tenant_filter(2)).unwrap();
+        assert_eq!(first_tenant_searcher.num_docs(), 1);
+        assert_eq!(second_tenant_searcher.num_docs(), 3);
+
+        let count = |scoped_searcher: &ScopedSearcher, query: &Query| {
+            let mut count_collector = CountCollector::default();
+            scoped_searcher.search(query, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count(&first_tenant_searcher, &AllQuery), 1);
+        assert_eq!(count(&second_tenant_searcher, &AllQuery), 3);
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let red_query = query_parser.parse_query("red").unwrap();
+        assert_eq!(count(&first_tenant_searcher, &*red_query), 1);
+        assert_eq!(count(&second_tenant_searcher, &*red_query), 1);
+        let apple_query = query_parser.parse_query("apple").unwrap();
+        {
+            let mut top_collector = TopCollector::with_limit(10);
+            first_tenant_searcher
+                .search(&*apple_query, &mut top_collector)
+                .unwrap();
+            let doc_addresses = top_collector.docs();
+            assert_eq!(doc_addresses.len(), 1);
+            let doc = first_tenant_searcher.doc(&doc_addresses[0]).unwrap();
+            assert_eq!(doc.get_first(tenant_id).unwrap().u64_value(), 1u64);
+        }
+        // the statistics are not scoped.
+        assert_eq!(apple_query.count(first_tenant_searcher.searcher()).unwrap(), 2);
+
+        let facet_counts = |scoped_searcher: &ScopedSearcher| {
+            let mut facet_collector = FacetCollector::for_field(category);
+            facet_collector.add_facet("/fruits");
+            scoped_searcher
+                .search(&AllQuery, &mut facet_collector)
+                .unwrap();
+            facet_collector
+                .harvest()
+                .get("/fruits")
+                .map(|(facet, count)| (facet.clone(), count))
+                .collect::<Vec<(Facet, u64)>>()
+        };
+        assert_eq!(
+            facet_counts(&first_tenant_searcher),
+            vec![(Facet::from("/fruits/apple"), 1)]
+        );
+        assert_eq!(
+            facet_counts(&second_tenant_searcher),
+            vec![
+                (Facet::from("/fruits/apple"), 1),
+                (Facet::from("/fruits/banana"), 1),
+                (Facet::from("/fruits/cherry"), 1),
+            ]
+        );
+        // a tenant without any document does not match anything.
+        let unknown_tenant_searcher = ScopedSearcher::new(&*searcher, &tenant_filter(3)).unwrap();
+        assert_eq!(count(&unknown_tenant_searcher, &AllQuery), 0);
+    }
+}
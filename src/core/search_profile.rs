@@ -0,0 +1,406 @@
+use Result;
+use DocId;
+use Score;
+use SegmentLocalId;
+use core::SegmentId;
+use core::SegmentReader;
+use core::Searcher;
+use collector::Collector;
+use query::{collect_scorer, search_segments, Query, QueryContext, Scorer, ScorerDiag,
+            SearchObserver, Weight};
+use std::cmp;
+use time::{self, PreciseTime};
+
+fn elapsed_micros(start: PreciseTime, stop: PreciseTime) -> i64 {
+    start.to(stop).num_microseconds().unwrap_or(i64::max_value())
+}
+
+/// Profiling information about the execution of a query
+/// on a single segment.
+///
+/// All durations are expressed in microseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentSearchProfile {
+    segment_ord: SegmentLocalId,
+    segment_id: SegmentId,
+    scorer_micros: i64,
+    collection_micros: i64,
+    advance_micros: i64,
+    collect_micros: i64,
+    num_docs_matched: u32,
+    diag: ScorerDiag,
+}
+
+impl SegmentSearchProfile {
+    /// Returns the ordinal of the segment within the searcher.
+    pub fn segment_ord(&self) -> SegmentLocalId {
+        self.segment_ord
+    }
+
+    /// Returns the id of the segment.
+    pub fn segment_id(&self) -> SegmentId {
+        self.segment_id
+    }
+
+    /// Time spent creating the `Scorer` for this segment.
+    pub fn scorer_micros(&self) -> i64 {
+        self.scorer_micros
+    }
+
+    /// Time spent iterating over the scorer and pushing
+    /// documents to the collector.
+    ///
+    /// This is the sum of [`.advance_micros()`](#method.advance_micros)
+    /// and [`.collect_micros()`](#method.collect_micros).
+    pub fn collection_micros(&self) -> i64 {
+        self.collection_micros
+    }
+
+    /// Time spent advancing and scoring the scorer.
+    pub fn advance_micros(&self) -> i64 {
+        self.advance_micros
+    }
+
+    /// Time spent within the collector, collecting the documents.
+    pub fn collect_micros(&self) -> i64 {
+        self.collect_micros
+    }
+
+    /// Number of documents pushed to the collector for this segment.
+    pub fn num_docs_matched(&self) -> u32 {
+        self.num_docs_matched
+    }
+
+    /// Diagnostics reported by the `Weight` when creating
+    /// the `Scorer` of this segment.
+    pub fn diag(&self) -> &ScorerDiag {
+        &self.diag
+    }
+}
+
+/// Profiling information about the execution of a query,
+/// as returned by `Searcher::search_profiled`.
+///
+/// The search runs the same loop over the segments as a plain search.
+/// Timings are sampled around each segment, except for the time
+/// spent in the collector which is sampled around each of its calls.
+///
+/// All durations are expressed in microseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchProfile {
+    weight_micros: i64,
+    total_micros: i64,
+    segments: Vec<SegmentSearchProfile>,
+    skipped_segments: Vec<SegmentLocalId>,
+}
+
+impl SearchProfile {
+    /// Time spent creating the query's `Weight`.
+    pub fn weight_micros(&self) -> i64 {
+        self.weight_micros
+    }
+
+    /// Overall time spent in the search.
+    pub fn total_micros(&self) -> i64 {
+        self.total_micros
+    }
+
+    /// Returns the profile of each of the segments, in the
+    /// order they were visited.
+    ///
+    /// Segments that were skipped because they could not contain
+    /// any match are not listed, see
+    /// [`.skipped_segments()`](#method.skipped_segments).
+    pub fn segments(&self) -> &[SegmentSearchProfile] {
+        &self.segments
+    }
+
+    /// Returns the ordinals of the segments that were skipped,
+    /// because judging from their `SegmentMeta`, none of their documents
+    /// could match.
+    pub fn skipped_segments(&self) -> &[SegmentLocalId] {
+        &self.skipped_segments
+    }
+
+    /// Returns the overall number of documents pushed to the collector.
+    pub fn num_docs_matched(&self) -> u64 {
+        self.segments
+            .iter()
+            .map(|segment| u64::from(segment.num_docs_matched))
+            .sum()
+    }
+}
+
+/// Wraps the user collector to count the number of documents
+/// collected within a segment, and the time spent collecting them.
+struct TimingCollector<'a> {
+    underlying: &'a mut Collector,
+    count: u32,
+    collect_nanos: u64,
+}
+
+impl<'a> Collector for TimingCollector<'a> {
+    fn set_segment(
+        &mut self,
+        segment_local_id: SegmentLocalId,
+        segment: &SegmentReader,
+    ) -> Result<()> {
+        self.underlying.set_segment(segment_local_id, segment)
+    }
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let collect_start = time::precise_time_ns();
+        self.underlying.collect(doc, score);
+        self.collect_nanos += time::precise_time_ns() - collect_start;
+        self.count += 1;
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.underlying.requires_scoring()
+    }
+}
+
+/// Records the profile of the segments, as they are
+/// visited by the search loop.
+struct ProfilingObserver {
+    segments: Vec<SegmentSearchProfile>,
+    skipped_segments: Vec<SegmentLocalId>,
+}
+
+impl SearchObserver for ProfilingObserver {
+    fn segment_skipped(&mut self, segment_ord: SegmentLocalId, _segment_reader: &SegmentReader) {
+        self.skipped_segments.push(segment_ord);
+    }
+
+    fn scorer(
+        &mut self,
+        weight: &Weight,
+        segment_ord: SegmentLocalId,
+        segment_reader: &SegmentReader,
+        _context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        let scorer_start = PreciseTime::now();
+        let (scorer, diag) = weight.scorer_with_diag(segment_reader)?;
+        self.segments.push(SegmentSearchProfile {
+            segment_ord,
+            segment_id: segment_reader.segment_id(),
+            scorer_micros: elapsed_micros(scorer_start, PreciseTime::now()),
+            collection_micros: 0,
+            advance_micros: 0,
+            collect_micros: 0,
+            num_docs_matched: 0,
+            diag,
+        });
+        Ok(scorer)
+    }
+
+    fn collect(&mut self, scorer: &mut Scorer, collector: &mut Collector, scoring_enabled: bool) {
+        let collection_start = PreciseTime::now();
+        let (num_docs_matched, collect_nanos) = {
+            let mut timing_collector = TimingCollector {
+                underlying: collector,
+                count: 0u32,
+                collect_nanos: 0u64,
+            };
+            collect_scorer(scorer, &mut timing_collector, scoring_enabled);
+            (timing_collector.count, timing_collector.collect_nanos)
+        };
+        let collection_micros = elapsed_micros(collection_start, PreciseTime::now());
+        let collect_micros = cmp::min((collect_nanos / 1_000) as i64, collection_micros);
+        if let Some(segment) = self.segments.last_mut() {
+            segment.num_docs_matched = num_docs_matched;
+            segment.collection_micros = collection_micros;
+            segment.advance_micros = collection_micros - collect_micros;
+            segment.collect_micros = collect_micros;
+        }
+    }
+}
+
+pub(crate) fn search_profiled(
+    searcher: &Searcher,
+    query: &Query,
+    collector: &mut Collector,
+) -> Result<SearchProfile> {
+    let scoring_enabled = collector.requires_scoring();
+    let weight_start = PreciseTime::now();
+    let weight = query.weight(searcher, scoring_enabled)?;
+    let weight_stop = PreciseTime::now();
+    collector.prepare(searcher)?;
+    let mut observer = ProfilingObserver {
+        segments: Vec::with_capacity(searcher.segment_readers().len()),
+        skipped_segments: Vec::new(),
+    };
+    search_segments(
+        searcher,
+        &*weight,
+        collector,
+        scoring_enabled,
+        &mut observer,
+    )?;
+    Ok(SearchProfile {
+        weight_micros: elapsed_micros(weight_start, weight_stop),
+        total_micros: elapsed_micros(weight_start, PreciseTime::now()),
+        segments: observer.segments,
+        skipped_segments: observer.skipped_segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, Term, INT_INDEXED, TEXT};
+    use collector::CountCollector;
+    use query::{QueryParser, RangeQuery, SkipReason, TermQuery};
+    use indexer::NoMergePolicy;
+    use schema::IndexRecordOption;
+    use serde_json;
+
+    #[test]
+    fn test_search_profiled() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b")).unwrap();
+            index_writer.add_document(doc!(text_field=>"a")).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"b")).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"a c")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let mut count_collector = CountCollector::default();
+        let profile = searcher
+            .search_profiled(&query, &mut count_collector)
+            .unwrap();
+        assert_eq!(count_collector.count(), 3);
+        assert_eq!(profile.segments().len(), searcher.segment_readers().len());
+        assert_eq!(profile.num_docs_matched(), 3);
+        for (segment_ord, segment_profile) in profile.segments().iter().enumerate() {
+            let segment_reader = searcher.segment_reader(segment_ord as u32);
+            assert_eq!(segment_profile.segment_ord(), segment_ord as u32);
+            assert_eq!(segment_profile.segment_id(), segment_reader.segment_id());
+            assert!(segment_profile.num_docs_matched() <= segment_reader.max_doc());
+            assert_eq!(
+                segment_profile.advance_micros() + segment_profile.collect_micros(),
+                segment_profile.collection_micros()
+            );
+        }
+        assert!(profile.skipped_segments().is_empty());
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(json["segments"].as_array().unwrap().len(), 3);
+        assert!(json["segments"][0]["num_docs_matched"].is_number());
+    }
+
+    #[test]
+    fn test_search_profiled_skipped_segments() {
+        let mut schema_builder = SchemaBuilder::default();
+        let int_field = schema_builder.add_u64_field("int", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(int_field=>1u64)).unwrap();
+            index_writer.add_document(doc!(int_field=>3u64)).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(int_field=>100u64)).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_ord_out_of_range = (0..searcher.segment_readers().len() as u32)
+            .find(|&segment_ord| searcher.segment_reader(segment_ord).max_doc() == 1)
+            .unwrap();
+        let query = RangeQuery::new_u64(int_field, 0..10);
+        let mut count_collector = CountCollector::default();
+        let profile = searcher
+            .search_profiled(&query, &mut count_collector)
+            .unwrap();
+        assert_eq!(count_collector.count(), 2);
+        assert_eq!(profile.skipped_segments(), &[segment_ord_out_of_range]);
+        assert_eq!(profile.segments().len(), 1);
+        assert_eq!(profile.segments()[0].num_docs_matched(), 2);
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(json["skipped_segments"][0], segment_ord_out_of_range);
+    }
+
+    #[test]
+    fn test_search_profiled_diag() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let other_field = schema_builder.add_text_field("other", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(text_field=>"a b")).unwrap();
+            index_writer.add_document(doc!(text_field=>"a")).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field=>"b")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_ord_with_max_doc = |max_doc: u32| {
+            (0..searcher.segment_readers().len())
+                .find(|&segment_ord| {
+                    searcher.segment_reader(segment_ord as u32).max_doc() == max_doc
+                })
+                .unwrap()
+        };
+        let segment_with_a = segment_ord_with_max_doc(2);
+        let segment_without_a = segment_ord_with_max_doc(1);
+        let term_a = Term::from_field_text(text_field, "a");
+        {
+            let query = TermQuery::new(term_a.clone(), IndexRecordOption::Basic);
+            let mut count_collector = CountCollector::default();
+            let profile = searcher
+                .search_profiled(&query, &mut count_collector)
+                .unwrap();
+            let diag_with_a = profile.segments()[segment_with_a].diag();
+            assert!(diag_with_a.skip_reasons().is_empty());
+            assert_eq!(diag_with_a.estimated_num_candidates(), 2);
+            let diag_without_a = profile.segments()[segment_without_a].diag();
+            assert_eq!(
+                diag_without_a.skip_reasons(),
+                &[
+                    SkipReason::TermNotFound {
+                        field: text_field,
+                        value_bytes: b"a".to_vec(),
+                    },
+                ]
+            );
+            assert_eq!(diag_without_a.estimated_num_candidates(), 0);
+        }
+        {
+            let query_parser = QueryParser::for_index(&index, vec![text_field]);
+            let query = query_parser.parse_query("a other:c").unwrap();
+            let mut count_collector = CountCollector::default();
+            let profile = searcher
+                .search_profiled(&*query, &mut count_collector)
+                .unwrap();
+            assert_eq!(count_collector.count(), 2);
+            let diag_with_a = profile.segments()[segment_with_a].diag();
+            assert_eq!(
+                diag_with_a.skip_reasons(),
+                &[SkipReason::FieldAbsent(other_field)]
+            );
+            assert_eq!(diag_with_a.estimated_num_candidates(), 2);
+            let diag_without_a = profile.segments()[segment_without_a].diag();
+            assert_eq!(diag_without_a.skip_reasons().len(), 2);
+            assert!(
+                diag_without_a
+                    .skip_reasons()
+                    .contains(&SkipReason::term_not_found(&term_a))
+            );
+        }
+    }
+}
@@ -6,11 +6,22 @@ use common::TimerTree;
 use query::Query;
 use DocId;
 use DocAddress;
-use schema::{Field, Term};
+use SegmentLocalId;
+use core::SegmentId;
+use error::ErrorKind;
+use schema::{Field, Schema, SchemaBuilder, Term};
 use termdict::{TermDictionary, TermMerger};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use termdict::TermOrdinalMapping;
 use std::fmt;
 use core::InvertedIndexReader;
+use core::SearchProfile;
+use core::search_profile;
+use core::suggest;
+use core::Suggestion;
+use query::QueryValidationError;
+use std::result;
 
 /// Holds a list of `SegmentReader`s ready for search.
 ///
@@ -18,10 +29,36 @@ use core::InvertedIndexReader;
 /// the destruction of the `Searcher`.
 ///
 pub struct Searcher {
+    schema: Schema,
     segment_readers: Vec<SegmentReader>,
+    // shared by all of the searchers of a same generation.
+    term_ordinal_mappings: Arc<Mutex<HashMap<Field, Arc<TermOrdinalMapping>>>>,
 }
 
 impl Searcher {
+    /// Creates a new `Searcher`
+    ///
+    /// `term_ordinal_mappings` is a cache of the `TermOrdinalMapping`
+    /// objects, which is meant to be shared by all of the searchers
+    /// over the same `segment_readers`.
+    pub(crate) fn new(
+        schema: Schema,
+        segment_readers: Vec<SegmentReader>,
+        term_ordinal_mappings: Arc<Mutex<HashMap<Field, Arc<TermOrdinalMapping>>>>,
+    ) -> Searcher {
+        Searcher {
+            schema,
+            segment_readers,
+            term_ordinal_mappings,
+        }
+    }
+
+    /// Returns the schema of the index the searcher is
+    /// searching into.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     /// Fetches a document from tantivy's store given a `DocAddress`.
     ///
     /// The searcher uses the segment ordinal to route the
@@ -32,12 +69,92 @@ impl Searcher {
         segment_reader.doc(doc_id)
     }
 
+    /// Fetches a batch of documents from tantivy's store,
+    /// typically the documents of a page of results.
+    ///
+    /// The documents are read segment by segment, in the order of their
+    /// `DocId`s, so that each block of the stores is decompressed once.
+    /// They are returned in the order of `doc_addresses`, which may
+    /// contain duplicates.
+    ///
+    /// Each document is fetched independently: an invalid address only
+    /// results in an `ErrorKind::InvalidArgument` error for its own item.
+    pub fn docs(&self, doc_addresses: &[DocAddress]) -> Vec<Result<Document>> {
+        self.map_docs(doc_addresses, |segment_reader, doc_id| segment_reader.doc(doc_id))
+    }
+
+    /// Calls `f` on the segment reader and the `DocId` of each document
+    /// address, in the order of the segments and of the `DocId`s,
+    /// and returns the results in the order of `doc_addresses`.
+    pub(crate) fn map_docs<T, F>(&self, doc_addresses: &[DocAddress], f: F) -> Vec<Result<T>>
+    where
+        F: Fn(&SegmentReader, DocId) -> Result<T>,
+    {
+        let mut ords: Vec<usize> = (0..doc_addresses.len()).collect();
+        ords.sort_by_key(|&ord| doc_addresses[ord]);
+        let mut results: Vec<Option<Result<T>>> = doc_addresses.iter().map(|_| None).collect();
+        for ord in ords {
+            let DocAddress(segment_ord, doc_id) = doc_addresses[ord];
+            let result = match self.segment_readers.get(segment_ord as usize) {
+                Some(segment_reader) if doc_id < segment_reader.max_doc() => {
+                    f(segment_reader, doc_id)
+                }
+                _ => Err(ErrorKind::InvalidArgument(format!(
+                    "invalid document address {:?}",
+                    doc_addresses[ord]
+                )).into()),
+            };
+            results[ord] = Some(result);
+        }
+        results
+            .into_iter()
+            .map(|result_opt| result_opt.expect("all of the documents are fetched"))
+            .collect()
+    }
+
     /// Returns the overall number of documents in the index.
-    pub fn num_docs(&self) -> DocId {
+    /// Deleted documents are not counted.
+    ///
+    /// The count is read from the metas of the segments,
+    /// without running any query.
+    pub fn num_docs(&self) -> u64 {
         self.segment_readers
             .iter()
-            .map(|segment_reader| segment_reader.num_docs())
-            .sum::<u32>()
+            .map(|segment_reader| u64::from(segment_reader.num_docs()))
+            .sum::<u64>()
+    }
+
+    /// Returns the overall number of documents in the index,
+    /// deleted documents included.
+    ///
+    /// This is the sum of the `max_doc` of the segments.
+    pub fn max_doc_total(&self) -> u64 {
+        self.segment_readers
+            .iter()
+            .map(|segment_reader| u64::from(segment_reader.max_doc()))
+            .sum::<u64>()
+    }
+
+    /// Returns the overall number of deleted documents
+    /// that are still held by the segments of the index.
+    pub fn num_deleted_docs(&self) -> u64 {
+        self.segment_readers
+            .iter()
+            .map(|segment_reader| u64::from(segment_reader.num_deleted_docs()))
+            .sum::<u64>()
+    }
+
+    /// Returns the overall number of non-deleted documents
+    /// holding at least one value for the given field.
+    ///
+    /// Segments written before field presence was recorded make this
+    /// method much slower. See `SegmentReader::num_docs_with_field`.
+    pub fn num_docs_with_field(&self, field: Field) -> Result<u64> {
+        let mut num_docs = 0u64;
+        for segment_reader in &self.segment_readers {
+            num_docs += u64::from(segment_reader.num_docs_with_field(field)?);
+        }
+        Ok(num_docs)
     }
 
     /// Return the overall number of documents containing
@@ -59,11 +176,103 @@ impl Searcher {
         &self.segment_readers[segment_ord as usize]
     }
 
+    /// Returns the segment ordinal of the segment with the given `SegmentId`
+    /// in this searcher, or `None` if the searcher does not contain such a segment.
+    ///
+    /// Unlike segment ordinals, `SegmentId`s are stable across reloads.
+    /// A segment will however disappear after it gets merged.
+    pub fn segment_ord(&self, segment_id: &SegmentId) -> Option<SegmentLocalId> {
+        self.segment_readers
+            .iter()
+            .position(|segment_reader| segment_reader.segment_id() == *segment_id)
+            .map(|segment_ord| segment_ord as SegmentLocalId)
+    }
+
+    /// Returns the segment_reader associated with the given `SegmentId`,
+    /// or `None` if the searcher does not contain such a segment.
+    pub fn segment_reader_by_id(&self, segment_id: &SegmentId) -> Option<&SegmentReader> {
+        self.segment_ord(segment_id)
+            .map(|segment_ord| self.segment_reader(segment_ord))
+    }
+
+    /// Fetches a document from tantivy's store given a `SegmentId`
+    /// and a segment local `DocId`.
+    ///
+    /// This is useful when documents are identified across processes,
+    /// as `SegmentId`s, unlike segment ordinals, do not depend on the searcher.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::SegmentNotFound` if the segment is not
+    /// part of this searcher anymore (e.g. it was merged away).
+    pub fn doc_by_segment(&self, segment_id: &SegmentId, doc_id: DocId) -> Result<Document> {
+        let segment_reader = self.segment_reader_by_id(segment_id)
+            .ok_or_else(|| ErrorKind::SegmentNotFound(*segment_id))?;
+        segment_reader.doc(doc_id)
+    }
+
     /// Runs a query on the segment readers wrapped by the searcher
     pub fn search<C: Collector>(&self, query: &Query, collector: &mut C) -> Result<TimerTree> {
         query.search(self, collector)
     }
 
+    /// Checks that a query is consistent with the schema.
+    ///
+    /// See [`Query::validate`](../query/trait.Query.html#method.validate).
+    pub fn check_query(&self, query: &Query) -> result::Result<(), QueryValidationError> {
+        query.validate(self)
+    }
+
+    /// Runs a query on the segment readers wrapped by the searcher,
+    /// and returns a breakdown of where the time was spent.
+    ///
+    /// The collector receives exactly the same calls as with `.search(...)`.
+    /// Timings are only sampled around each segment, so
+    /// the overhead of profiling remains small.
+    pub fn search_profiled<C: Collector>(
+        &self,
+        query: &Query,
+        collector: &mut C,
+    ) -> Result<SearchProfile> {
+        search_profile::search_profiled(self, query, collector)
+    }
+
+    /// Returns the `k` terms of a text field that are the closest to
+    /// a possibly misspelled word, e.g. to suggest a spelling correction.
+    ///
+    /// Only the terms within `max_distance` of the word are returned.
+    /// The distance is the Levenshtein distance, counted in chars.
+    /// The suggestions are ranked by increasing distance, then by
+    /// decreasing document frequency.
+    ///
+    /// The term dictionary of each segment is searched with a Levenshtein
+    /// automaton, so that only a small part of it is visited.
+    /// The terms that are not valid utf-8 are skipped, and no
+    /// suggestion is returned for the fields that are not indexed text fields.
+    pub fn suggest_terms(
+        &self,
+        field: Field,
+        input: &str,
+        max_distance: u8,
+        k: usize,
+    ) -> Vec<Suggestion> {
+        suggest::suggest_terms(self, field, input, max_distance, k)
+    }
+
+    /// Returns the `TermOrdinalMapping` of a facet or string field,
+    /// associating the terms of all of the segments to global ordinals.
+    ///
+    /// The mapping is built on the first call, and is then cached
+    /// and shared by all of the searchers of the same generation.
+    pub fn facet_ordinal_mapping(&self, field: Field) -> Arc<TermOrdinalMapping> {
+        let mut term_ordinal_mappings = self.term_ordinal_mappings
+            .lock()
+            .expect("Failed to acquire the term ordinal mapping lock.");
+        let segment_readers = &self.segment_readers;
+        Arc::clone(term_ordinal_mappings.entry(field).or_insert_with(|| {
+            Arc::new(TermOrdinalMapping::build(segment_readers, field))
+        }))
+    }
+
     /// Return the field searcher associated to a `Field`.
     pub fn field(&self, field: Field) -> FieldSearcher {
         let inv_index_readers = self.segment_readers
@@ -92,11 +301,30 @@ impl FieldSearcher {
             .collect();
         TermMerger::new(term_streamers)
     }
+
+    /// Returns a Stream over the sorted unique terms of the given field
+    /// appearing in at least `min_doc_freq` documents, deleted
+    /// documents included.
+    ///
+    /// The document frequencies of the segments are summed
+    /// before being compared to `min_doc_freq`.
+    pub fn terms_with_doc_freq_ge(&self, min_doc_freq: u32) -> TermMerger {
+        self.terms().doc_freq_ge(min_doc_freq)
+    }
 }
 
+/// Creates a `Searcher` over the given segment readers.
+///
+/// The schema is the one of the first segment reader, and
+/// the cache of the `TermOrdinalMapping`s is not shared
+/// with any other searcher.
 impl From<Vec<SegmentReader>> for Searcher {
     fn from(segment_readers: Vec<SegmentReader>) -> Searcher {
-        Searcher { segment_readers }
+        let schema = segment_readers
+            .first()
+            .map(|segment_reader| segment_reader.schema().clone())
+            .unwrap_or_else(|| SchemaBuilder::default().build());
+        Searcher::new(schema, segment_readers, Arc::default())
     }
 }
 
@@ -109,3 +337,234 @@ impl fmt::Debug for Searcher {
         write!(f, "Searcher({:?})", segment_ids)
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{Document, SchemaBuilder, STORED, STRING};
+    use indexer::NoMergePolicy;
+    use error::{Error, ErrorKind};
+    use futures::Future;
+
+    #[test]
+    fn test_doc_by_segment() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field=>"a0")).unwrap();
+        index_writer.add_document(doc!(text_field=>"a1")).unwrap();
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"b")).unwrap();
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field=>"c")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+
+        let (segment_a, other_segment_ids) = {
+            let searcher = index.searcher();
+            assert_eq!(searcher.segment_readers().len(), 3);
+            let segment_a = searcher
+                .segment_readers()
+                .iter()
+                .find(|segment_reader| segment_reader.max_doc() == 2)
+                .unwrap()
+                .segment_id();
+            let other_segment_ids = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.segment_id())
+                .filter(|segment_id| *segment_id != segment_a)
+                .collect::<Vec<_>>();
+            (segment_a, other_segment_ids)
+        };
+        let text = |doc: Document| doc.get_first(text_field).unwrap().text().to_string();
+
+        // merging unrelated segments
+        index_writer.merge(&other_segment_ids).wait().unwrap();
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            assert_eq!(searcher.segment_readers().len(), 2);
+            assert!(searcher.segment_reader_by_id(&segment_a).is_some());
+            let segment_ord = searcher.segment_ord(&segment_a).unwrap();
+            assert_eq!(
+                searcher.segment_reader(segment_ord).segment_id(),
+                segment_a
+            );
+            assert_eq!(text(searcher.doc_by_segment(&segment_a, 0).unwrap()), "a0");
+            assert_eq!(text(searcher.doc_by_segment(&segment_a, 1).unwrap()), "a1");
+            for segment_id in &other_segment_ids {
+                assert!(searcher.segment_reader_by_id(segment_id).is_none());
+            }
+        }
+
+        // merging the target segment
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert!(searcher.segment_reader_by_id(&segment_a).is_none());
+        match searcher.doc_by_segment(&segment_a, 1) {
+            Err(Error(ErrorKind::SegmentNotFound(segment_id), _)) => {
+                assert_eq!(segment_id, segment_a);
+            }
+            _ => panic!("Expected SegmentNotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_searcher_from_segment_readers() {
+        use core::Searcher;
+        use query::{AllQuery, Query};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field=>"a")).unwrap();
+        index_writer.add_document(doc!(text_field=>"b")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let segment_readers = index.searcher().segment_readers().to_vec();
+        let searcher = Searcher::from(segment_readers);
+        assert_eq!(searcher.schema().get_field("text"), Some(text_field));
+        assert_eq!(AllQuery.count(&searcher).unwrap(), 2);
+        let empty_searcher = Searcher::from(Vec::new());
+        assert_eq!(empty_searcher.num_docs(), 0);
+    }
+
+    #[test]
+    fn test_num_docs() {
+        use query::{AllQuery, ExistsQuery, Query};
+        use schema::{Field, Term};
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let other_field = schema_builder.add_text_field("other", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let check = |num_docs: u64, max_doc: u64, num_docs_with_tag: u64| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_docs(), num_docs);
+            assert_eq!(searcher.max_doc_total(), max_doc);
+            assert_eq!(searcher.num_deleted_docs(), max_doc - num_docs);
+            assert_eq!(AllQuery.count(&*searcher).unwrap() as u64, num_docs);
+            let count_with_field = |field: Field| {
+                let num_docs_with_field = searcher.num_docs_with_field(field).unwrap();
+                let query_count = ExistsQuery::new(field).count(&*searcher).unwrap();
+                assert_eq!(num_docs_with_field, query_count as u64);
+                num_docs_with_field
+            };
+            assert_eq!(count_with_field(id_field), num_docs);
+            assert_eq!(count_with_field(tag_field), num_docs_with_tag);
+            assert_eq!(count_with_field(other_field), 0);
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        check(0, 0, 0);
+        for i in 0..10 {
+            let mut doc = doc!(id_field => i.to_string());
+            if i % 2 == 0 {
+                doc.add_text(tag_field, "even");
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        check(10, 10, 5);
+        for i in 10..15 {
+            let mut doc = doc!(id_field => i.to_string());
+            if i % 2 == 0 {
+                doc.add_text(tag_field, "even");
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.delete_term(Term::from_field_text(id_field, "0"));
+        index_writer.delete_term(Term::from_field_text(id_field, "1"));
+        index_writer.commit().unwrap();
+        check(13, 15, 7);
+
+        // merging purges the deleted documents.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 2);
+        index_writer.merge(&segment_ids).wait().unwrap();
+        check(13, 13, 7);
+    }
+
+    #[test]
+    fn test_docs() {
+        use DocAddress;
+        use std::collections::HashSet;
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", STRING | STORED);
+        let body_field = schema_builder.add_text_field("body", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let body = "lorem ipsum ".repeat(100);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment in 0..2 {
+                for doc in 0..200 {
+                    let id = format!("{}-{}", segment, doc);
+                    index_writer
+                        .add_document(doc!(id_field => id, body_field => body.clone()))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let doc_addresses: Vec<DocAddress> = [
+            (1, 150),
+            (0, 3),
+            (1, 2),
+            (0, 199),
+            (0, 3),
+            (1, 151),
+            (0, 100),
+            (0, 4),
+        ].iter()
+            .map(|&(segment_ord, doc_id)| DocAddress(segment_ord, doc_id))
+            .collect();
+        let num_decompressed_blocks = || {
+            searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.get_store_reader().num_decompressed_blocks())
+                .sum::<usize>()
+        };
+        let num_blocks: usize = doc_addresses
+            .iter()
+            .map(|&DocAddress(segment_ord, doc_id)| {
+                let store_reader = searcher.segment_reader(segment_ord).get_store_reader();
+                (segment_ord, store_reader.block_offset(doc_id))
+            })
+            .collect::<HashSet<_>>()
+            .len();
+        assert!(num_blocks > 2);
+        assert_eq!(num_decompressed_blocks(), 0);
+        let docs = searcher.docs(&doc_addresses);
+        assert_eq!(num_decompressed_blocks(), num_blocks);
+        assert_eq!(docs.len(), doc_addresses.len());
+        for (doc, doc_address) in docs.into_iter().zip(doc_addresses.iter()) {
+            let DocAddress(segment_ord, doc_id) = *doc_address;
+            let doc = doc.unwrap();
+            let expected_id = format!("{}-{}", segment_ord, doc_id);
+            assert_eq!(doc.get_first(id_field).unwrap().text(), &expected_id[..]);
+            assert_eq!(doc, searcher.doc(doc_address).unwrap());
+        }
+
+        // invalid addresses only fail their own item.
+        let docs = searcher.docs(&[DocAddress(0, 200), DocAddress(1, 0), DocAddress(2, 0)]);
+        let is_invalid_argument = |doc: &Result<Document, Error>| match doc {
+            &Err(Error(ErrorKind::InvalidArgument(_), _)) => true,
+            _ => false,
+        };
+        assert!(is_invalid_argument(&docs[0]));
+        assert_eq!(docs[1].as_ref().unwrap().get_first(id_field).unwrap().text(), "1-0");
+        assert!(is_invalid_argument(&docs[2]));
+    }
+}
@@ -10,7 +10,10 @@ use core::Index;
 use std::result;
 use directory::Directory;
 use core::SegmentMeta;
+use indexer::Opstamp;
 use directory::error::{OpenReadError, OpenWriteError};
+use serde_json;
+use std::io::Write;
 
 /// A segment is a piece of the index.
 #[derive(Clone)]
@@ -49,7 +52,7 @@ impl Segment {
     }
 
     #[doc(hidden)]
-    pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: u64) {
+    pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: Opstamp) {
         self.meta.set_delete_meta(num_deleted_docs, opstamp);
     }
 
@@ -95,6 +98,20 @@ impl Segment {
         let write = self.index.directory_mut().open_write(&path)?;
         Ok(write)
     }
+
+    /// Writes the `META` component of a segment that was just written,
+    /// given its meta and the ids of the segments it was merged from.
+    pub(crate) fn write_meta_file(
+        &mut self,
+        segment_meta: &SegmentMeta,
+        merged_segment_ids: &[SegmentId],
+    ) -> Result<()> {
+        let buffer = serde_json::to_vec(&segment_meta.meta_file(merged_segment_ids))?;
+        let mut meta_file = self.open_write(SegmentComponent::META)?;
+        meta_file.write_all(&buffer)?;
+        meta_file.flush()?;
+        Ok(())
+    }
 }
 
 pub trait SerializableSegment {
@@ -2,7 +2,7 @@
 /// Each component is stored in its own file,
 /// using the pattern `segment_uuid`.`component_extension`,
 /// except the delete component that takes an `segment_uuid`.`delete_opstamp`.`component_extension`
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SegmentComponent {
     /// Postings (or inverted list). Sorted lists of document ids, associated to terms
     POSTINGS,
@@ -22,12 +22,25 @@ pub enum SegmentComponent {
     STORE,
     /// Bitset describing which document of the segment is deleted.
     DELETE,
+    /// Bloom filters over the terms of the fields
+    /// configured with a bloom filter in the schema.
+    BLOOMFILTERS,
+    /// Bitsets describing which documents contain
+    /// at least one value for each field.
+    FIELDPRESENCE,
+    /// Display labels of the facets, associated
+    /// to their facet ordinals.
+    FACETLABELS,
+    /// Copy of the meta of the segment, as of when it was written,
+    /// and the ids of the segments it was merged from.
+    /// It makes it possible to rebuild a lost `meta.json`.
+    META,
 }
 
 impl SegmentComponent {
     /// Iterates through the components.
     pub fn iterator() -> impl Iterator<Item = &'static SegmentComponent> {
-        static SEGMENT_COMPONENTS: [SegmentComponent; 7] = [
+        static SEGMENT_COMPONENTS: [SegmentComponent; 11] = [
             SegmentComponent::POSTINGS,
             SegmentComponent::POSITIONS,
             SegmentComponent::FASTFIELDS,
@@ -35,6 +48,10 @@ impl SegmentComponent {
             SegmentComponent::TERMS,
             SegmentComponent::STORE,
             SegmentComponent::DELETE,
+            SegmentComponent::BLOOMFILTERS,
+            SegmentComponent::FIELDPRESENCE,
+            SegmentComponent::FACETLABELS,
+            SegmentComponent::META,
         ];
         SEGMENT_COMPONENTS.into_iter()
     }
@@ -59,6 +59,13 @@ impl SegmentId {
     pub fn uuid_string(&self) -> String {
         self.0.simple().to_string()
     }
+
+    /// Parses a segment id from its uuid string.
+    ///
+    /// Returns `None` if the string is not a valid uuid.
+    pub(crate) fn from_uuid_string(uuid_string: &str) -> Option<SegmentId> {
+        Uuid::parse_str(uuid_string).ok().map(SegmentId)
+    }
 }
 
 impl fmt::Debug for SegmentId {
@@ -78,3 +85,19 @@ impl Ord for SegmentId {
         self.0.as_bytes().cmp(other.0.as_bytes())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::SegmentId;
+    use serde_json;
+
+    #[test]
+    fn test_segment_id_serde_roundtrip() {
+        let segment_id = SegmentId::generate_random();
+        let json = serde_json::to_string(&segment_id).unwrap();
+        assert_eq!(json, format!("\"{}\"", segment_id.0.hyphenated()));
+        let deserialized: SegmentId = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, segment_id);
+    }
+}
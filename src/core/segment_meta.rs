@@ -1,12 +1,14 @@
 use core::SegmentId;
+use indexer::Opstamp;
 use super::SegmentComponent;
+use schema::{Field, IndexRecordOption};
 use std::path::PathBuf;
 use std::collections::HashSet;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct DeleteMeta {
     num_deleted_docs: u32,
-    opstamp: u64,
+    opstamp: Opstamp,
 }
 
 /// `SegmentMeta` contains simple meta information about a segment.
@@ -18,9 +20,83 @@ pub struct SegmentMeta {
     segment_id: SegmentId,
     max_doc: u32,
     deletes: Option<DeleteMeta>,
+    // segments written by upstream tantivy, or before this
+    // statistic was recorded, do not have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_num_tokens: Option<Vec<(Field, u64)>>,
+    // the schema may have been modified since
+    // the segment was written.
+    #[serde(default)]
+    index_record_options: Vec<(Field, IndexRecordOption)>,
+    #[serde(default)]
+    int_value_ranges: Vec<(Field, u64, u64)>,
+    #[serde(default)]
+    fast_field_value_ranges: Vec<(Field, u64, u64)>,
+    // segments written by upstream tantivy, or before
+    // the format version was recorded, do not have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format_version: Option<u32>,
+}
+
+/// Content of the `META` component of a segment.
+///
+/// It holds the part of the `SegmentMeta` that is known when the
+/// segment is written, i.e. everything but its id and its deletes,
+/// and the ids of the segments it was merged from, if any.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SegmentMetaFile {
+    max_doc: u32,
+    total_num_tokens: Vec<(Field, u64)>,
+    index_record_options: Vec<(Field, IndexRecordOption)>,
+    int_value_ranges: Vec<(Field, u64, u64)>,
+    #[serde(default)]
+    fast_field_value_ranges: Vec<(Field, u64, u64)>,
+    format_version: Option<u32>,
+    merged_segment_ids: Vec<SegmentId>,
+}
+
+impl SegmentMetaFile {
+    /// Returns the number of documents of the segment.
+    pub fn max_doc(&self) -> u32 {
+        self.max_doc
+    }
+
+    /// Returns the ids of the segments the segment was merged from.
+    pub fn merged_segment_ids(&self) -> &[SegmentId] {
+        &self.merged_segment_ids
+    }
+
+    /// Rebuilds the meta of the segment, without its deletes.
+    pub fn segment_meta(&self, segment_id: SegmentId) -> SegmentMeta {
+        SegmentMeta {
+            segment_id,
+            max_doc: self.max_doc,
+            deletes: None,
+            total_num_tokens: Some(self.total_num_tokens.clone()),
+            index_record_options: self.index_record_options.clone(),
+            int_value_ranges: self.int_value_ranges.clone(),
+            fast_field_value_ranges: self.fast_field_value_ranges.clone(),
+            format_version: self.format_version,
+        }
+    }
 }
 
 impl SegmentMeta {
+    /// Returns the content of the `META` component of the segment.
+    pub(crate) fn meta_file(&self, merged_segment_ids: &[SegmentId]) -> SegmentMetaFile {
+        let mut merged_segment_ids = merged_segment_ids.to_vec();
+        merged_segment_ids.sort();
+        SegmentMetaFile {
+            max_doc: self.max_doc,
+            total_num_tokens: self.total_num_tokens.clone().unwrap_or_default(),
+            index_record_options: self.index_record_options.clone(),
+            int_value_ranges: self.int_value_ranges.clone(),
+            fast_field_value_ranges: self.fast_field_value_ranges.clone(),
+            format_version: self.format_version,
+            merged_segment_ids,
+        }
+    }
+
     /// Creates a new segment meta for
     /// a segment with no deletes and no documents.
     pub fn new(segment_id: SegmentId) -> SegmentMeta {
@@ -28,6 +104,11 @@ impl SegmentMeta {
             segment_id,
             max_doc: 0,
             deletes: None,
+            total_num_tokens: None,
+            index_record_options: Vec::new(),
+            int_value_ranges: Vec::new(),
+            fast_field_value_ranges: Vec::new(),
+            format_version: None,
         }
     }
 
@@ -69,7 +150,13 @@ impl SegmentMeta {
             SegmentComponent::STORE => ".store".to_string(),
             SegmentComponent::FASTFIELDS => ".fast".to_string(),
             SegmentComponent::FIELDNORMS => ".fieldnorm".to_string(),
-            SegmentComponent::DELETE => format!(".{}.del", self.delete_opstamp().unwrap_or(0)),
+            SegmentComponent::DELETE => {
+                format!(".{}.del", self.delete_opstamp().unwrap_or_default())
+            }
+            SegmentComponent::BLOOMFILTERS => ".bloom".to_string(),
+            SegmentComponent::FIELDPRESENCE => ".presence".to_string(),
+            SegmentComponent::FACETLABELS => ".labels".to_string(),
+            SegmentComponent::META => ".meta".to_string(),
         });
         PathBuf::from(path)
     }
@@ -88,12 +175,104 @@ impl SegmentMeta {
         self.max_doc() - self.num_deleted_docs()
     }
 
+    /// Returns the ratio of the deleted documents over all of
+    /// the documents of the segment, between `0` and `1`.
+    pub fn deleted_docs_ratio(&self) -> f32 {
+        if self.max_doc == 0 {
+            0f32
+        } else {
+            self.num_deleted_docs() as f32 / self.max_doc as f32
+        }
+    }
+
     /// Returns the opstamp of the last delete operation
     /// taken in account in this segment.
-    pub fn delete_opstamp(&self) -> Option<u64> {
+    pub fn delete_opstamp(&self) -> Option<Opstamp> {
         self.deletes.as_ref().map(|delete_meta| delete_meta.opstamp)
     }
 
+    /// Returns the total number of tokens of an indexed text field,
+    /// over all of the documents of the segment.
+    ///
+    /// Deleted documents are counted until they are
+    /// expunged by a merge.
+    ///
+    /// Returns `None` if the field is not an indexed text field,
+    /// or if the segment was written before this statistic was recorded.
+    pub fn total_num_tokens(&self, field: Field) -> Option<u64> {
+        self.total_num_tokens
+            .as_ref()?
+            .iter()
+            .find(|&&(stat_field, _)| stat_field == field)
+            .map(|&(_, total_num_tokens)| total_num_tokens)
+    }
+
+    /// Returns the `IndexRecordOption` the postings of an indexed
+    /// field were written with in this segment.
+    ///
+    /// Returns `None` if the field is not indexed, or if the segment was
+    /// written before this information was recorded. In the latter case,
+    /// the option of the schema applies.
+    pub fn index_record_option(&self, field: Field) -> Option<IndexRecordOption> {
+        self.index_record_options
+            .iter()
+            .find(|&&(option_field, _)| option_field == field)
+            .map(|&(_, index_record_option)| index_record_option)
+    }
+
+    /// Returns the smallest and the largest value indexed
+    /// for a `u64` or `i64` field in this segment.
+    ///
+    /// Values are expressed in their `u64` representation. (`i64` are mapped
+    /// using `common::i64_to_u64`)
+    /// Deleted documents are taken in account until they are
+    /// expunged by a merge.
+    ///
+    /// Returns `None` if the segment does not contain any value for this field,
+    /// or if the segment was written before this statistic was recorded.
+    pub fn int_value_range(&self, field: Field) -> Option<(u64, u64)> {
+        self.int_value_ranges
+            .iter()
+            .find(|&&(range_field, _, _)| range_field == field)
+            .map(|&(_, min_value, max_value)| (min_value, max_value))
+    }
+
+    /// Returns the smallest and the largest value of a single-valued
+    /// `u64` or `i64` fast field in this segment.
+    ///
+    /// Unlike `int_value_range`, the field does not need to be indexed,
+    /// and the default value of the documents without any value for the
+    /// field is accounted for. Values are expressed in their `u64`
+    /// representation, and deleted documents are taken in account until
+    /// they are expunged by a merge.
+    ///
+    /// Returns `None` if the field is not a single-valued fast field,
+    /// or if the segment was written before this statistic was recorded.
+    pub fn fast_field_value_range(&self, field: Field) -> Option<(u64, u64)> {
+        self.fast_field_value_ranges
+            .iter()
+            .find(|&&(range_field, _, _)| range_field == field)
+            .map(|&(_, min_value, max_value)| (min_value, max_value))
+    }
+
+    /// Returns the version of the format the segment was written in.
+    ///
+    /// Returns `None` if the segment was written by upstream tantivy,
+    /// or before the format version was recorded. See the
+    /// [`compat`](./compat/index.html) module.
+    pub fn format_version(&self) -> Option<u32> {
+        self.format_version
+    }
+
+    /// Returns true iff the meta records the statistics of the segment
+    /// that only this fork writes, starting with `total_num_tokens`.
+    ///
+    /// This tells the segments written by this fork before the format
+    /// version was recorded from the segments written by upstream tantivy.
+    pub(crate) fn has_fork_statistics(&self) -> bool {
+        self.total_num_tokens.is_some()
+    }
+
     /// Returns true iff the segment meta contains
     /// delete information.
     pub fn has_deletes(&self) -> bool {
@@ -106,7 +285,35 @@ impl SegmentMeta {
     }
 
     #[doc(hidden)]
-    pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: u64) {
+    pub fn set_total_num_tokens(&mut self, total_num_tokens: Vec<(Field, u64)>) {
+        self.total_num_tokens = Some(total_num_tokens);
+    }
+
+    #[doc(hidden)]
+    pub fn set_index_record_options(
+        &mut self,
+        index_record_options: Vec<(Field, IndexRecordOption)>,
+    ) {
+        self.index_record_options = index_record_options;
+    }
+
+    #[doc(hidden)]
+    pub fn set_int_value_ranges(&mut self, int_value_ranges: Vec<(Field, u64, u64)>) {
+        self.int_value_ranges = int_value_ranges;
+    }
+
+    #[doc(hidden)]
+    pub fn set_fast_field_value_ranges(&mut self, fast_field_value_ranges: Vec<(Field, u64, u64)>) {
+        self.fast_field_value_ranges = fast_field_value_ranges;
+    }
+
+    #[doc(hidden)]
+    pub fn set_format_version(&mut self, format_version: u32) {
+        self.format_version = Some(format_version);
+    }
+
+    #[doc(hidden)]
+    pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: Opstamp) {
         self.deletes = Some(DeleteMeta {
             num_deleted_docs,
             opstamp,
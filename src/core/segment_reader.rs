@@ -7,6 +7,8 @@ use common::HasLen;
 use core::SegmentMeta;
 use fastfield::{self, FastFieldNotAvailableError};
 use fastfield::DeleteBitSet;
+use fastfield::FieldPresenceReader;
+use fastfield::FacetLabelsReader;
 use store::StoreReader;
 use schema::Document;
 use DocId;
@@ -17,6 +19,7 @@ use std::fmt;
 use core::InvertedIndexReader;
 use schema::Field;
 use schema::FieldType;
+use schema::IndexRecordOption;
 use error::ErrorKind;
 use termdict::TermDictionaryImpl;
 use fastfield::FacetReader;
@@ -25,6 +28,18 @@ use schema::Schema;
 use termdict::TermDictionary;
 use fastfield::{FastValue, MultiValueIntFastFieldReader};
 use schema::Cardinality;
+use datastruct::BloomFilter;
+use core::{ComponentSpaceUsage, SegmentSpaceUsage};
+use schema::value_bytes_to_string;
+use termdict::TermStreamer;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+use common::BitSet;
+use compat;
+
+/// Number of terms listed per field by `SegmentReader::dump_summary`.
+const NUM_TOP_TERMS: usize = 10;
 
 /// Entry point to access all of the datastructures of the `Segment`
 ///
@@ -36,6 +51,8 @@ use schema::Cardinality;
 ///
 /// The segment reader has a very low memory footprint,
 /// as close to all of the memory data is mmapped.
+/// Small term dictionaries and fast fields can however be copied
+/// into anonymous memory (see `SegmentReader::open_with_eager_loading`).
 ///
 ///
 /// TODO fix not decoding docfreq
@@ -45,12 +62,17 @@ pub struct SegmentReader {
 
     segment_id: SegmentId,
     segment_meta: SegmentMeta,
+    format_version: u32,
 
     termdict_composite: CompositeFile,
     postings_composite: CompositeFile,
     positions_composite: CompositeFile,
     fast_fields_composite: CompositeFile,
     fieldnorms_composite: CompositeFile,
+    bloom_filters_composite: CompositeFile,
+    // `None` for the segments written before field presence was recorded.
+    field_presence_composite: Option<CompositeFile>,
+    facet_labels_composite: CompositeFile,
 
     store_reader: StoreReader,
     delete_bitset: DeleteBitSet,
@@ -75,6 +97,16 @@ impl SegmentReader {
         self.segment_meta.num_docs()
     }
 
+    /// Returns the meta information of the segment.
+    pub fn segment_meta(&self) -> &SegmentMeta {
+        &self.segment_meta
+    }
+
+    /// Returns the schema of the segment.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     /// Return the number of documents that have been
     /// deleted in the segment.
     pub fn num_deleted_docs(&self) -> DocId {
@@ -130,15 +162,66 @@ impl SegmentReader {
         }
     }
 
+    /// Accessor to the reader of the value position offsets of a text field.
+    ///
+    /// Returns a `FastFieldNotAvailableError` if the field does not record
+    /// its value position offsets.
+    /// (See [`TextFieldIndexing::set_value_position_offsets`](
+    /// schema/struct.TextFieldIndexing.html#method.set_value_position_offsets))
+    pub fn value_position_offsets_reader(
+        &self,
+        field: Field,
+    ) -> fastfield::Result<MultiValueIntFastFieldReader<u64>> {
+        let field_entry = self.schema.get_field_entry(field);
+        let value_position_offsets = match *field_entry.field_type() {
+            FieldType::Str(ref text_options) => text_options
+                .get_indexing_options()
+                .map(|indexing_options| indexing_options.value_position_offsets())
+                .unwrap_or(false),
+            _ => false,
+        };
+        if !value_position_offsets {
+            return Err(FastFieldNotAvailableError::new(field_entry));
+        }
+        let idx_reader = self.fast_fields_composite
+            .open_read_with_idx(field, 0)
+            .ok_or_else(|| FastFieldNotAvailableError::new(field_entry))
+            .map(FastFieldReader::open)?;
+        let vals_reader = self.fast_fields_composite
+            .open_read_with_idx(field, 1)
+            .ok_or_else(|| FastFieldNotAvailableError::new(field_entry))
+            .map(FastFieldReader::open)?;
+        Ok(MultiValueIntFastFieldReader::open(idx_reader, vals_reader))
+    }
+
+    /// Returns the position of the first token of each of the values of
+    /// a multivalued text field, for a given document.
+    ///
+    /// The values of a field are indexed as one single stream of tokens, in the
+    /// order they were added to the document. The position `pos` of a token
+    /// belongs to the last value whose offset is lower or equal to `pos`.
+    ///
+    /// The offsets of the values that do not produce any token are still recorded,
+    /// so that the value indexes match the values of the stored document.
+    /// A document without any value for the field has no offsets.
+    ///
+    /// Returns a `FastFieldNotAvailableError` if the field does not record
+    /// its value position offsets.
+    pub fn value_position_offsets(&self, field: Field, doc: DocId) -> fastfield::Result<Vec<u32>> {
+        let reader = self.value_position_offsets_reader(field)?;
+        let mut offsets = vec![];
+        reader.get_vals(doc, &mut offsets);
+        Ok(offsets.into_iter().map(|offset| offset as u32).collect())
+    }
+
     /// Accessor to the `FacetReader` associated to a given `Field`.
+    ///
+    /// Returns a `NotAFacetField` error if the field is not
+    /// a hierarchical facet field.
     pub fn facet_reader(&self, field: Field) -> Result<FacetReader> {
         let field_entry = self.schema.get_field_entry(field);
         if field_entry.field_type() != &FieldType::HierarchicalFacet {
-            return Err(ErrorKind::InvalidArgument(format!(
-                "The field {:?} is not a \
-                 hierarchical facet.",
-                field_entry
-            )).into());
+            bail!(ErrorKind::NotAFacetField(field_entry.name().to_string()));
         }
         let term_ords_reader = self.multi_fast_field_reader(field)?;
         let termdict_source = self.termdict_composite.open_read(field).ok_or_else(|| {
@@ -150,7 +233,10 @@ impl SegmentReader {
             ))
         })?;
         let termdict = TermDictionaryImpl::from_source(termdict_source);
-        let facet_reader = FacetReader::new(term_ords_reader, termdict);
+        let mut facet_reader = FacetReader::new(term_ords_reader, termdict);
+        if let Some(labels_source) = self.facet_labels_composite.open_read(field) {
+            facet_reader.set_labels(FacetLabelsReader::open(labels_source));
+        }
         Ok(facet_reader)
     }
 
@@ -162,7 +248,15 @@ impl SegmentReader {
     ///
     /// They are simply stored as a fast field, serialized in
     /// the `.fieldnorm` file of the segment.
+    ///
+    /// Returns `None` for the fields that do not have field norms
+    /// (see [`FieldEntry::has_fieldnorms`](schema/struct.FieldEntry.html#method.has_fieldnorms)),
+    /// even if the segment was written with norms for this field.
+    /// All of their documents should be considered as having a norm of 1.
     pub fn get_fieldnorms_reader(&self, field: Field) -> Option<FastFieldReader<u64>> {
+        if !self.schema.get_field_entry(field).has_fieldnorms() {
+            return None;
+        }
         self.fieldnorms_composite
             .open_read(field)
             .map(FastFieldReader::open)
@@ -175,10 +269,26 @@ impl SegmentReader {
 
     /// Open a new segment for reading.
     pub fn open(segment: &Segment) -> Result<SegmentReader> {
+        SegmentReader::open_with_eager_loading(segment, 0)
+    }
+
+    /// Open a new segment for reading, copying the term dictionaries
+    /// and the fast fields that are smaller than `eager_loading_threshold`
+    /// bytes into anonymous memory.
+    ///
+    /// Accessing the copied data does not trigger any page fault,
+    /// which makes the latency of the first searches more predictable.
+    /// Larger files remain mmapped.
+    pub fn open_with_eager_loading(
+        segment: &Segment,
+        eager_loading_threshold: usize,
+    ) -> Result<SegmentReader> {
         let termdict_source = segment.open_read(SegmentComponent::TERMS)?;
-        let termdict_composite = CompositeFile::open(&termdict_source)?;
+        let mut termdict_composite = CompositeFile::open(&termdict_source)?;
+        termdict_composite.load_small_files(eager_loading_threshold);
 
         let store_source = segment.open_read(SegmentComponent::STORE)?;
+        let format_version = compat::segment_format_version(segment.meta(), &store_source)?;
         let store_reader = StoreReader::from_source(store_source);
 
         let postings_source = segment.open_read(SegmentComponent::POSTINGS)?;
@@ -193,11 +303,36 @@ impl SegmentReader {
         };
 
         let fast_fields_data = segment.open_read(SegmentComponent::FASTFIELDS)?;
-        let fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
+        let mut fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
+        fast_fields_composite.load_small_files(eager_loading_threshold);
 
         let fieldnorms_data = segment.open_read(SegmentComponent::FIELDNORMS)?;
         let fieldnorms_composite = CompositeFile::open(&fieldnorms_data)?;
 
+        let bloom_filters_composite = {
+            if let Ok(source) = segment.open_read(SegmentComponent::BLOOMFILTERS) {
+                CompositeFile::open(&source)?
+            } else {
+                CompositeFile::empty()
+            }
+        };
+
+        let field_presence_composite = {
+            if let Ok(source) = segment.open_read(SegmentComponent::FIELDPRESENCE) {
+                Some(CompositeFile::open(&source)?)
+            } else {
+                None
+            }
+        };
+
+        let facet_labels_composite = {
+            if let Ok(source) = segment.open_read(SegmentComponent::FACETLABELS) {
+                CompositeFile::open(&source)?
+            } else {
+                CompositeFile::empty()
+            }
+        };
+
         let delete_bitset = if segment.meta().has_deletes() {
             let delete_data = segment.open_read(SegmentComponent::DELETE)?;
             DeleteBitSet::open(delete_data)
@@ -209,10 +344,14 @@ impl SegmentReader {
         Ok(SegmentReader {
             inv_idx_reader_cache: Arc::new(RwLock::new(HashMap::new())),
             segment_meta: segment.meta().clone(),
+            format_version,
             termdict_composite,
             postings_composite,
             fast_fields_composite,
             fieldnorms_composite,
+            bloom_filters_composite,
+            field_presence_composite,
+            facet_labels_composite,
             segment_id: segment.id(),
             store_reader,
             delete_bitset,
@@ -238,7 +377,7 @@ impl SegmentReader {
         }
         let field_entry = self.schema.get_field_entry(field);
         let field_type = field_entry.field_type();
-        let record_option_opt = field_type.get_index_record_option();
+        let record_option_opt = self.index_record_option(field);
 
         if record_option_opt.is_none() {
             panic!("Field {:?} does not seem indexed.", field_entry.name());
@@ -266,12 +405,26 @@ impl SegmentReader {
             .open_read(field)
             .expect("Index corrupted. Failed to open field positions in composite file.");
 
+        // segments written before the bloom filter was enabled
+        // do not have any bloom filter.
+        let bloom_filter_opt = self.bloom_filters_composite
+            .open_read(field)
+            .map(BloomFilter::open);
+
+        // segments written before the total number of tokens was recorded
+        // in the segment meta fall back to summing their field norms.
+        let total_num_tokens = self.segment_meta
+            .total_num_tokens(field)
+            .unwrap_or_else(|| self.sum_fieldnorms(field, true));
+
         let inv_idx_reader = Arc::new(InvertedIndexReader::new(
             TermDictionaryImpl::from_source(termdict_source),
             postings_source,
             positions_source,
             self.delete_bitset.clone(),
             record_option,
+            bloom_filter_opt,
+            total_num_tokens,
         ));
 
         // by releasing the lock in between, we may end up opening the inverting index
@@ -284,6 +437,158 @@ impl SegmentReader {
         inv_idx_reader
     }
 
+    /// Returns true iff the inverted index of the field
+    /// has already been opened.
+    #[cfg(test)]
+    pub(crate) fn is_inverted_index_open(&self, field: Field) -> bool {
+        self.inv_idx_reader_cache
+            .read()
+            .expect("Lock poisoned. This should never happen")
+            .contains_key(&field)
+    }
+
+    /// Returns the `IndexRecordOption` the postings of a field
+    /// were written with in this segment, or `None` if the field
+    /// is not indexed.
+    ///
+    /// The schema may have been modified since the segment was written,
+    /// in which case this option may differ from the one of the schema.
+    pub fn index_record_option(&self, field: Field) -> Option<IndexRecordOption> {
+        let schema_record_option = self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option();
+        schema_record_option.map(|schema_record_option| {
+            self.segment_meta
+                .index_record_option(field)
+                .unwrap_or(schema_record_option)
+        })
+    }
+
+    /// Returns the sum of the field norms of a field,
+    /// that is its total number of tokens.
+    ///
+    /// Deleted documents are only counted if `include_deleted` is true.
+    pub(crate) fn sum_fieldnorms(&self, field: Field, include_deleted: bool) -> u64 {
+        self.get_fieldnorms_reader(field)
+            .map(|fieldnorms_reader| {
+                (0..self.max_doc())
+                    .filter(|&doc| include_deleted || !self.is_deleted(doc))
+                    .map(|doc| fieldnorms_reader.get(doc))
+                    .sum()
+            })
+            .unwrap_or(0u64)
+    }
+
+    /// Returns the fields for which at least one document of the
+    /// segment holds a value, sorted by field id.
+    ///
+    /// Deleted documents are accounted for.
+    ///
+    /// Segments written before field presence was recorded
+    /// only report their indexed fields.
+    pub fn fields(&self) -> Vec<Field> {
+        let composite_file = self.field_presence_composite
+            .as_ref()
+            .unwrap_or(&self.postings_composite);
+        let mut fields: Vec<Field> = composite_file
+            .files()
+            .into_iter()
+            .map(|(field, _, _)| field)
+            .collect();
+        fields.sort();
+        fields.dedup();
+        fields
+    }
+
+    /// Returns true iff at least one document of the segment
+    /// holds a value for the field.
+    ///
+    /// See [`.fields()`](#method.fields).
+    pub fn has_field(&self, field: Field) -> bool {
+        let composite_file = self.field_presence_composite
+            .as_ref()
+            .unwrap_or(&self.postings_composite);
+        composite_file.open_read(field).is_some()
+    }
+
+    /// Returns the set of the non-deleted documents that hold
+    /// at least one value for the field.
+    ///
+    /// # Warning
+    ///
+    /// Segments written before field presence was recorded
+    /// have to rebuild this set. For an indexed field, this requires
+    /// to go through the postings of all of its terms. Otherwise,
+    /// every stored document is read. For fields that are neither
+    /// indexed nor stored, the returned set is empty.
+    pub fn docs_with_field(&self, field: Field) -> Result<BitSet> {
+        let max_doc = self.max_doc();
+        let mut docs = BitSet::with_max_value(max_doc);
+        if let Some(ref field_presence_composite) = self.field_presence_composite {
+            if let Some(source) = field_presence_composite.open_read(field) {
+                let field_presence = FieldPresenceReader::open(source);
+                for doc in 0..max_doc {
+                    if field_presence.contains(doc) && !self.is_deleted(doc) {
+                        docs.insert(doc);
+                    }
+                }
+            }
+            return Ok(docs);
+        }
+        if self.index_record_option(field).is_some() {
+            let inverted_index = self.inverted_index(field);
+            let mut term_stream = inverted_index.terms().stream();
+            while term_stream.advance() {
+                let term_info = term_stream.value();
+                let mut block_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+                while block_postings.advance() {
+                    for &doc in block_postings.docs() {
+                        if !self.is_deleted(doc) {
+                            docs.insert(doc);
+                        }
+                    }
+                }
+            }
+        } else if self.schema.get_field_entry(field).is_stored() {
+            for doc in 0..max_doc {
+                if !self.is_deleted(doc) && self.doc(doc)?.get_first(field).is_some() {
+                    docs.insert(doc);
+                }
+            }
+        }
+        Ok(docs)
+    }
+
+    /// Returns the number of non-deleted documents that hold
+    /// at least one value for the field.
+    ///
+    /// This only counts the bits of the field presence bitset,
+    /// and does not run any query. If the segment has deletes, the
+    /// field presence bitset is intersected with the complement of the
+    /// delete bitset, one 64 bits word at a time.
+    ///
+    /// # Warning
+    ///
+    /// Segments written before field presence was recorded fall back on
+    /// [`.docs_with_field(...)`](#method.docs_with_field), which is much slower.
+    pub fn num_docs_with_field(&self, field: Field) -> Result<DocId> {
+        if let Some(ref field_presence_composite) = self.field_presence_composite {
+            let field_presence = match field_presence_composite.open_read(field) {
+                Some(source) => FieldPresenceReader::open(source),
+                None => {
+                    return Ok(0);
+                }
+            };
+            if !self.delete_bitset.has_deletes() {
+                return Ok(field_presence.num_docs());
+            }
+            return Ok(field_presence.num_alive_docs(&self.delete_bitset));
+        }
+        Ok(self.docs_with_field(field)?.len() as DocId)
+    }
+
     /// Returns the document (or to be accurate, its stored field)
     /// bearing the given doc id.
     /// This method is slow and should seldom be called from
@@ -297,17 +602,140 @@ impl SegmentReader {
         self.segment_id
     }
 
+    /// Returns the version of the format the segment was written in.
+    ///
+    /// See the [`compat`](./compat/index.html) module.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
     /// Returns the bitset representing
     /// the documents that have been deleted.
     pub fn delete_bitset(&self) -> &DeleteBitSet {
         &self.delete_bitset
     }
 
+    /// Returns the number of bytes used by the per-field components
+    /// of the segment, as well as where their data is held.
+    pub fn space_usage(&self) -> SegmentSpaceUsage {
+        SegmentSpaceUsage::new(vec![
+            ComponentSpaceUsage::new(SegmentComponent::TERMS, &self.termdict_composite),
+            ComponentSpaceUsage::new(SegmentComponent::POSTINGS, &self.postings_composite),
+            ComponentSpaceUsage::new(SegmentComponent::POSITIONS, &self.positions_composite),
+            ComponentSpaceUsage::new(SegmentComponent::FASTFIELDS, &self.fast_fields_composite),
+            ComponentSpaceUsage::new(SegmentComponent::FIELDNORMS, &self.fieldnorms_composite),
+            ComponentSpaceUsage::new(
+                SegmentComponent::BLOOMFILTERS,
+                &self.bloom_filters_composite,
+            ),
+            ComponentSpaceUsage::new(
+                SegmentComponent::FIELDPRESENCE,
+                self.field_presence_composite
+                    .as_ref()
+                    .unwrap_or(&CompositeFile::empty()),
+            ),
+            ComponentSpaceUsage::new(
+                SegmentComponent::FACETLABELS,
+                &self.facet_labels_composite,
+            ),
+        ])
+    }
+
     /// Returns true iff the `doc` is marked
     /// as deleted.
     pub fn is_deleted(&self, doc: DocId) -> bool {
         self.delete_bitset.is_deleted(doc)
     }
+
+    // min value, max value and number of bits of a single or multivalued int fast field.
+    fn fast_field_summary<Item: FastValue + ToString>(
+        &self,
+        field: Field,
+    ) -> Option<(String, String, u8)> {
+        if let Ok(reader) = self.fast_field_reader::<Item>(field) {
+            Some((
+                reader.min_value().to_string(),
+                reader.max_value().to_string(),
+                reader.num_bits(),
+            ))
+        } else if let Ok(reader) = self.multi_fast_field_reader::<Item>(field) {
+            Some((
+                reader.min_value().to_string(),
+                reader.max_value().to_string(),
+                reader.num_bits(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Writes a human readable report about the content of the segment.
+    ///
+    /// The report contains the document counts and, for each field,
+    /// its number of terms, the terms with the highest document
+    /// frequency and, for single-valued int fast fields, the
+    /// minimum and maximum value.
+    ///
+    /// This is meant as a debugging tool. The format of the
+    /// report is not stable.
+    pub fn dump_summary<W: Write>(&self, schema: &Schema, write: &mut W) -> io::Result<()> {
+        writeln!(write, "segment: {}", self.segment_id.uuid_string())?;
+        writeln!(write, "max_doc: {}", self.max_doc())?;
+        writeln!(write, "num_docs: {}", self.num_docs())?;
+        writeln!(write, "num_deleted_docs: {}", self.num_deleted_docs())?;
+        for (field_id, field_entry) in schema.fields().iter().enumerate() {
+            let field = Field(field_id as u32);
+            let field_type = field_entry.field_type();
+            let field_type_name = match *field_type {
+                FieldType::Str(_) => "text",
+                FieldType::U64(_) => "u64",
+                FieldType::I64(_) => "i64",
+                FieldType::HierarchicalFacet => "facet",
+            };
+            writeln!(write, "field: {} ({})", field_entry.name(), field_type_name)?;
+            if field_entry.is_indexed() {
+                let inverted_index = self.inverted_index(field);
+                let term_dict = inverted_index.terms();
+                writeln!(write, "  num_terms: {}", term_dict.num_terms())?;
+                // min-heap keeping the most frequent terms, the smallest
+                // terms being preferred among terms with the same frequency.
+                let mut top_terms = BinaryHeap::with_capacity(NUM_TOP_TERMS + 1);
+                let mut term_stream = term_dict.stream();
+                while term_stream.advance() {
+                    let doc_freq = term_stream.value().doc_freq;
+                    top_terms.push(Reverse((doc_freq, Reverse(term_stream.key().to_owned()))));
+                    if top_terms.len() > NUM_TOP_TERMS {
+                        top_terms.pop();
+                    }
+                }
+                if !top_terms.is_empty() {
+                    writeln!(write, "  top_terms:")?;
+                }
+                // sorting the `Reverse` entries lists the most frequent terms first.
+                for Reverse((doc_freq, Reverse(term_bytes))) in top_terms.into_sorted_vec() {
+                    writeln!(
+                        write,
+                        "    {} doc_freq={}",
+                        value_bytes_to_string(field_type, &term_bytes),
+                        doc_freq
+                    )?;
+                }
+            }
+            let fast_field_summary_opt = match *field_type {
+                FieldType::U64(_) => self.fast_field_summary::<u64>(field),
+                FieldType::I64(_) => self.fast_field_summary::<i64>(field),
+                _ => None,
+            };
+            if let Some((min_value, max_value, num_bits)) = fast_field_summary_opt {
+                writeln!(
+                    write,
+                    "  fast_field: min={} max={} num_bits={}",
+                    min_value, max_value, num_bits
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Debug for SegmentReader {
@@ -315,3 +743,318 @@ impl fmt::Debug for SegmentReader {
         write!(f, "SegmentReader({:?})", self.segment_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use core::{Residency, SegmentComponent};
+    use schema::{Field, SchemaBuilder, Term, FAST, INT_INDEXED, STORED, STRING, TEXT};
+    use schema::{IndexRecordOption, TextFieldIndexing, TextOptions};
+    use query::{PhraseQuery, Query};
+    use collector::tests::TestCollector;
+    use postings::Postings;
+    use docset::{DocSet, SkipResult};
+    use futures::Future;
+
+    fn residency(index: &Index, component: SegmentComponent, field: Field) -> (usize, Residency) {
+        let searcher = index.searcher();
+        let space_usage = searcher.segment_reader(0).space_usage();
+        let field_space_usage = space_usage.component(component).unwrap().field(field)[0].clone();
+        (field_space_usage.num_bytes(), field_space_usage.residency())
+    }
+
+    fn check_search(index: &Index, small: Field, large: Field, num: Field) {
+        let searcher = index.searcher();
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(small, "a")), 1_000);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(large, "term42")), 1);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(large, "term1000")), 0);
+        let fast_field_reader = searcher
+            .segment_reader(0)
+            .fast_field_reader::<u64>(num)
+            .unwrap();
+        for doc in 0..1_000u32 {
+            assert_eq!(fast_field_reader.get(doc), u64::from(doc));
+        }
+    }
+
+    #[test]
+    fn test_eager_loading_threshold() {
+        let mut schema_builder = SchemaBuilder::default();
+        let small = schema_builder.add_text_field("small", STRING);
+        let large = schema_builder.add_text_field("large", STRING);
+        let num = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create_from_tempdir(schema_builder.build()).unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000u64 {
+                index_writer.add_document(doc!(
+                    small => "a",
+                    large => format!("term{}", i),
+                    num => i
+                )).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let (small_num_bytes, small_residency) =
+            residency(&index, SegmentComponent::TERMS, small);
+        let (large_num_bytes, large_residency) =
+            residency(&index, SegmentComponent::TERMS, large);
+        let (num_num_bytes, num_residency) = residency(&index, SegmentComponent::FASTFIELDS, num);
+        assert!(small_num_bytes < large_num_bytes);
+        assert_eq!(small_residency, Residency::Mmap);
+        assert_eq!(large_residency, Residency::Mmap);
+        assert_eq!(num_residency, Residency::Mmap);
+        check_search(&index, small, large, num);
+
+        let threshold = small_num_bytes + 1;
+        index.set_eager_loading_threshold(threshold);
+        index.load_searchers().unwrap();
+        assert_eq!(
+            residency(&index, SegmentComponent::TERMS, small),
+            (small_num_bytes, Residency::Anonymous)
+        );
+        assert_eq!(
+            residency(&index, SegmentComponent::TERMS, large),
+            (large_num_bytes, Residency::Mmap)
+        );
+        let expected_num_residency = if num_num_bytes < threshold {
+            Residency::Anonymous
+        } else {
+            Residency::Mmap
+        };
+        assert_eq!(
+            residency(&index, SegmentComponent::FASTFIELDS, num),
+            (num_num_bytes, expected_num_residency)
+        );
+        check_search(&index, small, large, num);
+
+        index.set_eager_loading_threshold(large_num_bytes + num_num_bytes + 1);
+        index.load_searchers().unwrap();
+        assert_eq!(
+            residency(&index, SegmentComponent::TERMS, large).1,
+            Residency::Anonymous
+        );
+        assert_eq!(
+            residency(&index, SegmentComponent::FASTFIELDS, num).1,
+            Residency::Anonymous
+        );
+        check_search(&index, small, large, num);
+    }
+
+    #[test]
+    fn test_value_position_offsets() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_value_position_offsets(true),
+        );
+        let text = schema_builder.add_text_field("text", text_options);
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(
+                text => "the quick fox",
+                text => "lazy dog",
+                text => "jumps over the moon"
+            )).unwrap();
+            index_writer.add_document(doc!(text => "moon")).unwrap();
+            index_writer.add_document(doc!(title => "moon")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            let segment_reader = searcher.segment_reader(0);
+            assert_eq!(segment_reader.value_position_offsets(text, 0).unwrap(), vec![0, 4, 7]);
+            assert_eq!(segment_reader.value_position_offsets(text, 1).unwrap(), vec![0]);
+            assert!(segment_reader.value_position_offsets(text, 2).unwrap().is_empty());
+            assert!(segment_reader.value_position_offsets(title, 0).is_err());
+
+            let mut test_collector = TestCollector::default();
+            let phrase_query = PhraseQuery::from(vec![
+                Term::from_field_text(text, "the"),
+                Term::from_field_text(text, "moon"),
+            ]);
+            phrase_query.search(&*searcher, &mut test_collector).unwrap();
+            assert_eq!(test_collector.docs(), vec![0]);
+            let mut postings = segment_reader
+                .inverted_index(text)
+                .read_postings(
+                    &Term::from_field_text(text, "the"),
+                    IndexRecordOption::WithFreqsAndPositions,
+                )
+                .unwrap();
+            assert_eq!(postings.skip_next(0), SkipResult::Reached);
+            assert_eq!(postings.positions(), &[0, 9]);
+            // the phrase starts at the second occurence of "the", in the third value.
+            let offsets = segment_reader.value_position_offsets(text, 0).unwrap();
+            let value_idx = offsets.iter().rposition(|&offset| offset <= 9);
+            assert_eq!(value_idx, Some(2));
+        }
+        // the offsets survive merges.
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a", text => "", text => "b c")).unwrap();
+            index_writer.commit().unwrap();
+            let segment_ids = index.searchable_segment_ids().unwrap();
+            index_writer.merge(&segment_ids).wait().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let mut all_offsets: Vec<Vec<u32>> = (0..4)
+            .map(|doc| segment_reader.value_position_offsets(text, doc).unwrap())
+            .collect();
+        all_offsets.sort();
+        assert_eq!(
+            all_offsets,
+            vec![vec![], vec![0], vec![0, 2, 2], vec![0, 4, 7]]
+        );
+    }
+
+    #[test]
+    fn test_no_fieldnorms_for_int_fields() {
+        let mut schema_builder = SchemaBuilder::default();
+        let num = schema_builder.add_u64_field("num", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..10u64 {
+                index_writer.add_document(doc!(num => i)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.get_fieldnorms_reader(num).is_none());
+        let space_usage = segment_reader.space_usage();
+        let fieldnorms_usage = space_usage.component(SegmentComponent::FIELDNORMS).unwrap();
+        assert!(fieldnorms_usage.fields().is_empty());
+        assert_eq!(segment_reader.segment_meta().total_num_tokens(num), None);
+        assert_eq!(searcher.doc_freq(&Term::from_field_u64(num, 3)), 1);
+    }
+
+    #[test]
+    fn test_dump_summary() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_i64_field("year", INT_INDEXED | FAST);
+        let isbn = schema_builder.add_text_field("isbn", STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(
+                title => "The old man and the sea and a boat",
+                year => 1952i64,
+                isbn => "0-684-80122-1"
+            )).unwrap();
+            index_writer.add_document(doc!(title => "Of Mice and Men", year => 1937i64)).unwrap();
+            index_writer
+                .add_document(doc!(title => "The Grapes of Wrath", year => 1939i64))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut report = vec![];
+        searcher
+            .segment_reader(0)
+            .dump_summary(&schema, &mut report)
+            .unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let mut lines = report.lines();
+        assert!(lines.next().unwrap().starts_with("segment: "));
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            vec![
+                "max_doc: 3",
+                "num_docs: 3",
+                "num_deleted_docs: 0",
+                "field: title (text)",
+                "  num_terms: 12",
+                "  top_terms:",
+                "    \"and\" doc_freq=2",
+                "    \"of\" doc_freq=2",
+                "    \"the\" doc_freq=2",
+                "    \"a\" doc_freq=1",
+                "    \"boat\" doc_freq=1",
+                "    \"grapes\" doc_freq=1",
+                "    \"man\" doc_freq=1",
+                "    \"men\" doc_freq=1",
+                "    \"mice\" doc_freq=1",
+                "    \"old\" doc_freq=1",
+                "field: year (i64)",
+                "  num_terms: 3",
+                "  top_terms:",
+                "    1937 doc_freq=1",
+                "    1939 doc_freq=1",
+                "    1952 doc_freq=1",
+                "  fast_field: min=1937 max=1952 num_bits=4",
+                "field: isbn (text)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_kind_mismatch_errors() {
+        use error::ErrorKind;
+        use schema::{Cardinality, IntOptions};
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let price = schema_builder.add_u64_field("price", INT_INDEXED);
+        let multi_options = IntOptions::default().set_fast(Cardinality::MultiValues);
+        let sizes = schema_builder.add_u64_field("sizes", multi_options);
+        let category = schema_builder.add_facet_field("category");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "a", price => 3u64, sizes => 4u64, category => "/a"))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+
+        assert!(segment_reader.facet_reader(category).is_ok());
+        for &(field, field_name) in &[(title, "title"), (price, "price"), (sizes, "sizes")] {
+            let error = segment_reader.facet_reader(field).err().unwrap();
+            match *error.kind() {
+                ErrorKind::NotAFacetField(ref name) => assert_eq!(name, field_name),
+                _ => panic!("Unexpected error {:?}", error),
+            }
+            assert_eq!(
+                error.to_string(),
+                format!("the field '{}' is not a hierarchical facet field", field_name)
+            );
+        }
+
+        let fast_field_error = |field: Field| {
+            segment_reader
+                .fast_field_reader::<u64>(field)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            fast_field_error(title),
+            "the field 'title' is a text field, and does not have \
+             a fast field of the requested type and cardinality"
+        );
+        let price_error = fast_field_error(price);
+        assert!(price_error.starts_with("the field 'price' is a u64 field that is not fast"));
+        let sizes_error = fast_field_error(sizes);
+        assert!(sizes_error.starts_with("the field 'sizes' is a multivalued u64 fast field"));
+        let multi_error = segment_reader.multi_fast_field_reader::<i64>(sizes).err().unwrap();
+        assert_eq!(multi_error.field_name(), "sizes");
+    }
+}
@@ -0,0 +1,141 @@
+use common::CompositeFile;
+use common::HasLen;
+use core::SegmentComponent;
+use schema::Field;
+
+/// Describes where the data of a file is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Residency {
+    /// The data is memory mapped, and only loaded
+    /// by the OS as it is accessed.
+    Mmap,
+    /// The data is held in anonymous memory.
+    Anonymous,
+}
+
+/// Space usage of the data associated to a field,
+/// within one of the components of a segment.
+#[derive(Debug, Clone)]
+pub struct FieldSpaceUsage {
+    field: Field,
+    idx: usize,
+    num_bytes: usize,
+    residency: Residency,
+}
+
+impl FieldSpaceUsage {
+    /// Returns the field.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the index of the file among the files of the field.
+    ///
+    /// For instance, multivalued fast fields are stored as two files:
+    /// the index (`0`) and the values (`1`).
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// Returns the number of bytes used by the file.
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Returns where the data of the file is held.
+    pub fn residency(&self) -> Residency {
+        self.residency
+    }
+}
+
+/// Space usage of one of the components of a segment.
+#[derive(Debug, Clone)]
+pub struct ComponentSpaceUsage {
+    component: SegmentComponent,
+    fields: Vec<FieldSpaceUsage>,
+}
+
+impl ComponentSpaceUsage {
+    pub(crate) fn new(
+        component: SegmentComponent,
+        composite_file: &CompositeFile,
+    ) -> ComponentSpaceUsage {
+        let fields = composite_file
+            .files()
+            .into_iter()
+            .map(|(field, idx, source)| FieldSpaceUsage {
+                field,
+                idx,
+                num_bytes: source.len(),
+                residency: if source.is_anonymous() {
+                    Residency::Anonymous
+                } else {
+                    Residency::Mmap
+                },
+            })
+            .collect();
+        ComponentSpaceUsage { component, fields }
+    }
+
+    /// Returns the component.
+    pub fn component(&self) -> SegmentComponent {
+        self.component
+    }
+
+    /// Returns the space usage of each of the fields,
+    /// sorted by field.
+    pub fn fields(&self) -> &[FieldSpaceUsage] {
+        &self.fields
+    }
+
+    /// Returns the space usage of the data of a given field.
+    pub fn field(&self, field: Field) -> Vec<&FieldSpaceUsage> {
+        self.fields
+            .iter()
+            .filter(|field_space_usage| field_space_usage.field == field)
+            .collect()
+    }
+
+    /// Returns the total number of bytes used by the component.
+    pub fn num_bytes(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|field_space_usage| field_space_usage.num_bytes)
+            .sum()
+    }
+}
+
+/// Space usage of a segment, as returned by
+/// [`SegmentReader::space_usage`](./struct.SegmentReader.html#method.space_usage).
+///
+/// Only the components that are partitioned by field are reported.
+#[derive(Debug, Clone)]
+pub struct SegmentSpaceUsage {
+    components: Vec<ComponentSpaceUsage>,
+}
+
+impl SegmentSpaceUsage {
+    pub(crate) fn new(components: Vec<ComponentSpaceUsage>) -> SegmentSpaceUsage {
+        SegmentSpaceUsage { components }
+    }
+
+    /// Returns the space usage of all of the reported components.
+    pub fn components(&self) -> &[ComponentSpaceUsage] {
+        &self.components
+    }
+
+    /// Returns the space usage of a given component, if it is reported.
+    pub fn component(&self, component: SegmentComponent) -> Option<&ComponentSpaceUsage> {
+        self.components
+            .iter()
+            .find(|component_space_usage| component_space_usage.component == component)
+    }
+
+    /// Returns the total number of bytes used by the reported components.
+    pub fn num_bytes(&self) -> usize {
+        self.components
+            .iter()
+            .map(|component_space_usage| component_space_usage.num_bytes())
+            .sum()
+    }
+}
@@ -0,0 +1,173 @@
+use core::Searcher;
+use schema::{Field, FieldType};
+use std::collections::HashMap;
+use std::str;
+use termdict::{edit_distance, LevenshteinAutomaton, TermStreamer, TermStreamerBuilder};
+
+/// A term of the index that is close to a possibly misspelled word,
+/// as returned by
+/// [`Searcher::suggest_terms`](./struct.Searcher.html#method.suggest_terms).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Suggestion {
+    term: String,
+    distance: u8,
+    doc_freq: u64,
+}
+
+impl Suggestion {
+    /// Returns the text of the term.
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    /// Returns the Levenshtein distance between the word
+    /// and the term, counted in chars.
+    pub fn distance(&self) -> u8 {
+        self.distance
+    }
+
+    /// Returns the number of documents containing the term,
+    /// over all of the segments.
+    ///
+    /// Deleted documents are counted until they are
+    /// expunged by a merge.
+    pub fn doc_freq(&self) -> u64 {
+        self.doc_freq
+    }
+}
+
+pub(crate) fn suggest_terms(
+    searcher: &Searcher,
+    field: Field,
+    input: &str,
+    max_distance: u8,
+    k: usize,
+) -> Vec<Suggestion> {
+    let field_entry = searcher.schema().get_field_entry(field);
+    match *field_entry.field_type() {
+        FieldType::Str(_) if field_entry.is_indexed() => {}
+        _ => {
+            return Vec::new();
+        }
+    }
+    // doc freqs of the same terms in the different segments are summed.
+    let mut doc_freqs: HashMap<String, u64> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field);
+        let automaton = LevenshteinAutomaton::new(input, max_distance);
+        let mut term_stream = inverted_index.terms().search(automaton).into_stream();
+        while term_stream.advance() {
+            if let Ok(term) = str::from_utf8(term_stream.key()) {
+                *doc_freqs.entry(term.to_string()).or_insert(0) +=
+                    u64::from(term_stream.doc_freq());
+            }
+        }
+    }
+    let mut suggestions: Vec<Suggestion> = doc_freqs
+        .into_iter()
+        .map(|(term, doc_freq)| Suggestion {
+            distance: edit_distance(input, &term) as u8,
+            term,
+            doc_freq,
+        })
+        .collect();
+    suggestions.sort_by(|left, right| {
+        left.distance
+            .cmp(&right.distance)
+            .then(right.doc_freq.cmp(&left.doc_freq))
+            .then_with(|| left.term.cmp(&right.term))
+    });
+    suggestions.truncate(k);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use Index;
+    use indexer::NoMergePolicy;
+    use schema::{SchemaBuilder, INT_INDEXED, STRING, TEXT};
+
+    #[test]
+    fn test_suggest_terms() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let num_field = schema_builder.add_u64_field("num", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        // `hello` and `help` appear in both segments.
+        for text in &["hello", "hello", "help", "hell", "yellow", "jello", "héllo"] {
+            index_writer.add_document(doc!(text_field => *text)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        for text in &["hello", "help", "help", "helm", "hollow", "world"] {
+            index_writer.add_document(doc!(text_field => *text)).unwrap();
+        }
+        index_writer
+            .add_document(doc!(id_field => "hello", num_field => 1u64))
+            .unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let suggestions = |word: &str, max_distance: u8, k: usize| -> Vec<(String, u8, u64)> {
+            searcher
+                .suggest_terms(text_field, word, max_distance, k)
+                .into_iter()
+                .map(|suggestion| {
+                    (
+                        suggestion.term().to_string(),
+                        suggestion.distance(),
+                        suggestion.doc_freq(),
+                    )
+                })
+                .collect()
+        };
+        let suggestion = |term: &str, distance: u8, doc_freq: u64| {
+            (term.to_string(), distance, doc_freq)
+        };
+
+        // ranked by distance, then by decreasing doc freq,
+        // then in the order of the terms.
+        assert_eq!(
+            suggestions("helo", 1, 10),
+            vec![
+                suggestion("hello", 1, 3),
+                suggestion("help", 1, 3),
+                suggestion("hell", 1, 1),
+                suggestion("helm", 1, 1),
+            ]
+        );
+        assert_eq!(
+            suggestions("hello", 2, 10),
+            vec![
+                suggestion("hello", 0, 3),
+                suggestion("hell", 1, 1),
+                suggestion("héllo", 1, 1),
+                suggestion("jello", 1, 1),
+                suggestion("help", 2, 3),
+                suggestion("helm", 2, 1),
+                suggestion("hollow", 2, 1),
+                suggestion("yellow", 2, 1),
+            ]
+        );
+        // the k cutoff.
+        assert_eq!(
+            suggestions("hello", 2, 3),
+            vec![
+                suggestion("hello", 0, 3),
+                suggestion("hell", 1, 1),
+                suggestion("héllo", 1, 1),
+            ]
+        );
+        assert!(suggestions("hello", 2, 0).is_empty());
+        assert_eq!(suggestions("hello", 0, 10), vec![suggestion("hello", 0, 3)]);
+        assert!(suggestions("qwerty", 2, 10).is_empty());
+
+        // the fields are not mixed up.
+        assert_eq!(searcher.suggest_terms(id_field, "helo", 1, 10).len(), 1);
+        assert!(searcher.suggest_terms(num_field, "1", 2, 10).is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use std::cmp;
+use std::io::{self, Write};
+use directory::ReadOnlySource;
+use super::stacker::hashmap::murmurhash2::murmurhash2_with_seed;
+
+const SEED_1: u32 = 1_540_483_477u32;
+const SEED_2: u32 = 2_246_822_519u32;
+
+/// Maximum number of hash functions used by a bloom filter.
+const MAX_NUM_HASHES: usize = 30;
+
+/// The smallest bloom filter is 64 bits long.
+const MIN_NUM_BITS: usize = 64;
+
+fn key_hashes(key: &[u8]) -> (u64, u64) {
+    (
+        u64::from(murmurhash2_with_seed(key, SEED_1)),
+        u64::from(murmurhash2_with_seed(key, SEED_2)),
+    )
+}
+
+/// Iterates over the positions of the bits associated
+/// to a key, using double hashing.
+fn bit_positions(
+    (h1, h2): (u64, u64),
+    num_hashes: usize,
+    num_bits: u64,
+) -> impl Iterator<Item = usize> {
+    (0..num_hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+}
+
+/// Builds a bloom filter over a set of keys.
+///
+/// The size of the filter is only known once all of the keys
+/// have been inserted, so the builder buffers a pair of hashes
+/// per key.
+pub struct BloomFilterBuilder {
+    num_bits_per_key: usize,
+    key_hashes: Vec<(u64, u64)>,
+}
+
+impl BloomFilterBuilder {
+    /// Creates a new `BloomFilterBuilder`, allocating `num_bits_per_key` bits
+    /// for each of the inserted keys.
+    ///
+    /// `10` bits per key result in a false positive rate of about 1%.
+    pub fn new(num_bits_per_key: usize) -> BloomFilterBuilder {
+        assert!(num_bits_per_key > 0);
+        BloomFilterBuilder {
+            num_bits_per_key,
+            key_hashes: Vec::new(),
+        }
+    }
+
+    /// Inserts a key in the filter.
+    pub fn insert(&mut self, key: &[u8]) {
+        self.key_hashes.push(key_hashes(key));
+    }
+
+    /// Serializes the filter.
+    ///
+    /// The format is the number of hash functions, encoded
+    /// on a single byte, followed by the bits of the filter.
+    pub fn serialize<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        let num_hashes = cmp::min(
+            cmp::max(1, (self.num_bits_per_key as f64 * 0.69).round() as usize),
+            MAX_NUM_HASHES,
+        );
+        let num_bytes = (cmp::max(MIN_NUM_BITS, self.key_hashes.len() * self.num_bits_per_key)
+            + 7) / 8;
+        let num_bits = (num_bytes * 8) as u64;
+        let mut bytes = vec![0u8; num_bytes];
+        for &hashes in &self.key_hashes {
+            for bit in bit_positions(hashes, num_hashes, num_bits) {
+                bytes[bit / 8] |= 1u8 << (bit % 8);
+            }
+        }
+        write.write_all(&[num_hashes as u8])?;
+        write.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/// Read-only bloom filter, as serialized by the `BloomFilterBuilder`.
+///
+/// A bloom filter answers whether a key may belong
+/// to a set. It never returns false negatives, but may
+/// return false positives.
+#[derive(Clone)]
+pub struct BloomFilter {
+    num_hashes: usize,
+    data: ReadOnlySource,
+}
+
+impl BloomFilter {
+    /// Opens a bloom filter.
+    pub fn open(source: ReadOnlySource) -> BloomFilter {
+        let num_hashes = source.as_slice()[0] as usize;
+        BloomFilter {
+            num_hashes,
+            data: source.slice_from(1),
+        }
+    }
+
+    /// Returns false if the key was definitely not inserted in the filter.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        let bytes = self.data.as_slice();
+        let num_bits = (bytes.len() * 8) as u64;
+        bit_positions(key_hashes(key), self.num_hashes, num_bits)
+            .all(|bit| bytes[bit / 8] & (1u8 << (bit % 8)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{BloomFilter, BloomFilterBuilder};
+    use directory::ReadOnlySource;
+
+    fn build(keys: &[String], num_bits_per_key: usize) -> BloomFilter {
+        let mut builder = BloomFilterBuilder::new(num_bits_per_key);
+        for key in keys {
+            builder.insert(key.as_bytes());
+        }
+        let mut buffer = vec![];
+        builder.serialize(&mut buffer).unwrap();
+        BloomFilter::open(ReadOnlySource::from(buffer))
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("key{}", i)).collect();
+        let bloom_filter = build(&keys, 10);
+        for key in &keys {
+            assert!(bloom_filter.may_contain(key.as_bytes()));
+        }
+        let num_false_positives = (0..10_000)
+            .map(|i| format!("other{}", i))
+            .filter(|key| bloom_filter.may_contain(key.as_bytes()))
+            .count();
+        assert!(num_false_positives < 300);
+    }
+
+    #[test]
+    fn test_bloom_filter_empty() {
+        let bloom_filter = build(&[], 10);
+        assert!(!bloom_filter.may_contain(b"key"));
+        assert!(!bloom_filter.may_contain(b""));
+    }
+}
@@ -1,4 +1,6 @@
 mod skip;
+mod bloom_filter;
 pub mod stacker;
 
 pub use self::skip::{SkipList, SkipListBuilder};
+pub use self::bloom_filter::{BloomFilter, BloomFilterBuilder};
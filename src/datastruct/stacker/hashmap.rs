@@ -1,20 +1,26 @@
+use std::cmp;
 use std::iter;
 use std::mem;
 use postings::UnorderedTermId;
 use super::heap::{BytesRef, Heap, HeapAllocable};
 
-mod murmurhash2 {
+pub(crate) mod murmurhash2 {
 
     const SEED: u32 = 3_242_157_231u32;
 
     #[inline(always)]
     pub fn murmurhash2(key: &[u8]) -> u32 {
+        murmurhash2_with_seed(key, SEED)
+    }
+
+    #[inline(always)]
+    pub fn murmurhash2_with_seed(key: &[u8], seed: u32) -> u32 {
         let mut key_ptr: *const u32 = key.as_ptr() as *const u32;
         let m: u32 = 0x5bd1_e995;
         let r = 24;
         let len = key.len() as u32;
 
-        let mut h: u32 = SEED ^ len;
+        let mut h: u32 = seed ^ len;
         let num_blocks = len >> 2;
         for _ in 0..num_blocks {
             let mut k: u32 = unsafe { *key_ptr };
@@ -102,13 +108,23 @@ impl KeyValue {
 /// the computation of the hash of the key twice,
 /// or copying the key as long as there is no insert.
 ///
+/// The table is doubled and rehashed as soon as its load factor
+/// exceeds `MAX_LOAD_FACTOR`. Keys and values live in the heap,
+/// so only the bucket array is reallocated. Its growth is charged
+/// to the heap, so that it counts against the same memory budget.
 pub struct TermHashMap<'a> {
     table: Box<[KeyValue]>,
     heap: &'a Heap,
     mask: usize,
     occupied: Vec<usize>,
+    num_rehashes: usize,
+    max_probe_len: usize,
 }
 
+/// The table is resized when `num_terms > MAX_LOAD_FACTOR * num_buckets`.
+/// The load factor is expressed in tenths.
+const MAX_LOAD_FACTOR_TENTHS: usize = 7;
+
 struct QuadraticProbing {
     hash: usize,
     i: usize,
@@ -131,15 +147,27 @@ impl QuadraticProbing {
     }
 }
 
+/// Memory used by a table of `table_size` buckets, and by the
+/// list of its occupied buckets once it is full.
+fn table_num_bytes(table_size: usize) -> usize {
+    table_size * mem::size_of::<KeyValue>() + table_size / 2 * mem::size_of::<usize>()
+}
+
+fn empty_table(table_size: usize) -> Box<[KeyValue]> {
+    let table: Vec<KeyValue> = iter::repeat(KeyValue::default()).take(table_size).collect();
+    table.into_boxed_slice()
+}
+
 impl<'a> TermHashMap<'a> {
     pub fn new(num_bucket_power_of_2: usize, heap: &'a Heap) -> TermHashMap<'a> {
         let table_size = 1 << num_bucket_power_of_2;
-        let table: Vec<KeyValue> = iter::repeat(KeyValue::default()).take(table_size).collect();
         TermHashMap {
-            table: table.into_boxed_slice(),
+            table: empty_table(table_size),
             heap,
             mask: table_size - 1,
             occupied: Vec::with_capacity(table_size / 2),
+            num_rehashes: 0,
+            max_probe_len: 0,
         }
     }
 
@@ -147,8 +175,52 @@ impl<'a> TermHashMap<'a> {
         QuadraticProbing::compute(hash as usize, self.mask)
     }
 
-    pub fn is_saturated(&self) -> bool {
-        self.table.len() < self.occupied.len() * 3
+    /// Returns the number of terms in the hash map.
+    pub fn len(&self) -> usize {
+        self.occupied.len()
+    }
+
+    /// Returns the number of buckets of the table.
+    pub fn num_buckets(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns the number of time the table has been resized.
+    pub fn num_rehashes(&self) -> usize {
+        self.num_rehashes
+    }
+
+    /// Returns the longest number of probes that was required
+    /// to find or insert a key.
+    pub fn max_probe_len(&self) -> usize {
+        self.max_probe_len
+    }
+
+    fn is_above_load_factor(&self) -> bool {
+        self.occupied.len() * 10 > self.table.len() * MAX_LOAD_FACTOR_TENTHS
+    }
+
+    /// Doubles the size of the table, and reinserts all of the
+    /// entries, preserving their insertion order.
+    fn resize(&mut self) {
+        let new_table_size = self.table.len() * 2;
+        self.heap
+            .charge(table_num_bytes(new_table_size) - table_num_bytes(self.table.len()));
+        let old_table = mem::replace(&mut self.table, empty_table(new_table_size));
+        let old_occupied = mem::replace(&mut self.occupied, Vec::with_capacity(new_table_size / 2));
+        self.mask = new_table_size - 1;
+        for old_bucket in old_occupied {
+            let kv = old_table[old_bucket];
+            let mut probe = self.probe(kv.hash);
+            loop {
+                let bucket = probe.next_probe();
+                if self.table[bucket].is_empty() {
+                    self.set_bucket(kv.hash, kv.key_value_addr, bucket);
+                    break;
+                }
+            }
+        }
+        self.num_rehashes += 1;
     }
 
     #[inline(never)]
@@ -165,14 +237,22 @@ impl<'a> TermHashMap<'a> {
         };
     }
 
+    /// Iterates over the `(key, value address, term id)` of the map,
+    /// in the order in which the keys were inserted.
     pub fn iter<'b: 'a>(&'b self) -> impl Iterator<Item = (&'a [u8], u32, UnorderedTermId)> + 'b {
         self.occupied.iter().cloned().map(move |bucket: usize| {
             let kv = self.table[bucket];
             let (key, offset) = self.get_key_value(kv.key_value_addr);
-            (key, offset, bucket as UnorderedTermId)
+            (key, offset, UnorderedTermId::from(kv.key_value_addr.addr()))
         })
     }
 
+    /// Returns the value associated to the key, after creating it
+    /// if it did not exist.
+    ///
+    /// The `UnorderedTermId` returned is the address of the key
+    /// in the heap. Unlike the bucket, it is not affected by
+    /// the resizing of the table.
     pub fn get_or_create<S: AsRef<[u8]>, V: HeapAllocable>(
         &mut self,
         key: S,
@@ -180,20 +260,28 @@ impl<'a> TermHashMap<'a> {
         let key_bytes: &[u8] = key.as_ref();
         let hash = murmurhash2::murmurhash2(key.as_ref());
         let mut probe = self.probe(hash);
+        let mut probe_len = 0;
         loop {
             let bucket = probe.next_probe();
+            probe_len += 1;
             let kv: KeyValue = self.table[bucket];
             if kv.is_empty() {
-                let key_bytes_ref = self.heap.allocate_and_set(key_bytes);
-                let (addr, val): (u32, &mut V) = self.heap.allocate_object();
+                self.max_probe_len = cmp::max(self.max_probe_len, probe_len);
+                let heap: &'a Heap = self.heap;
+                let key_bytes_ref = heap.allocate_and_set(key_bytes);
+                let (addr, val): (u32, &mut V) = heap.allocate_object();
                 assert_eq!(addr, key_bytes_ref.addr() + 2 + key_bytes.len() as u32);
                 self.set_bucket(hash, key_bytes_ref, bucket);
-                return (bucket as UnorderedTermId, val);
+                if self.is_above_load_factor() {
+                    self.resize();
+                }
+                return (UnorderedTermId::from(key_bytes_ref.addr()), val);
             } else if kv.hash == hash {
                 let (stored_key, expull_addr): (&[u8], u32) = self.get_key_value(kv.key_value_addr);
                 if stored_key == key_bytes {
+                    self.max_probe_len = cmp::max(self.max_probe_len, probe_len);
                     return (
-                        bucket as UnorderedTermId,
+                        UnorderedTermId::from(kv.key_value_addr.addr()),
                         self.heap.get_mut_ref(expull_addr),
                     );
                 }
@@ -269,6 +357,52 @@ mod tests {
         assert!(iter_values.next().is_none());
     }
 
+    #[test]
+    fn test_hash_map_resize() {
+        let heap = Heap::with_capacity(2_000_000);
+        let mut hash_map: TermHashMap = TermHashMap::new(2, &heap);
+        let mut term_ids = vec![];
+        for i in 0..1_000u32 {
+            let (term_id, v): (_, &mut TestValue) = hash_map.get_or_create(format!("k{}", i));
+            assert_eq!(v.val, 0u32);
+            v.val = i;
+            term_ids.push(term_id);
+        }
+        assert_eq!(hash_map.len(), 1_000);
+        assert_eq!(hash_map.num_buckets(), 2_048);
+        assert_eq!(hash_map.num_rehashes(), 9);
+        // the growth of the table is charged to the heap.
+        let num_used_bytes = heap.num_used_bytes() as usize;
+        assert_eq!(
+            heap.num_free_bytes() as usize,
+            2_000_000 - num_used_bytes - (table_num_bytes(2_048) - table_num_bytes(4))
+        );
+        for i in 0..1_000u32 {
+            let (term_id, v): (_, &mut TestValue) = hash_map.get_or_create(format!("k{}", i));
+            assert_eq!(v.val, i);
+            assert_eq!(term_id, term_ids[i as usize]);
+        }
+        assert_eq!(hash_map.len(), 1_000);
+        // the iteration order is the insertion order.
+        for (i, (key, addr, term_id)) in hash_map.iter().enumerate() {
+            assert_eq!(key, format!("k{}", i).as_bytes());
+            assert_eq!(heap.get_ref::<TestValue>(addr).val, i as u32);
+            assert_eq!(term_id, term_ids[i]);
+        }
+    }
+
+    #[test]
+    fn test_hash_map_max_probe_len() {
+        let heap = Heap::with_capacity(20_000_000);
+        let mut hash_map: TermHashMap = TermHashMap::new(4, &heap);
+        for i in 0..200_000u32 {
+            hash_map.get_or_create::<_, TestValue>(format!("unique{}", i));
+        }
+        assert_eq!(hash_map.len(), 200_000);
+        assert!(hash_map.len() * 10 <= hash_map.num_buckets() * 7);
+        assert!(hash_map.max_probe_len() < 100);
+    }
+
     #[test]
     fn test_murmur() {
         let s1 = "abcdef";
@@ -292,6 +426,23 @@ mod tests {
         assert_eq!(set.len(), 10_000);
     }
 
+    #[bench]
+    fn bench_hash_map_unique_terms(b: &mut Bencher) {
+        let keys: Vec<String> = (0..5_000_000).map(|i| format!("id{}", i)).collect();
+        let heap = Heap::with_capacity(200_000_000);
+        b.iter(|| {
+            heap.clear();
+            let mut hash_map: TermHashMap = TermHashMap::new(10, &heap);
+            for key in &keys {
+                hash_map.get_or_create::<_, TestValue>(key);
+            }
+            // 5M terms fit below the load factor in 2^23 buckets.
+            assert_eq!(hash_map.num_rehashes(), 13);
+            assert!(hash_map.max_probe_len() < 100);
+            hash_map.max_probe_len()
+        });
+    }
+
     #[bench]
     fn bench_murmurhash_2(b: &mut Bencher) {
         let keys: Vec<&'static str> =
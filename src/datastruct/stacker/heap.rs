@@ -62,6 +62,20 @@ impl Heap {
         self.inner().num_free_bytes()
     }
 
+    /// Returns the number of bytes allocated in the heap.
+    pub fn num_used_bytes(&self) -> u32 {
+        self.inner().num_used_bytes()
+    }
+
+    /// Charges the heap for memory allocated outside of it,
+    /// but within its budget.
+    ///
+    /// The charged bytes are deducted from the free bytes
+    /// of the heap until it is cleared.
+    pub fn charge(&self, num_bytes: usize) {
+        self.inner().charge(num_bytes);
+    }
+
     /// Allocate a given amount of space and returns an address
     /// in the Heap.
     pub fn allocate_space(&self, num_bytes: usize) -> u32 {
@@ -108,6 +122,7 @@ struct InnerHeap {
     buffer: Vec<u8>,
     buffer_len: u32,
     used: u32,
+    num_charged_bytes: usize,
     next_heap: Option<Box<InnerHeap>>,
 }
 
@@ -119,21 +134,35 @@ impl InnerHeap {
             buffer_len: num_bytes as u32,
             next_heap: None,
             used: 0u32,
+            num_charged_bytes: 0,
         }
     }
 
     pub fn clear(&mut self) {
         self.used = 0u32;
+        self.num_charged_bytes = 0;
         self.next_heap = None;
     }
 
-    // Returns the number of free bytes. If the buffer
+    pub fn charge(&mut self, num_bytes: usize) {
+        self.num_charged_bytes += num_bytes;
+    }
+
+    // Returns the number of free bytes, minus the charged bytes. If the buffer
     // has reached it's capacity and overflowed to another buffer, return 0.
     pub fn num_free_bytes(&self) -> u32 {
         if self.next_heap.is_some() {
             0u32
         } else {
-            self.buffer_len - self.used
+            let num_free_bytes = (self.buffer_len - self.used) as usize;
+            num_free_bytes.saturating_sub(self.num_charged_bytes) as u32
+        }
+    }
+
+    pub fn num_used_bytes(&self) -> u32 {
+        match self.next_heap {
+            Some(ref next_heap) => self.buffer_len + next_heap.num_used_bytes(),
+            None => self.used,
         }
     }
 
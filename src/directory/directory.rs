@@ -1,6 +1,6 @@
 use std::marker::Send;
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use directory::error::{DeleteError, OpenReadError, OpenWriteError};
 use directory::{ReadOnlySource, WritePtr};
 use std::result;
@@ -51,7 +51,8 @@ pub trait Directory: fmt::Debug + Send + Sync + 'static {
     /// to ensure that subsequent `read` operations
     /// will take into account preceding `write` operations.
     ///
-    /// Flush operation should also be persistent.
+    /// Flushed data does not need to be persistent before
+    /// the next call to [`sync`](#method.sync).
     ///
     /// The user shall not rely on `Drop` triggering `flush`.
     /// Note that `RAMDirectory` will panic! if `flush`
@@ -71,9 +72,43 @@ pub trait Directory: fmt::Debug + Send + Sync + 'static {
     /// This calls ensure that reads can never *observe*
     /// a partially written file.
     ///
+    /// Once `atomic_write` has returned, any call to `atomic_read`
+    /// or `open_read` on the same path that starts afterwards, from any thread
+    /// and on any clone of the directory, must observe the new content
+    /// (or the content of a later write).
+    /// `IndexWriter::commit` relies on this to make sure that
+    /// `Index::load_searchers` sees the commits that returned before it was called.
+    ///
     /// The file may or may not previously exist.
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()>;
 
+    /// Makes the files written and flushed through this directory,
+    /// or any of its clones, persistent.
+    ///
+    /// The `IndexWriter` calls this method on commit, right before
+    /// writing the `meta.json` file. This makes it possible to
+    /// issue all of the syncs at once rather than one per file.
+    ///
+    /// The default implementation does nothing, which is correct
+    /// for directories that persist data on flush.
+    fn sync(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Lists the files of the directory, as paths relative to it.
+    ///
+    /// [`Index::recover_meta`](../struct.Index.html#method.recover_meta)
+    /// relies on it to find the segment files.
+    ///
+    /// The default implementation returns an error, for the directories
+    /// that cannot list their files.
+    fn list_files(&self) -> io::Result<Vec<PathBuf>> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "This directory cannot list its files",
+        ))
+    }
+
     /// Clones the directory and boxes the clone
     fn box_clone(&self) -> Box<Directory>;
 }
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use common::HasLen;
+use directory::{Directory, ReadOnlySource, SeekableWrite, WritePtr};
+use directory::error::{DeleteError, OpenReadError, OpenWriteError};
+
+/// IO counters of one file of an `InstrumentedDirectory`.
+#[derive(Debug, Default)]
+struct FileCounters {
+    num_open_reads: AtomicUsize,
+    num_bytes_opened: AtomicU64,
+    num_open_writes: AtomicUsize,
+    num_bytes_written: AtomicU64,
+    num_atomic_reads: AtomicUsize,
+    num_bytes_atomic_read: AtomicU64,
+    num_atomic_writes: AtomicUsize,
+    num_bytes_atomic_written: AtomicU64,
+}
+
+impl FileCounters {
+    fn snapshot(&self) -> FileIoStats {
+        FileIoStats {
+            num_open_reads: self.num_open_reads.load(Ordering::Relaxed),
+            num_bytes_opened: self.num_bytes_opened.load(Ordering::Relaxed),
+            num_open_writes: self.num_open_writes.load(Ordering::Relaxed),
+            num_bytes_written: self.num_bytes_written.load(Ordering::Relaxed),
+            num_atomic_reads: self.num_atomic_reads.load(Ordering::Relaxed),
+            num_bytes_atomic_read: self.num_bytes_atomic_read.load(Ordering::Relaxed),
+            num_atomic_writes: self.num_atomic_writes.load(Ordering::Relaxed),
+            num_bytes_atomic_written: self.num_bytes_atomic_written.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.num_open_reads.store(0, Ordering::Relaxed);
+        self.num_bytes_opened.store(0, Ordering::Relaxed);
+        self.num_open_writes.store(0, Ordering::Relaxed);
+        self.num_bytes_written.store(0, Ordering::Relaxed);
+        self.num_atomic_reads.store(0, Ordering::Relaxed);
+        self.num_bytes_atomic_read.store(0, Ordering::Relaxed);
+        self.num_atomic_writes.store(0, Ordering::Relaxed);
+        self.num_bytes_atomic_written.store(0, Ordering::Relaxed);
+    }
+}
+
+/// IO statistics of one file of an `InstrumentedDirectory`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileIoStats {
+    /// Number of calls to `open_read`.
+    pub num_open_reads: usize,
+    /// Number of bytes of the `ReadOnlySource`s returned by `open_read`,
+    /// that is the length of the file for each call.
+    ///
+    /// This is an upper bound of the number of bytes actually read:
+    /// the accesses to the data of a source are not recorded.
+    pub num_bytes_opened: u64,
+    /// Number of calls to `open_write`.
+    pub num_open_writes: usize,
+    /// Number of bytes written to the `WritePtr` returned by `open_write`.
+    pub num_bytes_written: u64,
+    /// Number of calls to `atomic_read`.
+    pub num_atomic_reads: usize,
+    /// Number of bytes returned by `atomic_read`.
+    pub num_bytes_atomic_read: u64,
+    /// Number of calls to `atomic_write`.
+    pub num_atomic_writes: usize,
+    /// Number of bytes written by `atomic_write`.
+    pub num_bytes_atomic_written: u64,
+}
+
+impl FileIoStats {
+    fn add(&mut self, other: &FileIoStats) {
+        self.num_open_reads += other.num_open_reads;
+        self.num_bytes_opened += other.num_bytes_opened;
+        self.num_open_writes += other.num_open_writes;
+        self.num_bytes_written += other.num_bytes_written;
+        self.num_atomic_reads += other.num_atomic_reads;
+        self.num_bytes_atomic_read += other.num_bytes_atomic_read;
+        self.num_atomic_writes += other.num_atomic_writes;
+        self.num_bytes_atomic_written += other.num_bytes_atomic_written;
+    }
+}
+
+/// Snapshot of the IO statistics of an `InstrumentedDirectory`,
+/// per file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IoStats {
+    files: HashMap<PathBuf, FileIoStats>,
+}
+
+impl IoStats {
+    /// Returns the statistics of the file at `path`.
+    ///
+    /// The statistics of a file without any IO are all `0`.
+    pub fn file(&self, path: &Path) -> FileIoStats {
+        self.files.get(path).cloned().unwrap_or_default()
+    }
+
+    /// Returns the statistics summed over all of the files.
+    pub fn total(&self) -> FileIoStats {
+        let mut total = FileIoStats::default();
+        for file_stats in self.files.values() {
+            total.add(file_stats);
+        }
+        total
+    }
+
+    /// Iterates over the files with some IO, and their statistics.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a Path, &'a FileIoStats)> {
+        self.files
+            .iter()
+            .map(|(path, file_stats)| (path.as_path(), file_stats))
+    }
+}
+
+/// Write recording the number of bytes written to a file.
+struct InstrumentedWrite {
+    write: WritePtr,
+    counters: Arc<FileCounters>,
+}
+
+impl Write for InstrumentedWrite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = self.write.write(buf)?;
+        self.counters
+            .num_bytes_written
+            .fetch_add(num_bytes as u64, Ordering::Relaxed);
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+impl Seek for InstrumentedWrite {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.write.seek(pos)
+    }
+}
+
+/// Directory wrapping another `Directory`, and recording
+/// the IOs on each of its files.
+///
+/// The writes are recorded as they go through the `WritePtr`s.
+///
+/// The reads are recorded when the files are opened: `open_read`
+/// returns a `ReadOnlySource` giving access to the whole file, memory
+/// mapped or held in anonymous memory, whose data is then accessed
+/// in place. Each call to `open_read` is therefore recorded with the
+/// length of the file, whether its data is then accessed or not.
+/// In particular, the `ReadOnlySource`s of a `SegmentReader` are all
+/// opened, once, when it is opened.
+///
+/// The statistics are shared by all of the clones of the directory,
+/// so that it is possible to keep a clone of it to observe an `Index`.
+/// Recording them only costs a few atomic increments.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::directory::{InstrumentedDirectory, RAMDirectory};
+/// use tantivy::schema::{SchemaBuilder, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let directory = InstrumentedDirectory::new(RAMDirectory::create());
+/// let index = Index::create_in_directory(directory.clone(), schema_builder.build())?;
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "The Old Man and the Sea"))?;
+///     index_writer.commit()?;
+/// }
+/// assert!(directory.snapshot().total().num_bytes_written > 0);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct InstrumentedDirectory<D: Directory> {
+    directory: D,
+    files: Arc<RwLock<HashMap<PathBuf, Arc<FileCounters>>>>,
+}
+
+impl<D: Directory> InstrumentedDirectory<D> {
+    /// Wraps `directory`, with all of its statistics set to `0`.
+    pub fn new(directory: D) -> InstrumentedDirectory<D> {
+        InstrumentedDirectory {
+            directory,
+            files: Arc::default(),
+        }
+    }
+
+    /// Returns the wrapped directory.
+    pub fn inner(&self) -> &D {
+        &self.directory
+    }
+
+    /// Returns the statistics recorded since the creation
+    /// of the directory, or since the last call to `reset`.
+    pub fn snapshot(&self) -> IoStats {
+        let files = self.files
+            .read()
+            .expect("IO stats lock is poisoned.")
+            .iter()
+            .map(|(path, counters)| (path.clone(), counters.snapshot()))
+            .filter(|&(_, ref file_stats)| *file_stats != FileIoStats::default())
+            .collect();
+        IoStats { files }
+    }
+
+    /// Sets all of the statistics to `0`.
+    ///
+    /// The sources and writers already open keep on being recorded.
+    pub fn reset(&self) {
+        for counters in self.files
+            .read()
+            .expect("IO stats lock is poisoned.")
+            .values()
+        {
+            counters.reset();
+        }
+    }
+
+    fn counters(&self, path: &Path) -> Arc<FileCounters> {
+        if let Some(counters) = self.files
+            .read()
+            .expect("IO stats lock is poisoned.")
+            .get(path)
+        {
+            return Arc::clone(counters);
+        }
+        let mut files = self.files.write().expect("IO stats lock is poisoned.");
+        Arc::clone(files.entry(path.to_owned()).or_insert_with(Arc::default))
+    }
+}
+
+impl<D: Directory + Clone> Directory for InstrumentedDirectory<D> {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        let source = self.directory.open_read(path)?;
+        let counters = self.counters(path);
+        counters.num_open_reads.fetch_add(1, Ordering::Relaxed);
+        counters
+            .num_bytes_opened
+            .fetch_add(source.len() as u64, Ordering::Relaxed);
+        Ok(source)
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        self.directory.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        let write = self.directory.open_write(path)?;
+        let counters = self.counters(path);
+        counters.num_open_writes.fetch_add(1, Ordering::Relaxed);
+        let instrumented_write: Box<SeekableWrite> = box InstrumentedWrite { write, counters };
+        // the wrapped `WritePtr` is already buffered.
+        Ok(BufWriter::with_capacity(0, instrumented_write))
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        let data = self.directory.atomic_read(path)?;
+        let counters = self.counters(path);
+        counters.num_atomic_reads.fetch_add(1, Ordering::Relaxed);
+        counters
+            .num_bytes_atomic_read
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        Ok(data)
+    }
+
+    fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.directory.atomic_write(path, data)?;
+        let counters = self.counters(path);
+        counters.num_atomic_writes.fetch_add(1, Ordering::Relaxed);
+        counters
+            .num_bytes_atomic_written
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        self.directory.sync()
+    }
+
+    fn list_files(&self) -> io::Result<Vec<PathBuf>> {
+        self.directory.list_files()
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        box self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use directory::{MmapDirectory, RAMDirectory};
+    use schema::{SchemaBuilder, Term, STORED, TEXT};
+    use query::TermQuery;
+    use schema::IndexRecordOption;
+    use collector::CountCollector;
+    use Index;
+
+    fn write_file(directory: &mut Directory, path: &Path, data: &[u8]) {
+        let mut write = directory.open_write(path).unwrap();
+        write.write_all(&data[..3]).unwrap();
+        write.write_all(&data[3..]).unwrap();
+        write.flush().unwrap();
+    }
+
+    fn test_read_write_stats<D: Directory + Clone>(directory: D) {
+        let path = Path::new("file");
+        let other_path = Path::new("other_file");
+        let mut directory = InstrumentedDirectory::new(directory);
+        write_file(&mut directory, path, &[0u8; 100]);
+        let stats = directory.snapshot().file(path);
+        assert_eq!(stats.num_open_writes, 1);
+        assert_eq!(stats.num_bytes_written, 100);
+        assert_eq!(stats.num_open_reads, 0);
+
+        let source = directory.open_read(path).unwrap();
+        assert_eq!(source.len(), 100);
+        assert_eq!(directory.snapshot().file(path).num_bytes_opened, 100);
+        // the accesses to the source are not recorded.
+        let (left, right) = source.slice(10, 60).split(20);
+        assert_eq!(left.slice_from(5).len(), 15);
+        assert_eq!(right.as_slice(), &[0u8; 30][..]);
+        let stats = directory.snapshot().file(path);
+        assert_eq!(stats.num_open_reads, 1);
+        assert_eq!(stats.num_bytes_opened, 100);
+        assert_eq!(directory.snapshot().file(other_path), FileIoStats::default());
+
+        directory.atomic_write(other_path, b"abcd").unwrap();
+        assert_eq!(directory.atomic_read(other_path).unwrap(), b"abcd");
+        let other_stats = directory.snapshot().file(other_path);
+        assert_eq!(other_stats.num_atomic_writes, 1);
+        assert_eq!(other_stats.num_bytes_atomic_written, 4);
+        assert_eq!(other_stats.num_atomic_reads, 1);
+        assert_eq!(other_stats.num_bytes_atomic_read, 4);
+        assert_eq!(directory.snapshot().total().num_open_reads, 1);
+
+        directory.reset();
+        assert_eq!(directory.snapshot(), IoStats::default());
+        let _ = source.slice_to(7);
+        assert_eq!(directory.snapshot(), IoStats::default());
+        let _ = directory.open_read(path).unwrap();
+        assert_eq!(directory.snapshot().file(path).num_bytes_opened, 100);
+        assert_eq!(directory.snapshot().iter().count(), 1);
+    }
+
+    #[test]
+    fn test_instrumented_ram_directory() {
+        test_read_write_stats(RAMDirectory::create());
+    }
+
+    #[test]
+    fn test_instrumented_mmap_directory() {
+        test_read_write_stats(MmapDirectory::create_from_tempdir().unwrap());
+    }
+
+    #[test]
+    fn test_nested_instrumented_directory() {
+        let path = Path::new("file");
+        let inner_directory = InstrumentedDirectory::new(RAMDirectory::create());
+        let mut directory = InstrumentedDirectory::new(inner_directory.clone());
+        write_file(&mut directory, path, &[1u8; 10]);
+        let source = directory.open_read(path).unwrap();
+        assert_eq!(source.as_slice(), &[1u8; 10]);
+        let inner_source = inner_directory.open_read(path).unwrap();
+        assert_eq!(inner_source.slice_to(3).as_slice(), &[1u8; 3]);
+
+        let stats = directory.snapshot().file(path);
+        assert_eq!(stats.num_open_writes, 1);
+        assert_eq!(stats.num_bytes_written, 10);
+        assert_eq!(stats.num_open_reads, 1);
+        assert_eq!(stats.num_bytes_opened, 10);
+        let inner_stats = inner_directory.snapshot().file(path);
+        assert_eq!(inner_stats.num_open_writes, 1);
+        assert_eq!(inner_stats.num_bytes_written, 10);
+        assert_eq!(inner_stats.num_open_reads, 2);
+        assert_eq!(inner_stats.num_bytes_opened, 20);
+    }
+
+    #[test]
+    fn test_instrumented_directory_search() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT | STORED);
+        let directory = InstrumentedDirectory::new(RAMDirectory::create());
+        let index = Index::create_in_directory(directory.clone(), schema_builder.build()).unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a b")).unwrap();
+            index_writer.add_document(doc!(text => "b c")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        assert!(directory.snapshot().file(Path::new("meta.json")).num_atomic_writes > 0);
+        directory.reset();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let has_extension = |path: &Path, extension: &str| {
+            path.extension().map(|ext| ext == extension).unwrap_or(false)
+        };
+        // the files of the segment are all opened with the segment reader.
+        let stats = directory.snapshot();
+        for extension in &["idx", "store"] {
+            assert!(stats.iter().any(|(path, file_stats)| {
+                has_extension(path, extension) && file_stats.num_bytes_opened > 0
+            }));
+        }
+
+        directory.reset();
+        let query = TermQuery::new(Term::from_field_text(text, "b"), IndexRecordOption::Basic);
+        let mut count_collector = CountCollector::default();
+        searcher.search(&query, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 2);
+        // the search accesses the data of the sources opened above.
+        let total_stats = directory.snapshot().total();
+        assert_eq!(total_stats.num_open_reads, 0);
+        assert_eq!(total_stats.num_bytes_opened, 0);
+    }
+}
@@ -264,6 +264,14 @@ impl Directory for ManagedDirectory {
         self.directory.exists(path)
     }
 
+    fn sync(&self) -> io::Result<()> {
+        self.directory.sync()
+    }
+
+    fn list_files(&self) -> io::Result<Vec<PathBuf>> {
+        self.directory.list_files()
+    }
+
     fn box_clone(&self) -> Box<Directory> {
         box self.clone()
     }
@@ -17,7 +17,7 @@ use std::io::{BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::result;
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{Mutex, MutexGuard, RwLock};
 use tempdir::TempDir;
 
 /// Returns None iff the file exists, can be read, but is empty (and hence
@@ -60,6 +60,39 @@ pub struct CacheInfo {
     pub mmapped: Vec<PathBuf>,
 }
 
+/// Counters describing the writes performed through
+/// a `MmapDirectory` and its clones.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct WriteCounters {
+    /// Number of files opened for write.
+    pub num_files_written: usize,
+    /// Number of bytes written to the files.
+    pub num_bytes_written: usize,
+    /// Number of `write` calls issued to the files.
+    pub num_write_calls: usize,
+    /// Number of syncs of a file.
+    pub num_file_syncs: usize,
+    /// Number of syncs of the directory itself.
+    pub num_directory_syncs: usize,
+}
+
+/// Files that were flushed but not synced yet.
+#[derive(Default)]
+struct WriteState {
+    counters: WriteCounters,
+    unsynced_files: HashMap<PathBuf, File>,
+    // true iff files were created since the last sync of the directory.
+    directory_unsynced: bool,
+}
+
+fn lock_write_state(write_state: &Mutex<WriteState>) -> MutexGuard<WriteState> {
+    write_state.lock().expect("Write state lock is poisoned.")
+}
+
+/// Size of the buffer of the `WritePtr`, unless
+/// configured otherwise with `MmapDirectory::with_write_buffer_size`.
+const DEFAULT_WRITE_BUFFER_SIZE: usize = 8 * 1024;
+
 struct MmapCache {
     counters: CacheCounters,
     cache: HashMap<PathBuf, MmapReadOnly>,
@@ -116,6 +149,8 @@ impl MmapCache {
 pub struct MmapDirectory {
     root_path: PathBuf,
     mmap_cache: Arc<RwLock<MmapCache>>,
+    write_state: Arc<Mutex<WriteState>>,
+    write_buffer_size: usize,
     _temp_directory: Arc<Option<TempDir>>,
 }
 
@@ -136,6 +171,8 @@ impl MmapDirectory {
         let directory = MmapDirectory {
             root_path: tempdir_path,
             mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
+            write_state: Arc::new(Mutex::new(WriteState::default())),
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
             _temp_directory: Arc::new(Some(tempdir)),
         };
         Ok(directory)
@@ -159,11 +196,23 @@ impl MmapDirectory {
             Ok(MmapDirectory {
                 root_path: PathBuf::from(directory_path),
                 mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
+                write_state: Arc::new(Mutex::new(WriteState::default())),
+                write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
                 _temp_directory: Arc::new(None),
             })
         }
     }
 
+    /// Sets the size of the buffer of the `WritePtr` returned by `open_write`.
+    ///
+    /// Defaults to 8KB. Larger buffers reduce the number of write
+    /// calls, which helps on filesystems with a large per-call
+    /// overhead, such as network filesystems.
+    pub fn with_write_buffer_size(mut self, write_buffer_size: usize) -> MmapDirectory {
+        self.write_buffer_size = write_buffer_size;
+        self
+    }
+
     /// Joins a relative_path to the directory `root_path`
     /// to create a proper complete `filepath`.
     fn resolve_path(&self, relative_path: &Path) -> PathBuf {
@@ -194,9 +243,20 @@ impl MmapDirectory {
 
         let fd = open_opts.open(&self.root_path)?;
         fd.sync_all()?;
+        self.lock_write_state().counters.num_directory_syncs += 1;
         Ok(())
     }
 
+    fn lock_write_state(&self) -> MutexGuard<WriteState> {
+        lock_write_state(&self.write_state)
+    }
+
+    /// Returns the counters of the writes performed
+    /// through this directory and its clones.
+    pub fn get_write_counters(&self) -> WriteCounters {
+        self.lock_write_state().counters.clone()
+    }
+
     /// Returns some statistical information
     /// about the Mmap cache.
     ///
@@ -211,29 +271,51 @@ impl MmapDirectory {
 }
 
 /// This Write wraps a File, but has the specificity of
-/// call `sync_all` on flush.
-struct SafeFileWriter(File);
+/// registering the file to be synced on the next call
+/// to `Directory::sync` on flush.
+struct SafeFileWriter {
+    path: PathBuf,
+    file: File,
+    write_state: Arc<Mutex<WriteState>>,
+}
 
 impl SafeFileWriter {
-    fn new(file: File) -> SafeFileWriter {
-        SafeFileWriter(file)
+    fn new(path: PathBuf, file: File, write_state: Arc<Mutex<WriteState>>) -> SafeFileWriter {
+        SafeFileWriter {
+            path,
+            file,
+            write_state,
+        }
+    }
+
+    fn lock_write_state(&self) -> MutexGuard<WriteState> {
+        lock_write_state(&self.write_state)
     }
 }
 
 impl Write for SafeFileWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        let num_bytes = self.file.write(buf)?;
+        let mut write_state = self.lock_write_state();
+        write_state.counters.num_write_calls += 1;
+        write_state.counters.num_bytes_written += num_bytes;
+        Ok(num_bytes)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()?;
-        self.0.sync_all()
+        self.file.flush()?;
+        let mut write_state = self.lock_write_state();
+        if !write_state.unsynced_files.contains_key(&self.path) {
+            let file = self.file.try_clone()?;
+            write_state.unsynced_files.insert(self.path.clone(), file);
+        }
+        Ok(())
     }
 }
 
 impl Seek for SafeFileWriter {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.0.seek(pos)
+        self.file.seek(pos)
     }
 }
 
@@ -278,13 +360,19 @@ impl Directory for MmapDirectory {
         file.flush()
             .map_err(|e| IOError::with_path(path.to_owned(), e))?;
 
-        // Apparetntly, on some filesystem syncing the parent
-        // directory is required.
-        self.sync_directory()
-            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        {
+            let mut write_state = self.lock_write_state();
+            write_state.counters.num_files_written += 1;
+            // Apparetntly, on some filesystem syncing the parent
+            // directory is required. This is deferred to the next `sync`.
+            write_state.directory_unsynced = true;
+        }
 
-        let writer = SafeFileWriter::new(file);
-        Ok(BufWriter::new(Box::new(writer)))
+        let writer = SafeFileWriter::new(path.to_owned(), file, Arc::clone(&self.write_state));
+        Ok(BufWriter::with_capacity(
+            self.write_buffer_size,
+            Box::new(writer),
+        ))
     }
 
     /// Any entry associated to the path in the mmap will be
@@ -301,6 +389,8 @@ impl Directory for MmapDirectory {
             IOError::with_path(path.to_owned(), make_io_err(msg))
         })?;
         mmap_cache.discard_from_cache(path);
+        // a deleted file does not need to be synced anymore.
+        self.lock_write_state().unsynced_files.remove(path);
 
         // Removing the entry in the MMap cache.
         // The munmap will appear on Drop,
@@ -343,6 +433,10 @@ impl Directory for MmapDirectory {
         }
     }
 
+    /// The data is written to a temporary file, which is then
+    /// renamed over `path`. The rename being atomic, `atomic_read`
+    /// either opens the former file or the new one, and the
+    /// new one as soon as this method has returned.
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
         debug!("Atomic Write {:?}", path);
         let full_path = self.resolve_path(path);
@@ -351,6 +445,50 @@ impl Directory for MmapDirectory {
         Ok(())
     }
 
+    /// All of the files flushed since the last call are synced first,
+    /// then the directory is synced once, if files were created.
+    fn sync(&self) -> io::Result<()> {
+        let (unsynced_files, directory_unsynced) = {
+            let mut write_state = self.lock_write_state();
+            let unsynced_files: Vec<(PathBuf, File)> =
+                write_state.unsynced_files.drain().collect();
+            let directory_unsynced = write_state.directory_unsynced;
+            write_state.directory_unsynced = false;
+            (unsynced_files, directory_unsynced)
+        };
+        let mut unsynced_files_it = unsynced_files.into_iter();
+        while let Some((path, file)) = unsynced_files_it.next() {
+            debug!("Sync {:?}", path);
+            if let Err(err) = file.sync_all() {
+                // the files that were not synced are synced on the next call.
+                let mut write_state = self.lock_write_state();
+                write_state.unsynced_files.insert(path, file);
+                write_state.unsynced_files.extend(unsynced_files_it);
+                write_state.directory_unsynced |= directory_unsynced;
+                return Err(err);
+            }
+            self.lock_write_state().counters.num_file_syncs += 1;
+        }
+        if directory_unsynced {
+            if let Err(err) = self.sync_directory() {
+                self.lock_write_state().directory_unsynced = true;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn list_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.root_path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(PathBuf::from(entry.file_name()));
+            }
+        }
+        Ok(paths)
+    }
+
     fn box_clone(&self) -> Box<Directory> {
         Box::new(self.clone())
     }
@@ -420,4 +558,65 @@ mod tests {
         assert_eq!(mmap_directory.get_cache_info().mmapped.len(), 0);
     }
 
+    #[test]
+    fn test_write_buffer_size() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir()
+            .unwrap()
+            .with_write_buffer_size(16);
+        let mut w = mmap_directory.open_write(Path::new("test")).unwrap();
+        w.write_all(&[1u8; 10]).unwrap();
+        assert_eq!(mmap_directory.get_write_counters().num_bytes_written, 0);
+        // the buffer is full: its content is written, and the new data is buffered.
+        w.write_all(&[2u8; 10]).unwrap();
+        {
+            let counters = mmap_directory.get_write_counters();
+            assert_eq!(counters.num_write_calls, 1);
+            assert_eq!(counters.num_bytes_written, 10);
+        }
+        // data larger than the buffer bypasses it.
+        w.write_all(&[3u8; 100]).unwrap();
+        w.flush().unwrap();
+        let counters = mmap_directory.get_write_counters();
+        assert_eq!(counters.num_write_calls, 3);
+        assert_eq!(counters.num_bytes_written, 120);
+        assert_eq!(counters.num_files_written, 1);
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir().unwrap();
+        for i in 0..3 {
+            let path = PathBuf::from(format!("file_{}", i));
+            let mut w = mmap_directory.open_write(&path).unwrap();
+            w.write_all(&[1u8, 2u8]).unwrap();
+            w.flush().unwrap();
+            // flushing several times does not sync the file several times.
+            w.write_all(&[3u8]).unwrap();
+            w.flush().unwrap();
+        }
+        {
+            let counters = mmap_directory.get_write_counters();
+            assert_eq!(counters.num_files_written, 3);
+            assert_eq!(counters.num_bytes_written, 9);
+            assert_eq!(counters.num_file_syncs, 0);
+            assert_eq!(counters.num_directory_syncs, 0);
+        }
+        // syncs are shared by the clones.
+        mmap_directory.box_clone().sync().unwrap();
+        {
+            let counters = mmap_directory.get_write_counters();
+            assert_eq!(counters.num_file_syncs, 3);
+            assert_eq!(counters.num_directory_syncs, 1);
+        }
+        // nothing left to sync.
+        mmap_directory.sync().unwrap();
+        {
+            let counters = mmap_directory.get_write_counters();
+            assert_eq!(counters.num_file_syncs, 3);
+            assert_eq!(counters.num_directory_syncs, 1);
+        }
+        let data = mmap_directory.open_read(Path::new("file_1")).unwrap();
+        assert_eq!(data.as_slice(), &[1u8, 2u8, 3u8]);
+    }
+
 }
@@ -9,6 +9,7 @@ mod directory;
 mod read_only_source;
 mod shared_vec_slice;
 mod managed_directory;
+mod instrumented_directory;
 
 /// Errors specific to the directory module.
 pub mod error;
@@ -18,7 +19,8 @@ use std::io::{BufWriter, Seek, Write};
 pub use self::read_only_source::ReadOnlySource;
 pub use self::directory::Directory;
 pub use self::ram_directory::RAMDirectory;
-pub use self::mmap_directory::MmapDirectory;
+pub use self::mmap_directory::{MmapDirectory, WriteCounters};
+pub use self::instrumented_directory::{FileIoStats, InstrumentedDirectory, IoStats};
 
 pub(crate) use self::read_only_source::SourceRead;
 pub(crate) use self::managed_directory::{FileProtection, ManagedDirectory};
@@ -39,6 +41,9 @@ mod tests {
     use super::*;
     use std::path::Path;
     use std::io::{Seek, SeekFrom, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
 
     lazy_static! {
         static ref TEST_PATH: &'static Path = Path::new("some_path_for_test");
@@ -56,6 +61,12 @@ mod tests {
         test_directory(&mut mmap_directory);
     }
 
+    #[test]
+    fn test_instrumented_directory() {
+        let mut instrumented_directory = InstrumentedDirectory::new(RAMDirectory::create());
+        test_directory(&mut instrumented_directory);
+    }
+
     #[test]
     #[should_panic]
     fn ram_directory_panics_if_flush_forgotten() {
@@ -149,12 +160,61 @@ mod tests {
         assert!(directory.delete(*TEST_PATH).is_err());
     }
 
+    // Concurrent readers never observe a partially written file,
+    // and observe a write as soon as `atomic_write` has returned.
+    fn test_atomic_write_visibility(directory: &mut Directory) {
+        let path = Path::new("atomic_file");
+        let content = |i: u8| vec![i; 10_000];
+        directory.atomic_write(path, &content(0)).unwrap();
+        let last_written = Arc::new(AtomicBool::new(false));
+        let reader_thread = {
+            let directory = directory.box_clone();
+            let last_written = Arc::clone(&last_written);
+            thread::spawn(move || {
+                let mut previous: u8 = 0;
+                loop {
+                    let done = last_written.load(Ordering::SeqCst);
+                    let data = directory.atomic_read(path).unwrap();
+                    assert_eq!(data.len(), 10_000);
+                    assert!(data.iter().all(|&b| b == data[0]));
+                    assert!(data[0] >= previous);
+                    previous = data[0];
+                    if done {
+                        assert_eq!(previous, 100);
+                        break;
+                    }
+                }
+            })
+        };
+        for i in 1..101 {
+            directory.atomic_write(path, &content(i)).unwrap();
+        }
+        last_written.store(true, Ordering::SeqCst);
+        reader_thread.join().unwrap();
+    }
+
+    fn test_list_files(directory: &mut Directory) {
+        let path = Path::new("listed_file");
+        let is_listed =
+            |directory: &Directory| directory.list_files().unwrap().contains(&path.to_path_buf());
+        assert!(!is_listed(directory));
+        {
+            let mut write_file = directory.open_write(path).unwrap();
+            write_file.flush().unwrap();
+        }
+        assert!(is_listed(directory));
+        directory.delete(path).unwrap();
+        assert!(!is_listed(directory));
+    }
+
     fn test_directory(directory: &mut Directory) {
         test_simple(directory);
         test_seek(directory);
         test_rewrite_forbidden(directory);
         test_write_create_the_file(directory);
         test_directory_delete(directory);
+        test_atomic_write_visibility(directory);
+        test_list_files(directory);
     }
 
 }
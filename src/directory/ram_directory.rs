@@ -134,6 +134,15 @@ impl InnerDirectory {
             .expect("Failed to get read lock directory.")
             .contains_key(path)
     }
+
+    fn list_files(&self) -> Vec<PathBuf> {
+        self.0
+            .read()
+            .expect("Failed to get read lock directory.")
+            .keys()
+            .cloned()
+            .collect()
+    }
 }
 
 impl fmt::Debug for RAMDirectory {
@@ -196,14 +205,16 @@ impl Directory for RAMDirectory {
     }
 
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
-        let path_buf = PathBuf::from(path);
-        let mut vec_writer = VecWriter::new(path_buf.clone(), self.fs.clone());
-        self.fs.write(path_buf, &Vec::new())?;
-        vec_writer.write_all(data)?;
-        vec_writer.flush()?;
+        // The content is replaced while holding the directory lock,
+        // so that readers never observe an empty or partial file.
+        self.fs.write(PathBuf::from(path), data)?;
         Ok(())
     }
 
+    fn list_files(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self.fs.list_files())
+    }
+
     fn box_clone(&self) -> Box<Directory> {
         Box::new(self.clone())
     }
@@ -44,6 +44,25 @@ impl ReadOnlySource {
         }
     }
 
+    /// Returns true iff the data is held in anonymous memory,
+    /// as opposed to being memory mapped.
+    pub fn is_anonymous(&self) -> bool {
+        match *self {
+            ReadOnlySource::Mmap(_) => false,
+            ReadOnlySource::Anonymous(_) => true,
+        }
+    }
+
+    /// Copies the data into a new `ReadOnlySource`, held in
+    /// anonymous memory.
+    ///
+    /// The copy only retains the data of this source. In particular, copying a
+    /// small slice of a large `ReadOnlySource` makes it possible to release the
+    /// latter.
+    pub fn to_anonymous(&self) -> ReadOnlySource {
+        ReadOnlySource::from(self.as_slice().to_vec())
+    }
+
     /// Splits into 2 `ReadOnlySource`, at the offset given
     /// as an argument.
     pub fn split(self, addr: usize) -> (ReadOnlySource, ReadOnlySource) {
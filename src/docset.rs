@@ -83,10 +83,32 @@ pub trait DocSet {
     /// Returns the current document
     fn doc(&self) -> DocId;
 
-    /// Returns a best-effort hint of the
-    /// length of the docset.
+    /// Returns an upper bound of the number of documents
+    /// the `DocSet` contains from its current position,
+    /// the current document included.
+    ///
+    /// The bound does not take deleted documents into account,
+    /// and does not need to be tight: for instance, the postings of a term
+    /// keep returning their document frequency while they are consumed.
+    /// Implementations combining several `DocSet`s must saturate
+    /// rather than overflow, and clamp the bound to the `max_doc`
+    /// of the segment when they know it.
+    ///
+    /// Use [`count_estimate`](#method.count_estimate) to decide which
+    /// `DocSet` to go through first, and [`count`](#method.count)
+    /// to get an exact count.
     fn size_hint(&self) -> u32;
 
+    /// Returns an estimate of the number of documents of the `DocSet`,
+    /// used as a cost to decide in which order to go through several `DocSet`s,
+    /// for instance in an intersection.
+    ///
+    /// Contrary to `size_hint`, it is not required to be an upper bound.
+    /// It defaults to `size_hint`.
+    fn count_estimate(&self) -> u32 {
+        self.size_hint()
+    }
+
     /// Appends all docs to a `bitset`.
     fn append_to_bitset(&mut self, bitset: &mut BitSet) {
         while self.advance() {
@@ -106,6 +128,8 @@ pub trait DocSet {
     }
 }
 
+/// Boxed `DocSet`s are `DocSet`s as well, so that `DocSet`s of different
+/// types can be combined as `Box<DocSet>` (e.g. in an `Intersection`).
 impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
     fn advance(&mut self) -> bool {
         let unboxed: &mut TDocSet = self.borrow_mut();
@@ -117,6 +141,11 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         unboxed.skip_next(target)
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        let unboxed: &mut TDocSet = self.borrow_mut();
+        unboxed.fill_buffer(buffer)
+    }
+
     fn doc(&self) -> DocId {
         let unboxed: &TDocSet = self.borrow();
         unboxed.doc()
@@ -127,6 +156,11 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         unboxed.size_hint()
     }
 
+    fn count_estimate(&self) -> u32 {
+        let unboxed: &TDocSet = self.borrow();
+        unboxed.count_estimate()
+    }
+
     fn count(&mut self) -> u32 {
         let unboxed: &mut TDocSet = self.borrow_mut();
         unboxed.count()
@@ -137,3 +171,129 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         unboxed.append_to_bitset(bitset);
     }
 }
+
+/// A `DocSet` combining several `DocSet`s of the same type,
+/// like a [`Union`](./query/struct.Union.html) or an
+/// [`Intersection`](./query/struct.Intersection.html).
+///
+/// Building some `DocSet`s, like the scorers of the terms, is expensive.
+/// `into_docsets` gives back the ownership of the combined `DocSet`s,
+/// so that they can be reused, e.g. once a first pass collected candidates.
+///
+/// The `DocSet`s are not rewound: they are left where the group
+/// left them. See the implementations for their exact position.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tantivy;
+/// use tantivy::{DocSet, DocSetGroup, Index};
+/// use tantivy::query::Intersection;
+/// use tantivy::schema::{IndexRecordOption, SchemaBuilder, Term, TEXT};
+///
+/// # fn main() { run().unwrap(); }
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::default();
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     for text in &["rust fast", "rust", "rust fast", "rust", "rust fast"] {
+///         index_writer.add_document(doc!(body => *text))?;
+///     }
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let inverted_index = searcher.segment_reader(0).inverted_index(body);
+/// let postings = |text: &str| {
+///     let term = Term::from_field_text(body, text);
+///     inverted_index.read_postings(&term, IndexRecordOption::Basic).unwrap()
+/// };
+///
+/// // collects the first two documents about `rust` and `fast`...
+/// let mut intersection = Intersection::from(vec![postings("rust"), postings("fast")]);
+/// let mut candidates = vec![];
+/// while candidates.len() < 2 && intersection.advance() {
+///     candidates.push(intersection.doc());
+/// }
+/// assert_eq!(candidates, vec![0, 2]);
+///
+/// // ... then goes on with the postings of `rust` alone,
+/// // which are positioned on the last candidate.
+/// let mut rust_postings = intersection.into_docsets().remove(0);
+/// assert_eq!(rust_postings.doc(), 2);
+/// let mut remaining_docs = vec![];
+/// while rust_postings.advance() {
+///     remaining_docs.push(rust_postings.doc());
+/// }
+/// assert_eq!(remaining_docs, vec![3, 4]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait DocSetGroup<TDocSet>: DocSet {
+    /// Consumes the group, and returns its `DocSet`s,
+    /// in the order they were given to the group.
+    fn into_docsets(self) -> Vec<TDocSet>;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use DocId;
+    use super::DocSet;
+    use query::{ConstScorer, Exclude, Intersection, Scorer, Union, VecDocSet};
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    const MAX_DOC: u32 = 1_000;
+
+    /// Builds a random tree of unions, intersections and
+    /// exclusions, with `VecDocSet`s as leaves.
+    fn random_docset(rng: &mut XorShiftRng, depth: usize) -> Box<Scorer> {
+        let kind = if depth == 0 { 0 } else { rng.gen_range(0, 4) };
+        match kind {
+            0 => {
+                let ratio = rng.next_f32() * 0.3;
+                let doc_ids: Vec<DocId> = (0..MAX_DOC).filter(|_| rng.next_f32() < ratio).collect();
+                box ConstScorer::new(VecDocSet::from(doc_ids))
+            }
+            1 => {
+                let num_children = rng.gen_range(2, 5);
+                let children: Vec<Box<Scorer>> = (0..num_children)
+                    .map(|_| random_docset(rng, depth - 1))
+                    .collect();
+                box Union::<Box<Scorer>>::from(children)
+            }
+            2 => {
+                let num_children = rng.gen_range(2, 4);
+                let children: Vec<Box<Scorer>> = (0..num_children)
+                    .map(|_| random_docset(rng, depth - 1))
+                    .collect();
+                box Intersection::from(children)
+            }
+            _ => {
+                let underlying = random_docset(rng, depth - 1);
+                let excluded = random_docset(rng, depth - 1);
+                box Exclude::new(underlying, excluded)
+            }
+        }
+    }
+
+    #[test]
+    fn test_size_hint_is_an_upper_bound() {
+        for seed in 0..200u32 {
+            let build = || random_docset(&mut XorShiftRng::from_seed([1, 2, 3, seed]), 3);
+            let count = build().count();
+            let mut docset = build();
+            assert!(docset.size_hint() >= count);
+            let mut num_docs = 0u32;
+            while docset.advance() {
+                // the current document is included.
+                assert!(docset.size_hint() >= count - num_docs);
+                num_docs += 1;
+            }
+            assert_eq!(num_docs, count);
+        }
+    }
+}
@@ -8,6 +8,8 @@ use directory::error::{IOError, OpenDirectoryError, OpenReadError, OpenWriteErro
 use query;
 use schema;
 use fastfield::FastFieldNotAvailableError;
+use core::SegmentId;
+use indexer::Opstamp;
 use serde_json;
 
 error_chain!(
@@ -48,15 +50,57 @@ error_chain!(
             description("an error occurred in a thread")
             display("an error occurred in a thread: '{}'", err)
         }
+        /// An indexing worker thread panicked, typically while indexing
+        /// the document with the given opstamp.
+        ///
+        /// The documents it was indexing are lost. The `IndexWriter` keeps
+        /// returning this error until it is rolled back.
+        WorkerPanicked(message: String, opstamp: Opstamp) {
+            description("an indexing worker thread panicked")
+            display("an indexing worker thread panicked while indexing the document \
+                     with opstamp {}: '{}'", opstamp, message)
+        }
         /// An Error appeared related to the lack of a field.
         SchemaError(field: String) {
             description("a schema field is missing")
             display("a schema field is missing: '{}'", field)
         }
+        /// A field used as a hierarchical facet field, for instance by
+        /// a `FacetCollector`, is of another type.
+        NotAFacetField(field_name: String) {
+            description("the field is not a hierarchical facet field")
+            display("the field '{}' is not a hierarchical facet field", field_name)
+        }
+        /// The schema of an existing index is not the expected one.
+        SchemaMismatch(msg: String) {
+            description("the schema of the index is not the expected one")
+            display("the schema of the index is not the expected one: '{}'", msg)
+        }
+        /// The segment requested is not part of the searcher, typically
+        /// because it was merged away.
+        SegmentNotFound(segment_id: SegmentId) {
+            description("segment not found")
+            display("segment not found: '{:?}'", segment_id)
+        }
+        /// A document does not match the schema.
+        InvalidDocument(err: schema::DocumentValidationError) {
+            description("the document does not match the schema")
+            display("the document does not match the schema: '{}'", err)
+        }
+        /// A component of the index was written in a format version
+        /// this version of tantivy cannot read.
+        ///
+        /// Reading the segments written by upstream tantivy
+        /// requires the `compat-upstream` feature.
+        IncompatibleIndex(component: String, format_version: u32, supported_version: u32) {
+            description("the index was written in an incompatible format")
+            display("the {} was written in the format version {}, while the supported \
+                     version is {}", component, format_version, supported_version)
+        }
         /// Tried to access a fastfield reader for a field not configured accordingly.
         FastFieldError(err: FastFieldNotAvailableError) {
             description("fast field not available")
-            display("fast field not available: '{:?}'", err)
+            display("fast field not available: {}", err)
         }
     }
 );
@@ -108,6 +152,12 @@ impl From<schema::DocParsingError> for Error {
     }
 }
 
+impl From<schema::DocumentValidationError> for Error {
+    fn from(error: schema::DocumentValidationError) -> Error {
+        ErrorKind::InvalidDocument(error).into()
+    }
+}
+
 impl From<OpenWriteError> for Error {
     fn from(error: OpenWriteError) -> Error {
         match error {
@@ -64,6 +64,13 @@ impl DeleteBitSet {
         self.len() > 0
     }
 
+    /// Returns the bytes of the bitset.
+    ///
+    /// The bitset of a segment without deletes may be empty.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
     /// Returns true iff the document is deleted.
     #[inline]
     pub fn is_deleted(&self, doc: DocId) -> bool {
@@ -1,5 +1,6 @@
+use std::fmt;
 use std::result;
-use schema::FieldEntry;
+use schema::{Cardinality, FieldEntry, FieldType};
 
 /// `FastFieldNotAvailableError` is returned when the
 /// user requested for a fast field reader, and the field was not
@@ -7,6 +8,24 @@ use schema::FieldEntry;
 #[derive(Debug)]
 pub struct FastFieldNotAvailableError {
     field_name: String,
+    field_description: &'static str,
+}
+
+fn describe_field_type(field_type: &FieldType) -> &'static str {
+    match *field_type {
+        FieldType::Str(_) => "a text field",
+        FieldType::U64(ref int_options) => match int_options.get_fastfield_cardinality() {
+            None => "a u64 field that is not fast",
+            Some(Cardinality::SingleValue) => "a single valued u64 fast field",
+            Some(Cardinality::MultiValues) => "a multivalued u64 fast field",
+        },
+        FieldType::I64(ref int_options) => match int_options.get_fastfield_cardinality() {
+            None => "an i64 field that is not fast",
+            Some(Cardinality::SingleValue) => "a single valued i64 fast field",
+            Some(Cardinality::MultiValues) => "a multivalued i64 fast field",
+        },
+        FieldType::HierarchicalFacet => "a hierarchical facet field",
+    }
 }
 
 impl FastFieldNotAvailableError {
@@ -16,8 +35,25 @@ impl FastFieldNotAvailableError {
     pub fn new(field_entry: &FieldEntry) -> FastFieldNotAvailableError {
         FastFieldNotAvailableError {
             field_name: field_entry.name().to_string(),
+            field_description: describe_field_type(field_entry.field_type()),
         }
     }
+
+    /// Name of the field for which fast fields are not available.
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+}
+
+impl fmt::Display for FastFieldNotAvailableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the field '{}' is {}, and does not have a fast field \
+             of the requested type and cardinality",
+            self.field_name, self.field_description
+        )
+    }
 }
 
 /// Result when trying to access a fast field reader.
@@ -0,0 +1,216 @@
+use byteorder::ByteOrder;
+use common::{BinarySerializable, CompositeWrite, Endianness};
+use directory::{ReadOnlySource, WritePtr};
+use postings::UnorderedTermId;
+use schema::Field;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str;
+use termdict::TermOrdinal;
+
+// ordinal (u64) and end offset of the label (u32)
+const ENTRY_NUM_BYTES: usize = 12;
+
+/// Records the display labels of the facets of a segment.
+///
+/// Labels are associated to the facet ordinals. While the segment is
+/// being written, the facets are identified by their `UnorderedTermId`,
+/// which are remapped to ordinals upon serialization.
+///
+/// If several labels are recorded for the same facet, the first one wins.
+///
+/// The labels of each field are serialized as
+/// - the number of labels, as a `u32`
+/// - for each label, sorted by facet ordinal, the facet ordinal
+/// as a `u64` and the end offset of the label as a `u32`
+/// - the concatenated labels.
+///
+/// Fields without any label are not serialized.
+#[derive(Default)]
+pub struct FacetLabelsWriter {
+    labels: HashMap<Field, HashMap<u64, String>>,
+}
+
+impl FacetLabelsWriter {
+    /// Creates an empty `FacetLabelsWriter`.
+    pub fn new() -> FacetLabelsWriter {
+        FacetLabelsWriter::default()
+    }
+
+    /// Records the label of a facet, unless the facet
+    /// already has a label.
+    pub fn record(&mut self, field: Field, facet_id: u64, label: &str) {
+        self.labels
+            .entry(field)
+            .or_insert_with(HashMap::new)
+            .entry(facet_id)
+            .or_insert_with(|| label.to_string());
+    }
+
+    /// Serializes the labels of each of the fields.
+    ///
+    /// The facet ids of the fields present in `mapping` are `UnorderedTermId`s
+    /// and get translated into ordinals. The ids of the other fields are
+    /// expected to be ordinals already.
+    pub fn serialize(
+        &self,
+        composite_write: &mut CompositeWrite<WritePtr>,
+        mapping: &HashMap<Field, HashMap<UnorderedTermId, usize>>,
+    ) -> io::Result<()> {
+        for (&field, field_labels) in &self.labels {
+            let field_mapping = mapping.get(&field);
+            let mut sorted_labels: Vec<(TermOrdinal, &str)> = field_labels
+                .iter()
+                .map(|(&facet_id, label)| {
+                    let facet_ord = field_mapping
+                        .map(|field_mapping| {
+                            *field_mapping
+                                .get(&facet_id)
+                                .expect("Missing term ordinal") as TermOrdinal
+                        })
+                        .unwrap_or(facet_id);
+                    (facet_ord, label.as_str())
+                })
+                .collect();
+            sorted_labels.sort();
+            let write = composite_write.for_field(field);
+            (sorted_labels.len() as u32).serialize(write)?;
+            let mut label_end = 0u32;
+            for &(facet_ord, label) in &sorted_labels {
+                label_end += label.len() as u32;
+                facet_ord.serialize(write)?;
+                label_end.serialize(write)?;
+            }
+            for &(_, label) in &sorted_labels {
+                write.write_all(label.as_bytes())?;
+            }
+            write.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Labels of the facets of a field, as serialized
+/// by the `FacetLabelsWriter`.
+#[derive(Clone)]
+pub struct FacetLabelsReader {
+    data: ReadOnlySource,
+    num_labels: usize,
+}
+
+impl FacetLabelsReader {
+    /// Opens the labels of a field given their data source.
+    pub fn open(data: ReadOnlySource) -> FacetLabelsReader {
+        let num_labels = Endianness::read_u32(&data.as_slice()[..4]) as usize;
+        FacetLabelsReader { data, num_labels }
+    }
+
+    /// Returns a reader without any label.
+    pub fn empty() -> FacetLabelsReader {
+        FacetLabelsReader {
+            data: ReadOnlySource::empty(),
+            num_labels: 0,
+        }
+    }
+
+    /// Returns the number of facets having a label.
+    pub fn num_labels(&self) -> usize {
+        self.num_labels
+    }
+
+    fn entry(&self, label_id: usize) -> (TermOrdinal, usize) {
+        let start = 4 + label_id * ENTRY_NUM_BYTES;
+        let entry = &self.data.as_slice()[start..start + ENTRY_NUM_BYTES];
+        (
+            Endianness::read_u64(&entry[..8]),
+            Endianness::read_u32(&entry[8..]) as usize,
+        )
+    }
+
+    fn label_at(&self, label_id: usize) -> &str {
+        let data = self.data.as_slice();
+        let labels_start = 4 + self.num_labels * ENTRY_NUM_BYTES;
+        let start = if label_id == 0 {
+            0
+        } else {
+            self.entry(label_id - 1).1
+        };
+        let stop = self.entry(label_id).1;
+        let label_bytes = &data[labels_start + start..labels_start + stop];
+        str::from_utf8(label_bytes).expect("Facet label is not valid utf-8")
+    }
+
+    /// Returns the label of the facet with the given ordinal, if any.
+    pub fn label(&self, facet_ord: TermOrdinal) -> Option<&str> {
+        let (mut start, mut stop) = (0, self.num_labels);
+        while start < stop {
+            let middle = start + (stop - start) / 2;
+            let (middle_ord, _) = self.entry(middle);
+            if middle_ord < facet_ord {
+                start = middle + 1;
+            } else if middle_ord > facet_ord {
+                stop = middle;
+            } else {
+                return Some(self.label_at(middle));
+            }
+        }
+        None
+    }
+
+    /// Iterates over the `(facet ordinal, label)` couples,
+    /// sorted by facet ordinal.
+    pub fn labels<'a>(&'a self) -> impl Iterator<Item = (TermOrdinal, &'a str)> + 'a {
+        (0..self.num_labels).map(move |label_id| (self.entry(label_id).0, self.label_at(label_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{FacetLabelsReader, FacetLabelsWriter};
+    use common::{CompositeFile, CompositeWrite};
+    use directory::{Directory, RAMDirectory};
+    use schema::Field;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    #[test]
+    fn test_facet_labels() {
+        let path = Path::new("test");
+        let mut directory = RAMDirectory::create();
+        {
+            let mut facet_labels_writer = FacetLabelsWriter::new();
+            facet_labels_writer.record(Field(0), 7, "Apple Inc.");
+            facet_labels_writer.record(Field(0), 3, "Brands");
+            facet_labels_writer.record(Field(0), 7, "Apple");
+            facet_labels_writer.record(Field(2), 0, "");
+            facet_labels_writer.record(Field(2), 1, "Électronique");
+            let mut mapping = HashMap::new();
+            let field_mapping: HashMap<u64, usize> = vec![(0, 1), (1, 0)].into_iter().collect();
+            mapping.insert(Field(2), field_mapping);
+            let mut composite_write = CompositeWrite::wrap(directory.open_write(path).unwrap());
+            facet_labels_writer
+                .serialize(&mut composite_write, &mapping)
+                .unwrap();
+            composite_write.close().unwrap();
+        }
+        let source = directory.open_read(path).unwrap();
+        let composite_file = CompositeFile::open(&source).unwrap();
+        assert!(composite_file.open_read(Field(1)).is_none());
+        let labels = FacetLabelsReader::open(composite_file.open_read(Field(0)).unwrap());
+        assert_eq!(labels.num_labels(), 2);
+        assert_eq!(labels.label(3), Some("Brands"));
+        assert_eq!(labels.label(7), Some("Apple Inc."));
+        assert_eq!(labels.label(0), None);
+        assert_eq!(labels.label(5), None);
+        assert_eq!(labels.label(8), None);
+        assert_eq!(
+            labels.labels().collect::<Vec<_>>(),
+            vec![(3, "Brands"), (7, "Apple Inc.")]
+        );
+        let remapped_labels = FacetLabelsReader::open(composite_file.open_read(Field(2)).unwrap());
+        assert_eq!(remapped_labels.label(0), Some("Électronique"));
+        assert_eq!(remapped_labels.label(1), Some(""));
+        assert_eq!(FacetLabelsReader::empty().label(0), None);
+    }
+}
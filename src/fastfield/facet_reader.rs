@@ -1,4 +1,5 @@
 use super::MultiValueIntFastFieldReader;
+use super::FacetLabelsReader;
 use DocId;
 use termdict::TermOrdinal;
 use schema::Facet;
@@ -17,9 +18,14 @@ use termdict::{TermDictionary, TermDictionaryImpl};
 /// Facet ordinals are defined as their position in the sorted
 /// list of facets. This ordinal is segment local and
 /// only makes sense for a given segment.
+///
+/// Facets added with
+/// [`Document::add_facet_with_label`](../schema/struct.Document.html#method.add_facet_with_label)
+/// also come with a display label, accessible via `.label_from_ord(...)`.
 pub struct FacetReader {
     term_ords: MultiValueIntFastFieldReader<u64>,
     term_dict: TermDictionaryImpl,
+    labels: FacetLabelsReader,
 }
 
 impl FacetReader {
@@ -37,9 +43,14 @@ impl FacetReader {
         FacetReader {
             term_ords,
             term_dict,
+            labels: FacetLabelsReader::empty(),
         }
     }
 
+    pub(crate) fn set_labels(&mut self, labels: FacetLabelsReader) {
+        self.labels = labels;
+    }
+
     /// Returns the size of the sets of facets in the segment.
     /// This does not take in account the documents that may be marked
     /// as deleted.
@@ -61,7 +72,23 @@ impl FacetReader {
         assert!(found_term, "Term ordinal {} no found.", facet_ord);
     }
 
-    /// Return the list of facet ordinals associated to a document.
+    /// Returns the display label of the facet with the given ordinal,
+    /// or `None` if the facet was not added with a label.
+    ///
+    /// When a facet was added with different labels within a segment,
+    /// the first label indexed is kept.
+    pub fn label_from_ord(&self, facet_ord: TermOrdinal) -> Option<&str> {
+        self.labels.label(facet_ord)
+    }
+
+    /// Iterates over the `(facet ordinal, label)` couples
+    /// of the facets having a label, sorted by facet ordinal.
+    pub fn labels<'a>(&'a self) -> impl Iterator<Item = (TermOrdinal, &'a str)> + 'a {
+        self.labels.labels()
+    }
+
+    /// Return the list of facet ordinals associated to a document,
+    /// in increasing order.
     pub fn facet_ords(&mut self, doc: DocId, output: &mut Vec<u64>) {
         self.term_ords.get_vals(doc, output);
     }
@@ -0,0 +1,274 @@
+use byteorder::{ByteOrder, LittleEndian};
+use common::CompositeWrite;
+use directory::{ReadOnlySource, WritePtr};
+use fastfield::DeleteBitSet;
+use schema::{Document, Field, FieldEntry, Schema};
+use std::io::{self, Write};
+use DocId;
+
+/// Reads the 64 bits word of a bitset starting at `offset`,
+/// the bytes past the end of the bitset being 0.
+fn read_word(bytes: &[u8], offset: usize) -> u64 {
+    if offset + 8 <= bytes.len() {
+        return LittleEndian::read_u64(&bytes[offset..offset + 8]);
+    }
+    let mut word_bytes = [0u8; 8];
+    if offset < bytes.len() {
+        let tail = &bytes[offset..];
+        word_bytes[..tail.len()].copy_from_slice(tail);
+    }
+    LittleEndian::read_u64(&word_bytes)
+}
+
+/// Records, for each field, the set of the documents that
+/// contain at least one value for this field.
+///
+/// The set of documents of each field is serialized as a bitset
+/// of `max_doc` bits, using the same layout as the delete bitset.
+/// Fields that do not appear in any document are not serialized.
+///
+/// Only the fields whose values are kept, that is indexed, stored
+/// or fast fields, are recorded.
+pub struct FieldPresenceWriter {
+    bitsets: Vec<Option<Vec<u8>>>,
+    num_bytes: usize,
+}
+
+/// Returns true iff the presence of the field has to be recorded.
+fn needs_field_presence(field_entry: &FieldEntry) -> bool {
+    field_entry.is_indexed() || field_entry.is_stored() || field_entry.is_int_fast()
+}
+
+impl FieldPresenceWriter {
+    /// Creates a `FieldPresenceWriter` for the fields of the schema.
+    pub fn from_schema(schema: &Schema) -> FieldPresenceWriter {
+        let bitsets = schema
+            .fields()
+            .iter()
+            .map(|field_entry| {
+                if needs_field_presence(field_entry) {
+                    Some(Vec::new())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        FieldPresenceWriter {
+            bitsets,
+            num_bytes: 0,
+        }
+    }
+
+    /// Returns true iff the presence of the field is recorded.
+    pub fn is_recorded(&self, field: Field) -> bool {
+        self.bitsets[field.0 as usize].is_some()
+    }
+
+    /// Returns true iff no field was recorded for any document,
+    /// in which case there is nothing to serialize.
+    pub fn is_empty(&self) -> bool {
+        self.num_bytes == 0
+    }
+
+    /// Returns the number of bytes used by the bitsets.
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Records that the document contains the given field.
+    ///
+    /// This is a no-op if the presence of the field is not recorded.
+    pub fn record(&mut self, field: Field, doc: DocId) {
+        if let Some(ref mut bitset) = self.bitsets[field.0 as usize] {
+            let byte_addr = (doc / 8) as usize;
+            if bitset.len() <= byte_addr {
+                self.num_bytes += byte_addr + 1 - bitset.len();
+                bitset.resize(byte_addr + 1, 0u8);
+            }
+            bitset[byte_addr] |= 1u8 << (doc % 8);
+        }
+    }
+
+    /// Records all of the fields of a new document.
+    pub fn add_document(&mut self, doc: DocId, document: &Document) {
+        for field_value in document.field_values() {
+            self.record(field_value.field(), doc);
+        }
+    }
+
+    /// Serializes the bitset of each of the fields.
+    pub fn serialize(
+        &self,
+        max_doc: DocId,
+        composite_write: &mut CompositeWrite<WritePtr>,
+    ) -> io::Result<()> {
+        let num_bytes = ((max_doc + 7) / 8) as usize;
+        for (field_id, bitset_opt) in self.bitsets.iter().enumerate() {
+            let bitset = match *bitset_opt {
+                Some(ref bitset) if !bitset.is_empty() => bitset,
+                _ => {
+                    continue;
+                }
+            };
+            let write = composite_write.for_field(Field(field_id as u32));
+            write.write_all(&bitset[..])?;
+            write.write_all(&vec![0u8; num_bytes - bitset.len()])?;
+            write.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Set of the documents containing a field, as serialized
+/// by the `FieldPresenceWriter`.
+#[derive(Clone)]
+pub struct FieldPresenceReader {
+    data: ReadOnlySource,
+}
+
+impl FieldPresenceReader {
+    /// Opens a field presence bitset given its data source.
+    pub fn open(data: ReadOnlySource) -> FieldPresenceReader {
+        FieldPresenceReader { data }
+    }
+
+    /// Returns true iff the document contains the field.
+    #[inline]
+    pub fn contains(&self, doc: DocId) -> bool {
+        let byte_offset = (doc / 8) as usize;
+        self.data
+            .as_slice()
+            .get(byte_offset)
+            .map(|byte| byte & (1u8 << (doc % 8)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Returns the number of documents containing the field,
+    /// deleted documents included.
+    pub fn num_docs(&self) -> u32 {
+        self.data
+            .as_slice()
+            .iter()
+            .map(|byte| byte.count_ones())
+            .sum()
+    }
+
+    /// Returns the number of documents containing the field,
+    /// that are not deleted.
+    ///
+    /// The bitsets are intersected 64 bits at a time.
+    pub fn num_alive_docs(&self, delete_bitset: &DeleteBitSet) -> u32 {
+        let presence_bytes = self.data.as_slice();
+        let delete_bytes = delete_bitset.as_bytes();
+        let num_words = (presence_bytes.len() + 7) / 8;
+        (0..num_words)
+            .map(|word_ord| {
+                let presence_word = read_word(presence_bytes, word_ord * 8);
+                let delete_word = read_word(delete_bytes, word_ord * 8);
+                (presence_word & !delete_word).count_ones()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{read_word, FieldPresenceReader, FieldPresenceWriter};
+    use common::{CompositeFile, CompositeWrite, HasLen};
+    use directory::{Directory, RAMDirectory, ReadOnlySource};
+    use fastfield::DeleteBitSet;
+    use schema::{Field, IntOptions, Schema, SchemaBuilder, INT_STORED, STRING};
+    use std::path::Path;
+
+    fn create_schema() -> Schema {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("a", STRING);
+        schema_builder.add_u64_field("b", INT_STORED);
+        schema_builder.add_text_field("c", STRING);
+        schema_builder.build()
+    }
+
+    #[test]
+    fn test_field_presence() {
+        let path = Path::new("test");
+        let mut directory = RAMDirectory::create();
+        {
+            let mut field_presence_writer = FieldPresenceWriter::from_schema(&create_schema());
+            assert!(field_presence_writer.is_empty());
+            field_presence_writer.record(Field(0), 0);
+            field_presence_writer.record(Field(0), 9);
+            field_presence_writer.record(Field(2), 1);
+            field_presence_writer.record(Field(2), 1);
+            assert_eq!(field_presence_writer.num_bytes(), 3);
+            let mut composite_write = CompositeWrite::wrap(directory.open_write(path).unwrap());
+            field_presence_writer
+                .serialize(20, &mut composite_write)
+                .unwrap();
+            composite_write.close().unwrap();
+        }
+        let source = directory.open_read(path).unwrap();
+        let composite_file = CompositeFile::open(&source).unwrap();
+        assert!(composite_file.open_read(Field(1)).is_none());
+        let docs = |field: Field| -> Vec<u32> {
+            let field_source = composite_file.open_read(field).unwrap();
+            let field_presence = FieldPresenceReader::open(field_source);
+            (0..20u32).filter(|&doc| field_presence.contains(doc)).collect()
+        };
+        assert_eq!(docs(Field(0)), vec![0, 9]);
+        assert_eq!(docs(Field(2)), vec![1]);
+        assert_eq!(composite_file.open_read(Field(0)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_field_presence_skips_fields_without_values() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let ignored_field = schema_builder.add_u64_field("ignored", IntOptions::default());
+        let schema = schema_builder.build();
+        let mut field_presence_writer = FieldPresenceWriter::from_schema(&schema);
+        assert!(field_presence_writer.is_recorded(text_field));
+        assert!(!field_presence_writer.is_recorded(ignored_field));
+        field_presence_writer.record(ignored_field, 100);
+        assert!(field_presence_writer.is_empty());
+        field_presence_writer.record(text_field, 100);
+        assert_eq!(field_presence_writer.num_bytes(), 13);
+    }
+
+    #[test]
+    fn test_read_word() {
+        let bytes: Vec<u8> = (1..11).collect();
+        assert_eq!(read_word(&bytes, 0), 0x0807_0605_0403_0201);
+        assert_eq!(read_word(&bytes, 8), 0x0a09);
+        assert_eq!(read_word(&bytes, 16), 0);
+    }
+
+    #[test]
+    fn test_num_alive_docs() {
+        let max_doc = 150u32;
+        let is_present = |doc: u32| doc % 3 != 0;
+        let is_deleted = |doc: u32| doc % 7 == 0 || doc > 140;
+        let mut presence_bytes = vec![0u8; ((max_doc + 7) / 8) as usize];
+        // the delete bitset is shorter, as if it only covered
+        // the first documents of the segment.
+        let mut delete_bytes = vec![0u8; 18];
+        for doc in 0..max_doc {
+            if is_present(doc) {
+                presence_bytes[(doc / 8) as usize] |= 1u8 << (doc % 8);
+            }
+            if is_deleted(doc) && ((doc / 8) as usize) < delete_bytes.len() {
+                delete_bytes[(doc / 8) as usize] |= 1u8 << (doc % 8);
+            }
+        }
+        let field_presence = FieldPresenceReader::open(ReadOnlySource::from(presence_bytes));
+        let delete_bitset = DeleteBitSet::open(ReadOnlySource::from(delete_bytes));
+        let expected_num_docs = (0..max_doc)
+            .filter(|&doc| field_presence.contains(doc) && !delete_bitset.is_deleted(doc))
+            .count();
+        assert_eq!(field_presence.num_alive_docs(&delete_bitset) as usize, expected_num_docs);
+        assert_eq!(
+            field_presence.num_alive_docs(&DeleteBitSet::empty()),
+            field_presence.num_docs()
+        );
+    }
+}
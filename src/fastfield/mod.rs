@@ -29,10 +29,14 @@ use schema::FieldType;
 use schema::Value;
 pub use self::delete::DeleteBitSet;
 pub use self::delete::write_delete_bitset;
+pub(crate) use self::field_presence::{FieldPresenceReader, FieldPresenceWriter};
+pub(crate) use self::facet_labels::{FacetLabelsReader, FacetLabelsWriter};
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
 pub use self::multivalued::MultiValueIntFastFieldReader;
+pub(crate) use self::multivalued::MultiValueIntFastFieldWriter;
 pub use self::reader::FastFieldReader;
+pub(crate) use self::reader::check_fast_field_source;
 pub use self::serializer::FastFieldSerializer;
 pub use self::writer::{FastFieldsWriter, IntFastFieldWriter};
 
@@ -41,6 +45,8 @@ mod writer;
 mod serializer;
 mod error;
 mod delete;
+mod field_presence;
+mod facet_labels;
 mod facet_reader;
 mod multivalued;
 
@@ -296,6 +302,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fast_field_min_max_num_bits() {
+        let check = |vals: Vec<u64>, num_bits: u8| {
+            let min_value = *vals.iter().min().unwrap();
+            let max_value = *vals.iter().max().unwrap();
+            let fast_field_reader = FastFieldReader::<u64>::from(vals);
+            assert_eq!(fast_field_reader.min_value(), min_value);
+            assert_eq!(fast_field_reader.max_value(), max_value);
+            assert_eq!(fast_field_reader.num_bits(), num_bits);
+        };
+        check(vec![7u64; 10], 0);
+        check(vec![0u64, 1u64, 0u64], 1);
+        check(vec![100u64, 355u64, 200u64], 8);
+        check(vec![1u64 << 20, 0u64], 21);
+        check(vec![0u64, u64::max_value()], 64);
+        let fast_field_reader = FastFieldReader::<i64>::from(vec![5i64, -5i64, 0i64]);
+        assert_eq!(fast_field_reader.min_value(), -5i64);
+        assert_eq!(fast_field_reader.max_value(), 5i64);
+        assert_eq!(fast_field_reader.num_bits(), 4);
+    }
+
     #[test]
     fn test_signed_intfastfield() {
         let path = Path::new("test");
@@ -22,10 +22,10 @@ mod tests {
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
-        index_writer.add_document(doc!(field=>1u64, field=>3u64));
-        index_writer.add_document(doc!());
-        index_writer.add_document(doc!(field=>4u64));
-        index_writer.add_document(doc!(field=>5u64, field=>20u64,field=>1u64));
+        index_writer.add_document(doc!(field=>1u64, field=>3u64)).unwrap();
+        index_writer.add_document(doc!()).unwrap();
+        index_writer.add_document(doc!(field=>4u64)).unwrap();
+        index_writer.add_document(doc!(field=>5u64, field=>20u64,field=>1u64)).unwrap();
         assert!(index_writer.commit().is_ok());
 
         index.load_searchers().unwrap();
@@ -45,6 +45,13 @@ mod tests {
             multi_value_reader.get_vals(1, &mut vals);
             assert!(vals.is_empty());
         }
+        assert_eq!(multi_value_reader.min_value(), 1u64);
+        assert_eq!(multi_value_reader.max_value(), 20u64);
+        assert_eq!(multi_value_reader.num_bits(), 5);
+        let idx_reader = multi_value_reader.idx_reader();
+        assert_eq!(idx_reader.min_value(), 0u64);
+        assert_eq!(idx_reader.max_value(), 6u64);
+        assert_eq!(idx_reader.num_bits(), 3);
     }
 
     #[test]
@@ -57,10 +64,10 @@ mod tests {
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(1, 3_000_000).unwrap();
-        index_writer.add_document(doc!(field=> 1i64, field => 3i64));
-        index_writer.add_document(doc!());
-        index_writer.add_document(doc!(field=> -4i64));
-        index_writer.add_document(doc!(field=> -5i64, field => -20i64, field=>1i64));
+        index_writer.add_document(doc!(field=> 1i64, field => 3i64)).unwrap();
+        index_writer.add_document(doc!()).unwrap();
+        index_writer.add_document(doc!(field=> -4i64)).unwrap();
+        index_writer.add_document(doc!(field=> -5i64, field => -20i64, field=>1i64)).unwrap();
         assert!(index_writer.commit().is_ok());
 
         index.load_searchers().unwrap();
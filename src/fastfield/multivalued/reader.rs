@@ -34,6 +34,37 @@ impl<Item: FastValue> MultiValueIntFastFieldReader<Item> {
         vals.resize(len, Item::default());
         self.vals_reader.get_range(start, &mut vals[..]);
     }
+
+    /// Returns the reader of the concatenated list of all values.
+    pub fn vals_reader(&self) -> &FastFieldReader<Item> {
+        &self.vals_reader
+    }
+
+    /// Returns the reader associating each document to the index of its first value.
+    pub fn idx_reader(&self) -> &FastFieldReader<u64> {
+        &self.idx_reader
+    }
+
+    /// Returns the minimum value of all documents.
+    ///
+    /// Like for `FastFieldReader::min_value`, deleted documents are
+    /// not taken in account.
+    pub fn min_value(&self) -> Item {
+        self.vals_reader.min_value()
+    }
+
+    /// Returns the maximum value of all documents.
+    ///
+    /// Like for `FastFieldReader::max_value`, deleted documents are
+    /// not taken in account.
+    pub fn max_value(&self) -> Item {
+        self.vals_reader.max_value()
+    }
+
+    /// Returns the number of bits used to store each value.
+    pub fn num_bits(&self) -> u8 {
+        self.vals_reader.num_bits()
+    }
 }
 
 #[cfg(test)]
@@ -55,17 +86,17 @@ mod tests {
             let mut doc = Document::new();
             doc.add_facet(facet_field, "/category/cat2");
             doc.add_facet(facet_field, "/category/cat1");
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         {
             let mut doc = Document::new();
             doc.add_facet(facet_field, "/category/cat2");
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         {
             let mut doc = Document::new();
             doc.add_facet(facet_field, "/category/cat3");
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().expect("Commit failed");
         index.load_searchers().expect("Reloading searchers");
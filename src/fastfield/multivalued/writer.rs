@@ -1,10 +1,11 @@
 use fastfield::FastFieldSerializer;
 use fastfield::serializer::FastSingleFieldSerializer;
 use fastfield::value_to_u64;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use postings::UnorderedTermId;
 use schema::{Document, Field};
-use std::io;
+use std::io::{self, Write};
 use itertools::Itertools;
 
 pub struct MultiValueIntFastFieldWriter {
@@ -52,6 +53,18 @@ impl MultiValueIntFastFieldWriter {
         }
     }
 
+    /// Sorts the values of each document.
+    ///
+    /// The facet collectors rely on the facet ordinals of a document
+    /// being sorted, so that the ordinals sharing an ancestor are contiguous.
+    fn sort_doc_vals(&self, vals: &mut [u64]) {
+        let num_vals = vals.len() as u64;
+        let doc_ends = self.doc_index.iter().skip(1).cloned().chain(Some(num_vals));
+        for (doc_start, doc_end) in self.doc_index.iter().cloned().zip(doc_ends) {
+            vals[doc_start as usize..doc_end as usize].sort();
+        }
+    }
+
     /// Serializes fast field values by pushing them to the `FastFieldSerializer`.
     ///
     /// HashMap makes it possible to remap them before serializing.
@@ -63,9 +76,9 @@ impl MultiValueIntFastFieldWriter {
     /// `tantivy` builds a mapping to convert this `UnorderedTermId` into
     /// term ordinals.
     ///
-    pub fn serialize(
+    pub fn serialize<W: Write>(
         &self,
-        serializer: &mut FastFieldSerializer,
+        serializer: &mut FastFieldSerializer<W>,
         mapping_opt: Option<&HashMap<UnorderedTermId, usize>>,
     ) -> io::Result<()> {
         {
@@ -80,29 +93,25 @@ impl MultiValueIntFastFieldWriter {
         }
         {
             // writing the values themselves.
-            let mut value_serializer: FastSingleFieldSerializer<_>;
-            match mapping_opt {
-                Some(mapping) => {
-                    value_serializer = serializer.new_u64_fast_field_with_idx(
-                        self.field,
-                        0u64,
-                        mapping.len() as u64,
-                        1,
-                    )?;
-                    for val in &self.vals {
-                        let remapped_val = *mapping.get(val).expect("Missing term ordinal") as u64;
-                        value_serializer.add_val(remapped_val)?;
-                    }
-                }
-                None => {
-                    let val_min_max = self.vals.iter().cloned().minmax();
-                    let (val_min, val_max) = val_min_max.into_option().unwrap_or((0u64, 0));
-                    value_serializer =
-                        serializer.new_u64_fast_field_with_idx(self.field, val_min, val_max, 1)?;
-                    for &val in &self.vals {
-                        value_serializer.add_val(val)?;
-                    }
-                }
+            let mut vals: Cow<[u64]> = match mapping_opt {
+                Some(mapping) => self.vals
+                    .iter()
+                    .map(|val| *mapping.get(val).expect("Missing term ordinal") as u64)
+                    .collect::<Vec<u64>>()
+                    .into(),
+                None => Cow::Borrowed(&self.vals[..]),
+            };
+            if self.is_facet {
+                self.sort_doc_vals(vals.to_mut());
+            }
+            let (val_min, val_max) = match mapping_opt {
+                Some(mapping) => (0u64, mapping.len() as u64),
+                None => vals.iter().cloned().minmax().into_option().unwrap_or((0u64, 0)),
+            };
+            let mut value_serializer: FastSingleFieldSerializer<_> =
+                serializer.new_u64_fast_field_with_idx(self.field, val_min, val_max, 1)?;
+            for &val in vals.iter() {
+                value_serializer.add_val(val)?;
             }
             value_serializer.close_field()?;
         }
@@ -10,11 +10,43 @@ use owning_ref::OwningRef;
 use schema::FAST;
 use schema::SchemaBuilder;
 use std::collections::HashMap;
+use std::io;
 use std::marker::PhantomData;
 use std::mem;
 use std::path::Path;
 use super::FastValue;
 
+/// Checks that the header of a fast field is consistent with the
+/// length of its data, given its number of values.
+///
+/// `num_vals` may be 0 if the number of values is unknown.
+pub(crate) fn check_fast_field_source(data: &ReadOnlySource, num_vals: usize) -> io::Result<()> {
+    let mut cursor = data.as_slice();
+    if cursor.len() < 16 + 7 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "fast field is too short to contain a header",
+        ));
+    }
+    let min_value = u64::deserialize(&mut cursor)?;
+    let amplitude = u64::deserialize(&mut cursor)?;
+    if min_value.checked_add(amplitude).is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "fast field header is corrupted",
+        ));
+    }
+    // the bitpacked values are followed by 7 bytes of padding.
+    let num_bits = compute_num_bits(amplitude) as usize;
+    if data.len() < 16 + (num_vals * num_bits + 7) / 8 + 7 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "fast field is too short to hold its values",
+        ));
+    }
+    Ok(())
+}
+
 /// Trait for accessing a fastfield.
 ///
 /// Depending on the field type, a different
@@ -24,6 +56,7 @@ pub struct FastFieldReader<Item: FastValue> {
     bit_unpacker: BitUnpacker<OwningRef<ReadOnlySource, [u8]>>,
     min_value_u64: u64,
     max_value_u64: u64,
+    num_bits: u8,
     _phantom: PhantomData<Item>,
 }
 
@@ -46,6 +79,7 @@ impl<Item: FastValue> FastFieldReader<Item> {
         FastFieldReader {
             min_value_u64: min_value,
             max_value_u64: max_value,
+            num_bits,
             bit_unpacker,
             _phantom: PhantomData,
         }
@@ -81,9 +115,9 @@ impl<Item: FastValue> FastFieldReader<Item> {
 
     /// Returns the minimum value for this fast field.
     ///
-    /// The max value does not take in account of possible
-    /// deleted document, and should be considered as an upper bound
-    /// of the actual maximum value.
+    /// The min value does not take in account of possible
+    /// deleted document, and should be considered as a lower bound
+    /// of the actual minimum value.
     pub fn min_value(&self) -> Item {
         Item::from_u64(self.min_value_u64)
     }
@@ -96,6 +130,14 @@ impl<Item: FastValue> FastFieldReader<Item> {
     pub fn max_value(&self) -> Item {
         Item::from_u64(self.max_value_u64)
     }
+
+    /// Returns the number of bits used to store each value.
+    ///
+    /// Values are stored as their difference with `min_value()`, so
+    /// a fast field in which all values are equal uses 0 bits.
+    pub fn num_bits(&self) -> u8 {
+        self.num_bits
+    }
 }
 
 impl<Item: FastValue> From<Vec<Item>> for FastFieldReader<Item> {
@@ -27,13 +27,25 @@ use std::io::{self, Write};
 /// * ...
 /// * `close_field()`
 /// * `close()`
-pub struct FastFieldSerializer {
-    composite_write: CompositeWrite<WritePtr>,
+pub struct FastFieldSerializer<W: Write = WritePtr> {
+    composite_write: CompositeWrite<W>,
 }
 
-impl FastFieldSerializer {
+impl FastFieldSerializer<Vec<u8>> {
+    /// Creates a `FastFieldSerializer` buffering the fast fields in memory.
+    ///
+    /// The buffered fast fields are then appended to another
+    /// serializer with `.append(...)`.
+    pub fn in_memory() -> FastFieldSerializer<Vec<u8>> {
+        FastFieldSerializer {
+            composite_write: CompositeWrite::wrap(Vec::new()),
+        }
+    }
+}
+
+impl<W: Write> FastFieldSerializer<W> {
     /// Constructor
-    pub fn from_write(write: WritePtr) -> io::Result<FastFieldSerializer> {
+    pub fn from_write(write: W) -> io::Result<FastFieldSerializer<W>> {
         // just making room for the pointer to header.
         let composite_write = CompositeWrite::wrap(write);
         Ok(FastFieldSerializer { composite_write })
@@ -45,7 +57,7 @@ impl FastFieldSerializer {
         field: Field,
         min_value: u64,
         max_value: u64,
-    ) -> io::Result<FastSingleFieldSerializer<CountingWriter<WritePtr>>> {
+    ) -> io::Result<FastSingleFieldSerializer<CountingWriter<W>>> {
         self.new_u64_fast_field_with_idx(field, min_value, max_value, 0)
     }
 
@@ -56,11 +68,17 @@ impl FastFieldSerializer {
         min_value: u64,
         max_value: u64,
         idx: usize,
-    ) -> io::Result<FastSingleFieldSerializer<CountingWriter<WritePtr>>> {
+    ) -> io::Result<FastSingleFieldSerializer<CountingWriter<W>>> {
         let field_write = self.composite_write.for_field_with_idx(field, idx);
         FastSingleFieldSerializer::open(field_write, min_value, max_value)
     }
 
+    /// Appends the fast fields buffered in an in-memory serializer,
+    /// in the order they were serialized.
+    pub fn append(&mut self, buffer: FastFieldSerializer<Vec<u8>>) -> io::Result<()> {
+        self.composite_write.append(buffer.composite_write)
+    }
+
     /// Closes the serializer
     ///
     /// After this call the data must be persistently save on disk.
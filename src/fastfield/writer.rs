@@ -1,6 +1,6 @@
 use schema::{Cardinality, Document, Field, Schema};
 use fastfield::FastFieldSerializer;
-use std::io;
+use std::io::{self, Write};
 use DocId;
 use schema::FieldType;
 use common;
@@ -9,11 +9,15 @@ use std::collections::HashMap;
 use postings::UnorderedTermId;
 use super::multivalued::MultiValueIntFastFieldWriter;
 use common::BinarySerializable;
+use crossbeam::{Scope, ScopedJoinHandle};
+use std::cmp;
+use std::ops::Range;
 
 /// The fastfieldswriter regroup all of the fast field writers.
 pub struct FastFieldsWriter {
     single_value_writers: Vec<IntFastFieldWriter>,
     multi_values_writers: Vec<MultiValueIntFastFieldWriter>,
+    value_position_offsets_writers: Vec<MultiValueIntFastFieldWriter>,
 }
 
 impl FastFieldsWriter {
@@ -21,6 +25,7 @@ impl FastFieldsWriter {
     pub fn from_schema(schema: &Schema) -> FastFieldsWriter {
         let mut single_value_writers = Vec::new();
         let mut multi_values_writers = Vec::new();
+        let mut value_position_offsets_writers = Vec::new();
 
         for (field_id, field_entry) in schema.fields().iter().enumerate() {
             let field = Field(field_id as u32);
@@ -48,12 +53,24 @@ impl FastFieldsWriter {
                     let fast_field_writer = MultiValueIntFastFieldWriter::new(field, true);
                     multi_values_writers.push(fast_field_writer);
                 }
+                FieldType::Str(ref text_options) => {
+                    let value_position_offsets = text_options
+                        .get_indexing_options()
+                        .map(|indexing_options| indexing_options.value_position_offsets())
+                        .unwrap_or(false);
+                    if value_position_offsets {
+                        // the values are pushed by the segment writer.
+                        let fast_field_writer = MultiValueIntFastFieldWriter::new(field, true);
+                        value_position_offsets_writers.push(fast_field_writer);
+                    }
+                }
                 _ => {}
             }
         }
         FastFieldsWriter {
             single_value_writers,
             multi_values_writers,
+            value_position_offsets_writers,
         }
     }
 
@@ -63,9 +80,26 @@ impl FastFieldsWriter {
         FastFieldsWriter {
             single_value_writers: fields.into_iter().map(IntFastFieldWriter::new).collect(),
             multi_values_writers: vec![],
+            value_position_offsets_writers: vec![],
         }
     }
 
+    /// Returns the smallest and the largest value of each of the
+    /// single-valued fast fields, in their `u64` representation.
+    ///
+    /// The default value of the documents without any value
+    /// for the field is accounted for.
+    pub fn single_value_ranges(&self) -> Vec<(Field, u64, u64)> {
+        self.single_value_writers
+            .iter()
+            .filter_map(|field_writer| {
+                field_writer
+                    .value_range()
+                    .map(|(min_value, max_value)| (field_writer.field(), min_value, max_value))
+            })
+            .collect()
+    }
+
     /// Get the `FastFieldWriter` associated to a field.
     pub fn get_field_writer(&mut self, field: Field) -> Option<&mut IntFastFieldWriter> {
         // TODO optimize
@@ -89,6 +123,18 @@ impl FastFieldsWriter {
             .find(|multivalue_writer| multivalue_writer.field() == field)
     }
 
+    /// Returns the writer of the value position offsets of the given text field.
+    ///
+    /// Returns None if the field does not record its value position offsets.
+    pub(crate) fn get_value_position_offsets_writer(
+        &mut self,
+        field: Field,
+    ) -> Option<&mut MultiValueIntFastFieldWriter> {
+        self.value_position_offsets_writers
+            .iter_mut()
+            .find(|multivalue_writer| multivalue_writer.field() == field)
+    }
+
     /// Indexes all of the fastfields of a new document.
     pub fn add_document(&mut self, doc: &Document) {
         for field_writer in &mut self.single_value_writers {
@@ -98,21 +144,80 @@ impl FastFieldsWriter {
             field_writer.next_doc();
             field_writer.add_document(doc);
         }
+        for field_writer in &mut self.value_position_offsets_writers {
+            field_writer.next_doc();
+        }
     }
 
     /// Serializes all of the `FastFieldWriter`s by pushing them in
     /// order to the fast field serializer.
-    pub fn serialize(
+    pub fn serialize<W: Write>(
         &self,
-        serializer: &mut FastFieldSerializer,
+        serializer: &mut FastFieldSerializer<W>,
         mapping: &HashMap<Field, HashMap<UnorderedTermId, usize>>,
     ) -> io::Result<()> {
-        for field_writer in &self.single_value_writers {
-            field_writer.serialize(serializer)?;
-        }
-        for field_writer in &self.multi_values_writers {
-            let field = field_writer.field();
-            field_writer.serialize(serializer, mapping.get(&field))?;
+        self.serialize_range(serializer, mapping, 0..self.num_field_writers())
+    }
+
+    /// Serializes the `FastFieldWriter`s in the background, in up to `num_threads`
+    /// threads of the given scope.
+    ///
+    /// Each thread serializes a contiguous range of fast fields in memory.
+    /// Appending the resulting serializers in order gives the same
+    /// result as `.serialize(...)`.
+    pub(crate) fn serialize_in_background<'a>(
+        &'a self,
+        scope: &Scope<'a>,
+        mapping: &'a HashMap<Field, HashMap<UnorderedTermId, usize>>,
+        num_threads: usize,
+    ) -> Vec<ScopedJoinHandle<io::Result<FastFieldSerializer<Vec<u8>>>>> {
+        let num_field_writers = self.num_field_writers();
+        let num_threads = cmp::max(num_threads, 1);
+        let num_writers_per_thread = cmp::max(
+            (num_field_writers + num_threads - 1) / num_threads,
+            1,
+        );
+        let num_jobs = (num_field_writers + num_writers_per_thread - 1) / num_writers_per_thread;
+        (0..num_jobs)
+            .map(move |job| {
+                let start = job * num_writers_per_thread;
+                let stop = cmp::min(start + num_writers_per_thread, num_field_writers);
+                scope.spawn(move || {
+                    let mut serializer = FastFieldSerializer::in_memory();
+                    self.serialize_range(&mut serializer, mapping, start..stop)
+                        .map(|()| serializer)
+                })
+            })
+            .collect()
+    }
+
+    fn num_field_writers(&self) -> usize {
+        self.single_value_writers.len() + self.multi_values_writers.len()
+            + self.value_position_offsets_writers.len()
+    }
+
+    // Serializes the `FastFieldWriter`s whose ordinal, in the order
+    // of serialization, is within `range`.
+    fn serialize_range<W: Write>(
+        &self,
+        serializer: &mut FastFieldSerializer<W>,
+        mapping: &HashMap<Field, HashMap<UnorderedTermId, usize>>,
+        range: Range<usize>,
+    ) -> io::Result<()> {
+        let num_single_value_writers = self.single_value_writers.len();
+        let num_multi_values_writers = self.multi_values_writers.len();
+        for ord in range {
+            if ord < num_single_value_writers {
+                self.single_value_writers[ord].serialize(serializer)?;
+            } else if ord < num_single_value_writers + num_multi_values_writers {
+                let field_writer = &self.multi_values_writers[ord - num_single_value_writers];
+                let field = field_writer.field();
+                field_writer.serialize(serializer, mapping.get(&field))?;
+            } else {
+                let ord = ord - num_single_value_writers - num_multi_values_writers;
+                // the positions are not term ids, and must not be remapped.
+                self.value_position_offsets_writers[ord].serialize(serializer, None)?;
+            }
         }
         Ok(())
     }
@@ -235,13 +340,19 @@ impl IntFastFieldWriter {
         self.add_val(val);
     }
 
-    /// Push the fast fields value to the `FastFieldWriter`.
-    pub fn serialize(&self, serializer: &mut FastFieldSerializer) -> io::Result<()> {
-        let (min, max) = if self.val_min > self.val_max {
-            (0, 0)
+    /// Returns the smallest and the largest value added so far,
+    /// or `None` if no value was added.
+    pub fn value_range(&self) -> Option<(u64, u64)> {
+        if self.val_min > self.val_max {
+            None
         } else {
-            (self.val_min, self.val_max)
-        };
+            Some((self.val_min, self.val_max))
+        }
+    }
+
+    /// Push the fast fields value to the `FastFieldWriter`.
+    pub fn serialize<W: Write>(&self, serializer: &mut FastFieldSerializer<W>) -> io::Result<()> {
+        let (min, max) = self.value_range().unwrap_or((0, 0));
 
         let mut single_field_serializer = serializer.new_u64_fast_field(self.field, min, max)?;
 
@@ -0,0 +1,445 @@
+/*!
+C-compatible API for the core search workflow.
+
+This module is only available with the `ffi` cargo feature.
+The corresponding C header is `src/ffi/tantivy.h`.
+
+All of the objects are exposed as opaque pointers, that must be released
+with their associated `_destroy` function.
+
+Functions that may fail return a status code. `TANTIVY_OK` means success.
+Upon failure, a description of the error can be obtained by calling
+`tantivy_last_error_message()` from the same thread.
+
+Panics never cross the FFI boundary : they are caught and
+reported as `TANTIVY_ERROR_PANIC`.
+*/
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use collector::TopCollector;
+use error::{Error, ErrorKind};
+use query::{Query, QueryParser};
+use schema::Field;
+use DocAddress;
+use Index;
+use Result;
+
+/// The call succeeded.
+pub const TANTIVY_OK: c_int = 0;
+/// An argument was invalid (null pointer, invalid utf-8,
+/// unknown field, query syntax error, ...).
+pub const TANTIVY_ERROR_INVALID_ARGUMENT: c_int = 1;
+/// An IO error happened or the index could not be found.
+pub const TANTIVY_ERROR_IO: c_int = 2;
+/// Any other error.
+pub const TANTIVY_ERROR_OTHER: c_int = 3;
+/// The call panicked.
+pub const TANTIVY_ERROR_PANIC: c_int = 4;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Opaque handle over an `Index`, and its searchers.
+pub struct TantivyIndex {
+    index: Index,
+}
+
+/// Opaque handle over a parsed query.
+pub struct TantivyQuery {
+    query: Box<Query>,
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', ""))
+        .expect("Null bytes were removed. This is a bug.");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn error_code(error: &Error) -> c_int {
+    match *error.kind() {
+        ErrorKind::InvalidArgument(_)
+        | ErrorKind::SchemaError(_)
+        | ErrorKind::SchemaMismatch(_)
+        | ErrorKind::InvalidDocument(_) => TANTIVY_ERROR_INVALID_ARGUMENT,
+        ErrorKind::PathDoesNotExist(_)
+        | ErrorKind::FileAlreadyExists(_)
+        | ErrorKind::IOError(_)
+        | ErrorKind::CorruptedFile(_) => TANTIVY_ERROR_IO,
+        _ => TANTIVY_ERROR_OTHER,
+    }
+}
+
+fn panic_message(payload: &(Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panic: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panic: {}", message)
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// Runs `f`, catching its errors and its panics,
+/// and returns the associated status code.
+fn ffi_try<F: FnOnce() -> Result<()>>(f: F) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => TANTIVY_OK,
+        Ok(Err(error)) => {
+            set_last_error(error.to_string());
+            error_code(&error)
+        }
+        Err(payload) => {
+            set_last_error(panic_message(&*payload));
+            TANTIVY_ERROR_PANIC
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str> {
+    if ptr.is_null() {
+        bail!(ErrorKind::InvalidArgument(format!("`{}` is null", name)));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| ErrorKind::InvalidArgument(format!("`{}` is not valid utf-8", name)).into())
+}
+
+unsafe fn ref_arg<'a, T>(ptr: *const T, name: &str) -> Result<&'a T> {
+    if ptr.is_null() {
+        bail!(ErrorKind::InvalidArgument(format!("`{}` is null", name)));
+    }
+    Ok(&*ptr)
+}
+
+unsafe fn out_arg<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut T> {
+    if ptr.is_null() {
+        bail!(ErrorKind::InvalidArgument(format!("`{}` is null", name)));
+    }
+    Ok(&mut *ptr)
+}
+
+unsafe fn buffer_arg<'a, T>(ptr: *mut T, len: usize, name: &str) -> Result<&'a mut [T]> {
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    if ptr.is_null() {
+        bail!(ErrorKind::InvalidArgument(format!("`{}` is null", name)));
+    }
+    Ok(slice::from_raw_parts_mut(ptr, len))
+}
+
+/// Returns the message of the last error that happened on
+/// the current thread, or null if no error happened.
+///
+/// The string is owned by tantivy, and remains valid until
+/// the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn tantivy_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| match *last_error.borrow() {
+        Some(ref message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Opens the index located in the directory `path`, and loads its searchers.
+///
+/// On success, the index is written in `index_out`, and must
+/// be released with `tantivy_index_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_index_open(
+    path: *const c_char,
+    index_out: *mut *mut TantivyIndex,
+) -> c_int {
+    ffi_try(|| {
+        let path = str_arg(path, "path")?;
+        let index_out = out_arg(index_out, "index_out")?;
+        let index = Index::open(path)?;
+        index.load_searchers()?;
+        *index_out = Box::into_raw(box TantivyIndex { index });
+        Ok(())
+    })
+}
+
+/// Loads the last commit of the index.
+///
+/// Document addresses obtained before the reload are
+/// not valid anymore.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_index_reload(index: *const TantivyIndex) -> c_int {
+    ffi_try(|| ref_arg(index, "index")?.index.load_searchers())
+}
+
+/// Releases an index. Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_index_destroy(index: *mut TantivyIndex) {
+    if !index.is_null() {
+        ffi_try(|| {
+            drop(Box::from_raw(index));
+            Ok(())
+        });
+    }
+}
+
+/// Parses a query, using the `num_default_fields` fields named
+/// in `default_fields` as the default fields.
+///
+/// On success, the query is written in `query_out`, and must
+/// be released with `tantivy_query_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_query_parse(
+    index: *const TantivyIndex,
+    query: *const c_char,
+    default_fields: *const *const c_char,
+    num_default_fields: usize,
+    query_out: *mut *mut TantivyQuery,
+) -> c_int {
+    ffi_try(|| {
+        let index = &ref_arg(index, "index")?.index;
+        let query = str_arg(query, "query")?;
+        let query_out = out_arg(query_out, "query_out")?;
+        let field_names: &[*const c_char] = if num_default_fields == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(ref_arg(default_fields, "default_fields")?, num_default_fields)
+        };
+        let schema = index.schema();
+        let mut fields: Vec<Field> = vec![];
+        for &field_name in field_names {
+            let field_name = str_arg(field_name, "default_fields")?;
+            let field = schema
+                .get_field(field_name)
+                .ok_or_else(|| ErrorKind::SchemaError(field_name.to_string()))?;
+            fields.push(field);
+        }
+        let query = QueryParser::for_index(index, fields).parse_query(query)?;
+        *query_out = Box::into_raw(box TantivyQuery { query });
+        Ok(())
+    })
+}
+
+/// Releases a query. Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_query_destroy(query: *mut TantivyQuery) {
+    if !query.is_null() {
+        ffi_try(|| {
+            drop(Box::from_raw(query));
+            Ok(())
+        });
+    }
+}
+
+/// Searches the `limit` best documents matching `query`.
+///
+/// `segment_ords_out`, `doc_ids_out` and `scores_out` are buffers
+/// allocated by the caller, of length at least `limit`. The hits are
+/// written in these buffers in decreasing order of score, and their
+/// number is written in `num_hits_out`.
+/// Scores are written as `float`, whatever the precision of tantivy's `Score`.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_search(
+    index: *const TantivyIndex,
+    query: *const TantivyQuery,
+    limit: usize,
+    segment_ords_out: *mut u32,
+    doc_ids_out: *mut u32,
+    scores_out: *mut f32,
+    num_hits_out: *mut usize,
+) -> c_int {
+    ffi_try(|| {
+        let index = &ref_arg(index, "index")?.index;
+        let query = &ref_arg(query, "query")?.query;
+        let segment_ords_out = buffer_arg(segment_ords_out, limit, "segment_ords_out")?;
+        let doc_ids_out = buffer_arg(doc_ids_out, limit, "doc_ids_out")?;
+        let scores_out = buffer_arg(scores_out, limit, "scores_out")?;
+        let num_hits_out = out_arg(num_hits_out, "num_hits_out")?;
+        *num_hits_out = 0;
+        if limit == 0 {
+            return Ok(());
+        }
+        let mut top_collector = TopCollector::with_limit(limit);
+        index.searcher().search(&**query, &mut top_collector)?;
+        let score_docs = top_collector.score_docs();
+        for (i, (score, DocAddress(segment_ord, doc_id))) in score_docs.into_iter().enumerate() {
+            segment_ords_out[i] = segment_ord;
+            doc_ids_out[i] = doc_id;
+            scores_out[i] = score as f32;
+            *num_hits_out += 1;
+        }
+        Ok(())
+    })
+}
+
+/// Fetches a stored document, and serializes it as a JSON object
+/// associating each field name to the list of its values.
+///
+/// On success, the nul-terminated JSON string is written in `json_out`,
+/// and must be released with `tantivy_string_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_doc_to_json(
+    index: *const TantivyIndex,
+    segment_ord: u32,
+    doc_id: u32,
+    json_out: *mut *mut c_char,
+) -> c_int {
+    ffi_try(|| {
+        let index = &ref_arg(index, "index")?.index;
+        let json_out = out_arg(json_out, "json_out")?;
+        let doc = index.searcher().doc(&DocAddress(segment_ord, doc_id))?;
+        let json = index.schema().to_json(&doc);
+        let json = CString::new(json).map_err(|_| {
+            Error::from_kind(ErrorKind::InvalidArgument(
+                "The document contains a null byte.".to_string(),
+            ))
+        })?;
+        *json_out = json.into_raw();
+        Ok(())
+    })
+}
+
+/// Releases a string returned by tantivy. Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn tantivy_string_destroy(s: *mut c_char) {
+    if !s.is_null() {
+        ffi_try(|| {
+            drop(CString::from_raw(s));
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use schema::{SchemaBuilder, STORED, TEXT};
+    use serde_json;
+    use tempdir::TempDir;
+
+    fn last_error_message() -> String {
+        unsafe { CStr::from_ptr(tantivy_last_error_message()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_ffi() {
+        let index_dir = TempDir::new("tantivy_ffi").unwrap();
+        {
+            let mut schema_builder = SchemaBuilder::default();
+            let title = schema_builder.add_text_field("title", TEXT | STORED);
+            let body = schema_builder.add_text_field("body", TEXT);
+            let index = Index::create(index_dir.path(), schema_builder.build()).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "Of Mice and Men", body => "a short novel"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "The Old Man and the Sea", body => "mice"))
+                .unwrap();
+            index_writer.add_document(doc!(title => "Frankenstein")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        unsafe {
+            let mut index: *mut TantivyIndex = ptr::null_mut();
+            let path = CString::new(index_dir.path().to_str().unwrap()).unwrap();
+            assert_eq!(tantivy_index_open(path.as_ptr(), &mut index), TANTIVY_OK);
+            assert!(!index.is_null());
+
+            let field_names = vec![CString::new("title").unwrap(), CString::new("body").unwrap()];
+            let field_name_ptrs: Vec<*const c_char> =
+                field_names.iter().map(|name| name.as_ptr()).collect();
+            let query_str = CString::new("mice").unwrap();
+            let mut query: *mut TantivyQuery = ptr::null_mut();
+            assert_eq!(
+                tantivy_query_parse(
+                    index,
+                    query_str.as_ptr(),
+                    field_name_ptrs.as_ptr(),
+                    field_name_ptrs.len(),
+                    &mut query,
+                ),
+                TANTIVY_OK
+            );
+
+            let mut segment_ords = [0u32; 10];
+            let mut doc_ids = [0u32; 10];
+            let mut scores = [0f32; 10];
+            let mut num_hits = 0usize;
+            assert_eq!(
+                tantivy_search(
+                    index,
+                    query,
+                    10,
+                    segment_ords.as_mut_ptr(),
+                    doc_ids.as_mut_ptr(),
+                    scores.as_mut_ptr(),
+                    &mut num_hits,
+                ),
+                TANTIVY_OK
+            );
+            assert_eq!(num_hits, 2);
+            assert!(scores[0] >= scores[1]);
+            let mut doc_ids_found: Vec<u32> = doc_ids[..num_hits].to_vec();
+            doc_ids_found.sort();
+            assert_eq!(doc_ids_found, vec![0, 1]);
+
+            let mut json: *mut c_char = ptr::null_mut();
+            assert_eq!(tantivy_doc_to_json(index, segment_ords[0], 0, &mut json), TANTIVY_OK);
+            let doc_json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(doc_json["title"][0], "Of Mice and Men");
+            assert!(doc_json.get("body").is_none());
+            tantivy_string_destroy(json);
+
+            // errors are reported with a code and a message.
+            let unknown_field = CString::new("unknown").unwrap();
+            let unknown_field_ptr = unknown_field.as_ptr();
+            let mut invalid_query: *mut TantivyQuery = ptr::null_mut();
+            assert_eq!(
+                tantivy_query_parse(
+                    index,
+                    query_str.as_ptr(),
+                    &unknown_field_ptr,
+                    1,
+                    &mut invalid_query,
+                ),
+                TANTIVY_ERROR_INVALID_ARGUMENT
+            );
+            assert!(invalid_query.is_null());
+            assert!(last_error_message().contains("unknown"));
+            assert_eq!(
+                tantivy_search(
+                    ptr::null(),
+                    query,
+                    10,
+                    segment_ords.as_mut_ptr(),
+                    doc_ids.as_mut_ptr(),
+                    scores.as_mut_ptr(),
+                    &mut num_hits,
+                ),
+                TANTIVY_ERROR_INVALID_ARGUMENT
+            );
+            let missing_path = index_dir.path().join("missing");
+            let missing_path = CString::new(missing_path.to_str().unwrap()).unwrap();
+            let mut missing_index: *mut TantivyIndex = ptr::null_mut();
+            assert_ne!(tantivy_index_open(missing_path.as_ptr(), &mut missing_index), TANTIVY_OK);
+            assert!(missing_index.is_null());
+
+            // panics do not cross the boundary.
+            assert_eq!(ffi_try(|| panic!("boom")), TANTIVY_ERROR_PANIC);
+            assert_eq!(last_error_message(), "panic: boom");
+
+            assert_eq!(tantivy_index_reload(index), TANTIVY_OK);
+            tantivy_query_destroy(query);
+            tantivy_index_destroy(index);
+        }
+    }
+}
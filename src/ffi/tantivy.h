@@ -0,0 +1,61 @@
+/*
+ * C API of tantivy.
+ *
+ * Requires tantivy to be compiled with the `ffi` feature.
+ * See `src/ffi/mod.rs` for the documentation of each function.
+ */
+
+#ifndef TANTIVY_H
+#define TANTIVY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TANTIVY_OK 0
+#define TANTIVY_ERROR_INVALID_ARGUMENT 1
+#define TANTIVY_ERROR_IO 2
+#define TANTIVY_ERROR_OTHER 3
+#define TANTIVY_ERROR_PANIC 4
+
+typedef struct TantivyIndex TantivyIndex;
+typedef struct TantivyQuery TantivyQuery;
+
+/* Owned by tantivy, valid until the next failing call on the same thread. */
+const char* tantivy_last_error_message(void);
+
+int tantivy_index_open(const char* path, TantivyIndex** index_out);
+int tantivy_index_reload(const TantivyIndex* index);
+void tantivy_index_destroy(TantivyIndex* index);
+
+int tantivy_query_parse(const TantivyIndex* index,
+                        const char* query,
+                        const char* const* default_fields,
+                        size_t num_default_fields,
+                        TantivyQuery** query_out);
+void tantivy_query_destroy(TantivyQuery* query);
+
+/* The three output buffers must have a length of at least `limit`. */
+int tantivy_search(const TantivyIndex* index,
+                   const TantivyQuery* query,
+                   size_t limit,
+                   uint32_t* segment_ords_out,
+                   uint32_t* doc_ids_out,
+                   float* scores_out,
+                   size_t* num_hits_out);
+
+/* The returned string must be released with `tantivy_string_destroy`. */
+int tantivy_doc_to_json(const TantivyIndex* index,
+                        uint32_t segment_ord,
+                        uint32_t doc_id,
+                        char** json_out);
+void tantivy_string_destroy(char* s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TANTIVY_H */
@@ -51,7 +51,7 @@ fn test_indexing() {
                 for i in 1u64..10u64 {
                     doc.add_u64(multiples_field, random_val * i);
                 }
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
         }
     }
@@ -1,4 +1,5 @@
 use super::operation::DeleteOperation;
+use indexer::Opstamp;
 use std::sync::{Arc, RwLock};
 use std::mem;
 use std::ops::DerefMut;
@@ -180,7 +181,7 @@ impl DeleteCursor {
     ///   queue are consume and the next get will return None.
     /// - the next get will return the first operation with an
     /// `opstamp >= target_opstamp`.
-    pub fn skip_to(&mut self, target_opstamp: u64) {
+    pub fn skip_to(&mut self, target_opstamp: Opstamp) {
         // TODO Can be optimize as we work with block.
         #[cfg_attr(feature = "cargo-clippy", allow(while_let_loop))]
         loop {
@@ -246,6 +247,8 @@ impl DeleteCursor {
 mod tests {
 
     use super::{DeleteOperation, DeleteQueue};
+    use indexer::operation::DeleteTarget;
+    use indexer::Opstamp;
     use schema::{Field, Term};
 
     #[test]
@@ -255,8 +258,8 @@ mod tests {
         let make_op = |i: usize| {
             let field = Field(1u32);
             DeleteOperation {
-                opstamp: i as u64,
-                term: Term::from_field_u64(field, i as u64),
+                opstamp: Opstamp(i as u64),
+                target: DeleteTarget::Term(Term::from_field_u64(field, i as u64)),
             }
         };
 
@@ -266,9 +269,9 @@ mod tests {
         let snapshot = delete_queue.cursor();
         {
             let mut operations_it = snapshot.clone();
-            assert_eq!(operations_it.get().unwrap().opstamp, 1);
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(1));
             operations_it.advance();
-            assert_eq!(operations_it.get().unwrap().opstamp, 2);
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(2));
             operations_it.advance();
             assert!(operations_it.get().is_none());
             operations_it.advance();
@@ -276,20 +279,20 @@ mod tests {
             let mut snapshot2 = delete_queue.cursor();
             assert!(snapshot2.get().is_none());
             delete_queue.push(make_op(3));
-            assert_eq!(snapshot2.get().unwrap().opstamp, 3);
-            assert_eq!(operations_it.get().unwrap().opstamp, 3);
-            assert_eq!(operations_it.get().unwrap().opstamp, 3);
+            assert_eq!(snapshot2.get().unwrap().opstamp, Opstamp(3));
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(3));
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(3));
             operations_it.advance();
             assert!(operations_it.get().is_none());
             operations_it.advance();
         }
         {
             let mut operations_it = snapshot.clone();
-            assert_eq!(operations_it.get().unwrap().opstamp, 1);
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(1));
             operations_it.advance();
-            assert_eq!(operations_it.get().unwrap().opstamp, 2);
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(2));
             operations_it.advance();
-            assert_eq!(operations_it.get().unwrap().opstamp, 3);
+            assert_eq!(operations_it.get().unwrap().opstamp, Opstamp(3));
             operations_it.advance();
             assert!(operations_it.get().is_none());
         }
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use indexer::Opstamp;
 use DocId;
 
 // Doc to opstamp is used to identify which
@@ -18,12 +19,12 @@ use DocId;
 // because of the way document id are allocated.
 #[derive(Clone)]
 pub enum DocToOpstampMapping {
-    WithMap(Arc<Vec<u64>>),
+    WithMap(Arc<Vec<Opstamp>>),
     None,
 }
 
-impl From<Vec<u64>> for DocToOpstampMapping {
-    fn from(opstamps: Vec<u64>) -> DocToOpstampMapping {
+impl From<Vec<Opstamp>> for DocToOpstampMapping {
+    fn from(opstamps: Vec<Opstamp>) -> DocToOpstampMapping {
         DocToOpstampMapping::WithMap(Arc::new(opstamps))
     }
 }
@@ -35,7 +36,7 @@ impl DocToOpstampMapping {
     //
     // The edge case opstamp = some doc opstamp is in practise
     // never called.
-    pub fn compute_doc_limit(&self, target_opstamp: u64) -> DocId {
+    pub fn compute_doc_limit(&self, target_opstamp: Opstamp) -> DocId {
         match *self {
             DocToOpstampMapping::WithMap(ref doc_opstamps) => {
                 match doc_opstamps.binary_search(&target_opstamp) {
@@ -51,12 +52,13 @@ impl DocToOpstampMapping {
 mod tests {
 
     use super::DocToOpstampMapping;
+    use indexer::Opstamp;
 
     #[test]
     fn test_doc_to_opstamp_mapping_none() {
         let doc_to_opstamp_mapping = DocToOpstampMapping::None;
         assert_eq!(
-            doc_to_opstamp_mapping.compute_doc_limit(1),
+            doc_to_opstamp_mapping.compute_doc_limit(Opstamp(1)),
             u32::max_value()
         );
     }
@@ -65,28 +67,33 @@ mod tests {
     fn test_doc_to_opstamp_mapping_complex() {
         {
             let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec![]);
-            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(0u64), 0);
-            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(2u64), 0);
+            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(0u64)), 0);
+            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(2u64)), 0);
         }
         {
-            let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec![1u64]);
-            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(0u64), 0);
-            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(2u64), 1);
+            let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec![Opstamp(1)]);
+            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(0u64)), 0);
+            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(2u64)), 1);
         }
         {
-            let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec![1u64, 12u64, 17u64, 23u64]);
-            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(0u64), 0);
+            let doc_to_opstamp_mapping = DocToOpstampMapping::from(vec![
+                Opstamp(1),
+                Opstamp(12),
+                Opstamp(17),
+                Opstamp(23),
+            ]);
+            assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(0u64)), 0);
             for i in 2u64..13u64 {
-                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(i), 1);
+                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(i)), 1);
             }
             for i in 13u64..18u64 {
-                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(i), 2);
+                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(i)), 2);
             }
             for i in 18u64..24u64 {
-                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(i), 3);
+                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(i)), 3);
             }
             for i in 24u64..30u64 {
-                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(i), 4);
+                assert_eq!(doc_to_opstamp_mapping.compute_doc_limit(Opstamp(i)), 4);
             }
         }
     }
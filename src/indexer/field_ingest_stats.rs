@@ -0,0 +1,54 @@
+use schema::Field;
+use std::collections::HashMap;
+
+/// Statistics about the values of a field, gathered while indexing.
+///
+/// They are reported for each commit by
+/// [`IndexWriter::last_commit_field_stats`](./struct.IndexWriter.html#method.last_commit_field_stats)
+/// and help noticing data quality issues, like a field that suddenly
+/// stops being populated, or whose values suddenly yield many more tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FieldIngestStats {
+    /// Number of documents with at least one value for the field.
+    pub num_docs: u64,
+    /// Number of values of the field.
+    pub num_values: u64,
+    /// Number of tokens produced by the tokenizer of the field,
+    /// dropped tokens included.
+    ///
+    /// It is only computed for the indexed text fields.
+    pub num_tokens: u64,
+    /// Number of values that were removed from their document
+    /// because they did not match the schema
+    /// (see [`ValidationMode::SkipInvalidValues`](./enum.ValidationMode.html)).
+    pub num_skipped_values: u64,
+    /// Number of tokens that were not indexed because
+    /// they exceeded the maximum token length.
+    pub num_dropped_tokens: u64,
+}
+
+impl FieldIngestStats {
+    /// Adds the statistics of another batch of documents.
+    pub fn merge(&mut self, other: &FieldIngestStats) {
+        self.num_docs += other.num_docs;
+        self.num_values += other.num_values;
+        self.num_tokens += other.num_tokens;
+        self.num_skipped_values += other.num_skipped_values;
+        self.num_dropped_tokens += other.num_dropped_tokens;
+    }
+}
+
+/// Adds the statistics of a batch of documents to `stats`.
+///
+/// `batch_stats` are indexed by field id.
+pub(crate) fn merge_field_stats(
+    stats: &mut HashMap<Field, FieldIngestStats>,
+    batch_stats: &[FieldIngestStats],
+) {
+    for (field_id, field_stats) in batch_stats.iter().enumerate() {
+        stats
+            .entry(Field(field_id as u32))
+            .or_insert_with(FieldIngestStats::default)
+            .merge(field_stats);
+    }
+}
@@ -6,32 +6,49 @@ use core::SegmentComponent;
 use core::SegmentId;
 use core::SegmentMeta;
 use core::SegmentReader;
-use indexer::stamper::Stamper;
+use compat;
+use indexer::stamper::{Opstamp, Stamper};
 use datastruct::stacker::Heap;
 use directory::FileProtection;
 use error::{Error, ErrorKind, Result, ResultExt};
 use fastfield::write_delete_bitset;
+use fastfield::{FastFieldReader, FastValue};
 use indexer::delete_queue::{DeleteCursor, DeleteQueue};
 use futures::Canceled;
+use futures::sync::oneshot;
 use datastruct::stacker::hashmap::split_memory;
 use futures::Future;
 use indexer::doc_opstamp_mapping::DocToOpstampMapping;
+use indexer::{merge_field_stats, FieldIngestStats};
 use indexer::MergePolicy;
-use indexer::operation::DeleteOperation;
+use indexer::operation::{DeleteOperation, DeleteTarget};
 use indexer::SegmentEntry;
 use indexer::SegmentWriter;
 use docset::DocSet;
 use schema::IndexRecordOption;
 use schema::Document;
+use schema::FieldValue;
 use schema::Term;
+use schema::Value;
+use schema::{Cardinality, Field};
+use std::any::Any;
+use std::cell::Cell;
+use std::cmp;
+use std::collections::HashMap;
+use std::ops::Range;
+use DocId;
 use std::mem;
 use std::mem::swap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread::JoinHandle;
 use indexer::DirectoryLock;
 use super::operation::AddOperation;
 use super::segment_updater::SegmentUpdater;
 use super::PreparedCommit;
 use std::thread;
+use tokenizer::MAX_TOKEN_LEN;
 
 // Size of the margin for the heap. A segment is closed when the remaining memory
 // in the heap goes below MARGIN_IN_BYTES.
@@ -40,13 +57,65 @@ pub const MARGIN_IN_BYTES: u32 = 1_000_000u32;
 // We impose the memory per thread to be at least 3 MB.
 pub const HEAP_SIZE_LIMIT: u32 = MARGIN_IN_BYTES * 3u32;
 
+// Number of fast field values decoded at once when applying range deletes.
+const DELETE_RANGE_BUFFER_LEN: usize = 1_024;
+
 // Add document will block if the number of docs waiting in the queue to be indexed
 // reaches `PIPELINE_MAX_SIZE_IN_DOCS`
 const PIPELINE_MAX_SIZE_IN_DOCS: usize = 10_000;
 
+// Maximum number of documents added between two commits for them
+// to be coalesced into a single segment (see `set_commit_segment_coalescing`).
+const COMMIT_COALESCING_MAX_DOCS: usize = 1_000;
+
 type DocumentSender = chan::Sender<AddOperation>;
 type DocumentReceiver = chan::Receiver<AddOperation>;
 
+/// Callback called with the id and the number of documents
+/// of the segments flushed by the indexing threads.
+type SegmentFlushListener = Arc<Fn(SegmentId, DocId) + Send + Sync>;
+type SegmentFlushListenerCell = Arc<RwLock<Option<SegmentFlushListener>>>;
+
+/// Message and opstamp of the first panic of an indexing worker.
+type WorkerPanicCell = Arc<Mutex<Option<(String, Opstamp)>>>;
+
+/// Result of the addition of a document to the `IndexWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddDocumentResult {
+    /// Opstamp of the add operation.
+    pub opstamp: Opstamp,
+    /// `DocId` of the document within the segment it was added to.
+    ///
+    /// It is only known if the `IndexWriter` has a single indexing thread,
+    /// and reports them (see
+    /// [`set_report_segment_local_docs`][set_report_segment_local_docs]).
+    /// It is `None` otherwise.
+    ///
+    /// [set_report_segment_local_docs]:
+    /// ./struct.IndexWriter.html#method.set_report_segment_local_docs
+    pub segment_local_doc: Option<DocId>,
+}
+
+/// Defines how the documents added to the `IndexWriter`
+/// are checked against the schema.
+///
+/// Values of the wrong type, facet fields holding text that is
+/// not a valid facet path (e.g. missing the leading `/`), and
+/// single valued fast fields holding several values are invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Documents holding an invalid value are rejected.
+    Strict,
+    /// Invalid values are removed, and the rest of
+    /// the document is indexed.
+    SkipInvalidValues,
+    /// Documents are not checked.
+    ///
+    /// Indexing an invalid document may then make its indexing thread panic.
+    /// Facets must be added as `Facet` values.
+    Off,
+}
+
 /// `IndexWriter` is the user entry-point to add document to an index.
 ///
 /// It manages a small number of indexing thread, as well as a shared
@@ -62,6 +131,33 @@ pub struct IndexWriter {
 
     heap_size_in_bytes_per_thread: usize,
 
+    // number of bits of the initial size of the term hash table,
+    // shared with the indexing workers.
+    term_table_num_bits: Arc<AtomicUsize>,
+
+    // maximum length of the indexed tokens, shared with the indexing workers.
+    max_token_len: Arc<AtomicUsize>,
+    // heap usage above which postings are spilled to disk, `0` if disabled.
+    postings_spill_threshold: Arc<AtomicUsize>,
+    // number of threads serializing the fast fields of a new segment.
+    num_serialization_threads: Arc<AtomicUsize>,
+    // number of tokens dropped by the indexing workers since the last commit.
+    num_dropped_tokens: Arc<AtomicUsize>,
+    last_commit_num_dropped_tokens: usize,
+    // number of times the postings were spilled since the last commit.
+    num_spilled_runs: Arc<AtomicUsize>,
+    last_commit_num_spilled_runs: usize,
+    // statistics of the term hash tables of the segments flushed since the last commit.
+    term_table_stats: Arc<TermTableStats>,
+    last_commit_num_term_rehashes: usize,
+    last_commit_max_term_probe_len: usize,
+    // statistics of the segments flushed by the indexing workers since the last commit.
+    field_ingest_stats: Arc<Mutex<HashMap<Field, FieldIngestStats>>>,
+    // number of values skipped by the validation since the last commit, per field.
+    num_skipped_values: HashMap<Field, u64>,
+    last_commit_field_stats: HashMap<Field, FieldIngestStats>,
+    last_commit_num_deleted_docs: u64,
+
     workers_join_handle: Vec<JoinHandle<Result<()>>>,
 
     document_receiver: DocumentReceiver,
@@ -78,7 +174,25 @@ pub struct IndexWriter {
     delete_queue: DeleteQueue,
 
     stamper: Stamper,
-    committed_opstamp: u64,
+    committed_opstamp: Opstamp,
+
+    validation_mode: ValidationMode,
+
+    report_segment_local_docs: bool,
+    segment_flush_listener: SegmentFlushListenerCell,
+
+    commit_segment_coalescing: bool,
+    // documents held back until the commit, to be indexed in a single segment,
+    // and the delete cursor of this segment.
+    coalesced_docs: Vec<AddOperation>,
+    coalesced_delete_cursor: Option<DeleteCursor>,
+    // estimated memory used by the documents held back.
+    coalesced_num_bytes: usize,
+    // set when too many documents were added since the last commit to coalesce them.
+    coalescing_overflowed: bool,
+
+    // set by the indexing workers when they panic.
+    worker_panic: WorkerPanicCell,
 }
 
 // IndexWriter cannot be sent to another thread.
@@ -116,17 +230,36 @@ pub fn open_index_writer(
 
     let delete_queue = DeleteQueue::new();
 
-    let current_opstamp = index.load_metas()?.opstamp;
+    let metas = index.load_metas()?;
+    let current_opstamp = metas.opstamp;
 
-    let stamper = Stamper::new(current_opstamp);
+    // opstamps keep increasing across the writers of the index.
+    let stamper = Stamper::new(metas.last_opstamp());
 
     let segment_updater =
         SegmentUpdater::new(index.clone(), stamper.clone(), &delete_queue.cursor())?;
 
+    let (_, term_table_num_bits) = split_memory(heap_size_in_bytes_per_thread);
+
     let mut index_writer = IndexWriter {
         _directory_lock: Some(directory_lock),
 
         heap_size_in_bytes_per_thread,
+        term_table_num_bits: Arc::new(AtomicUsize::new(term_table_num_bits)),
+        max_token_len: Arc::new(AtomicUsize::new(MAX_TOKEN_LEN)),
+        postings_spill_threshold: Arc::new(AtomicUsize::new(0)),
+        num_serialization_threads: Arc::new(AtomicUsize::new(1)),
+        num_dropped_tokens: Arc::new(AtomicUsize::new(0)),
+        last_commit_num_dropped_tokens: 0,
+        num_spilled_runs: Arc::new(AtomicUsize::new(0)),
+        last_commit_num_spilled_runs: 0,
+        term_table_stats: Arc::new(TermTableStats::default()),
+        last_commit_num_term_rehashes: 0,
+        last_commit_max_term_probe_len: 0,
+        field_ingest_stats: Arc::new(Mutex::new(HashMap::new())),
+        num_skipped_values: HashMap::new(),
+        last_commit_field_stats: HashMap::new(),
+        last_commit_num_deleted_docs: 0,
         index: index.clone(),
 
         document_receiver,
@@ -145,17 +278,127 @@ pub fn open_index_writer(
         generation: 0,
 
         worker_id: 0,
+
+        validation_mode: ValidationMode::Strict,
+
+        report_segment_local_docs: false,
+        segment_flush_listener: Arc::new(RwLock::new(None)),
+
+        commit_segment_coalescing: false,
+        coalesced_docs: Vec::new(),
+        coalesced_delete_cursor: None,
+        coalesced_num_bytes: 0,
+        coalescing_overflowed: false,
+
+        worker_panic: Arc::new(Mutex::new(None)),
     };
     index_writer.start_workers()?;
     Ok(index_writer)
 }
 
+/// Statistics of the term hash tables of the segments
+/// flushed by the indexing workers.
+#[derive(Default)]
+struct TermTableStats {
+    num_rehashes: AtomicUsize,
+    max_probe_len: AtomicUsize,
+}
+
+impl TermTableStats {
+    fn record(&self, num_rehashes: usize, max_probe_len: usize) {
+        self.num_rehashes.fetch_add(num_rehashes, Ordering::SeqCst);
+        let mut current_max = self.max_probe_len.load(Ordering::SeqCst);
+        while current_max < max_probe_len {
+            let previous_max = self.max_probe_len.compare_and_swap(
+                current_max,
+                max_probe_len,
+                Ordering::SeqCst,
+            );
+            if previous_max == current_max {
+                break;
+            }
+            current_max = previous_max;
+        }
+    }
+
+    /// Returns the number of rehashes and the longest probe
+    /// recorded so far, and resets them.
+    fn take(&self) -> (usize, usize) {
+        (
+            self.num_rehashes.swap(0, Ordering::SeqCst),
+            self.max_probe_len.swap(0, Ordering::SeqCst),
+        )
+    }
+}
+
+/// Estimates the memory used by a document.
+fn document_num_bytes(document: &Document) -> usize {
+    document
+        .field_values()
+        .iter()
+        .map(|field_value| {
+            let value_num_bytes = match *field_value.value() {
+                Value::Str(ref text) => text.len(),
+                Value::Facet(ref facet) => facet.encoded_bytes().len(),
+                Value::U64(_) | Value::I64(_) => 0,
+            };
+            mem::size_of::<FieldValue>() + value_num_bytes
+        })
+        .sum()
+}
+
+fn panic_message(payload: &(Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Marks as deleted all of the documents (below `num_docs`)
+/// whose fast field value is within `range`.
+///
+/// The fast field min and max values are checked first, so that
+/// segments that cannot contain any such documents are not scanned.
+///
+/// Returns true iff at least one document was marked as deleted.
+fn delete_fast_field_range<Item: FastValue + PartialOrd>(
+    delete_bitset: &mut BitSet,
+    ff_reader: &FastFieldReader<Item>,
+    range: &Range<Item>,
+    num_docs: DocId,
+) -> bool {
+    if range.start >= range.end || ff_reader.max_value() < range.start
+        || ff_reader.min_value() >= range.end
+    {
+        return false;
+    }
+    let mut might_have_changed = false;
+    let mut buffer = [Item::default(); DELETE_RANGE_BUFFER_LEN];
+    let mut start: DocId = 0;
+    while start < num_docs {
+        let len = cmp::min(DELETE_RANGE_BUFFER_LEN, (num_docs - start) as usize);
+        let vals = &mut buffer[..len];
+        ff_reader.get_range(start, vals);
+        for (offset, val) in vals.iter().enumerate() {
+            if range.start <= *val && *val < range.end {
+                delete_bitset.insert(start as usize + offset);
+                might_have_changed = true;
+            }
+        }
+        start += len as DocId;
+    }
+    might_have_changed
+}
+
 pub fn compute_deleted_bitset(
     delete_bitset: &mut BitSet,
     segment_reader: &SegmentReader,
     delete_cursor: &mut DeleteCursor,
     doc_opstamps: &DocToOpstampMapping,
-    target_opstamp: u64,
+    target_opstamp: Opstamp,
 ) -> Result<bool> {
     let mut might_have_changed = false;
 
@@ -171,14 +414,31 @@ pub fn compute_deleted_bitset(
                 // Limit doc helps identify the first document
                 // that may be affected by the delete operation.
                 let limit_doc = doc_opstamps.compute_doc_limit(delete_op.opstamp);
-                let inverted_index = segment_reader.inverted_index(delete_op.term.field());
-                if let Some(mut docset) =
-                    inverted_index.read_postings(&delete_op.term, IndexRecordOption::Basic)
-                {
-                    while docset.advance() {
-                        let deleted_doc = docset.doc();
-                        if deleted_doc < limit_doc {
-                            delete_bitset.insert(deleted_doc as usize);
+                let num_docs = cmp::min(limit_doc, segment_reader.max_doc());
+                match delete_op.target {
+                    DeleteTarget::Term(ref term) => {
+                        let inverted_index = segment_reader.inverted_index(term.field());
+                        if let Some(mut docset) =
+                            inverted_index.read_postings(term, IndexRecordOption::Basic)
+                        {
+                            while docset.advance() {
+                                let deleted_doc = docset.doc();
+                                if deleted_doc < limit_doc {
+                                    delete_bitset.insert(deleted_doc as usize);
+                                    might_have_changed = true;
+                                }
+                            }
+                        }
+                    }
+                    DeleteTarget::U64Range(field, ref range) => {
+                        let ff_reader = segment_reader.fast_field_reader::<u64>(field)?;
+                        if delete_fast_field_range(delete_bitset, &ff_reader, range, num_docs) {
+                            might_have_changed = true;
+                        }
+                    }
+                    DeleteTarget::I64Range(field, ref range) => {
+                        let ff_reader = segment_reader.fast_field_reader::<i64>(field)?;
+                        if delete_fast_field_range(delete_bitset, &ff_reader, range, num_docs) {
                             might_have_changed = true;
                         }
                     }
@@ -192,12 +452,69 @@ pub fn compute_deleted_bitset(
     Ok(might_have_changed)
 }
 
+/// Returns true iff the delete operation cannot delete any of the
+/// documents of the segment, given the range of values of its fast
+/// fields recorded in its meta.
+fn is_out_of_value_range(segment_meta: &SegmentMeta, delete_target: &DeleteTarget) -> bool {
+    let (field, start, end) = match *delete_target {
+        DeleteTarget::Term(_) => {
+            return false;
+        }
+        DeleteTarget::U64Range(field, ref range) => {
+            (field, range.start.to_u64(), range.end.to_u64())
+        }
+        DeleteTarget::I64Range(field, ref range) => {
+            (field, range.start.to_u64(), range.end.to_u64())
+        }
+    };
+    if start >= end {
+        return true;
+    }
+    match segment_meta.fast_field_value_range(field) {
+        Some((min_value, max_value)) => max_value < start || min_value >= end,
+        // the segment predates the recording of the value ranges.
+        None => false,
+    }
+}
+
+/// Skips the delete operations up to the target opstamp
+/// if none of them can delete any document of the segment.
+///
+/// Returns true iff the operations were skipped, in which case
+/// the segment does not need to be opened.
+fn skip_out_of_range_deletes(segment_entry: &mut SegmentEntry, target_opstamp: Opstamp) -> bool {
+    if segment_entry.delete_bitset().is_some() {
+        // the deletes computed when the segment was flushed
+        // have yet to be written.
+        return false;
+    }
+    let mut delete_cursor = segment_entry.delete_cursor().clone();
+    loop {
+        let is_skippable = match delete_cursor.get() {
+            Some(delete_op) if delete_op.opstamp <= target_opstamp => {
+                is_out_of_value_range(segment_entry.meta(), &delete_op.target)
+            }
+            _ => break,
+        };
+        if !is_skippable {
+            return false;
+        }
+        delete_cursor.advance();
+    }
+    *segment_entry.delete_cursor() = delete_cursor;
+    true
+}
+
 /// Advance delete for the given segment up
 /// to the target opstamp.
+///
+/// Segments that none of the delete operations can affect, because
+/// they only target ranges of fast field values that are out of the
+/// range of values of the segment, are left untouched.
 pub fn advance_deletes(
     mut segment: Segment,
     segment_entry: &mut SegmentEntry,
-    target_opstamp: u64,
+    target_opstamp: Opstamp,
 ) -> Result<Option<FileProtection>> {
     let mut file_protect: Option<FileProtection> = None;
 
@@ -208,6 +525,9 @@ pub fn advance_deletes(
                 return Ok(file_protect);
             }
         }
+        if skip_out_of_range_deletes(segment_entry, target_opstamp) {
+            return Ok(file_protect);
+        }
         let segment_reader = SegmentReader::open(&segment)?;
         let max_doc = segment_reader.max_doc();
 
@@ -245,9 +565,19 @@ pub fn advance_deletes(
     Ok(file_protect)
 }
 
+#[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
 fn index_documents(
     heap: &mut Heap,
-    table_size: usize,
+    table_bits: usize,
+    max_token_len: usize,
+    postings_spill_threshold: usize,
+    num_serialization_threads: usize,
+    num_dropped_tokens: &AtomicUsize,
+    num_spilled_runs: &AtomicUsize,
+    term_table_stats: &TermTableStats,
+    field_ingest_stats: &Mutex<HashMap<Field, FieldIngestStats>>,
+    segment_flush_listener: &SegmentFlushListenerCell,
+    current_opstamp: &Cell<Opstamp>,
     segment: &Segment,
     generation: usize,
     document_iterator: &mut Iterator<Item = AddOperation>,
@@ -258,32 +588,63 @@ fn index_documents(
     let schema = segment.schema();
     let segment_id = segment.id();
     let mut segment_writer =
-        SegmentWriter::for_segment(heap, table_size, segment.clone(), &schema)?;
-    for doc in document_iterator {
+        SegmentWriter::for_segment(heap, table_bits, segment.clone(), &schema)?;
+    segment_writer.set_max_token_len(max_token_len)?;
+    if postings_spill_threshold > 0 {
+        segment_writer.set_postings_spill_threshold(postings_spill_threshold);
+    }
+    segment_writer.set_num_serialization_threads(num_serialization_threads)?;
+    for mut doc in document_iterator {
+        current_opstamp.set(doc.opstamp);
+        let doc_id_sender_opt = doc.doc_id_sender.take();
         segment_writer.add_document(doc, &schema)?;
-        // There is two possible conditions to close the segment.
-        // One is the memory arena dedicated to the segment is
-        // getting full.
-        if segment_writer.is_buffer_full() {
-            info!(
-                "Buffer limit reached, flushing segment with maxdoc={}.",
-                segment_writer.max_doc()
-            );
-            break;
+        if let Some(doc_id_sender) = doc_id_sender_opt {
+            // the `IndexWriter` may have been dropped in the meantime.
+            let _ = doc_id_sender.send(segment_writer.max_doc() - 1);
         }
-        // The second is the term dictionary hash table
-        // is reaching saturation.
+        // The segment is closed when the memory arena dedicated
+        // to the segment is getting full.
         //
-        // Tantivy does not resize its hashtable. When it reaches
-        // capacity, we just stop indexing new document.
-        if segment_writer.is_term_saturated() {
+        // The term dictionary hash table on the other hand
+        // is resized as it fills up, and does not trigger any flush.
+        if segment_writer.is_buffer_full() {
             info!(
-                "Term dic saturated, flushing segment with maxdoc={}.",
+                "Buffer limit reached, flushing segment with maxdoc={}.",
                 segment_writer.max_doc()
             );
             break;
         }
     }
+    info!(
+        "Term hash table resized {} times, max probe length {}.",
+        segment_writer.num_term_rehashes(),
+        segment_writer.max_term_probe_len()
+    );
+    term_table_stats.record(
+        segment_writer.num_term_rehashes(),
+        segment_writer.max_term_probe_len(),
+    );
+    if segment_writer.num_spilled_runs() > 0 {
+        info!(
+            "Postings spilled {} times, merging the runs.",
+            segment_writer.num_spilled_runs()
+        );
+        num_spilled_runs.fetch_add(segment_writer.num_spilled_runs(), Ordering::SeqCst);
+    }
+    let num_segment_dropped_tokens = segment_writer.num_dropped_tokens();
+    if num_segment_dropped_tokens > 0 {
+        warn!(
+            "{} tokens exceeding the maximum token length were not indexed.",
+            num_segment_dropped_tokens
+        );
+        num_dropped_tokens.fetch_add(num_segment_dropped_tokens, Ordering::SeqCst);
+    }
+    merge_field_stats(
+        &mut *field_ingest_stats
+            .lock()
+            .expect("Field ingest stats lock poisoned"),
+        segment_writer.field_ingest_stats(),
+    );
 
     if !segment_updater.is_alive() {
         return Ok(false);
@@ -295,12 +656,23 @@ fn index_documents(
     // the worker thread.
     assert!(num_docs > 0);
 
-    let doc_opstamps: Vec<u64> = segment_writer.finalize()?;
+    let total_num_tokens = segment_writer.total_num_tokens();
+    let index_record_options = segment_writer.index_record_options();
+    let int_value_ranges = segment_writer.int_value_ranges();
+    let fast_field_value_ranges = segment_writer.fast_field_value_ranges();
+
+    let doc_opstamps: Vec<Opstamp> = segment_writer.finalize()?;
 
     let mut segment_meta = SegmentMeta::new(segment_id);
     segment_meta.set_max_doc(num_docs);
+    segment_meta.set_total_num_tokens(total_num_tokens);
+    segment_meta.set_index_record_options(index_record_options);
+    segment_meta.set_int_value_ranges(int_value_ranges);
+    segment_meta.set_fast_field_value_ranges(fast_field_value_ranges);
+    segment_meta.set_format_version(compat::INDEX_FORMAT_VERSION);
+    segment.clone().write_meta_file(&segment_meta, &[])?;
 
-    let last_docstamp: u64 = *(doc_opstamps.last().unwrap());
+    let last_docstamp: Opstamp = *(doc_opstamps.last().unwrap());
 
     let doc_to_opstamps = DocToOpstampMapping::from(doc_opstamps);
     let segment_reader = SegmentReader::open(segment)?;
@@ -321,7 +693,17 @@ fn index_documents(
         }
     });
 
-    Ok(segment_updater.add_segment(generation, segment_entry))
+    let segment_added = segment_updater.add_segment(generation, segment_entry);
+    if segment_added {
+        let segment_flush_listener_opt = segment_flush_listener
+            .read()
+            .expect("Segment flush listener lock poisoned")
+            .clone();
+        if let Some(segment_flush_listener) = segment_flush_listener_opt {
+            (*segment_flush_listener)(segment_id, num_docs);
+        }
+    }
+    Ok(segment_added)
 }
 
 impl IndexWriter {
@@ -372,25 +754,49 @@ impl IndexWriter {
     /// The thread consumes documents from the pipeline.
     ///
     fn add_indexing_worker(&mut self) -> Result<()> {
-        let document_receiver_clone = self.document_receiver.clone();
+        let document_receiver = self.document_receiver.clone();
+        let delete_cursor = self.delete_queue.cursor();
+        let join_handle = self.spawn_indexing_worker(document_receiver, delete_cursor)?;
+        self.workers_join_handle.push(join_handle);
+        Ok(())
+    }
+
+    /// Spawns a worker thread indexing the documents of `document_receiver`,
+    /// until the channel is closed.
+    fn spawn_indexing_worker(
+        &mut self,
+        document_receiver: DocumentReceiver,
+        mut delete_cursor: DeleteCursor,
+    ) -> Result<JoinHandle<Result<()>>> {
         let mut segment_updater = self.segment_updater.clone();
-        let (heap_size, table_size) = split_memory(self.heap_size_in_bytes_per_thread);
-        info!("heap size {}, table_size {}", heap_size, table_size);
+        let (heap_size, _) = split_memory(self.heap_size_in_bytes_per_thread);
+        info!("heap size {}", heap_size);
         let mut heap = Heap::with_capacity(heap_size);
+        let term_table_num_bits = Arc::clone(&self.term_table_num_bits);
+        let max_token_len = Arc::clone(&self.max_token_len);
+        let postings_spill_threshold = Arc::clone(&self.postings_spill_threshold);
+        let num_serialization_threads = Arc::clone(&self.num_serialization_threads);
+        let num_dropped_tokens = Arc::clone(&self.num_dropped_tokens);
+        let num_spilled_runs = Arc::clone(&self.num_spilled_runs);
+        let term_table_stats = Arc::clone(&self.term_table_stats);
+        let field_ingest_stats = Arc::clone(&self.field_ingest_stats);
+        let segment_flush_listener = Arc::clone(&self.segment_flush_listener);
+        let worker_panic = Arc::clone(&self.worker_panic);
 
         let generation = self.generation;
 
-        let mut delete_cursor = self.delete_queue.cursor();
-
         let join_handle: JoinHandle<Result<()>> = thread::Builder::new()
             .name(format!(
                 "indexing thread {} for gen {}",
                 self.worker_id, generation
             ))
             .spawn(move || {
-                loop {
+                // opstamp of the document being indexed, or of the last document
+                // of the segment while it is being flushed.
+                let current_opstamp = Cell::new(Opstamp(0));
+                let worker_result = panic::catch_unwind(AssertUnwindSafe(|| loop {
                     let mut document_iterator =
-                        document_receiver_clone.clone().into_iter().peekable();
+                        document_receiver.clone().into_iter().peekable();
 
                     // the peeking here is to avoid
                     // creating a new segment's files
@@ -410,18 +816,49 @@ impl IndexWriter {
                     let segment = segment_updater.new_segment();
                     index_documents(
                         &mut heap,
-                        table_size,
+                        term_table_num_bits.load(Ordering::SeqCst),
+                        max_token_len.load(Ordering::SeqCst),
+                        postings_spill_threshold.load(Ordering::SeqCst),
+                        num_serialization_threads.load(Ordering::SeqCst),
+                        &num_dropped_tokens,
+                        &num_spilled_runs,
+                        &term_table_stats,
+                        &field_ingest_stats,
+                        &segment_flush_listener,
+                        &current_opstamp,
                         &segment,
                         generation,
                         &mut document_iterator,
                         &mut segment_updater,
                         delete_cursor.clone(),
                     )?;
-                }
+                }));
+                worker_result.unwrap_or_else(|payload| {
+                    let message = panic_message(&*payload);
+                    let opstamp = current_opstamp.get();
+                    error!(
+                        "Indexing worker panicked on the document with opstamp {}: {}",
+                        opstamp, message
+                    );
+                    let mut worker_panic = worker_panic
+                        .lock()
+                        .expect("Worker panic lock poisoned");
+                    if worker_panic.is_none() {
+                        *worker_panic = Some((message.clone(), opstamp));
+                    }
+                    drop(worker_panic);
+                    // The writer keeps a receiver of the document queue, so that the queue
+                    // never disconnects: if this was the only worker, `add_document` would
+                    // block forever on a full queue, and never report the panic.
+                    //
+                    // The documents are discarded until the queue is cut
+                    // by a commit or a rollback.
+                    for _ in document_receiver.clone() {}
+                    bail!(ErrorKind::WorkerPanicked(message, opstamp))
+                })
             })?;
         self.worker_id += 1;
-        self.workers_join_handle.push(join_handle);
-        Ok(())
+        Ok(join_handle)
     }
 
     /// Accessor to the merge policy.
@@ -434,6 +871,415 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
 
+    /// Returns the number of bits of the initial size
+    /// of the term hash table of the segments being written.
+    pub fn term_table_num_bits(&self) -> usize {
+        self.term_table_num_bits.load(Ordering::SeqCst)
+    }
+
+    /// Sets the initial size of the term hash table of the segments
+    /// being written, expressed as a number of bits.
+    /// (The table starts with `2^num_bits` buckets).
+    ///
+    /// The table is doubled whenever its load factor exceeds `0.7`.
+    /// A larger initial table avoids these rehashes when indexing
+    /// fields with a very high cardinality, at the cost of memory.
+    /// (See `num_term_rehashes()`.)
+    ///
+    /// By default, a third of the per-thread memory budget is
+    /// dedicated to the initial table. The table is not allocated
+    /// within the heap, but its growth is charged to the heap, so that
+    /// a segment whose table grows is flushed earlier.
+    ///
+    /// The change applies to the segments created after the call.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if `num_bits` is not
+    /// within `[1, 32)`.
+    pub fn set_term_table_num_bits(&self, num_bits: usize) -> Result<()> {
+        if num_bits == 0 || num_bits >= 32 {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The number of bits of the term hash table must be within [1, 32), got {}.",
+                num_bits
+            )));
+        }
+        self.term_table_num_bits.store(num_bits, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the maximum length of the indexed tokens, in bytes.
+    pub fn max_token_len(&self) -> usize {
+        self.max_token_len.load(Ordering::SeqCst)
+    }
+
+    /// Sets the maximum length of the indexed tokens, in bytes.
+    ///
+    /// Longer tokens are dropped, whatever the tokenizer of their field,
+    /// and counted in `num_dropped_tokens()`. The rest of the document
+    /// is indexed normally.
+    /// The limit defaults to (and may not exceed) `MAX_TOKEN_LEN`.
+    /// A lower limit can also be applied to a specific tokenizer
+    /// with the `RemoveLongFilter`.
+    ///
+    /// The change applies to the segments created after the call.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if `max_token_len`
+    /// exceeds `MAX_TOKEN_LEN`.
+    pub fn set_max_token_len(&self, max_token_len: usize) -> Result<()> {
+        if max_token_len > MAX_TOKEN_LEN {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The maximum token length may not exceed {}, got {}.",
+                MAX_TOKEN_LEN, max_token_len
+            )));
+        }
+        self.max_token_len.store(max_token_len, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the heap usage, in bytes, above which the postings
+    /// are spilled to disk, if spilling is enabled.
+    pub fn postings_spill_threshold(&self) -> Option<usize> {
+        match self.postings_spill_threshold.load(Ordering::SeqCst) {
+            0 => None,
+            postings_spill_threshold => Some(postings_spill_threshold),
+        }
+    }
+
+    /// Enables (or disables, with `None`) the spilling of the postings to disk.
+    ///
+    /// By default, a segment is flushed as soon as the per-thread heap,
+    /// holding its terms and postings, is full.
+    /// With spilling enabled, whenever the heap holds more than
+    /// `postings_spill_threshold` bytes, its postings are written to an
+    /// anonymous temporary file, sorted by term, and indexing resumes
+    /// with an empty heap. The temporary files are merged when the segment
+    /// is flushed, on commit, or if the heap gets full despite the spilling.
+    ///
+    /// This makes it possible to build large segments with a bounded heap.
+    /// The fast fields and the field norms of the segment are still
+    /// kept in anonymous memory until the segment is flushed.
+    ///
+    /// The change applies to the segments created after the call.
+    pub fn set_postings_spill_threshold(&self, postings_spill_threshold: Option<usize>) {
+        assert!(
+            postings_spill_threshold != Some(0),
+            "The postings spill threshold must be positive."
+        );
+        self.postings_spill_threshold
+            .store(postings_spill_threshold.unwrap_or(0), Ordering::SeqCst);
+    }
+
+    /// Returns the number of threads serializing the postings, the fast
+    /// fields and the field norms of each new segment.
+    pub fn num_serialization_threads(&self) -> usize {
+        self.num_serialization_threads.load(Ordering::SeqCst)
+    }
+
+    /// Sets the number of threads serializing the postings, the fast fields
+    /// and the field norms of each new segment, when it is flushed.
+    ///
+    /// By default, a segment is serialized by its indexing thread, one
+    /// component after the other. With more than one thread, the postings
+    /// are split into ranges of fields with about the same number of terms,
+    /// and serialized in memory in parallel. The fast fields and the field
+    /// norms are then split into ranges of fields, serialized in memory in
+    /// the background while the store is being finalized. All of them are
+    /// appended in the order of the fields. This reduces the latency of the
+    /// commits on schemas with many fields, at the expense of some extra
+    /// memory.
+    ///
+    /// If the postings of the segment were spilled to disk (see
+    /// `set_postings_spill_threshold`), they are merged by a single thread.
+    ///
+    /// The segment files are the same whatever the number of threads.
+    ///
+    /// The change applies to the segments created after the call.
+    ///
+    /// # Errors
+    /// Returns `InvalidArgument` if `num_threads` is 0.
+    pub fn set_num_serialization_threads(&self, num_threads: usize) -> Result<()> {
+        if num_threads == 0 {
+            bail!(ErrorKind::InvalidArgument(
+                "The number of serialization threads must be at least 1.".to_string()
+            ));
+        }
+        self.num_serialization_threads
+            .store(num_threads, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns whether the files of the new segments are
+    /// verified before they get published.
+    pub fn verify_segments_on_commit(&self) -> bool {
+        self.segment_updater.verify_segments()
+    }
+
+    /// Enables (the default) or disables the verification of the files of
+    /// the new segments, before a commit or a merge gets published.
+    ///
+    /// All of the component files of each new segment are checked to exist,
+    /// and their footers and headers to be consistent with their length.
+    /// Only a few bytes of each file are read.
+    ///
+    /// If a file is corrupted, e.g. because it was written short on a
+    /// full filesystem, the commit fails with a `CorruptedFile` error
+    /// and the index remains at the previous commit. The `IndexWriter`
+    /// should then be rolled back. A corrupted merge is cancelled.
+    pub fn set_verify_segments_on_commit(&self, verify_segments: bool) {
+        self.segment_updater.set_verify_segments(verify_segments);
+    }
+
+    /// Returns the number of tokens that were not indexed
+    /// in the last commit, because they exceeded the maximum token length.
+    pub fn num_dropped_tokens(&self) -> usize {
+        self.last_commit_num_dropped_tokens
+    }
+
+    /// Returns the number of times the postings were spilled to disk
+    /// while indexing the documents of the last commit.
+    ///
+    /// See `set_postings_spill_threshold`.
+    pub fn num_spilled_runs(&self) -> usize {
+        self.last_commit_num_spilled_runs
+    }
+
+    /// Returns the number of times the term hash tables of the
+    /// segments of the last commit were resized.
+    ///
+    /// A large number suggests increasing `set_term_table_num_bits`.
+    pub fn num_term_rehashes(&self) -> usize {
+        self.last_commit_num_term_rehashes
+    }
+
+    /// Returns the longest probe sequence observed in the term hash
+    /// tables of the segments of the last commit.
+    ///
+    /// A large value is a sign of a poor hash distribution.
+    pub fn max_term_probe_len(&self) -> usize {
+        self.last_commit_max_term_probe_len
+    }
+
+    /// Returns statistics about the values of the documents
+    /// of the last commit, for each of the fields of the schema.
+    ///
+    /// They are computed by each indexing thread for the segments
+    /// it flushes, and gathered when the commit is prepared.
+    /// Documents deleted in the same commit are still accounted for.
+    pub fn last_commit_field_stats(&self) -> &HashMap<Field, FieldIngestStats> {
+        &self.last_commit_field_stats
+    }
+
+    /// Returns the number of documents deleted by the last commit.
+    ///
+    /// It includes the documents deleted by the delete operations of the
+    /// commit, whether they were targetted by a term or by a fast field
+    /// range, and the documents of the commit that were deleted by an
+    /// `upsert_document`. Documents deleted by a merge are not counted.
+    pub fn last_commit_num_deleted_docs(&self) -> u64 {
+        self.last_commit_num_deleted_docs
+    }
+
+    /// Gathers the field statistics of the documents added since the last commit.
+    fn take_field_stats(&mut self) -> HashMap<Field, FieldIngestStats> {
+        let mut field_stats = mem::replace(
+            &mut *self.field_ingest_stats
+                .lock()
+                .expect("Field ingest stats lock poisoned"),
+            HashMap::new(),
+        );
+        let num_fields = self.index.schema().fields().len();
+        for field_id in 0..num_fields {
+            field_stats
+                .entry(Field(field_id as u32))
+                .or_insert_with(FieldIngestStats::default);
+        }
+        for (field, num_skipped_values) in self.num_skipped_values.drain() {
+            if let Some(stats) = field_stats.get_mut(&field) {
+                stats.num_skipped_values += num_skipped_values;
+            }
+        }
+        field_stats
+    }
+
+    /// Returns an error if one of the indexing workers panicked.
+    ///
+    /// The documents it was indexing are lost, so the `IndexWriter`
+    /// may not be used anymore until it is rolled back.
+    fn check_worker_panic(&self) -> Result<()> {
+        let worker_panic = self.worker_panic
+            .lock()
+            .expect("Worker panic lock poisoned");
+        if let Some((ref message, opstamp)) = *worker_panic {
+            bail!(ErrorKind::WorkerPanicked(message.clone(), opstamp));
+        }
+        Ok(())
+    }
+
+    /// Returns how the added documents are checked against the schema.
+    pub fn validation_mode(&self) -> ValidationMode {
+        self.validation_mode
+    }
+
+    /// Sets how the added documents are checked against the schema.
+    ///
+    /// Defaults to `ValidationMode::Strict`. Validation is performed
+    /// in the thread calling `add_document`, so performance sensitive
+    /// users that trust their documents may turn it `Off`.
+    pub fn set_validation(&mut self, validation_mode: ValidationMode) {
+        self.validation_mode = validation_mode;
+    }
+
+    /// Makes `add_document` report the `DocId` each document gets within
+    /// its segment, in [`AddDocumentResult::segment_local_doc`][segment_local_doc].
+    ///
+    /// This is only possible with a single indexing thread, in which case
+    /// `add_document` waits for the document to be indexed before returning,
+    /// which slows down indexing. With several indexing threads, this
+    /// setting is ignored and `segment_local_doc` is always `None`.
+    ///
+    /// The documents of a segment get consecutive `DocId`s starting from 0,
+    /// in the order they were added. The end of a segment is signaled to the
+    /// listener registered with
+    /// [`set_segment_flush_listener`](#method.set_segment_flush_listener).
+    ///
+    /// [segment_local_doc]: ./struct.AddDocumentResult.html#structfield.segment_local_doc
+    pub fn set_report_segment_local_docs(&mut self, report_segment_local_docs: bool) {
+        self.report_segment_local_docs = report_segment_local_docs;
+    }
+
+    /// Registers a callback called by the indexing threads, with the id and
+    /// the number of documents of each segment they flush.
+    ///
+    /// The callback is called once the segment is written,
+    /// before it is committed. Segments resulting from a merge
+    /// are not reported.
+    pub fn set_segment_flush_listener<F>(&mut self, segment_flush_listener: F)
+    where
+        F: Fn(SegmentId, DocId) + Send + Sync + 'static,
+    {
+        let segment_flush_listener: SegmentFlushListener = Arc::new(segment_flush_listener);
+        *self.segment_flush_listener
+            .write()
+            .expect("Segment flush listener lock poisoned") = Some(segment_flush_listener);
+    }
+
+    /// Returns true iff the documents of small commits are indexed in a single segment.
+    pub fn commit_segment_coalescing(&self) -> bool {
+        self.commit_segment_coalescing
+    }
+
+    /// Sets whether the documents of small commits are indexed in a single segment.
+    ///
+    /// The documents are otherwise dispatched to the indexing threads as they
+    /// are added, and each thread that received at least one document flushes
+    /// its own segment at commit time. With several threads, a trickle of
+    /// documents then produces many tiny segments.
+    ///
+    /// When enabled, the documents are held back until the commit,
+    /// where a single worker indexes them, in the order of their opstamps.
+    /// Once 1,000 documents have been added since the last commit, or once
+    /// the documents held back exceed the heap size of an indexing thread,
+    /// they are dispatched to the indexing threads as usual, until the
+    /// next commit.
+    ///
+    /// This is disabled by default.
+    pub fn set_commit_segment_coalescing(&mut self, commit_segment_coalescing: bool) {
+        if !commit_segment_coalescing {
+            self.flush_coalesced_docs();
+        }
+        self.commit_segment_coalescing = commit_segment_coalescing;
+    }
+
+    /// Dispatches the documents held back for coalescing to the indexing threads.
+    fn flush_coalesced_docs(&mut self) {
+        self.coalesced_delete_cursor = None;
+        self.coalesced_num_bytes = 0;
+        for add_operation in self.coalesced_docs.drain(..) {
+            self.document_sender.send(add_operation);
+        }
+    }
+
+    /// Indexes the documents held back for coalescing in a single segment.
+    fn index_coalesced_docs(&mut self) -> Result<()> {
+        self.coalescing_overflowed = false;
+        let delete_cursor = match self.coalesced_delete_cursor.take() {
+            Some(delete_cursor) => delete_cursor,
+            None => {
+                return Ok(());
+            }
+        };
+        let coalesced_docs = mem::replace(&mut self.coalesced_docs, vec![]);
+        self.coalesced_num_bytes = 0;
+        info!("Coalescing {} documents in a single segment", coalesced_docs.len());
+        let (batch_sender, batch_receiver): (DocumentSender, DocumentReceiver) =
+            chan::sync(coalesced_docs.len());
+        for add_operation in coalesced_docs {
+            batch_sender.send(add_operation);
+        }
+        // closing the channel stops the worker once it has indexed the batch.
+        drop(batch_sender);
+        let join_handle = self.spawn_indexing_worker(batch_receiver, delete_cursor)?;
+        join_handle
+            .join()
+            .map_err(|e| Error::from_kind(ErrorKind::ErrorInThread(format!("{:?}", e))))?
+    }
+
+    /// Returns the number of indexing worker threads.
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// Sets the number of indexing worker threads.
+    ///
+    /// When growing, the new workers are started right away.
+    ///
+    /// When shrinking, the document queue is cut and all of the
+    /// current workers are stopped after indexing the pending documents.
+    /// Their in-memory segments are flushed to disk, and will be
+    /// part of the next commit.
+    ///
+    /// # Errors
+    /// Returns `InvalidArgument` if `num_threads` is 0.
+    pub fn set_num_threads(&mut self, num_threads: usize) -> Result<()> {
+        if num_threads == 0 {
+            bail!(ErrorKind::InvalidArgument(
+                "The number of indexing threads must be at least 1.".to_string()
+            ));
+        }
+        if num_threads < self.workers_join_handle.len() {
+            info!("Shrinking to {} indexing threads", num_threads);
+            self.recreate_document_channel();
+            let former_workers_join_handle = mem::replace(&mut self.workers_join_handle, vec![]);
+            for worker_handle in former_workers_join_handle {
+                let indexing_worker_result = worker_handle
+                    .join()
+                    .map_err(|e| Error::from_kind(ErrorKind::ErrorInThread(format!("{:?}", e))))?;
+                indexing_worker_result?;
+            }
+        }
+        self.num_threads = num_threads;
+        while self.workers_join_handle.len() < num_threads {
+            self.add_indexing_worker()?;
+        }
+        Ok(())
+    }
+
+    /// Commits the pending changes, waits for the merging threads
+    /// and releases the lock on the index.
+    ///
+    /// Unlike dropping the `IndexWriter`, errors are reported, and a new
+    /// `IndexWriter` can be opened as soon as this method has returned.
+    ///
+    /// To discard the pending changes instead, call `.rollback()` first.
+    ///
+    /// Returns the opstamp of the final commit.
+    pub fn shutdown(mut self) -> Result<Opstamp> {
+        let opstamp = self.commit()?;
+        self.wait_merging_threads()?;
+        Ok(opstamp)
+    }
+
     fn start_workers(&mut self) -> Result<()> {
         for _ in 0..self.num_threads {
             self.add_indexing_worker()?;
@@ -455,6 +1301,32 @@ impl IndexWriter {
         self.segment_updater.start_merge(segment_ids)
     }
 
+    /// Rewrites the segments whose ratio of deleted documents exceeds
+    /// `max_deletes_ratio`, to expunge their deleted documents.
+    ///
+    /// Each of these segments is rewritten on its own, whatever the
+    /// merge policy, and the method waits until all of them are rewritten.
+    /// Only the deletes of the last commit are taken in account, and
+    /// the segments that are being merged are skipped.
+    ///
+    /// See also `LogMergePolicy::set_max_deletes_ratio`, that makes
+    /// the merge policy rewrite these segments.
+    ///
+    /// Returns the metas of the rewritten segments.
+    pub fn expunge_deletes(&mut self, max_deletes_ratio: f32) -> Result<Vec<SegmentMeta>> {
+        let rewrites = self.segment_updater.expunge_deletes(max_deletes_ratio)?;
+        rewrites
+            .into_iter()
+            .map(|rewrite| {
+                rewrite.wait().map_err(|_| {
+                    Error::from(ErrorKind::ErrorInThread(
+                        "The rewrite of a segment failed.".to_string(),
+                    ))
+                })
+            })
+            .collect()
+    }
+
     /// Closes the current document channel send.
     /// and replace all the channels by new ones.
     ///
@@ -480,7 +1352,11 @@ impl IndexWriter {
     /// After calling rollback, the index is in the same
     /// state as it was after the last commit.
     ///
-    /// The opstamp at the last commit is returned.
+    /// The opstamps given to the cancelled operations are not reused:
+    /// the following operations get greater opstamps.
+    ///
+    /// This is also how an `IndexWriter` recovers from the panic
+    /// of one of its indexing workers.
     pub fn rollback(&mut self) -> Result<()> {
         info!("Rolling back to opstamp {}", self.committed_opstamp);
 
@@ -495,12 +1371,31 @@ impl IndexWriter {
             .take()
             .expect("The IndexWriter does not have any lock. This is a bug, please report.");
 
-        let new_index_writer: IndexWriter = open_index_writer(
+        let mut new_index_writer: IndexWriter = open_index_writer(
             &self.index,
             self.num_threads,
             self.heap_size_in_bytes_per_thread,
             directory_lock,
         )?;
+        new_index_writer.set_term_table_num_bits(self.term_table_num_bits())?;
+        new_index_writer.set_max_token_len(self.max_token_len())?;
+        new_index_writer.set_postings_spill_threshold(self.postings_spill_threshold());
+        new_index_writer.set_num_serialization_threads(self.num_serialization_threads())?;
+        new_index_writer.set_verify_segments_on_commit(self.verify_segments_on_commit());
+        new_index_writer.set_commit_segment_coalescing(self.commit_segment_coalescing());
+        new_index_writer.set_validation(self.validation_mode());
+        new_index_writer.set_report_segment_local_docs(self.report_segment_local_docs);
+        // the indexing workers of the new writer already share its listener cell.
+        *new_index_writer
+            .segment_flush_listener
+            .write()
+            .expect("Segment flush listener lock poisoned") = self.segment_flush_listener
+            .read()
+            .expect("Segment flush listener lock poisoned")
+            .clone();
+        new_index_writer
+            .stamper
+            .skip_past(self.stamper.last_opstamp());
 
         // the current `self` is dropped right away because of this call.
         //
@@ -571,6 +1466,16 @@ impl IndexWriter {
             // add a new worker for the next generation.
             self.add_indexing_worker()?;
         }
+        self.index_coalesced_docs()?;
+        // a worker of the generation may have panicked
+        // during a previous attempt to commit.
+        self.check_worker_panic()?;
+        self.last_commit_num_dropped_tokens = self.num_dropped_tokens.swap(0, Ordering::SeqCst);
+        self.last_commit_num_spilled_runs = self.num_spilled_runs.swap(0, Ordering::SeqCst);
+        let (num_term_rehashes, max_term_probe_len) = self.term_table_stats.take();
+        self.last_commit_num_term_rehashes = num_term_rehashes;
+        self.last_commit_max_term_probe_len = max_term_probe_len;
+        self.last_commit_field_stats = self.take_field_stats();
 
         let commit_opstamp = self.stamper.stamp();
         let prepared_commit = PreparedCommit::new(self, commit_opstamp);
@@ -589,15 +1494,37 @@ impl IndexWriter {
     /// long as the hard disk is spared), it will be possible
     /// to resume indexing from this point.
     ///
-    /// Commit returns the `opstamp` of the last document
-    /// that made it in the commit.
+    /// Commit returns the `opstamp` of the commit. It is greater
+    /// than the opstamps of all of the operations that made it in the commit.
     ///
-    pub fn commit(&mut self) -> Result<u64> {
+    pub fn commit(&mut self) -> Result<Opstamp> {
         self.prepare_commit()?.commit()
     }
 
-    pub(crate) fn segment_updater(&self) -> &SegmentUpdater {
-        &self.segment_updater
+    /// Commits, and then loads a new generation of searchers.
+    ///
+    /// This is equivalent to calling `commit()` followed by
+    /// `index.load_searchers()`, on the `Index` this writer was
+    /// created from (or any of its clones).
+    /// Once this method has returned, the searchers acquired via
+    /// `Index::searcher()`, from any thread, include this commit.
+    ///
+    /// Searches may be in flight during the whole operation. They keep
+    /// using the generation of searchers they acquired.
+    pub fn commit_and_reload(&mut self) -> Result<Opstamp> {
+        let opstamp = self.commit()?;
+        self.index.load_searchers()?;
+        Ok(opstamp)
+    }
+
+    /// Publishes the segments of a prepared commit.
+    pub(crate) fn commit_segments(
+        &mut self,
+        opstamp: Opstamp,
+        payload: Option<String>,
+    ) -> Result<()> {
+        self.last_commit_num_deleted_docs = self.segment_updater.commit(opstamp, payload)?;
+        Ok(())
     }
 
     /// Delete all documents containing a given term.
@@ -608,13 +1535,74 @@ impl IndexWriter {
     ///
     /// Like adds, the deletion itself will be visible
     /// only after calling `commit()`.
-    pub fn delete_term(&mut self, term: Term) -> u64 {
+    pub fn delete_term(&mut self, term: Term) -> Opstamp {
         let opstamp = self.stamper.stamp();
-        let delete_operation = DeleteOperation { opstamp, term };
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::Term(term),
+        };
         self.delete_queue.push(delete_operation);
         opstamp
     }
 
+    fn check_fast_field<Item: FastValue>(&self, field: Field) -> Result<()> {
+        let schema = self.index.schema();
+        let field_entry = schema.get_field_entry(field);
+        if Item::fast_field_cardinality(field_entry.field_type()) != Some(Cardinality::SingleValue)
+        {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The field {:?} is not a single-valued fast field of the requested type.",
+                field_entry.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Delete all documents whose value for the given
+    /// `u64` fast field is within `range`.
+    ///
+    /// This is typically useful to implement a retention policy
+    /// (e.g. deleting all documents with a timestamp lower than
+    /// some cutoff). Rather than going through the term dictionary,
+    /// the deletion is applied by scanning the fast field. The range of
+    /// values of each segment is recorded in its meta when it is flushed,
+    /// and the segments whose range does not overlap `range` are not even
+    /// opened.
+    ///
+    /// Like `delete_term`, the deletion only affects documents
+    /// added before the operation, and will be visible only
+    /// after calling `commit()`. The number of deleted documents
+    /// is then given by `last_commit_num_deleted_docs()`.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if the field is not
+    /// a single-valued `u64` fast field.
+    pub fn delete_by_u64_range(&mut self, field: Field, range: Range<u64>) -> Result<Opstamp> {
+        self.check_fast_field::<u64>(field)?;
+        let opstamp = self.stamper.stamp();
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::U64Range(field, range),
+        };
+        self.delete_queue.push(delete_operation);
+        Ok(opstamp)
+    }
+
+    /// Delete all documents whose value for the given
+    /// `i64` fast field is within `range`.
+    ///
+    /// See [`delete_by_u64_range`](#method.delete_by_u64_range).
+    pub fn delete_by_i64_range(&mut self, field: Field, range: Range<i64>) -> Result<Opstamp> {
+        self.check_fast_field::<i64>(field)?;
+        let opstamp = self.stamper.stamp();
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::I64Range(field, range),
+        };
+        self.delete_queue.push(delete_operation);
+        Ok(opstamp)
+    }
+
     /// Returns the opstamp of the last successful commit.
     ///
     /// This is, for instance, the opstamp the index will
@@ -622,37 +1610,207 @@ impl IndexWriter {
     ///
     /// This is also the opstamp of the commit that is currently
     /// available for searchers.
-    pub fn commit_opstamp(&self) -> u64 {
+    pub fn commit_opstamp(&self) -> Opstamp {
         self.committed_opstamp
     }
 
+    /// Returns the opstamp given to the last operation.
+    ///
+    /// Before the first operation of the writer, this is the last
+    /// opstamp recorded by the last commit: the operations of
+    /// the writer get opstamps strictly greater than it.
+    ///
+    /// Merges consume opstamps as well, so the following operation
+    /// may not get the opstamp right after this one.
+    pub fn last_assigned_opstamp(&self) -> Opstamp {
+        self.stamper.last_opstamp()
+    }
+
     /// Adds a document.
     ///
     /// If the indexing pipeline is full, this call may block.
     ///
-    /// The opstamp is an increasing `u64` that can
-    /// be used by the client to align commits with its own
-    /// document queue.
+    /// The opstamp of the returned [`AddDocumentResult`](./struct.AddDocumentResult.html)
+    /// can be used by the client to align commits with its own document queue.
+    /// See [`Opstamp`](./struct.Opstamp.html) for the guarantees it comes with.
     ///
-    /// Currently it represents the number of documents that
-    /// have been added since the creation of the index.
-    pub fn add_document(&mut self, document: Document) -> u64 {
+    /// # Errors
+    /// Unless validation is turned off (see [`set_validation`](#method.set_validation)),
+    /// returns `ErrorKind::InvalidDocument` if one of the values of the document
+    /// does not match the schema. The document is then not added.
+    ///
+    /// Returns `ErrorKind::WorkerPanicked` if an indexing worker panicked
+    /// since the last rollback (see [`rollback`](#method.rollback)).
+    pub fn add_document(&mut self, mut document: Document) -> Result<AddDocumentResult> {
+        self.check_worker_panic()?;
+        self.validate_document(&mut document)?;
+        if self.report_segment_local_docs && self.num_threads == 1 {
+            let (doc_id_sender, doc_id_receiver) = oneshot::channel();
+            let opstamp = self.send_document(document, Some(doc_id_sender));
+            let segment_local_doc = match doc_id_receiver.wait() {
+                Ok(segment_local_doc) => segment_local_doc,
+                Err(_) => {
+                    // the single worker stopped before indexing the document.
+                    // If it panicked, it keeps running until the document queue is cut.
+                    self.check_worker_panic()?;
+                    let former_workers_join_handle =
+                        mem::replace(&mut self.workers_join_handle, vec![]);
+                    for worker_handle in former_workers_join_handle {
+                        worker_handle
+                            .join()
+                            .map_err(|e| ErrorKind::ErrorInThread(format!("{:?}", e)))??;
+                    }
+                    bail!(ErrorKind::ErrorInThread(
+                        "Error in indexing worker thread.".into()
+                    ));
+                }
+            };
+            Ok(AddDocumentResult {
+                opstamp,
+                segment_local_doc: Some(segment_local_doc),
+            })
+        } else {
+            Ok(AddDocumentResult {
+                opstamp: self.send_document(document, None),
+                segment_local_doc: None,
+            })
+        }
+    }
+
+    fn validate_document(&mut self, document: &mut Document) -> Result<()> {
+        let skip_invalid_values = match self.validation_mode {
+            ValidationMode::Strict => false,
+            ValidationMode::SkipInvalidValues => true,
+            ValidationMode::Off => {
+                return Ok(());
+            }
+        };
+        let skipped_fields = document.validate(&self.index.schema(), skip_invalid_values)?;
+        for field in skipped_fields {
+            *self.num_skipped_values.entry(field).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    fn send_document(
+        &mut self,
+        document: Document,
+        doc_id_sender: Option<oneshot::Sender<DocId>>,
+    ) -> Opstamp {
         let opstamp = self.stamper.stamp();
-        let add_operation = AddOperation { opstamp, document };
-        self.document_sender.send(add_operation);
+        let add_operation = AddOperation {
+            opstamp,
+            document,
+            doc_id_sender,
+        };
+        if self.commit_segment_coalescing && !self.coalescing_overflowed
+            && add_operation.doc_id_sender.is_none()
+        {
+            if self.coalesced_docs.is_empty() {
+                // the deletes preceding the document cannot affect it.
+                self.coalesced_delete_cursor = Some(self.delete_queue.cursor());
+            }
+            self.coalesced_num_bytes += document_num_bytes(&add_operation.document);
+            self.coalesced_docs.push(add_operation);
+            // the documents held back count against the heap of the worker
+            // that will index them.
+            let (heap_size, _) = split_memory(self.heap_size_in_bytes_per_thread);
+            if self.coalesced_docs.len() >= COMMIT_COALESCING_MAX_DOCS
+                || self.coalesced_num_bytes >= heap_size
+            {
+                self.coalescing_overflowed = true;
+                self.flush_coalesced_docs();
+            }
+        } else {
+            // the documents held back are sent first, to keep the opstamp order.
+            self.flush_coalesced_docs();
+            self.document_sender.send(add_operation);
+        }
         opstamp
     }
+
+    /// Returns the term of the unique key of a document.
+    fn unique_key_term(&self, document: &Document) -> Result<Term> {
+        let schema = self.index.schema();
+        let unique_key = match schema.unique_key() {
+            Some(unique_key) => unique_key,
+            None => bail!(ErrorKind::InvalidArgument(
+                "The schema does not define a unique key.".to_string()
+            )),
+        };
+        let field_name = schema.get_field_name(unique_key);
+        let values = document.get_all(unique_key);
+        if values.len() != 1 {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The document must have exactly one value for the unique key {:?}, got {}.",
+                field_name,
+                values.len()
+            )));
+        }
+        match *values[0] {
+            Value::Str(ref text) => Ok(Term::from_field_text(unique_key, text)),
+            Value::U64(val) => Ok(Term::from_field_u64(unique_key, val)),
+            Value::I64(val) => Ok(Term::from_field_i64(unique_key, val)),
+            Value::Facet(_) => bail!(ErrorKind::InvalidArgument(format!(
+                "The unique key {:?} cannot be a facet.",
+                field_name
+            ))),
+        }
+    }
+
+    /// Adds a document, replacing any previous document
+    /// sharing the same unique key.
+    ///
+    /// The schema must define a unique key
+    /// (see [`SchemaBuilder::set_unique_key`](./schema/struct.SchemaBuilder.html#method.set_unique_key)),
+    /// and the document must have exactly one value for it.
+    ///
+    /// This is equivalent to a `delete_term` on the unique key,
+    /// immediately followed by an `add_document`. Since deletes only
+    /// affect documents with a lower opstamp, the newly added document
+    /// is the only version to survive, whatever the indexing thread
+    /// it ends up in.
+    ///
+    /// Like `add_document`, the change will be visible only after
+    /// calling `commit()`. Note that `add_document` does not check
+    /// for duplicates, even when the schema has a unique key.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if the schema does not
+    /// have a unique key, or if the document does not have exactly
+    /// one value for it, and `ErrorKind::InvalidDocument` if the
+    /// document does not match the schema.
+    pub fn upsert_document(&mut self, mut document: Document) -> Result<Opstamp> {
+        self.check_worker_panic()?;
+        self.validate_document(&mut document)?;
+        let term = self.unique_key_term(&document)?;
+        self.delete_term(term);
+        Ok(self.send_document(document, None))
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use indexer::NoMergePolicy;
+    use super::PIPELINE_MAX_SIZE_IN_DOCS;
+    use indexer::{LogMergePolicy, NoMergePolicy, Opstamp};
+    use core::SegmentComponent;
+    use directory::{InstrumentedDirectory, RAMDirectory};
     use schema::{self, Document};
     use Index;
+    use IndexWriter;
+    use ValidationMode;
+    use DocId;
+    use futures::Future;
     use Term;
     use error::*;
     use env_logger;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::path::Path;
+    use std::thread;
+    use tokenizer::{LowerCaser, SimpleTokenizer, Token, TokenFilter, TokenStream, Tokenizer,
+                    MAX_TOKEN_LEN};
 
     #[test]
     fn test_lockfile_stops_duplicates() {
@@ -673,7 +1831,7 @@ mod tests {
         assert_eq!(
             format!("{:?}", index_writer.get_merge_policy()),
             "LogMergePolicy { min_merge_size: 8, min_layer_size: 10000, \
-             level_log_size: 0.75 }"
+             level_log_size: 0.75, max_deletes_ratio: None }"
         );
         let merge_policy = box NoMergePolicy::default();
         index_writer.set_merge_policy(merge_policy);
@@ -683,6 +1841,87 @@ mod tests {
         );
     }
 
+    /// Creates an index with two segments: one with 6 documents out of 10
+    /// matching `text:drop`, and another one with 1 document out of 10
+    /// matching it, and deletes these documents.
+    fn create_index_with_deletes(index_writer: &mut IndexWriter, text_field: schema::Field) {
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for i in 0..10 {
+            let text = if i < 6 { "drop" } else { "keep" };
+            index_writer.add_document(doc!(text_field => text)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        for i in 0..10 {
+            let text = if i < 1 { "drop" } else { "other" };
+            index_writer.add_document(doc!(text_field => text)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(text_field, "drop"));
+    }
+
+    fn segment_docs(index: &Index) -> Vec<(u32, u32)> {
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut segment_docs: Vec<(u32, u32)> = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| (segment_reader.max_doc(), segment_reader.num_deleted_docs()))
+            .collect();
+        segment_docs.sort();
+        segment_docs
+    }
+
+    #[test]
+    fn test_max_deletes_ratio_rewrites_segment() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        create_index_with_deletes(&mut index_writer, text_field);
+        let segment_ids_before = index.searchable_segment_ids().unwrap();
+        let mut merge_policy = LogMergePolicy::default();
+        merge_policy.set_max_deletes_ratio(0.5);
+        index_writer.set_merge_policy(box merge_policy);
+        index_writer.commit().unwrap();
+        index_writer.wait_merging_threads().unwrap();
+
+        // the live documents of the rewritten segment are kept.
+        assert_eq!(segment_docs(&index), vec![(4, 0), (10, 1)]);
+        assert_eq!(index.searcher().num_docs(), 13);
+        let segment_ids_after = index.searchable_segment_ids().unwrap();
+        assert_eq!(
+            segment_ids_after
+                .iter()
+                .filter(|&segment_id| segment_ids_before.contains(segment_id))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_expunge_deletes() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        create_index_with_deletes(&mut index_writer, text_field);
+        // only the committed deletes are taken in account.
+        assert!(index_writer.expunge_deletes(0.5).unwrap().is_empty());
+        index_writer.commit().unwrap();
+        assert_eq!(segment_docs(&index), vec![(10, 1), (10, 6)]);
+
+        let rewritten_segment_metas = index_writer.expunge_deletes(0.5).unwrap();
+        assert_eq!(rewritten_segment_metas.len(), 1);
+        assert_eq!(rewritten_segment_metas[0].max_doc(), 4);
+        assert_eq!(rewritten_segment_metas[0].num_deleted_docs(), 0);
+        assert_eq!(segment_docs(&index), vec![(4, 0), (10, 1)]);
+        assert_eq!(index.searcher().num_docs(), 13);
+        // the rewritten segment has no deletes anymore.
+        assert!(index_writer.expunge_deletes(0.5).unwrap().is_empty());
+        assert_eq!(index_writer.expunge_deletes(0.05).unwrap().len(), 1);
+        assert_eq!(segment_docs(&index), vec![(4, 0), (9, 0)]);
+    }
+
     #[test]
     fn test_lockfile_released_on_drop() {
         let schema_builder = schema::SchemaBuilder::default();
@@ -710,16 +1949,17 @@ mod tests {
         {
             // writing the segment
             let mut index_writer = index.writer_with_num_threads(3, 40_000_000).unwrap();
-            index_writer.add_document(doc!(text_field=>"a"));
+            index_writer.add_document(doc!(text_field=>"a")).unwrap();
             index_writer.rollback().unwrap();
 
-            assert_eq!(index_writer.commit_opstamp(), 0u64);
+            assert_eq!(index_writer.commit_opstamp(), Opstamp(0));
             assert_eq!(num_docs_containing("a"), 0);
             {
-                index_writer.add_document(doc!(text_field=>"b"));
-                index_writer.add_document(doc!(text_field=>"c"));
+                index_writer.add_document(doc!(text_field=>"b")).unwrap();
+                index_writer.add_document(doc!(text_field=>"c")).unwrap();
             }
-            assert_eq!(index_writer.commit().unwrap(), 2u64);
+            // the opstamp of the rolled back document is not reused.
+            assert_eq!(index_writer.commit().unwrap(), Opstamp(4));
             index.load_searchers().unwrap();
             assert_eq!(num_docs_containing("a"), 0);
             assert_eq!(num_docs_containing("b"), 1);
@@ -747,13 +1987,13 @@ mod tests {
             for _doc in 0..100 {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().expect("commit failed");
             for _doc in 0..100 {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             // this should create 8 segments and trigger a merge.
             index_writer.commit().expect("commit failed");
@@ -767,6 +2007,503 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delete_by_u64_range() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let timestamp_field = schema_builder.add_u64_field("timestamp", schema::FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let add_segment = |index_writer: &mut IndexWriter, timestamps: &[u64]| {
+            for &timestamp in timestamps {
+                index_writer.add_document(doc!(timestamp_field => timestamp)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        };
+        add_segment(&mut index_writer, &[1, 2, 3, 4]);
+        add_segment(&mut index_writer, &[100, 101, 102]);
+        add_segment(&mut index_writer, &[5, 50, 150]);
+
+        index_writer
+            .delete_by_u64_range(timestamp_field, 0..10)
+            .unwrap();
+        // documents added after the delete operation are not affected.
+        index_writer.add_document(doc!(timestamp_field => 1u64)).unwrap();
+        index_writer.commit().unwrap();
+        assert_eq!(index_writer.last_commit_num_deleted_docs(), 5);
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            let mut num_deleted_docs: Vec<(u64, u32)> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| {
+                    let ff_reader = segment_reader
+                        .fast_field_reader::<u64>(timestamp_field)
+                        .unwrap();
+                    (ff_reader.max_value(), segment_reader.num_deleted_docs())
+                })
+                .collect();
+            num_deleted_docs.sort();
+            assert_eq!(num_deleted_docs, vec![(1, 0), (4, 4), (102, 0), (150, 1)]);
+            assert_eq!(searcher.num_docs(), 6);
+        }
+        {
+            // the segment out of the range was left untouched.
+            let metas = index.searchable_segment_metas().unwrap();
+            let untouched = metas
+                .iter()
+                .filter(|segment_meta| !segment_meta.has_deletes())
+                .map(|segment_meta| segment_meta.max_doc())
+                .collect::<Vec<_>>();
+            assert_eq!(untouched.len(), 2);
+            assert!(untouched.contains(&3));
+        }
+
+        // merging expunges the deleted documents.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            assert_eq!(searcher.segment_readers().len(), 1);
+            let segment_reader = searcher.segment_reader(0);
+            assert_eq!(segment_reader.max_doc(), 6);
+            assert_eq!(segment_reader.num_deleted_docs(), 0);
+            let ff_reader = segment_reader
+                .fast_field_reader::<u64>(timestamp_field)
+                .unwrap();
+            let mut timestamps: Vec<u64> = (0..6).map(|doc| ff_reader.get(doc)).collect();
+            timestamps.sort();
+            assert_eq!(timestamps, vec![1, 50, 100, 101, 102, 150]);
+        }
+    }
+
+    #[test]
+    fn test_delete_by_u64_range_skips_segments_out_of_range() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let timestamp_field = schema_builder.add_u64_field("timestamp", schema::FAST);
+        let directory = InstrumentedDirectory::new(RAMDirectory::create());
+        let index = Index::create_in_directory(directory.clone(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for timestamps in &[[1u64, 2, 3], [100, 101, 102], [5, 50, 150]] {
+            for &timestamp in timestamps {
+                index_writer.add_document(doc!(timestamp_field => timestamp)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        let mut value_ranges: Vec<Option<(u64, u64)>> = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.fast_field_value_range(timestamp_field))
+            .collect();
+        value_ranges.sort();
+        assert_eq!(
+            value_ranges,
+            vec![Some((1, 3)), Some((5, 150)), Some((100, 102))]
+        );
+
+        directory.reset();
+        index_writer
+            .delete_by_u64_range(timestamp_field, 0..10)
+            .unwrap();
+        index_writer.commit().unwrap();
+        assert_eq!(index_writer.last_commit_num_deleted_docs(), 4);
+        let stats = directory.snapshot();
+        for segment_meta in &segment_metas {
+            let fast_field_path = segment_meta.relative_path(SegmentComponent::FASTFIELDS);
+            let num_open_reads = stats.file(&fast_field_path).num_open_reads;
+            if segment_meta.fast_field_value_range(timestamp_field) == Some((100, 102)) {
+                // the segment out of the range is not even opened.
+                assert_eq!(num_open_reads, 0);
+            } else {
+                assert!(num_open_reads > 0);
+            }
+        }
+
+        // the range of values of a merged segment is recorded as well.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(
+            segment_metas[0].fast_field_value_range(timestamp_field),
+            Some((1, 150))
+        );
+        index_writer.commit().unwrap();
+        assert_eq!(index_writer.last_commit_num_deleted_docs(), 0);
+    }
+
+    #[test]
+    fn test_delete_by_i64_range() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let value_field = schema_builder.add_i64_field("value", schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for value in -5i64..5i64 {
+            index_writer.add_document(doc!(value_field => value)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        assert!(index_writer.delete_by_u64_range(value_field, 0..10).is_err());
+        assert!(index_writer.delete_by_i64_range(text_field, 0..10).is_err());
+        index_writer.delete_by_i64_range(value_field, -3..2).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 5);
+        let segment_reader = searcher.segment_reader(0);
+        let ff_reader = segment_reader
+            .fast_field_reader::<i64>(value_field)
+            .unwrap();
+        let alive_values: Vec<i64> = (0..segment_reader.max_doc())
+            .filter(|doc| !segment_reader.is_deleted(*doc))
+            .map(|doc| ff_reader.get(doc))
+            .collect();
+        assert_eq!(alive_values, vec![-5, -4, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_upsert_document() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", schema::STRING | schema::STORED);
+        let version_field = schema_builder.add_u64_field("version", schema::FAST);
+        schema_builder.set_unique_key(id_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(4, 40_000_000).unwrap();
+        for commit in 0..3u64 {
+            for version in 0..100u64 {
+                for id in &["a", "b", "c"] {
+                    let doc = doc!(id_field => *id, version_field => commit * 100 + version);
+                    index_writer.upsert_document(doc).unwrap();
+                }
+            }
+            index_writer.commit().unwrap();
+        }
+        // a plain `add_document` does not deduplicate.
+        index_writer.add_document(doc!(id_field => "c", version_field => 1_000u64)).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 4);
+        let mut alive_versions: Vec<(String, u64)> = vec![];
+        for segment_reader in searcher.segment_readers() {
+            let ff_reader = segment_reader
+                .fast_field_reader::<u64>(version_field)
+                .unwrap();
+            for doc in 0..segment_reader.max_doc() {
+                if !segment_reader.is_deleted(doc) {
+                    let stored_doc = segment_reader.doc(doc).unwrap();
+                    let id = stored_doc.get_first(id_field).unwrap().text().to_string();
+                    alive_versions.push((id, ff_reader.get(doc)));
+                }
+            }
+        }
+        alive_versions.sort();
+        assert_eq!(
+            alive_versions,
+            vec![
+                ("a".to_string(), 299),
+                ("b".to_string(), 299),
+                ("c".to_string(), 299),
+                ("c".to_string(), 1_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upsert_document_errors() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED);
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        schema_builder.set_unique_key(id_field);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert!(index_writer.upsert_document(doc!(text_field => "a")).is_err());
+        assert!(
+            index_writer
+                .upsert_document(doc!(id_field => 1u64, id_field => 2u64))
+                .is_err()
+        );
+        assert!(index_writer.upsert_document(doc!(id_field => 1u64)).is_ok());
+
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert!(index_writer.upsert_document(doc!(id_field => 1u64)).is_err());
+    }
+
+    #[test]
+    fn test_add_document_validation() {
+        use schema::{Cardinality, DocumentValidationError, Facet, FieldValue, IntOptions, Value};
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let count_field = schema_builder.add_u64_field("count", schema::INT_INDEXED);
+        let fast_options = IntOptions::default().set_fast(Cardinality::SingleValue);
+        let score_field = schema_builder.add_i64_field("score", fast_options);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert_eq!(index_writer.validation_mode(), ValidationMode::Strict);
+        let validation_error = |index_writer: &mut IndexWriter, doc: Document| {
+            match index_writer.add_document(doc) {
+                Err(Error(ErrorKind::InvalidDocument(validation_error), _)) => validation_error,
+                _ => panic!("Expected InvalidDocument error"),
+            }
+        };
+
+        // wrong value type
+        assert_eq!(
+            validation_error(&mut index_writer, doc!(count_field => "3")),
+            DocumentValidationError {
+                field: count_field,
+                field_name: "count".to_string(),
+                expected: "a u64".to_string(),
+                got: "the text \"3\"".to_string(),
+            }
+        );
+        let error = validation_error(&mut index_writer, doc!(score_field => 3u64));
+        assert_eq!(error.field, score_field);
+        assert_eq!(error.expected, "an i64");
+        let error = validation_error(&mut index_writer, doc!(text_field => 3i64));
+        assert_eq!(error.field, text_field);
+        let error = validation_error(&mut index_writer, doc!(text_field => Facet::from("/a")));
+        assert_eq!(error.got, "the facet /a");
+        assert_eq!(
+            error.to_string(),
+            "Invalid value for the field 'text': expected a text, got the facet /a."
+        );
+
+        // invalid facet
+        let error = validation_error(&mut index_writer, doc!(facet_field => "no/slash"));
+        assert_eq!(error.field, facet_field);
+        assert_eq!(error.got, "the text \"no/slash\"");
+        let error = validation_error(&mut index_writer, doc!(facet_field => 1u64));
+        assert_eq!(error.expected, "a facet");
+
+        // several values for a single valued fast field
+        let error = validation_error(
+            &mut index_writer,
+            doc!(score_field => 1i64, text_field => "a", score_field => 2i64),
+        );
+        assert_eq!(error.field, score_field);
+
+        // unknown field
+        let mut doc = Document::default();
+        doc.add(FieldValue::new(schema::Field(7), Value::U64(1)));
+        assert_eq!(validation_error(&mut index_writer, doc).got, "an unknown field");
+
+        // valid documents, the text of facet fields being parsed.
+        index_writer
+            .add_document(doc!(
+                text_field => "a",
+                count_field => 1u64,
+                count_field => 2u64,
+                score_field => -1i64,
+                facet_field => "/a/b",
+                facet_field => Facet::from("/c")
+            ))
+            .unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        let facet_reader = searcher.segment_reader(0).facet_reader(facet_field).unwrap();
+        let mut facet = Facet::root();
+        facet_reader.facet_from_ord(2, &mut facet);
+        assert_eq!(facet, Facet::from("/a/b"));
+    }
+
+    #[test]
+    fn test_add_document_skip_invalid_values() {
+        use schema::{Cardinality, Facet, IntOptions, Value, STORED};
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | STORED);
+        let fast_options = IntOptions::default()
+            .set_fast(Cardinality::SingleValue)
+            .set_stored();
+        let score_field = schema_builder.add_u64_field("score", fast_options);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_validation(ValidationMode::SkipInvalidValues);
+        assert_eq!(
+            index_writer.validation_mode(),
+            ValidationMode::SkipInvalidValues
+        );
+        index_writer
+            .add_document(doc!(
+                text_field => "a",
+                text_field => 1u64,
+                score_field => "high",
+                score_field => 3u64,
+                score_field => 4u64,
+                facet_field => "invalid",
+                facet_field => "/valid",
+                text_field => "b"
+            ))
+            .unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let doc = segment_reader.doc(0).unwrap();
+        assert_eq!(
+            doc.get_all(text_field)
+                .into_iter()
+                .map(|value| value.text())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(doc.get_all(score_field), vec![&Value::U64(3)]);
+        let score_reader = segment_reader.fast_field_reader::<u64>(score_field).unwrap();
+        assert_eq!(score_reader.get(0), 3);
+        let mut facet_reader = segment_reader.facet_reader(facet_field).unwrap();
+        let mut facet_ords = vec![];
+        facet_reader.facet_ords(0, &mut facet_ords);
+        assert_eq!(facet_ords.len(), 1);
+        let mut facet = Facet::root();
+        facet_reader.facet_from_ord(facet_ords[0], &mut facet);
+        assert_eq!(facet, Facet::from("/valid"));
+    }
+
+    #[test]
+    fn test_validation_mode_survives_rollback() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let count_field = schema_builder.add_u64_field("count", schema::INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_validation(ValidationMode::SkipInvalidValues);
+        index_writer.rollback().unwrap();
+        assert_eq!(
+            index_writer.validation_mode(),
+            ValidationMode::SkipInvalidValues
+        );
+        index_writer
+            .add_document(doc!(count_field => "3", count_field => 4u64))
+            .unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        assert_eq!(searcher.doc_freq(&Term::from_field_u64(count_field, 4u64)), 1);
+    }
+
+    #[test]
+    fn test_add_document_validation_off() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let score_field = schema_builder.add_u64_field("score", schema::FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_validation(ValidationMode::Off);
+        index_writer
+            .add_document(doc!(score_field => 3u64, score_field => 4u64))
+            .unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let score_reader = searcher
+            .segment_reader(0)
+            .fast_field_reader::<u64>(score_field)
+            .unwrap();
+        // only the first value is kept by the fast field.
+        assert_eq!(score_reader.get(0), 3);
+    }
+
+    #[test]
+    fn test_commit_and_reload() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let stop = Arc::new(AtomicBool::new(false));
+        let search_thread = {
+            let index = index.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut previous_num_docs = 0;
+                while !stop.load(Ordering::SeqCst) {
+                    let num_docs = index.searcher().num_docs();
+                    assert!(num_docs >= previous_num_docs);
+                    previous_num_docs = num_docs;
+                }
+            })
+        };
+        let mut index_writer = index.writer_with_num_threads(2, 40_000_000).unwrap();
+        let mut previous_opstamp = Opstamp(0);
+        for i in 0..50u32 {
+            index_writer.add_document(doc!(text_field => "a")).unwrap();
+            index_writer.add_document(doc!(text_field => "b")).unwrap();
+            let opstamp = index_writer.commit_and_reload().unwrap();
+            assert!(opstamp > previous_opstamp);
+            previous_opstamp = opstamp;
+            // the commit is visible as soon as `commit_and_reload` returns.
+            assert_eq!(index.searcher().num_docs(), 2 * u64::from(i + 1));
+        }
+        stop.store(true, Ordering::SeqCst);
+        search_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_load_searchers_without_changes() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        index_writer.commit_and_reload().unwrap();
+        let mapping = index.searcher().facet_ordinal_mapping(text_field);
+        // neither reloading without a commit, nor committing
+        // without changes, create a new generation of searchers.
+        index.load_searchers().unwrap();
+        index_writer.commit_and_reload().unwrap();
+        assert!(Arc::ptr_eq(
+            &mapping,
+            &index.searcher().facet_ordinal_mapping(text_field)
+        ));
+        index_writer.add_document(doc!(text_field => "b")).unwrap();
+        index_writer.commit_and_reload().unwrap();
+        assert!(!Arc::ptr_eq(
+            &mapping,
+            &index.searcher().facet_ordinal_mapping(text_field)
+        ));
+    }
+
+    #[test]
+    fn test_segment_files_written_once() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT | schema::STORED);
+        let directory = InstrumentedDirectory::new(RAMDirectory::create());
+        let index = Index::create_in_directory(directory.clone(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        index_writer.add_document(doc!(text_field => "b")).unwrap();
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.add_document(doc!(text_field => "c")).unwrap();
+        index_writer.commit().unwrap();
+        let stats = directory.snapshot();
+        let has_extension = |path: &Path, extension: &str| {
+            path.extension().map(|ext| ext == extension).unwrap_or(false)
+        };
+        // the files of the segments, including their delete files, are
+        // never rewritten: each of them is written by a single `WritePtr`.
+        assert!(stats.iter().any(|(path, _)| has_extension(path, "del")));
+        assert_eq!(
+            stats.iter().filter(|&(path, _)| has_extension(path, "idx")).count(),
+            2
+        );
+        for (path, file_stats) in stats.iter() {
+            if file_stats.num_bytes_written > 0 {
+                assert_eq!(file_stats.num_open_writes, 1, "{:?}", path);
+            }
+        }
+    }
+
     #[test]
     fn test_prepare_with_commit_message() {
         let _ = env_logger::init();
@@ -779,12 +2516,12 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(4, 4 * 30_000_000).unwrap();
             // create 8 segments with 100 tiny docs
             for _doc in 0..100 {
-                index_writer.add_document(doc!(text_field => "a"));
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
             }
             {
                 let mut prepared_commit = index_writer.prepare_commit().expect("commit failed");
                 prepared_commit.set_payload("first commit");
-                assert_eq!(prepared_commit.opstamp(), 100);
+                assert_eq!(prepared_commit.opstamp(), Opstamp(101));
                 prepared_commit.commit().expect("commit failed");
             }
             {
@@ -792,7 +2529,7 @@ mod tests {
                 assert_eq!(metas.payload.unwrap(), "first commit");
             }
             for _doc in 0..100 {
-                index_writer.add_document(doc!(text_field => "a"));
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
             }
             index_writer.commit().unwrap();
             {
@@ -802,6 +2539,244 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_report_segment_local_docs() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING | schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let flushed_segments = Arc::new(Mutex::new(Vec::new()));
+        let mut added_docs: Vec<(DocId, String)> = Vec::new();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            let flushed_segments_clone = Arc::clone(&flushed_segments);
+            index_writer.set_segment_flush_listener(move |segment_id, num_docs| {
+                flushed_segments_clone
+                    .lock()
+                    .unwrap()
+                    .push((segment_id, num_docs));
+            });
+            let result = index_writer.add_document(doc!(text_field => "a")).unwrap();
+            assert_eq!(result.segment_local_doc, None);
+            index_writer.set_report_segment_local_docs(true);
+            for &(num_docs, prefix) in &[(3, "b"), (2, "c")] {
+                for i in 0..num_docs {
+                    let text = format!("{}{}", prefix, i);
+                    let result = index_writer
+                        .add_document(doc!(text_field => text.clone()))
+                        .unwrap();
+                    added_docs.push((result.segment_local_doc.unwrap(), text));
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        // the first segment gets the document added before reporting was enabled.
+        let segment_local_docs: Vec<DocId> = added_docs.iter().map(|&(doc, _)| doc).collect();
+        assert_eq!(segment_local_docs, vec![1, 2, 3, 0, 1]);
+        let flushed_segments = flushed_segments.lock().unwrap().clone();
+        let num_docs: Vec<DocId> = flushed_segments
+            .iter()
+            .map(|&(_, num_docs)| num_docs)
+            .collect();
+        assert_eq!(num_docs, vec![4, 2]);
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = |segment_id| {
+            searcher
+                .segment_readers()
+                .iter()
+                .find(|segment_reader| segment_reader.segment_id() == segment_id)
+                .unwrap()
+        };
+        let (first_segment, second_segment) = (flushed_segments[0].0, flushed_segments[1].0);
+        for (i, &(doc, ref text)) in added_docs.iter().enumerate() {
+            let segment_id = if i < 3 { first_segment } else { second_segment };
+            let stored_doc = segment_reader(segment_id).doc(doc).unwrap();
+            assert_eq!(stored_doc.get_first(text_field).unwrap().text(), text.as_str());
+        }
+    }
+
+    #[test]
+    fn test_report_segment_local_docs_survives_rollback() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let flushed_num_docs = Arc::new(Mutex::new(Vec::new()));
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let flushed_num_docs_clone = Arc::clone(&flushed_num_docs);
+        index_writer.set_segment_flush_listener(move |_, num_docs| {
+            flushed_num_docs_clone.lock().unwrap().push(num_docs);
+        });
+        index_writer.set_report_segment_local_docs(true);
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        index_writer.rollback().unwrap();
+        for (i, text) in ["b", "c"].iter().enumerate() {
+            let result = index_writer.add_document(doc!(text_field => *text)).unwrap();
+            assert_eq!(result.segment_local_doc, Some(i as DocId));
+        }
+        index_writer.commit().unwrap();
+        assert_eq!(*flushed_num_docs.lock().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_report_segment_local_docs_several_threads() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(2, 40_000_000).unwrap();
+        index_writer.set_report_segment_local_docs(true);
+        let result = index_writer.add_document(doc!(text_field => "a")).unwrap();
+        assert_eq!(result.segment_local_doc, None);
+        index_writer.commit().unwrap();
+    }
+
+    #[test]
+    fn test_commit_with_idle_workers() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED | schema::FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(8, 10_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for id in 0..5u64 {
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let segment_docs_before = segment_docs(&index);
+        // the workers that did not receive any document do not flush a segment:
+        // each segment holds at least one of the 5 documents.
+        assert!(segment_docs_before.len() <= 5);
+        assert!(segment_docs_before.iter().all(|&(max_doc, _)| max_doc > 0));
+        assert_eq!(
+            segment_docs_before.iter().map(|&(max_doc, _)| max_doc).sum::<u32>(),
+            5
+        );
+        let segment_ids_before = index.searchable_segment_ids().unwrap();
+
+        // with coalescing, the documents are indexed in a single segment,
+        // however they would have been spread over the workers.
+        index_writer.set_commit_segment_coalescing(true);
+        for id in 5..10u64 {
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        assert_eq!(segment_docs(&index).len(), segment_docs_before.len() + 1);
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 10);
+        let coalesced_segment_readers: Vec<_> = searcher
+            .segment_readers()
+            .iter()
+            .filter(|segment_reader| !segment_ids_before.contains(&segment_reader.segment_id()))
+            .collect();
+        assert_eq!(coalesced_segment_readers.len(), 1);
+        let segment_reader = coalesced_segment_readers[0];
+        assert_eq!(segment_reader.max_doc(), 5);
+        let ff_reader = segment_reader.fast_field_reader::<u64>(id_field).unwrap();
+        // the documents are in the order of their opstamps.
+        let ids: Vec<u64> = (0..5).map(|doc| ff_reader.get(doc)).collect();
+        assert_eq!(ids, vec![5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_commit_segment_coalescing() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED | schema::FAST);
+        let text_field = schema_builder.add_text_field("text", schema::STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(8, 10_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        assert!(!index_writer.commit_segment_coalescing());
+        index_writer.set_commit_segment_coalescing(true);
+        for id in 0..5u64 {
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        // only deletes the document added before.
+        index_writer.delete_term(Term::from_field_u64(id_field, 1));
+        index_writer.add_document(doc!(id_field => 1u64)).unwrap();
+        index_writer.commit().unwrap();
+        assert_eq!(segment_docs(&index), vec![(6, 1)]);
+        {
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_docs(), 5);
+            let segment_reader = searcher.segment_reader(0);
+            let ff_reader = segment_reader.fast_field_reader::<u64>(id_field).unwrap();
+            let ids: Vec<(u64, bool)> = (0..6)
+                .map(|doc| (ff_reader.get(doc), segment_reader.is_deleted(doc)))
+                .collect();
+            // the documents are in the order of their opstamps.
+            assert_eq!(
+                ids,
+                vec![
+                    (0, false),
+                    (1, true),
+                    (2, false),
+                    (3, false),
+                    (4, false),
+                    (1, false),
+                ]
+            );
+        }
+
+        // too many documents to be coalesced.
+        for id in 0..1_500u64 {
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1_505);
+
+        // too many bytes to be coalesced.
+        let large_text: String = ::std::iter::repeat('a').take(1_000_000).collect();
+        for _ in 0..20 {
+            index_writer
+                .add_document(doc!(id_field => 0u64, text_field => large_text.clone()))
+                .unwrap();
+        }
+        assert!(index_writer.coalescing_overflowed);
+        assert!(index_writer.coalesced_docs.len() < 20);
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1_525);
+
+        // the documents held back are indexed when the coalescing is disabled.
+        index_writer.add_document(doc!(id_field => 0u64)).unwrap();
+        index_writer.set_commit_segment_coalescing(false);
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1_526);
+    }
+
+    #[test]
+    fn test_load_metas_from_directory() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let schema = schema_builder.build();
+        let directory = RAMDirectory::create();
+        {
+            let index = Index::create_in_directory(directory.clone(), schema).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer.add_document(doc!(text_field => "a")).unwrap();
+            index_writer.add_document(doc!(text_field => "b")).unwrap();
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field => "c")).unwrap();
+            let mut prepared_commit = index_writer.prepare_commit().unwrap();
+            prepared_commit.set_payload("second commit");
+            prepared_commit.commit().unwrap();
+        }
+        let metas = Index::load_metas_from_directory(&directory).unwrap();
+        assert_eq!(metas.opstamp(), Opstamp(5));
+        assert_eq!(metas.payload(), Some("second commit"));
+        assert_eq!(metas.schema().get_field("text"), Some(text_field));
+        let mut num_docs: Vec<u32> = metas
+            .segments()
+            .iter()
+            .map(|segment_meta| segment_meta.num_docs())
+            .collect();
+        num_docs.sort();
+        assert_eq!(num_docs, vec![1, 2]);
+    }
+
     #[test]
     fn test_prepare_but_rollback() {
         let _ = env_logger::init();
@@ -814,12 +2789,12 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(4, 4 * 30_000_000).unwrap();
             // create 8 segments with 100 tiny docs
             for _doc in 0..100 {
-                index_writer.add_document(doc!(text_field => "a"));
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
             }
             {
                 let mut prepared_commit = index_writer.prepare_commit().expect("commit failed");
                 prepared_commit.set_payload("first commit");
-                assert_eq!(prepared_commit.opstamp(), 100);
+                assert_eq!(prepared_commit.opstamp(), Opstamp(101));
                 prepared_commit.abort().expect("commit failed");
             }
             {
@@ -827,7 +2802,7 @@ mod tests {
                 assert!(metas.payload.is_none());
             }
             for _doc in 0..100 {
-                index_writer.add_document(doc!(text_field => "b"));
+                index_writer.add_document(doc!(text_field => "b")).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -841,4 +2816,622 @@ mod tests {
         assert_eq!(num_docs_containing("b"), 100);
     }
 
+    #[test]
+    fn test_term_table_resize() {
+        use collector::FacetCollector;
+        use query::AllQuery;
+        use schema::Facet;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            assert!(index_writer.set_term_table_num_bits(0).is_err());
+            assert!(index_writer.set_term_table_num_bits(32).is_err());
+            index_writer.set_term_table_num_bits(2).unwrap();
+            assert_eq!(index_writer.term_table_num_bits(), 2);
+            for i in 0..1_000 {
+                let mut doc = Document::default();
+                doc.add_text(text_field, &format!("id{}", i));
+                doc.add_facet(facet_field, Facet::from(&format!("/category/c{}", i % 7)));
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+            // about 1000 terms, from 4 up to 2048 buckets.
+            assert_eq!(index_writer.num_term_rehashes(), 9);
+            assert!(index_writer.max_term_probe_len() > 0);
+            index_writer.commit().unwrap();
+            assert_eq!(index_writer.num_term_rehashes(), 0);
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 1_000);
+        for i in 0..1_000 {
+            let term = Term::from_field_text(text_field, &format!("id{}", i));
+            assert_eq!(searcher.doc_freq(&term), 1);
+        }
+        // facet term ids must survive the resizing of the table.
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/category");
+        searcher.search(&AllQuery, &mut facet_collector).unwrap();
+        let counts = facet_collector.harvest();
+        let facets: Vec<(String, u64)> = counts
+            .get("/category")
+            .map(|(facet, count)| (facet.to_string(), count))
+            .collect();
+        assert_eq!(
+            facets,
+            (0..7)
+                .map(|i| (format!("/category/c{}", i), if i < 6 { 143 } else { 142 }))
+                .collect::<Vec<_>>()
+        );
+    }
+
+
+    #[test]
+    fn test_max_token_len() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field(
+            "text",
+            schema::TextOptions::default().set_indexing_options(
+                schema::TextFieldIndexing::default().set_tokenizer("unlimited"),
+            ),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        // unlike the default tokenizer, this one does not remove long tokens.
+        index
+            .tokenizers()
+            .register("unlimited", SimpleTokenizer.filter(LowerCaser));
+        let huge_token = "a".repeat(1_000_000);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            assert_eq!(index_writer.max_token_len(), MAX_TOKEN_LEN);
+            index_writer
+                .add_document(doc!(text_field => format!("hello {} world", huge_token)))
+                .unwrap();
+            index_writer.commit().unwrap();
+            assert_eq!(index_writer.num_dropped_tokens(), 1);
+
+            assert!(index_writer.set_max_token_len(MAX_TOKEN_LEN + 1).is_err());
+            assert_eq!(index_writer.max_token_len(), MAX_TOKEN_LEN);
+            index_writer.set_max_token_len(5).unwrap();
+            index_writer.add_document(doc!(text_field => "hello searchable world")).unwrap();
+            index_writer.commit().unwrap();
+            assert_eq!(index_writer.num_dropped_tokens(), 1);
+
+            // the counter is reset at every commit.
+            index_writer.commit().unwrap();
+            assert_eq!(index_writer.num_dropped_tokens(), 0);
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_freq = |text: &str| searcher.doc_freq(&Term::from_field_text(text_field, text));
+        assert_eq!(doc_freq("hello"), 2);
+        assert_eq!(doc_freq("world"), 2);
+        assert_eq!(doc_freq(&huge_token), 0);
+        assert_eq!(doc_freq("searchable"), 0);
+    }
+
+    #[test]
+    fn test_last_commit_field_stats() {
+        use schema::{STORED, TEXT};
+        use FieldIngestStats;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let count_field = schema_builder.add_u64_field("count", schema::INT_INDEXED);
+        let stored_field = schema_builder.add_text_field("stored", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(3, 40_000_000).unwrap();
+        index_writer.set_validation(ValidationMode::SkipInvalidValues);
+        index_writer.set_max_token_len(10).unwrap();
+        assert!(index_writer.last_commit_field_stats().is_empty());
+        for i in 0..10u64 {
+            let mut doc = doc!(title_field => "hello world", count_field => i);
+            // the body is absent from half of the documents.
+            if i % 2 == 0 {
+                doc.add_text(body_field, "a b c");
+                doc.add_text(body_field, "d averyveryverylongtoken");
+            }
+            if i == 3 {
+                // skipped by the validation.
+                doc.add_text(count_field, "three");
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        {
+            let field_stats = index_writer.last_commit_field_stats();
+            assert_eq!(field_stats.len(), 4);
+            assert_eq!(
+                field_stats[&title_field],
+                FieldIngestStats {
+                    num_docs: 10,
+                    num_values: 10,
+                    num_tokens: 20,
+                    num_skipped_values: 0,
+                    num_dropped_tokens: 0,
+                }
+            );
+            assert_eq!(
+                field_stats[&body_field],
+                FieldIngestStats {
+                    num_docs: 5,
+                    num_values: 10,
+                    num_tokens: 25,
+                    num_skipped_values: 0,
+                    num_dropped_tokens: 5,
+                }
+            );
+            assert_eq!(
+                field_stats[&count_field],
+                FieldIngestStats {
+                    num_docs: 10,
+                    num_values: 10,
+                    num_tokens: 0,
+                    num_skipped_values: 1,
+                    num_dropped_tokens: 0,
+                }
+            );
+            assert_eq!(field_stats[&stored_field], FieldIngestStats::default());
+        }
+
+        // the statistics only cover the last commit.
+        index_writer
+            .add_document(doc!(stored_field => "stored only"))
+            .unwrap();
+        let mut prepared_commit = index_writer.prepare_commit().unwrap();
+        {
+            let field_stats = prepared_commit.field_stats();
+            assert_eq!(field_stats[&title_field], FieldIngestStats::default());
+            assert_eq!(field_stats[&stored_field].num_docs, 1);
+            assert_eq!(field_stats[&stored_field].num_values, 1);
+            assert_eq!(field_stats[&stored_field].num_tokens, 0);
+        }
+        prepared_commit.set_payload("field stats");
+        prepared_commit.commit().unwrap();
+    }
+
+    #[test]
+    fn test_postings_spill() {
+        use collector::{CountCollector, FacetCollector};
+        use docset::DocSet;
+        use postings::Postings;
+        use query::{Query, QueryParser};
+        use schema::{Facet, IndexRecordOption, INT_INDEXED, STRING, TEXT};
+        use termdict::{TermDictionary, TermStreamer};
+
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let string_field = schema_builder.add_text_field("string", STRING);
+        let int_field = schema_builder.add_u64_field("int", INT_INDEXED);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+        let fields = vec![text_field, string_field, int_field, facet_field];
+
+        // returns the index, and the number of times the postings were spilled.
+        let build_index = |postings_spill_threshold: Option<usize>| {
+            let index = Index::create_in_ram(schema.clone());
+            let num_spilled_runs = {
+                let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+                index_writer.set_postings_spill_threshold(postings_spill_threshold);
+                for i in 0..2_000u64 {
+                    let words: Vec<String> = (0..(i % 10 + 1))
+                        .map(|k| format!("w{}", (i * 7 + k * k) % 50))
+                        .collect();
+                    let mut doc = doc!(
+                        text_field => format!("{} u{}", words.join(" "), i),
+                        string_field => format!("s{}", i % 30),
+                        int_field => i % 100
+                    );
+                    doc.add_facet(facet_field, &format!("/cat/c{}/d{}", i % 5, i % 3)[..]);
+                    index_writer.add_document(doc).unwrap();
+                }
+                index_writer.commit().unwrap();
+                index_writer.num_spilled_runs()
+            };
+            index.load_searchers().unwrap();
+            (index, num_spilled_runs)
+        };
+        let (index_without_spill, num_spilled_runs_without_spill) = build_index(None);
+        let (index_with_spill, num_spilled_runs_with_spill) = build_index(Some(10_000));
+        assert_eq!(num_spilled_runs_without_spill, 0);
+        assert!(num_spilled_runs_with_spill > 0);
+
+        let postings_dump = |index: &Index| {
+            let searcher = index.searcher();
+            assert_eq!(searcher.segment_readers().len(), 1);
+            let mut dump: Vec<(Vec<u8>, Vec<(DocId, u32, Vec<u32>)>)> = vec![];
+            for &field in &fields {
+                let inverted_index = searcher.segment_reader(0).inverted_index(field);
+                let mut term_stream = inverted_index.terms().stream();
+                while term_stream.advance() {
+                    let mut postings = inverted_index.read_postings_from_terminfo(
+                        term_stream.value(),
+                        IndexRecordOption::WithFreqsAndPositions,
+                    );
+                    let mut docs = vec![];
+                    while postings.advance() {
+                        docs.push((
+                            postings.doc(),
+                            postings.term_freq(),
+                            postings.positions().to_owned(),
+                        ));
+                    }
+                    let mut term_bytes = Term::from_field_text(field, "").as_slice().to_owned();
+                    term_bytes.extend_from_slice(term_stream.key());
+                    dump.push((term_bytes, docs));
+                }
+            }
+            dump
+        };
+        assert_eq!(
+            postings_dump(&index_without_spill),
+            postings_dump(&index_with_spill)
+        );
+
+        let search_results = |index: &Index| {
+            let searcher = index.searcher();
+            let query_parser = QueryParser::for_index(index, vec![text_field]);
+            let counts: Vec<usize> = ["w3", "\"w7 w8\"", "u1234", "string:s7", "int:42"]
+                .iter()
+                .map(|query_str| {
+                    let query = query_parser.parse_query(query_str).unwrap();
+                    let mut count_collector = CountCollector::default();
+                    query.search(&*searcher, &mut count_collector).unwrap();
+                    count_collector.count()
+                })
+                .collect();
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.add_facet("/cat");
+            facet_collector.add_facet("/cat/c2");
+            query_parser
+                .parse_query("w3")
+                .unwrap()
+                .search(&*searcher, &mut facet_collector)
+                .unwrap();
+            let facet_counts = facet_collector.harvest();
+            let facets: Vec<(Facet, u64)> = facet_counts
+                .get("/cat")
+                .chain(facet_counts.get("/cat/c2"))
+                .map(|(facet, count)| (facet.clone(), count))
+                .collect();
+            (counts, facets)
+        };
+        let (counts, facets) = search_results(&index_with_spill);
+        assert_eq!(counts[2], 1);
+        assert!(!facets.is_empty());
+        assert_eq!((counts, facets), search_results(&index_without_spill));
+    }
+
+    #[test]
+    fn test_num_serialization_threads() {
+        use core::SegmentComponent;
+        use schema::{Cardinality, IntOptions, FAST, STORED, STRING, TEXT};
+
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_fields: Vec<_> = (0..4)
+            .map(|i| schema_builder.add_text_field(&format!("text{}", i), TEXT | STORED))
+            .collect();
+        let u64_fields: Vec<_> = (0..6)
+            .map(|i| schema_builder.add_u64_field(&format!("u64_{}", i), FAST))
+            .collect();
+        let i64_field = schema_builder.add_i64_field("i64", FAST);
+        let multi_options = IntOptions::default().set_fast(Cardinality::MultiValues);
+        let multi_field = schema_builder.add_u64_field("multi", multi_options);
+        let string_field = schema_builder.add_text_field("string", STRING);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+
+        let build_index = |num_serialization_threads: usize| {
+            let index = Index::create_in_ram(schema.clone());
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            assert_eq!(index_writer.num_serialization_threads(), 1);
+            assert!(index_writer.set_num_serialization_threads(0).is_err());
+            index_writer
+                .set_num_serialization_threads(num_serialization_threads)
+                .unwrap();
+            for i in 0..1_000u64 {
+                let mut doc = Document::default();
+                for (k, &text_field) in text_fields.iter().enumerate() {
+                    let text = format!("w{} w{} v{}", i % 7, (i + k as u64) % 13, i);
+                    doc.add_text(text_field, &text);
+                }
+                for (k, &u64_field) in u64_fields.iter().enumerate() {
+                    doc.add_u64(u64_field, i * (k as u64 + 1) % 1_000);
+                }
+                doc.add_i64(i64_field, i as i64 - 500);
+                for j in 0..(i % 3) {
+                    doc.add_u64(multi_field, i + j);
+                }
+                doc.add_text(string_field, &format!("s{}", i % 30));
+                doc.add_facet(facet_field, &format!("/cat/c{}", i % 5)[..]);
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+            index
+        };
+        let segment_files = |index: &Index| {
+            let segments = index.searchable_segments().unwrap();
+            assert_eq!(segments.len(), 1);
+            SegmentComponent::iterator()
+                .map(|&component| {
+                    let data = segments[0]
+                        .open_read(component)
+                        .ok()
+                        .map(|source| source.as_slice().to_vec());
+                    (component, data)
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected_files = segment_files(&build_index(1));
+        for &num_serialization_threads in &[2, 3, 100] {
+            let files = segment_files(&build_index(num_serialization_threads));
+            for (&(component, ref expected_data), &(_, ref data)) in
+                expected_files.iter().zip(files.iter())
+            {
+                assert_eq!(expected_data, data, "{:?}", component);
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_num_threads() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(4, 4 * 30_000_000).unwrap();
+            for _ in 0..1_000 {
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
+            }
+            index_writer.set_num_threads(1).unwrap();
+            assert_eq!(index_writer.num_threads(), 1);
+            for _ in 0..1_000 {
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
+            }
+            index_writer.commit().unwrap();
+            index_writer.set_num_threads(2).unwrap();
+            assert_eq!(index_writer.num_threads(), 2);
+            for _ in 0..1_000 {
+                index_writer.add_document(doc!(text_field => "a")).unwrap();
+            }
+            index_writer.commit().unwrap();
+            assert!(index_writer.set_num_threads(0).is_err());
+            assert_eq!(index_writer.num_threads(), 2);
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 3_000);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "a")), 3_000);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(2, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        index_writer.add_document(doc!(text_field => "b")).unwrap();
+        let opstamp = index_writer.shutdown().unwrap();
+        assert_eq!(opstamp, Opstamp(3));
+        assert_eq!(index.load_metas().unwrap().opstamp, Opstamp(3));
+
+        // the lock has been released.
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "c")).unwrap();
+        index_writer.shutdown().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 3);
+    }
+
+    /// Token filter panicking on the token "boom".
+    #[derive(Clone)]
+    struct PanicOnBoom;
+
+    impl<TailTokenStream: TokenStream> TokenFilter<TailTokenStream> for PanicOnBoom {
+        type ResultTokenStream = PanicOnBoomTokenStream<TailTokenStream>;
+
+        fn transform(&self, tail: TailTokenStream) -> Self::ResultTokenStream {
+            PanicOnBoomTokenStream { tail }
+        }
+    }
+
+    struct PanicOnBoomTokenStream<TailTokenStream> {
+        tail: TailTokenStream,
+    }
+
+    impl<TailTokenStream: TokenStream> TokenStream for PanicOnBoomTokenStream<TailTokenStream> {
+        fn token(&self) -> &Token {
+            self.tail.token()
+        }
+
+        fn token_mut(&mut self) -> &mut Token {
+            self.tail.token_mut()
+        }
+
+        fn advance(&mut self) -> bool {
+            if !self.tail.advance() {
+                return false;
+            }
+            assert_ne!(self.tail.token().text, "boom", "the tokenizer exploded");
+            true
+        }
+    }
+
+    #[test]
+    fn test_worker_panic() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field(
+            "text",
+            schema::TextOptions::default().set_indexing_options(
+                schema::TextFieldIndexing::default().set_tokenizer("panicky"),
+            ),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        index
+            .tokenizers()
+            .register("panicky", SimpleTokenizer.filter(PanicOnBoom));
+        let is_worker_panic = |result: Result<Opstamp>, expected_opstamp: u64| match result {
+            Err(Error(ErrorKind::WorkerPanicked(ref message, opstamp), _)) => {
+                assert!(message.contains("the tokenizer exploded"));
+                assert_eq!(opstamp, Opstamp(expected_opstamp));
+                true
+            }
+            _ => false,
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "hello")).unwrap();
+        index_writer.add_document(doc!(text_field => "boom")).unwrap();
+        // the commit fails rather than hanging...
+        assert!(is_worker_panic(index_writer.commit(), 2));
+        // ... and so do the following operations.
+        assert!(is_worker_panic(
+            index_writer
+                .add_document(doc!(text_field => "world"))
+                .map(|add_result| add_result.opstamp),
+            2
+        ));
+        assert!(is_worker_panic(index_writer.commit(), 2));
+
+        // the writer is usable again after a rollback,
+        // which does not reuse the opstamps of the lost documents.
+        index_writer.rollback().unwrap();
+        index_writer.add_document(doc!(text_field => "world")).unwrap();
+        index_writer.commit().unwrap();
+
+        // when waiting for the documents to be indexed, the panic surfaces right away.
+        index_writer.set_report_segment_local_docs(true);
+        assert!(is_worker_panic(
+            index_writer
+                .add_document(doc!(text_field => "boom"))
+                .map(|add_result| add_result.opstamp),
+            5
+        ));
+        index_writer.rollback().unwrap();
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "world")), 1);
+    }
+
+    #[test]
+    fn test_worker_panic_with_full_document_queue() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field(
+            "text",
+            schema::TextOptions::default().set_indexing_options(
+                schema::TextFieldIndexing::default().set_tokenizer("panicky"),
+            ),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        index
+            .tokenizers()
+            .register("panicky", SimpleTokenizer.filter(PanicOnBoom));
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "boom")).unwrap();
+        // once the queue is full, the documents can only be consumed
+        // by the worker that panicked.
+        let mut num_added_docs = 0;
+        let add_result = loop {
+            match index_writer.add_document(doc!(text_field => "hello")) {
+                Ok(_) => {
+                    num_added_docs += 1;
+                    assert!(num_added_docs <= PIPELINE_MAX_SIZE_IN_DOCS + 1);
+                }
+                Err(e) => {
+                    break e;
+                }
+            }
+        };
+        match add_result {
+            Error(ErrorKind::WorkerPanicked(ref message, opstamp), _) => {
+                assert!(message.contains("the tokenizer exploded"));
+                assert_eq!(opstamp, Opstamp(1));
+            }
+            _ => panic!("expected a worker panic"),
+        }
+        index_writer.rollback().unwrap();
+        index_writer.add_document(doc!(text_field => "world")).unwrap();
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_opstamps_increase_across_writers() {
+        use collector::CountCollector;
+        use query::{Query, TermQuery};
+        use schema::IndexRecordOption;
+
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let term = |text: &str| Term::from_field_text(text_field, text);
+        assert_eq!(index.last_committed_opstamp().unwrap(), Opstamp(0));
+
+        let mut opstamps = vec![];
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            assert_eq!(index_writer.last_assigned_opstamp(), Opstamp(0));
+            let add_result = index_writer.add_document(doc!(text_field => "a")).unwrap();
+            opstamps.push(add_result.opstamp);
+            opstamps.push(index_writer.delete_term(term("b")));
+            let add_result = index_writer.add_document(doc!(text_field => "b")).unwrap();
+            opstamps.push(add_result.opstamp);
+            opstamps.push(index_writer.commit().unwrap());
+            assert_eq!(index_writer.last_assigned_opstamp(), opstamps[3]);
+        }
+        assert_eq!(index.last_committed_opstamp().unwrap(), opstamps[3]);
+        {
+            // the new writer resumes after the last commit.
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            assert_eq!(index_writer.last_assigned_opstamp(), opstamps[3]);
+            opstamps.push(index_writer.delete_term(term("a")));
+            let add_result = index_writer.add_document(doc!(text_field => "a")).unwrap();
+            opstamps.push(add_result.opstamp);
+            opstamps.push(index_writer.delete_term(term("b")));
+            let add_result = index_writer.add_document(doc!(text_field => "c")).unwrap();
+            opstamps.push(add_result.opstamp);
+            index_writer.rollback().unwrap();
+            // ... even after a rollback.
+            opstamps.push(index_writer.delete_term(term("a")));
+            let add_result = index_writer.add_document(doc!(text_field => "a")).unwrap();
+            opstamps.push(add_result.opstamp);
+            opstamps.push(index_writer.delete_term(term("b")));
+            opstamps.push(index_writer.commit().unwrap());
+        }
+        assert_eq!(index.last_committed_opstamp().unwrap(), opstamps[11]);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let add_result = index_writer.add_document(doc!(text_field => "d")).unwrap();
+            opstamps.push(add_result.opstamp);
+            opstamps.push(index_writer.shutdown().unwrap());
+        }
+        assert_eq!(index.last_committed_opstamp().unwrap(), opstamps[13]);
+        for window in opstamps.windows(2) {
+            assert!(window[0] < window[1], "{:?}", opstamps);
+        }
+
+        // the deletes only affect the documents added before them.
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let num_docs_containing = |text: &str| {
+            let query = TermQuery::new(term(text), IndexRecordOption::Basic);
+            let mut count_collector = CountCollector::default();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(num_docs_containing("a"), 1);
+        assert_eq!(num_docs_containing("b"), 0);
+        assert_eq!(num_docs_containing("c"), 0);
+        assert_eq!(num_docs_containing("d"), 1);
+    }
 }
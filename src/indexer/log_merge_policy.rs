@@ -1,6 +1,7 @@
 use super::merge_policy::{MergeCandidate, MergePolicy};
 use core::SegmentMeta;
 use std::cmp;
+use std::collections::HashSet;
 use std::f64;
 
 const DEFAULT_LEVEL_LOG_SIZE: f64 = 0.75;
@@ -14,6 +15,7 @@ pub struct LogMergePolicy {
     min_merge_size: usize,
     min_layer_size: u32,
     level_log_size: f64,
+    max_deletes_ratio: Option<f32>,
 }
 
 impl LogMergePolicy {
@@ -42,6 +44,21 @@ impl LogMergePolicy {
     pub fn set_level_log_size(&mut self, level_log_size: f64) {
         self.level_log_size = level_log_size;
     }
+
+    /// Set the ratio of deleted documents above which a segment
+    /// is rewritten, to expunge its deleted documents.
+    ///
+    /// Without such a limit, the deleted documents of a segment are only
+    /// expunged when the segment is merged with segments of similar sizes,
+    /// so that a large segment may keep most of its documents deleted for
+    /// a long time, and slow the queries down.
+    ///
+    /// A segment that is already part of a merge of its level is not
+    /// rewritten on its own. A rewritten segment has no deleted documents
+    /// anymore, so that it is only rewritten again after new deletes.
+    pub fn set_max_deletes_ratio(&mut self, max_deletes_ratio: f32) {
+        self.max_deletes_ratio = Some(max_deletes_ratio);
+    }
 }
 
 impl MergePolicy for LogMergePolicy {
@@ -74,11 +91,26 @@ impl MergePolicy for LogMergePolicy {
             levels.last_mut().unwrap().push(ind);
         }
 
-        levels
+        let mut merge_candidates: Vec<MergeCandidate> = levels
             .iter()
             .filter(|level| level.len() >= self.min_merge_size)
             .map(|ind_vec| MergeCandidate(ind_vec.iter().map(|&ind| segments[ind].id()).collect()))
-            .collect()
+            .collect();
+
+        if let Some(max_deletes_ratio) = self.max_deletes_ratio {
+            let merged_segment_ids: HashSet<_> = merge_candidates
+                .iter()
+                .flat_map(|merge_candidate| merge_candidate.0.iter().cloned())
+                .collect();
+            let rewrite_candidates: Vec<MergeCandidate> = segments
+                .iter()
+                .filter(|segment| segment.deleted_docs_ratio() > max_deletes_ratio)
+                .filter(|segment| !merged_segment_ids.contains(&segment.id()))
+                .map(|segment| MergeCandidate(vec![segment.id()]))
+                .collect();
+            merge_candidates.extend(rewrite_candidates);
+        }
+        merge_candidates
     }
 
     fn box_clone(&self) -> Box<MergePolicy> {
@@ -92,6 +124,7 @@ impl Default for LogMergePolicy {
             min_merge_size: DEFAULT_MIN_MERGE_SIZE,
             min_layer_size: DEFAULT_MIN_LAYER_SIZE,
             level_log_size: DEFAULT_LEVEL_LOG_SIZE,
+            max_deletes_ratio: None,
         }
     }
 }
@@ -101,6 +134,7 @@ mod tests {
     use super::*;
     use indexer::merge_policy::MergePolicy;
     use core::{SegmentId, SegmentMeta};
+    use indexer::Opstamp;
 
     fn test_merge_policy() -> LogMergePolicy {
         let mut log_merge_policy = LogMergePolicy::default();
@@ -181,4 +215,37 @@ mod tests {
         let result_list = test_merge_policy().compute_merge_candidates(&test_input);
         assert_eq!(result_list.len(), 1);
     }
+
+    fn seg_meta_with_deletes(max_doc: u32, num_deleted_docs: u32) -> SegmentMeta {
+        let mut segment_meta = seg_meta(max_doc);
+        segment_meta.set_delete_meta(num_deleted_docs, Opstamp(0));
+        segment_meta
+    }
+
+    #[test]
+    fn test_log_merge_policy_max_deletes_ratio() {
+        let test_input = vec![
+            seg_meta_with_deletes(10_000, 6_000),
+            seg_meta_with_deletes(10_000, 4_000),
+            // part of a merge of its level, with 8 documents left.
+            seg_meta_with_deletes(20, 12),
+            seg_meta(10),
+            seg_meta(10),
+        ];
+        assert_eq!(test_input[0].deleted_docs_ratio(), 0.6);
+        assert_eq!(seg_meta(0).deleted_docs_ratio(), 0.0);
+        let mut merge_policy = test_merge_policy();
+        assert_eq!(merge_policy.compute_merge_candidates(&test_input).len(), 1);
+        merge_policy.set_max_deletes_ratio(0.6);
+        assert_eq!(merge_policy.compute_merge_candidates(&test_input).len(), 1);
+        merge_policy.set_max_deletes_ratio(0.5);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 2);
+        assert_eq!(result_list[0].0.len(), 3);
+        assert_eq!(result_list[1].0, vec![test_input[0].id()]);
+        merge_policy.set_max_deletes_ratio(0.3);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 3);
+        assert_eq!(result_list[2].0, vec![test_input[1].id()]);
+    }
 }
@@ -1,5 +1,6 @@
 use error::{ErrorKind, Result};
 use core::SegmentReader;
+use compat;
 use core::Segment;
 use DocId;
 use core::SerializableSegment;
@@ -9,14 +10,24 @@ use itertools::Itertools;
 use postings::Postings;
 use docset::DocSet;
 use fastfield::DeleteBitSet;
-use schema::{Field, Schema};
+use schema::{Cardinality, Field, FieldType, IndexRecordOption, Schema};
 use termdict::TermMerger;
 use fastfield::FastFieldSerializer;
 use fastfield::FastFieldReader;
+use fastfield::FastValue;
+use fastfield::FieldPresenceWriter;
+use fastfield::FacetLabelsWriter;
+use fastfield::MultiValueIntFastFieldWriter;
+use common::CompositeWrite;
+use directory::WritePtr;
 use store::StoreWriter;
 use std::cmp::{max, min};
 use termdict::TermDictionary;
 use termdict::TermStreamer;
+use termdict::TermOrdinal;
+use termdict::TermOrdinalMapping;
+use std::collections::HashMap;
+use byteorder::{BigEndian, ByteOrder};
 
 pub struct IndexMerger {
     schema: Schema,
@@ -60,6 +71,47 @@ fn extract_fast_field_reader(
     segment_reader.fast_field_reader(field).ok()
 }
 
+// segments written before the range of values was recorded
+// in the segment meta fall back to the first and last term of
+// their term dictionary.
+fn int_value_range(reader: &SegmentReader, field: Field) -> Option<(u64, u64)> {
+    if let Some(int_value_range) = reader.segment_meta().int_value_range(field) {
+        return Some(int_value_range);
+    }
+    let inverted_index = reader.inverted_index(field);
+    let term_dict = inverted_index.terms();
+    let num_terms = term_dict.num_terms();
+    if num_terms == 0 {
+        return None;
+    }
+    let mut term_bytes = Vec::with_capacity(8);
+    term_dict.ord_to_term(0, &mut term_bytes);
+    let min_value = BigEndian::read_u64(&term_bytes);
+    term_bytes.clear();
+    term_dict.ord_to_term((num_terms - 1) as TermOrdinal, &mut term_bytes);
+    let max_value = BigEndian::read_u64(&term_bytes);
+    Some((min_value, max_value))
+}
+
+// segments written before the range of values was recorded
+// in the segment meta fall back to the header of their fast field.
+fn fast_field_value_range(
+    reader: &SegmentReader,
+    field: Field,
+    field_type: &FieldType,
+) -> Option<(u64, u64)> {
+    if let Some(fast_field_value_range) = reader.segment_meta().fast_field_value_range(field) {
+        return Some(fast_field_value_range);
+    }
+    if let FieldType::I64(_) = *field_type {
+        let ff_reader = reader.fast_field_reader::<i64>(field).ok()?;
+        Some((ff_reader.min_value().to_u64(), ff_reader.max_value().to_u64()))
+    } else {
+        let ff_reader = reader.fast_field_reader::<u64>(field).ok()?;
+        Some((ff_reader.min_value(), ff_reader.max_value()))
+    }
+}
+
 struct DeltaComputer {
     buffer: Vec<u32>,
 }
@@ -107,7 +159,7 @@ impl IndexMerger {
             .fields()
             .iter()
             .enumerate()
-            .filter(|&(_, field_entry)| field_entry.is_indexed())
+            .filter(|&(_, field_entry)| field_entry.has_fieldnorms())
             .map(|(field_id, _)| Field(field_id as u32))
             .collect();
         self.generic_write_fast_field(
@@ -247,16 +299,14 @@ impl IndexMerger {
             //                                seg0.max_doc + seg1.max_doc + seg2.max_doc]
             // ...
 
-            let mut field_serializer = serializer.new_field(indexed_field)?;
-
-            let field_entry = self.schema.get_field_entry(indexed_field);
-
             // ... set segment postings option the new field.
-            let segment_postings_option =
-                field_entry.field_type().get_index_record_option().expect(
-                    "Encountered a field that is not supposed to be
-                         indexed. Have you modified the schema?",
-                );
+            let segment_postings_option = self.index_record_option(indexed_field).expect(
+                "Encountered a field that is not supposed to be
+                     indexed. Have you modified the schema?",
+            );
+
+            let mut field_serializer =
+                serializer.new_field_with_record_option(indexed_field, segment_postings_option)?;
 
             while merged_terms.advance() {
                 let term_bytes: &[u8] = merged_terms.key();
@@ -329,14 +379,130 @@ impl IndexMerger {
         Ok(())
     }
 
+    fn write_value_position_offsets(
+        &self,
+        fast_field_serializer: &mut FastFieldSerializer,
+    ) -> Result<()> {
+        let fields: Vec<Field> = self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => text_options
+                    .get_indexing_options()
+                    .map(|indexing_options| indexing_options.value_position_offsets())
+                    .unwrap_or(false),
+                _ => false,
+            })
+            .map(|(field_id, _)| Field(field_id as u32))
+            .collect();
+        let mut offsets = vec![];
+        for field in fields {
+            let mut value_position_offsets_writer = MultiValueIntFastFieldWriter::new(field, true);
+            for reader in &self.readers {
+                let value_position_offsets_reader = reader.value_position_offsets_reader(field)?;
+                for doc in 0..reader.max_doc() {
+                    if reader.is_deleted(doc) {
+                        continue;
+                    }
+                    value_position_offsets_writer.next_doc();
+                    value_position_offsets_reader.get_vals(doc, &mut offsets);
+                    for &offset in &offsets {
+                        value_position_offsets_writer.add_val(offset);
+                    }
+                }
+            }
+            value_position_offsets_writer.serialize(fast_field_serializer, None)?;
+        }
+        Ok(())
+    }
+
+    // writes the facet ordinals, and returns the facet labels
+    // associated to the merged facet ordinals.
+    fn write_facet_fields(
+        &self,
+        fast_field_serializer: &mut FastFieldSerializer,
+    ) -> Result<FacetLabelsWriter> {
+        let facet_fields: Vec<Field> = self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| field_entry.field_type() == &FieldType::HierarchicalFacet)
+            .map(|(field_id, _)| Field(field_id as u32))
+            .collect();
+        let mut facet_labels_writer = FacetLabelsWriter::new();
+        let mut facet_ords = vec![];
+        for field in facet_fields {
+            // Every facet term has at least one posting, so that
+            // the global ordinals match the ordinals of the merged term dictionary.
+            let term_ordinal_mapping = TermOrdinalMapping::build(&self.readers, field);
+            let mut facet_ords_writer = MultiValueIntFastFieldWriter::new(field, true);
+            for (segment_ord, reader) in self.readers.iter().enumerate() {
+                let segment_mapping = term_ordinal_mapping.segment_mapping(segment_ord);
+                // segments obtained by a merge before facet ordinals
+                // were merged do not have any facet fast field.
+                let mut facet_reader_opt = reader.facet_reader(field).ok();
+                if let Some(ref facet_reader) = facet_reader_opt {
+                    for (facet_ord, label) in facet_reader.labels() {
+                        let merged_facet_ord = segment_mapping[facet_ord as usize];
+                        facet_labels_writer.record(field, merged_facet_ord, label);
+                    }
+                }
+                for doc in 0..reader.max_doc() {
+                    if reader.is_deleted(doc) {
+                        continue;
+                    }
+                    facet_ords_writer.next_doc();
+                    if let Some(ref mut facet_reader) = facet_reader_opt {
+                        facet_reader.facet_ords(doc, &mut facet_ords);
+                        for &facet_ord in &facet_ords {
+                            facet_ords_writer.add_val(segment_mapping[facet_ord as usize]);
+                        }
+                    }
+                }
+            }
+            facet_ords_writer.serialize(fast_field_serializer, None)?;
+        }
+        Ok(facet_labels_writer)
+    }
+
+    fn write_field_presence(&self, serializer: &mut SegmentSerializer) -> Result<()> {
+        let mut field_presence_writer = FieldPresenceWriter::from_schema(&self.schema);
+        let fields: Vec<Field> = (0..self.schema.fields().len())
+            .map(|field_id| Field(field_id as u32))
+            .filter(|&field| field_presence_writer.is_recorded(field))
+            .collect();
+        let mut merged_doc: DocId = 0;
+        for reader in &self.readers {
+            let field_docs = fields
+                .iter()
+                .map(|&field| reader.docs_with_field(field))
+                .collect::<Result<Vec<_>>>()?;
+            for doc in 0..reader.max_doc() {
+                if reader.is_deleted(doc) {
+                    continue;
+                }
+                for (&field, docs) in fields.iter().zip(field_docs.iter()) {
+                    if docs.contains(doc) {
+                        field_presence_writer.record(field, merged_doc);
+                    }
+                }
+                merged_doc += 1;
+            }
+        }
+        if !field_presence_writer.is_empty() {
+            field_presence_writer.serialize(self.max_doc, serializer.get_field_presence_write()?)?;
+        }
+        Ok(())
+    }
+
     fn write_storable_fields(&self, store_writer: &mut StoreWriter) -> Result<()> {
         for reader in &self.readers {
             let store_reader = reader.get_store_reader();
             if reader.num_deleted_docs() > 0 {
                 for doc_id in 0..reader.max_doc() {
                     if !reader.is_deleted(doc_id) {
-                        let doc = store_reader.get(doc_id)?;
-                        store_writer.store(&doc)?;
+                        store_writer.copy_doc(store_reader, doc_id)?;
                     }
                 }
             } else {
@@ -345,6 +511,133 @@ impl IndexMerger {
         }
         Ok(())
     }
+
+    /// Returns the `IndexRecordOption` the postings of a field
+    /// are written with in the merged segment.
+    ///
+    /// If the schema was modified, the segments may have been
+    /// written with different options. Since the missing information
+    /// cannot be made up, the merged segment gets the intersection of the options
+    /// of the schema and of the segments.
+    fn index_record_option(&self, field: Field) -> Option<IndexRecordOption> {
+        let schema_record_option = self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option()?;
+        Some(
+            self.readers
+                .iter()
+                .flat_map(|reader| reader.index_record_option(field))
+                .fold(schema_record_option, |merged_record_option, record_option| {
+                    merged_record_option.intersection(record_option)
+                }),
+        )
+    }
+
+    /// Returns the `IndexRecordOption` of each of the indexed
+    /// fields in the merged segment.
+    pub fn index_record_options(&self) -> Vec<(Field, IndexRecordOption)> {
+        (0..self.schema.fields().len())
+            .map(|field_id| Field(field_id as u32))
+            .filter_map(|field| {
+                self.index_record_option(field)
+                    .map(|index_record_option| (field, index_record_option))
+            })
+            .collect()
+    }
+
+    /// Returns the format version of the merged segment.
+    ///
+    /// The terms are copied as is, so that merging segments written by
+    /// upstream tantivy yields a segment that is still in the upstream format.
+    pub fn format_version(&self) -> u32 {
+        self.readers
+            .iter()
+            .map(|reader| reader.format_version())
+            .fold(compat::INDEX_FORMAT_VERSION, min)
+    }
+
+    /// Returns the smallest and the largest value of each indexed
+    /// `u64` and `i64` field, over the segments being merged.
+    ///
+    /// The range may be wider than the one of the merged segment,
+    /// as the values of the deleted documents are still accounted for.
+    pub fn int_value_ranges(&self) -> Vec<(Field, u64, u64)> {
+        self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| match *field_entry.field_type() {
+                FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
+                    int_options.is_indexed()
+                }
+                _ => false,
+            })
+            .filter_map(|(field_id, _)| {
+                let field = Field(field_id as u32);
+                self.readers
+                    .iter()
+                    .flat_map(|reader| int_value_range(reader, field))
+                    .fold1(|(left_min, left_max), (right_min, right_max)| {
+                        (min(left_min, right_min), max(left_max, right_max))
+                    })
+                    .map(|(min_value, max_value)| (field, min_value, max_value))
+            })
+            .collect()
+    }
+
+    /// Returns the smallest and the largest value of each single-valued
+    /// `u64` and `i64` fast field, over the segments being merged.
+    ///
+    /// Like for `int_value_ranges`, the values of the deleted
+    /// documents are still accounted for.
+    pub fn fast_field_value_ranges(&self) -> Vec<(Field, u64, u64)> {
+        self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| match *field_entry.field_type() {
+                FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
+                    int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+                }
+                _ => false,
+            })
+            .filter_map(|(field_id, field_entry)| {
+                let field = Field(field_id as u32);
+                self.readers
+                    .iter()
+                    .flat_map(|reader| {
+                        fast_field_value_range(reader, field, field_entry.field_type())
+                    })
+                    .fold1(|(left_min, left_max), (right_min, right_max)| {
+                        (min(left_min, right_min), max(left_max, right_max))
+                    })
+                    .map(|(min_value, max_value)| (field, min_value, max_value))
+            })
+            .collect()
+    }
+
+    /// Returns the total number of tokens of each field with field norms,
+    /// over the documents of the merged segment.
+    ///
+    /// The tokens of the deleted documents, which are expunged
+    /// by the merge, are not counted.
+    pub fn total_num_tokens(&self) -> Vec<(Field, u64)> {
+        self.schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| field_entry.has_fieldnorms())
+            .map(|(field_id, _)| {
+                let field = Field(field_id as u32);
+                let total_num_tokens = self.readers
+                    .iter()
+                    .map(|reader| reader.sum_fieldnorms(field, false))
+                    .sum();
+                (field, total_num_tokens)
+            })
+            .collect()
+    }
 }
 
 impl SerializableSegment for IndexMerger {
@@ -352,7 +645,12 @@ impl SerializableSegment for IndexMerger {
         self.write_postings(serializer.get_postings_serializer())?;
         self.write_fieldnorms(serializer.get_fieldnorms_serializer())?;
         self.write_fast_fields(serializer.get_fast_field_serializer())?;
+        self.write_value_position_offsets(serializer.get_fast_field_serializer())?;
+        let facet_labels_writer = self.write_facet_fields(serializer.get_fast_field_serializer())?;
+        // labels are already associated to the merged facet ordinals.
+        facet_labels_writer.serialize(serializer.get_facet_labels_write(), &HashMap::new())?;
         self.write_storable_fields(serializer.get_store_writer())?;
+        self.write_field_presence(&mut serializer)?;
         serializer.close()?;
         Ok(self.max_doc)
     }
@@ -399,19 +697,19 @@ mod tests {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "af b");
                     doc.add_u64(score_field, 3);
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "a b c");
                     doc.add_u64(score_field, 5);
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "a b c d");
                     doc.add_u64(score_field, 7);
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 index_writer.commit().expect("committed");
             }
@@ -422,13 +720,13 @@ mod tests {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "af b");
                     doc.add_u64(score_field, 11);
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "a b c g");
                     doc.add_u64(score_field, 13);
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 index_writer.commit().expect("Commit failed");
             }
@@ -534,16 +832,16 @@ mod tests {
             index_writer.add_document(doc!(
                     text_field => "a b d",
                     score_field => 1u64
-                ));
+                )).unwrap();
             index_writer.add_document(doc!(
                     text_field => "b c",
                     score_field => 2u64
-                ));
+                )).unwrap();
             index_writer.delete_term(Term::from_field_text(text_field, "c"));
             index_writer.add_document(doc!(
                     text_field => "c d",
                     score_field => 3u64
-                ));
+                )).unwrap();
             index_writer.commit().expect("committed");
             index.load_searchers().unwrap();
             let ref searcher = *index.searcher();
@@ -572,21 +870,21 @@ mod tests {
             index_writer.add_document(doc!(
                     text_field => "a d e",
                     score_field => 4_000u64
-                ));
+                )).unwrap();
             index_writer.add_document(doc!(
                     text_field => "e f",
                     score_field => 5_000u64
-                ));
+                )).unwrap();
             index_writer.delete_term(Term::from_field_text(text_field, "a"));
             index_writer.delete_term(Term::from_field_text(text_field, "f"));
             index_writer.add_document(doc!(
                     text_field => "f g",
                     score_field => 6_000u64
-                ));
+                )).unwrap();
             index_writer.add_document(doc!(
                     text_field => "g h",
                     score_field => 7_000u64
-                ));
+                )).unwrap();
             index_writer.commit().expect("committed");
             index.load_searchers().unwrap();
             let searcher = index.searcher();
@@ -805,4 +1103,434 @@ mod tests {
             assert_eq!(searcher.num_docs(), 0);
         }
     }
+
+    #[test]
+    fn test_merge_total_num_tokens() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED);
+        let schema = schema_builder.build();
+        let make_doc = |id: u64| {
+            let text = (0..id % 5 + 1)
+                .map(|token_id| format!("w{}", token_id))
+                .collect::<Vec<_>>()
+                .join(" ");
+            doc!(text_field => text, id_field => id)
+        };
+        let total_num_tokens = |index: &Index| -> Vec<u64> {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| segment_reader.inverted_index(text_field).total_num_tokens())
+                .collect()
+        };
+
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for id in 0..20u64 {
+            index_writer.add_document(make_doc(id)).unwrap();
+        }
+        index_writer.commit().expect("committed");
+        for id in 20..40u64 {
+            index_writer.add_document(make_doc(id)).unwrap();
+        }
+        index_writer.commit().expect("committed");
+        // deletes half of the documents of the second segment.
+        for id in (20..40u64).filter(|id| id % 2 == 0) {
+            index_writer.delete_term(Term::from_field_u64(id_field, id));
+        }
+        index_writer.commit().expect("committed");
+        // deleted documents are counted until they are expunged.
+        let mut totals_before_merge = total_num_tokens(&index);
+        totals_before_merge.sort();
+        assert_eq!(totals_before_merge, vec![60, 60]);
+
+        let segment_ids = index
+            .searchable_segment_ids()
+            .expect("Searchable segments failed.");
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+
+        let expected_index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = expected_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for id in (0..40u64).filter(|id| *id < 20 || id % 2 == 1) {
+                index_writer.add_document(make_doc(id)).unwrap();
+            }
+            index_writer.commit().expect("committed");
+        }
+        let expected_total_num_tokens = total_num_tokens(&expected_index);
+        assert_eq!(expected_total_num_tokens, vec![60 + 30]);
+        assert_eq!(total_num_tokens(&index), expected_total_num_tokens);
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(segment_metas[0].total_num_tokens(text_field), Some(90));
+        assert_eq!(segment_metas[0].total_num_tokens(id_field), None);
+    }
+
+    // Writes one segment per `IndexRecordOption`, modifying the schema of
+    // the index in between, and merges them.
+    //
+    // Returns the term frequencies of the term "a" before and after the merge,
+    // as well as the option of the merged segment.
+    fn merge_heterogeneous_record_options(
+        record_options: &[IndexRecordOption],
+        texts: &[&str],
+    ) -> (Vec<u32>, Vec<u32>, IndexRecordOption) {
+        use indexer::segment_updater::save_metas;
+        use directory::Directory;
+        use std::borrow::BorrowMut;
+        use docset::DocSet;
+        use postings::Postings;
+        use tempdir::TempDir;
+        let index_dir = TempDir::new("tantivy_merge_record_options").unwrap();
+        let make_schema = |record_option: IndexRecordOption| {
+            let mut schema_builder = schema::SchemaBuilder::default();
+            let text_field = schema_builder.add_text_field(
+                "text",
+                schema::TextOptions::default().set_indexing_options(
+                    TextFieldIndexing::default().set_index_option(record_option),
+                ),
+            );
+            (schema_builder.build(), text_field)
+        };
+        let (schema, text_field) = make_schema(record_options[0]);
+        Index::create(index_dir.path(), schema).unwrap();
+        for (&record_option, text) in record_options.iter().zip(texts) {
+            let index = Index::open(index_dir.path()).unwrap();
+            let metas = index.load_metas().unwrap();
+            let (schema, _) = make_schema(record_option);
+            save_metas(
+                metas.segments,
+                schema,
+                metas.settings,
+                metas.opstamp,
+                metas.last_opstamp,
+                None,
+                index.directory().box_clone().borrow_mut(),
+            ).unwrap();
+            let index = Index::open(index_dir.path()).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => *text)).unwrap();
+            index_writer.commit().unwrap();
+        }
+        let term_freqs = |index: &Index| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let term = Term::from_field_text(text_field, "a");
+            let mut term_freqs = vec![];
+            for segment_reader in searcher.segment_readers() {
+                let inverted_index = segment_reader.inverted_index(text_field);
+                let mut postings = inverted_index
+                    .read_postings(&term, IndexRecordOption::WithFreqsAndPositions)
+                    .unwrap();
+                while postings.advance() {
+                    term_freqs.push(postings.term_freq());
+                }
+            }
+            term_freqs.sort();
+            term_freqs
+        };
+        let index = Index::open(index_dir.path()).unwrap();
+        let term_freqs_before_merge = term_freqs(&index);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let segment_ids = index.searchable_segment_ids().unwrap();
+            index_writer.merge(&segment_ids).wait().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+        }
+        let term_freqs_after_merge = term_freqs(&index);
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        let merged_record_option = segment_metas[0].index_record_option(text_field).unwrap();
+        assert_eq!(
+            index
+                .searcher()
+                .segment_reader(0)
+                .inverted_index(text_field)
+                .record_option(),
+            merged_record_option
+        );
+        (
+            term_freqs_before_merge,
+            term_freqs_after_merge,
+            merged_record_option,
+        )
+    }
+
+    #[test]
+    fn test_merge_basic_and_freqs() {
+        let (before_merge, after_merge, merged_record_option) = merge_heterogeneous_record_options(
+            &[IndexRecordOption::WithFreqs, IndexRecordOption::Basic],
+            &["a b a", "a a a"],
+        );
+        // each segment is read with its own option, whatever the schema.
+        assert_eq!(before_merge, vec![1, 2]);
+        assert_eq!(merged_record_option, IndexRecordOption::Basic);
+        assert_eq!(after_merge, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_merge_freqs_and_positions() {
+        let (before_merge, after_merge, merged_record_option) = merge_heterogeneous_record_options(
+            &[
+                IndexRecordOption::WithFreqsAndPositions,
+                IndexRecordOption::WithFreqs,
+            ],
+            &["a b a", "a a a"],
+        );
+        assert_eq!(before_merge, vec![2, 3]);
+        assert_eq!(merged_record_option, IndexRecordOption::WithFreqs);
+        assert_eq!(after_merge, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_merge_upgraded_schema() {
+        // the schema now requires positions, but they cannot
+        // be made up for the segment written without them.
+        let (_, after_merge, merged_record_option) = merge_heterogeneous_record_options(
+            &[
+                IndexRecordOption::WithFreqs,
+                IndexRecordOption::WithFreqsAndPositions,
+            ],
+            &["a b a", "a a a"],
+        );
+        assert_eq!(merged_record_option, IndexRecordOption::WithFreqs);
+        assert_eq!(after_merge, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_merge_positions_without_freqs() {
+        // the positions are kept, the term frequencies are
+        // then given by the number of positions.
+        let (before_merge, after_merge, merged_record_option) = merge_heterogeneous_record_options(
+            &[
+                IndexRecordOption::WithFreqsAndPositions,
+                IndexRecordOption::WithPositions,
+            ],
+            &["a b a", "a a a"],
+        );
+        assert_eq!(before_merge, vec![2, 3]);
+        assert_eq!(merged_record_option, IndexRecordOption::WithPositions);
+        assert_eq!(after_merge, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_merge_freqs_with_positions_without_freqs() {
+        // neither the freqs nor the positions are available
+        // in both segments.
+        let (before_merge, after_merge, merged_record_option) = merge_heterogeneous_record_options(
+            &[IndexRecordOption::WithFreqs, IndexRecordOption::WithPositions],
+            &["a b a", "a a a"],
+        );
+        assert_eq!(before_merge, vec![2, 3]);
+        assert_eq!(merged_record_option, IndexRecordOption::Basic);
+        assert_eq!(after_merge, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_merge_facet_labels() {
+        use schema::Facet;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let id_options = schema::IntOptions::default()
+            .set_indexed()
+            .set_fast(Cardinality::SingleValue);
+        let id_field = schema_builder.add_u64_field("id", id_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        {
+            let mut doc = Document::default();
+            doc.add_u64(id_field, 0);
+            doc.add_facet_with_label(facet_field, "/brand/apple", "Apple Inc.");
+            doc.add_facet(facet_field, "/color/red");
+            index_writer.add_document(doc).unwrap();
+        }
+        {
+            let mut doc = Document::default();
+            doc.add_u64(id_field, 1);
+            doc.add_facet_with_label(facet_field, "/brand/samsung", "Samsung");
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().expect("committed");
+        {
+            let mut doc = Document::default();
+            doc.add_u64(id_field, 2);
+            doc.add_facet_with_label(facet_field, "/brand/acme", "ACME");
+            doc.add_facet_with_label(facet_field, "/brand/apple", "Apple Inc.");
+            index_writer.add_document(doc).unwrap();
+        }
+        {
+            let mut doc = Document::default();
+            doc.add_u64(id_field, 3);
+            doc.add_facet_with_label(facet_field, "/color/blue", "Blue");
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().expect("committed");
+        index_writer.delete_term(Term::from_field_u64(id_field, 3));
+        index_writer.commit().expect("committed");
+        let segment_ids = index
+            .searchable_segment_ids()
+            .expect("Searchable segments failed.");
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let mut facet_reader = segment_reader.facet_reader(facet_field).unwrap();
+        let id_reader = segment_reader.fast_field_reader::<u64>(id_field).unwrap();
+        let mut facets_by_id: Vec<(u64, Vec<(String, Option<String>)>)> = vec![];
+        let mut facet_ords = vec![];
+        let mut facet = Facet::root();
+        for doc in 0..segment_reader.max_doc() {
+            facet_reader.facet_ords(doc, &mut facet_ords);
+            let facets = facet_ords
+                .iter()
+                .map(|&facet_ord| {
+                    facet_reader.facet_from_ord(facet_ord, &mut facet);
+                    let label_opt = facet_reader.label_from_ord(facet_ord).map(String::from);
+                    (facet.to_string(), label_opt)
+                })
+                .collect();
+            facets_by_id.push((id_reader.get(doc), facets));
+        }
+        facets_by_id.sort();
+        let facet = |path: &str, label: Option<&str>| (path.to_string(), label.map(String::from));
+        assert_eq!(
+            facets_by_id,
+            vec![
+                (
+                    0,
+                    vec![
+                        facet("/brand/apple", Some("Apple Inc.")),
+                        facet("/color/red", None),
+                    ],
+                ),
+                (1, vec![facet("/brand/samsung", Some("Samsung"))]),
+                (
+                    2,
+                    vec![
+                        facet("/brand/acme", Some("ACME")),
+                        facet("/brand/apple", Some("Apple Inc.")),
+                    ],
+                ),
+            ]
+        );
+        // the ancestors of the labeled facets do not have any label.
+        assert_eq!(facet_reader.label_from_ord(0), None);
+    }
+
+    #[test]
+    fn test_merge_preserves_stored_documents() {
+        use IndexWriter;
+        use common::BinarySerializable;
+        use indexer::NoMergePolicy;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", schema::TEXT | schema::STORED);
+        let tag = schema_builder.add_text_field("tag", schema::STRING | schema::STORED);
+        let id = schema_builder.add_u64_field("id", schema::INT_INDEXED | schema::INT_STORED);
+        let delta = schema_builder.add_i64_field("delta", schema::INT_STORED);
+        let category = schema_builder.add_facet_field("category");
+        let index = Index::create_in_ram(schema_builder.build());
+        // the fields are added in an order depending on the id,
+        // and some of them several times.
+        let make_doc = |doc_id: u64| {
+            let mut doc = Document::default();
+            if doc_id % 2 == 0 {
+                doc.add_text(tag, &format!("tag{}", doc_id % 3));
+            }
+            doc.add_i64(delta, -(doc_id as i64));
+            doc.add_text(title, &format!("title {}", doc_id));
+            doc.add_facet(category, &format!("/category/{}", doc_id % 4));
+            doc.add_u64(id, doc_id);
+            doc.add_text(title, "another title");
+            if doc_id % 3 == 0 {
+                doc.add_text(tag, "first");
+            }
+            doc
+        };
+        let serialize = |doc: &Document| {
+            let mut doc_bytes = Vec::new();
+            doc.serialize(&mut doc_bytes).unwrap();
+            doc_bytes
+        };
+        let stored_docs = |index: &Index| -> Vec<Vec<u8>> {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let mut stored_docs = vec![];
+            for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+                for doc_id in 0..segment_reader.max_doc() {
+                    if !segment_reader.is_deleted(doc_id) {
+                        let doc_address = DocAddress(segment_ord as u32, doc_id);
+                        stored_docs.push(serialize(&searcher.doc(&doc_address).unwrap()));
+                    }
+                }
+            }
+            stored_docs.sort();
+            stored_docs
+        };
+        let expected_docs = |doc_ids: &[u64]| -> Vec<Vec<u8>> {
+            let mut expected_docs: Vec<Vec<u8>> = doc_ids
+                .iter()
+                .map(|&doc_id| serialize(&make_doc(doc_id)))
+                .collect();
+            // one empty document is added with each batch.
+            for _ in 0..3 {
+                expected_docs.push(serialize(&Document::default()));
+            }
+            expected_docs.sort();
+            expected_docs
+        };
+        let merge_all = |index_writer: &mut IndexWriter| {
+            let segment_ids = index
+                .searchable_segment_ids()
+                .expect("Searchable segments failed.");
+            index_writer
+                .merge(&segment_ids)
+                .wait()
+                .expect("Merging failed");
+        };
+
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for batch in 0..3u64 {
+            for doc_id in batch * 10..batch * 10 + 10 {
+                index_writer.add_document(make_doc(doc_id)).unwrap();
+            }
+            index_writer.add_document(Document::default()).unwrap();
+            index_writer.commit().expect("committed");
+        }
+        // the documents of the last segment are copied one by one,
+        // while the other segments are stacked.
+        for &doc_id in &[22u64, 25u64] {
+            index_writer.delete_term(Term::from_field_u64(id, doc_id));
+        }
+        index_writer.commit().expect("committed");
+        let doc_ids: Vec<u64> = (0..30u64).filter(|&doc_id| doc_id != 22 && doc_id != 25).collect();
+        assert_eq!(stored_docs(&index), expected_docs(&doc_ids));
+
+        merge_all(&mut index_writer);
+        index_writer.commit().expect("committed");
+        assert_eq!(stored_docs(&index), expected_docs(&doc_ids));
+
+        // merges the merged segment again, after deleting some of its documents.
+        index_writer.delete_term(Term::from_field_u64(id, 3u64));
+        index_writer.commit().expect("committed");
+        merge_all(&mut index_writer);
+        index_writer.wait_merging_threads().unwrap();
+        let doc_ids: Vec<u64> = doc_ids.into_iter().filter(|&doc_id| doc_id != 3).collect();
+        assert_eq!(stored_docs(&index), expected_docs(&doc_ids));
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().segment_readers().len(), 1);
+    }
 }
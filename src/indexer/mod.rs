@@ -14,12 +14,17 @@ mod doc_opstamp_mapping;
 pub mod operation;
 mod stamper;
 mod prepared_commit;
+mod field_ingest_stats;
+mod segment_verifier;
 
 pub use self::prepared_commit::PreparedCommit;
+pub use self::stamper::Opstamp;
+pub use self::field_ingest_stats::FieldIngestStats;
+pub(crate) use self::field_ingest_stats::merge_field_stats;
 pub use self::segment_entry::{SegmentEntry, SegmentState};
 pub use self::segment_serializer::SegmentSerializer;
 pub use self::segment_writer::SegmentWriter;
-pub use self::index_writer::IndexWriter;
+pub use self::index_writer::{AddDocumentResult, IndexWriter, ValidationMode};
 pub use self::log_merge_policy::LogMergePolicy;
 pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy};
 pub use self::segment_manager::SegmentManager;
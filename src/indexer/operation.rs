@@ -1,16 +1,36 @@
+use futures::sync::oneshot;
+use indexer::Opstamp;
 use schema::Document;
+use schema::Field;
 use schema::Term;
+use std::ops::Range;
+use DocId;
+
+/// Set of documents targetted by a delete operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DeleteTarget {
+    /// All of the documents containing the term.
+    Term(Term),
+    /// All of the documents whose u64 fast field value
+    /// is within the range.
+    U64Range(Field, Range<u64>),
+    /// All of the documents whose i64 fast field value
+    /// is within the range.
+    I64Range(Field, Range<i64>),
+}
 
 /// Timestamped Delete operation.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct DeleteOperation {
-    pub opstamp: u64,
-    pub term: Term,
+    pub opstamp: Opstamp,
+    pub target: DeleteTarget,
 }
 
 /// Timestamped Add operation.
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Debug)]
 pub struct AddOperation {
-    pub opstamp: u64,
+    pub opstamp: Opstamp,
     pub document: Document,
+    // used to report the `DocId` of the document within its segment.
+    pub doc_id_sender: Option<oneshot::Sender<DocId>>,
 }
@@ -1,15 +1,17 @@
 use Result;
-use super::IndexWriter;
+use super::{FieldIngestStats, IndexWriter, Opstamp};
+use schema::Field;
+use std::collections::HashMap;
 
 /// A prepared commit
 pub struct PreparedCommit<'a> {
     index_writer: &'a mut IndexWriter,
     payload: Option<String>,
-    opstamp: u64,
+    opstamp: Opstamp,
 }
 
 impl<'a> PreparedCommit<'a> {
-    pub(crate) fn new(index_writer: &'a mut IndexWriter, opstamp: u64) -> PreparedCommit {
+    pub(crate) fn new(index_writer: &'a mut IndexWriter, opstamp: Opstamp) -> PreparedCommit {
         PreparedCommit {
             index_writer,
             payload: None,
@@ -17,10 +19,19 @@ impl<'a> PreparedCommit<'a> {
         }
     }
 
-    pub fn opstamp(&self) -> u64 {
+    pub fn opstamp(&self) -> Opstamp {
         self.opstamp
     }
 
+    /// Returns statistics about the values of the documents of the commit.
+    ///
+    /// See [`IndexWriter::last_commit_field_stats`][last_commit_field_stats].
+    ///
+    /// [last_commit_field_stats]: ./struct.IndexWriter.html#method.last_commit_field_stats
+    pub fn field_stats(&self) -> &HashMap<Field, FieldIngestStats> {
+        self.index_writer.last_commit_field_stats()
+    }
+
     pub fn set_payload(&mut self, payload: &str) {
         self.payload = Some(payload.to_string())
     }
@@ -29,11 +40,10 @@ impl<'a> PreparedCommit<'a> {
         self.index_writer.rollback()
     }
 
-    pub fn commit(self) -> Result<u64> {
+    pub fn commit(self) -> Result<Opstamp> {
         info!("committing {}", self.opstamp);
         self.index_writer
-            .segment_updater()
-            .commit(self.opstamp, self.payload)?;
+            .commit_segments(self.opstamp, self.payload)?;
         Ok(self.opstamp)
     }
 }
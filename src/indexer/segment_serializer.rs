@@ -5,14 +5,19 @@ use core::SegmentComponent;
 use fastfield::FastFieldSerializer;
 use store::StoreWriter;
 use postings::InvertedIndexSerializer;
+use common::CompositeWrite;
+use directory::WritePtr;
 
 /// Segment serializer is in charge of laying out on disk
 /// the data accumulated and sorted by the `SegmentWriter`.
 pub struct SegmentSerializer {
+    segment: Segment,
     store_writer: StoreWriter,
     fast_field_serializer: FastFieldSerializer,
     fieldnorms_serializer: FastFieldSerializer,
     postings_serializer: InvertedIndexSerializer,
+    field_presence_write: Option<CompositeWrite<WritePtr>>,
+    facet_labels_write: CompositeWrite<WritePtr>,
 }
 
 impl SegmentSerializer {
@@ -27,11 +32,17 @@ impl SegmentSerializer {
         let fieldnorms_serializer = FastFieldSerializer::from_write(fieldnorms_write)?;
 
         let postings_serializer = InvertedIndexSerializer::open(segment)?;
+
+        let facet_labels_write =
+            CompositeWrite::wrap(segment.open_write(SegmentComponent::FACETLABELS)?);
         Ok(SegmentSerializer {
+            segment: segment.clone(),
             postings_serializer,
-            store_writer: StoreWriter::new(store_write),
+            store_writer: StoreWriter::new(store_write)?,
             fast_field_serializer,
             fieldnorms_serializer,
+            field_presence_write: None,
+            facet_labels_write,
         })
     }
 
@@ -50,6 +61,25 @@ impl SegmentSerializer {
         &mut self.fieldnorms_serializer
     }
 
+    /// Accessor to the writer of the field presence bitsets.
+    ///
+    /// The field presence file is only created on the first call,
+    /// so that segments without any field presence bitset do not have one.
+    pub(crate) fn get_field_presence_write(&mut self) -> Result<&mut CompositeWrite<WritePtr>> {
+        if self.field_presence_write.is_none() {
+            let write = self.segment.open_write(SegmentComponent::FIELDPRESENCE)?;
+            self.field_presence_write = Some(CompositeWrite::wrap(write));
+        }
+        Ok(self.field_presence_write
+            .as_mut()
+            .expect("the field presence write was just opened"))
+    }
+
+    /// Accessor to the writer of the facet labels.
+    pub(crate) fn get_facet_labels_write(&mut self) -> &mut CompositeWrite<WritePtr> {
+        &mut self.facet_labels_write
+    }
+
     /// Accessor to the `StoreWriter`.
     pub fn get_store_writer(&mut self) -> &mut StoreWriter {
         &mut self.store_writer
@@ -61,6 +91,41 @@ impl SegmentSerializer {
         self.postings_serializer.close()?;
         self.store_writer.close()?;
         self.fieldnorms_serializer.close()?;
+        if let Some(field_presence_write) = self.field_presence_write {
+            field_presence_write.close()?;
+        }
+        self.facet_labels_write.close()?;
+        Ok(())
+    }
+
+    /// Finalize the segment serialization, like `.close()`, except that
+    /// `append_fast_fields` is called with the fast field and the field norm
+    /// serializers after all of the other components were closed.
+    ///
+    /// This makes it possible to serialize the fast fields in the background
+    /// while the store and the postings are being finalized.
+    pub(crate) fn close_before_fast_fields<F>(self, append_fast_fields: F) -> Result<()>
+    where
+        F: FnOnce(&mut FastFieldSerializer, &mut FastFieldSerializer) -> Result<()>,
+    {
+        let SegmentSerializer {
+            store_writer,
+            mut fast_field_serializer,
+            mut fieldnorms_serializer,
+            postings_serializer,
+            field_presence_write,
+            facet_labels_write,
+            ..
+        } = self;
+        postings_serializer.close()?;
+        store_writer.close()?;
+        if let Some(field_presence_write) = field_presence_write {
+            field_presence_write.close()?;
+        }
+        facet_labels_write.close()?;
+        append_fast_fields(&mut fast_field_serializer, &mut fieldnorms_serializer)?;
+        fast_field_serializer.close()?;
+        fieldnorms_serializer.close()?;
         Ok(())
     }
 }
@@ -1,12 +1,13 @@
 use core::Index;
 use core::IndexMeta;
+use core::IndexSettings;
 use core::META_FILEPATH;
 use core::Segment;
 use core::SegmentId;
 use core::SegmentMeta;
 use core::SerializableSegment;
 use directory::Directory;
-use indexer::stamper::Stamper;
+use indexer::stamper::{Opstamp, Stamper};
 use error::{Error, ErrorKind, Result};
 use futures_cpupool::CpuPool;
 use futures::Future;
@@ -19,12 +20,13 @@ use indexer::MergeCandidate;
 use indexer::merger::IndexMerger;
 use indexer::SegmentEntry;
 use indexer::SegmentSerializer;
+use indexer::segment_verifier::verify_segment;
 use futures_cpupool::CpuFuture;
 use serde_json;
 use indexer::delete_queue::DeleteCursor;
 use schema::Schema;
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::mem;
 use std::ops::DerefMut;
@@ -45,8 +47,13 @@ use super::segment_manager::{get_mergeable_segments, SegmentManager};
 /// and flushed.
 ///
 /// This method is not part of tantivy's public API
-pub fn save_new_metas(schema: Schema, opstamp: u64, directory: &mut Directory) -> Result<()> {
-    save_metas(vec![], schema, opstamp, None, directory)
+pub fn save_new_metas(
+    schema: Schema,
+    settings: IndexSettings,
+    opstamp: Opstamp,
+    directory: &mut Directory,
+) -> Result<()> {
+    save_metas(vec![], schema, settings, opstamp, None, None, directory)
 }
 
 /// Save the index meta file.
@@ -61,7 +68,9 @@ pub fn save_new_metas(schema: Schema, opstamp: u64, directory: &mut Directory) -
 pub fn save_metas(
     segment_metas: Vec<SegmentMeta>,
     schema: Schema,
-    opstamp: u64,
+    settings: IndexSettings,
+    opstamp: Opstamp,
+    last_opstamp: Option<Opstamp>,
     payload: Option<String>,
     directory: &mut Directory,
 ) -> Result<()> {
@@ -69,10 +78,15 @@ pub fn save_metas(
         segments: segment_metas,
         schema,
         opstamp,
+        last_opstamp,
         payload,
+        settings,
     };
     let mut buffer = serde_json::to_vec_pretty(&metas)?;
     write!(&mut buffer, "\n")?;
+    // the files of the segments must be persisted before
+    // the meta file refers to them.
+    directory.sync()?;
     directory.atomic_write(&META_FILEPATH, &buffer[..])?;
     debug!("Saved metas {:?}", serde_json::to_string_pretty(&metas));
     Ok(())
@@ -90,7 +104,7 @@ fn perform_merge(
     segment_ids: &[SegmentId],
     segment_updater: &SegmentUpdater,
     mut merged_segment: Segment,
-    target_opstamp: u64,
+    target_opstamp: Opstamp,
 ) -> Result<SegmentEntry> {
     // first we need to apply deletes to our segment.
     info!("Start merge: {:?}", segment_ids);
@@ -142,6 +156,16 @@ fn perform_merge(
         .expect("Serializing merged index failed");
     let mut segment_meta = SegmentMeta::new(merged_segment.id());
     segment_meta.set_max_doc(num_docs);
+    segment_meta.set_total_num_tokens(merger.total_num_tokens());
+    segment_meta.set_index_record_options(merger.index_record_options());
+    segment_meta.set_int_value_ranges(merger.int_value_ranges());
+    segment_meta.set_fast_field_value_ranges(merger.fast_field_value_ranges());
+    segment_meta.set_format_version(merger.format_version());
+    merged_segment.write_meta_file(&segment_meta, segment_ids)?;
+
+    if segment_updater.verify_segments() {
+        verify_segment(&index.segment(segment_meta.clone()))?;
+    }
 
     let after_merge_segment_entry = SegmentEntry::new(segment_meta.clone(), delete_cursor, None);
     Ok(after_merge_segment_entry)
@@ -157,6 +181,7 @@ struct InnerSegmentUpdater {
     generation: AtomicUsize,
     killed: AtomicBool,
     stamper: Stamper,
+    verify_segments: AtomicBool,
 }
 
 impl SegmentUpdater {
@@ -177,6 +202,7 @@ impl SegmentUpdater {
             generation: AtomicUsize::default(),
             killed: AtomicBool::new(false),
             stamper,
+            verify_segments: AtomicBool::new(true),
         })))
     }
 
@@ -195,6 +221,16 @@ impl SegmentUpdater {
         *self.0.merge_policy.write().unwrap() = merge_policy;
     }
 
+    pub fn verify_segments(&self) -> bool {
+        self.0.verify_segments.load(Ordering::SeqCst)
+    }
+
+    pub fn set_verify_segments(&self, verify_segments: bool) {
+        self.0
+            .verify_segments
+            .store(verify_segments, Ordering::SeqCst);
+    }
+
     fn get_merging_thread_id(&self) -> usize {
         self.0.merging_thread_id.fetch_add(1, Ordering::SeqCst)
     }
@@ -231,24 +267,53 @@ impl SegmentUpdater {
     /// Apply deletes up to the target opstamp to all segments.
     ///
     /// Tne method returns copies of the segment entries,
-    /// updated with the delete information.
-    fn purge_deletes(&self, target_opstamp: u64) -> Result<Vec<SegmentEntry>> {
+    /// updated with the delete information, and the number
+    /// of documents they had that were newly deleted.
+    fn purge_deletes(&self, target_opstamp: Opstamp) -> Result<(Vec<SegmentEntry>, u64)> {
         let mut segment_entries = self.0.segment_manager.segment_entries();
+        let mut num_deleted_docs = 0u64;
         for segment_entry in &mut segment_entries {
+            let previous_num_deleted_docs = segment_entry.meta().num_deleted_docs();
             let segment = self.0.index.segment(segment_entry.meta().clone());
             advance_deletes(segment, segment_entry, target_opstamp)?;
+            num_deleted_docs +=
+                u64::from(segment_entry.meta().num_deleted_docs() - previous_num_deleted_docs);
         }
-        Ok(segment_entries)
+        Ok((segment_entries, num_deleted_docs))
+    }
+
+    /// Verifies the files of the segments that are not
+    /// part of the last commit as they are, i.e. the new segments
+    /// and the segments with new deletes.
+    fn verify_new_segments(&self, segment_entries: &[SegmentEntry]) -> Result<()> {
+        let committed_segments: HashSet<(SegmentId, Option<u64>)> = self.0
+            .segment_manager
+            .committed_segment_metas()
+            .iter()
+            .map(|segment_meta| (segment_meta.id(), segment_meta.delete_opstamp()))
+            .collect();
+        for segment_entry in segment_entries {
+            let segment_meta = segment_entry.meta();
+            if !committed_segments.contains(&(segment_meta.id(), segment_meta.delete_opstamp())) {
+                verify_segment(&self.0.index.segment(segment_meta.clone()))?;
+            }
+        }
+        Ok(())
     }
 
-    pub fn save_metas(&self, opstamp: u64, commit_message: Option<String>) {
+    pub fn save_metas(&self, opstamp: Opstamp, commit_message: Option<String>) {
         if self.is_alive() {
             let index = &self.0.index;
             let directory = index.directory();
+            // merges may have consumed opstamps greater than the one of
+            // the commit. A writer opened on the index resumes after them.
+            let last_opstamp = self.0.stamper.last_opstamp();
             save_metas(
                 self.0.segment_manager.committed_segment_metas(),
                 index.schema(),
+                index.settings().clone(),
                 opstamp,
+                Some(last_opstamp),
                 commit_message,
                 directory.box_clone().borrow_mut(),
             ).expect("Could not save metas.");
@@ -269,18 +334,26 @@ impl SegmentUpdater {
             .garbage_collect(|| self.0.segment_manager.list_files());
     }
 
-    pub fn commit(&self, opstamp: u64, payload: Option<String>) -> Result<()> {
-        self.run_async(move |segment_updater| {
-            if segment_updater.is_alive() {
-                let segment_entries = segment_updater
-                    .purge_deletes(opstamp)
-                    .expect("Failed purge deletes");
-                segment_updater.0.segment_manager.commit(segment_entries);
-                segment_updater.save_metas(opstamp, payload);
-                segment_updater.garbage_collect_files_exec();
-                segment_updater.consider_merge_options();
+    /// Commits the segments, and returns the number of documents
+    /// deleted by the commit.
+    pub fn commit(&self, opstamp: Opstamp, payload: Option<String>) -> Result<u64> {
+        self.run_async(move |segment_updater| -> Result<u64> {
+            if !segment_updater.is_alive() {
+                return Ok(0);
             }
-        }).wait()
+            let (segment_entries, num_deleted_docs) = segment_updater
+                .purge_deletes(opstamp)
+                .expect("Failed purge deletes");
+            // a corrupted segment must not be published in `meta.json`.
+            if segment_updater.verify_segments() {
+                segment_updater.verify_new_segments(&segment_entries)?;
+            }
+            segment_updater.0.segment_manager.commit(segment_entries);
+            segment_updater.save_metas(opstamp, payload);
+            segment_updater.garbage_collect_files_exec();
+            segment_updater.consider_merge_options();
+            Ok(num_deleted_docs)
+        }).wait()?
     }
 
     pub fn start_merge(
@@ -351,6 +424,27 @@ impl SegmentUpdater {
         merging_future_recv
     }
 
+    /// Starts rewriting, each on its own, the segments whose ratio
+    /// of deleted documents exceeds `max_deletes_ratio`, and returns
+    /// the futures of these rewrites.
+    ///
+    /// The segments that are being merged are skipped.
+    pub fn expunge_deletes(
+        &self,
+        max_deletes_ratio: f32,
+    ) -> Result<Vec<impl Future<Item = SegmentMeta, Error = Canceled>>> {
+        self.run_async(move |segment_updater| {
+            let (committed_segments, uncommitted_segments) =
+                get_mergeable_segments(&segment_updater.0.segment_manager);
+            committed_segments
+                .into_iter()
+                .chain(uncommitted_segments)
+                .filter(|segment_meta| segment_meta.deleted_docs_ratio() > max_deletes_ratio)
+                .map(|segment_meta| segment_updater.start_merge(&[segment_meta.id()]))
+                .collect::<Vec<_>>()
+        }).wait()
+    }
+
     fn consider_merge_options(&self) {
         let (committed_segments, uncommitted_segments) =
             get_mergeable_segments(&self.0.segment_manager);
@@ -481,8 +575,17 @@ impl SegmentUpdater {
 mod tests {
 
     use Index;
+    use ErrorKind;
     use schema::*;
     use indexer::merge_policy::tests::MergeWheneverPossible;
+    use indexer::NoMergePolicy;
+    use directory::{Directory, MmapDirectory, RAMDirectory, ReadOnlySource, SeekableWrite,
+                    WritePtr};
+    use directory::error::{DeleteError, OpenReadError, OpenWriteError};
+    use core::META_FILEPATH;
+    use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_delete_during_merge() {
@@ -498,23 +601,23 @@ mod tests {
 
         {
             for _ in 0..100 {
-                index_writer.add_document(doc!(text_field=>"a"));
-                index_writer.add_document(doc!(text_field=>"b"));
+                index_writer.add_document(doc!(text_field=>"a")).unwrap();
+                index_writer.add_document(doc!(text_field=>"b")).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
 
         {
             for _ in 0..100 {
-                index_writer.add_document(doc!(text_field=>"c"));
-                index_writer.add_document(doc!(text_field=>"d"));
+                index_writer.add_document(doc!(text_field=>"c")).unwrap();
+                index_writer.add_document(doc!(text_field=>"d")).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
 
         {
-            index_writer.add_document(doc!(text_field=>"e"));
-            index_writer.add_document(doc!(text_field=>"f"));
+            index_writer.add_document(doc!(text_field=>"e")).unwrap();
+            index_writer.add_document(doc!(text_field=>"f")).unwrap();
             assert!(index_writer.commit().is_ok());
         }
 
@@ -537,4 +640,320 @@ mod tests {
         assert_eq!(index.searcher().segment_readers().len(), 1);
         assert_eq!(index.searcher().num_docs(), 302);
     }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        // path, number of file syncs so far
+        Write(PathBuf, usize),
+        Sync,
+        AtomicWrite(PathBuf),
+    }
+
+    /// Records the sequence of the operations performed
+    /// on a `MmapDirectory`.
+    #[derive(Debug, Clone)]
+    struct SequencingDirectory {
+        directory: MmapDirectory,
+        events: Arc<Mutex<Vec<Event>>>,
+    }
+
+    impl SequencingDirectory {
+        fn push(&self, event: Event) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    struct SequencingWrite {
+        path: PathBuf,
+        write: WritePtr,
+        directory: SequencingDirectory,
+    }
+
+    impl Write for SequencingWrite {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.write.flush()?;
+            let num_file_syncs = self.directory.directory.get_write_counters().num_file_syncs;
+            self.directory
+                .push(Event::Write(self.path.clone(), num_file_syncs));
+            Ok(())
+        }
+    }
+
+    impl Seek for SequencingWrite {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.write.seek(pos)
+        }
+    }
+
+    impl Directory for SequencingDirectory {
+        fn open_read(&self, path: &Path) -> Result<ReadOnlySource, OpenReadError> {
+            self.directory.open_read(path)
+        }
+
+        fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+            self.directory.delete(path)
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.directory.exists(path)
+        }
+
+        fn open_write(&mut self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+            let sequencing_write: Box<SeekableWrite> = box SequencingWrite {
+                path: path.to_owned(),
+                write: self.directory.open_write(path)?,
+                directory: self.clone(),
+            };
+            // the writes are buffered by the wrapped `WritePtr`.
+            Ok(BufWriter::with_capacity(0, sequencing_write))
+        }
+
+        fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+            self.directory.atomic_read(path)
+        }
+
+        fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.push(Event::AtomicWrite(path.to_owned()));
+            self.directory.atomic_write(path, data)
+        }
+
+        fn sync(&self) -> io::Result<()> {
+            self.push(Event::Sync);
+            self.directory.sync()
+        }
+
+        fn box_clone(&self) -> Box<Directory> {
+            box self.clone()
+        }
+    }
+
+    #[test]
+    fn test_commit_syncs_before_writing_meta() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let mmap_directory = MmapDirectory::create_from_tempdir().unwrap();
+        let directory = SequencingDirectory {
+            directory: mmap_directory.clone(),
+            events: Arc::new(Mutex::new(vec![])),
+        };
+        let index = Index::create_in_directory(directory.clone(), schema).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        let counters_before_commit = mmap_directory.get_write_counters();
+        directory.events.lock().unwrap().clear();
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        index_writer.add_document(doc!(text_field => "b")).unwrap();
+        index_writer.commit().unwrap();
+
+        let events = directory.events.lock().unwrap().clone();
+        let meta_pos = events
+            .iter()
+            .position(|event| *event == Event::AtomicWrite(META_FILEPATH.clone()))
+            .expect("meta.json was not written");
+        // all of the files are written without being synced,
+        // then all of the syncs are issued, then the meta file is written.
+        let num_file_syncs_before_commit = counters_before_commit.num_file_syncs;
+        let written_files: Vec<PathBuf> = events[..meta_pos]
+            .iter()
+            .filter_map(|event| match *event {
+                Event::Write(ref path, num_file_syncs) => {
+                    assert_eq!(num_file_syncs, num_file_syncs_before_commit);
+                    Some(path.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        assert!(!written_files.is_empty());
+        let sync_positions: Vec<usize> = events
+            .iter()
+            .enumerate()
+            .filter(|&(_, event)| *event == Event::Sync)
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(sync_positions.len(), 1);
+        assert!(sync_positions[0] < meta_pos);
+        assert!(events[sync_positions[0]..meta_pos]
+            .iter()
+            .all(|event| match *event {
+                Event::Write(..) => false,
+                _ => true,
+            }));
+
+        let counters = mmap_directory.get_write_counters();
+        let mut distinct_written_files = written_files.clone();
+        distinct_written_files.sort();
+        distinct_written_files.dedup();
+        assert_eq!(
+            counters.num_file_syncs - num_file_syncs_before_commit,
+            distinct_written_files.len()
+        );
+        assert_eq!(
+            counters.num_directory_syncs - counters_before_commit.num_directory_syncs,
+            1
+        );
+    }
+
+    /// Number of bytes lost at the end of the files written short.
+    const NUM_LOST_BYTES: usize = 4;
+
+    /// Writes a file, and then drops its last bytes,
+    /// like a write cut short by a full filesystem.
+    struct ShortWrite {
+        path: PathBuf,
+        write: WritePtr,
+        directory: RAMDirectory,
+    }
+
+    impl Write for ShortWrite {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.write.flush()?;
+            let data = self.directory
+                .open_read(&self.path)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?
+                .as_slice()
+                .to_vec();
+            let short_len = data.len().saturating_sub(NUM_LOST_BYTES);
+            self.directory.atomic_write(&self.path, &data[..short_len])
+        }
+    }
+
+    impl Seek for ShortWrite {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.write.seek(pos)
+        }
+    }
+
+    /// Writes the files with a given extension short,
+    /// once it is armed.
+    #[derive(Debug, Clone)]
+    struct ShortWriteDirectory {
+        directory: RAMDirectory,
+        short_extension: Arc<Mutex<Option<&'static str>>>,
+    }
+
+    impl Directory for ShortWriteDirectory {
+        fn open_read(&self, path: &Path) -> Result<ReadOnlySource, OpenReadError> {
+            self.directory.open_read(path)
+        }
+
+        fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+            self.directory.delete(path)
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.directory.exists(path)
+        }
+
+        fn open_write(&mut self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+            let write = self.directory.open_write(path)?;
+            let is_short = match *self.short_extension.lock().unwrap() {
+                Some(extension) => path.to_string_lossy().ends_with(extension),
+                None => false,
+            };
+            if !is_short {
+                return Ok(write);
+            }
+            let short_write: Box<SeekableWrite> = box ShortWrite {
+                path: path.to_owned(),
+                write,
+                directory: self.directory.clone(),
+            };
+            Ok(BufWriter::with_capacity(0, short_write))
+        }
+
+        fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+            self.directory.atomic_read(path)
+        }
+
+        fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.directory.atomic_write(path, data)
+        }
+
+        fn sync(&self) -> io::Result<()> {
+            self.directory.sync()
+        }
+
+        fn box_clone(&self) -> Box<Directory> {
+            box self.clone()
+        }
+    }
+
+    #[test]
+    fn test_commit_fails_on_short_segment_file() {
+        for &extension in &[".idx", ".pos", ".term", ".fast", ".fieldnorm", ".store"] {
+            let mut schema_builder = SchemaBuilder::default();
+            let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+            let num_field = schema_builder.add_u64_field("num", FAST);
+            let schema = schema_builder.build();
+            let directory = ShortWriteDirectory {
+                directory: RAMDirectory::create(),
+                short_extension: Arc::new(Mutex::new(None)),
+            };
+            let index = Index::create_in_directory(directory.clone(), schema).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            index_writer
+                .add_document(doc!(text_field => "a b", num_field => 1u64))
+                .unwrap();
+            let first_opstamp = index_writer.commit().unwrap();
+
+            *directory.short_extension.lock().unwrap() = Some(extension);
+            index_writer
+                .add_document(doc!(text_field => "b c", num_field => 2u64))
+                .unwrap();
+            match index_writer.commit() {
+                Err(err) => match *err.kind() {
+                    ErrorKind::CorruptedFile(ref path) => {
+                        assert!(path.to_string_lossy().ends_with(extension));
+                    }
+                    _ => panic!("Unexpected error {:?} for {}", err, extension),
+                },
+                Ok(_) => panic!("The commit should fail for {}", extension),
+            }
+            *directory.short_extension.lock().unwrap() = None;
+
+            // the index remains at the previous commit.
+            let metas = index.load_metas().unwrap();
+            assert_eq!(metas.opstamp, first_opstamp);
+            assert_eq!(metas.segments.len(), 1);
+            index.load_searchers().unwrap();
+            assert_eq!(index.searcher().num_docs(), 1);
+
+            // ... and the writer recovers once rolled back.
+            index_writer.rollback().unwrap();
+            index_writer
+                .add_document(doc!(text_field => "c d", num_field => 3u64))
+                .unwrap();
+            index_writer.commit().unwrap();
+            index.load_searchers().unwrap();
+            assert_eq!(index.searcher().num_docs(), 2);
+        }
+    }
+
+    #[test]
+    fn test_commit_without_segment_verification() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let directory = ShortWriteDirectory {
+            directory: RAMDirectory::create(),
+            short_extension: Arc::new(Mutex::new(Some(".pos"))),
+        };
+        let index = Index::create_in_directory(directory, schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        assert!(index_writer.verify_segments_on_commit());
+        index_writer.set_verify_segments_on_commit(false);
+        index_writer.add_document(doc!(text_field => "a")).unwrap();
+        // the short file gets published.
+        assert!(index_writer.commit().is_ok());
+        assert_eq!(index.load_metas().unwrap().segments.len(), 1);
+    }
 }
@@ -0,0 +1,201 @@
+use Result;
+use common::{CompositeFile, HasLen};
+use core::{Segment, SegmentComponent, SegmentMetaFile};
+use directory::Directory;
+use error::ErrorKind;
+use fastfield::check_fast_field_source;
+use schema::{Cardinality, FieldType};
+use serde_json;
+use store::StoreReader;
+use termdict::{TermDictionary, TermDictionaryImpl};
+use std::io;
+use std::result;
+
+/// Checks the files of a composite component, given their segment.
+fn check_composite_file(
+    segment: &Segment,
+    component: SegmentComponent,
+    composite_file: &CompositeFile,
+) -> io::Result<()> {
+    let schema = segment.schema();
+    let max_doc = segment.meta().max_doc() as usize;
+    for (field, idx, source) in composite_file.files() {
+        let field_entry = schema.fields().get(field.0 as usize).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "unknown field in the footer")
+        })?;
+        match component {
+            SegmentComponent::TERMS => TermDictionaryImpl::check_source(&source)?,
+            SegmentComponent::FIELDNORMS => check_fast_field_source(&source, max_doc)?,
+            SegmentComponent::FASTFIELDS => {
+                // the number of values of the multivalued fast fields is unknown.
+                let num_vals = match *field_entry.field_type() {
+                    FieldType::U64(ref options) | FieldType::I64(ref options) => {
+                        match options.get_fastfield_cardinality() {
+                            Some(Cardinality::SingleValue) if idx == 0 => max_doc,
+                            _ => 0,
+                        }
+                    }
+                    _ => 0,
+                };
+                check_fast_field_source(&source, num_vals)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns true iff the component file is not written
+/// for the segments that have nothing to record in it.
+fn is_optional(component: SegmentComponent) -> bool {
+    match component {
+        SegmentComponent::POSITIONS
+        | SegmentComponent::BLOOMFILTERS
+        | SegmentComponent::FIELDPRESENCE
+        | SegmentComponent::FACETLABELS => true,
+        _ => false,
+    }
+}
+
+/// Checks one of the component files of a segment.
+///
+/// Returns the reason why the file is corrupted, if it is.
+fn check_component(segment: &Segment, component: SegmentComponent) -> result::Result<(), String> {
+    let source = segment
+        .open_read(component)
+        .map_err(|_| "the file is missing".to_string())?;
+    let max_doc = segment.meta().max_doc();
+    match component {
+        SegmentComponent::STORE => {
+            let store_max_doc = StoreReader::read_max_doc(&source).map_err(|e| e.to_string())?;
+            if store_max_doc != max_doc {
+                return Err(format!(
+                    "the store holds {} documents instead of {}",
+                    store_max_doc, max_doc
+                ));
+            }
+        }
+        SegmentComponent::DELETE => {
+            if source.len() * 8 < max_doc as usize {
+                return Err("the delete bitset is too short".to_string());
+            }
+        }
+        SegmentComponent::META => {
+            let meta_file: SegmentMetaFile =
+                serde_json::from_slice(source.as_slice()).map_err(|e| e.to_string())?;
+            if meta_file.max_doc() != max_doc {
+                return Err(format!(
+                    "the meta file holds {} documents instead of {}",
+                    meta_file.max_doc(),
+                    max_doc
+                ));
+            }
+        }
+        _ => {
+            let composite_file = CompositeFile::open(&source).map_err(|e| e.to_string())?;
+            check_composite_file(segment, component, &composite_file)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all of the component files of a newly written
+/// segment exist, and that their footers and headers are
+/// consistent with their length.
+///
+/// Positions, bloom filters, field presence and facet labels
+/// are optional: they are only checked if they exist.
+///
+/// Only a few bytes of each file are read: this detects the
+/// files that were written short, e.g. because the filesystem was full,
+/// before they get published in `meta.json`.
+///
+/// # Errors
+/// Returns `CorruptedFile` with the path of the first corrupted file.
+pub(crate) fn verify_segment(segment: &Segment) -> Result<()> {
+    let has_deletes = segment.meta().has_deletes();
+    for &component in SegmentComponent::iterator() {
+        if component == SegmentComponent::DELETE && !has_deletes {
+            continue;
+        }
+        let path = segment.relative_path(component);
+        if is_optional(component) && !segment.index().directory().exists(&path) {
+            continue;
+        }
+        if let Err(reason) = check_component(segment, component) {
+            error!("Segment file {:?} is corrupted: {}", path, reason);
+            bail!(ErrorKind::CorruptedFile(path));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::verify_segment;
+    use Index;
+    use core::SegmentComponent;
+    use directory::Directory;
+    use indexer::NoMergePolicy;
+    use schema::{Cardinality, Document, IntOptions, SchemaBuilder, Term, STORED, TEXT};
+
+    #[test]
+    fn test_verify_segment() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let single_options = IntOptions::default().set_fast(Cardinality::SingleValue);
+        let single_field = schema_builder.add_u64_field("single", single_options);
+        let multi_options = IntOptions::default().set_fast(Cardinality::MultiValues);
+        let multi_field = schema_builder.add_i64_field("multi", multi_options);
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for i in 0..1_000u64 {
+            let mut doc = doc!(
+                text_field => format!("hello {}", i % 13),
+                single_field => i * 1_000,
+                multi_field => -(i as i64)
+            );
+            doc.add_i64(multi_field, i as i64);
+            doc.add_facet(facet_field, format!("/category/{}", i % 7).as_str());
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "0"));
+        index_writer.commit().unwrap();
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        assert_eq!(segment_metas.len(), 1);
+        assert!(segment_metas[0].has_deletes());
+        let segment = index.segment(segment_metas[0].clone());
+        assert!(verify_segment(&segment).is_ok());
+    }
+
+    #[test]
+    fn test_verify_segment_without_optional_components() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        for _ in 0..2 {
+            index_writer.add_document(Document::default()).unwrap();
+            index_writer.commit().unwrap();
+        }
+        let segment_metas = index.searchable_segment_metas().unwrap();
+        assert_eq!(segment_metas.len(), 2);
+        let segment = index.segment(segment_metas[0].clone());
+        assert!(
+            !index
+                .directory()
+                .exists(&segment.relative_path(SegmentComponent::FIELDPRESENCE))
+        );
+        assert!(verify_segment(&segment).is_ok());
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 2);
+    }
+}
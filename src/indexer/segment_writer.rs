@@ -1,24 +1,31 @@
 use Result;
+use error::ErrorKind;
 use DocId;
 use std::io;
-use std::str;
 use schema::Schema;
 use schema::Term;
 use core::Segment;
 use core::SerializableSegment;
 use fastfield::FastFieldsWriter;
+use fastfield::FieldPresenceWriter;
+use fastfield::FacetLabelsWriter;
 use schema::Field;
 use schema::FieldType;
+use schema::IndexRecordOption;
 use indexer::segment_serializer::SegmentSerializer;
 use std::collections::HashMap;
+use std::cmp::{max, min};
+use common;
 use datastruct::stacker::Heap;
 use indexer::index_writer::MARGIN_IN_BYTES;
+use indexer::FieldIngestStats;
+use indexer::Opstamp;
 use super::operation::AddOperation;
 use postings::MultiFieldPostingsWriter;
 use tokenizer::BoxedTokenizer;
-use tokenizer::FacetTokenizer;
-use tokenizer::{TokenStream, Tokenizer};
-use schema::Value;
+use tokenizer::{TokenStream, TokenStreamChain, Tokenizer};
+use schema::{Facet, Value};
+use crossbeam;
 
 /// A `SegmentWriter` is in charge of creating segment index from a
 /// documents.
@@ -32,8 +39,19 @@ pub struct SegmentWriter<'a> {
     segment_serializer: SegmentSerializer,
     fast_field_writers: FastFieldsWriter,
     fieldnorms_writer: FastFieldsWriter,
-    doc_opstamps: Vec<u64>,
+    field_presence_writer: FieldPresenceWriter,
+    facet_labels_writer: FacetLabelsWriter,
+    doc_opstamps: Vec<Opstamp>,
     tokenizers: Vec<Option<Box<BoxedTokenizer>>>,
+    // total number of tokens, for the indexed text fields.
+    total_num_tokens: Vec<Option<u64>>,
+    index_record_options: Vec<(Field, IndexRecordOption)>,
+    // smallest and largest values, for the indexed int fields.
+    int_value_ranges: Vec<Option<(u64, u64)>>,
+    // indexed by field id.
+    field_ingest_stats: Vec<FieldIngestStats>,
+    postings_spill_threshold: Option<usize>,
+    num_serialization_threads: usize,
 }
 
 fn create_fieldnorms_writer(schema: &Schema) -> FastFieldsWriter {
@@ -41,7 +59,7 @@ fn create_fieldnorms_writer(schema: &Schema) -> FastFieldsWriter {
         .fields()
         .iter()
         .enumerate()
-        .filter(|&(_, field_entry)| field_entry.is_indexed())
+        .filter(|&(_, field_entry)| field_entry.has_fieldnorms())
         .map(|(field_id, _)| Field(field_id as u32))
         .collect();
     FastFieldsWriter::new(u64_fields)
@@ -79,6 +97,28 @@ impl<'a> SegmentWriter<'a> {
                 _ => None,
             })
             .collect();
+        let total_num_tokens = schema
+            .fields()
+            .iter()
+            .map(|field_entry| {
+                if field_entry.has_fieldnorms() {
+                    Some(0u64)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let index_record_options = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter_map(|(field_id, field_entry)| {
+                field_entry
+                    .field_type()
+                    .get_index_record_option()
+                    .map(|index_record_option| (Field(field_id as u32), index_record_option))
+            })
+            .collect();
         Ok(SegmentWriter {
             heap,
             max_doc: 0,
@@ -86,8 +126,16 @@ impl<'a> SegmentWriter<'a> {
             fieldnorms_writer: create_fieldnorms_writer(schema),
             segment_serializer,
             fast_field_writers: FastFieldsWriter::from_schema(schema),
+            field_presence_writer: FieldPresenceWriter::from_schema(schema),
+            facet_labels_writer: FacetLabelsWriter::new(),
             doc_opstamps: Vec::with_capacity(1_000),
             tokenizers,
+            total_num_tokens,
+            index_record_options,
+            int_value_ranges: vec![None; schema.fields().len()],
+            field_ingest_stats: vec![FieldIngestStats::default(); schema.fields().len()],
+            postings_spill_threshold: None,
+            num_serialization_threads: 1,
         })
     }
 
@@ -95,11 +143,15 @@ impl<'a> SegmentWriter<'a> {
     ///
     /// Finalize consumes the `SegmentWriter`, so that it cannot
     /// be used afterwards.
-    pub fn finalize(self) -> Result<Vec<u64>> {
+    pub fn finalize(self) -> Result<Vec<Opstamp>> {
         write(
             &self.multifield_postings,
             &self.fast_field_writers,
             &self.fieldnorms_writer,
+            &self.field_presence_writer,
+            &self.facet_labels_writer,
+            self.max_doc,
+            self.num_serialization_threads,
             self.segment_serializer,
         )?;
         Ok(self.doc_opstamps)
@@ -116,11 +168,132 @@ impl<'a> SegmentWriter<'a> {
         self.heap.num_free_bytes() <= MARGIN_IN_BYTES
     }
 
-    /// Return true if the term dictionary hashmap is reaching capacity.
-    /// It is one of the condition that triggers a `SegmentWriter` to
-    /// be finalized.
-    pub(crate) fn is_term_saturated(&self) -> bool {
-        self.multifield_postings.is_term_saturated()
+    /// Sets the maximum length of the indexed tokens, in bytes.
+    ///
+    /// Longer tokens are dropped, whatever the tokenizer of the field.
+    /// The value may not exceed `MAX_TOKEN_LEN`, which is also the default.
+    ///
+    /// # Errors
+    /// Returns `InvalidArgument` if `max_token_len` exceeds `MAX_TOKEN_LEN`.
+    pub fn set_max_token_len(&mut self, max_token_len: usize) -> Result<()> {
+        self.multifield_postings.set_max_token_len(max_token_len)
+    }
+
+    /// Returns the number of tokens that were not indexed because
+    /// they exceeded the maximum token length.
+    pub fn num_dropped_tokens(&self) -> usize {
+        self.multifield_postings.num_dropped_tokens()
+    }
+
+    /// Makes the postings be spilled to a temporary run file, and the heap
+    /// be cleared, whenever the heap holds more than `num_bytes` after
+    /// a document is indexed.
+    ///
+    /// The runs are merged when the segment is finalized.
+    pub fn set_postings_spill_threshold(&mut self, num_bytes: usize) {
+        self.postings_spill_threshold = Some(num_bytes);
+    }
+
+    /// Sets the number of threads serializing the postings, the fast fields
+    /// and the field norms when the segment is finalized.
+    ///
+    /// With more than one thread, the postings of the different fields are
+    /// serialized in parallel, and the fast fields and the field norms are
+    /// serialized in the background while the store is being finalized.
+    /// The segment files are the same whatever the number of threads.
+    ///
+    /// # Errors
+    /// Returns `InvalidArgument` if `num_threads` is 0.
+    pub fn set_num_serialization_threads(&mut self, num_threads: usize) -> Result<()> {
+        if num_threads == 0 {
+            bail!(ErrorKind::InvalidArgument(
+                "The number of serialization threads must be at least 1.".to_string()
+            ));
+        }
+        self.num_serialization_threads = num_threads;
+        Ok(())
+    }
+
+    /// Returns the number of times the postings were spilled
+    /// out of the heap.
+    pub fn num_spilled_runs(&self) -> usize {
+        self.multifield_postings.num_spilled_runs()
+    }
+
+    /// Returns the total number of tokens indexed so far,
+    /// for each of the indexed text fields.
+    pub fn total_num_tokens(&self) -> Vec<(Field, u64)> {
+        self.total_num_tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(field_id, total_num_tokens_opt)| {
+                total_num_tokens_opt
+                    .map(|total_num_tokens| (Field(field_id as u32), total_num_tokens))
+            })
+            .collect()
+    }
+
+    /// Returns the `IndexRecordOption` of each of the indexed fields.
+    ///
+    /// Newly written segments always follow the schema.
+    pub fn index_record_options(&self) -> Vec<(Field, IndexRecordOption)> {
+        self.index_record_options.clone()
+    }
+
+    /// Returns the smallest and the largest value indexed so far,
+    /// for each of the indexed `u64` and `i64` fields.
+    ///
+    /// Values are expressed in their `u64` representation.
+    pub fn int_value_ranges(&self) -> Vec<(Field, u64, u64)> {
+        self.int_value_ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(field_id, range_opt)| {
+                range_opt.map(|(min_value, max_value)| {
+                    (Field(field_id as u32), min_value, max_value)
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the smallest and the largest value of each of the
+    /// single-valued `u64` and `i64` fast fields, over the documents
+    /// indexed so far.
+    ///
+    /// Values are expressed in their `u64` representation.
+    pub fn fast_field_value_ranges(&self) -> Vec<(Field, u64, u64)> {
+        self.fast_field_writers.single_value_ranges()
+    }
+
+    /// Returns the statistics about the values of the documents
+    /// indexed so far, indexed by field id.
+    ///
+    /// Values skipped by the validation are not known
+    /// to the `SegmentWriter`, and are not counted.
+    pub fn field_ingest_stats(&self) -> &[FieldIngestStats] {
+        &self.field_ingest_stats
+    }
+
+    fn record_int_value(&mut self, field: Field, val: u64) {
+        let range = &mut self.int_value_ranges[field.0 as usize];
+        *range = Some(match *range {
+            Some((min_value, max_value)) => (min(min_value, val), max(max_value, val)),
+            None => (val, val),
+        });
+    }
+
+    /// Returns the number of times the term dictionary hash table
+    /// has been resized.
+    pub fn num_term_rehashes(&self) -> usize {
+        self.multifield_postings.term_index().num_rehashes()
+    }
+
+    /// Returns the longest probe sequence observed in the
+    /// term dictionary hash table.
+    ///
+    /// A large value is a sign of a poor hash distribution.
+    pub fn max_term_probe_len(&self) -> usize {
+        self.multifield_postings.term_index().max_probe_len()
     }
 
     /// Indexes a new document
@@ -132,43 +305,55 @@ impl<'a> SegmentWriter<'a> {
         self.doc_opstamps.push(add_operation.opstamp);
 
         self.fast_field_writers.add_document(&doc);
+        let field_presence_num_bytes = self.field_presence_writer.num_bytes();
+        self.field_presence_writer.add_document(doc_id, &doc);
+        // the bitsets are not allocated in the heap, but count in its budget.
+        self.heap.charge(self.field_presence_writer.num_bytes() - field_presence_num_bytes);
 
+        // values are processed field by field, in insertion order
+        // within a field, whatever the way fields are interleaved.
         for (field, field_values) in doc.get_sorted_field_values() {
+            {
+                let field_ingest_stats = &mut self.field_ingest_stats[field.0 as usize];
+                field_ingest_stats.num_docs += 1;
+                field_ingest_stats.num_values += field_values.len() as u64;
+            }
             let field_options = schema.get_field_entry(field);
             if !field_options.is_indexed() {
                 continue;
             }
             match *field_options.field_type() {
                 FieldType::HierarchicalFacet => {
-                    let facets: Vec<&[u8]> = field_values
-                        .iter()
-                        .flat_map(|field_value| match *field_value.value() {
-                            Value::Facet(ref facet) => Some(facet.encoded_bytes()),
+                    let mut term = unsafe { Term::with_capacity(100) };
+                    term.set_field(field);
+                    for field_value in &field_values {
+                        let facet: &Facet = match *field_value.value() {
+                            Value::Facet(ref facet) => facet,
                             _ => {
                                 panic!("Expected hierarchical facet");
                             }
-                        })
-                        .collect();
-                    let mut term = unsafe { Term::with_capacity(100) };
-                    term.set_field(field);
-                    for facet_bytes in facets {
+                        };
                         let mut unordered_term_id_opt = None;
-                        let fake_str = unsafe { str::from_utf8_unchecked(facet_bytes) };
-                        FacetTokenizer.token_stream(fake_str).process(&mut |token| {
-                            term.set_text(&token.text);
+                        for facet_prefix in facet.prefixes() {
+                            term.set_bytes(facet_prefix);
                             let unordered_term_id =
                                 self.multifield_postings.subscribe(doc_id, &term);
                             unordered_term_id_opt = Some(unordered_term_id);
-                        });
+                        }
                         if let Some(unordered_term_id) = unordered_term_id_opt {
                             self.fast_field_writers
                                 .get_multivalue_writer(field)
                                 .expect("multified writer for facet missing")
                                 .add_val(unordered_term_id);
+                            if let Some(label) = doc.facet_label(field, facet) {
+                                self.facet_labels_writer
+                                    .record(field, unordered_term_id, label);
+                            }
                         }
                     }
                 }
                 FieldType::Str(_) => {
+                    let num_dropped_tokens_before = self.multifield_postings.num_dropped_tokens();
                     let num_tokens = if let Some(ref mut tokenizer) =
                         self.tokenizers[field.0 as usize]
                     {
@@ -181,6 +366,18 @@ impl<'a> SegmentWriter<'a> {
                             .collect();
                         if texts.is_empty() {
                             0
+                        } else if let Some(value_position_offsets_writer) = self
+                            .fast_field_writers
+                            .get_value_position_offsets_writer(field)
+                        {
+                            let mut token_stream =
+                                TokenStreamChain::from_texts(&**tokenizer, &texts[..]);
+                            let num_tokens = self.multifield_postings
+                                .index_text(doc_id, field, &mut token_stream);
+                            for &start_position in token_stream.start_positions() {
+                                value_position_offsets_writer.add_val(start_position as u64);
+                            }
+                            num_tokens
                         } else {
                             let mut token_stream = tokenizer.token_stream_texts(&texts[..]);
                             self.multifield_postings
@@ -189,6 +386,18 @@ impl<'a> SegmentWriter<'a> {
                     } else {
                         0
                     };
+                    if let Some(ref mut total_num_tokens) =
+                        self.total_num_tokens[field.0 as usize]
+                    {
+                        *total_num_tokens += u64::from(num_tokens);
+                    }
+                    {
+                        let num_dropped_tokens = self.multifield_postings.num_dropped_tokens()
+                            - num_dropped_tokens_before;
+                        let field_ingest_stats = &mut self.field_ingest_stats[field.0 as usize];
+                        field_ingest_stats.num_tokens += u64::from(num_tokens);
+                        field_ingest_stats.num_dropped_tokens += num_dropped_tokens as u64;
+                    }
                     self.fieldnorms_writer
                         .get_field_writer(field)
                         .map(|field_norms_writer| {
@@ -198,22 +407,20 @@ impl<'a> SegmentWriter<'a> {
                 FieldType::U64(ref int_option) => {
                     if int_option.is_indexed() {
                         for field_value in field_values {
-                            let term = Term::from_field_u64(
-                                field_value.field(),
-                                field_value.value().u64_value(),
-                            );
+                            let val = field_value.value().u64_value();
+                            let term = Term::from_field_u64(field_value.field(), val);
                             self.multifield_postings.subscribe(doc_id, &term);
+                            self.record_int_value(field, val);
                         }
                     }
                 }
                 FieldType::I64(ref int_option) => {
                     if int_option.is_indexed() {
                         for field_value in field_values {
-                            let term = Term::from_field_i64(
-                                field_value.field(),
-                                field_value.value().i64_value(),
-                            );
+                            let val = field_value.value().i64_value();
+                            let term = Term::from_field_i64(field_value.field(), val);
                             self.multifield_postings.subscribe(doc_id, &term);
+                            self.record_int_value(field, common::i64_to_u64(val));
                         }
                     }
                 }
@@ -224,6 +431,13 @@ impl<'a> SegmentWriter<'a> {
         let doc_writer = self.segment_serializer.get_store_writer();
         doc_writer.store(&doc)?;
         self.max_doc += 1;
+        if let Some(postings_spill_threshold) = self.postings_spill_threshold {
+            if self.heap.num_used_bytes() as usize >= postings_spill_threshold {
+                self.multifield_postings.spill()?;
+                // clearing the heap also cleared the charge of the bitsets.
+                self.heap.charge(self.field_presence_writer.num_bytes());
+            }
+        }
         Ok(())
     }
 
@@ -249,18 +463,57 @@ impl<'a> SegmentWriter<'a> {
 }
 
 // This method is used as a trick to workaround the borrow checker
+#[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
 fn write(
     multifield_postings: &MultiFieldPostingsWriter,
     fast_field_writers: &FastFieldsWriter,
     fieldnorms_writer: &FastFieldsWriter,
+    field_presence_writer: &FieldPresenceWriter,
+    facet_labels_writer: &FacetLabelsWriter,
+    max_doc: DocId,
+    num_serialization_threads: usize,
     mut serializer: SegmentSerializer,
 ) -> Result<()> {
-    let term_ord_map = multifield_postings.serialize(serializer.get_postings_serializer())?;
-    fast_field_writers.serialize(serializer.get_fast_field_serializer(), &term_ord_map)?;
-    fieldnorms_writer.serialize(serializer.get_fieldnorms_serializer(), &HashMap::new())?;
-    serializer.close()?;
-
-    Ok(())
+    let term_ord_map = multifield_postings
+        .serialize_in_parallel(serializer.get_postings_serializer(), num_serialization_threads)?;
+    let no_mapping = HashMap::new();
+    if num_serialization_threads <= 1 {
+        fast_field_writers.serialize(serializer.get_fast_field_serializer(), &term_ord_map)?;
+        fieldnorms_writer.serialize(serializer.get_fieldnorms_serializer(), &no_mapping)?;
+        if !field_presence_writer.is_empty() {
+            field_presence_writer.serialize(max_doc, serializer.get_field_presence_write()?)?;
+        }
+        facet_labels_writer.serialize(serializer.get_facet_labels_write(), &term_ord_map)?;
+        serializer.close()?;
+        return Ok(());
+    }
+    crossbeam::scope(|scope| -> Result<()> {
+        let fast_field_jobs = fast_field_writers.serialize_in_background(
+            scope,
+            &term_ord_map,
+            num_serialization_threads,
+        );
+        let fieldnorms_jobs = fieldnorms_writer.serialize_in_background(
+            scope,
+            &no_mapping,
+            num_serialization_threads,
+        );
+        if !field_presence_writer.is_empty() {
+            field_presence_writer.serialize(max_doc, serializer.get_field_presence_write()?)?;
+        }
+        facet_labels_writer.serialize(serializer.get_facet_labels_write(), &term_ord_map)?;
+        serializer.close_before_fast_fields(|fast_field_serializer, fieldnorms_serializer| {
+            // the fields are appended in the order of the sequential serialization,
+            // so that the files do not depend on the number of threads.
+            for fast_field_job in fast_field_jobs {
+                fast_field_serializer.append(fast_field_job.join()?)?;
+            }
+            for fieldnorms_job in fieldnorms_jobs {
+                fieldnorms_serializer.append(fieldnorms_job.join()?)?;
+            }
+            Ok(())
+        })
+    })
 }
 
 impl<'a> SerializableSegment for SegmentWriter<'a> {
@@ -270,8 +523,185 @@ impl<'a> SerializableSegment for SegmentWriter<'a> {
             &self.multifield_postings,
             &self.fast_field_writers,
             &self.fieldnorms_writer,
+            &self.field_presence_writer,
+            &self.facet_labels_writer,
+            max_doc,
+            self.num_serialization_threads,
             serializer,
         )?;
         Ok(max_doc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::SegmentWriter;
+    use core::{Index, SegmentComponent};
+    use datastruct::stacker::Heap;
+    use directory::{FileIoStats, InstrumentedDirectory, RAMDirectory};
+    use docset::DocSet;
+    use indexer::operation::AddOperation;
+    use postings::Postings;
+    use schema::{Document, Facet, Field, IndexRecordOption, IntOptions, SchemaBuilder, Term,
+                 Value, INT_STORED, TEXT};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use std::str;
+    use termdict::{TermDictionary, TermStreamer};
+    use test::Bencher;
+    use tokenizer::{FacetTokenizer, LowerCaser, SimpleTokenizer, StopWordFilter, SynonymFilter,
+                    TokenStream, Tokenizer};
+
+    fn facet_doc(facet_field: Field, doc_id: usize) -> Document {
+        let mut doc = Document::new();
+        doc.add_facet(
+            facet_field,
+            &format!("/top{}/mid{}/leaf{}", doc_id % 3, doc_id % 5, doc_id % 7),
+        );
+        doc.add_facet(facet_field, &format!("/top{}/mid{}", doc_id % 4, doc_id % 2));
+        doc.add_facet(facet_field, Facet::from_path(vec!["a", "", "b"]));
+        doc
+    }
+
+    #[test]
+    fn test_facet_dictionary() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        // doc freqs of the terms, as emitted by the `FacetTokenizer`.
+        let mut expected_doc_freqs: BTreeMap<Vec<u8>, u32> = BTreeMap::new();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for doc_id in 0..100 {
+                let doc = facet_doc(facet_field, doc_id);
+                let mut doc_terms: BTreeSet<Vec<u8>> = BTreeSet::new();
+                for value in doc.get_all(facet_field) {
+                    let facet = match *value {
+                        Value::Facet(ref facet) => facet,
+                        _ => panic!("Expected hierarchical facet"),
+                    };
+                    let text = str::from_utf8(facet.encoded_bytes()).unwrap();
+                    FacetTokenizer.token_stream(text).process(&mut |token| {
+                        doc_terms.insert(token.text.as_bytes().to_owned());
+                    });
+                }
+                for term in doc_terms {
+                    *expected_doc_freqs.entry(term).or_insert(0) += 1;
+                }
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let inverted_index = searcher.segment_reader(0).inverted_index(facet_field);
+        let mut doc_freqs: BTreeMap<Vec<u8>, u32> = BTreeMap::new();
+        let mut term_stream = inverted_index.terms().stream();
+        while term_stream.advance() {
+            doc_freqs.insert(term_stream.key().to_owned(), term_stream.value().doc_freq);
+        }
+        assert_eq!(doc_freqs, expected_doc_freqs);
+    }
+
+    #[test]
+    fn test_positions_follow_position_increments() {
+        let mut schema_builder = SchemaBuilder::new();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut synonyms = HashMap::new();
+        synonyms.insert("cat".to_string(), vec!["feline".to_string()]);
+        index.tokenizers().register(
+            "default",
+            SimpleTokenizer
+                .filter(LowerCaser)
+                .filter(StopWordFilter::remove(vec!["the", "a"]))
+                .filter(SynonymFilter::new(synonyms)),
+        );
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(
+                    text_field => "The cat ate a fish",
+                    text_field => "the end"
+                ))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let inverted_index = searcher.segment_reader(0).inverted_index(text_field);
+        let positions = |text: &str| {
+            let term = Term::from_field_text(text_field, text);
+            let mut postings = inverted_index
+                .read_postings(&term, IndexRecordOption::WithFreqsAndPositions)
+                .unwrap();
+            assert!(postings.advance());
+            postings.positions().to_vec()
+        };
+        // the stop words leave gaps, and the synonyms are stacked.
+        assert_eq!(positions("cat"), vec![1]);
+        assert_eq!(positions("feline"), vec![1]);
+        assert_eq!(positions("ate"), vec![2]);
+        assert_eq!(positions("fish"), vec![4]);
+        // the values of a field are separated by a gap of one position.
+        assert_eq!(positions("end"), vec![7]);
+    }
+
+    #[test]
+    fn test_field_presence_written_only_when_needed() {
+        let mut schema_builder = SchemaBuilder::new();
+        let stored_field = schema_builder.add_u64_field("stored", INT_STORED);
+        let ignored_field = schema_builder.add_u64_field("ignored", IntOptions::default());
+        let schema = schema_builder.build();
+        let directory = InstrumentedDirectory::new(RAMDirectory::create());
+        let index = Index::create_in_directory(directory.clone(), schema.clone()).unwrap();
+        let heap = Heap::with_capacity(10_000_000);
+        // returns the number of bytes charged to the heap for the documents,
+        // and the IO statistics of the field presence file.
+        let write_segment = |field: Field| -> (u32, FileIoStats) {
+            let segment = index.new_segment();
+            let field_presence_path = segment.relative_path(SegmentComponent::FIELDPRESENCE);
+            let mut segment_writer =
+                SegmentWriter::for_segment(&heap, 10, segment, &schema).unwrap();
+            let num_free_bytes = heap.num_free_bytes();
+            for opstamp in 0..8_000u64 {
+                let add_operation = AddOperation {
+                    opstamp,
+                    document: doc!(field => opstamp),
+                    doc_id_sender: None,
+                };
+                segment_writer.add_document(add_operation, &schema).unwrap();
+            }
+            let num_charged_bytes = num_free_bytes - heap.num_free_bytes();
+            segment_writer.finalize().unwrap();
+            (num_charged_bytes, directory.snapshot().file(&field_presence_path))
+        };
+        // the values of the field are not kept, so its presence is not recorded.
+        let (num_charged_bytes, field_presence_stats) = write_segment(ignored_field);
+        assert_eq!(num_charged_bytes, 0);
+        assert_eq!(field_presence_stats.num_open_writes, 0);
+        // one bit per document.
+        let (num_charged_bytes, field_presence_stats) = write_segment(stored_field);
+        assert_eq!(num_charged_bytes, 1_000);
+        assert_eq!(field_presence_stats.num_open_writes, 1);
+        assert!(field_presence_stats.num_bytes_written >= 1_000);
+    }
+
+    #[bench]
+    fn bench_index_facets(b: &mut Bencher) {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+        let docs: Vec<Document> = (0..10_000)
+            .map(|doc_id| facet_doc(facet_field, doc_id))
+            .collect();
+        b.iter(|| {
+            let index = Index::create_in_ram(schema.clone());
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for doc in &docs {
+                index_writer.add_document(doc.clone()).unwrap();
+            }
+            index_writer.commit().unwrap();
+        });
+    }
+}
@@ -1,15 +1,91 @@
+use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-#[derive(Clone, Default)]
+/// Stamp of an operation on an index.
+///
+/// Every operation of an `IndexWriter` (adding a document, deleting
+/// documents, committing...) is given an opstamp. Opstamps are strictly
+/// increasing over the whole lifetime of an index, across writer
+/// restarts: the first writer of an index starts at `1`, and a newly
+/// opened writer resumes strictly after the last opstamp recorded
+/// by the last commit, which is persisted in `meta.json`.
+///
+/// Opstamps are not contiguous. Merges consume opstamps, and a rollback
+/// does not reuse the opstamps of the operations it cancels.
+/// The operations of a writer dropped without committing are lost
+/// altogether: only their opstamps may be given again, by the next writer.
+///
+/// They can therefore be used by external systems as a cursor, for
+/// instance to know which of their own operations made it into the index.
+///
+/// The opstamp `0` is the one of the creation of the index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+         Deserialize)]
+pub struct Opstamp(pub u64);
+
+impl Opstamp {
+    /// Returns the opstamp following this one.
+    pub fn next(self) -> Opstamp {
+        Opstamp(self.0 + 1)
+    }
+}
+
+impl fmt::Display for Opstamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Holds the next opstamp to assign.
+#[derive(Clone)]
 pub struct Stamper(Arc<AtomicU64>);
 
 impl Stamper {
-    pub fn new(first_opstamp: u64) -> Stamper {
-        Stamper(Arc::new(AtomicU64::new(first_opstamp)))
+    /// Creates a stamper assigning opstamps strictly after `last_opstamp`.
+    pub fn new(last_opstamp: Opstamp) -> Stamper {
+        Stamper(Arc::new(AtomicU64::new(last_opstamp.next().0)))
     }
 
-    pub fn stamp(&self) -> u64 {
-        self.0.fetch_add(1u64, Ordering::SeqCst)
+    pub fn stamp(&self) -> Opstamp {
+        Opstamp(self.0.fetch_add(1u64, Ordering::SeqCst))
+    }
+
+    /// Returns the last assigned opstamp.
+    pub fn last_opstamp(&self) -> Opstamp {
+        // the stamper starts after an opstamp, so this never underflows.
+        Opstamp(self.0.load(Ordering::SeqCst) - 1)
+    }
+
+    /// Makes sure the following opstamps are strictly after `opstamp`.
+    pub fn skip_past(&self, opstamp: Opstamp) {
+        let mut next_opstamp = self.0.load(Ordering::SeqCst);
+        while next_opstamp <= opstamp.0 {
+            let previous = self.0
+                .compare_and_swap(next_opstamp, opstamp.0 + 1, Ordering::SeqCst);
+            if previous == next_opstamp {
+                break;
+            }
+            next_opstamp = previous;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Opstamp, Stamper};
+
+    #[test]
+    fn test_stamper() {
+        let stamper = Stamper::new(Opstamp(3));
+        assert_eq!(stamper.last_opstamp(), Opstamp(3));
+        assert_eq!(stamper.stamp(), Opstamp(4));
+        assert_eq!(stamper.clone().stamp(), Opstamp(5));
+        assert_eq!(stamper.last_opstamp(), Opstamp(5));
+        stamper.skip_past(Opstamp(2));
+        assert_eq!(stamper.stamp(), Opstamp(6));
+        stamper.skip_past(Opstamp(10));
+        assert_eq!(stamper.last_opstamp(), Opstamp(10));
+        assert_eq!(stamper.stamp(), Opstamp(11));
     }
 }
@@ -74,7 +74,7 @@
 //!     body => "He was an old man who fished alone in a skiff in \
 //!             the Gulf Stream and he had gone eighty-four days \
 //!             now without taking a fish."
-//! ));
+//! ))?;
 //!
 //! // We need to call .commit() explicitly to force the
 //! // index_writer to finish processing the documents in the queue,
@@ -171,6 +171,7 @@ extern crate rand;
 extern crate test;
 
 extern crate tinysegmenter;
+extern crate unicode_normalization;
 
 #[macro_use]
 extern crate downcast;
@@ -204,13 +205,26 @@ pub mod collector;
 pub mod postings;
 pub mod schema;
 pub mod fastfield;
+pub mod compat;
+// shared with the benchmarks and the integration tests,
+// but not part of the public API.
+#[doc(hidden)]
+pub mod test_utils;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 mod docset;
-pub use self::docset::{DocSet, SkipResult};
+pub use self::docset::{DocSet, DocSetGroup, SkipResult};
 
 pub use directory::Directory;
-pub use core::{Index, Searcher, Segment, SegmentId, SegmentMeta};
-pub use indexer::IndexWriter;
+pub use core::{Index, ScopedSearcher, Searcher, Segment, SegmentId, SegmentMeta};
+pub use core::{IndexBuilder, IndexMeta, IndexSettings};
+pub use core::{SearchProfile, SegmentSearchProfile};
+pub use core::Suggestion;
+pub use core::{ComponentSpaceUsage, FieldSpaceUsage, Residency, SegmentSpaceUsage};
+pub use core::RecoveryReport;
+pub use indexer::{AddDocumentResult, FieldIngestStats, IndexWriter, Opstamp, ValidationMode};
 pub use schema::{Document, Term};
 pub use core::{InvertedIndexReader, SegmentReader};
 pub use self::common::TimerTree;
@@ -243,13 +257,28 @@ pub mod merge_policy {
 /// as they are added in the segment.
 pub type DocId = u32;
 
-/// A f32 that represents the relevance of the document to the query
+/// A float that represents the relevance of the document to the query
 ///
-/// This is modelled internally as a `f32`. The
+/// This is modelled internally as a `f32`, or as a `f64` if the
+/// `f64score` feature is enabled. The
 /// larger the number, the more relevant the document
-/// to the search
+/// to the search.
+///
+/// Scores are never NaN. A scorer returning a NaN score triggers
+/// a panic in debug mode, and the score is replaced by `0` in release mode.
+#[cfg(not(feature = "f64score"))]
 pub type Score = f32;
 
+/// A float that represents the relevance of the document to the query
+///
+/// This is modelled internally as a `f64`, as the `f64score` feature is enabled.
+/// The larger the number, the more relevant the document to the search.
+///
+/// Scores are never NaN. A scorer returning a NaN score triggers
+/// a panic in debug mode, and the score is replaced by `0` in release mode.
+#[cfg(feature = "f64score")]
+pub type Score = f64;
+
 /// A `SegmentLocalId` identifies a segment.
 /// It only makes sense for a given searcher.
 pub type SegmentLocalId = u32;
@@ -337,15 +366,15 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"af b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -358,23 +387,23 @@ mod tests {
         let index = Index::create_in_ram(schema_builder.build());
         let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
         {
-            index_writer.add_document(doc!(text_field=>"a b c"));
+            index_writer.add_document(doc!(text_field=>"a b c")).unwrap();
             index_writer.commit().unwrap();
         }
         {
             {
                 let doc = doc!(text_field=>"a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
         {
             let doc = doc!(text_field=>"c");
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
             index_writer.commit().unwrap();
         }
         {
@@ -400,15 +429,15 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!();
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -439,22 +468,22 @@ mod tests {
             {
                 // 0
                 let doc = doc!(text_field=>"a b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 1
                 let doc = doc!(text_field=>" a c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 2
                 let doc = doc!(text_field=>" b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 3
                 let doc = doc!(text_field=>" b d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 index_writer.delete_term(Term::from_field_text(text_field, "c"));
@@ -465,12 +494,12 @@ mod tests {
             {
                 // 4
                 let doc = doc!(text_field=>" b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 5
                 let doc = doc!(text_field=>" a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -509,7 +538,7 @@ mod tests {
             {
                 // 0
                 let doc = doc!(text_field=>"a b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 1
@@ -552,7 +581,7 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"a b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 index_writer.delete_term(Term::from_field_text(text_field, "c"));
@@ -606,7 +635,7 @@ mod tests {
 
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
-        index_writer.add_document(doc!(field=>1u64));
+        index_writer.add_document(doc!(field=>1u64)).unwrap();
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
         let searcher = index.searcher();
@@ -630,7 +659,7 @@ mod tests {
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
         let negative_val = -1i64;
-        index_writer.add_document(doc!(value_field => negative_val));
+        index_writer.add_document(doc!(value_field => negative_val)).unwrap();
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
         let searcher = index.searcher();
@@ -653,7 +682,7 @@ mod tests {
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(2, 40_000_000).unwrap();
-        index_writer.add_document(doc!(text_field=>"a"));
+        index_writer.add_document(doc!(text_field=>"a")).unwrap();
         assert!(index_writer.commit().is_ok());
         assert!(index.load_searchers().is_ok());
         let searcher = index.searcher();
@@ -673,7 +702,7 @@ mod tests {
 
         let add_document = |index_writer: &mut IndexWriter, val: &'static str| {
             let doc = doc!(text_field=>val);
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         };
 
         let remove_document = |index_writer: &mut IndexWriter, val: &'static str| {
@@ -708,7 +737,7 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"af af af bc bc");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -746,15 +775,15 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"af af af b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -821,15 +850,15 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field=>"af b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field=>"a b c d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             index_writer.commit().unwrap();
         }
@@ -867,7 +896,7 @@ mod tests {
         let mut index_writer = index.writer_with_num_threads(1, 50_000_000).unwrap();
         {
             let document = doc!(fast_field_unsigned => 4u64, fast_field_signed=>4i64);
-            index_writer.add_document(document);
+            index_writer.add_document(document).unwrap();
             index_writer.commit().unwrap();
         }
 
@@ -18,6 +18,10 @@ macro_rules! get(
 ///
 /// The value can be a `u64`, a `&str`, a `i64`, or a `String`.
 ///
+/// A field may be repeated to add several values to it. Values are
+/// added in the order they are listed, which defines their order within
+/// the field (see [`Document`](schema/struct.Document.html)).
+///
 /// # Warning
 ///
 /// The document hence created, is not yet validated against a schema.
@@ -69,6 +73,47 @@ macro_rules! doc(
     };
 );
 
+/// `facet!` is a shortcut to build a `Facet` from its
+/// text representation.
+///
+/// Unlike `Facet::from`, the path is validated
+/// (see `Facet::from_str`).
+/// Without any argument, it returns the root facet.
+///
+/// # Panics
+///
+/// Panics if the path is not a valid facet.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate tantivy;
+///
+/// use tantivy::schema::Facet;
+///
+/// # fn main() {
+/// let facet = facet!("/category/fiction/fantasy");
+/// assert_eq!(facet, "/category/fiction/fantasy");
+/// assert_eq!(facet!(), Facet::root());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! facet(
+    () => {
+        $crate::schema::Facet::root()
+    };
+    ($path:expr) => {
+        {
+            let path: &str = $path;
+            match path.parse::<$crate::schema::Facet>() {
+                Ok(facet) => facet,
+                Err(err) => panic!("Invalid facet {:?}: {}", path, err),
+            }
+        }
+    };
+);
+
 #[cfg(test)]
 mod test {
     use schema::{SchemaBuilder, FAST, TEXT};
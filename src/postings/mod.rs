@@ -13,10 +13,12 @@ mod serializer;
 mod postings_writer;
 mod term_info;
 mod segment_postings;
+mod spill;
 
 use self::recorder::{NothingRecorder, Recorder, TFAndPositionRecorder, TermFrequencyRecorder};
 pub use self::serializer::{FieldSerializer, InvertedIndexSerializer};
 pub(crate) use self::postings_writer::MultiFieldPostingsWriter;
+use self::spill::{PostingsSink, RunReader, RunWriter};
 
 pub use self::term_info::TermInfo;
 pub use self::postings::Postings;
@@ -28,10 +30,15 @@ pub use common::HasLen;
 pub(crate) type UnorderedTermId = u64;
 
 #[allow(enum_variant_names)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum FreqReadingOption {
     NoFreq,
     SkipFreq,
     ReadFreq,
+    // the postings do not contain any term frequency, but the positions
+    // are read and the number of positions of each document, written
+    // before them, is used instead.
+    ReadPositionCounts,
 }
 
 #[cfg(test)]
@@ -43,7 +50,7 @@ pub mod tests {
     use Score;
     use query::Intersection;
     use query::Scorer;
-    use schema::{Document, SchemaBuilder, Term, INT_INDEXED, STRING, TEXT};
+    use schema::{Document, SchemaBuilder, Term, INT_INDEXED, STORED, STRING, TEXT};
     use core::SegmentComponent;
     use indexer::SegmentWriter;
     use core::SegmentReader;
@@ -52,9 +59,11 @@ pub mod tests {
     use std::iter;
     use datastruct::stacker::Heap;
     use query::TermQuery;
+    use collector::CountCollector;
     use schema::Field;
     use test::{self, Bencher};
     use indexer::operation::AddOperation;
+    use indexer::Opstamp;
     use tests;
     use rand::{Rng, SeedableRng, XorShiftRng};
 
@@ -89,12 +98,12 @@ pub mod tests {
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema);
         let mut index_writer = index.writer_with_num_threads(1, 30_000_000).unwrap();
-        index_writer.add_document(doc!(title => r#"abc abc abc"#));
-        index_writer.add_document(doc!(title => r#"abc be be be be abc"#));
+        index_writer.add_document(doc!(title => r#"abc abc abc"#)).unwrap();
+        index_writer.add_document(doc!(title => r#"abc be be be be abc"#)).unwrap();
         for _ in 0..1_000 {
-            index_writer.add_document(doc!(title => r#"abc abc abc"#));
+            index_writer.add_document(doc!(title => r#"abc abc abc"#)).unwrap();
         }
-        index_writer.add_document(doc!(title => r#"abc be be be be abc"#));
+        index_writer.add_document(doc!(title => r#"abc be be be be abc"#)).unwrap();
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
         let searcher = index.searcher();
@@ -147,6 +156,123 @@ pub mod tests {
         }
     }
 
+    #[test]
+    pub fn test_positions_without_freqs() {
+        use collector::tests::TestCollector;
+        use query::PhraseQuery;
+        use schema::{TextFieldIndexing, TextOptions};
+        let text_options = |record_option: IndexRecordOption| {
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer("default")
+                    .set_index_option(record_option),
+            )
+        };
+        let mut schema_builder = SchemaBuilder::default();
+        let with_freqs = schema_builder.add_text_field(
+            "with_freqs",
+            text_options(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let without_freqs = schema_builder
+            .add_text_field("without_freqs", text_options(IndexRecordOption::WithPositions));
+        let index = Index::create_in_ram(schema_builder.build());
+        let seed: &[u32; 4] = &[1, 2, 3, 4];
+        let mut rng: XorShiftRng = XorShiftRng::from_seed(*seed);
+        let words = ["a", "a", "a", "b", "b", "c", "d", "e"];
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for _ in 0..3_000 {
+                let num_words = rng.gen_range(1, 20);
+                let text: Vec<&str> = (0..num_words)
+                    .map(|_| *rng.choose(&words).unwrap())
+                    .collect();
+                let text = text.join(" ");
+                index_writer
+                    .add_document(doc!(with_freqs => text.clone(), without_freqs => text))
+                    .unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+
+        // the term frequencies are not written in the postings anymore.
+        let space_usage = segment_reader.space_usage();
+        let postings_num_bytes = |field: Field| -> usize {
+            space_usage
+                .component(SegmentComponent::POSTINGS)
+                .unwrap()
+                .field(field)
+                .iter()
+                .map(|field_space_usage| field_space_usage.num_bytes())
+                .sum()
+        };
+        assert!(postings_num_bytes(without_freqs) < postings_num_bytes(with_freqs));
+
+        // the phrase queries match the same documents.
+        let phrases: &[(&[&str], u32)] = &[
+            (&["a", "b"], 0),
+            (&["b", "a", "a"], 0),
+            (&["c", "d", "e"], 0),
+            (&["e", "a"], 2),
+            (&["d", "c", "b"], 1),
+        ];
+        for &(phrase_words, slop) in phrases {
+            let matching_docs = |field: Field| {
+                let terms: Vec<Term> = phrase_words
+                    .iter()
+                    .map(|word| Term::from_field_text(field, word))
+                    .collect();
+                let phrase_query = PhraseQuery::new_with_slop(terms, slop);
+                let mut test_collector = TestCollector::default();
+                searcher.search(&phrase_query, &mut test_collector).unwrap();
+                test_collector.docs()
+            };
+            let docs = matching_docs(with_freqs);
+            assert!(!docs.is_empty());
+            assert_eq!(matching_docs(without_freqs), docs);
+        }
+
+        // the positions are the same, and the term frequencies are
+        // the numbers of positions if, and only if, they are read.
+        for &(target, record_option) in &[
+            (0, IndexRecordOption::Basic),
+            (0, IndexRecordOption::WithPositions),
+            (1_500, IndexRecordOption::Basic),
+            (1_500, IndexRecordOption::WithPositions),
+        ] {
+            let read_postings = |field: Field, record_option: IndexRecordOption| {
+                segment_reader
+                    .inverted_index(field)
+                    .read_postings(&Term::from_field_text(field, "a"), record_option)
+                    .unwrap()
+            };
+            let mut expected_postings =
+                read_postings(with_freqs, IndexRecordOption::WithFreqsAndPositions);
+            let mut postings = read_postings(without_freqs, record_option);
+            assert_eq!(
+                expected_postings.skip_next(target),
+                postings.skip_next(target)
+            );
+            loop {
+                assert_eq!(postings.doc(), expected_postings.doc());
+                if record_option.has_positions() {
+                    assert_eq!(postings.term_freq(), expected_postings.term_freq());
+                    assert_eq!(postings.positions(), expected_postings.positions());
+                } else {
+                    assert_eq!(postings.term_freq(), 1);
+                    assert!(postings.positions().is_empty());
+                }
+                let has_next = expected_postings.advance();
+                assert_eq!(postings.advance(), has_next);
+                if !has_next {
+                    break;
+                }
+            }
+        }
+    }
+
     #[test]
     pub fn test_position_and_fieldnorm1() {
         let mut schema_builder = SchemaBuilder::default();
@@ -165,8 +291,9 @@ pub mod tests {
                 doc.add_text(text_field, "a b a c a d a a.");
                 doc.add_text(text_field, "d d d d a");
                 let op = AddOperation {
-                    opstamp: 0u64,
+                    opstamp: Opstamp(0),
                     document: doc,
+                    doc_id_sender: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -174,8 +301,9 @@ pub mod tests {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "b a");
                 let op = AddOperation {
-                    opstamp: 1u64,
+                    opstamp: Opstamp(1),
                     document: doc,
+                    doc_id_sender: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -185,8 +313,9 @@ pub mod tests {
                 text.push_str(" a");
                 doc.add_text(text_field, &text);
                 let op = AddOperation {
-                    opstamp: 2u64,
+                    opstamp: Opstamp(2),
                     document: doc,
+                    doc_id_sender: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -256,6 +385,59 @@ pub mod tests {
         }
     }
 
+    #[test]
+    pub fn test_position_interleaved_fields() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let mut doc = Document::default();
+            doc.add_text(title_field, "a b");
+            doc.add_text(body_field, "x a");
+            doc.add_text(title_field, "c a");
+            doc.add_text(body_field, "a");
+            doc.add_text(title_field, "a b");
+            index_writer.add_document(doc).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let positions = |field: Field| {
+            let term = Term::from_field_text(field, "a");
+            let mut postings = segment_reader
+                .inverted_index(field)
+                .read_postings(&term, IndexRecordOption::WithFreqsAndPositions)
+                .unwrap();
+            assert!(postings.advance());
+            postings.positions().to_vec()
+        };
+        assert_eq!(positions(title_field), vec![0, 3, 4]);
+        assert_eq!(positions(body_field), vec![1, 2]);
+        let fieldnorm_reader = segment_reader.get_fieldnorms_reader(title_field).unwrap();
+        assert_eq!(fieldnorm_reader.get(0), 6);
+
+        let stored_doc = segment_reader.doc(0).unwrap();
+        let stored_texts: Vec<(Field, &str)> = stored_doc
+            .field_values()
+            .iter()
+            .map(|field_value| (field_value.field(), field_value.value().text()))
+            .collect();
+        assert_eq!(
+            stored_texts,
+            vec![
+                (title_field, "a b"),
+                (body_field, "x a"),
+                (title_field, "c a"),
+                (body_field, "a"),
+                (title_field, "a b"),
+            ]
+        );
+    }
+
     #[test]
     pub fn test_position_and_fieldnorm2() {
         let mut schema_builder = SchemaBuilder::default();
@@ -267,12 +449,12 @@ pub mod tests {
             {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "g b b d c g c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let mut doc = Document::default();
                 doc.add_text(text_field, "g a b b a d c g c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -291,6 +473,54 @@ pub mod tests {
         assert_eq!(term_scorer.postings().positions(), &[1u32, 4]);
     }
 
+    #[test]
+    fn test_fill_buffer() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                let mut doc = Document::default();
+                if i % 3 == 0 {
+                    doc.add_text(text_field, "a b");
+                } else {
+                    doc.add_text(text_field, "a");
+                }
+                index_writer.add_document(doc).unwrap();
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+        let term_a = Term::from_field_text(text_field, "a");
+        let targets = vec![3, 200, 201, 640, 641, 999, 1_500];
+        let check_fill_buffer = || {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let segment_reader = searcher.segment_reader(0);
+            for &index_record_option in &[
+                IndexRecordOption::Basic,
+                IndexRecordOption::WithFreqsAndPositions,
+            ] {
+                test_fill_buffer_against_unoptimized(
+                    || -> Box<DocSet> {
+                        box segment_reader
+                            .inverted_index(text_field)
+                            .read_postings(&term_a, index_record_option)
+                            .unwrap()
+                    },
+                    targets.clone(),
+                );
+            }
+        };
+        check_fill_buffer();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "b"));
+            assert!(index_writer.commit().is_ok());
+        }
+        check_fill_buffer();
+    }
+
     #[test]
     fn test_skip_next() {
         let term_0 = Term::from_field_u64(Field(0), 0);
@@ -312,7 +542,7 @@ pub mod tests {
                     doc.add_u64(value_field, 2);
                     doc.add_u64(value_field, (i % 2) as u64);
 
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 assert!(index_writer.commit().is_ok());
             }
@@ -507,7 +737,7 @@ pub mod tests {
                     if rng.gen_weighted_bool(1) {
                         doc.add_text(text_field, "d");
                     }
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 assert!(index_writer.commit().is_ok());
             }
@@ -633,6 +863,39 @@ pub mod tests {
         });
     }
 
+    #[bench]
+    fn bench_segment_postings_fill_buffer(b: &mut Bencher) {
+        let searcher = INDEX.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        b.iter(|| {
+            let mut segment_postings = segment_reader
+                .inverted_index(TERM_C.field())
+                .read_postings(&*TERM_C, IndexRecordOption::Basic)
+                .unwrap();
+            let mut buffer = [0u32; 128];
+            let mut count = 0;
+            loop {
+                let num_docs = segment_postings.fill_buffer(&mut buffer[..]);
+                count += num_docs;
+                if num_docs < buffer.len() {
+                    break;
+                }
+            }
+            count
+        });
+    }
+
+    #[bench]
+    fn bench_count_common_term(b: &mut Bencher) {
+        let searcher = INDEX.searcher();
+        let term_query = TermQuery::new(TERM_C.clone(), IndexRecordOption::Basic);
+        b.iter(|| {
+            let mut count_collector = CountCollector::default();
+            searcher.search(&term_query, &mut count_collector).unwrap();
+            count_collector.count()
+        });
+    }
+
     /// Wraps a given docset, and forward alls call but the
     /// `.skip_next(...)`. This is useful to test that a specialized
     /// implementation of `.skip_next(...)` is consistent
@@ -699,4 +962,43 @@ pub mod tests {
         }
     }
 
+    /// Checks that `.fill_buffer(...)` returns the same documents
+    /// as the default implementation, for different buffer lengths,
+    /// and when interleaved with calls to `.skip_next(...)`.
+    pub fn test_fill_buffer_against_unoptimized<F: Fn() -> Box<DocSet>>(
+        postings_factory: F,
+        targets: Vec<u32>,
+    ) {
+        for &buffer_len in &[1, 3, 64, 128, 1_000] {
+            let mut postings_opt = postings_factory();
+            let mut postings_unopt = UnoptimizedDocSet::wrap(postings_factory());
+            let mut buffer_opt = vec![0u32; buffer_len];
+            let mut buffer_unopt = vec![0u32; buffer_len];
+            let mut targets_it = targets.iter();
+            loop {
+                let num_docs_opt = postings_opt.fill_buffer(&mut buffer_opt[..]);
+                let num_docs_unopt = postings_unopt.fill_buffer(&mut buffer_unopt[..]);
+                assert_eq!(num_docs_opt, num_docs_unopt);
+                assert_eq!(
+                    &buffer_opt[..num_docs_opt],
+                    &buffer_unopt[..num_docs_unopt]
+                );
+                if num_docs_opt < buffer_len {
+                    break;
+                }
+                assert_eq!(postings_opt.doc(), postings_unopt.doc());
+                if let Some(&target) = targets_it.next() {
+                    if target > postings_opt.doc() {
+                        let skip_result = postings_opt.skip_next(target);
+                        assert_eq!(skip_result, postings_unopt.skip_next(target));
+                        if skip_result == SkipResult::End {
+                            break;
+                        }
+                        assert_eq!(postings_opt.doc(), postings_unopt.doc());
+                    }
+                }
+            }
+        }
+    }
+
 }
@@ -1,10 +1,11 @@
 use DocId;
 use schema::Term;
 use postings::{FieldSerializer, InvertedIndexSerializer};
-use std::io;
+use std::io::{self, Seek, SeekFrom, Write};
 use std::collections::HashMap;
 use postings::Recorder;
 use Result;
+use error::ErrorKind;
 use schema::{Field, Schema};
 use std::marker::PhantomData;
 use std::ops::DerefMut;
@@ -16,6 +17,11 @@ use tokenizer::Token;
 use tokenizer::TokenStream;
 use schema::IndexRecordOption;
 use postings::UnorderedTermId;
+use postings::{RunReader, RunWriter};
+use tokenizer::MAX_TOKEN_LEN;
+use std::fs::File;
+use std::cmp;
+use crossbeam;
 
 fn posting_from_field_entry<'a>(
     field_entry: &FieldEntry,
@@ -31,7 +37,7 @@ fn posting_from_field_entry<'a>(
                 IndexRecordOption::WithFreqs => {
                     SpecializedPostingsWriter::<TermFrequencyRecorder>::new_boxed(heap)
                 }
-                IndexRecordOption::WithFreqsAndPositions => {
+                IndexRecordOption::WithPositions | IndexRecordOption::WithFreqsAndPositions => {
                     SpecializedPostingsWriter::<TFAndPositionRecorder>::new_boxed(heap)
                 }
             })
@@ -42,10 +48,61 @@ fn posting_from_field_entry<'a>(
     }
 }
 
+/// Splits terms sorted by their bytes into the ranges
+/// of terms of each field.
+fn field_ranges(term_offsets: &[(&[u8], u32, UnorderedTermId)]) -> Vec<(Field, usize, usize)> {
+    let mut field_ranges: Vec<(Field, usize, usize)> = vec![];
+    for (offset, &(key, _, _)) in term_offsets.iter().enumerate() {
+        let field = Term::wrap(key).field();
+        if let Some(&mut (last_field, _, ref mut stop)) = field_ranges.last_mut() {
+            if last_field == field {
+                *stop = offset + 1;
+                continue;
+            }
+        }
+        field_ranges.push((field, offset, offset + 1));
+    }
+    field_ranges
+}
+
+/// Returns the mapping from the `UnorderedTermId`s of the terms
+/// of a field, sorted by their bytes, to their term ordinals.
+fn term_ord_mapping(
+    term_offsets: &[(&[u8], u32, UnorderedTermId)],
+) -> HashMap<UnorderedTermId, usize> {
+    term_offsets
+        .iter()
+        .map(|&(_, _, term_unord_id)| term_unord_id)
+        .enumerate()
+        .map(|(term_ord, term_unord_id)| (term_unord_id, term_ord))
+        .collect()
+}
+
+/// Shares a `MultiFieldPostingsWriter` with the threads serializing its fields.
+///
+/// The heap is only read while the postings are serialized,
+/// and each thread reads the recorders of the terms of its own fields.
+struct SharedPostingsWriter<'p, 'a: 'p>(&'p MultiFieldPostingsWriter<'a>);
+
+unsafe impl<'p, 'a> Send for SharedPostingsWriter<'p, 'a> {}
+
+impl<'p, 'a> Clone for SharedPostingsWriter<'p, 'a> {
+    fn clone(&self) -> Self {
+        SharedPostingsWriter(self.0)
+    }
+}
+
+impl<'p, 'a> Copy for SharedPostingsWriter<'p, 'a> {}
+
 pub struct MultiFieldPostingsWriter<'a> {
     heap: &'a Heap,
+    table_bits: usize,
     term_index: TermHashMap<'a>,
     per_field_postings_writers: Vec<Box<PostingsWriter + 'a>>,
+    max_token_len: usize,
+    num_dropped_tokens: usize,
+    // postings spilled out of the heap, in temporary files.
+    spilled_runs: Vec<File>,
 }
 
 impl<'a> MultiFieldPostingsWriter<'a> {
@@ -61,66 +118,126 @@ impl<'a> MultiFieldPostingsWriter<'a> {
 
         MultiFieldPostingsWriter {
             heap,
+            table_bits,
             term_index,
             per_field_postings_writers,
+            max_token_len: MAX_TOKEN_LEN,
+            num_dropped_tokens: 0,
+            spilled_runs: Vec::new(),
+        }
+    }
+
+    /// Offset added to the `UnorderedTermId`s of the terms
+    /// of the heap, so that they remain unique across spilled runs.
+    fn term_id_offset(&self) -> UnorderedTermId {
+        (self.spilled_runs.len() as u64) << 32
+    }
+
+    /// Returns the number of runs spilled so far.
+    pub fn num_spilled_runs(&self) -> usize {
+        self.spilled_runs.len()
+    }
+
+    /// Moves the postings of the heap to a temporary run file,
+    /// and clears the heap.
+    ///
+    /// Runs are merged back when the segment is serialized.
+    pub fn spill(&mut self) -> io::Result<()> {
+        let run_file = self.write_run()?;
+        self.spilled_runs.push(run_file);
+        self.heap.clear();
+        self.term_index = TermHashMap::new(self.table_bits, self.heap);
+        Ok(())
+    }
+
+    fn write_run(&self) -> io::Result<File> {
+        let mut term_offsets: Vec<(&[u8], u32, UnorderedTermId)> = self.term_index.iter().collect();
+        term_offsets.sort_by_key(|&(k, _, _)| k);
+        let term_id_offset = self.term_id_offset();
+        let mut run_writer = RunWriter::create()?;
+        for (field, start, stop) in field_ranges(&term_offsets) {
+            let postings_writer = &self.per_field_postings_writers[field.0 as usize];
+            postings_writer.spill(
+                &term_offsets[start..stop],
+                term_id_offset,
+                &mut run_writer,
+                self.heap,
+            )?;
+        }
+        run_writer.finish()
+    }
+
+    /// Sets the maximum length of the indexed tokens.
+    /// Longer tokens are dropped.
+    ///
+    /// Returns an error if `max_token_len` exceeds `MAX_TOKEN_LEN`.
+    pub fn set_max_token_len(&mut self, max_token_len: usize) -> Result<()> {
+        if max_token_len > MAX_TOKEN_LEN {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The maximum token length may not exceed {}, got {}.",
+                MAX_TOKEN_LEN, max_token_len
+            )));
         }
+        self.max_token_len = max_token_len;
+        Ok(())
+    }
+
+    /// Returns the number of tokens that were dropped because
+    /// they exceeded the maximum token length.
+    pub fn num_dropped_tokens(&self) -> usize {
+        self.num_dropped_tokens
     }
 
     pub fn index_text(&mut self, doc: DocId, field: Field, token_stream: &mut TokenStream) -> u32 {
         let postings_writer = self.per_field_postings_writers[field.0 as usize].deref_mut();
-        postings_writer.index_text(&mut self.term_index, doc, field, token_stream, self.heap)
+        postings_writer.index_text(
+            &mut self.term_index,
+            doc,
+            field,
+            token_stream,
+            self.heap,
+            self.max_token_len,
+            &mut self.num_dropped_tokens,
+        )
     }
 
     pub fn subscribe(&mut self, doc: DocId, term: &Term) -> UnorderedTermId {
+        let term_id_offset = self.term_id_offset();
         let postings_writer = self.per_field_postings_writers[term.field().0 as usize].deref_mut();
-        postings_writer.subscribe(&mut self.term_index, doc, 0u32, term, self.heap)
+        term_id_offset | postings_writer.subscribe(&mut self.term_index, doc, 0u32, term, self.heap)
     }
 
     /// Serialize the inverted index.
     /// It pushes all term, one field at a time, towards the
     /// postings serializer.
-    #[allow(needless_range_loop)]
+    ///
+    /// Returns, for each field, the mapping from the `UnorderedTermId`s
+    /// to the term ordinals.
     pub fn serialize(
         &self,
         serializer: &mut InvertedIndexSerializer,
+    ) -> Result<HashMap<Field, HashMap<UnorderedTermId, usize>>> {
+        if self.spilled_runs.is_empty() {
+            self.serialize_heap(serializer)
+        } else {
+            self.serialize_runs(serializer)
+        }
+    }
+
+    fn serialize_heap(
+        &self,
+        serializer: &mut InvertedIndexSerializer,
     ) -> Result<HashMap<Field, HashMap<UnorderedTermId, usize>>> {
         let mut term_offsets: Vec<(&[u8], u32, UnorderedTermId)> = self.term_index.iter().collect();
         term_offsets.sort_by_key(|&(k, _, _)| k);
 
-        let mut offsets: Vec<(Field, usize)> = vec![];
-        let term_offsets_it = term_offsets
-            .iter()
-            .cloned()
-            .map(|(key, _, _)| Term::wrap(key).field())
-            .enumerate();
-
         let mut unordered_term_mappings: HashMap<Field, HashMap<UnorderedTermId, usize>> =
             HashMap::new();
 
-        let mut prev_field = Field(u32::max_value());
-        for (offset, field) in term_offsets_it {
-            if field != prev_field {
-                offsets.push((field, offset));
-                prev_field = field;
-            }
-        }
-        offsets.push((Field(0), term_offsets.len()));
-
-        for i in 0..(offsets.len() - 1) {
-            let (field, start) = offsets[i];
-            let (_, stop) = offsets[i + 1];
-
+        for (field, start, stop) in field_ranges(&term_offsets) {
             // populating the unordered term ord -> ordered term ord mapping
             // for the field.
-            let mut mapping = HashMap::new();
-            for (term_ord, term_unord_id) in term_offsets[start..stop]
-                .iter()
-                .map(|&(_, _, bucket)| bucket)
-                .enumerate()
-            {
-                mapping.insert(term_unord_id, term_ord);
-            }
-            unordered_term_mappings.insert(field, mapping);
+            unordered_term_mappings.insert(field, term_ord_mapping(&term_offsets[start..stop]));
 
             let postings_writer = &self.per_field_postings_writers[field.0 as usize];
             let mut field_serializer = serializer.new_field(field)?;
@@ -134,9 +251,140 @@ impl<'a> MultiFieldPostingsWriter<'a> {
         Ok(unordered_term_mappings)
     }
 
-    /// Return true iff the term dictionary is saturated.
-    pub fn is_term_saturated(&self) -> bool {
-        self.term_index.is_saturated()
+    /// Serializes the inverted index like `.serialize(...)`, with up
+    /// to `num_threads` threads.
+    ///
+    /// The fields are split into ranges of fields, with about the same
+    /// number of terms, that are serialized in memory in parallel and
+    /// appended in the order of the fields. The result is the same as
+    /// with `.serialize(...)`.
+    ///
+    /// If some postings were spilled, the runs are merged
+    /// by the calling thread, as in `.serialize(...)`.
+    pub fn serialize_in_parallel(
+        &self,
+        serializer: &mut InvertedIndexSerializer,
+        num_threads: usize,
+    ) -> Result<HashMap<Field, HashMap<UnorderedTermId, usize>>> {
+        if num_threads <= 1 || !self.spilled_runs.is_empty() {
+            return self.serialize(serializer);
+        }
+        let mut term_offsets: Vec<(&[u8], u32, UnorderedTermId)> = self.term_index.iter().collect();
+        term_offsets.sort_by_key(|&(k, _, _)| k);
+        let field_ranges = field_ranges(&term_offsets);
+
+        // cuts the fields into ranges of consecutive fields,
+        // each of them holding at least `num_terms_per_job` terms.
+        let num_terms_per_job = cmp::max((term_offsets.len() + num_threads - 1) / num_threads, 1);
+        let mut jobs: Vec<&[(Field, usize, usize)]> = Vec::with_capacity(num_threads);
+        let mut job_start = 0;
+        for (ord, &(_, _, stop)) in field_ranges.iter().enumerate() {
+            let job_first_term = field_ranges[job_start].1;
+            if stop - job_first_term >= num_terms_per_job || ord + 1 == field_ranges.len() {
+                jobs.push(&field_ranges[job_start..ord + 1]);
+                job_start = ord + 1;
+            }
+        }
+
+        let shared_postings_writer = SharedPostingsWriter(self);
+        let buffers = crossbeam::scope(|scope| {
+            let job_handles: Vec<_> = jobs.iter()
+                .map(|&job_field_ranges| {
+                    let term_offsets = &term_offsets;
+                    let in_memory_serializer = serializer.in_memory();
+                    scope.spawn(move || -> io::Result<InvertedIndexSerializer<Vec<u8>>> {
+                        let postings_writer = shared_postings_writer;
+                        let mut in_memory_serializer = in_memory_serializer;
+                        for &(field, start, stop) in job_field_ranges {
+                            postings_writer.0.serialize_field_in_memory(
+                                field,
+                                &term_offsets[start..stop],
+                                &mut in_memory_serializer,
+                            )?;
+                        }
+                        Ok(in_memory_serializer)
+                    })
+                })
+                .collect();
+            job_handles
+                .into_iter()
+                .map(|job_handle| job_handle.join())
+                .collect::<io::Result<Vec<_>>>()
+        })?;
+        for buffer in buffers {
+            serializer.append(buffer)?;
+        }
+        Ok(field_ranges
+            .into_iter()
+            .map(|(field, start, stop)| (field, term_ord_mapping(&term_offsets[start..stop])))
+            .collect())
+    }
+
+    fn serialize_field_in_memory(
+        &self,
+        field: Field,
+        term_offsets: &[(&[u8], u32, UnorderedTermId)],
+        serializer: &mut InvertedIndexSerializer<Vec<u8>>,
+    ) -> io::Result<()> {
+        let postings_writer = &self.per_field_postings_writers[field.0 as usize];
+        let mut field_serializer = serializer.new_field(field)?;
+        postings_writer.serialize_in_memory(term_offsets, &mut field_serializer, self.heap)?;
+        field_serializer.close()
+    }
+
+    /// Merges the spilled runs, as well as the postings
+    /// remaining in the heap.
+    ///
+    /// The runs were spilled in increasing doc id order, so
+    /// that the postings of a term are simply concatenated.
+    fn serialize_runs(
+        &self,
+        serializer: &mut InvertedIndexSerializer,
+    ) -> Result<HashMap<Field, HashMap<UnorderedTermId, usize>>> {
+        let mut run_readers = Vec::with_capacity(self.spilled_runs.len() + 1);
+        for spilled_run in &self.spilled_runs {
+            let mut run_file = spilled_run.try_clone()?;
+            run_file.seek(SeekFrom::Start(0))?;
+            run_readers.push(RunReader::open(run_file)?);
+        }
+        run_readers.push(RunReader::open(self.write_run()?)?);
+
+        let mut unordered_term_mappings: HashMap<Field, HashMap<UnorderedTermId, usize>> =
+            HashMap::new();
+        let mut term_bytes: Vec<u8> = Vec::new();
+        loop {
+            let field = match run_readers.iter().filter_map(RunReader::term).min() {
+                Some(min_term) => Term::wrap(min_term).field(),
+                None => break,
+            };
+            let mut mapping = HashMap::new();
+            let mut field_serializer = serializer.new_field(field)?;
+            for term_ord in 0.. {
+                match run_readers.iter().filter_map(RunReader::term).min() {
+                    Some(min_term) if Term::wrap(min_term).field() == field => {
+                        term_bytes.clear();
+                        term_bytes.extend_from_slice(min_term);
+                    }
+                    _ => break,
+                }
+                field_serializer.new_term(&term_bytes[4..])?;
+                for run_reader in &mut run_readers {
+                    if run_reader.term() == Some(&term_bytes[..]) {
+                        mapping.insert(run_reader.term_id(), term_ord);
+                        run_reader.copy_postings(&mut field_serializer)?;
+                    }
+                }
+                field_serializer.close_term()?;
+            }
+            field_serializer.close()?;
+            unordered_term_mappings.insert(field, mapping);
+        }
+        Ok(unordered_term_mappings)
+    }
+
+    /// Accessor to the term dictionary hash map.
+    pub(crate) fn term_index(&self) -> &TermHashMap<'a> {
+        &self.term_index
     }
 }
 
@@ -170,7 +418,32 @@ pub trait PostingsWriter {
         heap: &Heap,
     ) -> io::Result<()>;
 
+    /// Serializes the postings in memory, like `.serialize(...)`.
+    fn serialize_in_memory(
+        &self,
+        term_addrs: &[(&[u8], u32, UnorderedTermId)],
+        serializer: &mut FieldSerializer<Vec<u8>>,
+        heap: &Heap,
+    ) -> io::Result<()>;
+
+    /// Writes the postings to a temporary run.
+    ///
+    /// `term_id_offset` is added to the `UnorderedTermId`s of the terms.
+    fn spill(
+        &self,
+        term_addrs: &[(&[u8], u32, UnorderedTermId)],
+        term_id_offset: UnorderedTermId,
+        run_writer: &mut RunWriter,
+        heap: &Heap,
+    ) -> io::Result<()>;
+
     /// Tokenize a text and subscribe all of its token.
+    ///
+    /// The position of each token is the sum of the position increments
+    /// of the tokens so far, starting from `0`. Tokens longer than
+    /// `max_token_len` bytes are not indexed, and counted in
+    /// `num_dropped_tokens`, but their position increment is honored.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     fn index_text(
         &mut self,
         term_index: &mut TermHashMap,
@@ -178,12 +451,20 @@ pub trait PostingsWriter {
         field: Field,
         token_stream: &mut TokenStream,
         heap: &Heap,
+        max_token_len: usize,
+        num_dropped_tokens: &mut usize,
     ) -> u32 {
         let mut term = unsafe { Term::with_capacity(100) };
         term.set_field(field);
+        let mut position = u32::max_value();
         let mut sink = |token: &Token| {
+            position = position.wrapping_add(token.position_increment);
+            if token.text.len() > max_token_len {
+                *num_dropped_tokens += 1;
+                return;
+            }
             term.set_text(token.text.as_str());
-            self.subscribe(term_index, doc_id, token.position as u32, &term, heap);
+            self.subscribe(term_index, doc_id, position, &term, heap);
         };
         token_stream.process(&mut sink)
     }
@@ -209,6 +490,21 @@ impl<'a, Rec: Recorder + 'static> SpecializedPostingsWriter<'a, Rec> {
     pub fn new_boxed(heap: &'a Heap) -> Box<PostingsWriter + 'a> {
         Box::new(SpecializedPostingsWriter::<Rec>::new(heap))
     }
+
+    fn serialize_terms<W: Write>(
+        &self,
+        term_addrs: &[(&[u8], u32, UnorderedTermId)],
+        serializer: &mut FieldSerializer<W>,
+        heap: &Heap,
+    ) -> io::Result<()> {
+        for &(term_bytes, addr, _) in term_addrs {
+            let recorder: &mut Rec = self.heap.get_mut_ref(addr);
+            serializer.new_term(&term_bytes[4..])?;
+            recorder.serialize(addr, serializer, heap)?;
+            serializer.close_term()?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'a, Rec> {
@@ -239,11 +535,30 @@ impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'
         serializer: &mut FieldSerializer,
         heap: &Heap,
     ) -> io::Result<()> {
-        for &(term_bytes, addr, _) in term_addrs {
+        self.serialize_terms(term_addrs, serializer, heap)
+    }
+
+    fn serialize_in_memory(
+        &self,
+        term_addrs: &[(&[u8], u32, UnorderedTermId)],
+        serializer: &mut FieldSerializer<Vec<u8>>,
+        heap: &Heap,
+    ) -> io::Result<()> {
+        self.serialize_terms(term_addrs, serializer, heap)
+    }
+
+    fn spill(
+        &self,
+        term_addrs: &[(&[u8], u32, UnorderedTermId)],
+        term_id_offset: UnorderedTermId,
+        run_writer: &mut RunWriter,
+        heap: &Heap,
+    ) -> io::Result<()> {
+        for &(term_bytes, addr, term_id) in term_addrs {
             let recorder: &mut Rec = self.heap.get_mut_ref(addr);
-            serializer.new_term(&term_bytes[4..])?;
-            recorder.serialize(addr, serializer, heap)?;
-            serializer.close_term()?;
+            run_writer.new_term(term_bytes, term_id_offset | term_id)?;
+            recorder.serialize(addr, run_writer, heap)?;
+            run_writer.close_term()?;
         }
         Ok(())
     }
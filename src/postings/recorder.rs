@@ -1,6 +1,6 @@
 use DocId;
 use std::{self, io};
-use postings::FieldSerializer;
+use postings::PostingsSink;
 use datastruct::stacker::{ExpUnrolledLinkedList, Heap, HeapAllocable};
 
 const EMPTY_ARRAY: [u32; 0] = [0u32; 0];
@@ -26,11 +26,12 @@ pub trait Recorder: HeapAllocable {
     fn record_position(&mut self, position: u32, heap: &Heap);
     /// Close the document. It will help record the term frequency.
     fn close_doc(&mut self, heap: &Heap);
-    /// Pushes the postings information to the serializer.
-    fn serialize(
+    /// Pushes the postings information to the serializer,
+    /// or to a spilled run.
+    fn serialize<S: PostingsSink>(
         &self,
         self_addr: u32,
-        serializer: &mut FieldSerializer,
+        serializer: &mut S,
         heap: &Heap,
     ) -> io::Result<()>;
 }
@@ -64,10 +65,10 @@ impl Recorder for NothingRecorder {
 
     fn close_doc(&mut self, _heap: &Heap) {}
 
-    fn serialize(
+    fn serialize<S: PostingsSink>(
         &self,
         self_addr: u32,
-        serializer: &mut FieldSerializer,
+        serializer: &mut S,
         heap: &Heap,
     ) -> io::Result<()> {
         for doc in self.stack.iter(self_addr, heap) {
@@ -114,10 +115,10 @@ impl Recorder for TermFrequencyRecorder {
         self.current_tf = 0;
     }
 
-    fn serialize(
+    fn serialize<S: PostingsSink>(
         &self,
         self_addr: u32,
-        serializer: &mut FieldSerializer,
+        serializer: &mut S,
         heap: &Heap,
     ) -> io::Result<()> {
         // the last document has not been closed...
@@ -169,10 +170,10 @@ impl Recorder for TFAndPositionRecorder {
         self.stack.push(POSITION_END, heap);
     }
 
-    fn serialize(
+    fn serialize<S: PostingsSink>(
         &self,
         self_addr: u32,
-        serializer: &mut FieldSerializer,
+        serializer: &mut S,
         heap: &Heap,
     ) -> io::Result<()> {
         let mut doc_positions = Vec::with_capacity(100);
@@ -13,29 +13,44 @@ use std::cell::UnsafeCell;
 use directory::{ReadOnlySource, SourceRead};
 use postings::FreqReadingOption;
 use postings::serializer::PostingsSerializer;
+use query::query_context::Pooled;
 
 const EMPTY_POSITIONS: [u32; 0] = [0u32; 0];
 
 struct PositionComputer {
     // store the amount of position int
-    // before reading positions.
+    // before reading positions, or the number of documents
+    // to skip if `has_position_counts`.
     //
     // if none, position are already loaded in
     // the positions vec.
     position_to_skip: Option<usize>,
     positions: Vec<u32>,
     positions_stream: CompressedIntStream,
+    // true iff the number of positions of each document
+    // is written before them in the stream.
+    has_position_counts: bool,
 }
 
 impl PositionComputer {
-    pub fn new(positions_stream: CompressedIntStream) -> PositionComputer {
+    pub fn new(
+        positions_stream: CompressedIntStream,
+        has_position_counts: bool,
+    ) -> PositionComputer {
         PositionComputer {
             position_to_skip: None,
             positions: vec![],
             positions_stream,
+            has_position_counts,
         }
     }
 
+    fn read_position_count(&mut self) -> usize {
+        let mut position_count = [0u32];
+        self.positions_stream.read(&mut position_count);
+        position_count[0] as usize
+    }
+
     pub fn add_skip(&mut self, num_skip: usize) {
         self.position_to_skip = Some(
             self.position_to_skip
@@ -44,20 +59,30 @@ impl PositionComputer {
         );
     }
 
+    // `term_freq` is ignored if `has_position_counts`.
     pub fn positions(&mut self, term_freq: usize) -> &[u32] {
         if let Some(num_skip) = self.position_to_skip {
-            self.positions.resize(term_freq, 0u32);
-            self.positions_stream.skip(num_skip);
-            self.positions_stream.read(&mut self.positions[..term_freq]);
+            let num_positions = if self.has_position_counts {
+                for _ in 0..num_skip {
+                    let num_positions_to_skip = self.read_position_count();
+                    self.positions_stream.skip(num_positions_to_skip);
+                }
+                self.read_position_count()
+            } else {
+                self.positions_stream.skip(num_skip);
+                term_freq
+            };
+            self.positions.resize(num_positions, 0u32);
+            self.positions_stream.read(&mut self.positions[..]);
 
             let mut cum = 0u32;
-            for i in 0..term_freq as usize {
+            for i in 0..num_positions {
                 cum += self.positions[i];
                 self.positions[i] = cum;
             }
             self.position_to_skip = None;
         }
-        &self.positions[..term_freq]
+        &self.positions[..]
     }
 }
 
@@ -67,7 +92,8 @@ impl PositionComputer {
 /// As we iterate through the `SegmentPostings`, the frequencies are optionally decoded.
 /// Positions on the other hand, are optionally entirely decoded upfront.
 pub struct SegmentPostings {
-    block_cursor: BlockSegmentPostings,
+    // taken from a `QueryContext`, if any, to which it is given back on drop.
+    block_cursor: Pooled<BlockSegmentPostings>,
     cur: usize,
     delete_bitset: DeleteBitSet,
     position_computer: Option<UnsafeCell<PositionComputer>>,
@@ -111,8 +137,22 @@ impl SegmentPostings {
         delete_bitset: DeleteBitSet,
         positions_stream_opt: Option<CompressedIntStream>,
     ) -> SegmentPostings {
-        let position_computer =
-            positions_stream_opt.map(|stream| UnsafeCell::new(PositionComputer::new(stream)));
+        SegmentPostings::from_pooled_block_postings(
+            Pooled::unpooled(box segment_block_postings),
+            delete_bitset,
+            positions_stream_opt,
+        )
+    }
+
+    pub(crate) fn from_pooled_block_postings(
+        segment_block_postings: Pooled<BlockSegmentPostings>,
+        delete_bitset: DeleteBitSet,
+        positions_stream_opt: Option<CompressedIntStream>,
+    ) -> SegmentPostings {
+        let has_position_counts =
+            segment_block_postings.freq_reading_option == FreqReadingOption::ReadPositionCounts;
+        let position_computer = positions_stream_opt
+            .map(|stream| UnsafeCell::new(PositionComputer::new(stream, has_position_counts)));
         SegmentPostings {
             block_cursor: segment_block_postings,
             cur: COMPRESSION_BLOCK_SIZE, // cursor within the block
@@ -125,21 +165,33 @@ impl SegmentPostings {
     pub fn empty() -> SegmentPostings {
         let empty_block_cursor = BlockSegmentPostings::empty();
         SegmentPostings {
-            block_cursor: empty_block_cursor,
+            block_cursor: Pooled::unpooled(box empty_block_cursor),
             delete_bitset: DeleteBitSet::empty(),
             cur: COMPRESSION_BLOCK_SIZE,
             position_computer: None,
         }
     }
 
-    fn position_add_skip<F: FnOnce() -> usize>(&self, num_skips_fn: F) {
+    // Skips the positions of `num_docs` documents, holding
+    // `num_positions_fn()` positions in total.
+    fn position_add_skip<F: FnOnce() -> usize>(&self, num_docs: usize, num_positions_fn: F) {
         if let Some(position_computer) = self.position_computer.as_ref() {
-            let num_skips = num_skips_fn();
             unsafe {
-                (*position_computer.get()).add_skip(num_skips);
+                let position_computer = &mut *position_computer.get();
+                let num_skips = if position_computer.has_position_counts {
+                    num_docs
+                } else {
+                    num_positions_fn()
+                };
+                position_computer.add_skip(num_skips);
             }
         }
     }
+
+    fn has_position_counts(&self) -> bool {
+        self.block_cursor.freq_reading_option == FreqReadingOption::ReadPositionCounts
+            && self.position_computer.is_some()
+    }
 }
 
 impl DocSet for SegmentPostings {
@@ -148,7 +200,7 @@ impl DocSet for SegmentPostings {
     #[inline]
     fn advance(&mut self) -> bool {
         loop {
-            self.position_add_skip(|| self.term_freq() as usize);
+            self.position_add_skip(1, || self.block_cursor.freq(self.cur) as usize);
             self.cur += 1;
             if self.cur >= self.block_cursor.block_len() {
                 self.cur = 0;
@@ -185,7 +237,8 @@ impl DocSet for SegmentPostings {
                 // just set itself to Some(0) or effectively
                 // add the term freq.
                 //let num_skips: u32 = ;
-                self.position_add_skip(|| {
+                let num_docs_skipped = self.block_cursor.block_len() - self.cur;
+                self.position_add_skip(num_docs_skipped, || {
                     let freqs_skipped = &self.block_cursor.freqs()[self.cur..];
                     let sum_freq: u32 = freqs_skipped.iter().cloned().sum();
                     sum_freq as usize
@@ -245,7 +298,7 @@ impl DocSet for SegmentPostings {
             // `doc` is now >= `target`
             let doc = block_docs[start];
 
-            self.position_add_skip(|| {
+            self.position_add_skip(start - self.cur, || {
                 let freqs_skipped = &self.block_cursor.freqs()[self.cur..start];
                 let sum_freqs: u32 = freqs_skipped.iter().sum();
                 sum_freqs as usize
@@ -268,6 +321,40 @@ impl DocSet for SegmentPostings {
         }
     }
 
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        if self.position_computer.is_some() || self.delete_bitset.has_deletes() {
+            // positions and deletes require to go through
+            // the documents one by one.
+            for (i, buffer_val) in buffer.iter_mut().enumerate() {
+                if self.advance() {
+                    *buffer_val = self.doc();
+                } else {
+                    return i;
+                }
+            }
+            return buffer.len();
+        }
+        let mut num_docs = 0;
+        while num_docs < buffer.len() {
+            self.cur += 1;
+            if self.cur >= self.block_cursor.block_len() {
+                self.cur = 0;
+                if !self.block_cursor.advance() {
+                    self.cur = COMPRESSION_BLOCK_SIZE;
+                    return num_docs;
+                }
+            }
+            // copy the rest of the current block, or as much as the buffer can hold.
+            let block_docs = &self.block_cursor.docs()[self.cur..];
+            let num_copied = cmp::min(block_docs.len(), buffer.len() - num_docs);
+            buffer[num_docs..num_docs + num_copied].copy_from_slice(&block_docs[..num_copied]);
+            num_docs += num_copied;
+            // the cursor points to the last copied document.
+            self.cur += num_copied - 1;
+        }
+        num_docs
+    }
+
     fn size_hint(&self) -> u32 {
         self.len() as u32
     }
@@ -307,11 +394,15 @@ impl HasLen for SegmentPostings {
 
 impl Postings for SegmentPostings {
     fn term_freq(&self) -> u32 {
-        self.block_cursor.freq(self.cur)
+        if self.has_position_counts() {
+            self.positions().len() as u32
+        } else {
+            self.block_cursor.freq(self.cur)
+        }
     }
 
     fn positions(&self) -> &[u32] {
-        let term_freq = self.term_freq();
+        let term_freq = self.block_cursor.freq(self.cur);
         self.position_computer
             .as_ref()
             .map(|position_computer| unsafe {
@@ -380,6 +471,27 @@ impl BlockSegmentPostings {
         self.doc_freq = doc_freq;
     }
 
+    // Resets the block segment postings on another position
+    // in the postings file, to be read with another `FreqReadingOption`.
+    //
+    // Unlike `reset`, the block segment postings is then in the
+    // same state as a new one: this is what makes it possible to
+    // reuse it for another posting list, of another field.
+    pub(crate) fn reset_with_option(
+        &mut self,
+        doc_freq: usize,
+        postings_data: SourceRead,
+        freq_reading_option: FreqReadingOption,
+    ) {
+        self.reset(doc_freq, postings_data);
+        self.doc_decoder.output_len = 0;
+        if freq_reading_option != FreqReadingOption::ReadFreq {
+            // the term frequencies that are not read are all equal to 1.
+            self.freq_decoder = BlockDecoder::with_val(1);
+        }
+        self.freq_reading_option = freq_reading_option;
+    }
+
     /// Returns the document frequency associated to this block postings.
     ///
     /// This `doc_freq` is simply the sum of the length of all of the blocks
@@ -434,7 +546,7 @@ impl BlockSegmentPostings {
                 .uncompress_block_sorted(self.remaining_data.as_ref(), self.doc_offset);
             self.remaining_data.advance(num_consumed_bytes);
             match self.freq_reading_option {
-                FreqReadingOption::NoFreq => {}
+                FreqReadingOption::NoFreq | FreqReadingOption::ReadPositionCounts => {}
                 FreqReadingOption::SkipFreq => {
                     let num_bytes_to_skip = compressed_block_size(self.remaining_data.as_ref()[0]);
                     self.remaining_data.advance(num_bytes_to_skip);
@@ -457,7 +569,9 @@ impl BlockSegmentPostings {
             );
             self.remaining_data.advance(num_compressed_bytes);
             match self.freq_reading_option {
-                FreqReadingOption::NoFreq | FreqReadingOption::SkipFreq => {}
+                FreqReadingOption::NoFreq
+                | FreqReadingOption::SkipFreq
+                | FreqReadingOption::ReadPositionCounts => {}
                 FreqReadingOption::ReadFreq => {
                     self.freq_decoder
                         .uncompress_vint_unsorted(self.remaining_data.as_ref(), self.num_vint_docs);
@@ -512,6 +626,7 @@ mod tests {
     use schema::IndexRecordOption;
     use common::HasLen;
     use super::BlockSegmentPostings;
+    use postings::tests::test_fill_buffer_against_unoptimized;
 
     #[test]
     fn test_empty_segment_postings() {
@@ -521,6 +636,20 @@ mod tests {
         assert_eq!(postings.len(), 0);
     }
 
+    #[test]
+    fn test_segment_postings_fill_buffer() {
+        let docs: Vec<u32> = (0..1_000u32).map(|i| i * 3 + i % 7).collect();
+        test_fill_buffer_against_unoptimized(
+            || box SegmentPostings::create_from_docs(&docs),
+            vec![1, 2, 500, 1_200, 1_201, 2_500, 5_000],
+        );
+        test_fill_buffer_against_unoptimized(
+            || box SegmentPostings::create_from_docs(&docs[..10]),
+            vec![3, 17],
+        );
+        test_fill_buffer_against_unoptimized(|| box SegmentPostings::empty(), vec![]);
+    }
+
     #[test]
     fn test_empty_block_segment_postings() {
         let mut postings = BlockSegmentPostings::empty();
@@ -537,7 +666,7 @@ mod tests {
         let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
         for _ in 0..100_000 {
             let doc = doc!(int_field=>0u64);
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
@@ -572,7 +701,7 @@ mod tests {
         // the other containing odd numbers.
         for i in 0..6 {
             let doc = doc!(int_field=> (i % 2) as u64);
-            index_writer.add_document(doc);
+            index_writer.add_document(doc).unwrap();
         }
         index_writer.commit().unwrap();
         index.load_searchers().unwrap();
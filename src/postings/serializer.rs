@@ -4,6 +4,7 @@ use super::TermInfo;
 use schema::Field;
 use schema::FieldEntry;
 use schema::FieldType;
+use schema::IndexRecordOption;
 use schema::Schema;
 use directory::WritePtr;
 use compression::{BlockEncoder, COMPRESSION_BLOCK_SIZE};
@@ -14,12 +15,14 @@ use compression::VIntEncoder;
 use common::CountingWriter;
 use common::CompositeWrite;
 use termdict::TermDictionaryBuilder;
+use datastruct::BloomFilterBuilder;
 
 /// `PostingsSerializer` is in charge of serializing
 /// postings on disk, in the
 /// * `.idx` (inverted index)
 /// * `.pos` (positions file)
 /// * `.term` (term dictionary)
+/// * `.bloom` (bloom filters, for the fields configured with one)
 ///
 /// `PostingsWriter` are in charge of pushing the data to the
 /// serializer.
@@ -46,54 +49,114 @@ use termdict::TermDictionaryBuilder;
 ///
 /// A description of the serialization format is
 /// [available here](https://fulmicoton.gitbooks.io/tantivy-doc/content/inverted-index.html).
-pub struct InvertedIndexSerializer {
-    terms_write: CompositeWrite<WritePtr>,
-    postings_write: CompositeWrite<WritePtr>,
-    positions_write: CompositeWrite<WritePtr>,
+pub struct InvertedIndexSerializer<W: Write = WritePtr> {
+    terms_write: CompositeWrite<W>,
+    postings_write: CompositeWrite<W>,
+    positions_write: CompositeWrite<W>,
+    bloom_filters_write: CompositeWrite<W>,
     schema: Schema,
 }
 
 impl InvertedIndexSerializer {
+    /// Open a new `PostingsSerializer` for the given segment
+    pub fn open(segment: &mut Segment) -> Result<InvertedIndexSerializer> {
+        use SegmentComponent::{BLOOMFILTERS, POSITIONS, POSTINGS, TERMS};
+        InvertedIndexSerializer::new(
+            CompositeWrite::wrap(segment.open_write(TERMS)?),
+            CompositeWrite::wrap(segment.open_write(POSTINGS)?),
+            CompositeWrite::wrap(segment.open_write(POSITIONS)?),
+            CompositeWrite::wrap(segment.open_write(BLOOMFILTERS)?),
+            segment.schema(),
+        )
+    }
+
+    /// Appends the fields serialized by an in-memory serializer,
+    /// in the order they were serialized.
+    ///
+    /// The result is the same as if these fields had been serialized
+    /// directly by this serializer.
+    pub fn append(&mut self, buffer: InvertedIndexSerializer<Vec<u8>>) -> io::Result<()> {
+        self.terms_write.append(buffer.terms_write)?;
+        self.postings_write.append(buffer.postings_write)?;
+        self.positions_write.append(buffer.positions_write)?;
+        self.bloom_filters_write.append(buffer.bloom_filters_write)
+    }
+}
+
+impl<W: Write> InvertedIndexSerializer<W> {
     /// Open a new `PostingsSerializer` for the given segment
     fn new(
-        terms_write: CompositeWrite<WritePtr>,
-        postings_write: CompositeWrite<WritePtr>,
-        positions_write: CompositeWrite<WritePtr>,
+        terms_write: CompositeWrite<W>,
+        postings_write: CompositeWrite<W>,
+        positions_write: CompositeWrite<W>,
+        bloom_filters_write: CompositeWrite<W>,
         schema: Schema,
-    ) -> Result<InvertedIndexSerializer> {
+    ) -> Result<InvertedIndexSerializer<W>> {
         Ok(InvertedIndexSerializer {
             terms_write,
             postings_write,
             positions_write,
+            bloom_filters_write,
             schema,
         })
     }
 
-    /// Open a new `PostingsSerializer` for the given segment
-    pub fn open(segment: &mut Segment) -> Result<InvertedIndexSerializer> {
-        use SegmentComponent::{POSITIONS, POSTINGS, TERMS};
-        InvertedIndexSerializer::new(
-            CompositeWrite::wrap(segment.open_write(TERMS)?),
-            CompositeWrite::wrap(segment.open_write(POSTINGS)?),
-            CompositeWrite::wrap(segment.open_write(POSITIONS)?),
-            segment.schema(),
-        )
+    /// Creates a serializer with the same schema, buffering the fields in memory.
+    ///
+    /// The buffered fields are then appended to a serializer with `.append(...)`.
+    pub fn in_memory(&self) -> InvertedIndexSerializer<Vec<u8>> {
+        InvertedIndexSerializer {
+            terms_write: CompositeWrite::wrap(Vec::new()),
+            postings_write: CompositeWrite::wrap(Vec::new()),
+            positions_write: CompositeWrite::wrap(Vec::new()),
+            bloom_filters_write: CompositeWrite::wrap(Vec::new()),
+            schema: self.schema.clone(),
+        }
     }
 
     /// Must be called before starting pushing terms of
     /// a given field.
     ///
     /// Loads the indexing options for the given field.
-    pub fn new_field(&mut self, field: Field) -> io::Result<FieldSerializer> {
+    pub fn new_field(&mut self, field: Field) -> io::Result<FieldSerializer<W>> {
+        let record_option = self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option()
+            .unwrap_or(IndexRecordOption::Basic);
+        self.new_field_with_record_option(field, record_option)
+    }
+
+    /// Must be called before starting pushing terms of
+    /// a given field.
+    ///
+    /// Unlike `.new_field(...)`, the postings are written
+    /// with the given `IndexRecordOption` rather than with the one
+    /// of the schema. This is used when merging segments that were
+    /// written with different options.
+    pub fn new_field_with_record_option(
+        &mut self,
+        field: Field,
+        record_option: IndexRecordOption,
+    ) -> io::Result<FieldSerializer<W>> {
         let field_entry: &FieldEntry = self.schema.get_field_entry(field);
         let term_dictionary_write = self.terms_write.for_field(field);
         let postings_write = self.postings_write.for_field(field);
         let positions_write = self.positions_write.for_field(field);
+        let bloom_filter_opt = match field_entry.bloom_filter() {
+            Some(num_bits_per_term) => Some((
+                BloomFilterBuilder::new(num_bits_per_term),
+                self.bloom_filters_write.for_field(field),
+            )),
+            None => None,
+        };
         FieldSerializer::new(
             field_entry.field_type().clone(),
+            record_option,
             term_dictionary_write,
             postings_write,
             positions_write,
+            bloom_filter_opt,
         )
     }
 
@@ -102,41 +165,37 @@ impl InvertedIndexSerializer {
         self.terms_write.close()?;
         self.postings_write.close()?;
         self.positions_write.close()?;
+        self.bloom_filters_write.close()?;
         Ok(())
     }
 }
 
 /// The field serializer is in charge of
 /// the serialization of a specific field.
-pub struct FieldSerializer<'a> {
-    term_dictionary_builder: TermDictionaryBuilderImpl<&'a mut CountingWriter<WritePtr>>,
-    postings_serializer: PostingsSerializer<&'a mut CountingWriter<WritePtr>>,
-    positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<WritePtr>>>,
+pub struct FieldSerializer<'a, W: Write + 'a = WritePtr> {
+    term_dictionary_builder: TermDictionaryBuilderImpl<&'a mut CountingWriter<W>>,
+    postings_serializer: PostingsSerializer<&'a mut CountingWriter<W>>,
+    positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<W>>>,
+    // true iff the number of positions of each document
+    // is written before them, in place of its term frequency.
+    position_counts_enabled: bool,
+    bloom_filter_opt: Option<(BloomFilterBuilder, &'a mut CountingWriter<W>)>,
     current_term_info: TermInfo,
     term_open: bool,
 }
 
-impl<'a> FieldSerializer<'a> {
+impl<'a, W: Write> FieldSerializer<'a, W> {
     fn new(
         field_type: FieldType,
-        term_dictionary_write: &'a mut CountingWriter<WritePtr>,
-        postings_write: &'a mut CountingWriter<WritePtr>,
-        positions_write: &'a mut CountingWriter<WritePtr>,
-    ) -> io::Result<FieldSerializer<'a>> {
-        let (term_freq_enabled, position_enabled): (bool, bool) = match field_type {
-            FieldType::Str(ref text_options) => {
-                if let Some(text_indexing_options) = text_options.get_indexing_options() {
-                    let index_option = text_indexing_options.index_option();
-                    (
-                        index_option.is_termfreq_enabled(),
-                        index_option.is_position_enabled(),
-                    )
-                } else {
-                    (false, false)
-                }
-            }
-            _ => (false, false),
-        };
+        record_option: IndexRecordOption,
+        term_dictionary_write: &'a mut CountingWriter<W>,
+        postings_write: &'a mut CountingWriter<W>,
+        positions_write: &'a mut CountingWriter<W>,
+        bloom_filter_opt: Option<(BloomFilterBuilder, &'a mut CountingWriter<W>)>,
+    ) -> io::Result<FieldSerializer<'a, W>> {
+        let term_freq_enabled = record_option.is_termfreq_enabled();
+        let position_enabled = record_option.is_position_enabled();
+        let position_counts_enabled = position_enabled && !term_freq_enabled;
         let term_dictionary_builder =
             TermDictionaryBuilderImpl::new(term_dictionary_write, field_type)?;
         let postings_serializer = PostingsSerializer::new(postings_write, term_freq_enabled);
@@ -150,6 +209,8 @@ impl<'a> FieldSerializer<'a> {
             term_dictionary_builder,
             postings_serializer,
             positions_serializer_opt,
+            position_counts_enabled,
+            bloom_filter_opt,
             current_term_info: TermInfo::default(),
             term_open: false,
         })
@@ -180,6 +241,9 @@ impl<'a> FieldSerializer<'a> {
         self.term_open = true;
         self.postings_serializer.clear();
         self.current_term_info = self.current_term_info();
+        if let Some((ref mut bloom_filter_builder, _)) = self.bloom_filter_opt {
+            bloom_filter_builder.insert(term);
+        }
         self.term_dictionary_builder.insert_key(term)
     }
 
@@ -192,6 +256,11 @@ impl<'a> FieldSerializer<'a> {
     ///
     /// Term frequencies and positions may be ignored by the serializer depending
     /// on the configuration of the field in the `Schema`.
+    ///
+    /// If the positions are recorded without the term frequencies
+    /// (see `IndexRecordOption::WithPositions`), the number of positions
+    /// is written in the positions file, right before them. It is what
+    /// makes it possible to find the positions of a document.
     pub fn write_doc(
         &mut self,
         doc_id: DocId,
@@ -201,6 +270,9 @@ impl<'a> FieldSerializer<'a> {
         self.current_term_info.doc_freq += 1;
         self.postings_serializer.write_doc(doc_id, term_freq)?;
         if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
+            if self.position_counts_enabled {
+                positions_serializer.write(&[position_deltas.len() as u32])?;
+            }
             positions_serializer.write(position_deltas)?;
         }
         Ok(())
@@ -228,6 +300,9 @@ impl<'a> FieldSerializer<'a> {
         }
         self.postings_serializer.close()?;
         self.term_dictionary_builder.finish()?;
+        if let Some((bloom_filter_builder, bloom_filter_write)) = self.bloom_filter_opt {
+            bloom_filter_builder.serialize(bloom_filter_write)?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,221 @@
+/*!
+Temporary run files, holding the postings spilled
+out of the indexing heap.
+
+A run file contains the terms of the heap at the time of the spill,
+sorted in the order of their bytes (field included),
+each followed by its postings.
+
+Terms are written as
+* the length of the term bytes, as a `VInt`,
+* the term bytes,
+* the `UnorderedTermId` of the term, as a `VInt`,
+* for each document, the doc id plus one, the term frequency
+and the number of positions as `VInt`s, followed by the position deltas,
+* a `0` `VInt` closing the postings of the term.
+
+The run ends with a term length of `0`.
+*/
+use DocId;
+use common::{BinarySerializable, VInt};
+use postings::{FieldSerializer, UnorderedTermId};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use tempfile;
+
+/// Receives the postings of a term, document by document,
+/// in increasing doc id order.
+pub trait PostingsSink {
+    /// Records the presence of the current term in a document.
+    ///
+    /// Positions are delta-encoded.
+    fn write_doc(&mut self, doc_id: DocId, term_freq: u32, position_deltas: &[u32])
+        -> io::Result<()>;
+}
+
+impl<'a, W: Write> PostingsSink for FieldSerializer<'a, W> {
+    fn write_doc(
+        &mut self,
+        doc_id: DocId,
+        term_freq: u32,
+        position_deltas: &[u32],
+    ) -> io::Result<()> {
+        FieldSerializer::write_doc(self, doc_id, term_freq, position_deltas)
+    }
+}
+
+/// Writes a run in an anonymous temporary file.
+pub struct RunWriter {
+    write: BufWriter<File>,
+}
+
+impl RunWriter {
+    /// Creates a run in a new temporary file.
+    pub fn create() -> io::Result<RunWriter> {
+        Ok(RunWriter {
+            write: BufWriter::new(tempfile::tempfile()?),
+        })
+    }
+
+    /// Starts the postings of a new term.
+    ///
+    /// Terms have to be pushed in sorted order.
+    pub fn new_term(&mut self, term_bytes: &[u8], term_id: UnorderedTermId) -> io::Result<()> {
+        debug_assert!(!term_bytes.is_empty());
+        VInt(term_bytes.len() as u64).serialize(&mut self.write)?;
+        self.write.write_all(term_bytes)?;
+        VInt(term_id).serialize(&mut self.write)
+    }
+
+    /// Ends the postings of the current term.
+    pub fn close_term(&mut self) -> io::Result<()> {
+        VInt(0u64).serialize(&mut self.write)
+    }
+
+    /// Closes the run, and returns its file,
+    /// ready to be read from the start.
+    pub fn finish(mut self) -> io::Result<File> {
+        VInt(0u64).serialize(&mut self.write)?;
+        let mut file = self.write.into_inner()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    }
+}
+
+impl PostingsSink for RunWriter {
+    fn write_doc(
+        &mut self,
+        doc_id: DocId,
+        term_freq: u32,
+        position_deltas: &[u32],
+    ) -> io::Result<()> {
+        VInt(u64::from(doc_id) + 1).serialize(&mut self.write)?;
+        VInt(u64::from(term_freq)).serialize(&mut self.write)?;
+        VInt(position_deltas.len() as u64).serialize(&mut self.write)?;
+        for &position_delta in position_deltas {
+            VInt(u64::from(position_delta)).serialize(&mut self.write)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a run, one term at a time.
+pub struct RunReader {
+    read: BufReader<File>,
+    term_bytes: Vec<u8>,
+    term_id: UnorderedTermId,
+    exhausted: bool,
+    position_deltas: Vec<u32>,
+}
+
+impl RunReader {
+    /// Opens a run, and positions the reader on its first term.
+    pub fn open(file: File) -> io::Result<RunReader> {
+        let mut run_reader = RunReader {
+            read: BufReader::new(file),
+            term_bytes: Vec::new(),
+            term_id: 0u64,
+            exhausted: false,
+            position_deltas: Vec::new(),
+        };
+        run_reader.read_term()?;
+        Ok(run_reader)
+    }
+
+    fn read_term(&mut self) -> io::Result<()> {
+        let term_len = VInt::deserialize(&mut self.read)?.val() as usize;
+        if term_len == 0 {
+            self.exhausted = true;
+            return Ok(());
+        }
+        self.term_bytes.resize(term_len, 0u8);
+        io::Read::read_exact(&mut self.read, &mut self.term_bytes[..])?;
+        self.term_id = VInt::deserialize(&mut self.read)?.val();
+        Ok(())
+    }
+
+    /// Returns the bytes of the current term, or `None`
+    /// if all of the terms of the run have been read.
+    pub fn term(&self) -> Option<&[u8]> {
+        if self.exhausted {
+            None
+        } else {
+            Some(&self.term_bytes[..])
+        }
+    }
+
+    /// Returns the `UnorderedTermId` of the current term.
+    pub fn term_id(&self) -> UnorderedTermId {
+        self.term_id
+    }
+
+    /// Pushes the postings of the current term to the sink,
+    /// and moves on to the next term.
+    pub fn copy_postings<S: PostingsSink>(&mut self, sink: &mut S) -> io::Result<()> {
+        loop {
+            let doc_plus_one = VInt::deserialize(&mut self.read)?.val();
+            if doc_plus_one == 0 {
+                break;
+            }
+            let term_freq = VInt::deserialize(&mut self.read)?.val() as u32;
+            let num_positions = VInt::deserialize(&mut self.read)?.val() as usize;
+            self.position_deltas.clear();
+            for _ in 0..num_positions {
+                let position_delta = VInt::deserialize(&mut self.read)?.val() as u32;
+                self.position_deltas.push(position_delta);
+            }
+            sink.write_doc((doc_plus_one - 1) as DocId, term_freq, &self.position_deltas)?;
+        }
+        self.read_term()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{PostingsSink, RunReader, RunWriter};
+    use DocId;
+
+    #[derive(Default)]
+    struct VecSink(Vec<(DocId, u32, Vec<u32>)>);
+
+    impl PostingsSink for VecSink {
+        fn write_doc(
+            &mut self,
+            doc_id: DocId,
+            term_freq: u32,
+            position_deltas: &[u32],
+        ) -> ::std::io::Result<()> {
+            self.0.push((doc_id, term_freq, position_deltas.to_owned()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_roundtrip() {
+        let mut run_writer = RunWriter::create().unwrap();
+        run_writer.new_term(b"\x00\x00\x00\x00abc", 3u64).unwrap();
+        run_writer.write_doc(0, 2, &[1, 3]).unwrap();
+        run_writer.write_doc(7, 1, &[4]).unwrap();
+        run_writer.close_term().unwrap();
+        run_writer
+            .new_term(b"\x00\x00\x00\x01d", (1u64 << 32) | 5u64)
+            .unwrap();
+        run_writer.write_doc(2, 0, &[]).unwrap();
+        run_writer.close_term().unwrap();
+        let run_file = run_writer.finish().unwrap();
+
+        let mut run_reader = RunReader::open(run_file).unwrap();
+        assert_eq!(run_reader.term(), Some(&b"\x00\x00\x00\x00abc"[..]));
+        assert_eq!(run_reader.term_id(), 3u64);
+        let mut sink = VecSink::default();
+        run_reader.copy_postings(&mut sink).unwrap();
+        assert_eq!(sink.0, vec![(0, 2, vec![1, 3]), (7, 1, vec![4])]);
+        assert_eq!(run_reader.term(), Some(&b"\x00\x00\x00\x01d"[..]));
+        assert_eq!(run_reader.term_id(), (1u64 << 32) | 5u64);
+        let mut sink = VecSink::default();
+        run_reader.copy_postings(&mut sink).unwrap();
+        assert_eq!(sink.0, vec![(2, 0, vec![])]);
+        assert_eq!(run_reader.term(), None);
+    }
+}
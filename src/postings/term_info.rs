@@ -2,25 +2,56 @@ use common::{BinarySerializable, FixedSize};
 use std::io;
 
 /// `TermInfo` contains all of the information
-/// associated to terms in the `.term` file.
+/// associated to a term in the term dictionary of a segment.
 ///
-/// It consists of
-/// * `doc_freq` : the number of document in the segment
-/// containing this term. It is also the length of the
-/// posting list associated to this term
-/// * `postings_offset` : an offset in the `.idx` file
-/// addressing the start of the posting list associated
-/// to this term.
+/// It is the value returned by the term dictionary lookups
+/// and by the [`TermStreamer`](../termdict/trait.TermStreamer.html)s,
+/// and serves as an address of the posting list of the term, as read by
+/// [`InvertedIndexReader::read_postings_from_terminfo`][read_postings_from_terminfo].
+///
+/// Its fields are only readable through accessors,
+/// so that the format of the index can evolve.
+///
+/// [read_postings_from_terminfo]:
+/// ../struct.InvertedIndexReader.html#method.read_postings_from_terminfo
 #[derive(Debug, Default, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct TermInfo {
     /// Number of documents in the segment containing the term
-    pub doc_freq: u32,
+    pub(crate) doc_freq: u32,
     /// Offset within the postings (`.idx`) file.
-    pub postings_offset: u64,
+    pub(crate) postings_offset: u64,
     /// Offset within the position (`.pos`) file.
-    pub positions_offset: u64,
+    pub(crate) positions_offset: u64,
     /// Offset within the position block.
-    pub positions_inner_offset: u8,
+    pub(crate) positions_inner_offset: u8,
+}
+
+impl TermInfo {
+    /// Returns the number of documents of the segment containing the term.
+    ///
+    /// It is also the length of the posting list of the term.
+    /// Deleted documents are included.
+    pub fn doc_freq(&self) -> u32 {
+        self.doc_freq
+    }
+
+    /// Returns the offset of the posting list of the term,
+    /// within the postings (`.idx`) file.
+    pub fn postings_offset(&self) -> u64 {
+        self.postings_offset
+    }
+
+    /// Returns the offset of the block containing the first position
+    /// of the term, within the positions (`.pos`) file.
+    pub fn positions_offset(&self) -> u64 {
+        self.positions_offset
+    }
+
+    /// Returns the offset of the first position of the term,
+    /// within its block of positions.
+    pub fn positions_inner_offset(&self) -> u8 {
+        self.positions_inner_offset
+    }
 }
 
 impl FixedSize for TermInfo {
@@ -58,10 +89,27 @@ impl BinarySerializable for TermInfo {
 mod tests {
 
     use super::TermInfo;
-    use common::test::fixed_size_test;
+    use common::FixedSize;
+    use common::test::{fixed_size_test, serialize_test};
 
     #[test]
     fn test_fixed_size() {
         fixed_size_test::<TermInfo>();
     }
+
+    #[test]
+    fn test_serialize_term_info() {
+        assert_eq!(serialize_test(TermInfo::default()), TermInfo::SIZE_IN_BYTES);
+        let term_info = TermInfo {
+            doc_freq: 3_000_000,
+            postings_offset: u64::max_value(),
+            positions_offset: 1u64 << 40,
+            positions_inner_offset: 127u8,
+        };
+        assert_eq!(serialize_test(term_info.clone()), TermInfo::SIZE_IN_BYTES);
+        assert_eq!(term_info.doc_freq(), 3_000_000);
+        assert_eq!(term_info.postings_offset(), u64::max_value());
+        assert_eq!(term_info.positions_offset(), 1u64 << 40);
+        assert_eq!(term_info.positions_inner_offset(), 127u8);
+    }
 }
@@ -2,22 +2,32 @@ use query::Query;
 use query::Weight;
 use query::Scorer;
 use core::SegmentReader;
-use docset::DocSet;
+use docset::{DocSet, SkipResult};
 use Result;
 use Score;
 use DocId;
 use core::Searcher;
+use schema::Schema;
+use fastfield::DeleteBitSet;
+use common::HasLen;
+use std::cmp;
+use std::fmt;
 
 /// Query that matches all of the documents.
 ///
-/// All of the document get the score 1f32.
-#[derive(Debug)]
+/// All of the document get the score 1.
+/// Deleted documents are not matched.
+#[derive(Clone, Debug)]
 pub struct AllQuery;
 
 impl Query for AllQuery {
     fn weight(&self, _: &Searcher, _: bool) -> Result<Box<Weight>> {
         Ok(box AllWeight)
     }
+
+    fn fmt_tree(&self, _schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "All")
+    }
 }
 
 /// Weight associated to the `AllQuery` query.
@@ -25,42 +35,207 @@ pub struct AllWeight;
 
 impl Weight for AllWeight {
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
-        Ok(box AllScorer {
-            started: false,
-            doc: 0u32,
-            max_doc: reader.max_doc(),
-        })
+        Ok(box AllScorer::new(reader.max_doc(), reader.delete_bitset().clone()))
     }
 }
 
 /// Scorer associated to the `AllQuery` query.
+///
+/// It is also used to drive the queries made of `MustNot` clauses only,
+/// so that skipping to a given document does not go through
+/// the documents before it.
 pub struct AllScorer {
     started: bool,
     doc: DocId,
     max_doc: DocId,
+    num_docs: DocId,
+    delete_bitset: DeleteBitSet,
+}
+
+impl AllScorer {
+    /// Creates an `AllScorer` over the documents `[0, max_doc)`
+    /// that are not deleted.
+    pub(crate) fn new(max_doc: DocId, delete_bitset: DeleteBitSet) -> AllScorer {
+        let num_docs = max_doc - delete_bitset.len() as DocId;
+        AllScorer {
+            started: false,
+            doc: 0u32,
+            max_doc,
+            num_docs,
+            delete_bitset,
+        }
+    }
+
+    // Positions the scorer on the first document, greater or equal
+    // to `doc`, that is not deleted.
+    fn go_to_live_doc(&mut self, doc: DocId) -> bool {
+        self.started = true;
+        self.doc = doc;
+        while self.doc < self.max_doc && self.delete_bitset.is_deleted(self.doc) {
+            self.doc += 1u32;
+        }
+        self.doc < self.max_doc
+    }
 }
 
 impl DocSet for AllScorer {
     fn advance(&mut self) -> bool {
-        if self.started {
-            self.doc += 1u32;
+        let next_doc = if self.started { self.doc + 1u32 } else { 0u32 };
+        self.go_to_live_doc(next_doc)
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        // skip is required to advance.
+        let next_doc = if self.started {
+            cmp::max(self.doc + 1u32, target)
+        } else {
+            target
+        };
+        if !self.go_to_live_doc(next_doc) {
+            SkipResult::End
+        } else if self.doc == target {
+            SkipResult::Reached
         } else {
-            self.started = true;
+            SkipResult::OverStep
         }
-        self.doc < self.max_doc
     }
 
     fn doc(&self) -> DocId {
         self.doc
     }
 
+    /// Returns the number of documents that are not deleted,
+    /// including the ones already gone through.
     fn size_hint(&self) -> u32 {
-        self.max_doc
+        self.num_docs
     }
 }
 
 impl Scorer for AllScorer {
     fn score(&mut self) -> Score {
-        1f32
+        1.0
+    }
+
+    fn name(&self) -> &'static str {
+        "AllScorer"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::AllScorer;
+    use DocId;
+    use directory::ReadOnlySource;
+    use docset::{DocSet, SkipResult};
+    use fastfield::DeleteBitSet;
+    use postings::tests::{test_fill_buffer_against_unoptimized, test_skip_against_unoptimized};
+    use query::{Exclude, Intersection, VecDocSet};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn delete_bitset(max_doc: DocId, deleted_docs: &[DocId]) -> DeleteBitSet {
+        let mut bytes = vec![0u8; (max_doc as usize + 7) / 8];
+        for &doc in deleted_docs {
+            bytes[(doc / 8) as usize] |= 1u8 << (doc % 8);
+        }
+        DeleteBitSet::open(ReadOnlySource::from(bytes))
+    }
+
+    /// Counts the calls to `.advance()` and `.skip_next(...)`
+    /// of the wrapped `DocSet`.
+    struct CountingDocSet<TDocSet: DocSet> {
+        docset: TDocSet,
+        num_calls: Rc<Cell<usize>>,
+    }
+
+    impl<TDocSet: DocSet> DocSet for CountingDocSet<TDocSet> {
+        fn advance(&mut self) -> bool {
+            self.num_calls.set(self.num_calls.get() + 1);
+            self.docset.advance()
+        }
+
+        fn skip_next(&mut self, target: DocId) -> SkipResult {
+            self.num_calls.set(self.num_calls.get() + 1);
+            self.docset.skip_next(target)
+        }
+
+        fn doc(&self) -> DocId {
+            self.docset.doc()
+        }
+
+        fn size_hint(&self) -> u32 {
+            self.docset.size_hint()
+        }
+    }
+
+    #[test]
+    fn test_all_scorer_skips_deleted_docs() {
+        let deleted_docs: Vec<DocId> = (10..200).chain(vec![0, 998, 999]).collect();
+        let mut all_scorer = AllScorer::new(1_000, delete_bitset(1_000, &deleted_docs));
+        assert_eq!(all_scorer.size_hint(), 1_000 - deleted_docs.len() as u32);
+        assert!(all_scorer.advance());
+        assert_eq!(all_scorer.doc(), 1);
+        assert_eq!(all_scorer.skip_next(9), SkipResult::Reached);
+        assert_eq!(all_scorer.skip_next(20), SkipResult::OverStep);
+        assert_eq!(all_scorer.doc(), 200);
+        assert_eq!(all_scorer.skip_next(150), SkipResult::OverStep);
+        assert_eq!(all_scorer.doc(), 201);
+        assert_eq!(all_scorer.skip_next(997), SkipResult::Reached);
+        assert!(!all_scorer.advance());
+
+        let mut all_scorer = AllScorer::new(1_000, delete_bitset(1_000, &deleted_docs));
+        assert_eq!(all_scorer.skip_next(998), SkipResult::End);
+        assert!(!all_scorer.advance());
+    }
+
+    #[test]
+    fn test_all_scorer_against_unoptimized() {
+        let deleted_docs: Vec<DocId> = (0..1_000u32)
+            .filter(|&doc| doc % 7 == 0 || (doc >= 300 && doc < 400))
+            .collect();
+        let targets = vec![0, 1, 2, 7, 8, 299, 300, 350, 400, 401, 998, 999, 1_000, 2_000];
+        test_skip_against_unoptimized(
+            || box AllScorer::new(1_000, delete_bitset(1_000, &deleted_docs)),
+            targets.clone(),
+        );
+        test_fill_buffer_against_unoptimized(
+            || box AllScorer::new(1_000, delete_bitset(1_000, &deleted_docs)),
+            targets.clone(),
+        );
+        test_skip_against_unoptimized(
+            || box AllScorer::new(1_000, DeleteBitSet::empty()),
+            targets.clone(),
+        );
+        test_skip_against_unoptimized(|| box AllScorer::new(0, DeleteBitSet::empty()), targets);
+    }
+
+    #[test]
+    fn test_must_not_anchored_intersection_skips() {
+        let max_doc = 1_000_000u32;
+        let deleted_docs: Vec<DocId> = (0..max_doc).filter(|doc| doc % 1_000 == 3).collect();
+        let sparse_docs: Vec<DocId> = (0..max_doc).filter(|doc| doc % 100_000 < 5).collect();
+        let excluded_docs: Vec<DocId> = (0..max_doc).filter(|doc| doc % 100_000 == 1).collect();
+        let num_calls = Rc::new(Cell::new(0));
+        let all_scorer = CountingDocSet {
+            docset: AllScorer::new(max_doc, delete_bitset(max_doc, &deleted_docs)),
+            num_calls: num_calls.clone(),
+        };
+        // `-excluded`, intersected with a sparse `DocSet`.
+        let must_not_docset = Exclude::new(all_scorer, VecDocSet::from(excluded_docs));
+        let mut intersection: Intersection<Box<DocSet>> = Intersection::from(vec![
+            box VecDocSet::from(sparse_docs.clone()) as Box<DocSet>,
+            box must_not_docset as Box<DocSet>,
+        ]);
+        let mut docs = Vec::new();
+        while intersection.advance() {
+            docs.push(intersection.doc());
+        }
+        let expected_docs: Vec<DocId> = sparse_docs
+            .into_iter()
+            .filter(|doc| doc % 100_000 != 1 && doc % 1_000 != 3)
+            .collect();
+        assert_eq!(docs, expected_docs);
+        assert!(num_calls.get() <= 3 * expected_docs.len() + 10);
     }
 }
@@ -1,14 +1,13 @@
 use common::{BitSet, TinySet};
 use DocId;
 use docset::{DocSet, SkipResult};
-use std::cmp::Ordering;
 
 /// A `BitSetDocSet` makes it possible to iterate through a bitset as if it was a `DocSet`.
 ///
 /// # Implementation detail
 ///
-/// Skipping is relatively fast here as we can directly point to the
-/// right tiny bitset bucket.
+/// Skipping does not go through the documents: it directly points to the
+/// right tiny bitset bucket, and masks the documents lower than the target.
 ///
 /// TODO: Consider implementing a `BitTreeSet` in order to advance faster
 /// when the bitset is sparse
@@ -63,43 +62,54 @@ impl DocSet for BitSetDocSet {
         if !self.advance() {
             return SkipResult::End;
         }
-        let target_bucket = target / 64u32;
-
-        // Mask for all of the bits greater or equal
-        // to our target document.
-        match target_bucket.cmp(&self.cursor_bucket) {
-            Ordering::Greater => {
+        if self.doc() < target {
+            if target >= self.docs.max_value() {
+                // consumes the `DocSet`.
+                self.cursor_bucket = (self.docs.max_value() - 1u32) / 64u32;
+                self.cursor_tinybitset = TinySet::empty();
+                return SkipResult::End;
+            }
+            let target_bucket = target / 64u32;
+            if target_bucket > self.cursor_bucket {
                 self.go_to_bucket(target_bucket);
-                let greater_filter: TinySet = TinySet::range_greater_or_equal(target);
-                self.cursor_tinybitset = self.cursor_tinybitset.intersect(greater_filter);
-                if !self.advance() {
-                    SkipResult::End
-                } else if self.doc() == target {
-                    SkipResult::Reached
+            }
+            // Mask for all of the bits greater or equal
+            // to our target document.
+            let greater_filter: TinySet = TinySet::range_greater_or_equal(target);
+            self.cursor_tinybitset = self.cursor_tinybitset.intersect(greater_filter);
+            if !self.advance() {
+                return SkipResult::End;
+            }
+        }
+        if self.doc() == target {
+            SkipResult::Reached
+        } else {
+            debug_assert!(self.doc() > target);
+            SkipResult::OverStep
+        }
+    }
+
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        let mut num_docs = 0;
+        loop {
+            // empty the current tiny bitset first...
+            while num_docs < buffer.len() {
+                if let Some(lower) = self.cursor_tinybitset.pop_lowest() {
+                    self.doc = (self.cursor_bucket * 64u32) | lower;
+                    buffer[num_docs] = self.doc;
+                    num_docs += 1;
                 } else {
-                    debug_assert!(self.doc() > target);
-                    SkipResult::OverStep
+                    break;
                 }
             }
-            Ordering::Equal => loop {
-                match self.doc().cmp(&target) {
-                    Ordering::Less => {
-                        if !self.advance() {
-                            return SkipResult::End;
-                        }
-                    }
-                    Ordering::Equal => {
-                        return SkipResult::Reached;
-                    }
-                    Ordering::Greater => {
-                        debug_assert!(self.doc() > target);
-                        return SkipResult::OverStep;
-                    }
-                }
-            },
-            Ordering::Less => {
-                debug_assert!(self.doc() > target);
-                SkipResult::OverStep
+            if num_docs == buffer.len() {
+                return num_docs;
+            }
+            // ... and jump to the next non-empty one.
+            if let Some(cursor_bucket) = self.docs.first_non_empty_bucket(self.cursor_bucket + 1) {
+                self.go_to_bucket(cursor_bucket);
+            } else {
+                return num_docs;
             }
         }
     }
@@ -109,10 +119,8 @@ impl DocSet for BitSetDocSet {
         self.doc
     }
 
-    /// Returns half of the `max_doc`
-    /// This is quite a terrible heuristic,
-    /// but we don't have access to any better
-    /// value.
+    /// Returns the number of documents of the bitset,
+    /// including the ones already gone through.
     fn size_hint(&self) -> u32 {
         self.docs.len() as u32
     }
@@ -124,6 +132,7 @@ mod tests {
     use common::BitSet;
     use docset::{DocSet, SkipResult};
     use super::BitSetDocSet;
+    use postings::tests::{test_fill_buffer_against_unoptimized, test_skip_against_unoptimized};
     extern crate test;
     use tests;
 
@@ -219,6 +228,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_docbitset_skip_against_unoptimized() {
+        let docs: Vec<DocId> = (0..2_000u32).filter(|doc| doc % 5 < 2 || doc % 61 == 0).collect();
+        test_skip_against_unoptimized(
+            || box create_docbitset(&docs, 2_000),
+            vec![0, 1, 2, 63, 64, 65, 500, 1_024, 1_025, 1_999, 2_000, 5_000],
+        );
+        test_skip_against_unoptimized(
+            || box create_docbitset(&[63, 64, 1_999], 2_000),
+            vec![0, 63, 64, 65, 1_998, 1_999, 2_000],
+        );
+        test_skip_against_unoptimized(|| box create_docbitset(&[], 2_000), vec![0, 10]);
+    }
+
+    #[test]
+    fn test_docbitset_skip_beyond_max_value() {
+        let mut docset = create_docbitset(&[1, 5], 100);
+        assert!(docset.advance());
+        assert_eq!(docset.skip_next(100), SkipResult::End);
+        assert!(!docset.advance());
+    }
+
+    #[test]
+    fn test_docbitset_fill_buffer() {
+        let docs: Vec<DocId> = (0..2_000u32).filter(|doc| doc % 5 < 2 || doc % 61 == 0).collect();
+        test_fill_buffer_against_unoptimized(
+            || box create_docbitset(&docs, 10_000),
+            vec![1, 63, 64, 500, 1_024, 1_025, 1_998],
+        );
+        test_fill_buffer_against_unoptimized(
+            || box create_docbitset(&[63, 64, 5112], 10_000),
+            vec![64],
+        );
+        test_fill_buffer_against_unoptimized(|| box create_docbitset(&[], 10_000), vec![]);
+    }
+
     #[bench]
     fn bench_bitset_1pct_insert(b: &mut test::Bencher) {
         use tests;
@@ -7,6 +7,10 @@ use schema::Term;
 use query::TermQuery;
 use schema::IndexRecordOption;
 use query::Occur;
+use std::result;
+use query::QueryValidationError;
+use schema::Schema;
+use std::fmt;
 
 /// The boolean query combines a set of queries
 ///
@@ -18,14 +22,32 @@ use query::Occur;
 /// `MustNot` occurence.
 /// * match at least one of the subqueries that is not
 /// a `MustNot` occurence.
-#[derive(Debug)]
+/// * match at least `minimum_should_match` of the sub queries
+/// associated with the `Should` occurence, if it is set.
+/// See [`set_minimum_should_match`](#method.set_minimum_should_match).
+///
+/// A boolean query that only contains `MustNot` subqueries
+/// matches all of the documents of the segment that are
+/// not deleted, except those matched by the `MustNot` subqueries.
+///
+/// When some of the `Must` subqueries support two-phase iteration
+/// (e.g. phrase queries), their expensive checks only run on the documents
+/// matching the approximations of all of the `Must` subqueries.
+/// See [`TwoPhase`](./trait.TwoPhase.html).
+#[derive(Clone, Debug)]
 pub struct BooleanQuery {
     subqueries: Vec<(Occur, Box<Query>)>,
+    two_phase_iteration: bool,
+    minimum_should_match: usize,
 }
 
 impl From<Vec<(Occur, Box<Query>)>> for BooleanQuery {
     fn from(subqueries: Vec<(Occur, Box<Query>)>) -> BooleanQuery {
-        BooleanQuery { subqueries }
+        BooleanQuery {
+            subqueries,
+            two_phase_iteration: true,
+            minimum_should_match: 0,
+        }
     }
 }
 
@@ -37,11 +59,41 @@ impl Query for BooleanQuery {
                 Ok((*occur, subquery.weight(searcher, scoring_enabled)?))
             })
             .collect::<Result<_>>()?;
-        Ok(box BooleanWeight::new(sub_weights, scoring_enabled))
+        Ok(box BooleanWeight::new(
+            sub_weights,
+            scoring_enabled,
+            self.two_phase_iteration,
+            self.minimum_should_match,
+        ))
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        for &(_, ref subquery) in &self.subqueries {
+            subquery.validate(searcher)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.minimum_should_match > 0 {
+            writeln!(f, "Boolean(minimum_should_match={})", self.minimum_should_match)?;
+        } else {
+            writeln!(f, "Boolean")?;
+        }
+        for &(ref occur, ref subquery) in &self.subqueries {
+            write!(f, "{:indent$}{:?} ", "", occur, indent = 2 * (depth + 1))?;
+            subquery.fmt_tree(schema, depth + 1, f)?;
+        }
+        Ok(())
     }
 }
 
 impl BooleanQuery {
+    /// Returns the subqueries, with their occurence.
+    pub fn clauses(&self) -> &[(Occur, Box<Query>)] {
+        &self.subqueries[..]
+    }
+
     /// Helper method to create a boolean query matching a given list of terms.
     /// The resulting query is a disjunction of the terms.
     pub fn new_multiterms_query(terms: Vec<Term>) -> BooleanQuery {
@@ -54,4 +106,32 @@ impl BooleanQuery {
             .collect();
         BooleanQuery::from(occur_term_queries)
     }
+
+    /// Enables or disables two-phase iteration over the `Must` subqueries.
+    ///
+    /// It is enabled by default, and does not change the documents
+    /// matched by the query.
+    pub fn set_two_phase_iteration(&mut self, two_phase_iteration: bool) {
+        self.two_phase_iteration = two_phase_iteration;
+    }
+
+    /// Sets the minimum number of `Should` subqueries a document
+    /// needs to match to be matched by the query.
+    ///
+    /// It is `0` by default, in which case the `Should` subqueries
+    /// are only required when there is no `Must` subquery.
+    /// Otherwise, they are required even in the presence of `Must` subqueries.
+    ///
+    /// A query requiring more `Should` subqueries than it has
+    /// does not match any document.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match;
+    }
+
+    /// Returns the minimum number of `Should` subqueries a document needs to match.
+    ///
+    /// See [`set_minimum_should_match`](#method.set_minimum_should_match).
+    pub fn minimum_should_match(&self) -> usize {
+        self.minimum_should_match
+    }
 }
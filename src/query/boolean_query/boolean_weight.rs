@@ -1,19 +1,33 @@
 use query::Weight;
 use core::SegmentReader;
-use query::{Intersection, Union};
+use core::SegmentMeta;
+use query::{Intersection, TwoPhaseIntersection, Union};
 use std::collections::HashMap;
 use query::EmptyScorer;
+use query::AllWeight;
 use query::Scorer;
+use query::QueryContext;
+use query::ScorerDiag;
+use query::Explanation;
+use query::explanation::does_not_match;
+use docset::{DocSet, SkipResult};
 use downcast::Downcast;
 use query::term_query::TermScorer;
 use std::borrow::Borrow;
 use query::Exclude;
 use query::Occur;
 use query::RequiredOptionalScorer;
+use query::minimum_match_union::MinimumMatchUnion;
 use query::score_combiner::{DoNothingCombiner, ScoreCombiner, SumWithCoordsCombiner};
 use Result;
+use DocId;
 
-fn scorer_union<TScoreCombiner>(scorers: Vec<Box<Scorer>>) -> Box<Scorer>
+// `max_doc` is the `max_doc` of the segment, that bounds the size hint of the union.
+fn scorer_union<TScoreCombiner>(
+    scorers: Vec<Box<Scorer>>,
+    max_doc: DocId,
+    context: &QueryContext,
+) -> Box<Scorer>
 where
     TScoreCombiner: ScoreCombiner,
 {
@@ -30,51 +44,140 @@ where
                 .into_iter()
                 .map(|scorer| *Downcast::<TermScorer>::downcast(scorer).unwrap())
                 .collect();
-            let scorer: Box<Scorer> = box Union::<TermScorer, TScoreCombiner>::from(scorers);
+            let mut union = Union::<TermScorer, TScoreCombiner>::with_context(scorers, context);
+            union.set_max_doc(max_doc);
+            let scorer: Box<Scorer> = box union;
             scorer
         } else {
-            let scorer: Box<Scorer> = box Union::<_, TScoreCombiner>::from(scorers);
+            let mut union = Union::<_, TScoreCombiner>::with_context(scorers, context);
+            union.set_max_doc(max_doc);
+            let scorer: Box<Scorer> = box union;
             scorer
         }
     }
 }
 
+// Returns the scorer of a subquery, recording its
+// diagnostics into `diag_opt` if any.
+fn sub_scorer(
+    weight: &Weight,
+    reader: &SegmentReader,
+    context: &mut QueryContext,
+    diag_opt: Option<&mut ScorerDiag>,
+) -> Result<Box<Scorer>> {
+    match diag_opt {
+        Some(diag) => {
+            let (scorer, sub_diag) = weight.scorer_with_diag(reader)?;
+            diag.merge_skip_reasons(sub_diag);
+            Ok(scorer)
+        }
+        None => weight.scorer_with_context(reader, context),
+    }
+}
+
 pub struct BooleanWeight {
     weights: Vec<(Occur, Box<Weight>)>,
     scoring_enabled: bool,
+    two_phase_iteration: bool,
+    minimum_should_match: usize,
 }
 
 impl BooleanWeight {
-    pub fn new(weights: Vec<(Occur, Box<Weight>)>, scoring_enabled: bool) -> BooleanWeight {
+    pub fn new(
+        weights: Vec<(Occur, Box<Weight>)>,
+        scoring_enabled: bool,
+        two_phase_iteration: bool,
+        minimum_should_match: usize,
+    ) -> BooleanWeight {
         BooleanWeight {
             weights,
             scoring_enabled,
+            two_phase_iteration,
+            minimum_should_match,
+        }
+    }
+
+    fn num_should_clauses(&self) -> usize {
+        self.weights
+            .iter()
+            .filter(|&&(occur, _)| occur == Occur::Should)
+            .count()
+    }
+
+    fn scorer_with_diag_opt(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+        diag_opt: Option<&mut ScorerDiag>,
+    ) -> Result<Box<Scorer>> {
+        if self.weights.is_empty() || self.minimum_should_match > self.num_should_clauses() {
+            Ok(box EmptyScorer)
+        } else if self.weights.len() == 1 {
+            let &(occur, ref weight) = &self.weights[0];
+            if occur == Occur::MustNot {
+                let excluded_scorer = weight.scorer_with_context(reader, context)?;
+                Ok(box Exclude::new(AllWeight.scorer(reader)?, excluded_scorer))
+            } else {
+                sub_scorer(weight.as_ref(), reader, context, diag_opt)
+            }
+        } else if self.scoring_enabled {
+            self.complex_scorer::<SumWithCoordsCombiner>(reader, context, diag_opt)
+        } else {
+            self.complex_scorer::<DoNothingCombiner>(reader, context, diag_opt)
         }
     }
 
     fn complex_scorer<TScoreCombiner: ScoreCombiner>(
         &self,
         reader: &SegmentReader,
+        context: &mut QueryContext,
+        mut diag_opt: Option<&mut ScorerDiag>,
     ) -> Result<Box<Scorer>> {
         let mut per_occur_scorers: HashMap<Occur, Vec<Box<Scorer>>> = HashMap::new();
         for &(ref occur, ref subweight) in &self.weights {
-            let sub_scorer: Box<Scorer> = subweight.scorer(reader)?;
+            // subqueries that cannot match do not need to open anything.
+            let sub_scorer: Box<Scorer> = if subweight.can_match(reader.segment_meta()) {
+                // only the positive clauses explain missing hits.
+                let sub_diag_opt = if *occur == Occur::MustNot {
+                    None
+                } else {
+                    diag_opt.as_mut().map(|diag| &mut **diag)
+                };
+                sub_scorer(subweight.as_ref(), reader, context, sub_diag_opt)?
+            } else {
+                box EmptyScorer
+            };
             per_occur_scorers
                 .entry(*occur)
                 .or_insert_with(Vec::new)
                 .push(sub_scorer);
         }
 
-        let should_scorer_opt: Option<Box<Scorer>> = per_occur_scorers
-            .remove(&Occur::Should)
-            .map(scorer_union::<TScoreCombiner>);
+        let minimum_should_match = self.minimum_should_match;
+        let max_doc = reader.max_doc();
+        let should_scorer_opt: Option<Box<Scorer>> =
+            per_occur_scorers.remove(&Occur::Should).map(|scorers| {
+                if minimum_should_match > 1 {
+                    let mut union = MinimumMatchUnion::<_, TScoreCombiner>::with_context(
+                        scorers,
+                        minimum_should_match,
+                        context,
+                    );
+                    union.set_max_doc(max_doc);
+                    let scorer: Box<Scorer> = box union;
+                    scorer
+                } else {
+                    scorer_union::<TScoreCombiner>(scorers, max_doc, context)
+                }
+            });
 
         let exclude_scorer_opt: Option<Box<Scorer>> = per_occur_scorers
             .remove(&Occur::MustNot)
-            .map(scorer_union::<TScoreCombiner>);
+            .map(|scorers| scorer_union::<TScoreCombiner>(scorers, max_doc, context));
 
+        let two_phase_iteration = self.two_phase_iteration;
         let must_scorer_opt: Option<Box<Scorer>> =
-            per_occur_scorers.remove(&Occur::Must).map(|scorers| {
+            per_occur_scorers.remove(&Occur::Must).map(|mut scorers| {
                 if scorers.len() == 1 {
                     scorers.into_iter().next().unwrap()
                 } else {
@@ -89,6 +192,11 @@ impl BooleanWeight {
                             .collect();
                         let scorer: Box<Scorer> = box Intersection::from(scorers);
                         scorer
+                    } else if two_phase_iteration
+                        && scorers.iter_mut().any(|scorer| scorer.two_phase().is_some())
+                    {
+                        let scorer: Box<Scorer> = box TwoPhaseIntersection::from(scorers);
+                        scorer
                     } else {
                         let scorer: Box<Scorer> = box Intersection::from(scorers);
                         scorer
@@ -98,7 +206,10 @@ impl BooleanWeight {
 
         let positive_scorer: Box<Scorer> = match (should_scorer_opt, must_scorer_opt) {
             (Some(should_scorer), Some(must_scorer)) => {
-                if self.scoring_enabled {
+                if minimum_should_match > 0 {
+                    // the `Should` clauses are required as well.
+                    box Intersection::from(vec![must_scorer, should_scorer])
+                } else if self.scoring_enabled {
                     box RequiredOptionalScorer::<_, _, TScoreCombiner>::new(
                         must_scorer,
                         should_scorer,
@@ -110,7 +221,12 @@ impl BooleanWeight {
             (None, Some(must_scorer)) => must_scorer,
             (Some(should_scorer), None) => should_scorer,
             (None, None) => {
-                return Ok(box EmptyScorer);
+                if exclude_scorer_opt.is_none() {
+                    return Ok(box EmptyScorer);
+                }
+                // A query made of `MustNot` clauses only matches
+                // all of the documents except the excluded ones.
+                AllWeight.scorer(reader)?
             }
         };
 
@@ -124,19 +240,74 @@ impl BooleanWeight {
 
 impl Weight for BooleanWeight {
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
-        if self.weights.is_empty() {
-            Ok(box EmptyScorer)
-        } else if self.weights.len() == 1 {
-            let &(occur, ref weight) = &self.weights[0];
+        self.scorer_with_context(reader, &mut QueryContext::new())
+    }
+
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        self.scorer_with_diag_opt(reader, context, None)
+    }
+
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let mut diag = ScorerDiag::default();
+        let scorer = self.scorer_with_diag_opt(reader, &mut QueryContext::new(), Some(&mut diag))?;
+        diag.set_estimated_num_candidates(scorer.size_hint());
+        Ok((scorer, diag))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.scorer(reader)?;
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new(
+            "Boolean, sum of the scores of the matching Must and Should clauses",
+            scorer.score(),
+        );
+        for &(occur, ref weight) in &self.weights {
+            // the document matches none of the `MustNot` clauses.
             if occur == Occur::MustNot {
-                Ok(box EmptyScorer)
-            } else {
-                weight.scorer(reader)
+                continue;
             }
-        } else if self.scoring_enabled {
-            self.complex_scorer::<SumWithCoordsCombiner>(reader)
+            if weight.scorer(reader)?.skip_next(doc) == SkipResult::Reached {
+                explanation.add_detail(weight.explain(reader, doc)?);
+            }
+        }
+        Ok(explanation)
+    }
+
+    fn can_match(&self, segment_meta: &SegmentMeta) -> bool {
+        if self.minimum_should_match > self.num_should_clauses() {
+            return false;
+        }
+        let mut has_must = false;
+        let mut has_should = false;
+        let mut should_can_match = false;
+        for &(occur, ref weight) in &self.weights {
+            match occur {
+                Occur::Must => {
+                    if !weight.can_match(segment_meta) {
+                        return false;
+                    }
+                    has_must = true;
+                }
+                Occur::Should => {
+                    has_should = true;
+                    should_can_match |= weight.can_match(segment_meta);
+                }
+                Occur::MustNot => {}
+            }
+        }
+        if self.minimum_should_match > 0 {
+            should_can_match
+        } else if has_must || !has_should {
+            // `MustNot` clauses alone match all of the other documents.
+            !self.weights.is_empty()
         } else {
-            self.complex_scorer::<DoNothingCombiner>(reader)
+            should_can_match
         }
     }
 }
@@ -20,6 +20,10 @@ mod tests {
     use query::QueryParser;
     use query::RequiredOptionalScorer;
     use query::score_combiner::SumWithCoordsCombiner;
+    use query::QueryContext;
+    use indexer::NoMergePolicy;
+    use DocSet;
+    use {DocId, Score};
 
     fn aux_test_helper() -> (Index, Field) {
         let mut schema_builder = SchemaBuilder::default();
@@ -31,23 +35,23 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field => "a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field => "a c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field => "b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field => "a b c d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 let doc = doc!(text_field => "d");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -63,6 +67,57 @@ mod tests {
         assert_eq!(query.count(&*index.searcher()).unwrap(), 3);
     }
 
+    #[test]
+    pub fn test_boolean_two_phase_iteration() {
+        use query::{PhraseQuery, TwoPhaseIntersection};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for text in &["a b c", "b a c", "a c b", "a b", "c a b c", "a x b c", "c"] {
+                index_writer.add_document(doc!(text_field => *text)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = |text: &str| Term::from_field_text(text_field, text);
+        let make_query = |two_phase_iteration: bool| {
+            let phrase_query: Box<Query> = box PhraseQuery::from(vec![term("a"), term("b")]);
+            let term_query: Box<Query> =
+                box TermQuery::new(term("c"), IndexRecordOption::WithFreqs);
+            let mut query =
+                BooleanQuery::from(vec![(Occur::Must, phrase_query), (Occur::Must, term_query)]);
+            query.set_two_phase_iteration(two_phase_iteration);
+            query
+        };
+        let scored_docs = |query: &BooleanQuery| {
+            let weight = query.weight(&*searcher, true).unwrap();
+            let mut scorer = weight.scorer(searcher.segment_reader(0)).unwrap();
+            let mut scored_docs = vec![];
+            while scorer.advance() {
+                scored_docs.push((scorer.doc(), scorer.score()));
+            }
+            scored_docs
+        };
+        let two_phase_query = make_query(true);
+        {
+            let weight = two_phase_query.weight(&*searcher, true).unwrap();
+            let scorer = weight.scorer(searcher.segment_reader(0)).unwrap();
+            assert!(Downcast::<TwoPhaseIntersection>::is_type(&*scorer));
+        }
+        let two_phase_scored_docs = scored_docs(&two_phase_query);
+        assert_eq!(
+            two_phase_scored_docs
+                .iter()
+                .map(|&(doc, _)| doc)
+                .collect::<Vec<_>>(),
+            vec![0, 4]
+        );
+        assert_eq!(two_phase_scored_docs, scored_docs(&make_query(false)));
+    }
+
     #[test]
     pub fn test_boolean_single_must_clause() {
         let (index, text_field) = aux_test_helper();
@@ -114,6 +169,47 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_boolean_scorer_with_context() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment_texts in &[["a b", "c"], ["a", "b d"], ["c d", "a c"]] {
+                for &text in segment_texts {
+                    index_writer.add_document(doc!(text_field => text)).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let query = query_parser.parse_query("(a d) -b").unwrap();
+        let weight = query.weight(&*searcher, true).unwrap();
+        let mut context = QueryContext::new();
+        for segment_reader in searcher.segment_readers() {
+            let mut expected_scorer = weight.scorer(segment_reader).unwrap();
+            let mut scorer = weight
+                .scorer_with_context(segment_reader, &mut context)
+                .unwrap();
+            while expected_scorer.advance() {
+                assert!(scorer.advance());
+                assert_eq!(scorer.doc(), expected_scorer.doc());
+                assert_eq!(scorer.score(), expected_scorer.score());
+            }
+            assert!(!scorer.advance());
+        }
+        // the bitsets and the scores of the union are only allocated for
+        // the first segment, and the postings of the terms `a`, `b` and `d`
+        // for the segments in which more of them are found than before.
+        assert_eq!(context.num_allocated_buffers(), 2 + 3);
+        assert_eq!(context.num_reused_buffers(), 4 + 4);
+    }
+
     #[test]
     pub fn test_boolean_query() {
         let (index, text_field) = aux_test_helper();
@@ -166,7 +262,253 @@ mod tests {
         }
         {
             let boolean_query = BooleanQuery::from(vec![(Occur::MustNot, make_term_query("d"))]);
-            assert_eq!(matching_docs(&boolean_query), Vec::<u32>::new());
+            assert_eq!(matching_docs(&boolean_query), vec![0, 1, 2]);
         }
     }
+
+    #[test]
+    pub fn test_boolean_query_negation() {
+        let (index, text_field) = aux_test_helper();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let matching_docs = |boolean_query: &Query| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let mut test_collector = TestCollector::default();
+            searcher.search(boolean_query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        let pure_negation = || {
+            BooleanQuery::from(vec![
+                (Occur::MustNot, make_term_query("a")),
+                (Occur::MustNot, make_term_query("d")),
+            ])
+        };
+        let double_negation = || {
+            let negation: Box<Query> = box BooleanQuery::from(vec![
+                (Occur::MustNot, make_term_query("a")),
+            ]);
+            BooleanQuery::from(vec![(Occur::MustNot, negation)])
+        };
+        let must_and_negation = || {
+            BooleanQuery::from(vec![
+                (Occur::Must, make_term_query("c")),
+                (Occur::MustNot, make_term_query("a")),
+            ])
+        };
+        assert_eq!(matching_docs(&pure_negation()), vec![2]);
+        assert_eq!(matching_docs(&double_negation()), vec![0, 1, 3]);
+        assert_eq!(matching_docs(&must_and_negation()), vec![2]);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // deletes the documents 0, 2 and 3.
+            index_writer.delete_term(Term::from_field_text(text_field, "b"));
+            index_writer.commit().unwrap();
+        }
+        assert!(matching_docs(&pure_negation()).is_empty());
+        assert_eq!(matching_docs(&double_negation()), vec![1]);
+        assert!(matching_docs(&must_and_negation()).is_empty());
+    }
+
+    #[test]
+    pub fn test_boolean_query_minimum_should_match() {
+        let (index, text_field) = aux_test_helper();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let make_query = |clauses: &[(Occur, &str)], minimum_should_match: usize| {
+            let mut boolean_query = BooleanQuery::from(
+                clauses
+                    .iter()
+                    .map(|&(occur, text)| (occur, make_term_query(text)))
+                    .collect::<Vec<_>>(),
+            );
+            boolean_query.set_minimum_should_match(minimum_should_match);
+            boolean_query
+        };
+        let searcher = index.searcher();
+        let matching_docs = |query: &Query| {
+            let mut test_collector = TestCollector::default();
+            searcher.search(query, &mut test_collector).unwrap();
+            test_collector.docs()
+        };
+        let should_abcd = [
+            (Occur::Should, "a"),
+            (Occur::Should, "b"),
+            (Occur::Should, "c"),
+            (Occur::Should, "d"),
+        ];
+        assert_eq!(matching_docs(&make_query(&should_abcd, 0)), vec![0, 1, 2, 3, 4]);
+        assert_eq!(matching_docs(&make_query(&should_abcd, 1)), vec![0, 1, 2, 3, 4]);
+        assert_eq!(matching_docs(&make_query(&should_abcd, 2)), vec![0, 1, 2, 3]);
+        assert_eq!(matching_docs(&make_query(&should_abcd, 3)), vec![0, 3]);
+        assert_eq!(matching_docs(&make_query(&should_abcd, 4)), vec![3]);
+        assert!(matching_docs(&make_query(&should_abcd, 5)).is_empty());
+        assert_eq!(make_query(&should_abcd, 3).count(&*searcher).unwrap(), 2);
+        assert_eq!(make_query(&should_abcd, 5).count(&*searcher).unwrap(), 0);
+
+        let must_d_should_ab = [
+            (Occur::Must, "d"),
+            (Occur::Should, "a"),
+            (Occur::Should, "b"),
+        ];
+        assert_eq!(matching_docs(&make_query(&must_d_should_ab, 0)), vec![3, 4]);
+        assert_eq!(matching_docs(&make_query(&must_d_should_ab, 1)), vec![3]);
+        assert_eq!(matching_docs(&make_query(&must_d_should_ab, 2)), vec![3]);
+        assert!(matching_docs(&make_query(&must_d_should_ab, 3)).is_empty());
+        assert_eq!(make_query(&must_d_should_ab, 0).count(&*searcher).unwrap(), 2);
+        assert_eq!(make_query(&must_d_should_ab, 1).count(&*searcher).unwrap(), 1);
+
+        let should_ab_must_not_c = [
+            (Occur::Should, "a"),
+            (Occur::Should, "b"),
+            (Occur::MustNot, "c"),
+        ];
+        assert!(matching_docs(&make_query(&should_ab_must_not_c, 2)).is_empty());
+        assert_eq!(matching_docs(&make_query(&[(Occur::Must, "c")], 0)), vec![0, 1, 2, 3]);
+        assert!(matching_docs(&make_query(&[(Occur::Must, "c")], 1)).is_empty());
+    }
+
+    #[test]
+    pub fn test_boolean_query_minimum_should_match_scores() {
+        let (index, text_field) = aux_test_helper();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let searcher = index.searcher();
+        let score_docs = |query: &Query| {
+            let mut scorer = query
+                .weight(&*searcher, true)
+                .unwrap()
+                .scorer(searcher.segment_reader(0u32))
+                .unwrap();
+            let mut scored_docs = vec![];
+            while scorer.advance() {
+                scored_docs.push((scorer.doc(), scorer.score()));
+            }
+            scored_docs
+        };
+        let clauses = || {
+            vec![
+                (Occur::Should, make_term_query("a")),
+                (Occur::Should, make_term_query("b")),
+                (Occur::Should, make_term_query("d")),
+            ]
+        };
+        let union_scores = score_docs(&BooleanQuery::from(clauses()));
+        let mut boolean_query = BooleanQuery::from(clauses());
+        boolean_query.set_minimum_should_match(2);
+        // the documents keep the score they have without the constraint.
+        let expected_scores: Vec<(DocId, Score)> = union_scores
+            .into_iter()
+            .filter(|&(doc, _)| doc == 0 || doc == 3)
+            .collect();
+        assert_eq!(score_docs(&boolean_query), expected_scores);
+    }
+
+    #[test]
+    pub fn test_boolean_query_explain() {
+        use collector::TopCollector;
+        use DocAddress;
+        let (index, text_field) = aux_test_helper();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, make_term_query("c")),
+            (Occur::Should, make_term_query("a")),
+            (Occur::Should, make_term_query("b")),
+            (Occur::MustNot, make_term_query("d")),
+        ]);
+        let searcher = index.searcher();
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&boolean_query, &mut top_collector).unwrap();
+        let score_docs = top_collector.score_docs();
+        assert_eq!(score_docs.len(), 3);
+        for (score, doc_address) in score_docs {
+            let explanation = boolean_query.explain(&*searcher, doc_address).unwrap();
+            assert_eq!(explanation.value(), score);
+        }
+        // "a b c" matches all of the clauses but the `MustNot` one.
+        let explanation = boolean_query.explain(&*searcher, DocAddress(0, 0)).unwrap();
+        let clause_descriptions: Vec<&str> = explanation
+            .details()
+            .iter()
+            .map(|detail| detail.description())
+            .collect();
+        assert_eq!(clause_descriptions.len(), 3);
+        assert!(clause_descriptions[0].starts_with("Term(text:\"c\")"));
+        assert!(clause_descriptions[1].starts_with("Term(text:\"a\")"));
+        assert!(clause_descriptions[2].starts_with("Term(text:\"b\")"));
+        let explanation = boolean_query.explain(&*searcher, DocAddress(0, 2)).unwrap();
+        assert_eq!(explanation.details().len(), 2);
+        assert!(explanation.to_pretty_json().contains("Term(text:\\\"b\\\")"));
+        assert!(boolean_query.explain(&*searcher, DocAddress(0, 3)).is_err());
+        assert!(boolean_query.explain(&*searcher, DocAddress(0, 4)).is_err());
+    }
+
+    #[test]
+    pub fn test_boolean_query_display() {
+        use query::{AllQuery, PhraseQuery, QueryDisplay, RangeQuery};
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let year_field = schema_builder.add_u64_field("year", INT_INDEXED);
+        let schema = schema_builder.build();
+        let term_query: Box<Query> = box TermQuery::new(
+            Term::from_field_text(title_field, "mice"),
+            IndexRecordOption::Basic,
+        );
+        let phrase_query: Box<Query> = box PhraseQuery::from(vec![
+            Term::from_field_text(title_field, "old"),
+            Term::from_field_text(title_field, "man"),
+        ]);
+        let all_query: Box<Query> = box AllQuery;
+        let range_query: Box<Query> = box RangeQuery::new_u64(year_field, 1960..1970);
+        let nested_query: Box<Query> = box BooleanQuery::from(vec![
+            (Occur::Should, all_query),
+            (Occur::MustNot, range_query),
+        ]);
+        let query = BooleanQuery::from(vec![
+            (Occur::Must, term_query),
+            (Occur::Should, phrase_query),
+            (Occur::Must, nested_query),
+        ]);
+        assert_eq!(
+            QueryDisplay::new(&query, &schema).to_string(),
+            "Boolean\n\
+             \x20 Must Term(title:\"mice\")\n\
+             \x20 Should Phrase(title:\"old\" \"man\")\n\
+             \x20 Must Boolean\n\
+             \x20   Should All\n\
+             \x20   MustNot Range(year:[1960..1970))\n"
+        );
+        let mut query = query;
+        query.set_minimum_should_match(1);
+        assert!(
+            QueryDisplay::new(&query, &schema)
+                .to_string()
+                .starts_with("Boolean(minimum_should_match=1)\n")
+        );
+    }
 }
@@ -0,0 +1,293 @@
+use query::{Explanation, Query, QueryContext, Scorer, ScorerDiag, TwoPhase, Weight};
+use core::{SegmentMeta, SegmentReader};
+use core::Searcher;
+use docset::{DocSet, SkipResult};
+use common::BitSet;
+use schema::Schema;
+use std::fmt;
+use std::result;
+use query::QueryValidationError;
+use DocId;
+use Result;
+use Score;
+
+/// `BoostQuery` matches the same documents as the query it wraps,
+/// with their score multiplied by a constant factor.
+///
+/// This makes it possible to give more (or less) importance
+/// to one of the clauses of a `BooleanQuery`.
+#[derive(Clone, Debug)]
+pub struct BoostQuery {
+    query: Box<Query>,
+    boost: Score,
+}
+
+impl BoostQuery {
+    /// Creates a new `BoostQuery`, multiplying the scores
+    /// of the documents matching `query` by `boost`.
+    pub fn new(query: Box<Query>, boost: Score) -> BoostQuery {
+        BoostQuery { query, boost }
+    }
+
+    /// The boost factor.
+    pub fn boost(&self) -> Score {
+        self.boost
+    }
+}
+
+impl Query for BoostQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        let weight = self.query.weight(searcher, scoring_enabled)?;
+        Ok(box BoostWeight {
+            weight,
+            boost: self.boost,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        self.query.validate(searcher)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Boost({}) ", self.boost)?;
+        self.query.fmt_tree(schema, depth, f)
+    }
+}
+
+/// Weight associated to the `BoostQuery` query.
+pub struct BoostWeight {
+    weight: Box<Weight>,
+    boost: Score,
+}
+
+impl Weight for BoostWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        self.scorer_with_context(reader, &mut QueryContext::new())
+    }
+
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        let scorer = self.weight.scorer_with_context(reader, context)?;
+        Ok(box BoostScorer {
+            scorer,
+            boost: self.boost,
+        })
+    }
+
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let (scorer, diag) = self.weight.scorer_with_diag(reader)?;
+        let boost_scorer: Box<Scorer> = box BoostScorer {
+            scorer,
+            boost: self.boost,
+        };
+        Ok((boost_scorer, diag))
+    }
+
+    fn can_match(&self, segment_meta: &SegmentMeta) -> bool {
+        self.weight.can_match(segment_meta)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let sub_explanation = self.weight.explain(reader, doc)?;
+        let mut explanation = Explanation::new(
+            "Boost, product of the score and the boost",
+            sub_explanation.value() * self.boost,
+        );
+        explanation.add_detail(sub_explanation);
+        explanation.add_const("boost", self.boost);
+        Ok(explanation)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> Result<u32> {
+        self.weight.count(reader)
+    }
+}
+
+/// Scorer associated to the `BoostQuery` query.
+pub struct BoostScorer {
+    scorer: Box<Scorer>,
+    boost: Score,
+}
+
+impl DocSet for BoostScorer {
+    fn advance(&mut self) -> bool {
+        self.scorer.advance()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.scorer.skip_next(target)
+    }
+
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        self.scorer.fill_buffer(buffer)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+
+    fn count_estimate(&self) -> u32 {
+        self.scorer.count_estimate()
+    }
+
+    fn append_to_bitset(&mut self, bitset: &mut BitSet) {
+        self.scorer.append_to_bitset(bitset);
+    }
+}
+
+impl Scorer for BoostScorer {
+    fn score(&mut self) -> Score {
+        self.scorer.score() * self.boost
+    }
+
+    fn name(&self) -> &'static str {
+        "BoostScorer"
+    }
+
+    fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+        self.scorer.two_phase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use DocAddress;
+    use Score;
+    use schema::{Field, SchemaBuilder, Term, TEXT};
+    use query::{BooleanQuery, Occur, PhraseQuery, Query, TermQuery};
+    use schema::IndexRecordOption;
+    use collector::TopCollector;
+    use super::BoostQuery;
+
+    #[test]
+    fn test_boost_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a b")).unwrap();
+            index_writer.add_document(doc!(text => "a")).unwrap();
+            index_writer.add_document(doc!(text => "c")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = || {
+            box TermQuery::new(
+                Term::from_field_text(text, "a"),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let scores = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            query.search(&*searcher, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let expected = scores(&*term_query());
+        let boosted = scores(&BoostQuery::new(term_query(), 3.0));
+        assert_eq!(expected.len(), 2);
+        assert_eq!(boosted.len(), 2);
+        for (&(score, doc), &(boosted_score, boosted_doc)) in expected.iter().zip(boosted.iter()) {
+            assert_eq!(doc, boosted_doc);
+            assert_eq!(score * 3.0, boosted_score);
+        }
+        assert_eq!(BoostQuery::new(term_query(), 3.0).count(&*searcher).unwrap(), 2);
+        for &(boosted_score, doc_address) in &boosted {
+            let explanation = BoostQuery::new(term_query(), 3.0)
+                .explain(&*searcher, doc_address)
+                .unwrap();
+            assert_eq!(explanation.value(), boosted_score);
+            assert_eq!(explanation.details()[1].value(), 3.0);
+        }
+    }
+
+    #[test]
+    fn test_boost_query_ordering() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "the rust book", body => "cooking"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "cooking", body => "rust"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "cooking", body => "gardening"))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |field: Field, text: &str| -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let phrase_query = || -> Box<Query> {
+            box PhraseQuery::from(vec![
+                Term::from_field_text(title, "rust"),
+                Term::from_field_text(title, "book"),
+            ])
+        };
+        let boost = |query: Box<Query>, factor: Score| -> Box<Query> {
+            box BoostQuery::new(query, factor)
+        };
+        let should = |queries: Vec<Box<Query>>| {
+            BooleanQuery::from(
+                queries
+                    .into_iter()
+                    .map(|query| (Occur::Should, query))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let top_docs = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            query.search(&*searcher, &mut top_collector).unwrap();
+            top_collector.docs()
+        };
+        let (doc_0, doc_1) = (DocAddress(0, 0), DocAddress(0, 1));
+
+        // the title of the document 0 is longer.
+        let query = should(vec![term_query(title, "rust"), term_query(body, "rust")]);
+        assert_eq!(top_docs(&query), vec![doc_1, doc_0]);
+        let query = should(vec![
+            boost(term_query(title, "rust"), 3.0),
+            term_query(body, "rust"),
+        ]);
+        assert_eq!(top_docs(&query), vec![doc_0, doc_1]);
+
+        // the score of a phrase is 1.
+        let query = should(vec![phrase_query(), term_query(body, "rust")]);
+        assert_eq!(top_docs(&query), vec![doc_1, doc_0]);
+        let query = should(vec![boost(phrase_query(), 2.0), term_query(body, "rust")]);
+        assert_eq!(top_docs(&query), vec![doc_0, doc_1]);
+
+        // nested boosts multiply.
+        let scores = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            query.search(&*searcher, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let expected = scores(&*boost(term_query(title, "rust"), 3.0));
+        let nested = scores(&*boost(boost(term_query(title, "rust"), 2.0), 1.5));
+        assert_eq!(expected.len(), 1);
+        assert_eq!(nested.len(), 1);
+        assert_eq!(expected[0].1, nested[0].1);
+        assert!((expected[0].0 - nested[0].0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,325 @@
+use query::{Query, QueryContext, Scorer, ScorerDiag, TwoPhase, Weight};
+use core::{SegmentMeta, SegmentReader};
+use core::Searcher;
+use docset::{DocSet, SkipResult};
+use common::BitSet;
+use schema::Schema;
+use std::fmt;
+use std::result;
+use query::QueryValidationError;
+use DocId;
+use Result;
+use Score;
+
+/// `ConstantScoreQuery` matches the same documents as the query it wraps,
+/// and gives all of them the same constant score.
+///
+/// This is typically used for the clauses of a `BooleanQuery` that
+/// act as filters, such as `status:published`, so that they do not
+/// interfere with the ranking of the documents.
+///
+/// The score defaults to 1, and the wrapped query is run with
+/// scoring disabled.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::TopCollector;
+/// use tantivy::query::{BooleanQuery, ConstantScoreQuery, Occur, Query, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, SchemaBuilder, Term, STRING, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let status = schema_builder.add_text_field("status", STRING);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "rust", status => "published"))?;
+///     index_writer.add_document(doc!(title => "rust", status => "draft"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let title_query: Box<Query> = Box::new(TermQuery::new(
+///     Term::from_field_text(title, "rust"),
+///     IndexRecordOption::WithFreqs,
+/// ));
+/// let status_query: Box<Query> = Box::new(TermQuery::new(
+///     Term::from_field_text(status, "published"),
+///     IndexRecordOption::Basic,
+/// ));
+/// let filter_query: Box<Query> = Box::new(ConstantScoreQuery::new(status_query));
+/// let query = BooleanQuery::from(vec![(Occur::Must, title_query), (Occur::Must, filter_query)]);
+/// let mut top_collector = TopCollector::with_limit(10);
+/// searcher.search(&query, &mut top_collector)?;
+/// assert_eq!(top_collector.docs().len(), 1);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConstantScoreQuery {
+    query: Box<Query>,
+    score: Score,
+}
+
+impl ConstantScoreQuery {
+    /// Creates a new `ConstantScoreQuery`, giving a score of 1
+    /// to all of the documents matching `query`.
+    pub fn new(query: Box<Query>) -> ConstantScoreQuery {
+        ConstantScoreQuery { query, score: 1.0 }
+    }
+
+    /// Sets the constant score to a different value.
+    pub fn set_score(&mut self, score: Score) {
+        self.score = score;
+    }
+
+    /// The constant score.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+}
+
+impl Query for ConstantScoreQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        let weight = self.query.weight(searcher, false)?;
+        Ok(box ConstantScoreWeight {
+            weight,
+            score: self.score,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        self.query.validate(searcher)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ConstantScore({}) ", self.score)?;
+        self.query.fmt_tree(schema, depth, f)
+    }
+}
+
+/// Weight associated to the `ConstantScoreQuery` query.
+pub struct ConstantScoreWeight {
+    weight: Box<Weight>,
+    score: Score,
+}
+
+impl Weight for ConstantScoreWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        self.scorer_with_context(reader, &mut QueryContext::new())
+    }
+
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        let scorer = self.weight.scorer_with_context(reader, context)?;
+        Ok(box ConstantScoreScorer {
+            scorer,
+            score: self.score,
+        })
+    }
+
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let (scorer, diag) = self.weight.scorer_with_diag(reader)?;
+        let constant_score_scorer: Box<Scorer> = box ConstantScoreScorer {
+            scorer,
+            score: self.score,
+        };
+        Ok((constant_score_scorer, diag))
+    }
+
+    fn can_match(&self, segment_meta: &SegmentMeta) -> bool {
+        self.weight.can_match(segment_meta)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> Result<u32> {
+        self.weight.count(reader)
+    }
+}
+
+/// Scorer associated to the `ConstantScoreQuery` query.
+///
+/// Unlike the `ConstScorer`, it wraps a `Scorer`, and
+/// forwards its two-phase iteration.
+pub struct ConstantScoreScorer {
+    scorer: Box<Scorer>,
+    score: Score,
+}
+
+impl DocSet for ConstantScoreScorer {
+    fn advance(&mut self) -> bool {
+        self.scorer.advance()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.scorer.skip_next(target)
+    }
+
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        self.scorer.fill_buffer(buffer)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+
+    fn count_estimate(&self) -> u32 {
+        self.scorer.count_estimate()
+    }
+
+    fn append_to_bitset(&mut self, bitset: &mut BitSet) {
+        self.scorer.append_to_bitset(bitset);
+    }
+}
+
+impl Scorer for ConstantScoreScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+
+    fn name(&self) -> &'static str {
+        "ConstantScoreScorer"
+    }
+
+    fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+        self.scorer.two_phase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use DocAddress;
+    use schema::{SchemaBuilder, Term, STRING, TEXT};
+    use query::{BooleanQuery, Occur, PhraseQuery, Query, QueryDisplay, TermQuery};
+    use schema::IndexRecordOption;
+    use collector::TopCollector;
+    use docset::{DocSet, SkipResult};
+    use query::Scorer;
+    use super::ConstantScoreQuery;
+
+    #[test]
+    fn test_constant_score_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let status = schema_builder.add_text_field("status", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "rust rust", status => "published"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "the rust book", status => "published"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "rust", status => "draft"))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "cooking", status => "published"))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let title_query = || -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(title, "rust"),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let status_query = || -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(status, "published"),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let scores = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            query.search(&*searcher, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+
+        let mut constant_score_query = ConstantScoreQuery::new(status_query());
+        assert_eq!(constant_score_query.score(), 1.0);
+        constant_score_query.set_score(2.5);
+        let score_docs = scores(&constant_score_query);
+        assert_eq!(score_docs.len(), 3);
+        for &(score, _) in &score_docs {
+            assert_eq!(score, 2.5);
+        }
+        assert_eq!(constant_score_query.count(&*searcher).unwrap(), 3);
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&constant_score_query, &schema)),
+            "ConstantScore(2.5) Term(status:\"published\")\n"
+        );
+
+        // the filter adds the same score to all of the documents.
+        let title_scores = scores(&*title_query());
+        let filter_query: Box<Query> = box constant_score_query.clone();
+        let filtered_scores = scores(&BooleanQuery::from(vec![
+            (Occur::Must, title_query()),
+            (Occur::Must, filter_query),
+        ]));
+        let expected: Vec<_> = title_scores
+            .iter()
+            .filter(|&&(_, doc)| doc != DocAddress(0, 2))
+            .cloned()
+            .collect();
+        assert_eq!(filtered_scores.len(), 2);
+        for (&(score, doc), &(filtered_score, filtered_doc)) in
+            expected.iter().zip(filtered_scores.iter())
+        {
+            assert_eq!(doc, filtered_doc);
+            assert!((score + 2.5 - filtered_score).abs() < 1e-5);
+        }
+
+        // the documents are the same as the ones of the wrapped query.
+        let phrase_query = || -> Box<Query> {
+            box PhraseQuery::from(vec![
+                Term::from_field_text(title, "rust"),
+                Term::from_field_text(title, "book"),
+            ])
+        };
+        let segment_reader = searcher.segment_reader(0);
+        for query in vec![title_query(), status_query(), phrase_query()] {
+            let constant_score_query = ConstantScoreQuery::new(query.clone());
+            let open_scorer = |query: &Query| {
+                query
+                    .weight(&*searcher, true)
+                    .unwrap()
+                    .scorer(segment_reader)
+                    .unwrap()
+            };
+            let mut scorer = open_scorer(&*query);
+            let mut constant_scorer = open_scorer(&constant_score_query);
+            let skip_result = scorer.skip_next(1);
+            assert_eq!(constant_scorer.skip_next(1), skip_result);
+            if skip_result != SkipResult::End {
+                assert_eq!(scorer.doc(), constant_scorer.doc());
+                assert_eq!(constant_scorer.score(), 1.0);
+            }
+            while scorer.advance() {
+                assert!(constant_scorer.advance());
+                assert_eq!(scorer.doc(), constant_scorer.doc());
+                assert_eq!(constant_scorer.score(), 1.0);
+            }
+            assert!(!constant_scorer.advance());
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use query::{EmptyScorer, Query, QueryContext, Scorer, ScorerDiag, Union, Weight};
+use query::score_combiner::{DisjunctionMaxCombiner, DoNothingCombiner};
+use core::{SegmentMeta, SegmentReader};
+use core::Searcher;
+use schema::Schema;
+use std::fmt;
+use std::result;
+use query::QueryValidationError;
+use Result;
+use Score;
+use DocId;
+
+/// `DisjunctionMaxQuery` matches the documents matching any of its subqueries,
+/// called disjuncts.
+///
+/// The score of a document is the maximum of the scores of the
+/// disjuncts it matches, plus the sum of their other scores multiplied
+/// by the tie breaker.
+///
+/// This is typically used to search the same text in several fields,
+/// such as `title` and `body`. Unlike the union of a `BooleanQuery`,
+/// a document matching in both fields with mediocre scores is not
+/// ranked above a document with one very good match. The tie breaker,
+/// usually much smaller than 1, still favors the former among documents
+/// with a similar best score.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::TopCollector;
+/// use tantivy::query::{DisjunctionMaxQuery, Query, TermQuery};
+/// use tantivy::schema::{Field, IndexRecordOption, SchemaBuilder, Term, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "rust", body => "cooking"))?;
+///     index_writer.add_document(doc!(title => "cooking", body => "rust"))?;
+///     index_writer.add_document(doc!(title => "cooking", body => "gardening"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let term_query = |field: Field, text: &str| -> Box<Query> {
+///     Box::new(TermQuery::new(
+///         Term::from_field_text(field, text),
+///         IndexRecordOption::WithFreqs,
+///     ))
+/// };
+/// let query = DisjunctionMaxQuery::new(
+///     vec![term_query(title, "rust"), term_query(body, "rust")],
+///     0.1,
+/// );
+/// let mut top_collector = TopCollector::with_limit(10);
+/// searcher.search(&query, &mut top_collector)?;
+/// assert_eq!(top_collector.docs().len(), 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DisjunctionMaxQuery {
+    disjuncts: Vec<Box<Query>>,
+    tie_breaker: Score,
+}
+
+impl DisjunctionMaxQuery {
+    /// Creates a new `DisjunctionMaxQuery`.
+    ///
+    /// A `tie_breaker` of `0` only keeps the maximum score of the
+    /// disjuncts, while a `tie_breaker` of `1` sums all of them.
+    pub fn new(disjuncts: Vec<Box<Query>>, tie_breaker: Score) -> DisjunctionMaxQuery {
+        DisjunctionMaxQuery {
+            disjuncts,
+            tie_breaker,
+        }
+    }
+
+    /// The subqueries of the query.
+    pub fn disjuncts(&self) -> &[Box<Query>] {
+        &self.disjuncts[..]
+    }
+
+    /// The tie breaker.
+    pub fn tie_breaker(&self) -> Score {
+        self.tie_breaker
+    }
+}
+
+impl Query for DisjunctionMaxQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        let weights = self.disjuncts
+            .iter()
+            .map(|disjunct| disjunct.weight(searcher, scoring_enabled))
+            .collect::<Result<_>>()?;
+        Ok(box DisjunctionMaxWeight {
+            weights,
+            tie_breaker: self.tie_breaker,
+            scoring_enabled,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        for disjunct in &self.disjuncts {
+            disjunct.validate(searcher)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "DisjunctionMax({})", self.tie_breaker)?;
+        for disjunct in &self.disjuncts {
+            write!(f, "{:indent$}", "", indent = 2 * (depth + 1))?;
+            disjunct.fmt_tree(schema, depth + 1, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Weight associated to the `DisjunctionMaxQuery` query.
+pub struct DisjunctionMaxWeight {
+    weights: Vec<Box<Weight>>,
+    tie_breaker: Score,
+    scoring_enabled: bool,
+}
+
+impl DisjunctionMaxWeight {
+    // `max_doc` is the `max_doc` of the segment, that bounds the size hint of the union.
+    fn union(
+        &self,
+        mut scorers: Vec<Box<Scorer>>,
+        max_doc: DocId,
+        context: &QueryContext,
+    ) -> Box<Scorer> {
+        if scorers.is_empty() {
+            box EmptyScorer
+        } else if scorers.len() == 1 {
+            // the maximum of a single score is the score itself.
+            scorers.pop().unwrap()
+        } else if self.scoring_enabled {
+            let score_combiner = DisjunctionMaxCombiner::with_tie_breaker(self.tie_breaker);
+            let mut union = Union::with_score_combiner(scorers, score_combiner, context);
+            union.set_max_doc(max_doc);
+            box union
+        } else {
+            let mut union = Union::<_, DoNothingCombiner>::with_context(scorers, context);
+            union.set_max_doc(max_doc);
+            box union
+        }
+    }
+}
+
+impl Weight for DisjunctionMaxWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        self.scorer_with_context(reader, &mut QueryContext::new())
+    }
+
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            scorers.push(weight.scorer_with_context(reader, context)?);
+        }
+        Ok(self.union(scorers, reader.max_doc(), context))
+    }
+
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let mut diag = ScorerDiag::default();
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            let (scorer, sub_diag) = weight.scorer_with_diag(reader)?;
+            diag.merge_skip_reasons(sub_diag);
+            scorers.push(scorer);
+        }
+        let scorer = self.union(scorers, reader.max_doc(), &QueryContext::new());
+        diag.set_estimated_num_candidates(scorer.size_hint());
+        Ok((scorer, diag))
+    }
+
+    fn can_match(&self, segment_meta: &SegmentMeta) -> bool {
+        self.weights
+            .iter()
+            .any(|weight| weight.can_match(segment_meta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use DocAddress;
+    use Score;
+    use schema::{Field, SchemaBuilder, Term, TEXT};
+    use query::{BooleanQuery, Occur, Query, QueryDisplay, TermQuery};
+    use schema::IndexRecordOption;
+    use collector::TopCollector;
+    use std::collections::BTreeMap;
+    use super::DisjunctionMaxQuery;
+
+    #[test]
+    fn test_disjunction_max_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // matches the title only, with a good score.
+            index_writer
+                .add_document(doc!(title => "rust", body => "cooking"))
+                .unwrap();
+            // matches both fields, with mediocre scores.
+            index_writer
+                .add_document(doc!(title => "rust and cooking", body => "rust cooking"))
+                .unwrap();
+            // matches the body only.
+            index_writer
+                .add_document(doc!(title => "cooking", body => "rust"))
+                .unwrap();
+            // matches none of them.
+            index_writer
+                .add_document(doc!(title => "cooking", body => "gardening"))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |field: Field| -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(field, "rust"),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let scores = |query: &Query| -> BTreeMap<DocAddress, Score> {
+            let mut top_collector = TopCollector::with_limit(10);
+            query.search(&*searcher, &mut top_collector).unwrap();
+            top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, doc)| (doc, score))
+                .collect()
+        };
+        let title_scores = scores(&*term_query(title));
+        let body_scores = scores(&*term_query(body));
+        let sum_scores = scores(&BooleanQuery::from(vec![
+            (Occur::Should, term_query(title)),
+            (Occur::Should, term_query(body)),
+        ]));
+        let (doc_0, doc_1, doc_2) = (DocAddress(0, 0), DocAddress(0, 1), DocAddress(0, 2));
+
+        // the plain union ranks the document matching both fields first.
+        assert!(sum_scores[&doc_1] > sum_scores[&doc_0]);
+
+        for &tie_breaker in &[0.0, 0.1, 1.0] {
+            let disjuncts = vec![term_query(title), term_query(body)];
+            let query = DisjunctionMaxQuery::new(disjuncts, tie_breaker);
+            let dis_max_scores = scores(&query);
+            assert_eq!(dis_max_scores.len(), 3);
+            assert_eq!(query.count(&*searcher).unwrap(), 3);
+            // documents matching one disjunct keep its score.
+            assert!((dis_max_scores[&doc_0] - title_scores[&doc_0]).abs() < 1e-5);
+            assert!((dis_max_scores[&doc_2] - body_scores[&doc_2]).abs() < 1e-5);
+            // documents matching both disjuncts get the best score,
+            // plus the other one multiplied by the tie breaker.
+            let (title_score, body_score) = (title_scores[&doc_1], body_scores[&doc_1]);
+            let expected = title_score.max(body_score) + tie_breaker * title_score.min(body_score);
+            assert!((dis_max_scores[&doc_1] - expected).abs() < 1e-5);
+        }
+
+        // with a small tie breaker, the best match comes first.
+        let query = DisjunctionMaxQuery::new(vec![term_query(title), term_query(body)], 0.1);
+        let dis_max_scores = scores(&query);
+        assert!(dis_max_scores[&doc_0] > dis_max_scores[&doc_1]);
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&query, &schema)),
+            "DisjunctionMax(0.1)\n  Term(title:\"rust\")\n  Term(body:\"rust\")\n"
+        );
+
+        let query = DisjunctionMaxQuery::new(vec![term_query(title)], 0.1);
+        assert_eq!(scores(&query), title_scores);
+        let query = DisjunctionMaxQuery::new(vec![], 0.1);
+        assert!(scores(&query).is_empty());
+    }
+}
@@ -38,6 +38,18 @@ where
             excluding_state: state,
         }
     }
+
+    /// Consumes the `Exclude`, and returns its underlying
+    /// and excluding `DocSet`s.
+    ///
+    /// This is the two-`DocSet` analogue of
+    /// [`DocSetGroup::into_docsets`](../trait.DocSetGroup.html#tymethod.into_docsets).
+    /// The underlying `DocSet` is positioned on the current document
+    /// of the `Exclude`, and the excluding `DocSet` on the next document
+    /// to exclude, or exhausted.
+    pub fn into_docsets(self) -> (TDocSet, TDocSetExclude) {
+        (self.underlying_docset, self.excluding_docset)
+    }
 }
 
 impl<TDocSet, TDocSetExclude> Exclude<TDocSet, TDocSetExclude>
@@ -114,6 +126,10 @@ where
     fn size_hint(&self) -> u32 {
         self.underlying_docset.size_hint()
     }
+
+    fn count_estimate(&self) -> u32 {
+        self.underlying_docset.count_estimate()
+    }
 }
 
 impl<TScorer, TDocSetExclude> Scorer for Exclude<TScorer, TDocSetExclude>
@@ -124,6 +140,10 @@ where
     fn score(&mut self) -> Score {
         self.underlying_docset.score()
     }
+
+    fn name(&self) -> &'static str {
+        "Exclude"
+    }
 }
 
 #[cfg(test)]
@@ -147,6 +167,19 @@ mod tests {
         assert_eq!(els, vec![5, 8, 15]);
     }
 
+    #[test]
+    fn test_exclude_into_docsets() {
+        let mut exclude_scorer = Exclude::new(
+            VecDocSet::from(vec![1, 2, 5, 8, 10]),
+            VecDocSet::from(vec![1, 2, 3, 10, 16]),
+        );
+        assert!(exclude_scorer.advance());
+        assert_eq!(exclude_scorer.doc(), 5);
+        let (underlying_docset, excluding_docset) = exclude_scorer.into_docsets();
+        assert_eq!(underlying_docset.doc(), 5);
+        assert_eq!(excluding_docset.doc(), 10);
+    }
+
     #[test]
     fn test_exclude_skip() {
         test_skip_against_unoptimized(
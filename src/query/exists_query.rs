@@ -0,0 +1,245 @@
+use schema::{Field, Schema};
+use query::{Query, Scorer, Weight};
+use core::SegmentReader;
+use Result;
+use core::Searcher;
+use query::BitSetDocSet;
+use query::ConstScorer;
+use std::result;
+use std::fmt;
+use query::QueryValidationError;
+use query::query_validation::get_field_entry;
+
+/// `ExistsQuery` matches all of the documents holding
+/// at least one value for a given field.
+///
+/// All of the matched documents get the score 1.
+///
+/// The field does not need to be indexed. The documents
+/// are looked up in the field presence bitsets of the segments.
+///
+/// # Warning
+///
+/// Segments written before field presence was recorded do not
+/// have such a bitset. For these segments, the set of documents
+/// is rebuilt by going through the postings of all of the terms of the
+/// field, or by reading all of the stored documents if the field is
+/// not indexed. This can be very slow on large segments, and such
+/// segments never match fields that are neither indexed nor stored.
+/// Merging them away removes the problem.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::schema::{SchemaBuilder, STORED, TEXT};
+/// use tantivy::query::{ExistsQuery, Query};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let author = schema_builder.add_text_field("author", STORED);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "The Name of the Wind", author => "Rothfuss"))?;
+///     index_writer.add_document(doc!(title => "Beowulf"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// assert_eq!(ExistsQuery::new(author).count(&*searcher)?, 1);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExistsQuery {
+    field: Field,
+}
+
+impl ExistsQuery {
+    /// Creates a new `ExistsQuery` for the given field.
+    pub fn new(field: Field) -> ExistsQuery {
+        ExistsQuery { field }
+    }
+
+    /// The field this query is targeting.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+}
+
+impl Query for ExistsQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(box ExistsWeight { field: self.field })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        get_field_entry(searcher.schema(), self.field).map(|_| ())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        let field_entry = schema.get_field_entry(self.field);
+        writeln!(f, "Exists({})", field_entry.name())
+    }
+}
+
+/// Weight associated to the `ExistsQuery` query.
+pub struct ExistsWeight {
+    field: Field,
+}
+
+impl Weight for ExistsWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let docs = reader.docs_with_field(self.field)?;
+        Ok(box ConstScorer::new(BitSetDocSet::from(docs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{Field, SchemaBuilder, Term, FAST, INT_INDEXED, STORED, TEXT};
+    use query::Query;
+    use indexer::NoMergePolicy;
+    use core::{SegmentReader, Searcher};
+    use docset::DocSet;
+    use futures::Future;
+    use tempdir::TempDir;
+    use std::fs;
+    use super::ExistsQuery;
+
+    fn fields() -> (Field, Field, Field, Field) {
+        (Field(0), Field(1), Field(2), Field(3))
+    }
+
+    fn create_index(index: &Index) {
+        let (title, author, year, rating) = fields();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy::default());
+        index_writer.add_document(doc!(title => "a", author => "x", year => 1u64)).unwrap();
+        index_writer.add_document(doc!(title => "b", year => 2u64, year => 3u64)).unwrap();
+        index_writer.add_document(doc!(title => "c", rating => 5u64)).unwrap();
+        index_writer.add_document(doc!(author => "y", author => "z")).unwrap();
+        index_writer.commit().unwrap();
+        // this segment does not contain any `author` or `year`.
+        index_writer.add_document(doc!(title => "d")).unwrap();
+        index_writer.add_document(doc!(title => "e", rating => 2u64)).unwrap();
+        index_writer.commit().unwrap();
+    }
+
+    fn create_schema_builder() -> SchemaBuilder {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_text_field("author", STORED);
+        schema_builder.add_u64_field("year", INT_INDEXED);
+        schema_builder.add_u64_field("rating", FAST);
+        schema_builder
+    }
+
+    // returns the matching documents as `(max_doc, doc)` pairs, as the segments
+    // are distinguished by their number of documents.
+    fn matching_docs(index: &Index, field: Field) -> Vec<(u32, u32)> {
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let weight = ExistsQuery::new(field).weight(&*searcher, false).unwrap();
+        let mut docs = vec![];
+        for segment_reader in searcher.segment_readers() {
+            let mut scorer = weight.scorer(segment_reader).unwrap();
+            while scorer.advance() {
+                assert_eq!(scorer.score(), 1.0);
+                docs.push((segment_reader.max_doc(), scorer.doc()));
+            }
+        }
+        docs.sort();
+        docs
+    }
+
+    fn find_segment_reader(searcher: &Searcher, max_doc: u32) -> &SegmentReader {
+        searcher
+            .segment_readers()
+            .iter()
+            .find(|segment_reader| segment_reader.max_doc() == max_doc)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_exists_query() {
+        let (title, author, year, rating) = fields();
+        let index = Index::create_in_ram(create_schema_builder().build());
+        create_index(&index);
+        assert_eq!(
+            matching_docs(&index, title),
+            vec![(2, 0), (2, 1), (4, 0), (4, 1), (4, 2)]
+        );
+        assert_eq!(matching_docs(&index, author), vec![(4, 0), (4, 3)]);
+        assert_eq!(matching_docs(&index, year), vec![(4, 0), (4, 1)]);
+        assert_eq!(matching_docs(&index, rating), vec![(2, 1), (4, 2)]);
+        {
+            let searcher = index.searcher();
+            assert_eq!(searcher.segment_readers().len(), 2);
+            let segment_reader = find_segment_reader(&*searcher, 4);
+            assert_eq!(segment_reader.fields(), vec![title, author, year, rating]);
+            let segment_reader = find_segment_reader(&*searcher, 2);
+            assert_eq!(segment_reader.fields(), vec![title, rating]);
+            assert!(segment_reader.has_field(rating));
+            assert!(!segment_reader.has_field(author));
+        }
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.delete_term(Term::from_field_text(title, "a"));
+            index_writer.commit().unwrap();
+        }
+        assert_eq!(matching_docs(&index, author), vec![(4, 3)]);
+        assert_eq!(matching_docs(&index, year), vec![(4, 1)]);
+        // field presence survives merges.
+        {
+            let segment_ids = index.searchable_segment_ids().unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.merge(&segment_ids).wait().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+        }
+        assert_eq!(index.searcher().segment_readers().len(), 1);
+        assert_eq!(matching_docs(&index, title).len(), 4);
+        assert_eq!(matching_docs(&index, author).len(), 1);
+        assert_eq!(matching_docs(&index, year).len(), 1);
+        assert_eq!(matching_docs(&index, rating).len(), 2);
+    }
+
+    #[test]
+    fn test_exists_query_without_field_presence() {
+        let (title, author, year, rating) = fields();
+        let temp_dir = TempDir::new("tantivy_exists_query_test").unwrap();
+        let index_path = temp_dir.path().to_path_buf();
+        {
+            let index = Index::create(&index_path, create_schema_builder().build()).unwrap();
+            create_index(&index);
+        }
+        // emulates segments written before field presence was recorded.
+        for entry in fs::read_dir(&index_path).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map(|ext| ext == "presence").unwrap_or(false) {
+                fs::remove_file(path).unwrap();
+            }
+        }
+        let index = Index::open(&index_path).unwrap();
+        assert_eq!(
+            matching_docs(&index, title),
+            vec![(2, 0), (2, 1), (4, 0), (4, 1), (4, 2)]
+        );
+        assert_eq!(matching_docs(&index, author), vec![(4, 0), (4, 3)]);
+        assert_eq!(matching_docs(&index, year), vec![(4, 0), (4, 1)]);
+        // fields that are neither indexed nor stored cannot be recovered.
+        assert!(matching_docs(&index, rating).is_empty());
+        // only the indexed fields are listed.
+        let searcher = index.searcher();
+        assert_eq!(find_segment_reader(&*searcher, 4).fields(), vec![title, year]);
+        assert!(!find_segment_reader(&*searcher, 2).has_field(year));
+    }
+}
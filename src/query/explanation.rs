@@ -0,0 +1,96 @@
+use DocId;
+use Error;
+use ErrorKind;
+use Score;
+use serde_json;
+
+/// Returns the error of the explanation of a document
+/// that is not matched by the query.
+pub(crate) fn does_not_match(doc: DocId) -> Error {
+    ErrorKind::InvalidArgument(format!("Document #({}) does not match the query", doc)).into()
+}
+
+/// Explanation of the score of a document,
+/// as returned by [`Query::explain`](./trait.Query.html#method.explain).
+///
+/// An explanation is a tree: the `value` of an explanation is computed
+/// from the values of its `details`, as stated by its `description`.
+///
+/// It can be serialized, for instance to JSON with
+/// [`to_pretty_json`](#method.to_pretty_json).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Explanation {
+    value: Score,
+    description: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<Explanation>,
+}
+
+impl Explanation {
+    /// Creates an explanation without any detail.
+    pub fn new<T: ToString>(description: T, value: Score) -> Explanation {
+        Explanation {
+            value,
+            description: description.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Returns the value explained.
+    pub fn value(&self) -> Score {
+        self.value
+    }
+
+    /// Returns the description of the value,
+    /// and of the way it is computed from the details.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the explanations of the values the value is computed from.
+    pub fn details(&self) -> &[Explanation] {
+        &self.details
+    }
+
+    /// Adds the explanation of a value the value is computed from.
+    pub fn add_detail(&mut self, detail: Explanation) {
+        self.details.push(detail);
+    }
+
+    /// Adds a value the value is computed from, that needs no further explanation.
+    pub fn add_const<T: ToString>(&mut self, description: T, value: Score) {
+        self.add_detail(Explanation::new(description, value));
+    }
+
+    /// Returns the explanation as an indented JSON string.
+    pub fn to_pretty_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("An explanation can always be serialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Explanation;
+    use serde_json::{self, Value};
+
+    #[test]
+    fn test_explanation_json() {
+        let mut explanation = Explanation::new("product of", 6.0);
+        explanation.add_const("a", 2.0);
+        explanation.add_detail(Explanation::new("b", 3.0));
+        assert_eq!(explanation.details().len(), 2);
+        assert_eq!(explanation.details()[1].description(), "b");
+        let json: Value = serde_json::from_str(&explanation.to_pretty_json()).unwrap();
+        let expected_json: Value = serde_json::from_str(
+            r#"{
+                "value": 6.0,
+                "description": "product of",
+                "details": [
+                    {"value": 2.0, "description": "a"},
+                    {"value": 3.0, "description": "b"}
+                ]
+            }"#,
+        ).unwrap();
+        assert_eq!(json, expected_json);
+    }
+}
@@ -0,0 +1,508 @@
+use common::{i64_to_u64, u64_to_i64, BitSet};
+use core::{Searcher, SegmentReader};
+use docset::{DocSet, SkipResult};
+use fastfield::{DeleteBitSet, FastFieldReader, FastValue};
+use query::query_validation::get_field_entry;
+use query::{AllWeight, BitSetDocSet, ConstScorer, EmptyScorer, Query, QueryValidationError,
+            Scorer, Weight};
+use schema::{Cardinality, Field, FieldType, Schema};
+use std::collections::Bound;
+use std::collections::range::RangeArgument;
+use std::{cmp, fmt, result};
+use DocId;
+use Result;
+
+/// Number of values decoded at once when scanning a fast field.
+const SCAN_BUFFER_LEN: usize = 1_024;
+
+/// Estimated fraction of the documents of a segment matching the range,
+/// above which the documents are filtered lazily rather than
+/// gathered into a `BitSet`.
+const LAZY_SELECTIVITY_THRESHOLD: f64 = 0.5;
+
+/// Converts a bound to an inclusive bound, returning
+/// `None` if no value can satisfy it.
+fn inclusive_lower_bound(bound: Bound<u64>) -> Option<u64> {
+    match bound {
+        Bound::Included(val) => Some(val),
+        Bound::Excluded(val) => val.checked_add(1),
+        Bound::Unbounded => Some(0u64),
+    }
+}
+
+fn inclusive_upper_bound(bound: Bound<u64>) -> Option<u64> {
+    match bound {
+        Bound::Included(val) => Some(val),
+        Bound::Excluded(val) => val.checked_sub(1),
+        Bound::Unbounded => Some(u64::max_value()),
+    }
+}
+
+fn map_bound<TFrom: Copy>(bound: Bound<&TFrom>, transform: fn(TFrom) -> u64) -> Bound<u64> {
+    match bound {
+        Bound::Included(val) => Bound::Included(transform(*val)),
+        Bound::Excluded(val) => Bound::Excluded(transform(*val)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Type of the values of the range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FastFieldType {
+    U64,
+    I64,
+}
+
+/// `FastFieldRangeQuery` matches the documents whose value for a
+/// single valued `u64` or `i64` fast field is within a range.
+///
+/// Unlike the [`RangeQuery`](./struct.RangeQuery.html), it does not require
+/// the field to be indexed: the values of each segment are scanned from the
+/// fast field, skipping the segments whose values are all out of the range.
+/// It is therefore a good fit for fields declared as `FAST` only.
+///
+/// Matched documents all get a constant `Score` of one.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::CountCollector;
+/// use tantivy::query::{FastFieldRangeQuery, Query};
+/// use tantivy::schema::{SchemaBuilder, FAST};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let price = schema_builder.add_u64_field("price", FAST);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     for val in 0u64..100u64 {
+///         index_writer.add_document(doc!(price => val))?;
+///     }
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let mut count_collector = CountCollector::default();
+/// FastFieldRangeQuery::new_u64(price, 10..20).search(&*searcher, &mut count_collector)?;
+/// assert_eq!(count_collector.count(), 10);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FastFieldRangeQuery {
+    field: Field,
+    field_type: FastFieldType,
+    // bounds of the range, in the `u64` representation of the values.
+    left_bound: Bound<u64>,
+    right_bound: Bound<u64>,
+}
+
+impl FastFieldRangeQuery {
+    /// Creates a new `FastFieldRangeQuery` over a `u64` fast field.
+    pub fn new_u64<TRangeArgument: RangeArgument<u64>>(
+        field: Field,
+        range: TRangeArgument,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery {
+            field,
+            field_type: FastFieldType::U64,
+            left_bound: map_bound(range.start(), |val: u64| val),
+            right_bound: map_bound(range.end(), |val: u64| val),
+        }
+    }
+
+    /// Creates a new `FastFieldRangeQuery` over an `i64` fast field.
+    pub fn new_i64<TRangeArgument: RangeArgument<i64>>(
+        field: Field,
+        range: TRangeArgument,
+    ) -> FastFieldRangeQuery {
+        FastFieldRangeQuery {
+            field,
+            field_type: FastFieldType::I64,
+            left_bound: map_bound(range.start(), i64_to_u64),
+            right_bound: map_bound(range.end(), i64_to_u64),
+        }
+    }
+
+    /// Returns true iff the field is a single valued fast field of the type of the range.
+    fn is_compatible(&self, field_type: &FieldType) -> bool {
+        match (self.field_type, field_type) {
+            (FastFieldType::U64, &FieldType::U64(ref options))
+            | (FastFieldType::I64, &FieldType::I64(ref options)) => {
+                options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+            }
+            _ => false,
+        }
+    }
+
+    fn fmt_val(&self, val: u64) -> String {
+        match self.field_type {
+            FastFieldType::U64 => val.to_string(),
+            FastFieldType::I64 => u64_to_i64(val).to_string(),
+        }
+    }
+}
+
+impl Query for FastFieldRangeQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        let range = inclusive_lower_bound(self.left_bound)
+            .and_then(|low| inclusive_upper_bound(self.right_bound).map(|high| (low, high)))
+            .and_then(|(low, high)| if low <= high { Some((low, high)) } else { None });
+        Ok(box FastFieldRangeWeight {
+            field: self.field,
+            field_type: self.field_type,
+            range,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        let field_entry = get_field_entry(searcher.schema(), self.field)?;
+        if !self.is_compatible(field_entry.field_type()) {
+            let expected_type = match self.field_type {
+                FastFieldType::U64 => "single valued u64 fast field",
+                FastFieldType::I64 => "single valued i64 fast field",
+            };
+            return Err(QueryValidationError::IncompatibleFieldType(
+                field_entry.name().to_string(),
+                expected_type.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FastFieldRange({}:",
+            schema.get_field_entry(self.field).name()
+        )?;
+        match self.left_bound {
+            Bound::Included(val) => write!(f, "[{}", self.fmt_val(val))?,
+            Bound::Excluded(val) => write!(f, "({}", self.fmt_val(val))?,
+            Bound::Unbounded => write!(f, "(*")?,
+        }
+        write!(f, "..")?;
+        match self.right_bound {
+            Bound::Included(val) => write!(f, "{}]", self.fmt_val(val))?,
+            Bound::Excluded(val) => write!(f, "{})", self.fmt_val(val))?,
+            Bound::Unbounded => write!(f, "*)")?,
+        }
+        writeln!(f, ")")
+    }
+}
+
+/// Weight associated to the `FastFieldRangeQuery`.
+pub struct FastFieldRangeWeight {
+    field: Field,
+    field_type: FastFieldType,
+    // inclusive bounds of the range, `None` if the range is empty.
+    range: Option<(u64, u64)>,
+}
+
+impl FastFieldRangeWeight {
+    fn scorer_for_reader<Item: FastValue + 'static>(
+        &self,
+        reader: &SegmentReader,
+        ff_reader: FastFieldReader<Item>,
+        low: u64,
+        high: u64,
+    ) -> Result<Box<Scorer>> {
+        let min_value = ff_reader.min_value().to_u64();
+        let max_value = ff_reader.max_value().to_u64();
+        if reader.max_doc() == 0 || high < min_value || max_value < low {
+            return Ok(box EmptyScorer);
+        }
+        if low <= min_value && max_value <= high {
+            return AllWeight.scorer(reader);
+        }
+        // assuming the values are uniformly distributed between
+        // the min and the max values.
+        let overlap = cmp::min(high, max_value) - cmp::max(low, min_value);
+        let selectivity = (overlap as f64 + 1f64) / ((max_value - min_value) as f64 + 1f64);
+        let docset = FastFieldRangeDocSet::new(ff_reader, low, high, reader);
+        if selectivity >= LAZY_SELECTIVITY_THRESHOLD {
+            Ok(box ConstScorer::new(docset))
+        } else {
+            let mut doc_bitset = BitSet::with_max_value(reader.max_doc());
+            docset.fill_bitset(&mut doc_bitset);
+            Ok(box ConstScorer::new(BitSetDocSet::from(doc_bitset)))
+        }
+    }
+}
+
+impl Weight for FastFieldRangeWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let (low, high) = match self.range {
+            Some(range) => range,
+            None => {
+                return Ok(box EmptyScorer);
+            }
+        };
+        match self.field_type {
+            FastFieldType::U64 => {
+                let ff_reader = reader.fast_field_reader::<u64>(self.field)?;
+                self.scorer_for_reader(reader, ff_reader, low, high)
+            }
+            FastFieldType::I64 => {
+                let ff_reader = reader.fast_field_reader::<i64>(self.field)?;
+                self.scorer_for_reader(reader, ff_reader, low, high)
+            }
+        }
+    }
+}
+
+/// `DocSet` filtering the documents of a segment
+/// on the values of a fast field, as it advances.
+///
+/// Deleted documents are skipped.
+struct FastFieldRangeDocSet<Item: FastValue> {
+    ff_reader: FastFieldReader<Item>,
+    low: u64,
+    high: u64,
+    delete_bitset: DeleteBitSet,
+    max_doc: DocId,
+    // values of the documents `buffer_start..buffer_start + buffer.len()`.
+    buffer: Vec<Item>,
+    buffer_start: DocId,
+    doc: DocId,
+    started: bool,
+}
+
+impl<Item: FastValue> FastFieldRangeDocSet<Item> {
+    fn new(
+        ff_reader: FastFieldReader<Item>,
+        low: u64,
+        high: u64,
+        reader: &SegmentReader,
+    ) -> FastFieldRangeDocSet<Item> {
+        FastFieldRangeDocSet {
+            ff_reader,
+            low,
+            high,
+            delete_bitset: reader.delete_bitset().clone(),
+            max_doc: reader.max_doc(),
+            buffer: Vec::with_capacity(SCAN_BUFFER_LEN),
+            buffer_start: 0,
+            doc: 0,
+            started: false,
+        }
+    }
+
+    fn matches(&mut self, doc: DocId) -> bool {
+        if doc < self.buffer_start || doc >= self.buffer_start + self.buffer.len() as DocId {
+            let len = cmp::min(SCAN_BUFFER_LEN, (self.max_doc - doc) as usize);
+            self.buffer.resize(len, Item::default());
+            self.ff_reader.get_range(doc, &mut self.buffer[..]);
+            self.buffer_start = doc;
+        }
+        let val = self.buffer[(doc - self.buffer_start) as usize].to_u64();
+        self.low <= val && val <= self.high && !self.delete_bitset.is_deleted(doc)
+    }
+
+    /// Positions the docset on the first matching document
+    /// greater or equal to `doc`.
+    fn seek(&mut self, mut doc: DocId) -> bool {
+        self.started = true;
+        while doc < self.max_doc {
+            if self.matches(doc) {
+                self.doc = doc;
+                return true;
+            }
+            doc += 1;
+        }
+        self.doc = self.max_doc;
+        false
+    }
+
+    /// Inserts all of the matching documents into the bitset.
+    fn fill_bitset(mut self, doc_bitset: &mut BitSet) {
+        while self.advance() {
+            doc_bitset.insert(self.doc);
+        }
+    }
+}
+
+impl<Item: FastValue> DocSet for FastFieldRangeDocSet<Item> {
+    fn advance(&mut self) -> bool {
+        let next_doc = if self.started { self.doc + 1 } else { 0 };
+        self.seek(next_doc)
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let target = if self.started {
+            cmp::max(target, self.doc + 1)
+        } else {
+            target
+        };
+        if !self.seek(target) {
+            SkipResult::End
+        } else if self.doc == target {
+            SkipResult::Reached
+        } else {
+            SkipResult::OverStep
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.max_doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::FastFieldRangeQuery;
+    use collector::TopCollector;
+    use indexer::NoMergePolicy;
+    use query::{from_json, BooleanQuery, Occur, Query, QueryDisplay, RangeQuery, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, FAST, INT_INDEXED, STRING};
+    use std::collections::Bound;
+    use DocAddress;
+    use Index;
+    use Searcher;
+
+    fn matching_docs(searcher: &Searcher, query: &Query) -> Vec<DocAddress> {
+        let mut top_collector = TopCollector::with_limit(10_000);
+        query.search(searcher, &mut top_collector).unwrap();
+        let mut doc_addresses = top_collector.docs();
+        doc_addresses.sort();
+        doc_addresses
+    }
+
+    #[test]
+    fn test_fast_field_range_query_against_range_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let unsigned_field = schema_builder.add_u64_field("unsigned", INT_INDEXED | FAST);
+        let signed_field = schema_builder.add_i64_field("signed", INT_INDEXED | FAST);
+        let parity_field = schema_builder.add_text_field("parity", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment in 0..3u64 {
+                for i in 0..700u64 {
+                    let val = segment * 500 + i;
+                    let parity = if val % 2 == 0 { "even" } else { "odd" };
+                    index_writer
+                        .add_document(doc!(
+                            unsigned_field => val,
+                            signed_field => val as i64 - 1_000,
+                            parity_field => parity
+                        ))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+            index_writer.delete_term(Term::from_field_u64(unsigned_field, 600));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+
+        let u64_ranges: Vec<(Bound<u64>, Bound<u64>)> = vec![
+            (Bound::Included(10), Bound::Excluded(20)),
+            (Bound::Included(0), Bound::Included(1_000)),
+            (Bound::Excluded(550), Bound::Unbounded),
+            (Bound::Unbounded, Bound::Excluded(600)),
+            (Bound::Included(590), Bound::Included(610)),
+            (Bound::Unbounded, Bound::Unbounded),
+            (Bound::Included(5_000), Bound::Unbounded),
+            (Bound::Excluded(30), Bound::Excluded(31)),
+            (Bound::Included(40), Bound::Included(30)),
+        ];
+        for range in u64_ranges {
+            let expected = matching_docs(&*searcher, &RangeQuery::new_u64(unsigned_field, range));
+            let fast_field_range_query = FastFieldRangeQuery::new_u64(unsigned_field, range);
+            assert!(fast_field_range_query.validate(&*searcher).is_ok());
+            assert_eq!(
+                matching_docs(&*searcher, &fast_field_range_query),
+                expected,
+                "{:?}",
+                range
+            );
+            // intersected with another query, the docset is skipped through.
+            let parity_query: Box<Query> = box TermQuery::new(
+                Term::from_field_text(parity_field, "odd"),
+                IndexRecordOption::Basic,
+            );
+            let intersection = |range_query: Box<Query>| {
+                BooleanQuery::from(vec![
+                    (Occur::Must, parity_query.clone()),
+                    (Occur::Must, range_query),
+                ])
+            };
+            assert_eq!(
+                matching_docs(&*searcher, &intersection(box fast_field_range_query)),
+                matching_docs(
+                    &*searcher,
+                    &intersection(box RangeQuery::new_u64(unsigned_field, range))
+                )
+            );
+        }
+        let i64_ranges: Vec<(Bound<i64>, Bound<i64>)> = vec![
+            (Bound::Included(-10), Bound::Excluded(20)),
+            (Bound::Unbounded, Bound::Included(-500)),
+            (Bound::Excluded(-1_000), Bound::Unbounded),
+        ];
+        for range in i64_ranges {
+            assert_eq!(
+                matching_docs(&*searcher, &FastFieldRangeQuery::new_i64(signed_field, range)),
+                matching_docs(&*searcher, &RangeQuery::new_i64(signed_field, range)),
+                "{:?}",
+                range
+            );
+        }
+        // the type of the range must match the type of the field.
+        assert!(
+            FastFieldRangeQuery::new_i64(unsigned_field, 0..1)
+                .validate(&*searcher)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_fast_field_range_query_fast_only_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let price_field = schema_builder.add_u64_field("price", FAST);
+        let delta_field = schema_builder.add_i64_field("delta", INT_INDEXED | FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..100u64 {
+                index_writer
+                    .add_document(doc!(price_field => i * 10, delta_field => i as i64 - 50))
+                    .unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        // the range of a fast only field is evaluated on the fast field...
+        let query = from_json(&schema, r#"{"range": {"price": {"gte": 100, "lt": 200}}}"#)
+            .unwrap();
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&*query, &schema)),
+            "FastFieldRange(price:[100..200))\n"
+        );
+        assert!(query.validate(&*searcher).is_ok());
+        assert_eq!(query.count(&*searcher).unwrap(), 10);
+        // ... while the terms are preferred for indexed fields.
+        let query = from_json(&schema, r#"{"range": {"delta": {"gt": -10}}}"#).unwrap();
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&*query, &schema)),
+            "Range(delta:(-10..*))\n"
+        );
+        assert_eq!(query.count(&*searcher).unwrap(), 59);
+    }
+}
@@ -1,21 +1,27 @@
-use docset::{DocSet, SkipResult};
+use docset::{DocSet, DocSetGroup, SkipResult};
 use query::Scorer;
 use DocId;
 use Score;
+use query::score_combiner::checked_score;
 
 /// Creates a `DocSet` that iterator through the intersection of two `DocSet`s.
 pub struct Intersection<TDocSet: DocSet> {
     docsets: Vec<TDocSet>,
+    // position of each docset in the `Vec` the intersection was built from.
+    ords: Vec<usize>,
     finished: bool,
     doc: DocId,
 }
 
 impl<TDocSet: DocSet> From<Vec<TDocSet>> for Intersection<TDocSet> {
-    fn from(mut docsets: Vec<TDocSet>) -> Intersection<TDocSet> {
+    fn from(docsets: Vec<TDocSet>) -> Intersection<TDocSet> {
         assert!(docsets.len() >= 2);
-        docsets.sort_by_key(|docset| docset.size_hint());
+        let mut ord_docsets: Vec<(usize, TDocSet)> = docsets.into_iter().enumerate().collect();
+        ord_docsets.sort_by_key(|&(_, ref docset)| docset.count_estimate());
+        let (ords, docsets) = ord_docsets.into_iter().unzip();
         Intersection {
             docsets,
+            ords,
             finished: false,
             doc: 0u32,
         }
@@ -118,6 +124,27 @@ impl<TDocSet: DocSet> DocSet for Intersection<TDocSet> {
             .min()
             .unwrap_or(0u32)
     }
+
+    fn count_estimate(&self) -> u32 {
+        self.docsets
+            .iter()
+            .map(|docset| docset.count_estimate())
+            .min()
+            .unwrap_or(0u32)
+    }
+}
+
+/// While the intersection is positioned on a document, all of its
+/// `DocSet`s are positioned on this document. Once the intersection
+/// is exhausted, at least one of them is exhausted, and the others
+/// are positioned on or after the last document of the intersection.
+impl<TDocSet: DocSet> DocSetGroup<TDocSet> for Intersection<TDocSet> {
+    fn into_docsets(self) -> Vec<TDocSet> {
+        let mut ord_docsets: Vec<(usize, TDocSet)> =
+            self.ords.into_iter().zip(self.docsets).collect();
+        ord_docsets.sort_by_key(|&(ord, _)| ord);
+        ord_docsets.into_iter().map(|(_, docset)| docset).collect()
+    }
 }
 
 impl<TScorer> Scorer for Intersection<TScorer>
@@ -125,16 +152,22 @@ where
     TScorer: Scorer,
 {
     fn score(&mut self) -> Score {
-        self.docsets.iter_mut().map(Scorer::score).sum()
+        self.docsets.iter_mut().map(checked_score).sum()
+    }
+
+    fn name(&self) -> &'static str {
+        "Intersection"
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use docset::{DocSet, SkipResult};
+    use docset::{DocSet, DocSetGroup, SkipResult};
     use super::Intersection;
-    use query::VecDocSet;
+    use query::{BitSetDocSet, ConstScorer, VecDocSet};
+    use common::HasLen;
     use postings::tests::test_skip_against_unoptimized;
+    use common::BitSet;
 
     #[test]
     fn test_intersection() {
@@ -159,6 +192,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_intersection_into_docsets() {
+        let docsets = vec![
+            VecDocSet::from(vec![1, 3, 9, 10, 12]),
+            VecDocSet::from(vec![3, 9, 10]),
+            VecDocSet::from(vec![0, 3, 4, 9, 10, 18]),
+        ];
+        {
+            // the docsets are positioned on the current document.
+            let mut intersection = Intersection::from(docsets);
+            assert!(intersection.advance());
+            assert_eq!(intersection.doc(), 3);
+            let docsets = intersection.into_docsets();
+            assert_eq!(
+                docsets.iter().map(|docset| docset.doc()).collect::<Vec<_>>(),
+                vec![3, 3, 3]
+            );
+        }
+        let docsets = vec![
+            VecDocSet::from(vec![1, 3, 9, 10, 12]),
+            VecDocSet::from(vec![3, 9, 10]),
+            VecDocSet::from(vec![0, 3, 4, 9, 10, 18]),
+        ];
+        let mut intersection = Intersection::from(docsets);
+        let mut docs = vec![];
+        while intersection.advance() {
+            docs.push(intersection.doc());
+        }
+        assert_eq!(docs, vec![3, 9, 10]);
+        // the docsets are given back in their original order,
+        // the shortest one being exhausted.
+        let mut docsets = intersection.into_docsets();
+        assert_eq!(
+            docsets.iter().map(|docset| docset.len()).collect::<Vec<_>>(),
+            vec![5, 3, 6]
+        );
+        assert!(!docsets[1].advance());
+        assert!(docsets[0].doc() >= 10);
+        assert!(docsets[2].doc() >= 10);
+        // once rewound, they can be reused in another intersection.
+        for docset in &mut docsets {
+            docset.reset();
+        }
+        docsets.remove(1);
+        let mut intersection = Intersection::from(docsets);
+        let mut docs = vec![];
+        while intersection.advance() {
+            docs.push(intersection.doc());
+        }
+        assert_eq!(docs, vec![3, 9, 10]);
+    }
+
+    #[test]
+    fn test_const_scorer_into_docsets() {
+        let intersection = Intersection::from(vec![
+            VecDocSet::from(vec![1, 2, 3]),
+            VecDocSet::from(vec![2]),
+        ]);
+        let mut const_scorer = ConstScorer::new(intersection);
+        assert!(const_scorer.advance());
+        assert_eq!(const_scorer.doc(), 2);
+        let docsets: Vec<VecDocSet> = const_scorer.into_docsets();
+        assert_eq!(
+            docsets.iter().map(|docset| docset.len()).collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+    }
+
     #[test]
     fn test_intersection_zero() {
         let left = VecDocSet::from(vec![0]);
@@ -219,4 +320,23 @@ mod tests {
         let mut intersection = Intersection::from(vec![a, b, c]);
         assert!(!intersection.advance());
     }
+
+    #[test]
+    fn test_intersection_boxed_docsets() {
+        let mut bitset = BitSet::with_max_value(10);
+        for &doc in &[1, 4, 5, 9] {
+            bitset.insert(doc);
+        }
+        let docsets: Vec<Box<DocSet>> = vec![
+            box VecDocSet::from(vec![1, 3, 5, 7, 9]),
+            box BitSetDocSet::from(bitset),
+        ];
+        let mut intersection = Intersection::from(docsets);
+        assert_eq!(intersection.docsets().len(), 2);
+        let mut docs = vec![];
+        while intersection.advance() {
+            docs.push(intersection.doc());
+        }
+        assert_eq!(docs, vec![1, 5, 9]);
+    }
 }
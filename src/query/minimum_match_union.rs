@@ -0,0 +1,193 @@
+use docset::{DocSet, SkipResult};
+use query::Scorer;
+use query::Union;
+use query::QueryContext;
+use query::score_combiner::{CountingCombiner, ScoreCombiner};
+use DocId;
+use Score;
+
+/// Union of several `Scorer`s that only matches the documents
+/// contained by at least `minimum_match` of them.
+///
+/// The scores of the matching scorers are combined
+/// by the `TScoreCombiner`, as in a `Union`.
+pub(crate) struct MinimumMatchUnion<TScorer, TScoreCombiner> {
+    union: Union<TScorer, CountingCombiner<TScoreCombiner>>,
+    minimum_match: usize,
+}
+
+impl<TScorer, TScoreCombiner> MinimumMatchUnion<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    /// Creates a `MinimumMatchUnion` whose buffers are taken from the given `QueryContext`.
+    pub fn with_context(
+        scorers: Vec<TScorer>,
+        minimum_match: usize,
+        context: &QueryContext,
+    ) -> MinimumMatchUnion<TScorer, TScoreCombiner> {
+        MinimumMatchUnion {
+            union: Union::with_context(scorers, context),
+            minimum_match,
+        }
+    }
+
+    /// Bounds the size hint of the union by the `max_doc` of the segment.
+    ///
+    /// See [`Union::set_max_doc`](./struct.Union.html#method.set_max_doc).
+    pub fn set_max_doc(&mut self, max_doc: DocId) {
+        self.union.set_max_doc(max_doc);
+    }
+
+    fn is_match(&self) -> bool {
+        self.union.doc_score_combiner().num_matches() >= self.minimum_match
+    }
+}
+
+impl<TScorer, TScoreCombiner> DocSet for MinimumMatchUnion<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    fn advance(&mut self) -> bool {
+        while self.union.advance() {
+            if self.is_match() {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        match self.union.skip_next(target) {
+            SkipResult::End => SkipResult::End,
+            skip_result => {
+                if self.is_match() {
+                    skip_result
+                } else if self.advance() {
+                    SkipResult::OverStep
+                } else {
+                    SkipResult::End
+                }
+            }
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.union.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.union.size_hint()
+    }
+
+    fn count_estimate(&self) -> u32 {
+        self.union.count_estimate()
+    }
+}
+
+impl<TScorer, TScoreCombiner> Scorer for MinimumMatchUnion<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    fn score(&mut self) -> Score {
+        self.union.score()
+    }
+
+    fn name(&self) -> &'static str {
+        "MinimumMatchUnion"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::MinimumMatchUnion;
+    use tests;
+    use docset::DocSet;
+    use DocId;
+    use postings::tests::test_skip_against_unoptimized;
+    use query::{ConstScorer, QueryContext, Scorer, VecDocSet};
+    use query::score_combiner::{DoNothingCombiner, SumCombiner};
+    use std::collections::BTreeMap;
+
+    fn make_scorers(vals: &[Vec<DocId>]) -> Vec<ConstScorer<VecDocSet>> {
+        vals.iter()
+            .cloned()
+            .map(VecDocSet::from)
+            .map(ConstScorer::new)
+            .collect()
+    }
+
+    fn aux_test_minimum_match_union(
+        vals: &[Vec<DocId>],
+        minimum_match: usize,
+        skip_targets: Vec<DocId>,
+    ) {
+        let mut doc_counts: BTreeMap<DocId, usize> = BTreeMap::new();
+        for vs in vals {
+            for &v in vs {
+                *doc_counts.entry(v).or_insert(0) += 1;
+            }
+        }
+        let expected_docs: Vec<DocId> = doc_counts
+            .iter()
+            .filter(|&(_, &count)| count >= minimum_match)
+            .map(|(&doc, _)| doc)
+            .collect();
+        let context = QueryContext::new();
+        let docset_factory = || {
+            let res: Box<DocSet> = box MinimumMatchUnion::<_, DoNothingCombiner>::with_context(
+                make_scorers(vals),
+                minimum_match,
+                &context,
+            );
+            res
+        };
+        let mut docset = docset_factory();
+        let mut docs = vec![];
+        while docset.advance() {
+            docs.push(docset.doc());
+        }
+        assert_eq!(docs, expected_docs);
+        assert_eq!(docset_factory().count(), expected_docs.len() as u32);
+        test_skip_against_unoptimized(docset_factory, skip_targets);
+    }
+
+    #[test]
+    fn test_minimum_match_union() {
+        let vals = vec![vec![1, 3, 5], vec![1, 2, 5], vec![2, 5, 9]];
+        let skip_targets = vec![0, 1, 2, 3, 4, 5, 6, 9, 10];
+        aux_test_minimum_match_union(&vals, 1, skip_targets.clone());
+        aux_test_minimum_match_union(&vals, 2, skip_targets.clone());
+        aux_test_minimum_match_union(&vals, 3, skip_targets.clone());
+        aux_test_minimum_match_union(&vals, 4, skip_targets);
+        let samples = vec![
+            tests::sample_with_seed(100_000, 0.05, 1),
+            tests::sample_with_seed(100_000, 0.02, 2),
+            tests::sample_with_seed(100_000, 0.1, 3),
+            tests::sample_with_seed(100_000, 0.01, 4),
+        ];
+        for minimum_match in 1..5 {
+            aux_test_minimum_match_union(
+                &samples,
+                minimum_match,
+                tests::sample_with_seed(100_000, 0.001, 5),
+            );
+        }
+    }
+
+    #[test]
+    fn test_minimum_match_union_score() {
+        let context = QueryContext::new();
+        let scorers = make_scorers(&[vec![1, 3, 5], vec![1, 2, 5], vec![2, 5, 9]]);
+        let mut union = MinimumMatchUnion::<_, SumCombiner>::with_context(scorers, 2, &context);
+        let mut scored_docs = vec![];
+        while union.advance() {
+            scored_docs.push((union.doc(), union.score()));
+        }
+        assert_eq!(scored_docs, vec![(1, 2.0), (2, 2.0), (5, 3.0)]);
+    }
+}
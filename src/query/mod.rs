@@ -13,15 +13,32 @@ mod phrase_query;
 mod all_query;
 mod bitset;
 mod range_query;
+mod prefix_query;
+mod term_set_query;
+mod regex_query;
+mod fast_field_range_query;
+mod exists_query;
+mod boost_query;
+mod constant_score_query;
+mod disjunction_max_query;
+mod synonym_query;
+mod more_like_this_query;
+mod query_dsl;
 mod exclude;
 mod union;
+mod minimum_match_union;
 mod intersection;
 mod reqopt_scorer;
+mod query_validation;
+mod scorer_diag;
+mod two_phase;
+mod explanation;
 
 #[cfg(test)]
 mod vec_docset;
 
 pub(crate) mod score_combiner;
+pub(crate) mod query_context;
 
 pub use self::intersection::Intersection;
 pub use self::union::Union;
@@ -34,14 +51,33 @@ pub use self::exclude::Exclude;
 pub use self::bitset::BitSetDocSet;
 pub use self::boolean_query::BooleanQuery;
 pub use self::occur::Occur;
-pub use self::phrase_query::PhraseQuery;
+pub use self::phrase_query::{PhrasePrefixQuery, PhraseQuery};
 pub use self::query_parser::QueryParserError;
+pub use self::query_parser::UnfieldedNumericBehavior;
+pub use self::query_parser::AutoPhrase;
 pub use self::query_parser::QueryParser;
-pub use self::query::Query;
+pub use self::query::{Query, QueryAny, QueryClone, QueryDisplay};
+pub(crate) use self::query::{collect_scorer, search_segments, SearchObserver};
+pub use self::query_validation::QueryValidationError;
 pub use self::scorer::EmptyScorer;
 pub use self::scorer::Scorer;
 pub use self::term_query::TermQuery;
 pub use self::weight::Weight;
+pub use self::query_context::QueryContext;
+pub use self::scorer_diag::{ScorerDiag, SkipReason};
+pub use self::explanation::Explanation;
+pub use self::two_phase::{TwoPhase, TwoPhaseIntersection};
 pub use self::all_query::{AllQuery, AllScorer, AllWeight};
 pub use self::range_query::RangeQuery;
+pub use self::prefix_query::{PrefixQuery, PrefixWeight};
+pub use self::term_set_query::{TermSetQuery, TermSetWeight};
+pub use self::regex_query::{RegexQuery, RegexWeight};
+pub use self::fast_field_range_query::{FastFieldRangeQuery, FastFieldRangeWeight};
+pub use self::exists_query::{ExistsQuery, ExistsWeight};
+pub use self::boost_query::{BoostQuery, BoostScorer, BoostWeight};
+pub use self::constant_score_query::{ConstantScoreQuery, ConstantScoreScorer, ConstantScoreWeight};
+pub use self::disjunction_max_query::{DisjunctionMaxQuery, DisjunctionMaxWeight};
+pub use self::synonym_query::{SynonymQuery, SynonymScorer, SynonymWeight};
+pub use self::more_like_this_query::{MltParams, MoreLikeThisQuery};
+pub use self::query_dsl::{from_json, QueryDslError};
 pub use self::scorer::ConstScorer;
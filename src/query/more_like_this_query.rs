@@ -0,0 +1,478 @@
+use schema::{Field, FieldType, IndexRecordOption, Schema, Term, Value};
+use query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery, Weight};
+use core::Searcher;
+use tokenizer::TokenizerManager;
+use error::ErrorKind;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::result;
+use query::QueryValidationError;
+use DocAddress;
+use Result;
+use Score;
+
+/// Parameters of a [`MoreLikeThisQuery`](./struct.MoreLikeThisQuery.html).
+#[derive(Clone, Debug)]
+pub struct MltParams {
+    /// Maximum number of terms of the query.
+    ///
+    /// Defaults to `25`.
+    pub max_query_terms: usize,
+    /// Minimum number of occurrences of a term in the source document
+    /// or text, for the term to be part of the query.
+    ///
+    /// Defaults to `2`.
+    pub min_term_freq: u32,
+    /// Minimum number of documents of the index containing a term,
+    /// for the term to be part of the query.
+    ///
+    /// Defaults to `5`.
+    pub min_doc_freq: u32,
+    /// Text fields the terms are extracted from, and searched in.
+    ///
+    /// If empty, all of the indexed text fields are used.
+    pub fields: Vec<Field>,
+    /// Field holding a unique key of the documents, e.g. an id.
+    ///
+    /// The source document of
+    /// [`for_document`](./struct.MoreLikeThisQuery.html#method.for_document)
+    /// is excluded from the results through its value for this field,
+    /// which should therefore be indexed as a single term (e.g. an integer
+    /// or a `STRING` field), and stored.
+    pub key_field: Option<Field>,
+}
+
+impl Default for MltParams {
+    fn default() -> MltParams {
+        MltParams {
+            max_query_terms: 25,
+            min_term_freq: 2,
+            min_doc_freq: 5,
+            fields: Vec::new(),
+            key_field: None,
+        }
+    }
+}
+
+/// `MoreLikeThisQuery` matches the documents similar to a given
+/// document or text.
+///
+/// The source text is analyzed with the tokenizers of the fields, and
+/// its most distinctive terms are kept: the ones with the highest TF-IDF,
+/// the term frequency being counted in the source, and the document frequency
+/// in the index. The query is then a disjunction of these terms, each boosted
+/// by its TF-IDF relatively to the best term.
+///
+/// The terms of a document are read from its stored text,
+/// so that the fields that are not stored are ignored.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::TopCollector;
+/// use tantivy::query::{MltParams, MoreLikeThisQuery};
+/// use tantivy::schema::{SchemaBuilder, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "The Old Man and the Sea"))?;
+///     index_writer.add_document(doc!(title => "Twenty Thousand Leagues Under the Sea"))?;
+///     index_writer.add_document(doc!(title => "Of Mice and Men"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let params = MltParams {
+///     min_term_freq: 1,
+///     min_doc_freq: 1,
+///     ..MltParams::default()
+/// };
+/// let query = MoreLikeThisQuery::for_text(&*searcher, index.tokenizers(), "sea", &params)?;
+/// let mut top_collector = TopCollector::with_limit(10);
+/// searcher.search(&query, &mut top_collector)?;
+/// assert_eq!(top_collector.docs().len(), 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MoreLikeThisQuery {
+    terms: Vec<(Term, Score)>,
+    excluded_term: Option<Term>,
+}
+
+impl MoreLikeThisQuery {
+    /// Creates a `MoreLikeThisQuery` matching the documents similar
+    /// to a document of the index.
+    ///
+    /// The document is excluded from the results if
+    /// `params.key_field` is set.
+    pub fn for_document(
+        searcher: &Searcher,
+        tokenizers: &TokenizerManager,
+        doc_address: &DocAddress,
+        params: &MltParams,
+    ) -> Result<MoreLikeThisQuery> {
+        let doc = searcher.doc(doc_address)?;
+        let schema = searcher.schema();
+        let mut term_freqs = HashMap::new();
+        for field in text_fields(schema, params) {
+            for value in doc.get_all(field) {
+                if let Value::Str(ref text) = *value {
+                    add_term_freqs(schema, tokenizers, field, text, &mut term_freqs)?;
+                }
+            }
+        }
+        let excluded_term = params
+            .key_field
+            .and_then(|key_field| doc.get_first(key_field).map(|value| (key_field, value)))
+            .map(|(key_field, value)| key_term(key_field, value));
+        Ok(MoreLikeThisQuery {
+            terms: select_terms(searcher, term_freqs, params),
+            excluded_term,
+        })
+    }
+
+    /// Creates a `MoreLikeThisQuery` matching the documents similar
+    /// to a text, e.g. a document that is not indexed.
+    ///
+    /// The text is analyzed with the tokenizer of each of the fields.
+    pub fn for_text(
+        searcher: &Searcher,
+        tokenizers: &TokenizerManager,
+        text: &str,
+        params: &MltParams,
+    ) -> Result<MoreLikeThisQuery> {
+        let schema = searcher.schema();
+        let mut term_freqs = HashMap::new();
+        for field in text_fields(schema, params) {
+            add_term_freqs(schema, tokenizers, field, text, &mut term_freqs)?;
+        }
+        Ok(MoreLikeThisQuery {
+            terms: select_terms(searcher, term_freqs, params),
+            excluded_term: None,
+        })
+    }
+
+    /// Returns the terms of the query, along with their boost,
+    /// by decreasing boost.
+    pub fn terms(&self) -> &[(Term, Score)] {
+        &self.terms
+    }
+
+    fn boolean_query(&self) -> BooleanQuery {
+        if self.terms.is_empty() {
+            // the excluded term alone would match all of the other documents.
+            return BooleanQuery::from(Vec::new());
+        }
+        let mut subqueries: Vec<(Occur, Box<Query>)> = self.terms
+            .iter()
+            .map(|&(ref term, boost)| {
+                let term_query: Box<Query> =
+                    box TermQuery::new(term.clone(), IndexRecordOption::WithFreqs);
+                let boost_query: Box<Query> = box BoostQuery::new(term_query, boost);
+                (Occur::Should, boost_query)
+            })
+            .collect();
+        if let Some(ref excluded_term) = self.excluded_term {
+            let excluded_query: Box<Query> =
+                box TermQuery::new(excluded_term.clone(), IndexRecordOption::Basic);
+            subqueries.push((Occur::MustNot, excluded_query));
+        }
+        BooleanQuery::from(subqueries)
+    }
+}
+
+impl Query for MoreLikeThisQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        self.boolean_query().weight(searcher, scoring_enabled)
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        self.boolean_query().validate(searcher)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MoreLikeThis ")?;
+        self.boolean_query().fmt_tree(schema, depth, f)
+    }
+}
+
+/// Returns the fields the terms are extracted from.
+fn text_fields(schema: &Schema, params: &MltParams) -> Vec<Field> {
+    if !params.fields.is_empty() {
+        return params.fields.clone();
+    }
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|&(_, field_entry)| match *field_entry.field_type() {
+            FieldType::Str(ref options) => options.get_indexing_options().is_some(),
+            _ => false,
+        })
+        .map(|(field_id, _)| Field(field_id as u32))
+        .collect()
+}
+
+/// Counts the occurrences of the terms of `text` in `term_freqs`.
+fn add_term_freqs(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    field: Field,
+    text: &str,
+    term_freqs: &mut HashMap<Term, u32>,
+) -> Result<()> {
+    let field_entry = schema.get_field_entry(field);
+    let tokenizer_name = match *field_entry.field_type() {
+        FieldType::Str(ref options) => options
+            .get_indexing_options()
+            .map(|indexing_options| indexing_options.tokenizer().to_string()),
+        _ => None,
+    }.ok_or_else(|| {
+        ErrorKind::InvalidArgument(format!(
+            "The field {:?} is not an indexed text field",
+            field_entry.name()
+        ))
+    })?;
+    let tokenizer = tokenizers.get(&tokenizer_name).ok_or_else(|| {
+        ErrorKind::InvalidArgument(format!("Unknown tokenizer {:?}", tokenizer_name))
+    })?;
+    let mut token_stream = tokenizer.token_stream(text);
+    token_stream.process(&mut |token| {
+        *term_freqs
+            .entry(Term::from_field_text(field, &token.text))
+            .or_insert(0) += 1;
+    });
+    Ok(())
+}
+
+/// Returns the term matching the value of the key field of a document.
+fn key_term(key_field: Field, value: &Value) -> Term {
+    match *value {
+        Value::Str(ref text) => Term::from_field_text(key_field, text),
+        Value::U64(val) => Term::from_field_u64(key_field, val),
+        Value::I64(val) => Term::from_field_i64(key_field, val),
+        Value::Facet(ref facet) => Term::from_facet(key_field, facet),
+    }
+}
+
+/// Returns the `params.max_query_terms` terms with the highest TF-IDF,
+/// boosted by their TF-IDF relatively to the best term.
+fn select_terms(
+    searcher: &Searcher,
+    term_freqs: HashMap<Term, u32>,
+    params: &MltParams,
+) -> Vec<(Term, Score)> {
+    let num_docs = searcher.num_docs() as Score;
+    let mut scored_terms: Vec<(Term, Score)> = term_freqs
+        .into_iter()
+        .filter(|&(_, term_freq)| term_freq >= params.min_term_freq)
+        .filter_map(|(term, term_freq)| {
+            let doc_freq = searcher.doc_freq(&term);
+            if doc_freq == 0 || doc_freq < params.min_doc_freq {
+                return None;
+            }
+            let idf = 1.0 + (num_docs / (doc_freq as Score + 1.0)).ln();
+            Some((term, term_freq as Score * idf))
+        })
+        .collect();
+    scored_terms.sort_by(|left, right| {
+        right
+            .1
+            .partial_cmp(&left.1)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| left.0.cmp(&right.0))
+    });
+    scored_terms.truncate(params.max_query_terms);
+    if let Some(best_score) = scored_terms.first().map(|&(_, score)| score) {
+        for scored_term in &mut scored_terms {
+            scored_term.1 /= best_score;
+        }
+    }
+    scored_terms
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{MltParams, MoreLikeThisQuery};
+    use collector::TopCollector;
+    use core::Searcher;
+    use schema::{Field, SchemaBuilder, INT_INDEXED, INT_STORED, STORED, TEXT};
+    use query::{Query, QueryDisplay};
+    use Index;
+    use DocAddress;
+
+    const BODIES: [&'static str; 11] = [
+        // cooking
+        "Slowly simmer the tomato sauce with garlic and basil, \
+         then pour the tomato sauce over the pasta.",
+        "This pasta recipe uses a rich tomato sauce, fresh basil and a little garlic.",
+        "Bake the pasta with tomato sauce and cheese in a hot oven.",
+        "Season the garlic butter sauce with basil and serve it over fresh pasta.",
+        // space
+        "The rocket launched the satellite into orbit around the planet.",
+        "Astronauts aboard the station watched the planet from orbit.",
+        "The telescope observed a distant planet orbiting another star.",
+        "A new rocket engine will send the probe beyond the orbit of Mars.",
+        // football
+        "The striker scored a late goal and the team won the match.",
+        "The goalkeeper saved a penalty in the final minutes of the match.",
+        "Fans celebrated as the team lifted the trophy after the final match.",
+    ];
+
+    fn create_index() -> (Index, Field, Field) {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED | INT_STORED);
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for (doc_id, text) in BODIES.iter().enumerate() {
+                index_writer
+                    .add_document(doc!(id => doc_id as u64, body => *text))
+                    .unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        (index, id, body)
+    }
+
+    fn search_ids(searcher: &Searcher, query: &Query, id: Field) -> Vec<u64> {
+        let mut top_collector = TopCollector::with_limit(BODIES.len());
+        searcher.search(query, &mut top_collector).unwrap();
+        top_collector
+            .docs()
+            .iter()
+            .map(|doc_address| {
+                let doc = searcher.doc(doc_address).unwrap();
+                doc.get_first(id).unwrap().u64_value()
+            })
+            .collect()
+    }
+
+    fn sorted(mut ids: Vec<u64>) -> Vec<u64> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_more_like_this_document() {
+        let (index, id, body) = create_index();
+        let searcher = index.searcher();
+        let params = MltParams {
+            min_term_freq: 1,
+            min_doc_freq: 1,
+            fields: vec![body],
+            key_field: Some(id),
+            ..MltParams::default()
+        };
+        let source_doc = DocAddress(0, 0);
+        let query =
+            MoreLikeThisQuery::for_document(&*searcher, index.tokenizers(), &source_doc, &params)
+                .unwrap();
+        let ids = search_ids(&*searcher, &query, id);
+        // the other cooking documents rank first...
+        assert_eq!(sorted(ids[..3].to_vec()), vec![1, 2, 3]);
+        // ... and the source document is excluded.
+        assert!(!ids.contains(&0));
+
+        // without a key field, the source document is the best match.
+        let params = MltParams {
+            key_field: None,
+            ..params
+        };
+        let query =
+            MoreLikeThisQuery::for_document(&*searcher, index.tokenizers(), &source_doc, &params)
+                .unwrap();
+        assert_eq!(search_ids(&*searcher, &query, id)[0], 0);
+    }
+
+    #[test]
+    fn test_more_like_this_text() {
+        let (index, id, _) = create_index();
+        let searcher = index.searcher();
+        let params = MltParams {
+            min_term_freq: 1,
+            min_doc_freq: 1,
+            ..MltParams::default()
+        };
+        let query = MoreLikeThisQuery::for_text(
+            &*searcher,
+            index.tokenizers(),
+            "A rocket sent into orbit around a distant planet",
+            &params,
+        ).unwrap();
+        let ids = search_ids(&*searcher, &query, id);
+        assert_eq!(sorted(ids[..4].to_vec()), vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_more_like_this_terms() {
+        let (index, _, _) = create_index();
+        let searcher = index.searcher();
+        let source_doc = DocAddress(0, 0);
+        let term_texts = |query: &MoreLikeThisQuery| -> Vec<String> {
+            query
+                .terms()
+                .iter()
+                .map(|&(ref term, _)| term.text().to_string())
+                .collect()
+        };
+
+        // `the` is the only term occurring twice in the document,
+        // and in 5 documents of the index.
+        let query = MoreLikeThisQuery::for_document(
+            &*searcher,
+            index.tokenizers(),
+            &source_doc,
+            &MltParams::default(),
+        ).unwrap();
+        assert_eq!(term_texts(&query), vec!["the".to_string()]);
+        assert_eq!(query.terms()[0].1, 1.0);
+
+        let params = MltParams {
+            max_query_terms: 2,
+            min_term_freq: 1,
+            min_doc_freq: 1,
+            ..MltParams::default()
+        };
+        let query =
+            MoreLikeThisQuery::for_document(&*searcher, index.tokenizers(), &source_doc, &params)
+                .unwrap();
+        assert_eq!(
+            term_texts(&query),
+            vec!["tomato".to_string(), "sauce".to_string()]
+        );
+        assert_eq!(query.terms()[0].1, 1.0);
+        assert!(query.terms()[1].1 < 1.0);
+        assert_eq!(
+            QueryDisplay::new(&query, searcher.schema()).to_string(),
+            format!(
+                "MoreLikeThis Boolean\n\
+                 \x20 Should Boost(1) Term(body:\"tomato\")\n\
+                 \x20 Should Boost({}) Term(body:\"sauce\")\n",
+                query.terms()[1].1
+            )
+        );
+
+        // nothing is similar to a text with unknown terms only.
+        let query = MoreLikeThisQuery::for_text(
+            &*searcher,
+            index.tokenizers(),
+            "zzz",
+            &MltParams::default(),
+        ).unwrap();
+        assert!(query.terms().is_empty());
+    }
+}
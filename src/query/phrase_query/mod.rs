@@ -1,10 +1,13 @@
 mod phrase_query;
 mod phrase_weight;
 mod phrase_scorer;
+mod phrase_prefix_query;
+mod multi_term_postings;
 
 pub use self::phrase_query::PhraseQuery;
 pub use self::phrase_weight::PhraseWeight;
 pub use self::phrase_scorer::PhraseScorer;
+pub use self::phrase_prefix_query::{PhrasePrefixQuery, PhrasePrefixWeight};
 
 #[cfg(test)]
 mod tests {
@@ -13,6 +16,7 @@ mod tests {
     use core::Index;
     use schema::{SchemaBuilder, Term, TEXT};
     use collector::tests::TestCollector;
+    use query::QueryDisplay;
 
     #[test]
     pub fn test_phrase_query() {
@@ -25,27 +29,27 @@ mod tests {
             {
                 // 0
                 let doc = doc!(text_field=>"b b b d c g c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 1
                 let doc = doc!(text_field=>"a b b d c g c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 2
                 let doc = doc!(text_field=>"a b a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 3
                 let doc = doc!(text_field=>"c a b a d ga a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 4
                 let doc = doc!(text_field=>"a b c");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -74,6 +78,70 @@ mod tests {
         assert_eq!(test_query(vec!["g", "a"]), empty_vec);
     }
 
+    #[test]
+    pub fn test_phrase_query_with_offset() {
+        use collector::CountCollector;
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c d")).unwrap(); // 0
+            index_writer.add_document(doc!(text_field=>"a x c d")).unwrap(); // 1
+            index_writer.add_document(doc!(text_field=>"a c d")).unwrap(); // 2
+            index_writer.add_document(doc!(text_field=>"c a")).unwrap(); // 3
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let phrase_query = |terms: &[(usize, &str)]| {
+            let terms: Vec<(usize, Term)> = terms
+                .iter()
+                .map(|&(offset, text)| (offset, Term::from_field_text(text_field, text)))
+                .collect();
+            PhraseQuery::new_with_offset(terms)
+        };
+        let test_query = |phrase_query: &PhraseQuery| {
+            let mut test_collector = TestCollector::default();
+            searcher
+                .search(phrase_query, &mut test_collector)
+                .expect("search should succeed");
+            test_collector.docs()
+        };
+
+        assert_eq!(test_query(&phrase_query(&[(0, "a"), (2, "c")])), vec![0, 1]);
+        // only the differences between the positions matter.
+        assert_eq!(test_query(&phrase_query(&[(5, "a"), (7, "c")])), vec![0, 1]);
+        assert_eq!(test_query(&phrase_query(&[(2, "c"), (0, "a")])), vec![0, 1]);
+        assert_eq!(test_query(&phrase_query(&[(0, "a"), (2, "c"), (3, "d")])), vec![0, 1]);
+        assert_eq!(test_query(&phrase_query(&[(0, "a"), (1, "c")])), vec![2]);
+
+        let mut counting_query = phrase_query(&[(0, "a"), (2, "c")]);
+        counting_query.set_count_occurrences(true);
+        assert_eq!(test_query(&counting_query), vec![0, 1]);
+        let mut count_collector = CountCollector::default();
+        searcher.search(&counting_query, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 2);
+
+        let mut sloppy_query = phrase_query(&[(0, "a"), (2, "c")]);
+        sloppy_query.set_slop(1);
+        assert_eq!(test_query(&sloppy_query), vec![0, 1, 2]);
+
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&phrase_query(&[(0, "a"), (2, "c")]), &schema)),
+            "Phrase(text:\"a\" ? \"c\")\n"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                QueryDisplay::new(&phrase_query(&[(0, "a"), (0, "b"), (1, "c")]), &schema)
+            ),
+            "Phrase(text:\"a\"|\"b\" \"c\")\n"
+        );
+    }
+
     #[test] // motivated by #234
     pub fn test_phrase_query_docfreq_order() {
         let mut schema_builder = SchemaBuilder::default();
@@ -85,17 +153,17 @@ mod tests {
             {
                 // 0
                 let doc = doc!(text_field=>"b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 1
                 let doc = doc!(text_field=>"a b");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             {
                 // 2
                 let doc = doc!(text_field=>"b a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -117,4 +185,297 @@ mod tests {
         assert_eq!(test_query(vec!["a", "b"]), vec![1]);
         assert_eq!(test_query(vec!["b", "a"]), vec![2]);
     }
+
+    #[test]
+    pub fn test_phrase_query_count_occurrences() {
+        use collector::TopCollector;
+        use docset::DocSet;
+        use query::Scorer;
+        use schema::IndexRecordOption;
+        use DocAddress;
+        use Score;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // both of the documents have 6 tokens.
+            index_writer.add_document(doc!(text_field=>"a b c d e f")).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b a b a b")).unwrap();
+            index_writer.add_document(doc!(text_field=>"c d")).unwrap();
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let terms = vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ];
+        let scores = |count_occurrences: bool| {
+            let mut phrase_query = PhraseQuery::from(terms.clone());
+            phrase_query.set_count_occurrences(count_occurrences);
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(&phrase_query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+
+        let score_docs = scores(false);
+        assert_eq!(score_docs.len(), 2);
+        for &(score, _) in &score_docs {
+            assert_eq!(score, 1.0);
+        }
+
+        // `a` and `b` appear in 2 of the 3 documents: their idf is 1.
+        let score_docs = scores(true);
+        assert_eq!(score_docs.len(), 2);
+        let (score_three, doc_three) = score_docs[0];
+        let (score_once, doc_once) = score_docs[1];
+        assert_eq!(doc_three, DocAddress(0, 1));
+        assert_eq!(doc_once, DocAddress(0, 0));
+        assert!((score_three - 2.0 * (3.0 as Score / 6.0).sqrt()).abs() < 1e-5);
+        assert!((score_once - 2.0 * (1.0 as Score / 6.0).sqrt()).abs() < 1e-5);
+
+        let segment_reader = searcher.segment_reader(0);
+        let inverted_index = segment_reader.inverted_index(text_field);
+        let postings = || {
+            terms
+                .iter()
+                .map(|term| {
+                    inverted_index
+                        .read_postings(term, IndexRecordOption::WithFreqsAndPositions)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the search stops at the first occurrence of the phrase.
+        let mut phrase_scorer = PhraseScorer::new(postings());
+        assert!(phrase_scorer.advance());
+        assert_eq!(phrase_scorer.doc(), 0);
+        assert_eq!(phrase_scorer.num_positions_read(), 2);
+        assert!(phrase_scorer.advance());
+        assert_eq!(phrase_scorer.doc(), 1);
+        assert_eq!(phrase_scorer.phrase_freq(), 1);
+        assert_eq!(phrase_scorer.num_positions_read(), 4);
+        assert_eq!(phrase_scorer.score(), 1.0);
+        assert!(!phrase_scorer.advance());
+
+        // all of the positions are read to count the occurrences.
+        let mut phrase_scorer = PhraseScorer::with_phrase_freq(postings(), 2.0, None);
+        assert!(phrase_scorer.advance());
+        assert_eq!(phrase_scorer.doc(), 0);
+        assert_eq!(phrase_scorer.phrase_freq(), 1);
+        assert_eq!(phrase_scorer.num_positions_read(), 2);
+        assert!(phrase_scorer.advance());
+        assert_eq!(phrase_scorer.doc(), 1);
+        assert_eq!(phrase_scorer.phrase_freq(), 3);
+        assert_eq!(phrase_scorer.num_positions_read(), 8);
+        assert!((phrase_scorer.score() - 2.0 * (3.0 as Score).sqrt()).abs() < 1e-5);
+        assert!(!phrase_scorer.advance());
+    }
+
+    #[test]
+    pub fn test_phrase_query_explain() {
+        use collector::TopCollector;
+        use query::Query;
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b c d e f")).unwrap();
+            index_writer.add_document(doc!(text_field=>"a b a b a b")).unwrap();
+            index_writer.add_document(doc!(text_field=>"c d")).unwrap();
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut phrase_query = PhraseQuery::from(vec![
+            Term::from_field_text(text_field, "a"),
+            Term::from_field_text(text_field, "b"),
+        ]);
+
+        let explanation = phrase_query.explain(&searcher, DocAddress(0, 1)).unwrap();
+        assert_eq!(explanation.value(), 1.0);
+        assert_eq!(explanation.details().len(), 1);
+        assert_eq!(explanation.details()[0].value(), 1.0);
+
+        phrase_query.set_count_occurrences(true);
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&phrase_query, &mut top_collector).unwrap();
+        for (score, doc_address) in top_collector.score_docs() {
+            let explanation = phrase_query.explain(&searcher, doc_address).unwrap();
+            assert_eq!(explanation.value(), score);
+        }
+        let explanation = phrase_query.explain(&searcher, DocAddress(0, 1)).unwrap();
+        assert_eq!(explanation.details().len(), 3);
+        // idf, phrase frequency and fieldnorm.
+        assert_eq!(explanation.details()[1].value(), 3.0);
+        assert_eq!(explanation.details()[2].value(), 6.0);
+
+        assert!(phrase_query.explain(&searcher, DocAddress(0, 2)).is_err());
+    }
+
+    #[test]
+    pub fn test_phrase_query_slop() {
+        use collector::TopCollector;
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for text in &[
+                "the quick brown fox", // 0
+                "the quick fox",       // 1
+                "the fox quick",       // 2
+                "quick a b c fox",     // 3
+                "fox",                 // 4
+                "fox fox",             // 5
+                "fox jumps quick",     // 6
+            ] {
+                index_writer.add_document(doc!(text_field => *text)).unwrap();
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let phrase_query = |texts: &[&str], slop: u32| {
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            PhraseQuery::new_with_slop(terms, slop)
+        };
+        let test_query = |texts: &[&str], slop: u32| {
+            let mut test_collector = TestCollector::default();
+            searcher
+                .search(&phrase_query(texts, slop), &mut test_collector)
+                .expect("search should succeed");
+            test_collector.docs()
+        };
+
+        let empty_vec = Vec::<u32>::new();
+
+        assert_eq!(test_query(&["quick", "fox"], 0), vec![1]);
+        assert_eq!(test_query(&["quick", "fox"], 1), vec![0, 1]);
+        // the terms are swapped in the document 2.
+        assert_eq!(test_query(&["quick", "fox"], 2), vec![0, 1, 2]);
+        assert_eq!(test_query(&["quick", "fox"], 3), vec![0, 1, 2, 3, 6]);
+        assert_eq!(test_query(&["fox", "quick"], 0), vec![2]);
+        assert_eq!(test_query(&["fox", "quick"], 1), vec![2, 6]);
+        assert_eq!(test_query(&["the", "quick", "fox"], 1), vec![0, 1]);
+        assert_eq!(test_query(&["the", "fox", "quick"], 1), vec![2]);
+
+        // the repeated terms of the phrase match distinct positions.
+        assert_eq!(test_query(&["fox", "fox"], 0), vec![5]);
+        assert_eq!(test_query(&["fox", "fox"], 10), vec![5]);
+        assert_eq!(test_query(&["fox", "fox", "fox"], 10), empty_vec);
+        assert_eq!(test_query(&["quick", "fox", "quick"], 10), empty_vec);
+
+        // the closer the occurrence, the higher the score.
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher
+            .search(&phrase_query(&["quick", "fox"], 2), &mut top_collector)
+            .unwrap();
+        let score_docs = top_collector.score_docs();
+        let expected_score_docs = vec![
+            (1.0, DocAddress(0, 1)),
+            (0.5, DocAddress(0, 0)),
+            (1.0 / 3.0, DocAddress(0, 2)),
+        ];
+        assert_eq!(score_docs.len(), expected_score_docs.len());
+        for (&(score, doc), &(expected_score, expected_doc)) in
+            score_docs.iter().zip(expected_score_docs.iter())
+        {
+            assert_eq!(doc, expected_doc);
+            assert!((score - expected_score).abs() < 1e-5);
+        }
+
+        // counting the occurrences sums their weights.
+        let mut sloppy_query = phrase_query(&["quick", "fox"], 2);
+        sloppy_query.set_count_occurrences(true);
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&sloppy_query, &mut top_collector).unwrap();
+        assert_eq!(top_collector.docs().len(), 3);
+
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&phrase_query(&["quick", "fox"], 2), &schema)),
+            "Phrase(text:\"quick\" \"fox\")~2\n"
+        );
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&phrase_query(&["quick", "fox"], 0), &schema)),
+            "Phrase(text:\"quick\" \"fox\")\n"
+        );
+    }
+
+    #[test]
+    pub fn test_phrase_prefix_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for text in &[
+                "New York",              // 0
+                "new yorkshire pudding", // 1
+                "York new",              // 2
+                "new jersey",            // 3
+                "the new yoyo",          // 4
+                "new and york",          // 5
+                "old new new yorktown",  // 6
+            ] {
+                index_writer.add_document(doc!(text_field => *text)).unwrap();
+            }
+            assert!(index_writer.commit().is_ok());
+        }
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |texts: Vec<&str>, max_expansions: usize| {
+            let mut test_collector = TestCollector::default();
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            let mut phrase_prefix_query = PhrasePrefixQuery::new(terms);
+            phrase_prefix_query.set_max_expansions(max_expansions);
+            assert!(searcher.check_query(&phrase_prefix_query).is_ok());
+            searcher
+                .search(&phrase_prefix_query, &mut test_collector)
+                .expect("search should succeed");
+            test_collector.docs()
+        };
+
+        let empty_vec = Vec::<u32>::new();
+
+        assert_eq!(test_query(vec!["new", "yor"], 50), vec![0, 1, 6]);
+        assert_eq!(test_query(vec!["new", "yo"], 50), vec![0, 1, 4, 6]);
+        assert_eq!(test_query(vec!["new", "york"], 50), vec![0, 1, 6]);
+        assert_eq!(test_query(vec!["new", "yorks"], 50), vec![1]);
+        assert_eq!(test_query(vec!["the", "new", "yo"], 50), vec![4]);
+        assert_eq!(test_query(vec!["old", "new", "new", "yor"], 50), vec![6]);
+        assert_eq!(test_query(vec!["new", "z"], 50), empty_vec);
+        assert_eq!(test_query(vec!["jersey", "yor"], 50), empty_vec);
+        assert_eq!(test_query(vec!["nothing", "yor"], 50), empty_vec);
+
+        // the expansions are taken in sorted order:
+        // `york`, `yorkshire`, then `yorktown`.
+        assert_eq!(test_query(vec!["new", "yor"], 1), vec![0]);
+        assert_eq!(test_query(vec!["new", "yor"], 2), vec![0, 1]);
+        assert_eq!(test_query(vec!["new", "yor"], 0), empty_vec);
+
+        let phrase_prefix_query = PhrasePrefixQuery::new(vec![
+            Term::from_field_text(text_field, "new"),
+            Term::from_field_text(text_field, "yor"),
+        ]);
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&phrase_prefix_query, &schema)),
+            "PhrasePrefix(text:\"new\" \"yor\")\n"
+        );
+    }
 }
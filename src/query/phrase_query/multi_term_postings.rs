@@ -0,0 +1,134 @@
+use DocId;
+use std::cmp;
+use docset::{DocSet, SkipResult};
+use postings::{Postings, SegmentPostings};
+
+/// Union of the postings of several terms, that behaves
+/// as the postings of one single term.
+///
+/// The positions of a document are the merged positions
+/// of all of the terms it contains, and its term frequency
+/// is the sum of their term frequencies.
+pub struct MultiTermPostings {
+    // postings that are not exhausted yet.
+    postings: Vec<SegmentPostings>,
+    started: bool,
+    doc: DocId,
+    term_freq: u32,
+    positions: Vec<u32>,
+    // bound of the size hint.
+    max_doc: DocId,
+}
+
+impl MultiTermPostings {
+    /// Creates the union of the postings, of a segment with `max_doc` documents.
+    pub fn new(postings: Vec<SegmentPostings>, max_doc: DocId) -> MultiTermPostings {
+        MultiTermPostings {
+            postings,
+            started: false,
+            doc: 0,
+            term_freq: 0,
+            positions: Vec::new(),
+            max_doc,
+        }
+    }
+
+    /// Moves each of the postings with `move_postings`,
+    /// and removes the postings it exhausted.
+    fn move_postings<F>(&mut self, mut move_postings: F)
+    where
+        F: FnMut(&mut SegmentPostings) -> bool,
+    {
+        let mut i = 0;
+        while i < self.postings.len() {
+            if move_postings(&mut self.postings[i]) {
+                i += 1;
+            } else {
+                self.postings.swap_remove(i);
+            }
+        }
+    }
+
+    /// Positions the union on the smallest document
+    /// of the remaining postings.
+    fn update_current(&mut self) -> bool {
+        let doc = match self.postings.iter().map(|postings| postings.doc()).min() {
+            Some(doc) => doc,
+            None => {
+                return false;
+            }
+        };
+        self.doc = doc;
+        self.term_freq = 0;
+        self.positions.clear();
+        for postings in &self.postings {
+            if postings.doc() == doc {
+                self.term_freq += postings.term_freq();
+                self.positions.extend_from_slice(postings.positions());
+            }
+        }
+        self.positions.sort();
+        self.positions.dedup();
+        true
+    }
+}
+
+impl Postings for MultiTermPostings {
+    fn term_freq(&self) -> u32 {
+        self.term_freq
+    }
+
+    fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+}
+
+impl DocSet for MultiTermPostings {
+    fn advance(&mut self) -> bool {
+        let (started, doc) = (self.started, self.doc);
+        self.started = true;
+        self.move_postings(|postings| {
+            if started && postings.doc() != doc {
+                true
+            } else {
+                postings.advance()
+            }
+        });
+        self.update_current()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let (started, doc) = (self.started, self.doc);
+        self.started = true;
+        self.move_postings(|postings| {
+            if !started {
+                postings.skip_next(target) != SkipResult::End
+            } else if postings.doc() == doc && doc >= target {
+                // skipping always moves past the current document.
+                postings.advance()
+            } else if postings.doc() >= target {
+                true
+            } else {
+                postings.skip_next(target) != SkipResult::End
+            }
+        });
+        if !self.update_current() {
+            SkipResult::End
+        } else if self.doc == target {
+            SkipResult::Reached
+        } else {
+            SkipResult::OverStep
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        let size_hint = self.postings
+            .iter()
+            .fold(0u32, |size_hint, postings| size_hint.saturating_add(postings.size_hint()));
+        cmp::min(size_hint, self.max_doc)
+    }
+}
@@ -0,0 +1,173 @@
+use schema::Term;
+use query::{EmptyScorer, Query, Scorer, Weight};
+use query::prefix_query::prefix_term_stream;
+use core::SegmentReader;
+use core::searcher::Searcher;
+use super::PhraseScorer;
+use super::multi_term_postings::MultiTermPostings;
+use termdict::TermStreamer;
+use Result;
+use std::result;
+use schema::IndexRecordOption;
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use schema::{term_to_string, value_bytes_to_string, Schema};
+use std::fmt;
+
+const DEFAULT_MAX_EXPANSIONS: usize = 50;
+
+/// `PhrasePrefixQuery` matches a sequence of words,
+/// the last of which is only given by a prefix.
+///
+/// For instance, the phrase prefix query for `"new yor"`
+/// matches the sentences **I love New York** and
+/// **New Yorkshire pudding**, but not **York is new**.
+///
+/// The last term of the phrase is expanded to the terms
+/// of the segment starting with it, taken in sorted order.
+/// Only the first [`max_expansions`](#method.set_max_expansions)
+/// of them are searched: the others are silently ignored.
+///
+/// As for the [`PhraseQuery`](./struct.PhraseQuery.html),
+/// the positions of the field must be indexed, and all of the
+/// matching documents get a score of 1.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::CountCollector;
+/// use tantivy::query::PhrasePrefixQuery;
+/// use tantivy::schema::{SchemaBuilder, Term, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "I love New York"))?;
+///     index_writer.add_document(doc!(title => "New Yorkshire pudding"))?;
+///     index_writer.add_document(doc!(title => "York is new"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let query = PhrasePrefixQuery::new(vec![
+///     Term::from_field_text(title, "new"),
+///     Term::from_field_text(title, "yor"),
+/// ]);
+/// let mut count_collector = CountCollector::default();
+/// searcher.search(&query, &mut count_collector)?;
+/// assert_eq!(count_collector.count(), 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PhrasePrefixQuery {
+    phrase_terms: Vec<Term>,
+    prefix: Term,
+    max_expansions: usize,
+}
+
+impl PhrasePrefixQuery {
+    /// Creates a new phrase prefix query.
+    ///
+    /// The last of the terms is the prefix.
+    ///
+    /// # Panics
+    /// Panics if there are less than two terms.
+    pub fn new(mut terms: Vec<Term>) -> PhrasePrefixQuery {
+        assert!(terms.len() > 1);
+        let prefix = terms.pop().expect("A phrase prefix query has at least two terms");
+        PhrasePrefixQuery {
+            phrase_terms: terms,
+            prefix,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Sets the maximum number of terms the prefix is expanded to,
+    /// in each segment.
+    ///
+    /// The terms starting with the prefix are taken in sorted order, and
+    /// the terms beyond the limit are silently ignored.
+    ///
+    /// Defaults to `50`.
+    pub fn set_max_expansions(&mut self, max_expansions: usize) {
+        self.max_expansions = max_expansions;
+    }
+}
+
+impl Query for PhrasePrefixQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(box PhrasePrefixWeight {
+            phrase_terms: self.phrase_terms.clone(),
+            prefix: self.prefix.clone(),
+            max_expansions: self.max_expansions,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        for term in self.phrase_terms.iter().chain(Some(&self.prefix)) {
+            validate_indexed_field(
+                searcher.schema(),
+                term.field(),
+                IndexRecordOption::WithPositions,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PhrasePrefix({}", term_to_string(schema, &self.phrase_terms[0]))?;
+        for term in self.phrase_terms[1..].iter().chain(Some(&self.prefix)) {
+            let field_type = schema.get_field_entry(term.field()).field_type();
+            write!(f, " {}", value_bytes_to_string(field_type, term.value_bytes()))?;
+        }
+        writeln!(f, ")")
+    }
+}
+
+pub struct PhrasePrefixWeight {
+    phrase_terms: Vec<Term>,
+    prefix: Term,
+    max_expansions: usize,
+}
+
+impl Weight for PhrasePrefixWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let mut term_postings_list = Vec::new();
+        for term in &self.phrase_terms {
+            if let Some(postings) = reader
+                .inverted_index(term.field())
+                .read_postings(term, IndexRecordOption::WithFreqsAndPositions)
+            {
+                term_postings_list.push(MultiTermPostings::new(vec![postings], reader.max_doc()));
+            } else {
+                return Ok(box EmptyScorer);
+            }
+        }
+        let inverted_index = reader.inverted_index(self.prefix.field());
+        let mut prefix_postings = Vec::new();
+        {
+            let mut term_stream =
+                prefix_term_stream(inverted_index.terms(), self.prefix.value_bytes());
+            while prefix_postings.len() < self.max_expansions && term_stream.advance() {
+                prefix_postings.push(inverted_index.read_postings_from_terminfo(
+                    term_stream.value(),
+                    IndexRecordOption::WithFreqsAndPositions,
+                ));
+            }
+        }
+        if prefix_postings.is_empty() {
+            return Ok(box EmptyScorer);
+        }
+        term_postings_list.push(MultiTermPostings::new(prefix_postings, reader.max_doc()));
+        Ok(box PhraseScorer::new(term_postings_list))
+    }
+}
@@ -4,6 +4,13 @@ use core::searcher::Searcher;
 use super::PhraseWeight;
 use query::Weight;
 use Result;
+use Score;
+use std::result;
+use schema::IndexRecordOption;
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use schema::{term_to_string, value_bytes_to_string, Schema};
+use std::fmt;
 
 /// `PhraseQuery` matches a specific sequence of words.
 ///
@@ -19,26 +26,161 @@ use Result;
 /// Using a `PhraseQuery` on a field requires positions
 /// to be indexed for this field.
 ///
-#[derive(Debug)]
+/// By default, all of the matching documents get a score of 1.
+/// See [`set_count_occurrences`](#method.set_count_occurrences)
+/// to score them by the number of occurrences of the phrase.
+///
+/// The terms of the phrase must be adjacent, unless a slop
+/// is set with [`set_slop`](#method.set_slop).
+/// Their positions in the phrase may also be given explicitly,
+/// with [`new_with_offset`](#method.new_with_offset).
+///
+#[derive(Clone, Debug)]
 pub struct PhraseQuery {
-    phrase_terms: Vec<Term>,
+    // the terms of the phrase, sorted by their positions in the phrase.
+    phrase_terms: Vec<(usize, Term)>,
+    count_occurrences: bool,
+    slop: u32,
+}
+
+impl PhraseQuery {
+    /// Creates a new `PhraseQuery` for the terms at the given
+    /// positions in the phrase.
+    ///
+    /// Positions may be skipped, e.g. for the stop words that the
+    /// tokenizer removed, and several terms may share the same position,
+    /// e.g. for the synonyms injected by the tokenizer.
+    /// Only the differences between the positions matter.
+    ///
+    /// # Panics
+    ///
+    /// If there are less than two terms.
+    pub fn new_with_offset(mut phrase_terms: Vec<(usize, Term)>) -> PhraseQuery {
+        assert!(phrase_terms.len() > 1);
+        phrase_terms.sort_by_key(|&(offset, _)| offset);
+        PhraseQuery {
+            phrase_terms,
+            count_occurrences: false,
+            slop: 0,
+        }
+    }
+
+    /// Creates a new `PhraseQuery` with a given slop.
+    ///
+    /// See [`set_slop`](#method.set_slop).
+    pub fn new_with_slop(phrase_terms: Vec<Term>, slop: u32) -> PhraseQuery {
+        let mut phrase_query = PhraseQuery::from(phrase_terms);
+        phrase_query.set_slop(slop);
+        phrase_query
+    }
+
+    /// Sets the slop of the phrase, i.e. how far its terms
+    /// may be from their positions in the phrase.
+    ///
+    /// The distance of an occurrence is the difference between the
+    /// largest and the smallest shift of its terms from their positions
+    /// in the phrase. For instance, with a slop of 1, `"quick fox"`
+    /// matches `quick brown fox`. With a slop of 2, it also matches
+    /// `fox quick`. The repeated terms of the phrase must match
+    /// distinct positions.
+    ///
+    /// The occurrences are weighted by `1 / (1 + distance)`: without
+    /// [counting the occurrences](#method.set_count_occurrences), a
+    /// document gets the weight of its closest occurrence as a score.
+    ///
+    /// The slop defaults to 0, which only matches the exact phrase.
+    pub fn set_slop(&mut self, slop: u32) {
+        self.slop = slop;
+    }
+
+    /// The slop of the phrase.
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+
+    /// Enables or disables the counting of the occurrences
+    /// of the phrase in each document.
+    ///
+    /// When enabled, the number of occurrences of the phrase is
+    /// used as the term frequency of a `TermQuery`, together with the
+    /// fieldnorm of the field, and with the sum of the idf of the terms
+    /// as the idf of the phrase.
+    ///
+    /// When disabled, the search stops at the first occurrence of the
+    /// phrase in each document, which is faster, and all of the matching
+    /// documents get a score of 1.
+    ///
+    /// It is disabled by default.
+    pub fn set_count_occurrences(&mut self, count_occurrences: bool) {
+        self.count_occurrences = count_occurrences;
+    }
+
+    fn idf(&self, searcher: &Searcher) -> Score {
+        let num_docs = searcher.num_docs() as Score;
+        self.phrase_terms
+            .iter()
+            .map(|&(_, ref term)| {
+                let doc_freq = searcher.doc_freq(term) as Score;
+                1.0 + (num_docs / (doc_freq + 1.0)).ln()
+            })
+            .sum()
+    }
 }
 
 impl Query for PhraseQuery {
     /// Create the weight associated to a query.
     ///
     /// See [`Weight`](./trait.Weight.html).
-    fn weight(&self, _searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
-        Ok(box PhraseWeight::new(
-            self.phrase_terms.clone(),
-            scoring_enabled,
-        ))
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        let idf_opt = if self.count_occurrences && scoring_enabled {
+            Some(self.idf(searcher))
+        } else {
+            None
+        };
+        let mut phrase_weight = PhraseWeight::new_with_offset(self.phrase_terms.clone(), idf_opt);
+        phrase_weight.set_slop(self.slop);
+        Ok(box phrase_weight)
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        for &(_, ref term) in &self.phrase_terms {
+            validate_indexed_field(
+                searcher.schema(),
+                term.field(),
+                IndexRecordOption::WithPositions,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        let (&(first_offset, ref first_term), other_terms) = self.phrase_terms
+            .split_first()
+            .expect("A phrase query has at least two terms");
+        write!(f, "Phrase({}", term_to_string(schema, first_term))?;
+        // skipped positions are written as `?`, and the terms
+        // sharing a position are separated by `|`.
+        let mut previous_offset = first_offset;
+        for &(offset, ref term) in other_terms {
+            if offset == previous_offset {
+                write!(f, "|")?;
+            } else {
+                write!(f, " {}", "? ".repeat(offset - previous_offset - 1))?;
+            }
+            previous_offset = offset;
+            let field_type = schema.get_field_entry(term.field()).field_type();
+            write!(f, "{}", value_bytes_to_string(field_type, term.value_bytes()))?;
+        }
+        if self.slop > 0 {
+            writeln!(f, ")~{}", self.slop)
+        } else {
+            writeln!(f, ")")
+        }
     }
 }
 
 impl From<Vec<Term>> for PhraseQuery {
     fn from(phrase_terms: Vec<Term>) -> PhraseQuery {
-        assert!(phrase_terms.len() > 1);
-        PhraseQuery { phrase_terms }
+        PhraseQuery::new_with_offset(phrase_terms.into_iter().enumerate().collect())
     }
 }
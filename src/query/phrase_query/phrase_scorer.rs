@@ -1,67 +1,329 @@
 use DocId;
+use Score;
 use docset::{DocSet, SkipResult};
 use postings::{Postings, SegmentPostings};
-use query::{Intersection, Scorer};
+use query::{Explanation, Intersection, Scorer, TwoPhase};
+use fastfield::FastFieldReader;
+use std::cmp;
 
-struct PostingsWithOffset {
-    offset: u32,
-    segment_postings: SegmentPostings,
+// the postings of a term, with the ordinal of the term in the phrase.
+struct PostingsWithOrd<TPostings> {
+    ord: usize,
+    postings: TPostings,
 }
 
-impl PostingsWithOffset {
-    pub fn new(segment_postings: SegmentPostings, offset: u32) -> PostingsWithOffset {
-        PostingsWithOffset {
-            offset,
-            segment_postings,
-        }
+impl<TPostings: Postings> PostingsWithOrd<TPostings> {
+    pub fn new(postings: TPostings, ord: usize) -> PostingsWithOrd<TPostings> {
+        PostingsWithOrd { ord, postings }
     }
 }
 
-impl Postings for PostingsWithOffset {
+impl<TPostings: Postings> Postings for PostingsWithOrd<TPostings> {
     fn term_freq(&self) -> u32 {
-        self.segment_postings.term_freq()
+        self.postings.term_freq()
     }
 
     fn positions(&self) -> &[u32] {
-        self.segment_postings.positions()
+        self.postings.positions()
     }
 }
 
-impl DocSet for PostingsWithOffset {
+impl<TPostings: Postings> DocSet for PostingsWithOrd<TPostings> {
     fn advance(&mut self) -> bool {
-        self.segment_postings.advance()
+        self.postings.advance()
     }
 
     fn skip_next(&mut self, target: DocId) -> SkipResult {
-        self.segment_postings.skip_next(target)
+        self.postings.skip_next(target)
     }
 
     fn doc(&self) -> DocId {
-        self.segment_postings.doc()
+        self.postings.doc()
     }
 
     fn size_hint(&self) -> u32 {
-        self.segment_postings.size_hint()
+        self.postings.size_hint()
+    }
+}
+
+pub struct PhraseScorer<TPostings: Postings = SegmentPostings> {
+    intersection_docset: Intersection<PostingsWithOrd<TPostings>>,
+    // position of each term in the phrase, relative to the first one.
+    offsets: Vec<u32>,
+    // idf of the phrase, if its occurrences are counted for scoring.
+    idf_opt: Option<Score>,
+    fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
+    phrase_freq: u32,
+    #[cfg(test)]
+    num_positions_read: u64,
+    slop: u32,
+    // identifies the terms of the phrase, for sloppy phrases.
+    term_ids: Vec<usize>,
+    // weight of the sloppy occurrences of the phrase.
+    sloppy_freq: Score,
+}
+
+/// Returns the positions of `left` that are followed,
+/// `offset` positions later, by a position of `right`.
+fn intersect_positions(left: &[u32], right: &[u32], offset: u32) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut right_it = right.iter().cloned().peekable();
+    for &pos in left {
+        let target = pos + offset;
+        while right_it.peek().map(|&right_pos| right_pos < target).unwrap_or(false) {
+            right_it.next();
+        }
+        match right_it.peek() {
+            Some(&right_pos) if right_pos == target => result.push(pos),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    result
+}
+
+// Sloppy occurrences count less the farther their terms are
+// from their positions in the phrase.
+fn sloppy_weight(distance: u32) -> Score {
+    1.0 / (1.0 + distance as Score)
+}
+
+/// Returns the occurrence of the phrase, if any, whose smallest shift
+/// of a term from its position in the phrase is exactly `start`,
+/// and whose largest shift is at most `start + slop`.
+///
+/// The shifts are returned as the distance of the occurrence,
+/// i.e. the difference between its largest and its smallest shift.
+fn sloppy_occurrence(
+    positions_arr: &[&[u32]],
+    offsets: &[u32],
+    term_ids: &[usize],
+    start: i64,
+    slop: u32,
+) -> Option<u32> {
+    let mut matched_positions: Vec<i64> = Vec::with_capacity(positions_arr.len());
+    let (mut min_shift, mut max_shift) = (i64::max_value(), i64::min_value());
+    for (ord, positions) in positions_arr.iter().enumerate() {
+        let offset = i64::from(offsets[ord]);
+        let mut lower_bound = start + offset;
+        // the occurrences of a repeated term need distinct positions.
+        // Taking the first available position, in the order of the
+        // phrase, is enough to find them.
+        for (prev_ord, &prev_pos) in matched_positions.iter().enumerate() {
+            if term_ids[prev_ord] == term_ids[ord] {
+                lower_bound = cmp::max(lower_bound, prev_pos + 1);
+            }
+        }
+        let first_candidate = match positions.binary_search(&(cmp::max(lower_bound, 0) as u32)) {
+            Ok(i) | Err(i) => i,
+        };
+        let pos = i64::from(*positions.get(first_candidate)?);
+        let shift = pos - offset;
+        if shift > start + i64::from(slop) {
+            return None;
+        }
+        matched_positions.push(pos);
+        min_shift = cmp::min(min_shift, shift);
+        max_shift = cmp::max(max_shift, shift);
+    }
+    if min_shift == start {
+        Some((max_shift - min_shift) as u32)
+    } else {
+        // this occurrence is found again, starting from `min_shift`.
+        None
     }
 }
 
-pub struct PhraseScorer {
-    intersection_docset: Intersection<PostingsWithOffset>,
+/// Returns the distance of each of the sloppy occurrences of the phrase.
+///
+/// Each term may be shifted from its position in the phrase,
+/// and the distance of an occurrence is the difference between the
+/// largest and the smallest shift of its terms.
+/// It is 0 for an exact occurrence, 1 if a term is off by one,
+/// and 2 for two swapped adjacent terms.
+fn sloppy_occurrences(
+    positions_arr: &[&[u32]],
+    offsets: &[u32],
+    term_ids: &[usize],
+    slop: u32,
+) -> Vec<u32> {
+    let mut starts: Vec<i64> = positions_arr
+        .iter()
+        .zip(offsets)
+        .flat_map(|(positions, &offset)| {
+            positions
+                .iter()
+                .map(move |&pos| i64::from(pos) - i64::from(offset))
+        })
+        .collect();
+    starts.sort();
+    starts.dedup();
+    starts
+        .into_iter()
+        .filter_map(|start| sloppy_occurrence(positions_arr, offsets, term_ids, start, slop))
+        .collect()
 }
 
-impl PhraseScorer {
-    pub fn new(term_postings: Vec<SegmentPostings>) -> PhraseScorer {
-        let postings_with_offsets: Vec<_> = term_postings
-            .into_iter()
-            .enumerate()
-            .map(|(offset, postings)| PostingsWithOffset::new(postings, offset as u32))
-            .collect();
+impl<TPostings: Postings> PhraseScorer<TPostings> {
+    /// Creates a phrase scorer that stops at the first occurrence
+    /// of the phrase in each document, and gives all of the
+    /// matching documents a score of 1.
+    pub fn new(term_postings: Vec<TPostings>) -> PhraseScorer<TPostings> {
+        PhraseScorer::new_with_offset(term_postings.into_iter().enumerate().collect())
+    }
+
+    /// Creates a phrase scorer, like `new`, for the terms at the
+    /// given positions in the phrase.
+    ///
+    /// The positions must be sorted in increasing order. Several terms
+    /// may share a position, and positions may be skipped, e.g. where
+    /// the tokenizer removed a stop word.
+    pub fn new_with_offset(term_postings: Vec<(usize, TPostings)>) -> PhraseScorer<TPostings> {
+        assert!(
+            term_postings.windows(2).all(|window| window[0].0 <= window[1].0),
+            "The positions of the terms of a phrase must be sorted"
+        );
+        let first_offset = term_postings.first().map(|&(offset, _)| offset).unwrap_or(0);
+        let mut offsets = Vec::with_capacity(term_postings.len());
+        let mut postings_with_ords = Vec::with_capacity(term_postings.len());
+        for (ord, (offset, postings)) in term_postings.into_iter().enumerate() {
+            offsets.push((offset - first_offset) as u32);
+            postings_with_ords.push(PostingsWithOrd::new(postings, ord));
+        }
         PhraseScorer {
-            intersection_docset: Intersection::from(postings_with_offsets),
+            intersection_docset: Intersection::from(postings_with_ords),
+            offsets,
+            idf_opt: None,
+            fieldnorm_reader_opt: None,
+            phrase_freq: 0,
+            #[cfg(test)]
+            num_positions_read: 0,
+            slop: 0,
+            term_ids: Vec::new(),
+            sloppy_freq: 0.0,
+        }
+    }
+
+    /// Makes the scorer also match the occurrences of the phrase
+    /// whose terms are not exactly at their positions in the phrase.
+    ///
+    /// The distance of an occurrence is the difference between the largest and
+    /// the smallest shift of its terms from their positions in the phrase, and
+    /// only the occurrences with a distance of at most `slop` are matched.
+    /// For instance, `"quick fox"` matches `quick brown fox` with a slop of 1,
+    /// and `fox quick` with a slop of 2.
+    ///
+    /// `term_ids` identifies the terms of the phrase: the repeated terms of
+    /// the phrase have the same id, and must match distinct positions.
+    ///
+    /// Each occurrence is weighted by `1 / (1 + distance)`, and the weights
+    /// of the occurrences are summed instead of counting them. If the
+    /// occurrences are not counted, the score is the weight of
+    /// the closest occurrence.
+    pub fn set_slop(&mut self, slop: u32, term_ids: Vec<usize>) {
+        assert_eq!(term_ids.len(), self.intersection_docset.docsets().len());
+        self.slop = slop;
+        self.term_ids = term_ids;
+    }
+
+    /// Creates a phrase scorer that counts all of the occurrences
+    /// of the phrase in each document.
+    ///
+    /// The score is then defined as for a `TermScorer`, with
+    /// the number of occurrences of the phrase as the term frequency.
+    pub fn with_phrase_freq(
+        term_postings: Vec<TPostings>,
+        idf: Score,
+        fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
+    ) -> PhraseScorer<TPostings> {
+        let mut phrase_scorer = PhraseScorer::new(term_postings);
+        phrase_scorer.count_phrase_freq(idf, fieldnorm_reader_opt);
+        phrase_scorer
+    }
+
+    /// Makes the scorer count all of the occurrences of the phrase
+    /// in each document, as in `with_phrase_freq`.
+    pub fn count_phrase_freq(
+        &mut self,
+        idf: Score,
+        fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
+    ) {
+        self.idf_opt = Some(idf);
+        self.fieldnorm_reader_opt = fieldnorm_reader_opt;
+    }
+
+    /// Returns the number of occurrences of the phrase in the current document.
+    ///
+    /// If the occurrences are not counted, this is 1 for all
+    /// of the matching documents.
+    pub fn phrase_freq(&self) -> u32 {
+        self.phrase_freq
+    }
+
+    /// Returns the number of positions that were read
+    /// so far to check for the phrase.
+    #[cfg(test)]
+    pub fn num_positions_read(&self) -> u64 {
+        self.num_positions_read
+    }
+
+    fn phrase_match(&mut self) -> bool {
+        if self.slop > 0 {
+            return self.sloppy_phrase_match();
+        }
+        self.phrase_freq = if self.idf_opt.is_some() {
+            self.count_phrase_occurrences()
+        } else if self.first_phrase_match() {
+            1
+        } else {
+            0
+        };
+        self.phrase_freq > 0
+    }
+
+    fn count_phrase_occurrences(&mut self) -> u32 {
+        let docsets = self.intersection_docset.docsets();
+        let mut positions_arr: Vec<&[u32]> = vec![&[]; docsets.len()];
+        for docset in docsets {
+            positions_arr[docset.ord] = docset.positions();
+        }
+        #[cfg(test)]
+        self.num_positions_read += positions_arr[0].len() as u64;
+        let mut phrase_starts: Vec<u32> = positions_arr[0].to_vec();
+        for (ord, positions) in positions_arr.iter().enumerate().skip(1) {
+            #[cfg(test)]
+            self.num_positions_read += positions.len() as u64;
+            phrase_starts = intersect_positions(&phrase_starts, positions, self.offsets[ord]);
+            if phrase_starts.is_empty() {
+                return 0;
+            }
+        }
+        phrase_starts.len() as u32
+    }
+
+    fn sloppy_phrase_match(&mut self) -> bool {
+        let docsets = self.intersection_docset.docsets();
+        let mut positions_arr: Vec<&[u32]> = vec![&[]; docsets.len()];
+        for docset in docsets {
+            positions_arr[docset.ord] = docset.positions();
+            #[cfg(test)]
+            self.num_positions_read += docset.positions().len() as u64;
+        }
+        let distances =
+            sloppy_occurrences(&positions_arr, &self.offsets, &self.term_ids, self.slop);
+        if self.idf_opt.is_some() {
+            self.phrase_freq = distances.len() as u32;
+            self.sloppy_freq = distances.iter().cloned().map(sloppy_weight).sum();
+        } else if let Some(&min_distance) = distances.iter().min() {
+            self.phrase_freq = 1;
+            self.sloppy_freq = sloppy_weight(min_distance);
+        } else {
+            self.phrase_freq = 0;
         }
+        self.phrase_freq > 0
     }
 
-    fn phrase_match(&self) -> bool {
+    fn first_phrase_match(&mut self) -> bool {
         // TODO maybe we could avoid decoding positions lazily for all terms
         // when there is > 2 terms.
         //
@@ -70,7 +332,7 @@ impl PhraseScorer {
         let docsets = self.intersection_docset.docsets();
         let mut positions_arr: Vec<&[u32]> = vec![&[]; docsets.len()];
         for docset in docsets {
-            positions_arr[docset.offset as usize] = docset.positions();
+            positions_arr[docset.ord] = docset.positions();
         }
 
         let num_postings = positions_arr.len() as u32;
@@ -78,13 +340,17 @@ impl PhraseScorer {
         let mut ord = 1u32;
         let mut pos_candidate = positions_arr[0][0];
         positions_arr[0] = &(positions_arr[0])[1..];
+        #[cfg(test)]
+        self.num_positions_read += 1;
         let mut count_matching = 1;
 
         #[cfg_attr(feature = "cargo-clippy", allow(never_loop))]
         'outer: loop {
-            let target = pos_candidate + ord;
+            let target = pos_candidate + self.offsets[ord as usize];
             let positions = positions_arr[ord as usize];
             for (i, pos_i) in positions.iter().cloned().enumerate() {
+                #[cfg(test)]
+                self.num_positions_read += 1;
                 if pos_i < target {
                     continue;
                 }
@@ -95,7 +361,7 @@ impl PhraseScorer {
                     }
                 } else if pos_i > target {
                     count_matching = 1;
-                    pos_candidate = positions[i] - ord;
+                    pos_candidate = positions[i] - self.offsets[ord as usize];
                     positions_arr[ord as usize] = &(positions_arr[ord as usize])[(i + 1)..];
                 }
                 ord += 1;
@@ -109,7 +375,7 @@ impl PhraseScorer {
     }
 }
 
-impl DocSet for PhraseScorer {
+impl<TPostings: Postings> DocSet for PhraseScorer<TPostings> {
     fn advance(&mut self) -> bool {
         while self.intersection_docset.advance() {
             if self.phrase_match() {
@@ -144,10 +410,101 @@ impl DocSet for PhraseScorer {
     fn size_hint(&self) -> u32 {
         self.intersection_docset.size_hint()
     }
+
+    fn count_estimate(&self) -> u32 {
+        self.intersection_docset.count_estimate()
+    }
+}
+
+/// The approximation of a phrase is the intersection of the postings
+/// of its terms, and the check verifies the positions of the terms.
+impl<TPostings: Postings> TwoPhase for PhraseScorer<TPostings> {
+    fn approximation(&mut self) -> &mut DocSet {
+        &mut self.intersection_docset
+    }
+
+    fn matches(&mut self) -> bool {
+        self.phrase_match()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.intersection_docset.docsets().len() as f32
+    }
+}
+
+impl<TPostings: Postings + 'static> PhraseScorer<TPostings> {
+    /// Explains the score of the current document.
+    pub fn explain(&mut self) -> Explanation {
+        let phrase_freq = if self.slop > 0 {
+            let mut explanation = Explanation::new(
+                "sloppy phrase_freq, sum of 1 / (1 + distance) over the occurrences",
+                self.sloppy_freq,
+            );
+            explanation.add_const(
+                "number of occurrences of the phrase within the slop",
+                self.phrase_freq as Score,
+            );
+            explanation
+        } else {
+            Explanation::new(
+                "phrase_freq, number of occurrences of the phrase",
+                self.phrase_freq as Score,
+            )
+        };
+        let idf = match self.idf_opt {
+            Some(idf) => idf,
+            None => {
+                let mut explanation = Explanation::new(
+                    "Phrase, the occurrences of the phrase are not counted",
+                    self.score(),
+                );
+                explanation.add_detail(phrase_freq);
+                return explanation;
+            }
+        };
+        let mut explanation = Explanation::new(
+            "Phrase, computed as idf * sqrt(phrase_freq / fieldnorm)",
+            self.score(),
+        );
+        explanation.add_const("idf of the phrase", idf);
+        explanation.add_detail(phrase_freq);
+        if let Some(ref fieldnorm_reader) = self.fieldnorm_reader_opt {
+            let field_norm = fieldnorm_reader.get(self.doc()) as Score;
+            explanation.add_const("fieldnorm, number of tokens of the field", field_norm);
+        }
+        explanation
+    }
 }
 
-impl Scorer for PhraseScorer {
-    fn score(&mut self) -> f32 {
-        1f32
+impl<TPostings: Postings + 'static> Scorer for PhraseScorer<TPostings> {
+    fn score(&mut self) -> Score {
+        let phrase_freq = if self.slop > 0 {
+            self.sloppy_freq
+        } else {
+            self.phrase_freq as Score
+        };
+        let idf = match self.idf_opt {
+            Some(idf) => idf,
+            None => {
+                // 1, unless the phrase is sloppy.
+                return phrase_freq;
+            }
+        };
+        let tf = match self.fieldnorm_reader_opt {
+            Some(ref fieldnorm_reader) => {
+                let field_norm = fieldnorm_reader.get(self.doc());
+                phrase_freq / field_norm as Score
+            }
+            None => phrase_freq,
+        };
+        idf * tf.sqrt()
+    }
+
+    fn name(&self) -> &'static str {
+        "PhraseScorer"
+    }
+
+    fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+        Some(self)
     }
 }
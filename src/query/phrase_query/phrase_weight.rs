@@ -1,42 +1,110 @@
 use query::Weight;
 use query::Scorer;
+use query::Explanation;
+use query::explanation::does_not_match;
+use docset::{DocSet, SkipResult};
 use schema::Term;
 use schema::IndexRecordOption;
 use core::SegmentReader;
 use super::PhraseScorer;
 use query::EmptyScorer;
 use Result;
+use Score;
+use DocId;
 
 pub struct PhraseWeight {
-    phrase_terms: Vec<Term>,
+    // the terms of the phrase, with their positions in the phrase.
+    phrase_terms: Vec<(usize, Term)>,
+    // idf of the phrase, if its occurrences are counted for scoring.
+    idf_opt: Option<Score>,
+    slop: u32,
 }
 
 impl PhraseWeight {
     /// Creates a new phrase weight.
     ///
-    /// Right now `scoring_enabled` is actually ignored.
-    /// In the future, disabling scoring will result in a small performance boost.
-    // TODO use the scoring disable information to avoid compute the
-    // phrase freq in that case, and compute the phrase freq when scoring is enabled.
-    // Right now we never compute it :|
-    pub fn new(phrase_terms: Vec<Term>, _scoring_enabled: bool) -> PhraseWeight {
-        PhraseWeight { phrase_terms }
+    /// If `idf_opt` is `None`, the scorers stop at the first occurrence
+    /// of the phrase in each document, and all of the matching documents
+    /// get a score of 1. Otherwise, all of the occurrences of the phrase
+    /// are counted, and scored with the given idf.
+    pub fn new(phrase_terms: Vec<Term>, idf_opt: Option<Score>) -> PhraseWeight {
+        PhraseWeight::new_with_offset(phrase_terms.into_iter().enumerate().collect(), idf_opt)
     }
-}
 
-impl Weight for PhraseWeight {
-    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+    /// Creates a new phrase weight, for the terms at the given
+    /// positions in the phrase, sorted in increasing order.
+    ///
+    /// See [`PhraseScorer::new_with_offset`](./struct.PhraseScorer.html#method.new_with_offset).
+    pub fn new_with_offset(
+        phrase_terms: Vec<(usize, Term)>,
+        idf_opt: Option<Score>,
+    ) -> PhraseWeight {
+        PhraseWeight {
+            phrase_terms,
+            idf_opt,
+            slop: 0,
+        }
+    }
+
+    /// Sets the slop of the phrase.
+    ///
+    /// See [`PhraseScorer::set_slop`](./struct.PhraseScorer.html#method.set_slop).
+    pub fn set_slop(&mut self, slop: u32) {
+        self.slop = slop;
+    }
+
+    // the repeated terms of the phrase get the same id.
+    fn term_ids(&self) -> Vec<usize> {
+        self.phrase_terms
+            .iter()
+            .map(|&(_, ref term)| {
+                self.phrase_terms
+                    .iter()
+                    .position(|&(_, ref other_term)| other_term == term)
+                    .expect("The term belongs to the phrase")
+            })
+            .collect()
+    }
+
+    // returns `None` if one of the terms is absent from the segment.
+    fn phrase_scorer(&self, reader: &SegmentReader) -> Result<Option<PhraseScorer>> {
         let mut term_postings_list = Vec::new();
-        for term in &self.phrase_terms {
+        for &(offset, ref term) in &self.phrase_terms {
             if let Some(postings) = reader
                 .inverted_index(term.field())
                 .read_postings(term, IndexRecordOption::WithFreqsAndPositions)
             {
-                term_postings_list.push(postings);
+                term_postings_list.push((offset, postings));
             } else {
-                return Ok(box EmptyScorer);
+                return Ok(None);
+            }
+        }
+        let mut phrase_scorer = PhraseScorer::new_with_offset(term_postings_list);
+        if let Some(idf) = self.idf_opt {
+            let field = self.phrase_terms[0].1.field();
+            phrase_scorer.count_phrase_freq(idf, reader.get_fieldnorms_reader(field));
+        }
+        if self.slop > 0 {
+            phrase_scorer.set_slop(self.slop, self.term_ids());
+        }
+        Ok(Some(phrase_scorer))
+    }
+}
+
+impl Weight for PhraseWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        match self.phrase_scorer(reader)? {
+            Some(phrase_scorer) => Ok(box phrase_scorer),
+            None => Ok(box EmptyScorer),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        if let Some(mut phrase_scorer) = self.phrase_scorer(reader)? {
+            if phrase_scorer.skip_next(doc) == SkipResult::Reached {
+                return Ok(phrase_scorer.explain());
             }
         }
-        Ok(box PhraseScorer::new(term_postings_list))
+        Err(does_not_match(doc))
     }
 }
@@ -0,0 +1,242 @@
+use schema::{Field, IndexRecordOption, Term};
+use query::{Query, Scorer, Weight};
+use termdict::{TermDictionary, TermStreamer, TermStreamerBuilder};
+use core::SegmentReader;
+use common::BitSet;
+use Result;
+use core::Searcher;
+use query::BitSetDocSet;
+use query::ConstScorer;
+use std::result;
+use std::fmt;
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use schema::{term_to_string, Schema};
+
+/// Returns the smallest key greater than all of the keys
+/// starting with `prefix`, or `None` if there is no such key,
+/// i.e. if the prefix only contains `0xFF` bytes.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper_bound = prefix.to_vec();
+    while let Some(last_byte) = upper_bound.pop() {
+        if last_byte < u8::max_value() {
+            upper_bound.push(last_byte + 1);
+            return Some(upper_bound);
+        }
+    }
+    None
+}
+
+/// Returns a stream over the terms of a term dictionary
+/// starting with `prefix`, in sorted order.
+pub(crate) fn prefix_term_stream<'a, T>(term_dict: &'a T, prefix: &[u8]) -> T::Streamer
+where
+    T: TermDictionary<'a> + 'a,
+{
+    let term_stream_builder = term_dict.range().ge(prefix);
+    match prefix_upper_bound(prefix) {
+        Some(upper_bound) => term_stream_builder.lt(upper_bound),
+        None => term_stream_builder,
+    }.into_stream()
+}
+
+/// A Prefix Query matches all of the documents
+/// containing a term starting with a given prefix.
+///
+/// The prefix is given as a `Term`, and is matched
+/// against the bytes of the indexed terms: for instance,
+/// the prefix `Term::from_field_text(title, "yor")` matches
+/// the terms `york` and `yorkshire` of the field `title`.
+///
+/// All of the matching documents get a score of 1.
+///
+/// See [`PhrasePrefixQuery`](./struct.PhrasePrefixQuery.html)
+/// to complete the last word of a phrase.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::CountCollector;
+/// use tantivy::query::PrefixQuery;
+/// use tantivy::schema::{SchemaBuilder, Term, TEXT};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "New York"))?;
+///     index_writer.add_document(doc!(title => "Yorkshire pudding"))?;
+///     index_writer.add_document(doc!(title => "Your song"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let query = PrefixQuery::new(Term::from_field_text(title, "yor"));
+/// let mut count_collector = CountCollector::default();
+/// searcher.search(&query, &mut count_collector)?;
+/// assert_eq!(count_collector.count(), 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrefixQuery {
+    prefix: Term,
+}
+
+impl PrefixQuery {
+    /// Creates a `PrefixQuery` matching the terms
+    /// of the field of `prefix` that start with its value.
+    pub fn new(prefix: Term) -> PrefixQuery {
+        PrefixQuery { prefix }
+    }
+
+    /// Returns the prefix of the query.
+    pub fn prefix(&self) -> &Term {
+        &self.prefix
+    }
+}
+
+impl Query for PrefixQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(box PrefixWeight {
+            field: self.prefix.field(),
+            prefix: self.prefix.value_bytes().to_owned(),
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        validate_indexed_field(
+            searcher.schema(),
+            self.prefix.field(),
+            IndexRecordOption::Basic,
+        )
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Prefix({})", term_to_string(schema, &self.prefix))
+    }
+}
+
+pub struct PrefixWeight {
+    field: Field,
+    prefix: Vec<u8>,
+}
+
+impl Weight for PrefixWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        let mut term_stream = prefix_term_stream(term_dict, &self.prefix);
+        while term_stream.advance() {
+            let term_info = term_stream.value();
+            let mut block_segment_postings = inverted_index
+                .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+            while block_segment_postings.advance() {
+                for &doc in block_segment_postings.docs() {
+                    if !reader.is_deleted(doc) {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(box ConstScorer::new(doc_bitset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, Term, STRING};
+    use collector::CountCollector;
+    use query::{Query, QueryDisplay};
+    use indexer::NoMergePolicy;
+    use super::{prefix_upper_bound, PrefixQuery};
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound(b"abc"), Some(b"abd".to_vec()));
+        assert_eq!(prefix_upper_bound(b"ab\xFF"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_upper_bound(b"a\xFF\xFF"), Some(b"b".to_vec()));
+        assert_eq!(prefix_upper_bound(b"\xFF\xFF"), None);
+        assert_eq!(prefix_upper_bound(b""), None);
+    }
+
+    #[test]
+    fn test_prefix_query() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            let segments: [&[&str]; 2] = [
+                &["york", "yorkshire", "yo", "new york", "yorz"],
+                &["yor", "yos", "yoraaa", "yor\u{FF}"],
+            ];
+            for segment_codes in &segments {
+                for segment_code in segment_codes.iter() {
+                    index_writer.add_document(doc!(code => *segment_code)).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let count = |prefix: &str| {
+            let query = PrefixQuery::new(Term::from_field_text(code, prefix));
+            assert!(searcher.check_query(&query).is_ok());
+            let mut count_collector = CountCollector::default();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count("yor"), 6);
+        assert_eq!(count("york"), 2);
+        assert_eq!(count("yorks"), 1);
+        assert_eq!(count("yo"), 8);
+        assert_eq!(count(""), 9);
+        assert_eq!(count("x"), 0);
+        assert_eq!(count("yorkshire pudding"), 0);
+
+        let query = PrefixQuery::new(Term::from_field_text(code, "yor"));
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&query, &schema)),
+            "Prefix(code:\"yor\")\n"
+        );
+    }
+
+    #[test]
+    fn test_prefix_query_ignores_deleted_docs() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for segment_code in &["york", "yorz", "yo"] {
+                index_writer.add_document(doc!(code => *segment_code)).unwrap();
+            }
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(code, "york"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = PrefixQuery::new(Term::from_field_text(code, "yor"));
+        let mut count_collector = CountCollector::default();
+        query.search(&*searcher, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 1);
+    }
+}
@@ -3,8 +3,17 @@ use collector::Collector;
 use core::searcher::Searcher;
 use common::TimerTree;
 use SegmentLocalId;
+use DocAddress;
+use super::Explanation;
 use super::Weight;
+use super::QueryContext;
+use super::Scorer;
+use core::SegmentReader;
+use std::any::Any;
 use std::fmt;
+use std::result;
+use super::QueryValidationError;
+use schema::Schema;
 
 /// The `Query` trait defines a set of documents and a scoring method
 /// for those documents.
@@ -39,7 +48,20 @@ use std::fmt;
 ///
 /// When implementing a new type of `Query`, it is normal to implement a
 /// dedicated `Query`, `Weight` and `Scorer`.
-pub trait Query: fmt::Debug {
+///
+/// # Thread safety
+///
+/// Queries are immutable descriptions of a search, and are required to be
+/// `Send + Sync`: a query, typically parsed once and cached, can be shared
+/// by several threads and used concurrently for any number of searches.
+/// Queries must not rely on interior mutability.
+/// Boxed queries can also be duplicated with `clone`
+/// (see [`QueryClone`](./trait.QueryClone.html)), and downcast
+/// to their concrete type (see [`QueryAny`](./trait.QueryAny.html)).
+///
+/// On the contrary, `Weight`s and `Scorer`s are created for every search,
+/// are used by a single thread, and are not required to be `Send` or `Sync`.
+pub trait Query: QueryClone + QueryAny + fmt::Debug + Send + Sync {
     /// Create the weight associated to a query.
     ///
     /// If scoring is not required, setting `scoring_enabled` to `false`
@@ -48,11 +70,57 @@ pub trait Query: fmt::Debug {
     /// See [`Weight`](./trait.Weight.html).
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>>;
 
+    /// Checks that the query is consistent with the schema of the
+    /// index, and returns an error naming the field and the missing
+    /// capability otherwise.
+    ///
+    /// A query that does not pass validation, for instance a `PhraseQuery`
+    /// on a field indexed without positions, does not fail at search time
+    /// but returns empty or meaningless results.
+    ///
+    /// The default implementation only checks that the query's
+    /// `Weight` can be built.
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        self.weight(searcher, true)
+            .map(|_| ())
+            .map_err(|err| QueryValidationError::InvalidQuery(err.to_string()))
+    }
+
+    /// Writes a human readable representation of the query tree.
+    ///
+    /// The caller is in charge of writing the indentation of the first
+    /// line, while all of the following lines should be indented
+    /// by `depth` levels. Every line, including the last one,
+    /// ends with a new line.
+    ///
+    /// The default implementation writes the `Debug` representation of the query.
+    ///
+    /// See [`QueryDisplay`](./struct.QueryDisplay.html).
+    fn fmt_tree(&self, _schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:?}", self)
+    }
+
+    /// Explains the score of the document at the given address.
+    ///
+    /// The explanation details how the score is computed,
+    /// which is useful to debug the relevance of the results.
+    /// Returns an error if the document does not match the query.
+    ///
+    /// See [`Explanation`](./struct.Explanation.html).
+    fn explain(&self, searcher: &Searcher, doc_address: DocAddress) -> Result<Explanation> {
+        let DocAddress(segment_ord, doc) = doc_address;
+        let weight = self.weight(searcher, true)?;
+        weight.explain(searcher.segment_reader(segment_ord), doc)
+    }
+
     /// Returns the number of documents matching the query.
     fn count(&self, searcher: &Searcher) -> Result<usize> {
         let weight = self.weight(searcher, false)?;
         let mut result = 0;
         for reader in searcher.segment_readers() {
+            if !weight.can_match(reader.segment_meta()) {
+                continue;
+            }
             result += weight.count(reader)? as usize;
         }
         Ok(result)
@@ -60,32 +128,226 @@ pub trait Query: fmt::Debug {
 
     /// Search works as follows :
     ///
-    /// First the weight object associated to the query is created.
+    /// First the weight object associated to the query is created,
+    /// and the collector is prepared
+    /// (see [`Collector::prepare`](../collector/trait.Collector.html#method.prepare)).
     ///
     /// Then, the query loops over the segments and for each segment :
     /// - setup the collector and informs it that the segment being processed has changed.
-    /// - creates a `Scorer` object associated for this segment
+    /// - if the segment may contain a match
+    /// (see [`Weight::can_match`](./trait.Weight.html#method.can_match)),
+    /// creates a `Scorer` object associated for this segment. The temporary
+    /// buffers of the scorers are reused from one segment to the next
+    /// (see [`QueryContext`](./struct.QueryContext.html)).
     /// - iterate throw the matched documents and push them to the collector.
     ///
+    /// If the collector does not require scoring, the matched documents
+    /// are fetched by batches (see [`Scorer::collect_doc_ids`](./trait.Scorer.html#method.collect_doc_ids)).
+    ///
     fn search(&self, searcher: &Searcher, collector: &mut Collector) -> Result<TimerTree> {
-        let mut timer_tree = TimerTree::default();
         let scoring_enabled = collector.requires_scoring();
         let weight = self.weight(searcher, scoring_enabled)?;
-        {
-            let mut search_timer = timer_tree.open("search");
-            for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
-                let mut segment_search_timer = search_timer.open("segment_search");
-                {
-                    let _ = segment_search_timer.open("set_segment");
-                    collector.set_segment(segment_ord as SegmentLocalId, segment_reader)?;
-                }
-                let mut scorer = weight.scorer(segment_reader)?;
-                {
-                    let _collection_timer = segment_search_timer.open("collection");
-                    scorer.collect(collector);
-                }
+        collector.prepare(searcher)?;
+        search_segments(
+            searcher,
+            &*weight,
+            collector,
+            scoring_enabled,
+            &mut NoSearchObserver,
+        )
+    }
+}
+
+/// Hooks into the loop of a search over the segments,
+/// e.g. to profile the search.
+///
+/// The default implementations do not observe anything,
+/// and behave as a plain search.
+pub(crate) trait SearchObserver {
+    /// Called for each of the segments that were skipped because
+    /// none of their documents can match.
+    fn segment_skipped(&mut self, _segment_ord: SegmentLocalId, _segment_reader: &SegmentReader) {}
+
+    /// Creates the scorer of the segment.
+    fn scorer(
+        &mut self,
+        weight: &Weight,
+        _segment_ord: SegmentLocalId,
+        segment_reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        weight.scorer_with_context(segment_reader, context)
+    }
+
+    /// Pushes the documents matched by the scorer to the collector.
+    fn collect(&mut self, scorer: &mut Scorer, collector: &mut Collector, scoring_enabled: bool) {
+        collect_scorer(scorer, collector, scoring_enabled);
+    }
+}
+
+struct NoSearchObserver;
+
+impl SearchObserver for NoSearchObserver {}
+
+/// Pushes the documents matched by the scorer to the collector,
+/// by batches if the collector does not require scoring.
+pub(crate) fn collect_scorer(
+    scorer: &mut Scorer,
+    collector: &mut Collector,
+    scoring_enabled: bool,
+) {
+    if scoring_enabled {
+        scorer.collect(collector);
+    } else {
+        scorer.collect_doc_ids(collector);
+    }
+}
+
+/// Runs the weight on each of the segments of the searcher,
+/// and pushes the matched documents to the collector.
+///
+/// The collector is expected to have been prepared already.
+pub(crate) fn search_segments(
+    searcher: &Searcher,
+    weight: &Weight,
+    collector: &mut Collector,
+    scoring_enabled: bool,
+    observer: &mut SearchObserver,
+) -> Result<TimerTree> {
+    let mut timer_tree = TimerTree::default();
+    let mut context = QueryContext::new();
+    {
+        let mut search_timer = timer_tree.open("search");
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let segment_ord = segment_ord as SegmentLocalId;
+            let mut segment_search_timer = search_timer.open("segment_search");
+            {
+                let _ = segment_search_timer.open("set_segment");
+                collector.set_segment(segment_ord, segment_reader)?;
+            }
+            if !weight.can_match(segment_reader.segment_meta()) {
+                observer.segment_skipped(segment_ord, segment_reader);
+                continue;
+            }
+            let mut scorer = observer.scorer(weight, segment_ord, segment_reader, &mut context)?;
+            {
+                let _collection_timer = segment_search_timer.open("collection");
+                observer.collect(&mut *scorer, collector, scoring_enabled);
             }
         }
-        Ok(timer_tree)
+    }
+    Ok(timer_tree)
+}
+
+/// Duplication of a boxed `Query`.
+///
+/// It is implemented for all of the queries implementing `Clone`,
+/// and makes `Box<Query>` implement `Clone`.
+pub trait QueryClone {
+    /// Returns a boxed copy of the query.
+    fn box_clone(&self) -> Box<Query>;
+}
+
+impl<T: 'static + Query + Clone> QueryClone for T {
+    fn box_clone(&self) -> Box<Query> {
+        box self.clone()
+    }
+}
+
+impl Clone for Box<Query> {
+    fn clone(&self) -> Box<Query> {
+        (**self).box_clone()
+    }
+}
+
+/// Downcasting of a `Query` to its concrete type.
+///
+/// It is implemented for all of the queries, e.g. to inspect the
+/// queries built by the [`QueryParser`](./struct.QueryParser.html).
+///
+/// ```rust
+/// use tantivy::query::{AllQuery, Query, QueryAny};
+///
+/// let query: Box<Query> = Box::new(AllQuery);
+/// assert!(query.as_any().downcast_ref::<AllQuery>().is_some());
+/// ```
+pub trait QueryAny {
+    /// Returns the query as an `Any`.
+    fn as_any(&self) -> &Any;
+}
+
+impl<T: 'static + Query> QueryAny for T {
+    fn as_any(&self) -> &Any {
+        self
+    }
+}
+
+/// Human readable, multi-line representation of a query tree.
+///
+/// Field names are resolved using the schema, and subqueries
+/// are indented according to their depth in the tree.
+///
+/// ```text
+/// Boolean
+///   Must Term(title:"mice")
+///   MustNot Phrase(title:"old" "man")
+/// ```
+pub struct QueryDisplay<'a> {
+    query: &'a Query,
+    schema: &'a Schema,
+}
+
+impl<'a> QueryDisplay<'a> {
+    /// Creates a `QueryDisplay` for the given query.
+    pub fn new(query: &'a Query, schema: &'a Schema) -> QueryDisplay<'a> {
+        QueryDisplay { query, schema }
+    }
+}
+
+impl<'a> fmt::Display for QueryDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.query.fmt_tree(self.schema, 0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Query;
+    use query::{AllQuery, BooleanQuery, BoostQuery, ExistsQuery, Occur, PhraseQuery, RangeQuery,
+                SynonymQuery, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, TEXT};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_queries_are_send_sync() {
+        assert_send_sync::<AllQuery>();
+        assert_send_sync::<BooleanQuery>();
+        assert_send_sync::<BoostQuery>();
+        assert_send_sync::<ExistsQuery>();
+        assert_send_sync::<PhraseQuery>();
+        assert_send_sync::<RangeQuery>();
+        assert_send_sync::<SynonymQuery>();
+        assert_send_sync::<TermQuery>();
+        assert_send_sync::<Box<Query>>();
+    }
+
+    #[test]
+    fn test_box_clone() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let term_query: Box<Query> = box TermQuery::new(
+            Term::from_field_text(title, "mice"),
+            IndexRecordOption::Basic,
+        );
+        let range_query: Box<Query> = box RangeQuery::new_u64(year, 1990..2000);
+        let boolean_query: Box<Query> = box BooleanQuery::from(vec![
+            (Occur::Must, term_query),
+            (Occur::MustNot, box BoostQuery::new(range_query, 2.0) as Box<Query>),
+        ]);
+        let cloned_query = boolean_query.clone();
+        assert_eq!(format!("{:?}", cloned_query), format!("{:?}", boolean_query));
     }
 }
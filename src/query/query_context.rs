@@ -0,0 +1,178 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+#[derive(Default)]
+struct ScorerArena {
+    buffers: HashMap<TypeId, Vec<Box<Any>>>,
+    num_allocated_buffers: usize,
+    num_reused_buffers: usize,
+}
+
+/// Context shared by the scorers of a search.
+///
+/// It acts as an arena from which the scorers obtain their
+/// temporary buffers. Buffers are given back to the context
+/// when the scorer owning them is dropped, so that the scorers
+/// of the next segment can reuse them instead of allocating
+/// new ones.
+///
+/// A context is meant to be used by a single thread,
+/// typically for the duration of a search.
+/// Cloning a `QueryContext` returns a handle to the same arena.
+#[derive(Clone, Default)]
+pub struct QueryContext {
+    arena: Rc<RefCell<ScorerArena>>,
+}
+
+impl QueryContext {
+    /// Creates a new empty context.
+    pub fn new() -> QueryContext {
+        QueryContext::default()
+    }
+
+    /// Returns the number of buffers that had to be allocated
+    /// because no buffer was available for reuse.
+    pub fn num_allocated_buffers(&self) -> usize {
+        self.arena.borrow().num_allocated_buffers
+    }
+
+    /// Returns the number of buffers that were reused.
+    pub fn num_reused_buffers(&self) -> usize {
+        self.arena.borrow().num_reused_buffers
+    }
+
+    /// Takes a buffer of type `T` from the arena.
+    ///
+    /// A recycled buffer is first reset using `reset`. If no buffer
+    /// is available, a new one is created using `create`.
+    pub(crate) fn take_buffer<T, TCreate, TReset>(
+        &self,
+        create: TCreate,
+        reset: TReset,
+    ) -> Pooled<T>
+    where
+        T: Any,
+        TCreate: FnOnce() -> Box<T>,
+        TReset: FnOnce(&mut T),
+    {
+        let recycled_opt: Option<Box<Any>> = {
+            let mut arena = self.arena.borrow_mut();
+            let recycled_opt = arena
+                .buffers
+                .get_mut(&TypeId::of::<T>())
+                .and_then(|buffers| buffers.pop());
+            if recycled_opt.is_some() {
+                arena.num_reused_buffers += 1;
+            } else {
+                arena.num_allocated_buffers += 1;
+            }
+            recycled_opt
+        };
+        let buffer = match recycled_opt {
+            Some(recycled) => {
+                let mut buffer: Box<T> = recycled
+                    .downcast::<T>()
+                    .expect("Buffers are indexed by their type id");
+                reset(&mut *buffer);
+                buffer
+            }
+            None => create(),
+        };
+        Pooled {
+            value: Some(buffer),
+            recycler_opt: Some((self.clone(), QueryContext::recycle::<T>)),
+        }
+    }
+
+    fn recycle<T: Any>(&self, buffer: Box<T>) {
+        self.arena
+            .borrow_mut()
+            .buffers
+            .entry(TypeId::of::<T>())
+            .or_insert_with(Vec::new)
+            .push(buffer);
+    }
+}
+
+/// Buffer that is given back to its `QueryContext`, if any,
+/// when dropped.
+pub(crate) struct Pooled<T> {
+    value: Option<Box<T>>,
+    recycler_opt: Option<(QueryContext, fn(&QueryContext, Box<T>))>,
+}
+
+impl<T> Pooled<T> {
+    /// Wraps a buffer that does not belong to any context.
+    pub fn unpooled(value: Box<T>) -> Pooled<T> {
+        Pooled {
+            value: Some(value),
+            recycler_opt: None,
+        }
+    }
+}
+
+impl<T> Deref for Pooled<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value.as_ref().expect("The buffer is only taken on drop")
+    }
+}
+
+impl<T> DerefMut for Pooled<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().expect("The buffer is only taken on drop")
+    }
+}
+
+impl<T> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        if let Some((context, recycle)) = self.recycler_opt.take() {
+            if let Some(value) = self.value.take() {
+                recycle(&context, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::QueryContext;
+
+    #[test]
+    fn test_query_context_reuses_buffers() {
+        let context = QueryContext::new();
+        {
+            let mut buffer = context.take_buffer(|| Box::new([0u32; 16]), |_| {});
+            buffer[3] = 7;
+            assert_eq!(context.num_allocated_buffers(), 1);
+        }
+        {
+            let buffer = context.take_buffer(
+                || Box::new([0u32; 16]),
+                |buffer| {
+                    assert_eq!(buffer[3], 7);
+                    buffer[3] = 0;
+                },
+            );
+            assert_eq!(buffer[3], 0);
+            let other_buffer = context.take_buffer(|| Box::new([1u32; 16]), |_| {});
+            assert_eq!(other_buffer[3], 1);
+            let vec_buffer = context.take_buffer(|| Box::new(vec![1u8]), |_| {});
+            assert_eq!(vec_buffer.len(), 1);
+        }
+        assert_eq!(context.num_allocated_buffers(), 3);
+        assert_eq!(context.num_reused_buffers(), 1);
+        let _first = context.take_buffer(|| Box::new([0u32; 16]), |_| {});
+        let _second = context.take_buffer(|| Box::new([0u32; 16]), |_| {});
+        let _third = context.take_buffer(|| Box::new([0u32; 16]), |_| {});
+        assert_eq!(context.num_allocated_buffers(), 4);
+        assert_eq!(context.num_reused_buffers(), 3);
+    }
+}
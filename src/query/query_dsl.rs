@@ -0,0 +1,724 @@
+use query::{AllQuery, BooleanQuery, BoostQuery, FastFieldRangeQuery, Occur, PhraseQuery, Query,
+            RangeQuery, TermQuery};
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use schema::{Cardinality, Field, FieldType, IndexRecordOption, Schema, Term};
+use serde_json::{self, Map, Value};
+use std::collections::Bound;
+use std::result;
+use Score;
+
+/// Possible error when converting a JSON query into a `Query`.
+///
+/// Except for `InvalidJson`, the first argument of each variant is the
+/// [JSON pointer](https://tools.ietf.org/html/rfc6901) of the node
+/// of the document that could not be converted.
+#[derive(Debug, PartialEq)]
+pub enum QueryDslError {
+    /// `InvalidJson(message: String)`
+    /// The document is not valid JSON.
+    InvalidJson(String),
+    /// `UnexpectedValue(path: String, expected: String)`
+    /// The node does not have the expected shape.
+    UnexpectedValue(String, String),
+    /// `UnknownQueryType(path: String, query_type: String)`
+    /// The node is not one of the supported query types.
+    UnknownQueryType(String, String),
+    /// `FieldDoesNotExist(path: String, field_name: String)`
+    /// The query references a field that is not in the schema.
+    FieldDoesNotExist(String, String),
+    /// `IncompatibleFieldType(path: String, field_name: String, expected_type: String)`
+    /// The value cannot be converted to a term of the field.
+    IncompatibleFieldType(String, String, String),
+    /// `Unsupported(path: String, feature: String)`
+    /// The query is valid, but relies on a feature tantivy does not support.
+    Unsupported(String, String),
+    /// `InvalidQuery(path: String, error: QueryValidationError)`
+    /// The query is not consistent with the schema.
+    /// (e.g. a phrase query on a field indexed without positions)
+    InvalidQuery(String, QueryValidationError),
+}
+
+impl QueryDslError {
+    /// Returns the JSON pointer of the node that could not be converted,
+    /// or `None` if the document is not valid JSON.
+    pub fn path(&self) -> Option<&str> {
+        match *self {
+            QueryDslError::InvalidJson(_) => None,
+            QueryDslError::UnexpectedValue(ref path, _)
+            | QueryDslError::UnknownQueryType(ref path, _)
+            | QueryDslError::FieldDoesNotExist(ref path, _)
+            | QueryDslError::IncompatibleFieldType(ref path, _, _)
+            | QueryDslError::Unsupported(ref path, _)
+            | QueryDslError::InvalidQuery(ref path, _) => Some(path),
+        }
+    }
+}
+
+/// Builds a `Query` from its JSON representation.
+///
+/// Each node of the document is an object with a single key, the type of the query:
+///
+/// * `{"term": {"title": "rust"}}` matches the documents containing a term.
+/// The value is not tokenized, and must be a number for `u64` and `i64` fields.
+/// * `{"phrase": {"title": ["part", "time"]}}` matches a sequence of terms.
+/// The terms may also be given as `{"terms": [...], "slop": 1}`,
+/// the slop being how far the terms may be from their positions in the phrase
+/// (see [`PhraseQuery::set_slop`](./struct.PhraseQuery.html#method.set_slop)).
+/// * `{"bool": {"must": [...], "should": [...], "must_not": [...]}}` combines
+/// queries. Each of the occur lists is optional.
+/// * `{"range": {"year": {"gte": 1960, "lt": 1970}}}` matches the documents with
+/// a term within a range. The bounds are given with `gt`, `gte`, `lt` and `lte`.
+/// The range of a `u64` or `i64` field that is not indexed but is a
+/// single valued fast field is evaluated on the fast field
+/// (see [`FastFieldRangeQuery`](./struct.FastFieldRangeQuery.html)).
+/// * `{"all": {}}` matches all of the documents.
+/// * `{"boost": {"query": {...}, "boost": 2.0}}` multiplies the scores of a query.
+///
+/// Fields are checked against the schema. An error names the
+/// [JSON pointer](https://tools.ietf.org/html/rfc6901) of the node that
+/// could not be converted.
+///
+/// ```rust
+/// # extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, INT_INDEXED, TEXT};
+/// use tantivy::query::{from_json, QueryDisplay};
+///
+/// # fn main() {
+/// let mut schema_builder = SchemaBuilder::new();
+/// schema_builder.add_text_field("title", TEXT);
+/// schema_builder.add_u64_field("year", INT_INDEXED);
+/// let schema = schema_builder.build();
+///
+/// let query = from_json(
+///     &schema,
+///     r#"{"bool": {
+///         "must": [{"term": {"title": "rust"}}],
+///         "must_not": [{"range": {"year": {"lt": 2015}}}]
+///     }}"#,
+/// ).unwrap();
+/// assert_eq!(
+///     format!("{}", QueryDisplay::new(&*query, &schema)),
+///     "Boolean\n  Must Term(title:\"rust\")\n  MustNot Range(year:(*..2015))\n"
+/// );
+/// # }
+/// ```
+pub fn from_json(schema: &Schema, json: &str) -> result::Result<Box<Query>, QueryDslError> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|err| QueryDslError::InvalidJson(err.to_string()))?;
+    QueryDslConverter { schema }.convert_query(&value, "")
+}
+
+/// Escapes a key to be used as a JSON pointer reference token.
+fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, escape_pointer_token(key))
+}
+
+fn unexpected_value<T>(path: &str, expected: &str) -> result::Result<T, QueryDslError> {
+    Err(QueryDslError::UnexpectedValue(
+        path.to_string(),
+        expected.to_string(),
+    ))
+}
+
+fn as_object<'a>(
+    value: &'a Value,
+    path: &str,
+) -> result::Result<&'a Map<String, Value>, QueryDslError> {
+    match *value {
+        Value::Object(ref map) => Ok(map),
+        _ => unexpected_value(path, "an object"),
+    }
+}
+
+/// Returns the only key of an object, and its value.
+fn as_single_entry<'a>(
+    value: &'a Value,
+    path: &str,
+) -> result::Result<(&'a str, &'a Value), QueryDslError> {
+    let map = as_object(value, path)?;
+    if map.len() != 1 {
+        return unexpected_value(path, "an object with a single key");
+    }
+    let (key, value) = map.iter().next().expect("the object has one key");
+    Ok((key.as_str(), value))
+}
+
+struct QueryDslConverter<'a> {
+    schema: &'a Schema,
+}
+
+impl<'a> QueryDslConverter<'a> {
+    fn resolve_field(&self, field_name: &str, path: &str) -> result::Result<Field, QueryDslError> {
+        self.schema.get_field(field_name).ok_or_else(|| {
+            QueryDslError::FieldDoesNotExist(path.to_string(), field_name.to_string())
+        })
+    }
+
+    fn validate_field(
+        &self,
+        field: Field,
+        required: IndexRecordOption,
+        path: &str,
+    ) -> result::Result<(), QueryDslError> {
+        validate_indexed_field(self.schema, field, required)
+            .map_err(|err| QueryDslError::InvalidQuery(path.to_string(), err))
+    }
+
+    fn convert_term(
+        &self,
+        field: Field,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Term, QueryDslError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let incompatible = |expected_type: &str| {
+            QueryDslError::IncompatibleFieldType(
+                path.to_string(),
+                field_entry.name().to_string(),
+                expected_type.to_string(),
+            )
+        };
+        match *field_entry.field_type() {
+            FieldType::Str(_) => value
+                .as_str()
+                .map(|text| Term::from_field_text(field, text))
+                .ok_or_else(|| incompatible("str")),
+            FieldType::U64(_) => value
+                .as_u64()
+                .map(|val| Term::from_field_u64(field, val))
+                .ok_or_else(|| incompatible("u64")),
+            FieldType::I64(_) => value
+                .as_i64()
+                .map(|val| Term::from_field_i64(field, val))
+                .ok_or_else(|| incompatible("i64")),
+            FieldType::HierarchicalFacet => Err(QueryDslError::Unsupported(
+                path.to_string(),
+                "facet fields".to_string(),
+            )),
+        }
+    }
+
+    fn convert_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let (query_type, query_value) = as_single_entry(value, path)?;
+        let query_path = child_path(path, query_type);
+        match query_type {
+            "term" => self.convert_term_query(query_value, &query_path),
+            "phrase" => self.convert_phrase_query(query_value, &query_path),
+            "bool" => self.convert_bool_query(query_value, &query_path),
+            "range" => self.convert_range_query(query_value, &query_path),
+            "all" => {
+                if !as_object(query_value, &query_path)?.is_empty() {
+                    return unexpected_value(&query_path, "an empty object");
+                }
+                Ok(box AllQuery)
+            }
+            "boost" => self.convert_boost_query(query_value, &query_path),
+            _ => Err(QueryDslError::UnknownQueryType(
+                path.to_string(),
+                query_type.to_string(),
+            )),
+        }
+    }
+
+    fn convert_term_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let (field_name, term_value) = as_single_entry(value, path)?;
+        let field_path = child_path(path, field_name);
+        let field = self.resolve_field(field_name, &field_path)?;
+        self.validate_field(field, IndexRecordOption::Basic, &field_path)?;
+        let term = self.convert_term(field, term_value, &field_path)?;
+        // term frequencies are requested, if they are available.
+        let index_record_option = self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option()
+            .map(|indexed| indexed.intersection(IndexRecordOption::WithFreqs))
+            .unwrap_or(IndexRecordOption::WithFreqs);
+        Ok(box TermQuery::new(term, index_record_option))
+    }
+
+    fn convert_phrase_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let (field_name, phrase_value) = as_single_entry(value, path)?;
+        let field_path = child_path(path, field_name);
+        let field = self.resolve_field(field_name, &field_path)?;
+        self.validate_field(field, IndexRecordOption::WithPositions, &field_path)?;
+        let mut slop = 0u32;
+        let (terms_value, terms_path) = match *phrase_value {
+            Value::Object(ref map) => {
+                for (key, value) in map {
+                    match key.as_str() {
+                        "terms" => {}
+                        "slop" => match value.as_u64() {
+                            Some(slop_value) if slop_value <= u64::from(u32::max_value()) => {
+                                slop = slop_value as u32;
+                            }
+                            _ => {
+                                return unexpected_value(
+                                    &child_path(&field_path, key),
+                                    "a non-negative 32 bits integer",
+                                );
+                            }
+                        },
+                        _ => {
+                            return unexpected_value(
+                                &child_path(&field_path, key),
+                                "\"terms\" or \"slop\"",
+                            );
+                        }
+                    }
+                }
+                let terms_value = map.get("terms").ok_or_else(|| {
+                    QueryDslError::UnexpectedValue(
+                        field_path.clone(),
+                        "an object with a \"terms\" key".to_string(),
+                    )
+                })?;
+                (terms_value, child_path(&field_path, "terms"))
+            }
+            _ => (phrase_value, field_path.clone()),
+        };
+        let term_values = match *terms_value {
+            Value::Array(ref term_values) if term_values.len() >= 2 => term_values,
+            _ => {
+                return unexpected_value(&terms_path, "an array of at least two terms");
+            }
+        };
+        let terms = term_values
+            .iter()
+            .enumerate()
+            .map(|(ord, term_value)| {
+                self.convert_term(field, term_value, &child_path(&terms_path, &ord.to_string()))
+            })
+            .collect::<result::Result<Vec<Term>, QueryDslError>>()?;
+        Ok(box PhraseQuery::new_with_slop(terms, slop))
+    }
+
+    fn convert_bool_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let map = as_object(value, path)?;
+        if let Some(key) = map.keys()
+            .find(|key| !["must", "should", "must_not"].contains(&key.as_str()))
+        {
+            return unexpected_value(
+                &child_path(path, key),
+                "\"must\", \"should\" or \"must_not\"",
+            );
+        }
+        let mut subqueries: Vec<(Occur, Box<Query>)> = vec![];
+        // the clauses are listed in the order of the occurs,
+        // whatever the order of the keys in the document.
+        for &(key, occur) in &[
+            ("must", Occur::Must),
+            ("should", Occur::Should),
+            ("must_not", Occur::MustNot),
+        ] {
+            let occur_path = child_path(path, key);
+            let clauses = match map.get(key) {
+                Some(&Value::Array(ref clauses)) => clauses,
+                Some(_) => {
+                    return unexpected_value(&occur_path, "an array of queries");
+                }
+                None => {
+                    continue;
+                }
+            };
+            for (ord, clause) in clauses.iter().enumerate() {
+                let clause_path = child_path(&occur_path, &ord.to_string());
+                let subquery = self.convert_query(clause, &clause_path)?;
+                subqueries.push((occur, subquery));
+            }
+        }
+        if subqueries.is_empty() {
+            return unexpected_value(path, "at least one clause");
+        }
+        Ok(box BooleanQuery::from(subqueries))
+    }
+
+    fn convert_range_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let (field_name, bounds_value) = as_single_entry(value, path)?;
+        let field_path = child_path(path, field_name);
+        let field = self.resolve_field(field_name, &field_path)?;
+        let field_entry = self.schema.get_field_entry(field);
+        // terms are preferred when the field is indexed.
+        let on_fast_field = !field_entry.is_indexed() && match *field_entry.field_type() {
+            FieldType::U64(ref options) | FieldType::I64(ref options) => {
+                options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+            }
+            _ => false,
+        };
+        if !on_fast_field {
+            self.validate_field(field, IndexRecordOption::Basic, &field_path)?;
+        }
+        let bounds = as_object(bounds_value, &field_path)?;
+        let mut lower_bound: Bound<Term> = Bound::Unbounded;
+        let mut upper_bound: Bound<Term> = Bound::Unbounded;
+        for (key, bound_value) in bounds {
+            let bound_path = child_path(&field_path, key);
+            let (bound, is_included) = match key.as_str() {
+                "gt" => (&mut lower_bound, false),
+                "gte" => (&mut lower_bound, true),
+                "lt" => (&mut upper_bound, false),
+                "lte" => (&mut upper_bound, true),
+                _ => {
+                    return unexpected_value(
+                        &bound_path,
+                        "\"gt\", \"gte\", \"lt\" or \"lte\"",
+                    );
+                }
+            };
+            if let Bound::Unbounded = *bound {
+                let term = self.convert_term(field, bound_value, &bound_path)?;
+                *bound = if is_included {
+                    Bound::Included(term)
+                } else {
+                    Bound::Excluded(term)
+                };
+            } else {
+                return unexpected_value(&bound_path, "a single lower and a single upper bound");
+            }
+        }
+        let range_query: Box<Query> = match *field_entry.field_type() {
+            FieldType::U64(_) => {
+                let range = (
+                    map_bound(lower_bound, &|term: &Term| term.get_u64()),
+                    map_bound(upper_bound, &|term: &Term| term.get_u64()),
+                );
+                if on_fast_field {
+                    box FastFieldRangeQuery::new_u64(field, range)
+                } else {
+                    box RangeQuery::new_u64(field, range)
+                }
+            }
+            FieldType::I64(_) => {
+                let range = (
+                    map_bound(lower_bound, &|term: &Term| term.get_i64()),
+                    map_bound(upper_bound, &|term: &Term| term.get_i64()),
+                );
+                if on_fast_field {
+                    box FastFieldRangeQuery::new_i64(field, range)
+                } else {
+                    box RangeQuery::new_i64(field, range)
+                }
+            }
+            _ => {
+                let lower_text = map_bound(lower_bound, &|term: &Term| term.text().to_string());
+                let upper_text = map_bound(upper_bound, &|term: &Term| term.text().to_string());
+                box RangeQuery::new_str(
+                    field,
+                    (borrow_bound(&lower_text), borrow_bound(&upper_text)),
+                )
+            }
+        };
+        Ok(range_query)
+    }
+
+    fn convert_boost_query(
+        &self,
+        value: &Value,
+        path: &str,
+    ) -> result::Result<Box<Query>, QueryDslError> {
+        let map = as_object(value, path)?;
+        if let Some(key) = map.keys().find(|key| *key != "query" && *key != "boost") {
+            return unexpected_value(&child_path(path, key), "\"query\" or \"boost\"");
+        }
+        let boost_path = child_path(path, "boost");
+        let boost = match map.get("boost").and_then(|boost| boost.as_f64()) {
+            Some(boost) if boost >= 0.0 => boost as Score,
+            _ => {
+                return unexpected_value(&boost_path, "a non-negative number");
+            }
+        };
+        let query_path = child_path(path, "query");
+        let query_value = map.get("query").ok_or_else(|| {
+            QueryDslError::UnexpectedValue(query_path.clone(), "a query".to_string())
+        })?;
+        let query = self.convert_query(query_value, &query_path)?;
+        Ok(box BoostQuery::new(query, boost))
+    }
+}
+
+fn map_bound<T, F: Fn(&Term) -> T>(bound: Bound<Term>, transform: &F) -> Bound<T> {
+    match bound {
+        Bound::Included(ref term) => Bound::Included(transform(term)),
+        Bound::Excluded(ref term) => Bound::Excluded(transform(term)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn borrow_bound(bound: &Bound<String>) -> Bound<&str> {
+    match *bound {
+        Bound::Included(ref text) => Bound::Included(text.as_str()),
+        Bound::Excluded(ref text) => Bound::Excluded(text.as_str()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{from_json, QueryDslError};
+    use query::{AllQuery, BooleanQuery, BoostQuery, Occur, PhraseQuery, Query, QueryDisplay,
+                QueryValidationError, RangeQuery, TermQuery};
+    use schema::{Field, IndexRecordOption, Schema, SchemaBuilder, Term, INT_INDEXED, STORED,
+                 STRING, TEXT};
+    use std::collections::Bound;
+    use Index;
+
+    fn make_schema() -> (Schema, Field, Field, Field, Field) {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let delta = schema_builder.add_i64_field("delta", INT_INDEXED);
+        schema_builder.add_text_field("author", STORED);
+        (schema_builder.build(), title, tag, year, delta)
+    }
+
+    fn display(query: &Query, schema: &Schema) -> String {
+        format!("{}", QueryDisplay::new(query, schema))
+    }
+
+    fn check(schema: &Schema, json: &str, expected: &Query) {
+        let query = from_json(schema, json).unwrap();
+        assert_eq!(display(&*query, schema), display(expected, schema));
+    }
+
+    fn check_err(schema: &Schema, json: &str) -> QueryDslError {
+        from_json(schema, json).map(|_| ()).unwrap_err()
+    }
+
+    fn term_query(term: Term, index_record_option: IndexRecordOption) -> Box<Query> {
+        box TermQuery::new(term, index_record_option)
+    }
+
+    #[test]
+    fn test_query_dsl_round_trip() {
+        let (schema, title, tag, year, delta) = make_schema();
+        check(
+            &schema,
+            r#"{"term": {"title": "rust"}}"#,
+            &TermQuery::new(
+                Term::from_field_text(title, "rust"),
+                IndexRecordOption::WithFreqs,
+            ),
+        );
+        check(
+            &schema,
+            r#"{"term": {"year": 2018}}"#,
+            &TermQuery::new(Term::from_field_u64(year, 2018), IndexRecordOption::Basic),
+        );
+        check(
+            &schema,
+            r#"{"term": {"delta": -3}}"#,
+            &TermQuery::new(Term::from_field_i64(delta, -3), IndexRecordOption::Basic),
+        );
+        let phrase = || {
+            PhraseQuery::from(vec![
+                Term::from_field_text(title, "part"),
+                Term::from_field_text(title, "time"),
+            ])
+        };
+        check(&schema, r#"{"phrase": {"title": ["part", "time"]}}"#, &phrase());
+        check(
+            &schema,
+            r#"{"phrase": {"title": {"terms": ["part", "time"], "slop": 0}}}"#,
+            &phrase(),
+        );
+        check(
+            &schema,
+            r#"{"phrase": {"title": {"terms": ["part", "time"], "slop": 2}}}"#,
+            &PhraseQuery::new_with_slop(
+                vec![
+                    Term::from_field_text(title, "part"),
+                    Term::from_field_text(title, "time"),
+                ],
+                2,
+            ),
+        );
+        check(
+            &schema,
+            r#"{"range": {"year": {"gte": 1960, "lt": 1970}}}"#,
+            &RangeQuery::new_u64(year, 1960..1970),
+        );
+        check(
+            &schema,
+            r#"{"range": {"delta": {"gt": -10}}}"#,
+            &RangeQuery::new_i64(delta, (Bound::Excluded(-10), Bound::Unbounded)),
+        );
+        check(
+            &schema,
+            r#"{"range": {"tag": {"lte": "m"}}}"#,
+            &RangeQuery::new_str(tag, (Bound::Unbounded, Bound::Included("m"))),
+        );
+        check(&schema, r#"{"all": {}}"#, &AllQuery);
+        check(
+            &schema,
+            r#"{"boost": {"query": {"all": {}}, "boost": 2.5}}"#,
+            &BoostQuery::new(box AllQuery, 2.5),
+        );
+        let range_query: Box<Query> = box RangeQuery::new_u64(year, 2015..);
+        check(
+            &schema,
+            r#"{"bool": {
+                "must_not": [{"term": {"tag": "draft"}}],
+                "should": [
+                    {"term": {"title": "rust"}},
+                    {"boost": {"query": {"phrase": {"title": ["part", "time"]}}, "boost": 2}}
+                ],
+                "must": [{"range": {"year": {"gte": 2015}}}]
+            }}"#,
+            &BooleanQuery::from(vec![
+                (Occur::Must, range_query),
+                (
+                    Occur::Should,
+                    term_query(
+                        Term::from_field_text(title, "rust"),
+                        IndexRecordOption::WithFreqs,
+                    ),
+                ),
+                (Occur::Should, box BoostQuery::new(box phrase(), 2.0)),
+                (
+                    Occur::MustNot,
+                    term_query(Term::from_field_text(tag, "draft"), IndexRecordOption::Basic),
+                ),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_query_dsl_phrase_slop() {
+        let (schema, title, ..) = make_schema();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer
+                .add_document(doc!(title => "part of the time"))
+                .unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |slop: u32| {
+            let json = format!(
+                r#"{{"phrase": {{"title": {{"terms": ["part", "time"], "slop": {}}}}}}}"#,
+                slop
+            );
+            let query = from_json(&schema, &json).unwrap();
+            query.count(&*searcher).unwrap()
+        };
+        assert_eq!(count(0), 0);
+        assert_eq!(count(1), 0);
+        assert_eq!(count(2), 1);
+    }
+
+    #[test]
+    fn test_query_dsl_errors() {
+        let (schema, ..) = make_schema();
+        assert_matches!(check_err(&schema, "{"), QueryDslError::InvalidJson(_));
+        assert_eq!(
+            check_err(&schema, r#"{"fuzzy": {"title": "rust"}}"#),
+            QueryDslError::UnknownQueryType("".to_string(), "fuzzy".to_string())
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"all": {}, "term": {"title": "rust"}}"#),
+            QueryDslError::UnexpectedValue(
+                "".to_string(),
+                "an object with a single key".to_string()
+            )
+        );
+        assert_eq!(
+            check_err(
+                &schema,
+                r#"{"bool": {"must": [{"all": {}}, {"term": {"titl": "rust"}}]}}"#
+            ),
+            QueryDslError::FieldDoesNotExist(
+                "/bool/must/1/term/titl".to_string(),
+                "titl".to_string()
+            )
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"term": {"year": "2018"}}"#),
+            QueryDslError::IncompatibleFieldType(
+                "/term/year".to_string(),
+                "year".to_string(),
+                "u64".to_string()
+            )
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"range": {"year": {"gte": -1}}}"#).path(),
+            Some("/range/year/gte")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"range": {"year": {"gt": 1, "gte": 2}}}"#).path(),
+            Some("/range/year/gte")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"term": {"author": "rothfuss"}}"#),
+            QueryDslError::InvalidQuery(
+                "/term/author".to_string(),
+                QueryValidationError::FieldNotIndexed("author".to_string())
+            )
+        );
+        assert_matches!(
+            check_err(&schema, r#"{"phrase": {"tag": ["a", "b"]}}"#),
+            QueryDslError::InvalidQuery(_, QueryValidationError::InsufficientIndexRecordOption(..))
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"phrase": {"title": {"terms": ["a", "b"], "slop": -1}}}"#)
+                .path(),
+            Some("/phrase/title/slop")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"phrase": {"title": ["a", 3]}}"#).path(),
+            Some("/phrase/title/1")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"phrase": {"title": ["a"]}}"#).path(),
+            Some("/phrase/title")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"bool": {"must": [], "filter": []}}"#).path(),
+            Some("/bool/filter")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"bool": {"must": []}}"#).path(),
+            Some("/bool")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"boost": {"query": {"all": {}}}}"#).path(),
+            Some("/boost/boost")
+        );
+        assert_eq!(
+            check_err(&schema, r#"{"boost": {"query": {"all": []}, "boost": 1}}"#).path(),
+            Some("/boost/query/all")
+        );
+        // keys are escaped in the pointers.
+        assert_eq!(
+            check_err(&schema, r#"{"term": {"a/b~c": "rust"}}"#).path(),
+            Some("/term/a~1b~0c")
+        );
+    }
+}
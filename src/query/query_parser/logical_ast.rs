@@ -1,17 +1,59 @@
 use std::fmt;
-use schema::Term;
+use std::collections::Bound;
+use schema::{Field, Term};
 use query::Occur;
+use Score;
 
 #[derive(Clone)]
 pub enum LogicalLiteral {
     Term(Term),
-    Phrase(Vec<Term>),
+    // the terms with their positions, and the slop of the phrase.
+    Phrase(Vec<(usize, Term)>, u32),
+    Synonyms(Vec<Term>),
+    Range(Field, Bound<Term>, Bound<Term>),
 }
 
 #[derive(Clone)]
 pub enum LogicalAST {
     Clause(Vec<(Occur, LogicalAST)>),
     Leaf(Box<LogicalLiteral>),
+    Boost(Box<LogicalAST>, Score),
+}
+
+impl LogicalAST {
+    /// Boosts the AST.
+    ///
+    /// The boosts of the nested boosted ASTs are multiplied.
+    pub fn boosted(self, boost: Score) -> LogicalAST {
+        match self {
+            LogicalAST::Boost(ast, inner_boost) => LogicalAST::Boost(ast, inner_boost * boost),
+            ast => LogicalAST::Boost(box ast, boost),
+        }
+    }
+
+    /// Sets the slop of the phrases of a literal.
+    ///
+    /// The ASTs that are not phrases, e.g. because the text of
+    /// the literal was a single token, are left unchanged.
+    pub fn with_phrase_slop(self, slop: u32) -> LogicalAST {
+        match self {
+            LogicalAST::Leaf(literal) => match *literal {
+                LogicalLiteral::Phrase(terms, _) => {
+                    LogicalAST::from(LogicalLiteral::Phrase(terms, slop))
+                }
+                literal => LogicalAST::from(literal),
+            },
+            LogicalAST::Clause(clause) => LogicalAST::Clause(
+                clause
+                    .into_iter()
+                    .map(|(occur, ast)| (occur, ast.with_phrase_slop(slop)))
+                    .collect(),
+            ),
+            LogicalAST::Boost(ast, boost) => {
+                LogicalAST::Boost(box ast.with_phrase_slop(slop), boost)
+            }
+        }
+    }
 }
 
 fn occur_letter(occur: Occur) -> &'static str {
@@ -39,6 +81,7 @@ impl fmt::Debug for LogicalAST {
                 Ok(())
             }
             LogicalAST::Leaf(ref literal) => write!(formatter, "{:?}", literal),
+            LogicalAST::Boost(ref ast, boost) => write!(formatter, "({:?})^{}", ast, boost),
         }
     }
 }
@@ -53,7 +96,42 @@ impl fmt::Debug for LogicalLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             LogicalLiteral::Term(ref term) => write!(formatter, "{:?}", term),
-            LogicalLiteral::Phrase(ref terms) => write!(formatter, "\"{:?}\"", terms),
+            LogicalLiteral::Phrase(ref terms, slop) => {
+                // the positions are only written if some are skipped or shared.
+                let has_adjacent_terms = terms
+                    .iter()
+                    .enumerate()
+                    .all(|(ord, &(offset, _))| offset == ord);
+                write!(formatter, "\"[")?;
+                for (ord, &(position, ref term)) in terms.iter().enumerate() {
+                    if ord > 0 {
+                        write!(formatter, ", ")?;
+                    }
+                    if !has_adjacent_terms {
+                        write!(formatter, "{}: ", position)?;
+                    }
+                    write!(formatter, "{:?}", term)?;
+                }
+                write!(formatter, "]\"")?;
+                if slop > 0 {
+                    write!(formatter, "~{}", slop)?;
+                }
+                Ok(())
+            }
+            LogicalLiteral::Synonyms(ref terms) => write!(formatter, "Synonyms({:?})", terms),
+            LogicalLiteral::Range(_, ref lower, ref upper) => {
+                match *lower {
+                    Bound::Included(ref term) => write!(formatter, "[{:?}", term)?,
+                    Bound::Excluded(ref term) => write!(formatter, "{{{:?}", term)?,
+                    Bound::Unbounded => write!(formatter, "{{*")?,
+                }
+                write!(formatter, " TO ")?;
+                match *upper {
+                    Bound::Included(ref term) => write!(formatter, "{:?}]", term),
+                    Bound::Excluded(ref term) => write!(formatter, "{:?}}}", term),
+                    Bound::Unbounded => write!(formatter, "*}}"),
+                }
+            }
         }
     }
 }
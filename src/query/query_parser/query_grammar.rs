@@ -1,35 +1,93 @@
 use combine::*;
 use combine::char::*;
 use super::user_input_ast::*;
+use Score;
+use std::cell::Cell;
+
+/// Counts the literals and the ranges of a query while it is parsed,
+/// and stops the parsing as soon as there are more than `max_clauses` of them.
+pub struct ClauseCounter {
+    num_clauses: Cell<usize>,
+    max_clauses: usize,
+}
+
+impl ClauseCounter {
+    /// Creates a counter stopping the parsing after `max_clauses` clauses.
+    pub fn new(max_clauses: usize) -> ClauseCounter {
+        ClauseCounter {
+            num_clauses: Cell::new(0),
+            max_clauses,
+        }
+    }
+
+    /// Returns the number of literals and ranges parsed so far.
+    pub fn num_clauses(&self) -> usize {
+        self.num_clauses.get()
+    }
+
+    /// Returns true iff the query has more than `max_clauses` clauses.
+    pub fn exceeds_max_clauses(&self) -> bool {
+        self.num_clauses.get() > self.max_clauses
+    }
+
+    fn count_clause(&self) {
+        self.num_clauses.set(self.num_clauses.get() + 1);
+    }
+}
+
+/// Returns true iff the char separates two clauses of the query.
+///
+/// On top of unicode whitespaces (including the full-width space
+/// `U+3000` and no-break spaces), zero-width characters are
+/// considered as separators, as they are not part of indexed terms.
+pub fn is_separator(c: char) -> bool {
+    c.is_whitespace() || match c {
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => true,
+        _ => false,
+    }
+}
+
+fn field_name<I>(input: I) -> ParseResult<String, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        letter(),
+        many(satisfy(|c: char| c.is_alphanumeric() || c == '_' || c == '.')),
+    ).map(|(s1, s2): (char, String)| format!("{}{}", s1, s2))
+        .parse_stream(input)
+}
 
 fn literal<I>(input: I) -> ParseResult<UserInputAST, I>
 where
     I: Stream<Item = char>,
 {
     let term_val = || {
-        let word = many1(satisfy(|c: char| c.is_alphanumeric()));
-        let phrase = (char('"'), many1(satisfy(|c| c != '"')), char('"')).map(|(_, s, _)| s);
+        let word = many1(satisfy(|c: char| c.is_alphanumeric())).map(|word| (word, None));
+        // out of range slops are rejected before parsing, by `check_modifiers`.
+        let slop = (char('~'), many1(digit()))
+            .map(|(_, slop): (char, String)| slop.parse::<u32>().unwrap_or(u32::max_value()));
+        let phrase = (char('"'), many1(satisfy(|c| c != '"')), char('"'), optional(slop))
+            .map(|(_, s, _, slop)| (s, slop));
         phrase.or(word)
     };
 
     let negative_numbers = (char('-'), many1(satisfy(|c: char| c.is_numeric())))
-        .map(|(s1, s2): (char, String)| format!("{}{}", s1, s2));
-
-    let field = (
-        letter(),
-        many(satisfy(|c: char| c.is_alphanumeric() || c == '_')),
-    ).map(|(s1, s2): (char, String)| format!("{}{}", s1, s2));
+        .map(|(s1, s2): (char, String)| (format!("{}{}", s1, s2), None));
 
     let term_val_with_field = negative_numbers.or(term_val());
 
-    let term_query =
-        (field, char(':'), term_val_with_field).map(|(field_name, _, phrase)| UserInputLiteral {
+    let term_query = (parser(field_name), char(':'), term_val_with_field).map(
+        |(field_name, _, (phrase, slop))| UserInputLiteral {
             field_name: Some(field_name),
             phrase,
-        });
-    let term_default_field = term_val().map(|phrase| UserInputLiteral {
+            slop,
+        },
+    );
+    let term_default_field = term_val().map(|(phrase, slop)| UserInputLiteral {
         field_name: None,
         phrase,
+        slop,
     });
     try(term_query)
         .or(term_default_field)
@@ -37,24 +95,206 @@ where
         .parse_stream(input)
 }
 
-fn leaf<I>(input: I) -> ParseResult<UserInputAST, I>
+fn range_bound(value: String, is_inclusive: bool) -> UserInputBound {
+    if value == "*" {
+        UserInputBound::Unbounded
+    } else if is_inclusive {
+        UserInputBound::Inclusive(value)
+    } else {
+        UserInputBound::Exclusive(value)
+    }
+}
+
+/// Parses a range, e.g. `price:[10 TO 100}`.
+///
+/// `[` and `]` delimit inclusive bounds, `{` and `}` exclusive
+/// bounds, and `*` stands for an unbounded side of the range.
+fn range<I>(input: I) -> ParseResult<UserInputAST, I>
+where
+    I: Stream<Item = char>,
+{
+    let bound_value =
+        || many1::<String, _>(satisfy(|c: char| !is_separator(c) && !"[]{}()\"".contains(c)));
+    let lower = (
+        char('[').or(char('{')),
+        skip_many(satisfy(is_separator)),
+        bound_value(),
+    ).map(|(bracket, _, value): (char, (), String)| range_bound(value, bracket == '['));
+    let upper = (
+        bound_value(),
+        skip_many(satisfy(is_separator)),
+        char(']').or(char('}')),
+    ).map(|(value, _, bracket): (String, (), char)| range_bound(value, bracket == ']'));
+    let to = (
+        skip_many1(satisfy(is_separator)),
+        string("TO"),
+        skip_many1(satisfy(is_separator)),
+    );
+    (parser(field_name), char(':'), lower, to, upper)
+        .map(|(field_name, _, lower, _, upper)| UserInputAST::Range {
+            field_name,
+            lower,
+            upper,
+        })
+        .parse_stream(input)
+}
+
+/// Parses the boost of a clause, e.g. `^1.5`.
+fn boost<I>(input: I) -> ParseResult<Score, I>
 where
     I: Stream<Item = char>,
 {
-    (char('-'), parser(leaf))
-        .map(|(_, expr)| UserInputAST::Not(box expr))
-        .or((char('+'), parser(leaf)).map(|(_, expr)| UserInputAST::Must(box expr)))
-        .or((char('('), parser(parse_to_ast), char(')')).map(|(_, expr, _)| expr))
+    let fraction = (char('.'), many1(digit())).map(|(_, digits): (char, String)| digits);
+    (char('^'), many1(digit()), optional(fraction))
+        .map(|(_, integer_part, fraction_opt): (char, String, Option<String>)| {
+            let boost = match fraction_opt {
+                Some(fraction) => format!("{}.{}", integer_part, fraction),
+                None => integer_part,
+            };
+            boost
+                .parse::<Score>()
+                .expect("Digits always parse as a float")
+        })
+        .parse_stream(input)
+}
+
+fn leaf<I>(clause_counter: &ClauseCounter, input: I) -> ParseResult<UserInputAST, I>
+where
+    I: Stream<Item = char>,
+{
+    if clause_counter.exceeds_max_clauses() {
+        // stops the parsing, the caller reporting the exceeded limit.
+        return unexpected("clause")
+            .map(|_| UserInputAST::Clause(Vec::new()))
+            .parse_stream(input);
+    }
+    let group = (
+        char('('),
+        parser(|input| parse_to_ast_with_counter(clause_counter, input)),
+        char(')'),
+    ).map(|(_, expr, _)| expr);
+    let counted_clause = try(parser(range))
         .or(parser(literal))
+        .map(|expr| {
+            clause_counter.count_clause();
+            expr
+        });
+    let clause = group.or(counted_clause);
+    let boosted_leaf = (clause, optional(parser(boost))).map(
+        |(expr, boost_opt)| match boost_opt {
+            Some(boost) => UserInputAST::Boost(box expr, boost),
+            None => expr,
+        },
+    );
+    // `NOT` is only an operator if it is followed by a clause,
+    // and is searched as a word otherwise.
+    let sub_leaf = || parser(|input| leaf(clause_counter, input));
+    let not = try((string("NOT"), skip_many1(satisfy(is_separator)), sub_leaf()))
+        .map(|(_, _, expr)| expr);
+    (char('-'), sub_leaf())
+        .map(|(_, expr)| expr)
+        .or(not)
+        .map(|expr| UserInputAST::Not(box expr))
+        .or((char('+'), sub_leaf()).map(|(_, expr)| UserInputAST::Must(box expr)))
+        .or(boosted_leaf)
+        .parse_stream(input)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BinaryOperator {
+    And,
+    Or,
+}
+
+/// Parses a binary operator, surrounded by separators, e.g. ` AND `.
+fn binary_operator<I>(input: I) -> ParseResult<BinaryOperator, I>
+where
+    I: Stream<Item = char>,
+{
+    let operator = string("AND")
+        .map(|_| BinaryOperator::And)
+        .or(string("OR").map(|_| BinaryOperator::Or));
+    (
+        skip_many1(satisfy(is_separator)),
+        operator,
+        skip_many1(satisfy(is_separator)),
+    ).map(|(_, operator, _): ((), BinaryOperator, ())| operator)
+        .parse_stream(input)
+}
+
+/// Groups the operands of the binary operators,
+/// `AND` taking precedence over `OR`.
+fn group_operands(
+    first: UserInputAST,
+    rest: Vec<(BinaryOperator, UserInputAST)>,
+) -> UserInputAST {
+    let mut disjuncts: Vec<Vec<UserInputAST>> = vec![vec![first]];
+    for (operator, operand) in rest {
+        match operator {
+            BinaryOperator::And => disjuncts.last_mut().unwrap().push(operand),
+            BinaryOperator::Or => disjuncts.push(vec![operand]),
+        }
+    }
+    let mut disjuncts: Vec<UserInputAST> = disjuncts
+        .into_iter()
+        .map(|mut conjuncts| {
+            if conjuncts.len() == 1 {
+                conjuncts.pop().unwrap()
+            } else {
+                UserInputAST::And(conjuncts.into_iter().map(Box::new).collect())
+            }
+        })
+        .collect();
+    if disjuncts.len() == 1 {
+        disjuncts.pop().unwrap()
+    } else {
+        UserInputAST::Or(disjuncts.into_iter().map(Box::new).collect())
+    }
+}
+
+/// Parses clauses joined by binary operators, e.g. `a AND b OR c`.
+///
+/// Like `NOT`, `AND` and `OR` are only operators if they are
+/// followed by a clause, and are searched as words otherwise.
+fn operation<I>(clause_counter: &ClauseCounter, input: I) -> ParseResult<UserInputAST, I>
+where
+    I: Stream<Item = char>,
+{
+    let operand = || parser(|input| leaf(clause_counter, input));
+    let operator_and_operand = try((parser(binary_operator), operand()));
+    (operand(), many::<Vec<_>, _>(operator_and_operand))
+        .map(|(first, rest)| group_operands(first, rest))
         .parse_stream(input)
 }
 
+#[cfg(test)]
 pub fn parse_to_ast<I>(input: I) -> ParseResult<UserInputAST, I>
 where
     I: Stream<Item = char>,
 {
-    sep_by(parser(leaf), spaces())
-        .map(|subqueries: Vec<UserInputAST>| {
+    parse_to_ast_with_counter(&ClauseCounter::new(::std::usize::MAX), input)
+}
+
+/// Parses a query, counting its literals and ranges with `clause_counter`.
+///
+/// The parsing stops as soon as the counter exceeds its maximum number
+/// of clauses, in which case the result must be ignored.
+///
+/// The whole query must be parsed: input left over after the last
+/// clause, other than separators, is a parse error.
+pub fn parse_to_ast_with_counter<I>(
+    clause_counter: &ClauseCounter,
+    input: I,
+) -> ParseResult<UserInputAST, I>
+where
+    I: Stream<Item = char>,
+{
+    let subqueries = sep_by(
+        parser(|input| operation(clause_counter, input)),
+        skip_many(satisfy(is_separator)),
+    );
+    (subqueries, skip_many(satisfy(is_separator)), eof())
+        .map(|(subqueries, _, _): (Vec<UserInputAST>, _, _)| {
             if subqueries.len() == 1 {
                 subqueries.into_iter().next().unwrap()
             } else {
@@ -93,4 +333,101 @@ mod test {
         test_parse_query_to_ast_helper("abc:\"a b\"", "abc:\"a b\"");
         test_is_parse_err("abc +    ");
     }
+
+    #[test]
+    fn test_parse_query_to_ast_not() {
+        test_parse_query_to_ast_helper("rust NOT java", "(\"rust\" -(\"java\"))");
+        test_parse_query_to_ast_helper("NOT abc:toto", "-(abc:\"toto\")");
+        test_parse_query_to_ast_helper("NOT  (a b)", "-((\"a\" \"b\"))");
+        test_parse_query_to_ast_helper("NOT -a", "-(-(\"a\"))");
+        test_parse_query_to_ast_helper("a NOT", "(\"a\" \"NOT\")");
+        test_parse_query_to_ast_helper("NOTa", "\"NOTa\"");
+        test_parse_query_to_ast_helper("not a", "(\"not\" \"a\")");
+        test_parse_query_to_ast_helper("\"NOT a\"", "\"NOT a\"");
+        test_parse_query_to_ast_helper("\"-a\"", "\"-a\"");
+    }
+
+    #[test]
+    fn test_parse_query_to_ast_binary_operators() {
+        test_parse_query_to_ast_helper("a AND b", "(\"a\" AND \"b\")");
+        test_parse_query_to_ast_helper("a OR b OR c", "(\"a\" OR \"b\" OR \"c\")");
+        test_parse_query_to_ast_helper(
+            "a OR b AND c",
+            "(\"a\" OR (\"b\" AND \"c\"))",
+        );
+        test_parse_query_to_ast_helper(
+            "a AND b OR c AND d",
+            "((\"a\" AND \"b\") OR (\"c\" AND \"d\"))",
+        );
+        test_parse_query_to_ast_helper(
+            "(rust OR golang) AND (async OR concurrent)",
+            "((\"rust\" OR \"golang\") AND (\"async\" OR \"concurrent\"))",
+        );
+        test_parse_query_to_ast_helper(
+            "a (b AND (c OR (d e)))",
+            "(\"a\" (\"b\" AND (\"c\" OR (\"d\" \"e\"))))",
+        );
+        test_parse_query_to_ast_helper(
+            "title:a AND -b OR NOT c",
+            "((title:\"a\" AND -(\"b\")) OR -(\"c\"))",
+        );
+        test_parse_query_to_ast_helper("a b OR c", "(\"a\" (\"b\" OR \"c\"))");
+        // not operators.
+        test_parse_query_to_ast_helper("a AND", "(\"a\" \"AND\")");
+        test_parse_query_to_ast_helper("OR a", "(\"OR\" \"a\")");
+        test_parse_query_to_ast_helper("a ANDb", "(\"a\" \"ANDb\")");
+        test_parse_query_to_ast_helper("a and b", "(\"a\" \"and\" \"b\")");
+        test_parse_query_to_ast_helper("\"a OR b\"", "\"a OR b\"");
+    }
+
+    #[test]
+    fn test_parse_query_to_ast_modifiers() {
+        test_parse_query_to_ast_helper("\"a b\"~2", "\"a b\"~2");
+        test_parse_query_to_ast_helper("abc:\"a b\"~2^0.5", "(abc:\"a b\"~2)^0.5");
+        test_parse_query_to_ast_helper("a^2 b", "((\"a\")^2 \"b\")");
+        test_parse_query_to_ast_helper("-a^2", "-((\"a\")^2)");
+        test_parse_query_to_ast_helper("(a b)^1.5", "((\"a\" \"b\"))^1.5");
+        test_parse_query_to_ast_helper("((a)^2)^3", "((\"a\")^2)^3");
+    }
+
+    #[test]
+    fn test_parse_query_to_ast_range() {
+        test_parse_query_to_ast_helper("year:[1990 TO 2000]", "year:[\"1990\" TO \"2000\"]");
+        test_parse_query_to_ast_helper("year:{1990 TO 2000}", "year:{\"1990\" TO \"2000\"}");
+        test_parse_query_to_ast_helper("year:[1990 TO 2000}", "year:[\"1990\" TO \"2000\"}");
+        test_parse_query_to_ast_helper("year:[* TO -10]", "year:{* TO \"-10\"]");
+        test_parse_query_to_ast_helper("year:{-10 TO *]", "year:{\"-10\" TO *}");
+        test_parse_query_to_ast_helper("year:[ 1  TO  2 ]", "year:[\"1\" TO \"2\"]");
+        test_parse_query_to_ast_helper("title:[a TO c]^2", "(title:[\"a\" TO \"c\"])^2");
+        test_parse_query_to_ast_helper(
+            "+year:[1 TO 2] -a",
+            "(+(year:[\"1\" TO \"2\"]) -(\"a\"))",
+        );
+        test_parse_query_to_ast_helper("(year:{1 TO 2})", "year:{\"1\" TO \"2\"}");
+        // not ranges.
+        test_parse_query_to_ast_helper("\"year:[1 TO 2]\"", "\"year:[1 TO 2]\"");
+        // malformed ranges are not parsed as a term on the field name.
+        test_is_parse_err("year:[1 2]");
+    }
+
+    #[test]
+    fn test_parse_query_to_ast_stops_at_max_clauses() {
+        let clause_counter = ClauseCounter::new(100);
+        assert!(parse_to_ast_with_counter(&clause_counter, "a (b OR c:[1 TO 2]) -\"d e\"").is_ok());
+        assert_eq!(clause_counter.num_clauses(), 4);
+        assert!(!clause_counter.exceeds_max_clauses());
+        // the clauses past the limit are not parsed.
+        let clause_counter = ClauseCounter::new(2);
+        let _ = parse_to_ast_with_counter(&clause_counter, "a b (c d) e f g");
+        assert_eq!(clause_counter.num_clauses(), 3);
+        assert!(clause_counter.exceeds_max_clauses());
+    }
+
+    #[test]
+    fn test_parse_query_to_ast_unicode_whitespaces() {
+        test_parse_query_to_ast_helper("a\u{3000}b", "(\"a\" \"b\")");
+        test_parse_query_to_ast_helper("a\u{A0}b", "(\"a\" \"b\")");
+        test_parse_query_to_ast_helper("a\u{200D}b", "(\"a\" \"b\")");
+        test_parse_query_to_ast_helper("abc:a\u{3000}+b", "(abc:\"a\" +(\"b\"))");
+    }
 }
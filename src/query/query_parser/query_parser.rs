@@ -3,16 +3,34 @@ use query::Query;
 use query::BooleanQuery;
 use super::logical_ast::*;
 use super::user_input_ast::*;
-use super::query_grammar::parse_to_ast;
+use super::query_grammar::{is_separator, parse_to_ast_with_counter, ClauseCounter};
 use query::Occur;
 use query::TermQuery;
 use schema::IndexRecordOption;
 use query::PhraseQuery;
-use schema::{FieldType, Term};
+use query::SynonymQuery;
+use query::BoostQuery;
+use query::RangeQuery;
+use query::FastFieldRangeQuery;
+use schema::{Cardinality, Facet, FacetParseError, FieldType, Term};
 use std::str::FromStr;
 use tokenizer::TokenizerManager;
 use std::num::ParseIntError;
+use std::ops::Range;
 use core::Index;
+use std::borrow::Cow;
+use std::collections::Bound;
+use std::collections::HashMap;
+use std::fmt;
+use std::result;
+use std::sync::Arc;
+use core::Searcher;
+use query::Weight;
+use unicode_normalization::UnicodeNormalization;
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use termdict::edit_distance;
+use Score;
 
 /// Possible error that may happen when parsing a query.
 #[derive(Debug, PartialEq, Eq)]
@@ -20,12 +38,28 @@ pub enum QueryParserError {
     /// Error in the query syntax
     SyntaxError,
     /// `FieldDoesNotExist(field_name: String)`
-    /// The query references a field that is not in the schema
+    /// The query references a field that is not in the schema,
+    /// nor an alias. See [`QueryParser::suggest_field_name`](
+    /// struct.QueryParser.html#method.suggest_field_name)
+    /// to find the field that was likely meant.
     FieldDoesNotExist(String),
+    /// `FieldNameConflict(name: String)`
+    /// An alias or a rename cannot be registered under this name,
+    /// as it is already the name of a field of the schema,
+    /// or of another alias or rename.
+    FieldNameConflict(String),
     /// The query contains a term for a `u64`-field, but the value
     /// is not a u64.
     ExpectedInt(ParseIntError),
-    /// It is forbidden queries that are only "excluding". (e.g. -title:pop)
+    /// The query contains a term for a facet field, but the value
+    /// is not a valid facet path (e.g. `category:books`).
+    ExpectedFacet(FacetParseError),
+    /// `InvalidRangeBound(field_name: String, bound: String)`
+    /// A bound of a range (e.g. `year:[1990 TO 20x0]`) is not
+    /// a valid value of the type of the field.
+    InvalidRangeBound(String, String),
+    /// It is forbidden queries that are only "excluding",
+    /// e.g. `-title:pop` or `NOT title:pop -title:rock`.
     AllButQueryForbidden,
     /// If no default field is declared, running a query without any
     /// field specified is forbbidden.
@@ -36,6 +70,49 @@ pub enum QueryParserError {
     /// The tokenizer for the given field is unknown
     /// The two argument strings are the name of the field, the name of the tokenizer
     UnknownTokenizer(String, String),
+    /// The query is not consistent with the schema.
+    /// (e.g. a term query on a field that is not indexed)
+    InvalidQuery(QueryValidationError),
+    /// `FieldDoesNotHavePositions(field_name: String)`
+    /// A phrase is searched in a field indexed without positions.
+    FieldDoesNotHavePositions(String),
+    /// `UnterminatedQuote(offset: usize)`
+    /// A phrase of the query is not terminated by a double quote.
+    /// `offset` is the byte offset of its opening quote in the query
+    /// (after its unicode normalization, if enabled).
+    UnterminatedQuote(usize),
+    /// `UnbalancedParenthesis(offset: usize)`
+    /// A parenthesis of the query is never closed, or closes
+    /// a group that was never opened. `offset` is the byte offset
+    /// of this parenthesis in the query (after its unicode
+    /// normalization, if enabled).
+    UnbalancedParenthesis(usize),
+    /// `EmptyGroup(offset: usize)`
+    /// The query contains an empty group, e.g. `a ()`.
+    /// `offset` is the byte offset of its opening parenthesis in the query
+    /// (after its unicode normalization, if enabled).
+    EmptyGroup(usize),
+    /// `TooManyClauses(max_clauses: usize)`
+    /// The query has more than `max_clauses` term or phrase clauses,
+    /// counted after their expansion to several fields.
+    TooManyClauses(usize),
+    /// `TooDeep(max_nesting_depth: usize)`
+    /// The parentheses and `+`/`-` operators of the query
+    /// are nested more than `max_nesting_depth` levels deep.
+    TooDeep(usize),
+    /// A slop (e.g. `"a b"~2`) or a boost (e.g. `title:rust^1.5`)
+    /// modifier of the query is not valid.
+    InvalidModifier {
+        /// Byte range of the modifier, including its `~` or `^` sign,
+        /// in the query (after its unicode normalization, if enabled).
+        span: Range<usize>,
+        /// What is wrong with the modifier.
+        what: String,
+    },
+    /// `InvalidRegex(message: String)`
+    /// The pattern of a [`RegexQuery`](./struct.RegexQuery.html)
+    /// is not a valid regular expression.
+    InvalidRegex(String),
 }
 
 impl From<ParseIntError> for QueryParserError {
@@ -44,6 +121,63 @@ impl From<ParseIntError> for QueryParserError {
     }
 }
 
+impl From<FacetParseError> for QueryParserError {
+    fn from(err: FacetParseError) -> QueryParserError {
+        QueryParserError::ExpectedFacet(err)
+    }
+}
+
+impl From<QueryValidationError> for QueryParserError {
+    fn from(err: QueryValidationError) -> QueryParserError {
+        QueryParserError::InvalidQuery(err)
+    }
+}
+
+/// Defines how the `QueryParser` handles the terms that look like
+/// numbers, when they are not prefixed by a field name.
+///
+/// Terms that are prefixed by a field name (e.g. `year:2020`)
+/// are not affected: they must match the type of their field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnfieldedNumericBehavior {
+    /// The term is only searched in the text default fields.
+    TextOnly,
+    /// The term is only searched in the numeric default fields,
+    /// if it is a valid number for at least one of them.
+    /// Otherwise, it is searched in the text default fields.
+    NumericOnly,
+    /// The term is searched in the text default fields, as well as in
+    /// the numeric default fields for which it is a valid number.
+    Both,
+}
+
+/// Defines how the `QueryParser` searches a text that the tokenizer of
+/// the field splits into several tokens, like the quoted text
+/// `"barack obama"`, `"wi-fi"`, or a CJK word split into bigrams.
+///
+/// A `PhraseQuery` is precise, but requires the field to be
+/// indexed with positions. The conjunction of the terms works on any field,
+/// but also matches the documents where the terms are not adjacent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoPhrase {
+    /// The text is searched as a phrase if the field is indexed with positions,
+    /// and as the conjunction of its terms otherwise.
+    ///
+    /// A quoted text followed by a slop, like `"barack obama"~2`, is
+    /// explicitly a phrase: it is rejected by the validation, with a
+    /// `QueryParserError::FieldDoesNotHavePositions` error,
+    /// if the field is not indexed with positions.
+    WhenPositionsAvailable,
+    /// The text is always searched as a phrase.
+    ///
+    /// The query is rejected by the validation, with a
+    /// `QueryParserError::FieldDoesNotHavePositions` error,
+    /// if the field is not indexed with positions.
+    Always,
+    /// The text is always searched as the conjunction of its terms.
+    Never,
+}
+
 /// Tantivy's Query parser
 ///
 /// The language covered by the current parser is extremely simple.
@@ -55,6 +189,11 @@ impl From<ParseIntError> for QueryParserError {
 ///   e.g. If `body` and `title` are default fields, our example terms are
 ///   `["title:barack", "body:barack", "title:obama", "body:obama"]`.
 ///   By default, all tokenized and indexed fields are default fields.
+///   The matches of some fields can be ranked above the others with
+///   `set_field_boost`.
+///
+///   Numeric default fields are only searched for the terms that are
+///   valid numbers (see `set_unfielded_numeric_behavior`).
 ///
 ///   Multiple terms are handled as an `OR` : any document containing at least
 ///   one of the term will go through the scoring.
@@ -63,22 +202,354 @@ impl From<ParseIntError> for QueryParserError {
 ///   by relevance : The user typically just scans through the first few
 ///   documents in order of decreasing relevance and will stop when the documents
 ///   are not relevant anymore.
-///   Making it possible to make this behavior customizable is tracked in
-///   [issue #27](https://github.com/fulmicoton/tantivy/issues/27).
+///   The terms can be handled as an `AND` instead with `set_conjunction_by_default`.
+///
+/// * negative terms: By prepending a term by a `-`, or by the `NOT` operator
+///   followed by a space, a term can be excluded from the search.
+///   This is useful for disambiguating a query.
+///   e.g. `apple -fruit` or `apple NOT fruit`
 ///
-/// * negative terms: By prepending a term by a `-`, a term can be excluded
-///   from the search. This is useful for disambiguating a query.
-///   e.g. `apple -fruit`
+///   A query made of negative terms only (e.g. `-fruit`) is rejected
+///   with a `QueryParserError::AllButQueryForbidden`. A quoted dash
+///   (e.g. `"-fruit"`) or a `NOT` not followed by a term is searched as is.
 ///
 /// * must terms: By prepending a term by a `+`, a term can be made required for the search.
 ///
+/// * boolean operators: the clauses joined by `AND` are all required,
+///   while at least one of the clauses joined by `OR` is required,
+///   whatever `set_conjunction_by_default`. `AND` takes precedence
+///   over `OR` (e.g. `a OR b AND c` is `a OR (b AND c)`), and both take
+///   precedence over the clauses only separated by spaces.
+///   Like `NOT`, they must be uppercase and followed by a clause
+///   to be operators, and are searched as words otherwise.
+///
+/// * groups: the clauses enclosed in parentheses form a sub-query,
+///   e.g. `(rust OR golang) AND (async OR concurrent)`. Groups can be
+///   nested up to `set_max_nesting_depth` levels deep.
+///   Unbalanced parentheses are rejected with a
+///   `QueryParserError::UnbalancedParenthesis`, and empty groups (e.g. `()`)
+///   with a `QueryParserError::EmptyGroup`.
+///
+/// * phrases: a quoted text (e.g. `"barack obama"`) is run through the
+///   tokenizer of the field, and searched as a phrase of the resulting terms,
+///   at the positions given by the tokenizer. In a field indexed without
+///   positions, it is searched as the conjunction of the terms instead
+///   (see `set_auto_phrase`). A phrase that cannot be searched as such
+///   is rejected with a `QueryParserError::FieldDoesNotHavePositions`,
+///   and a phrase without its closing quote with a
+///   `QueryParserError::UnterminatedQuote`.
+///
+/// * slop: a phrase followed by `~` and an integer (e.g. `"barack obama"~2`)
+///   also matches the documents where its terms are up to that many
+///   positions away from their place in the phrase.
+///   The slop is ignored if the text is a single token. Unless the setting
+///   is `AutoPhrase::Never`, a phrase with a slop is not degraded to the
+///   conjunction of its terms.
+///
+/// * boost: a term, a phrase or a parenthesized group followed by `^` and a
+///   number (e.g. `obama^1.5`, `(barack obama)^2`) has its score multiplied
+///   by the number. A phrase with a slop takes the boost after the slop
+///   (e.g. `"barack obama"~2^0.5`), and the boosts of nested groups
+///   multiply together.
+///
+///   Invalid slops and boosts are rejected with a `QueryParserError::InvalidModifier`.
+///
+/// * facets: the path of a facet, quoted, matches the documents associated
+///   to the facet or to one of its descendants.
+///   e.g. `category:"/category/fiction"`
+///
+/// * ranges: a field name followed by a range (e.g. `year:[1990 TO 2000}`)
+///   matches the documents with a value of the field within the range.
+///   `[` and `]` include the bound, `{` and `}` exclude it, and `*` leaves
+///   the range unbounded on its side (e.g. `price:{10 TO *]`).
+///
+///   The values of `u64` and `i64` fields are compared as numbers, and a
+///   bound that is not a number of the type of the field is rejected with a
+///   `QueryParserError::InvalidRangeBound`. The terms of text fields are
+///   compared lexicographically, with bounds that are not tokenized.
+///   A range on a `u64` or `i64` field that is not indexed but is a single
+///   valued fast field is run on its fast field
+///   (see [`FastFieldRangeQuery`](../struct.FastFieldRangeQuery.html)).
+///
+/// Clauses are separated by any unicode whitespace (including full-width
+/// spaces) or zero-width character.
+///
+/// * field names: a field name may be
+///   - the name of a field of the schema,
+///   - a name registered with `add_field_rename`,
+///   - an alias registered with `add_field_alias`, that searches several fields at once,
+///   - a dotted name (e.g. `author.name`), which stands for the field with
+///     the dots replaced by underscores (e.g. `author_name`).
+///
+///   These are tried in this order.
+///
 pub struct QueryParser {
     schema: Schema,
     default_fields: Vec<Field>,
     conjunction_by_default: bool,
+    validation_enabled: bool,
+    normalization_enabled: bool,
+    unfielded_numeric_behavior: UnfieldedNumericBehavior,
+    auto_phrase: AutoPhrase,
     tokenizer_manager: TokenizerManager,
+    field_renames: HashMap<String, Field>,
+    field_aliases: HashMap<String, Vec<Field>>,
+    field_boosts: HashMap<Field, Score>,
+    synonyms: HashMap<String, Vec<String>>,
+    required_filter: Option<Arc<Query>>,
+    max_clauses: usize,
+    max_nesting_depth: usize,
+}
+
+/// Query shared by all of the queries built by a `QueryParser`.
+#[derive(Clone, Debug)]
+struct SharedQuery(Arc<Query>);
+
+impl Query for SharedQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> ::Result<Box<Weight>> {
+        self.0.weight(searcher, scoring_enabled)
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        self.0.validate(searcher)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_tree(schema, depth, f)
+    }
+}
+
+/// Default maximum number of term or phrase clauses of a query.
+const DEFAULT_MAX_CLAUSES: usize = 1_024;
+
+/// Default maximum nesting depth of a query.
+const DEFAULT_MAX_NESTING_DEPTH: usize = 32;
+
+/// Returns true iff the parentheses and the `+`/`-`/`NOT` operators
+/// of the query are nested more than `max_nesting_depth` levels deep.
+///
+/// The query is scanned without being parsed, so that deeply
+/// nested queries are rejected before the (recursive) parser runs.
+fn exceeds_nesting_depth(query: &str, max_nesting_depth: usize) -> bool {
+    // depths to restore when closing the currently open parentheses.
+    let mut open_depths: Vec<usize> = Vec::new();
+    // depth of the clauses at the current level of parentheses.
+    let mut clause_depth = 0;
+    // depth, including the operators preceding the current clause.
+    let mut depth = 0;
+    let mut in_phrase = false;
+    // start of the word being scanned, if any.
+    let mut word_start: Option<usize> = None;
+    // true iff the separators being scanned follow a `NOT` operator.
+    let mut after_not = false;
+    for (offset, c) in query.char_indices() {
+        if in_phrase {
+            in_phrase = c != '"';
+            continue;
+        }
+        let word_start_opt = word_start.take();
+        if !is_separator(c) {
+            after_not = false;
+        }
+        match c {
+            '"' => {
+                in_phrase = true;
+            }
+            '+' | '-' => {
+                depth += 1;
+            }
+            '(' => {
+                open_depths.push(clause_depth);
+                clause_depth = depth + 1;
+                depth = clause_depth;
+            }
+            ')' => {
+                clause_depth = open_depths.pop().unwrap_or(0);
+                depth = clause_depth;
+            }
+            c if is_separator(c) => {
+                // the clause following a `NOT` operator is nested into it.
+                if word_start_opt.map(|start| &query[start..offset]) == Some("NOT") {
+                    depth += 1;
+                    after_not = true;
+                } else if !after_not {
+                    depth = clause_depth;
+                }
+            }
+            _ => {
+                word_start = Some(word_start_opt.unwrap_or(offset));
+            }
+        }
+        if depth > max_nesting_depth {
+            return true;
+        }
+    }
+    false
+}
+
+/// Checks that the parentheses of the query, outside of its phrases,
+/// are balanced and do not enclose empty groups.
+fn check_parentheses(query: &str) -> Result<(), QueryParserError> {
+    // offsets of the parentheses that are currently open.
+    let mut open_offsets: Vec<usize> = Vec::new();
+    // true iff only separators follow the last opening parenthesis.
+    let mut in_empty_group = false;
+    let mut in_phrase = false;
+    for (offset, c) in query.char_indices() {
+        if in_phrase {
+            in_phrase = c != '"';
+            continue;
+        }
+        match c {
+            '(' => {
+                open_offsets.push(offset);
+                in_empty_group = true;
+            }
+            ')' => {
+                let open_offset = open_offsets
+                    .pop()
+                    .ok_or(QueryParserError::UnbalancedParenthesis(offset))?;
+                if in_empty_group {
+                    return Err(QueryParserError::EmptyGroup(open_offset));
+                }
+            }
+            c if is_separator(c) => {}
+            c => {
+                in_phrase = c == '"';
+                in_empty_group = false;
+            }
+        }
+    }
+    match open_offsets.pop() {
+        Some(open_offset) => Err(QueryParserError::UnbalancedParenthesis(open_offset)),
+        None => Ok(()),
+    }
+}
+
+/// Returns the byte offset of the opening quote of the
+/// last phrase of the query, if it is not terminated.
+fn unterminated_quote(query: &str) -> Option<usize> {
+    let mut opening_quote: Option<usize> = None;
+    for (offset, c) in query.char_indices() {
+        if c == '"' {
+            opening_quote = match opening_quote {
+                Some(_) => None,
+                None => Some(offset),
+            };
+        }
+    }
+    opening_quote
+}
+
+/// The kind of element that the next char of the query
+/// directly follows, in `check_modifiers`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preceding {
+    Nothing,
+    Word,
+    Phrase,
+    Group,
+}
+
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Returns true iff `value` is a valid boost, e.g. `2` or `0.5`.
+fn is_valid_boost(value: &str) -> bool {
+    let mut parts = value.splitn(2, '.');
+    let integer_part = parts.next().unwrap_or("");
+    is_digits(integer_part) && parts.next().map(is_digits).unwrap_or(true)
+        && value.parse::<f32>().map(f32::is_finite).unwrap_or(false)
+}
+
+/// Checks the slop (e.g. `"a b"~2`) and boost (e.g. `title:rust^1.5`)
+/// modifiers of the query.
+///
+/// A quoted phrase may be followed by a slop, and any term, phrase or
+/// group by a boost, in this order. The query is scanned without being
+/// parsed, so that the invalid modifiers are reported with their span,
+/// rather than as syntax errors.
+fn check_modifiers(query: &str) -> Result<(), QueryParserError> {
+    let mut in_phrase = false;
+    let mut preceding = Preceding::Nothing;
+    let (mut has_slop, mut has_boost) = (false, false);
+    let mut char_indices = query.char_indices().peekable();
+    while let Some((start, c)) = char_indices.next() {
+        if in_phrase {
+            if c == '"' {
+                in_phrase = false;
+                preceding = Preceding::Phrase;
+                has_slop = false;
+                has_boost = false;
+            }
+            continue;
+        }
+        if c != '~' && c != '^' {
+            preceding = match c {
+                '"' => {
+                    in_phrase = true;
+                    Preceding::Nothing
+                }
+                ')' => Preceding::Group,
+                '(' | '+' | '-' | ':' => Preceding::Nothing,
+                c if is_separator(c) => Preceding::Nothing,
+                _ => Preceding::Word,
+            };
+            has_slop = false;
+            has_boost = false;
+            continue;
+        }
+        // the modifier extends up to the next separator, sign, quote or parenthesis.
+        let value_start = start + c.len_utf8();
+        let end = query[value_start..]
+            .find(|c: char| is_separator(c) || "~^\"()".contains(c))
+            .map(|offset| value_start + offset)
+            .unwrap_or(query.len());
+        while char_indices.peek().map(|&(i, _)| i < end).unwrap_or(false) {
+            char_indices.next();
+        }
+        let value = &query[value_start..end];
+        let error = |what: &str| {
+            Err(QueryParserError::InvalidModifier {
+                span: start..end,
+                what: what.to_string(),
+            })
+        };
+        if c == '~' {
+            if preceding != Preceding::Phrase {
+                return error("slop: only allowed after a quoted phrase");
+            }
+            if has_boost {
+                return error("slop: must precede the boost");
+            }
+            if has_slop {
+                return error("slop: duplicated");
+            }
+            if !is_digits(value) || value.parse::<u32>().is_err() {
+                return error("slop: expected a non-negative integer");
+            }
+            has_slop = true;
+        } else {
+            if preceding == Preceding::Nothing {
+                return error("boost: must follow a term, a phrase or a group");
+            }
+            if has_boost {
+                return error("boost: duplicated");
+            }
+            if !is_valid_boost(value) {
+                return error("boost: expected a non-negative number");
+            }
+            has_boost = true;
+        }
+    }
+    Ok(())
 }
 
+/// Maximum edit distance between an unknown field name
+/// and the suggested field name.
+const MAX_SUGGESTION_EDIT_DISTANCE: usize = 2;
+
 impl QueryParser {
     /// Creates a `QueryParser`, given
     /// * schema - index Schema
@@ -94,6 +565,17 @@ impl QueryParser {
             default_fields,
             tokenizer_manager,
             conjunction_by_default: false,
+            validation_enabled: true,
+            normalization_enabled: true,
+            unfielded_numeric_behavior: UnfieldedNumericBehavior::Both,
+            auto_phrase: AutoPhrase::WhenPositionsAvailable,
+            field_renames: HashMap::new(),
+            field_aliases: HashMap::new(),
+            field_boosts: HashMap::new(),
+            synonyms: HashMap::new(),
+            required_filter: None,
+            max_clauses: DEFAULT_MAX_CLAUSES,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
         }
     }
 
@@ -110,10 +592,189 @@ impl QueryParser {
     /// By default, the query `happy tax payer` is equivalent to the query
     /// `happy OR tax OR payer`. After calling `.set_conjunction_by_default()`
     /// `happy tax payer` will be interpreted by the parser as `happy AND tax AND payer`.
+    ///
+    /// The terms prefixed by `+` or `-` keep their operator: `happy +tax -payer`
+    /// requires `happy` and `tax`, and excludes `payer`.
     pub fn set_conjunction_by_default(&mut self) {
         self.conjunction_by_default = true;
     }
 
+    /// Sets the default way to compose queries back to a disjunction.
+    ///
+    /// See [`.set_conjunction_by_default()`](#method.set_conjunction_by_default).
+    pub fn set_disjunction_by_default(&mut self) {
+        self.conjunction_by_default = false;
+    }
+
+    /// Enables or disables the validation of the parsed queries
+    /// against the schema.
+    ///
+    /// Validation is enabled by default, and makes `parse_query` return
+    /// a `QueryParserError::FieldDoesNotHavePositions` error when a phrase
+    /// is searched in a field that was indexed without positions.
+    pub fn set_validation(&mut self, validation_enabled: bool) {
+        self.validation_enabled = validation_enabled;
+    }
+
+    /// Enables or disables the unicode NFC normalization of the queries.
+    ///
+    /// Normalization is enabled by default. It makes composed and
+    /// decomposed forms of the same characters (e.g. `é` and `e` followed
+    /// by a combining acute accent) produce the same terms.
+    /// It is only useful if the tokenizers of the searched fields
+    /// normalize the indexed text as well, for instance
+    /// by using the [`NfcNormalizer`](../tokenizer/struct.NfcNormalizer.html) filter.
+    pub fn set_normalization(&mut self, normalization_enabled: bool) {
+        self.normalization_enabled = normalization_enabled;
+    }
+
+    /// Sets the maximum number of term or phrase clauses of a query.
+    ///
+    /// The literals and the ranges of the query are counted while it is
+    /// parsed, each of them as one clause, and the parsing stops as soon as
+    /// the limit is exceeded. The clauses are then counted again after their
+    /// expansion to several fields, so that a term searched in three default
+    /// fields counts as three clauses. Queries with more clauses are rejected
+    /// with a `QueryParserError::TooManyClauses` error.
+    ///
+    /// Defaults to `1024`.
+    pub fn set_max_clauses(&mut self, max_clauses: usize) {
+        self.max_clauses = max_clauses;
+    }
+
+    /// Sets the maximum nesting depth of the parentheses
+    /// and the `+`/`-` operators of a query.
+    ///
+    /// Deeper queries are rejected with a `QueryParserError::TooDeep`
+    /// error, before being parsed.
+    ///
+    /// Defaults to `32`.
+    pub fn set_max_nesting_depth(&mut self, max_nesting_depth: usize) {
+        self.max_nesting_depth = max_nesting_depth;
+    }
+
+    /// Defines how the terms that are not prefixed by a field name
+    /// are searched in the numeric default fields.
+    ///
+    /// Defaults to `UnfieldedNumericBehavior::Both`.
+    pub fn set_unfielded_numeric_behavior(&mut self, behavior: UnfieldedNumericBehavior) {
+        self.unfielded_numeric_behavior = behavior;
+    }
+
+    /// Defines whether a text split into several tokens
+    /// is searched as a phrase or as the conjunction of its terms.
+    ///
+    /// Defaults to `AutoPhrase::WhenPositionsAvailable`.
+    pub fn set_auto_phrase(&mut self, auto_phrase: AutoPhrase) {
+        self.auto_phrase = auto_phrase;
+    }
+
+    /// Makes `name` stand for the given field in the queries.
+    ///
+    /// This is typically useful when the users know the fields under
+    /// other names than the ones of the schema, e.g. `author.name`
+    /// for `author_name`.
+    ///
+    /// Returns a `FieldNameConflict` error if `name` is already the name
+    /// of a field, of an alias or of a rename.
+    pub fn add_field_rename(&mut self, name: &str, field: Field) -> Result<(), QueryParserError> {
+        self.check_field_name_available(name)?;
+        self.field_renames.insert(name.to_string(), field);
+        Ok(())
+    }
+
+    /// Makes `name` an alias for several fields.
+    ///
+    /// A term prefixed by the alias (e.g. `author:smith`) is searched in all of
+    /// the fields, each with its own tokenizer, any of them being
+    /// allowed to match.
+    ///
+    /// Returns a `FieldNameConflict` error if `name` is already the name
+    /// of a field, of an alias or of a rename.
+    pub fn add_field_alias(
+        &mut self,
+        name: &str,
+        fields: Vec<Field>,
+    ) -> Result<(), QueryParserError> {
+        self.check_field_name_available(name)?;
+        self.field_aliases.insert(name.to_string(), fields);
+        Ok(())
+    }
+
+    /// Multiplies the score of the clauses searched in a given field
+    /// by `boost`, e.g. to rank the matches in the `title` above the ones
+    /// in the `body` when a term is searched in all of the default fields.
+    ///
+    /// The boost applies to the terms and phrases searched in the field,
+    /// whether it is a default field or not, and multiplies
+    /// the boosts of the query itself (e.g. `title:rust^2`).
+    pub fn set_field_boost(&mut self, field: Field, boost: Score) {
+        self.field_boosts.insert(field, boost);
+    }
+
+    /// Boosts the AST of a literal searched in the given field,
+    /// if a boost was set for the field.
+    fn with_field_boost(&self, field: Field, ast: LogicalAST) -> LogicalAST {
+        match self.field_boosts.get(&field) {
+            Some(&boost) => ast.boosted(boost),
+            None => ast,
+        }
+    }
+
+    /// Declares a group of words as synonyms.
+    ///
+    /// A query term matching one of the words is searched as a
+    /// [`SynonymQuery`](./struct.SynonymQuery.html) over all of the words of
+    /// the group, scored as one single term.
+    ///
+    /// The synonyms are looked up using the tokens of the query, in
+    /// any text field. They should therefore be given as they are output
+    /// by the tokenizers of the fields, e.g. lowercased. Synonyms of phrases,
+    /// as well as synonyms made of several words, are not supported.
+    ///
+    /// A word may belong to several groups, in which case all of the words
+    /// of these groups are its synonyms.
+    pub fn add_synonyms(&mut self, words: &[&str]) {
+        for &word in words {
+            let synonyms = self.synonyms
+                .entry(word.to_string())
+                .or_insert_with(Vec::new);
+            for &synonym in words {
+                if synonym != word && !synonyms.iter().any(|known| known == synonym) {
+                    synonyms.push(synonym.to_string());
+                }
+            }
+        }
+    }
+
+    /// Sets a filter that all of the parsed queries are required to match,
+    /// e.g. a `TermQuery` on the tenant of the user in a multi-tenant index.
+    ///
+    /// The parsed queries are then `BooleanQuery`s with two `Must` clauses:
+    /// the filter, boosted by `0` so that it does not contribute to
+    /// the scores, and the query itself. The filter cannot be escaped
+    /// by the query string, as it is not part of it.
+    ///
+    /// See also [`ScopedSearcher`](../struct.ScopedSearcher.html), that
+    /// evaluates the filter only once for many queries.
+    pub fn set_required_filter(&mut self, filter: Box<Query>) {
+        self.required_filter = Some(Arc::from(filter));
+    }
+
+    /// Removes the filter set by `set_required_filter`.
+    pub fn clear_required_filter(&mut self) {
+        self.required_filter = None;
+    }
+
+    fn check_field_name_available(&self, name: &str) -> Result<(), QueryParserError> {
+        if self.schema.get_field(name).is_some() || self.field_renames.contains_key(name)
+            || self.field_aliases.contains_key(name)
+        {
+            return Err(QueryParserError::FieldNameConflict(name.to_string()));
+        }
+        Ok(())
+    }
+
     /// Parse a query
     ///
     /// Note that `parse_query` returns an error if the input
@@ -126,37 +787,220 @@ impl QueryParser {
     /// in [Issue 5](https://github.com/fulmicoton/tantivy/issues/5)
     pub fn parse_query(&self, query: &str) -> Result<Box<Query>, QueryParserError> {
         let logical_ast = self.parse_query_to_logical_ast(query)?;
-        Ok(convert_to_query(logical_ast))
+        if self.validation_enabled {
+            self.validate_logical_ast(&logical_ast)?;
+        }
+        let query = self.convert_to_query(logical_ast);
+        if let Some(ref required_filter) = self.required_filter {
+            let filter_query: Box<Query> =
+                box BoostQuery::new(box SharedQuery(Arc::clone(required_filter)), 0.0);
+            return Ok(box BooleanQuery::from(vec![
+                (Occur::Must, filter_query),
+                (Occur::Must, query),
+            ]));
+        }
+        Ok(query)
+    }
+
+    /// Applies to the parsed query the checks of `Query::validate`.
+    fn validate_logical_ast(&self, logical_ast: &LogicalAST) -> Result<(), QueryParserError> {
+        match *logical_ast {
+            LogicalAST::Clause(ref clause) => {
+                for &(_, ref sub_ast) in clause {
+                    self.validate_logical_ast(sub_ast)?;
+                }
+                Ok(())
+            }
+            LogicalAST::Leaf(ref logical_literal) => match **logical_literal {
+                LogicalLiteral::Term(ref term) => {
+                    validate_indexed_field(&self.schema, term.field(), IndexRecordOption::Basic)
+                        .map_err(QueryParserError::from)
+                }
+                LogicalLiteral::Synonyms(ref terms) => {
+                    validate_indexed_field(&self.schema, terms[0].field(), IndexRecordOption::Basic)
+                        .map_err(QueryParserError::from)
+                }
+                LogicalLiteral::Range(field, _, _) => {
+                    let field_type = self.schema.get_field_entry(field).field_type();
+                    if is_fast_range_field(field_type) {
+                        return Ok(());
+                    }
+                    validate_indexed_field(&self.schema, field, IndexRecordOption::Basic)
+                        .map_err(QueryParserError::from)
+                }
+                LogicalLiteral::Phrase(ref terms, _) => {
+                    for &(_, ref term) in terms {
+                        let field_entry = self.schema.get_field_entry(term.field());
+                        let has_positions = field_entry
+                            .field_type()
+                            .get_index_record_option()
+                            .map(|index_record_option| index_record_option.has_positions())
+                            .unwrap_or(false);
+                        if !has_positions {
+                            let field_name = field_entry.name().to_string();
+                            return Err(QueryParserError::FieldDoesNotHavePositions(field_name));
+                        }
+                    }
+                    Ok(())
+                }
+            },
+            LogicalAST::Boost(ref sub_ast, _) => self.validate_logical_ast(sub_ast),
+        }
+    }
+
+    /// Returns the `IndexRecordOption` used for the `TermQuery`
+    /// on a given field. Term frequencies are requested, if they are
+    /// available.
+    fn term_index_record_option(&self, field: Field) -> IndexRecordOption {
+        self.schema
+            .get_field_entry(field)
+            .field_type()
+            .get_index_record_option()
+            .map(|indexed| indexed.intersection(IndexRecordOption::WithFreqs))
+            .unwrap_or(IndexRecordOption::WithFreqs)
+    }
+
+    fn convert_literal_to_query(&self, logical_literal: LogicalLiteral) -> Box<Query> {
+        match logical_literal {
+            LogicalLiteral::Term(term) => {
+                let index_record_option = self.term_index_record_option(term.field());
+                box TermQuery::new(term, index_record_option)
+            }
+            LogicalLiteral::Phrase(terms, slop) => {
+                let mut phrase_query = PhraseQuery::new_with_offset(terms);
+                phrase_query.set_slop(slop);
+                box phrase_query
+            }
+            LogicalLiteral::Synonyms(terms) => {
+                let field = terms[0].field();
+                box SynonymQuery::new(field, terms)
+            }
+            LogicalLiteral::Range(field, lower, upper) => {
+                let field_type = self.schema.get_field_entry(field).field_type();
+                if !is_fast_range_field(field_type) {
+                    box RangeQuery::new_term_bounds(field_type, field, lower, upper)
+                } else if let FieldType::I64(_) = *field_type {
+                    let range = (
+                        map_bound(lower, Term::get_i64),
+                        map_bound(upper, Term::get_i64),
+                    );
+                    box FastFieldRangeQuery::new_i64(field, range)
+                } else {
+                    let range = (
+                        map_bound(lower, Term::get_u64),
+                        map_bound(upper, Term::get_u64),
+                    );
+                    box FastFieldRangeQuery::new_u64(field, range)
+                }
+            }
+        }
+    }
+
+    fn convert_to_query(&self, logical_ast: LogicalAST) -> Box<Query> {
+        match logical_ast {
+            LogicalAST::Clause(clause) => {
+                let occur_subqueries = clause
+                    .into_iter()
+                    .map(|(occur, subquery)| (occur, self.convert_to_query(subquery)))
+                    .collect::<Vec<_>>();
+                box BooleanQuery::from(occur_subqueries)
+            }
+            LogicalAST::Leaf(logical_literal) => self.convert_literal_to_query(*logical_literal),
+            LogicalAST::Boost(logical_ast, boost) => {
+                box BoostQuery::new(self.convert_to_query(*logical_ast), boost)
+            }
+        }
     }
 
     /// Parse the user query into an AST.
     fn parse_query_to_logical_ast(&self, query: &str) -> Result<LogicalAST, QueryParserError> {
-        let (user_input_ast, _remaining) =
-            parse_to_ast(query).map_err(|_| QueryParserError::SyntaxError)?;
+        let query: Cow<str> = if self.normalization_enabled && !query.is_ascii() {
+            Cow::Owned(query.nfc().collect())
+        } else {
+            Cow::Borrowed(query)
+        };
+        if let Some(offset) = unterminated_quote(&query) {
+            return Err(QueryParserError::UnterminatedQuote(offset));
+        }
+        check_parentheses(&query)?;
+        if exceeds_nesting_depth(&query, self.max_nesting_depth) {
+            return Err(QueryParserError::TooDeep(self.max_nesting_depth));
+        }
+        check_modifiers(&query)?;
+        let clause_counter = ClauseCounter::new(self.max_clauses);
+        let parse_result = parse_to_ast_with_counter(&clause_counter, &query[..]);
+        if clause_counter.exceeds_max_clauses() {
+            return Err(QueryParserError::TooManyClauses(self.max_clauses));
+        }
+        // the grammar requires the whole query to be parsed.
+        let (user_input_ast, _) = parse_result.map_err(|_| QueryParserError::SyntaxError)?;
         self.compute_logical_ast(user_input_ast)
     }
 
-    fn resolve_field_name(&self, field_name: &str) -> Result<Field, QueryParserError> {
-        self.schema
-            .get_field(field_name)
-            .ok_or_else(|| QueryParserError::FieldDoesNotExist(String::from(field_name)))
+    /// Returns the fields a field name of the query stands for.
+    fn resolve_field_name(&self, field_name: &str) -> Result<Vec<Field>, QueryParserError> {
+        if let Some(field) = self.schema.get_field(field_name) {
+            return Ok(vec![field]);
+        }
+        if let Some(&field) = self.field_renames.get(field_name) {
+            return Ok(vec![field]);
+        }
+        if let Some(fields) = self.field_aliases.get(field_name) {
+            return Ok(fields.clone());
+        }
+        if field_name.contains('.') {
+            if let Some(field) = self.schema.get_field(&field_name.replace('.', "_")) {
+                return Ok(vec![field]);
+            }
+        }
+        Err(QueryParserError::FieldDoesNotExist(field_name.to_string()))
+    }
+
+    /// Returns the field name, alias or rename that is the closest
+    /// to an unknown field name, if it is close enough to be a likely typo.
+    ///
+    /// This is typically used to report a
+    /// [`QueryParserError::FieldDoesNotExist`](enum.QueryParserError.html).
+    pub fn suggest_field_name(&self, field_name: &str) -> Option<String> {
+        let field_names = self.schema
+            .fields()
+            .iter()
+            .map(|field_entry| field_entry.name())
+            .chain(self.field_renames.keys().map(|name| name.as_str()))
+            .chain(self.field_aliases.keys().map(|name| name.as_str()));
+        field_names
+            .map(|name| (edit_distance(field_name, name), name))
+            .filter(|&(distance, name)| {
+                distance <= MAX_SUGGESTION_EDIT_DISTANCE && distance < name.chars().count()
+            })
+            .min()
+            .map(|(_, name)| name.to_string())
     }
 
     fn compute_logical_ast(
         &self,
         user_input_ast: UserInputAST,
     ) -> Result<LogicalAST, QueryParserError> {
-        let (occur, ast) = self.compute_logical_ast_with_occur(user_input_ast)?;
+        let mut num_clauses = 0;
+        let (occur, ast) = self.compute_logical_ast_with_occur(user_input_ast, &mut num_clauses)?;
         if occur == Occur::MustNot {
             return Err(QueryParserError::AllButQueryForbidden);
         }
+        if let LogicalAST::Clause(ref clauses) = ast {
+            let only_excluding = !clauses.is_empty()
+                && clauses.iter().all(|&(occur, _)| occur == Occur::MustNot);
+            if only_excluding {
+                return Err(QueryParserError::AllButQueryForbidden);
+            }
+        }
         Ok(ast)
     }
     fn compute_logical_ast_for_leaf(
         &self,
         field: Field,
         phrase: &str,
-    ) -> Result<Option<LogicalLiteral>, QueryParserError> {
+        phrase_required: bool,
+    ) -> Result<Option<LogicalAST>, QueryParserError> {
         let field_entry = self.schema.get_field_entry(field);
         let field_type = field_entry.field_type();
         if !field_type.is_indexed() {
@@ -167,12 +1011,12 @@ impl QueryParser {
             FieldType::I64(_) => {
                 let val: i64 = i64::from_str(phrase)?;
                 let term = Term::from_field_i64(field, val);
-                Ok(Some(LogicalLiteral::Term(term)))
+                Ok(Some(LogicalAST::from(LogicalLiteral::Term(term))))
             }
             FieldType::U64(_) => {
                 let val: u64 = u64::from_str(phrase)?;
                 let term = Term::from_field_u64(field, val);
-                Ok(Some(LogicalLiteral::Term(term)))
+                Ok(Some(LogicalAST::from(LogicalLiteral::Term(term))))
             }
             FieldType::Str(ref str_options) => {
                 if let Some(option) = str_options.get_indexing_options() {
@@ -184,20 +1028,21 @@ impl QueryParser {
                                 option.tokenizer().to_string(),
                             )
                         })?;
-                    let mut terms: Vec<Term> = Vec::new();
+                    // the positions of the terms are kept for the phrases,
+                    // as the tokenizer may skip or share positions.
+                    let mut terms: Vec<(usize, Term)> = Vec::new();
                     let mut token_stream = tokenizer.token_stream(phrase);
                     token_stream.process(&mut |token| {
                         let term = Term::from_field_text(field, &token.text);
-                        terms.push(term);
+                        terms.push((token.position, term));
                     });
                     if terms.is_empty() {
                         Ok(None)
                     } else if terms.len() == 1 {
-                        Ok(Some(LogicalLiteral::Term(
-                            terms.into_iter().next().unwrap(),
-                        )))
+                        let (_, term) = terms.into_iter().next().unwrap();
+                        Ok(Some(LogicalAST::from(self.expand_synonyms(field, term))))
                     } else {
-                        Ok(Some(LogicalLiteral::Phrase(terms)))
+                        Ok(Some(self.compute_logical_ast_for_tokens(field, terms, phrase_required)))
                     }
                 } else {
                     // This should have been seen earlier really.
@@ -207,10 +1052,209 @@ impl QueryParser {
                 }
             }
             FieldType::HierarchicalFacet => {
-                let term = Term::from_field_text(field, phrase);
-                Ok(Some(LogicalLiteral::Term(term)))
+                let facet = Facet::from_str(phrase)?;
+                let term = Term::from_facet(field, &facet);
+                Ok(Some(LogicalAST::from(LogicalLiteral::Term(term))))
+            }
+        }
+    }
+
+    /// Returns the AST of a text that was split into several terms:
+    /// a phrase, or the conjunction of the terms, depending on
+    /// the `AutoPhrase` setting.
+    ///
+    /// If `phrase_required` is true, the text was explicitly written
+    /// as a phrase and is searched as a phrase unless the setting
+    /// is `AutoPhrase::Never`.
+    fn compute_logical_ast_for_tokens(
+        &self,
+        field: Field,
+        terms: Vec<(usize, Term)>,
+        phrase_required: bool,
+    ) -> LogicalAST {
+        let is_phrase = match self.auto_phrase {
+            AutoPhrase::WhenPositionsAvailable if phrase_required => true,
+            AutoPhrase::WhenPositionsAvailable => self.schema
+                .get_field_entry(field)
+                .field_type()
+                .get_index_record_option()
+                .map(|index_record_option| index_record_option.has_positions())
+                .unwrap_or(false),
+            AutoPhrase::Always => true,
+            AutoPhrase::Never => false,
+        };
+        if is_phrase {
+            LogicalAST::from(LogicalLiteral::Phrase(terms, 0))
+        } else {
+            let conjunction = terms
+                .into_iter()
+                .map(|(_, term)| (Occur::Must, LogicalAST::from(self.expand_synonyms(field, term))))
+                .collect();
+            LogicalAST::Clause(conjunction)
+        }
+    }
+
+    /// Returns a `Synonyms` literal if synonyms were declared
+    /// for the term, and a `Term` literal otherwise.
+    fn expand_synonyms(&self, field: Field, term: Term) -> LogicalLiteral {
+        match self.synonyms.get(term.text()) {
+            Some(synonyms) => {
+                let mut terms = vec![term];
+                terms.extend(
+                    synonyms
+                        .iter()
+                        .map(|synonym| Term::from_field_text(field, synonym)),
+                );
+                LogicalLiteral::Synonyms(terms)
+            }
+            None => LogicalLiteral::Term(term),
+        }
+    }
+
+    /// Computes the literals associated to a term prefixed by a field name,
+    /// for all of the fields this name stands for.
+    fn compute_logical_asts_for_fields(
+        &self,
+        fields: &[Field],
+        phrase: &str,
+        phrase_required: bool,
+    ) -> Result<Vec<LogicalAST>, QueryParserError> {
+        let mut asts: Vec<LogicalAST> = Vec::new();
+        let mut expected_int_error_opt = None;
+        for &field in fields {
+            match self.compute_logical_ast_for_leaf(field, phrase, phrase_required) {
+                Ok(Some(ast)) => asts.push(self.with_field_boost(field, ast)),
+                Ok(None) => {}
+                // aliases may group text and numeric fields.
+                Err(QueryParserError::ExpectedInt(ref err)) if fields.len() > 1 => {
+                    expected_int_error_opt = Some(err.clone());
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if asts.is_empty() {
+            if let Some(err) = expected_int_error_opt {
+                return Err(QueryParserError::ExpectedInt(err));
+            }
+        }
+        Ok(asts)
+    }
+
+    /// Computes the literal of a range searched in a given field.
+    fn compute_logical_ast_for_range(
+        &self,
+        field: Field,
+        lower: &UserInputBound,
+        upper: &UserInputBound,
+    ) -> Result<LogicalAST, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let field_type = field_entry.field_type();
+        if !field_type.is_indexed() && !is_fast_range_field(field_type) {
+            let field_name = field_entry.name().to_string();
+            return Err(QueryParserError::FieldNotIndexed(field_name));
+        }
+        if let FieldType::HierarchicalFacet = *field_type {
+            return Err(QueryParserError::InvalidQuery(
+                QueryValidationError::IncompatibleFieldType(
+                    field_entry.name().to_string(),
+                    "u64, i64 or str".to_string(),
+                ),
+            ));
+        }
+        let bound_term = |bound: &UserInputBound| -> Result<Bound<Term>, QueryParserError> {
+            let (value, is_inclusive) = match *bound {
+                UserInputBound::Inclusive(ref value) => (value, true),
+                UserInputBound::Exclusive(ref value) => (value, false),
+                UserInputBound::Unbounded => return Ok(Bound::Unbounded),
+            };
+            let term_opt = match *field_type {
+                FieldType::I64(_) => i64::from_str(value)
+                    .ok()
+                    .map(|val| Term::from_field_i64(field, val)),
+                FieldType::U64(_) => u64::from_str(value)
+                    .ok()
+                    .map(|val| Term::from_field_u64(field, val)),
+                _ => Some(Term::from_field_text(field, value)),
+            };
+            let term = term_opt.ok_or_else(|| {
+                QueryParserError::InvalidRangeBound(field_entry.name().to_string(), value.clone())
+            })?;
+            if is_inclusive {
+                Ok(Bound::Included(term))
+            } else {
+                Ok(Bound::Excluded(term))
+            }
+        };
+        let literal = LogicalLiteral::Range(field, bound_term(lower)?, bound_term(upper)?);
+        Ok(LogicalAST::from(literal))
+    }
+
+    /// Computes the literals associated to a range,
+    /// for all of the fields its field name stands for.
+    fn compute_logical_asts_for_range(
+        &self,
+        fields: &[Field],
+        lower: &UserInputBound,
+        upper: &UserInputBound,
+    ) -> Result<Vec<LogicalAST>, QueryParserError> {
+        let mut asts: Vec<LogicalAST> = Vec::new();
+        let mut invalid_bound_error_opt = None;
+        for &field in fields {
+            match self.compute_logical_ast_for_range(field, lower, upper) {
+                Ok(ast) => asts.push(self.with_field_boost(field, ast)),
+                // aliases may group text and numeric fields.
+                Err(QueryParserError::InvalidRangeBound(ref field_name, ref bound))
+                    if fields.len() > 1 =>
+                {
+                    invalid_bound_error_opt = Some((field_name.clone(), bound.clone()));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if asts.is_empty() {
+            if let Some((field_name, bound)) = invalid_bound_error_opt {
+                return Err(QueryParserError::InvalidRangeBound(field_name, bound));
+            }
+        }
+        Ok(asts)
+    }
+
+    /// Computes the literals associated to a term that was not
+    /// prefixed by a field name, for all of the default fields.
+    ///
+    /// Numeric default fields are only searched if the term parses as a
+    /// number of the proper type, as configured by the
+    /// `UnfieldedNumericBehavior`. Contrary to fielded terms, a term
+    /// that is not a valid number never results in an error.
+    fn compute_logical_asts_for_default_fields(
+        &self,
+        phrase: &str,
+        phrase_required: bool,
+    ) -> Result<Vec<LogicalAST>, QueryParserError> {
+        let mut asts: Vec<(bool, LogicalAST)> = Vec::new();
+        for &field in &self.default_fields {
+            let is_numeric = match *self.schema.get_field_entry(field).field_type() {
+                FieldType::U64(_) | FieldType::I64(_) => true,
+                _ => false,
+            };
+            if is_numeric && self.unfielded_numeric_behavior == UnfieldedNumericBehavior::TextOnly
+            {
+                continue;
             }
+            match self.compute_logical_ast_for_leaf(field, phrase, phrase_required) {
+                Ok(Some(ast)) => asts.push((is_numeric, self.with_field_boost(field, ast))),
+                Ok(None) => {}
+                Err(QueryParserError::ExpectedInt(_)) if is_numeric => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let has_numeric_ast = asts.iter().any(|&(is_numeric, _)| is_numeric);
+        if self.unfielded_numeric_behavior == UnfieldedNumericBehavior::NumericOnly
+            && has_numeric_ast
+        {
+            asts.retain(|&(is_numeric, _)| is_numeric);
         }
+        Ok(asts.into_iter().map(|(_, ast)| ast).collect())
     }
 
     fn default_occur(&self) -> Occur {
@@ -221,52 +1265,116 @@ impl QueryParser {
         }
     }
 
+    /// Computes the clause of the operands of a binary operator,
+    /// whose occur is `operator_occur` unless they have their own (e.g. `-a`).
+    fn compute_logical_ast_for_operands(
+        &self,
+        operands: Vec<Box<UserInputAST>>,
+        operator_occur: Occur,
+        num_clauses: &mut usize,
+    ) -> Result<(Occur, LogicalAST), QueryParserError> {
+        let mut logical_operands: Vec<(Occur, LogicalAST)> = Vec::new();
+        for operand in operands {
+            let (occur, operand_ast) = self.compute_logical_ast_with_occur(*operand, num_clauses)?;
+            logical_operands.push((compose_occur(operator_occur, occur), operand_ast));
+        }
+        Ok((Occur::Should, LogicalAST::Clause(logical_operands)))
+    }
+
+    /// `num_clauses` counts the term and phrase clauses computed so far.
     fn compute_logical_ast_with_occur(
         &self,
         user_input_ast: UserInputAST,
+        num_clauses: &mut usize,
     ) -> Result<(Occur, LogicalAST), QueryParserError> {
         match user_input_ast {
             UserInputAST::Clause(sub_queries) => {
                 let default_occur = self.default_occur();
                 let mut logical_sub_queries: Vec<(Occur, LogicalAST)> = Vec::new();
                 for sub_query in sub_queries {
-                    let (occur, sub_ast) = self.compute_logical_ast_with_occur(*sub_query)?;
+                    let (occur, sub_ast) =
+                        self.compute_logical_ast_with_occur(*sub_query, num_clauses)?;
                     let new_occur = compose_occur(default_occur, occur);
                     logical_sub_queries.push((new_occur, sub_ast));
                 }
                 Ok((Occur::Should, LogicalAST::Clause(logical_sub_queries)))
             }
+            UserInputAST::And(operands) => {
+                self.compute_logical_ast_for_operands(operands, Occur::Must, num_clauses)
+            }
+            UserInputAST::Or(operands) => {
+                self.compute_logical_ast_for_operands(operands, Occur::Should, num_clauses)
+            }
             UserInputAST::Not(subquery) => {
-                let (occur, logical_sub_queries) = self.compute_logical_ast_with_occur(*subquery)?;
+                let (occur, logical_sub_queries) =
+                    self.compute_logical_ast_with_occur(*subquery, num_clauses)?;
                 Ok((compose_occur(Occur::MustNot, occur), logical_sub_queries))
             }
             UserInputAST::Must(subquery) => {
-                let (occur, logical_sub_queries) = self.compute_logical_ast_with_occur(*subquery)?;
+                let (occur, logical_sub_queries) =
+                    self.compute_logical_ast_with_occur(*subquery, num_clauses)?;
                 Ok((compose_occur(Occur::Must, occur), logical_sub_queries))
             }
-            UserInputAST::Leaf(literal) => {
-                let term_phrases: Vec<(Field, String)> = match literal.field_name {
+            UserInputAST::Boost(subquery, boost) => {
+                let (occur, logical_sub_queries) =
+                    self.compute_logical_ast_with_occur(*subquery, num_clauses)?;
+                Ok((occur, logical_sub_queries.boosted(boost)))
+            }
+            UserInputAST::Range {
+                field_name,
+                lower,
+                upper,
+            } => {
+                let fields = self.resolve_field_name(&field_name)?;
+                let mut asts = self.compute_logical_asts_for_range(&fields, &lower, &upper)?;
+                *num_clauses += asts.len();
+                if *num_clauses > self.max_clauses {
+                    return Err(QueryParserError::TooManyClauses(self.max_clauses));
+                }
+                let result_ast = if asts.len() == 1 {
+                    asts.pop().unwrap()
+                } else {
+                    LogicalAST::Clause(asts.into_iter().map(|ast| (Occur::Should, ast)).collect())
+                };
+                Ok((Occur::Should, result_ast))
+            }
+            UserInputAST::Leaf(literal) => {
+                // a slop can only be written after a quoted phrase.
+                let phrase_required = literal.slop.is_some();
+                let asts: Vec<LogicalAST> = match literal.field_name {
                     Some(ref field_name) => {
-                        let field = self.resolve_field_name(field_name)?;
-                        vec![(field, literal.phrase.clone())]
+                        let fields = self.resolve_field_name(field_name)?;
+                        self.compute_logical_asts_for_fields(
+                            &fields,
+                            &literal.phrase,
+                            phrase_required,
+                        )?
                     }
                     None => {
                         if self.default_fields.is_empty() {
                             return Err(QueryParserError::NoDefaultFieldDeclared);
-                        } else {
-                            self.default_fields
-                                .iter()
-                                .map(|default_field| (*default_field, literal.phrase.clone()))
-                                .collect::<Vec<(Field, String)>>()
                         }
+                        let asts = self.compute_logical_asts_for_default_fields(
+                            &literal.phrase,
+                            phrase_required,
+                        )?;
+                        if asts.is_empty() {
+                            // none of the default fields can match this literal.
+                            return Ok((Occur::Should, LogicalAST::Clause(Vec::new())));
+                        }
+                        asts
                     }
                 };
-                let mut asts: Vec<LogicalAST> = Vec::new();
-                for (field, phrase) in term_phrases {
-                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &phrase)? {
-                        asts.push(LogicalAST::Leaf(box ast));
-                    }
+                *num_clauses += asts.len();
+                if *num_clauses > self.max_clauses {
+                    return Err(QueryParserError::TooManyClauses(self.max_clauses));
                 }
+                let asts = match literal.slop {
+                    Some(slop) => asts.into_iter()
+                        .map(|ast| ast.with_phrase_slop(slop))
+                        .collect(),
+                    None => asts,
+                };
                 let result_ast = if asts.is_empty() {
                     // this should never happen
                     return Err(QueryParserError::SyntaxError);
@@ -281,6 +1389,26 @@ impl QueryParser {
     }
 }
 
+/// Returns true iff the ranges on a field are run on its fast field,
+/// the field being a single valued `u64` or `i64` fast field that is not indexed.
+fn is_fast_range_field(field_type: &FieldType) -> bool {
+    match *field_type {
+        FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
+            !int_options.is_indexed()
+                && int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+        }
+        _ => false,
+    }
+}
+
+fn map_bound<T>(bound: Bound<Term>, transform: fn(&Term) -> T) -> Bound<T> {
+    match bound {
+        Bound::Included(ref term) => Bound::Included(transform(term)),
+        Bound::Excluded(ref term) => Bound::Excluded(transform(term)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
 /// Compose two occur values.
 fn compose_occur(left: Occur, right: Occur) -> Occur {
     match left {
@@ -302,26 +1430,6 @@ fn compose_occur(left: Occur, right: Occur) -> Occur {
     }
 }
 
-fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<Query> {
-    match logical_literal {
-        LogicalLiteral::Term(term) => box TermQuery::new(term, IndexRecordOption::WithFreqs),
-        LogicalLiteral::Phrase(terms) => box PhraseQuery::from(terms),
-    }
-}
-
-fn convert_to_query(logical_ast: LogicalAST) -> Box<Query> {
-    match logical_ast {
-        LogicalAST::Clause(clause) => {
-            let occur_subqueries = clause
-                .into_iter()
-                .map(|(occur, subquery)| (occur, convert_to_query(subquery)))
-                .collect::<Vec<_>>();
-            box BooleanQuery::from(occur_subqueries)
-        }
-        LogicalAST::Leaf(logical_literal) => convert_literal_to_query(*logical_literal),
-    }
-}
-
 #[cfg(test)]
 mod test {
     use schema::{SchemaBuilder, Term, INT_INDEXED, STORED, STRING, TEXT};
@@ -331,8 +1439,17 @@ mod test {
     use schema::{IndexRecordOption, TextFieldIndexing, TextOptions};
     use super::QueryParser;
     use super::QueryParserError;
+    use termdict::edit_distance;
+    use super::exceeds_nesting_depth;
+    use super::UnfieldedNumericBehavior;
+    use super::AutoPhrase;
+    use query::{BooleanQuery, Occur, QueryAny, QueryDisplay, QueryValidationError, TermQuery};
     use Index;
-    use tokenizer::SimpleTokenizer;
+    use tokenizer::{LowerCaser, NfcNormalizer, SimpleTokenizer, Tokenizer};
+    use tokenizer::{StopWordFilter, SynonymFilter};
+    use collector::{CountCollector, TopCollector};
+    use DocId;
+    use std::collections::HashMap;
     use super::super::logical_ast::*;
 
     fn make_query_parser() -> QueryParser {
@@ -345,6 +1462,7 @@ mod test {
         schema_builder.add_text_field("notindexed_u64", STORED);
         schema_builder.add_text_field("notindexed_i64", STORED);
         schema_builder.add_text_field("nottokenized", STRING);
+        schema_builder.add_facet_field("category");
         let schema = schema_builder.build();
         let default_fields = vec![title, text];
         let tokenizer_manager = TokenizerManager::default();
@@ -453,6 +1571,48 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_parse_query_unfielded_numeric_behavior() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body = schema_builder.add_text_field("body", TEXT);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let schema = schema_builder.build();
+        let text_term = "Term([0, 0, 0, 0, 50, 48, 50, 48])";
+        let year_term = "Term([0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 7, 228])";
+        let hello_term = "Term([0, 0, 0, 0, 104, 101, 108, 108, 111])";
+        let both_terms = format!("({} {})", text_term, year_term);
+        let behaviors = vec![
+            (UnfieldedNumericBehavior::TextOnly, text_term),
+            (UnfieldedNumericBehavior::NumericOnly, year_term),
+            (UnfieldedNumericBehavior::Both, &both_terms[..]),
+        ];
+        for (behavior, expected) in behaviors {
+            let mut query_parser =
+                QueryParser::new(schema.clone(), vec![body, year], TokenizerManager::default());
+            query_parser.set_unfielded_numeric_behavior(behavior);
+            let to_string = |query: &str| {
+                format!("{:?}", query_parser.parse_query_to_logical_ast(query).unwrap())
+            };
+            assert_eq!(to_string("2020"), expected);
+            assert_eq!(to_string("hello"), hello_term);
+            assert_eq!(to_string("year:2020"), year_term);
+            assert_eq!(to_string("body:2020"), text_term);
+            assert!(query_parser.parse_query("hello").is_ok());
+            assert_matches!(
+                query_parser.parse_query("year:hello"),
+                Err(QueryParserError::ExpectedInt(_))
+            );
+        }
+        // an unfielded term that matches none of the default fields
+        // does not result in an error.
+        let query_parser = QueryParser::new(schema, vec![year], TokenizerManager::default());
+        assert_eq!(
+            format!("{:?}", query_parser.parse_query_to_logical_ast("hello").unwrap()),
+            "<emptyclause>"
+        );
+        assert!(query_parser.parse_query("hello").is_ok());
+    }
+
     #[test]
     pub fn test_parse_query_to_ast_disjunction() {
         test_parse_query_to_logical_ast_helper(
@@ -559,6 +1719,32 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_query_parser_trailing_input() {
+        let query_parser = make_query_parser();
+        assert_matches!(
+            query_parser.parse_query("signed:[1 2]"),
+            Err(QueryParserError::SyntaxError)
+        );
+        assert!(query_parser.parse_query("signed:[1 TO 2]").is_ok());
+    }
+
+    #[test]
+    pub fn test_parse_query_facet() {
+        let query_parser = make_query_parser();
+        let query = query_parser
+            .parse_query("category:\"/category/fiction\"")
+            .unwrap();
+        assert_eq!(
+            QueryDisplay::new(&*query, &query_parser.schema).to_string(),
+            "Term(category:/category/fiction)\n"
+        );
+        assert_matches!(
+            query_parser.parse_query("category:fiction"),
+            Err(QueryParserError::ExpectedFacet(_))
+        );
+    }
+
     #[test]
     pub fn test_parse_query_to_ast_conjunction() {
         test_parse_query_to_logical_ast_helper(
@@ -598,4 +1784,942 @@ mod test {
             true,
         );
     }
+
+    #[test]
+    pub fn test_parse_query_validation() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default().set_index_option(IndexRecordOption::WithFreqs),
+        );
+        let freqs_only = schema_builder.add_text_field("freqs_only", text_options);
+        let schema = schema_builder.build();
+        let mut query_parser =
+            QueryParser::new(schema, vec![freqs_only], TokenizerManager::default());
+        assert!(query_parser.parse_query("a").is_ok());
+        assert!(query_parser.parse_query("\"a b\"").is_ok());
+        // a slop makes the quoted text explicitly a phrase.
+        assert_eq!(
+            query_parser.parse_query("\"a b\"~1").err().unwrap(),
+            QueryParserError::FieldDoesNotHavePositions("freqs_only".to_string())
+        );
+        query_parser.set_auto_phrase(AutoPhrase::Always);
+        assert_eq!(
+            query_parser.parse_query("\"a b\"").err().unwrap(),
+            QueryParserError::FieldDoesNotHavePositions("freqs_only".to_string())
+        );
+        query_parser.set_auto_phrase(AutoPhrase::Never);
+        assert!(query_parser.parse_query("\"a b\"~1").is_ok());
+        query_parser.set_auto_phrase(AutoPhrase::Always);
+        query_parser.set_validation(false);
+        assert!(query_parser.parse_query("\"a b\"").is_ok());
+        assert!(query_parser.parse_query("\"a b\"~1").is_ok());
+    }
+
+    fn make_query_parser_for_auto_phrase() -> QueryParser {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let basic_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default().set_index_option(IndexRecordOption::Basic),
+        );
+        let basic = schema_builder.add_text_field("basic", basic_options);
+        let schema = schema_builder.build();
+        // the default tokenizer splits `wi-fi` into `wi` and `fi`.
+        QueryParser::new(schema, vec![title, basic], TokenizerManager::default())
+    }
+
+    fn query_tree(query_parser: &QueryParser, query: &str) -> String {
+        let query = query_parser.parse_query(query).unwrap();
+        QueryDisplay::new(&*query, &query_parser.schema).to_string()
+    }
+
+    #[test]
+    pub fn test_parse_query_auto_phrase() {
+        let mut query_parser = make_query_parser_for_auto_phrase();
+
+        // `AutoPhrase::WhenPositionsAvailable`
+        assert_eq!(
+            query_tree(&query_parser, "title:\"wi-fi\""),
+            "Phrase(title:\"wi\" \"fi\")\n"
+        );
+        assert_eq!(
+            query_tree(&query_parser, "basic:\"wi-fi\""),
+            "Boolean\n\
+             \x20 Must Term(basic:\"wi\")\n\
+             \x20 Must Term(basic:\"fi\")\n"
+        );
+        assert_eq!(
+            query_tree(&query_parser, "\"wi-fi\""),
+            "Boolean\n\
+             \x20 Should Phrase(title:\"wi\" \"fi\")\n\
+             \x20 Should Boolean\n\
+             \x20   Must Term(basic:\"wi\")\n\
+             \x20   Must Term(basic:\"fi\")\n"
+        );
+        // a single token is a term, whatever the setting.
+        assert_eq!(query_tree(&query_parser, "basic:wifi"), "Term(basic:\"wifi\")\n");
+
+        query_parser.set_auto_phrase(AutoPhrase::Always);
+        assert_eq!(
+            query_tree(&query_parser, "title:\"wi-fi\""),
+            "Phrase(title:\"wi\" \"fi\")\n"
+        );
+        assert_eq!(
+            query_parser.parse_query("basic:\"wi-fi\"").err(),
+            Some(QueryParserError::FieldDoesNotHavePositions("basic".to_string()))
+        );
+        query_parser.set_validation(false);
+        assert_eq!(
+            query_tree(&query_parser, "basic:\"wi-fi\""),
+            "Phrase(basic:\"wi\" \"fi\")\n"
+        );
+        query_parser.set_validation(true);
+
+        query_parser.set_auto_phrase(AutoPhrase::Never);
+        assert_eq!(
+            query_tree(&query_parser, "title:\"wi-fi\""),
+            "Boolean\n\
+             \x20 Must Term(title:\"wi\")\n\
+             \x20 Must Term(title:\"fi\")\n"
+        );
+        assert_eq!(
+            query_tree(&query_parser, "basic:\"wi-fi\""),
+            "Boolean\n\
+             \x20 Must Term(basic:\"wi\")\n\
+             \x20 Must Term(basic:\"fi\")\n"
+        );
+        assert_eq!(
+            query_tree(&query_parser, "-basic:\"wi-fi\" title:wifi"),
+            "Boolean\n\
+             \x20 MustNot Boolean\n\
+             \x20   Must Term(basic:\"wi\")\n\
+             \x20   Must Term(basic:\"fi\")\n\
+             \x20 Should Term(title:\"wifi\")\n"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_quoted_phrase() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("stop_synonyms")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let title = schema_builder.add_text_field("title", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut synonyms = HashMap::new();
+        synonyms.insert("nyc".to_string(), vec!["newyork".to_string()]);
+        index.tokenizers().register(
+            "stop_synonyms",
+            SimpleTokenizer
+                .filter(LowerCaser)
+                .filter(StopWordFilter::remove(vec!["the", "of"]))
+                .filter(SynonymFilter::new(synonyms)),
+        );
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "The Great Gatsby")).unwrap();
+            index_writer.add_document(doc!(title => "Gatsby the Great")).unwrap();
+            index_writer.add_document(doc!(title => "Gatsby of Great Neck")).unwrap();
+            index_writer.add_document(doc!(title => "Great Gatsby")).unwrap();
+            index_writer.add_document(doc!(title => "I love NYC")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let count = |query: &str| {
+            let mut count_collector = CountCollector::default();
+            let query = query_parser.parse_query(query).unwrap();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+
+        assert_eq!(
+            query_tree(&query_parser, "\"the great gatsby\""),
+            "Phrase(title:\"great\" \"gatsby\")\n"
+        );
+        assert_eq!(count("\"the great gatsby\""), 2);
+        // the positions of the removed stop words are kept.
+        assert_eq!(
+            query_tree(&query_parser, "\"gatsby the great\""),
+            "Phrase(title:\"gatsby\" ? \"great\")\n"
+        );
+        assert_eq!(count("\"gatsby the great\""), 2);
+        assert_eq!(count("\"gatsby great\""), 0);
+        // the synonyms share the position of their token.
+        assert_eq!(
+            query_tree(&query_parser, "\"love nyc\""),
+            "Phrase(title:\"love\" \"nyc\"|\"newyork\")\n"
+        );
+        assert_eq!(count("\"love nyc\""), 1);
+        assert_eq!(count("\"love newyork\""), 1);
+
+        assert_eq!(
+            query_parser.parse_query("title:\"great gatsby").err(),
+            Some(QueryParserError::UnterminatedQuote(6))
+        );
+        assert_eq!(
+            query_parser.parse_query("\"great\" \"gatsby").err(),
+            Some(QueryParserError::UnterminatedQuote(8))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_unicode_whitespaces() {
+        test_parse_query_to_logical_ast_helper(
+            "title:a\u{3000}title:b",
+            "(Term([0, 0, 0, 0, 97]) Term([0, 0, 0, 0, 98]))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:a\u{200B}\u{A0}title:b",
+            "(Term([0, 0, 0, 0, 97]) Term([0, 0, 0, 0, 98]))",
+            false,
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_nfc_normalization() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("nfc")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "nfc",
+            SimpleTokenizer.filter(NfcNormalizer).filter(LowerCaser),
+        );
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "un caf\u{e9} cr\u{e8}me")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |query_parser: &QueryParser, query: &str| {
+            let mut count_collector = CountCollector::default();
+            match query_parser.parse_query(query) {
+                Ok(query) => {
+                    query.search(&*searcher, &mut count_collector).unwrap();
+                    count_collector.count()
+                }
+                Err(_) => 0,
+            }
+        };
+        let mut query_parser = QueryParser::for_index(&index, vec![text]);
+        assert_eq!(count(&query_parser, "caf\u{e9}"), 1);
+        assert_eq!(count(&query_parser, "cafe\u{301}"), 1);
+        assert_eq!(count(&query_parser, "\"cafe\u{301} cre\u{300}me\""), 1);
+        query_parser.set_normalization(false);
+        assert_eq!(count(&query_parser, "caf\u{e9}"), 1);
+        assert_eq!(count(&query_parser, "\"cafe\u{301} cre\u{300}me\""), 0);
+    }
+
+    fn make_query_parser_with_aliases() -> QueryParser {
+        let mut schema_builder = SchemaBuilder::default();
+        let author_name = schema_builder.add_text_field("author_name", TEXT);
+        let author_email = schema_builder.add_text_field("author_email", STRING);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let schema = schema_builder.build();
+        let mut query_parser = QueryParser::new(schema, vec![], TokenizerManager::default());
+        query_parser
+            .add_field_alias("author", vec![author_name, author_email])
+            .unwrap();
+        query_parser
+            .add_field_alias("any", vec![author_name, year])
+            .unwrap();
+        query_parser
+    }
+
+    fn logical_ast_str(query_parser: &QueryParser, query: &str) -> String {
+        format!("{:?}", query_parser.parse_query_to_logical_ast(query).unwrap())
+    }
+
+    #[test]
+    pub fn test_parse_query_field_alias() {
+        let query_parser = make_query_parser_with_aliases();
+        // each field is searched with its own tokenizer.
+        assert_eq!(
+            logical_ast_str(&query_parser, "author:Smith"),
+            "(Term([0, 0, 0, 0, 115, 109, 105, 116, 104]) \
+             Term([0, 0, 0, 1, 83, 109, 105, 116, 104]))"
+        );
+        assert_eq!(
+            logical_ast_str(&query_parser, "+author:Smith -year:2010"),
+            "(+(Term([0, 0, 0, 0, 115, 109, 105, 116, 104]) \
+             Term([0, 0, 0, 1, 83, 109, 105, 116, 104])) \
+             -Term([0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 7, 218]))"
+        );
+        assert_eq!(
+            logical_ast_str(&query_parser, "any:2010"),
+            "(Term([0, 0, 0, 0, 50, 48, 49, 48]) \
+             Term([0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 7, 218]))"
+        );
+        // numeric fields are skipped if the value is not a number.
+        assert_eq!(
+            logical_ast_str(&query_parser, "any:smith"),
+            "Term([0, 0, 0, 0, 115, 109, 105, 116, 104])"
+        );
+        assert!(query_parser.parse_query("author:Smith").is_ok());
+    }
+
+    #[test]
+    pub fn test_field_alias_conflicts() {
+        let mut query_parser = make_query_parser_with_aliases();
+        assert_eq!(
+            query_parser.add_field_alias("author_name", vec![Field(1)]),
+            Err(QueryParserError::FieldNameConflict("author_name".to_string()))
+        );
+        assert_eq!(
+            query_parser.add_field_rename("author", Field(0)),
+            Err(QueryParserError::FieldNameConflict("author".to_string()))
+        );
+        assert_eq!(query_parser.add_field_rename("writer", Field(0)), Ok(()));
+        assert_eq!(
+            query_parser.add_field_alias("writer", vec![Field(0)]),
+            Err(QueryParserError::FieldNameConflict("writer".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_field_rename() {
+        let mut query_parser = make_query_parser_with_aliases();
+        // dotted field names default to the field with underscores.
+        assert_eq!(
+            logical_ast_str(&query_parser, "author.name:Smith"),
+            "Term([0, 0, 0, 0, 115, 109, 105, 116, 104])"
+        );
+        // renames take precedence.
+        query_parser
+            .add_field_rename("author.name", Field(1))
+            .unwrap();
+        assert_eq!(
+            logical_ast_str(&query_parser, "author.name:Smith"),
+            "Term([0, 0, 0, 1, 83, 109, 105, 116, 104])"
+        );
+        assert_eq!(
+            logical_ast_str(&query_parser, "author_name:Smith"),
+            "Term([0, 0, 0, 0, 115, 109, 105, 116, 104])"
+        );
+    }
+
+    #[test]
+    pub fn test_field_name_suggestion() {
+        let query_parser = make_query_parser_with_aliases();
+        assert_eq!(
+            query_parser.parse_query("autor_name:smith").err().unwrap(),
+            QueryParserError::FieldDoesNotExist("autor_name".to_string())
+        );
+        assert_eq!(
+            query_parser.suggest_field_name("autor_name"),
+            Some("author_name".to_string())
+        );
+        assert_eq!(
+            query_parser.suggest_field_name("auhtor"),
+            Some("author".to_string())
+        );
+        assert_eq!(
+            query_parser.parse_query("title:smith").err().unwrap(),
+            QueryParserError::FieldDoesNotExist("title".to_string())
+        );
+        assert_eq!(query_parser.suggest_field_name("title"), None);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("author", "author"), 0);
+    }
+
+    #[test]
+    pub fn test_parse_query_synonyms() {
+        let mut query_parser = make_query_parser();
+        query_parser.add_synonyms(&["tv", "television"]);
+        query_parser.add_synonyms(&["tv", "telly"]);
+        assert_eq!(
+            logical_ast_str(&query_parser, "title:TV"),
+            "Synonyms([Term([0, 0, 0, 0, 116, 118]), \
+             Term([0, 0, 0, 0, 116, 101, 108, 101, 118, 105, 115, 105, 111, 110]), \
+             Term([0, 0, 0, 0, 116, 101, 108, 108, 121])])"
+        );
+        assert_eq!(
+            logical_ast_str(&query_parser, "title:telly"),
+            "Synonyms([Term([0, 0, 0, 0, 116, 101, 108, 108, 121]), \
+             Term([0, 0, 0, 0, 116, 118])])"
+        );
+        assert_eq!(
+            logical_ast_str(&query_parser, "title:radio"),
+            "Term([0, 0, 0, 0, 114, 97, 100, 105, 111])"
+        );
+        // phrases are not expanded.
+        assert_eq!(
+            logical_ast_str(&query_parser, "title:\"tv set\""),
+            "\"[Term([0, 0, 0, 0, 116, 118]), Term([0, 0, 0, 0, 115, 101, 116])]\""
+        );
+        let query = query_parser.parse_query("title:tv").unwrap();
+        assert_eq!(
+            QueryDisplay::new(&*query, &query_parser.schema).to_string(),
+            "Synonym(title:\"tv\" \"television\" \"telly\")\n"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_required_filter() {
+        let mut schema_builder = SchemaBuilder::default();
+        let tenant_id = schema_builder.add_u64_field("tenant_id", INT_INDEXED);
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(tenant_id => 1u64, title => "red apple")).unwrap();
+            index_writer.add_document(doc!(tenant_id => 2u64, title => "red cherry")).unwrap();
+            index_writer.add_document(doc!(tenant_id => 2u64, title => "green apple")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut query_parser = QueryParser::for_index(&index, vec![title]);
+        query_parser.set_required_filter(box TermQuery::new(
+            Term::from_field_u64(tenant_id, 2u64),
+            IndexRecordOption::Basic,
+        ));
+        let count = |query_parser: &QueryParser, query: &str| {
+            let mut count_collector = CountCollector::default();
+            let query = query_parser.parse_query(query).unwrap();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count(&query_parser, "apple"), 1);
+        assert_eq!(count(&query_parser, "red apple"), 2);
+        assert_eq!(count(&query_parser, "+red +apple"), 0);
+        // the filter cannot be escaped from the query.
+        assert_eq!(count(&query_parser, "tenant_id:1"), 0);
+        assert_eq!(count(&query_parser, "apple tenant_id:1"), 1);
+        let query = query_parser.parse_query("apple").unwrap();
+        assert_eq!(
+            QueryDisplay::new(&*query, &query_parser.schema).to_string(),
+            "Boolean\n\
+             \x20 Must Boost(0) Term(tenant_id:2)\n\
+             \x20 Must Term(title:\"apple\")\n"
+        );
+        query_parser.clear_required_filter();
+        assert_eq!(count(&query_parser, "apple"), 2);
+        assert_eq!(count(&query_parser, "tenant_id:1"), 1);
+    }
+
+    #[test]
+    pub fn test_parse_query_not() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "rust java")).unwrap();
+            index_writer.add_document(doc!(title => "rust")).unwrap();
+            index_writer.add_document(doc!(title => "java")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let count = |query: &str| {
+            let mut count_collector = CountCollector::default();
+            let query = query_parser.parse_query(query).unwrap();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        for &query in &["rust -java", "rust NOT java", "rust NOT  java"] {
+            assert_eq!(
+                query_tree(&query_parser, query),
+                "Boolean\n\
+                 \x20 Should Term(title:\"rust\")\n\
+                 \x20 MustNot Term(title:\"java\")\n"
+            );
+            assert_eq!(count(query), 1);
+        }
+        assert_eq!(count("NOT java rust"), 1);
+        assert_eq!(count("rust NOT (java rust)"), 0);
+        assert_eq!(count("rust java"), 3);
+        // the dash of a phrase does not exclude the term.
+        assert_eq!(query_tree(&query_parser, "\"-java\""), "Term(title:\"java\")\n");
+        assert_eq!(count("\"-java\""), 2);
+        // a trailing `NOT` is a plain word.
+        assert_eq!(count("rust NOT"), 2);
+        for &query in &["-java", "NOT java", "-rust NOT java", "(NOT rust -java)"] {
+            assert_eq!(
+                query_parser.parse_query(query).err(),
+                Some(QueryParserError::AllButQueryForbidden)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_parse_query_conjunction_by_default() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for &text in &[
+                "rust async runtime tokio",
+                "rust async runtime blocking",
+                "rust async",
+                "async runtime tokio",
+            ] {
+                index_writer.add_document(doc!(title => text)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let docs = |query_parser: &QueryParser, query: &str| -> Vec<DocId> {
+            let mut top_collector = TopCollector::with_limit(10);
+            let query = query_parser.parse_query(query).unwrap();
+            query.search(&*searcher, &mut top_collector).unwrap();
+            let mut docs: Vec<DocId> = top_collector
+                .docs()
+                .into_iter()
+                .map(|doc_address| doc_address.doc())
+                .collect();
+            docs.sort();
+            docs
+        };
+        let mut query_parser = QueryParser::for_index(&index, vec![title]);
+        let queries: Vec<(&str, Vec<DocId>, Vec<DocId>)> = vec![
+            ("rust async runtime", vec![0, 1, 2, 3], vec![0, 1]),
+            ("rust +tokio -blocking", vec![0, 3], vec![0]),
+            ("+rust tokio", vec![0, 1, 2], vec![0]),
+            ("runtime -blocking", vec![0, 3], vec![0, 3]),
+            ("rust (tokio blocking)", vec![0, 1, 2, 3], vec![]),
+        ];
+        for &(query, ref disjunction_docs, ref conjunction_docs) in &queries {
+            query_parser.set_conjunction_by_default();
+            assert_eq!(&docs(&query_parser, query), conjunction_docs, "{}", query);
+            query_parser.set_disjunction_by_default();
+            assert_eq!(&docs(&query_parser, query), disjunction_docs, "{}", query);
+        }
+        query_parser.set_conjunction_by_default();
+        assert_eq!(
+            query_tree(&query_parser, "rust +tokio -blocking"),
+            "Boolean\n\
+             \x20 Must Term(title:\"rust\")\n\
+             \x20 Must Term(title:\"tokio\")\n\
+             \x20 MustNot Term(title:\"blocking\")\n"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_max_clauses() {
+        let mut query_parser = make_query_parser();
+        let huge_query: Vec<String> = (0..10_000).map(|i| format!("term{}", i)).collect();
+        assert_eq!(
+            query_parser.parse_query(&huge_query.join(" ")).err(),
+            Some(QueryParserError::TooManyClauses(1_024))
+        );
+        // clauses are counted after their expansion to the default fields.
+        query_parser.set_max_clauses(3);
+        assert!(query_parser.parse_query("a").is_ok());
+        assert!(query_parser.parse_query("title:a title:b title:c").is_ok());
+        assert_eq!(
+            query_parser.parse_query("a b").err(),
+            Some(QueryParserError::TooManyClauses(3))
+        );
+        assert_eq!(
+            query_parser.parse_query("(title:a title:b) -(title:c title:d)").err(),
+            Some(QueryParserError::TooManyClauses(3))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_max_nesting_depth() {
+        let mut query_parser = make_query_parser();
+        let nested_query = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(
+            query_parser.parse_query(&nested_query(100)).err(),
+            Some(QueryParserError::TooDeep(32))
+        );
+        assert_eq!(
+            query_parser.parse_query(&"+-".repeat(50)).err(),
+            Some(QueryParserError::TooDeep(32))
+        );
+        assert!(query_parser.parse_query(&nested_query(10)).is_ok());
+        query_parser.set_max_nesting_depth(2);
+        assert!(query_parser.parse_query("(a (b c)) d").is_ok());
+        assert_eq!(
+            query_parser.parse_query("+(a -(b c))").err(),
+            Some(QueryParserError::TooDeep(2))
+        );
+    }
+
+    /// Returns the clauses of a `BooleanQuery`.
+    fn boolean_clauses(query: &Query) -> &[(Occur, Box<Query>)] {
+        query
+            .as_any()
+            .downcast_ref::<BooleanQuery>()
+            .expect("Expected a BooleanQuery")
+            .clauses()
+    }
+
+    /// Returns the text of the term of a `TermQuery`.
+    fn term_text(query: &Query) -> &str {
+        query
+            .as_any()
+            .downcast_ref::<TermQuery>()
+            .expect("Expected a TermQuery")
+            .term()
+            .text()
+    }
+
+    #[test]
+    pub fn test_parse_query_binary_operators() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let mut query_parser =
+            QueryParser::new(schema_builder.build(), vec![title], TokenizerManager::default());
+        for &conjunction_by_default in &[false, true] {
+            if conjunction_by_default {
+                query_parser.set_conjunction_by_default();
+            }
+            let query = query_parser
+                .parse_query("(rust OR golang) AND (async OR concurrent)")
+                .unwrap();
+            let clauses = boolean_clauses(&*query);
+            assert_eq!(clauses.len(), 2);
+            let expected_words = [["rust", "golang"], ["async", "concurrent"]];
+            for (&(occur, ref group), words) in clauses.iter().zip(expected_words.iter()) {
+                assert_eq!(occur, Occur::Must);
+                let group_clauses = boolean_clauses(&**group);
+                assert_eq!(group_clauses.len(), 2);
+                for (&(occur, ref term_query), word) in group_clauses.iter().zip(words.iter()) {
+                    assert_eq!(occur, Occur::Should);
+                    assert_eq!(term_text(&**term_query), *word);
+                }
+            }
+        }
+
+        // `AND` takes precedence over `OR`, and the groups nest.
+        let query = query_parser
+            .parse_query("a OR b AND -(c OR (d AND e))")
+            .unwrap();
+        let clauses = boolean_clauses(&*query);
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses[0].0, Occur::Should);
+        assert_eq!(term_text(&*clauses[0].1), "a");
+        assert_eq!(clauses[1].0, Occur::Should);
+        let conjuncts = boolean_clauses(&*clauses[1].1);
+        assert_eq!(conjuncts.len(), 2);
+        assert_eq!(conjuncts[0].0, Occur::Must);
+        assert_eq!(term_text(&*conjuncts[0].1), "b");
+        assert_eq!(conjuncts[1].0, Occur::MustNot);
+        let disjuncts = boolean_clauses(&*conjuncts[1].1);
+        assert_eq!(disjuncts[0].0, Occur::Should);
+        assert_eq!(term_text(&*disjuncts[0].1), "c");
+        assert_eq!(disjuncts[1].0, Occur::Should);
+        let innermost = boolean_clauses(&*disjuncts[1].1);
+        assert_eq!(
+            innermost
+                .iter()
+                .map(|&(occur, ref query)| (occur, term_text(&**query)))
+                .collect::<Vec<_>>(),
+            vec![(Occur::Must, "d"), (Occur::Must, "e")]
+        );
+
+        assert_eq!(
+            query_parser.parse_query("-a AND -b").err(),
+            Some(QueryParserError::AllButQueryForbidden)
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_parentheses_errors() {
+        let query_parser = make_query_parser();
+        let parse_err = |query: &str| query_parser.parse_query(query).err();
+        assert_eq!(
+            parse_err("(a b"),
+            Some(QueryParserError::UnbalancedParenthesis(0))
+        );
+        assert_eq!(
+            parse_err("a b)"),
+            Some(QueryParserError::UnbalancedParenthesis(3))
+        );
+        assert_eq!(
+            parse_err("(a (b) c"),
+            Some(QueryParserError::UnbalancedParenthesis(0))
+        );
+        assert_eq!(
+            parse_err("(a OR b)) AND c"),
+            Some(QueryParserError::UnbalancedParenthesis(8))
+        );
+        assert_eq!(parse_err("a ()"), Some(QueryParserError::EmptyGroup(2)));
+        assert_eq!(parse_err("a AND -(  )"), Some(QueryParserError::EmptyGroup(7)));
+        assert_eq!(parse_err("((a) ())"), Some(QueryParserError::EmptyGroup(5)));
+        // parentheses within phrases are not groups.
+        assert!(query_parser.parse_query("title:\"a (\" (b)").is_ok());
+        assert!(query_parser.parse_query("\"()\"").is_ok());
+    }
+
+    #[test]
+    pub fn test_parse_query_modifiers() {
+        let query_parser = make_query_parser();
+        let valid_queries = vec![
+            ("title:\"a b\"~2", "Phrase(title:\"a\" \"b\")~2\n"),
+            ("title:\"a b\"~0", "Phrase(title:\"a\" \"b\")\n"),
+            ("title:\"a b\"~2^0.5", "Boost(0.5) Phrase(title:\"a\" \"b\")~2\n"),
+            ("title:\"a\"~2", "Term(title:\"a\")\n"),
+            ("title:\"a~b\"", "Phrase(title:\"a\" \"b\")\n"),
+            ("title:a^1.5", "Boost(1.5) Term(title:\"a\")\n"),
+            (
+                "(title:a title:b)^2",
+                "Boost(2) Boolean\n\
+                 \x20 Should Term(title:\"a\")\n\
+                 \x20 Should Term(title:\"b\")\n",
+            ),
+            ("((title:a)^2)^3", "Boost(6) Term(title:\"a\")\n"),
+            (
+                "((title:a)^2 title:b)^3",
+                "Boost(3) Boolean\n\
+                 \x20 Should Boost(2) Term(title:\"a\")\n\
+                 \x20 Should Term(title:\"b\")\n",
+            ),
+        ];
+        for (query, expected_tree) in valid_queries {
+            assert_eq!(query_tree(&query_parser, query), expected_tree, "{}", query);
+        }
+        let invalid_queries = vec![
+            ("title:a~2", 7..9, "slop: only allowed after a quoted phrase"),
+            ("(title:a)~2", 9..11, "slop: only allowed after a quoted phrase"),
+            ("title:\"a b\"~", 11..12, "slop: expected a non-negative integer"),
+            ("title:\"a b\"~x", 11..13, "slop: expected a non-negative integer"),
+            ("title:\"a b\"~2x", 11..14, "slop: expected a non-negative integer"),
+            ("title:\"a b\"~99999999999", 11..23, "slop: expected a non-negative integer"),
+            ("title:\"a b\"^2~1", 13..15, "slop: must precede the boost"),
+            ("title:\"a b\"~1~2", 13..15, "slop: duplicated"),
+            ("^2 title:a", 0..2, "boost: must follow a term, a phrase or a group"),
+            ("title:a^", 7..8, "boost: expected a non-negative number"),
+            ("title:a^-1", 7..10, "boost: expected a non-negative number"),
+            ("title:a^1.5.2", 7..13, "boost: expected a non-negative number"),
+            ("title:a^2^3", 9..11, "boost: duplicated"),
+        ];
+        for (query, span, what) in invalid_queries {
+            assert_eq!(
+                query_parser.parse_query(query).err(),
+                Some(QueryParserError::InvalidModifier {
+                    span,
+                    what: what.to_string(),
+                }),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_parse_query_field_boosts() {
+        let mut query_parser = make_query_parser();
+        let title = query_parser.schema.get_field("title").unwrap();
+        let nottokenized = query_parser.schema.get_field("nottokenized").unwrap();
+        query_parser.set_field_boost(title, 2.0);
+        query_parser.set_field_boost(nottokenized, 3.0);
+        let queries = vec![
+            (
+                "a",
+                "Boolean\n\
+                 \x20 Should Boost(2) Term(title:\"a\")\n\
+                 \x20 Should Term(text:\"a\")\n",
+            ),
+            (
+                "\"a b\"~1",
+                "Boolean\n\
+                 \x20 Should Boost(2) Phrase(title:\"a\" \"b\")~1\n\
+                 \x20 Should Phrase(text:\"a\" \"b\")~1\n",
+            ),
+            ("title:a", "Boost(2) Term(title:\"a\")\n"),
+            ("title:a^1.5", "Boost(3) Term(title:\"a\")\n"),
+            ("text:a", "Term(text:\"a\")\n"),
+            ("nottokenized:a", "Boost(3) Term(nottokenized:\"a\")\n"),
+        ];
+        for (query, expected_tree) in queries {
+            assert_eq!(query_tree(&query_parser, query), expected_tree, "{}", query);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_query_range() {
+        let query_parser = make_query_parser();
+        let queries = vec![
+            ("signed:[-5 TO 10]", "Range(signed:[-5..10])\n"),
+            ("signed:{-5 TO 10}", "Range(signed:(-5..10))\n"),
+            ("unsigned:[* TO 10}", "Range(unsigned:(*..10))\n"),
+            ("unsigned:{10 TO *]", "Range(unsigned:(10..*))\n"),
+            ("title:[a TO c]", "Range(title:[\"a\"..\"c\"])\n"),
+            ("nottokenized:[Abc TO abc]", "Range(nottokenized:[\"Abc\"..\"abc\"])\n"),
+            ("signed:[1 TO 2]^2", "Boost(2) Range(signed:[1..2])\n"),
+            (
+                "title:a +signed:[1 TO 2]",
+                "Boolean\n\
+                 \x20 Should Term(title:\"a\")\n\
+                 \x20 Must Range(signed:[1..2])\n",
+            ),
+        ];
+        for (query, expected_tree) in queries {
+            assert_eq!(query_tree(&query_parser, query), expected_tree, "{}", query);
+        }
+        test_parse_query_to_logical_ast_helper(
+            "unsigned:[1 TO *]",
+            "[Term([0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 1]) TO *}",
+            false,
+        );
+        let invalid_bound = |field_name: &str, bound: &str| {
+            QueryParserError::InvalidRangeBound(field_name.to_string(), bound.to_string())
+        };
+        assert_eq!(
+            query_parser.parse_query("signed:[1 TO a]").unwrap_err(),
+            invalid_bound("signed", "a")
+        );
+        assert_eq!(
+            query_parser.parse_query("unsigned:{-1 TO 2]").unwrap_err(),
+            invalid_bound("unsigned", "-1")
+        );
+        assert_eq!(
+            query_parser
+                .parse_query("signed:[9223372036854775808 TO *]")
+                .unwrap_err(),
+            invalid_bound("signed", "9223372036854775808")
+        );
+        assert_matches!(
+            query_parser.parse_query("category:[a TO b]"),
+            Err(QueryParserError::InvalidQuery(
+                QueryValidationError::IncompatibleFieldType(_, _)
+            ))
+        );
+        assert_matches!(
+            query_parser.parse_query("notindexed_u64:[1 TO 2]"),
+            Err(QueryParserError::FieldNotIndexed(_))
+        );
+        assert_matches!(
+            query_parser.parse_query("sined:[1 TO 2]"),
+            Err(QueryParserError::FieldDoesNotExist(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_range_search() {
+        let mut schema_builder = SchemaBuilder::default();
+        let timestamp = schema_builder.add_i64_field("timestamp", INT_INDEXED);
+        let price = schema_builder.add_u64_field("price", INT_INDEXED);
+        let name = schema_builder.add_text_field("name", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for &(timestamp_val, price_val, name_val) in &[
+                (-1_600_000_000i64, 5u64, "apple"),
+                (-100, 10, "banana"),
+                (0, 50, "cherry"),
+                (1_500_000_000, 100, "date"),
+                (1_600_000_000, 150, "elderberry"),
+            ] {
+                index_writer
+                    .add_document(doc!(
+                        timestamp => timestamp_val,
+                        price => price_val,
+                        name => name_val
+                    ))
+                    .unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![name]);
+        let docs = |query: &str| -> Vec<DocId> {
+            let mut top_collector = TopCollector::with_limit(10);
+            let query = query_parser.parse_query(query).unwrap();
+            query.search(&*searcher, &mut top_collector).unwrap();
+            let mut docs: Vec<DocId> = top_collector
+                .docs()
+                .into_iter()
+                .map(|doc_address| doc_address.doc())
+                .collect();
+            docs.sort();
+            docs
+        };
+        let queries: Vec<(&str, Vec<DocId>)> = vec![
+            ("timestamp:[1500000000 TO 1600000000]", vec![3, 4]),
+            ("timestamp:{1500000000 TO 1600000000}", vec![]),
+            ("timestamp:[-1600000000 TO 0}", vec![0, 1]),
+            ("timestamp:{-1600000000 TO 0]", vec![1, 2]),
+            ("timestamp:[* TO -1]", vec![0, 1]),
+            ("timestamp:[-100 TO *]", vec![1, 2, 3, 4]),
+            ("timestamp:[* TO *]", vec![0, 1, 2, 3, 4]),
+            ("price:{10 TO 100}", vec![2]),
+            ("price:[10 TO 100]", vec![1, 2, 3]),
+            ("name:[banana TO date}", vec![1, 2]),
+            ("name:{banana TO *]", vec![2, 3, 4]),
+            ("apple timestamp:[0 TO *]", vec![0, 2, 3, 4]),
+            ("+price:[* TO 100] -timestamp:[* TO -1]", vec![2, 3]),
+        ];
+        for &(query, ref expected_docs) in &queries {
+            assert_eq!(&docs(query), expected_docs, "{}", query);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_query_fast_field_range() {
+        use schema::{Cardinality, IntOptions, FAST};
+        let mut schema_builder = SchemaBuilder::default();
+        let timestamp = schema_builder.add_i64_field("timestamp", FAST);
+        schema_builder.add_u64_field("price", FAST);
+        let multivalued_options = IntOptions::default().set_fast(Cardinality::MultiValues);
+        schema_builder.add_u64_field("sizes", multivalued_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for &timestamp_val in &[-1_600_000_000i64, -100, 0, 1_500_000_000] {
+                index_writer
+                    .add_document(doc!(timestamp => timestamp_val))
+                    .unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        assert_eq!(
+            query_tree(&query_parser, "timestamp:[-5 TO 10}"),
+            "FastFieldRange(timestamp:[-5..10))\n"
+        );
+        assert_eq!(
+            query_tree(&query_parser, "price:{10 TO *]"),
+            "FastFieldRange(price:(10..*))\n"
+        );
+        let count = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            query.count(&*searcher).unwrap()
+        };
+        assert_eq!(count("timestamp:[-1600000000 TO 0}"), 2);
+        assert_eq!(count("timestamp:{-100 TO *]"), 2);
+        assert_eq!(
+            query_parser.parse_query("sizes:[1 TO 2]").unwrap_err(),
+            QueryParserError::FieldNotIndexed("sizes".to_string())
+        );
+    }
+
+    #[test]
+    pub fn test_exceeds_nesting_depth() {
+        assert!(!exceeds_nesting_depth("a b c", 0));
+        assert!(!exceeds_nesting_depth("(a b) (c d)", 1));
+        assert!(exceeds_nesting_depth("(a (b c))", 1));
+        assert!(!exceeds_nesting_depth("(a (b c))", 2));
+        assert!(exceeds_nesting_depth("+(-a)", 2));
+        assert!(!exceeds_nesting_depth("+(-a)", 3));
+        assert!(!exceeds_nesting_depth("+a -b +c", 1));
+        assert!(!exceeds_nesting_depth("title:\"((((a\"", 0));
+        assert!(!exceeds_nesting_depth("a NOT b c", 1));
+        assert!(exceeds_nesting_depth("a NOT b c", 0));
+        assert!(exceeds_nesting_depth("NOT  NOT a", 1));
+        assert!(!exceeds_nesting_depth("NOT  NOT a", 2));
+        assert!(!exceeds_nesting_depth("NOTE a", 0));
+        assert!(!exceeds_nesting_depth("\"NOT a\"", 0));
+    }
 }
@@ -1,24 +1,66 @@
 use std::fmt;
+use Score;
 
 pub struct UserInputLiteral {
     pub field_name: Option<String>,
     pub phrase: String,
+    // slop of a quoted phrase, e.g. `"a b"~2`.
+    pub slop: Option<u32>,
 }
 
 impl fmt::Debug for UserInputLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self.field_name {
-            Some(ref field_name) => write!(formatter, "{}:\"{}\"", field_name, self.phrase),
-            None => write!(formatter, "\"{}\"", self.phrase),
+            Some(ref field_name) => write!(formatter, "{}:\"{}\"", field_name, self.phrase)?,
+            None => write!(formatter, "\"{}\"", self.phrase)?,
+        }
+        if let Some(slop) = self.slop {
+            write!(formatter, "~{}", slop)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bound of a range, e.g. `[10` or `*}`.
+pub enum UserInputBound {
+    Inclusive(String),
+    Exclusive(String),
+    Unbounded,
+}
+
+impl UserInputBound {
+    fn fmt_lower(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            UserInputBound::Inclusive(ref word) => write!(formatter, "[\"{}\"", word),
+            UserInputBound::Exclusive(ref word) => write!(formatter, "{{\"{}\"", word),
+            UserInputBound::Unbounded => write!(formatter, "{{*"),
+        }
+    }
+
+    fn fmt_upper(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            UserInputBound::Inclusive(ref word) => write!(formatter, "\"{}\"]", word),
+            UserInputBound::Exclusive(ref word) => write!(formatter, "\"{}\"}}", word),
+            UserInputBound::Unbounded => write!(formatter, "*}}"),
         }
     }
 }
 
 pub enum UserInputAST {
     Clause(Vec<Box<UserInputAST>>),
+    // operands of the `AND` operator, e.g. `a AND b`.
+    And(Vec<Box<UserInputAST>>),
+    // operands of the `OR` operator, e.g. `a OR b`.
+    Or(Vec<Box<UserInputAST>>),
     Not(Box<UserInputAST>),
     Must(Box<UserInputAST>),
     Leaf(Box<UserInputLiteral>),
+    Range {
+        field_name: String,
+        lower: UserInputBound,
+        upper: UserInputBound,
+    },
+    Boost(Box<UserInputAST>, Score),
 }
 
 impl From<UserInputLiteral> for UserInputAST {
@@ -27,6 +69,18 @@ impl From<UserInputLiteral> for UserInputAST {
     }
 }
 
+fn fmt_operands(
+    formatter: &mut fmt::Formatter,
+    operands: &[Box<UserInputAST>],
+    operator: &str,
+) -> Result<(), fmt::Error> {
+    write!(formatter, "({:?}", &operands[0])?;
+    for operand in &operands[1..] {
+        write!(formatter, " {} {:?}", operator, operand)?;
+    }
+    write!(formatter, ")")
+}
+
 impl fmt::Debug for UserInputAST {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
@@ -44,8 +98,23 @@ impl fmt::Debug for UserInputAST {
                 }
                 Ok(())
             }
+            UserInputAST::And(ref operands) => fmt_operands(formatter, operands, "AND"),
+            UserInputAST::Or(ref operands) => fmt_operands(formatter, operands, "OR"),
             UserInputAST::Not(ref subquery) => write!(formatter, "-({:?})", subquery),
             UserInputAST::Leaf(ref subquery) => write!(formatter, "{:?}", subquery),
+            UserInputAST::Range {
+                ref field_name,
+                ref lower,
+                ref upper,
+            } => {
+                write!(formatter, "{}:", field_name)?;
+                lower.fmt_lower(formatter)?;
+                write!(formatter, " TO ")?;
+                upper.fmt_upper(formatter)
+            }
+            UserInputAST::Boost(ref subquery, boost) => {
+                write!(formatter, "({:?})^{}", subquery, boost)
+            }
         }
     }
 }
@@ -0,0 +1,222 @@
+use std::result;
+use schema::{Field, FieldEntry, IndexRecordOption, Schema};
+
+/// Possible error returned by [`Query::validate`](./trait.Query.html#method.validate).
+///
+/// These errors describe a mismatch between a query and the
+/// schema of the index it is run against. Running such a query
+/// would not fail, but would silently return empty or
+/// meaningless results.
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryValidationError {
+    /// `FieldDoesNotExist(field: Field)`
+    /// The query references a field that is not in the schema.
+    FieldDoesNotExist(Field),
+    /// `FieldNotIndexed(field_name: String)`
+    /// The field searched for is not declared as indexed in the schema.
+    FieldNotIndexed(String),
+    /// `InsufficientIndexRecordOption(field_name: String, required: IndexRecordOption,
+    /// indexed: IndexRecordOption)`
+    /// The query requires more information than what was indexed for the
+    /// field. For instance, a `PhraseQuery` requires positions.
+    InsufficientIndexRecordOption(String, IndexRecordOption, IndexRecordOption),
+    /// `IncompatibleFieldType(field_name: String, expected_type: String)`
+    /// The query values are not of the type of the field.
+    IncompatibleFieldType(String, String),
+    /// The query could not be prepared for the searcher.
+    /// The argument is a description of the underlying error.
+    InvalidQuery(String),
+}
+
+/// Returns the `FieldEntry` associated to the field, or
+/// an error if it does not belong to the schema.
+pub(crate) fn get_field_entry(
+    schema: &Schema,
+    field: Field,
+) -> result::Result<&FieldEntry, QueryValidationError> {
+    schema
+        .fields()
+        .get(field.0 as usize)
+        .ok_or(QueryValidationError::FieldDoesNotExist(field))
+}
+
+/// Checks that the field exists, is indexed, and that its
+/// `IndexRecordOption` covers the `required` one.
+pub(crate) fn validate_indexed_field(
+    schema: &Schema,
+    field: Field,
+    required: IndexRecordOption,
+) -> result::Result<(), QueryValidationError> {
+    let field_entry = get_field_entry(schema, field)?;
+    let field_name = field_entry.name().to_string();
+    match field_entry.field_type().get_index_record_option() {
+        None => Err(QueryValidationError::FieldNotIndexed(field_name)),
+        Some(indexed) if !indexed.covers(required) => Err(
+            QueryValidationError::InsufficientIndexRecordOption(field_name, required, indexed),
+        ),
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use Searcher;
+    use super::QueryValidationError;
+    use schema::{Facet, Field, IndexRecordOption, SchemaBuilder, Term, TextFieldIndexing,
+                 TextOptions, INT_INDEXED, STORED, STRING, TEXT};
+    use query::{AllQuery, BooleanQuery, Occur, PhraseQuery, Query, RangeQuery, TermQuery};
+
+    fn check<Q: Query + 'static>(searcher: &Searcher, query: Q) -> Result<(), QueryValidationError> {
+        searcher.check_query(&query)
+    }
+
+    #[test]
+    fn test_query_validation() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let freqs_only = schema_builder.add_text_field(
+            "freqs_only",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default().set_index_option(IndexRecordOption::WithFreqs),
+            ),
+        );
+        let positions_only = schema_builder.add_text_field(
+            "positions_only",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default().set_index_option(IndexRecordOption::WithPositions),
+            ),
+        );
+        let id = schema_builder.add_text_field("id", STRING);
+        let stored = schema_builder.add_text_field("stored", STORED);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let category = schema_builder.add_facet_field("category");
+        let index = Index::create_in_ram(schema_builder.build());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+
+        let term_query = |term: Term, option: IndexRecordOption| TermQuery::new(term, option);
+        let phrase_query = |field: Field| {
+            PhraseQuery::from(vec![
+                Term::from_field_text(field, "a"),
+                Term::from_field_text(field, "b"),
+            ])
+        };
+
+        // valid queries
+        assert!(check(&searcher, AllQuery).is_ok());
+        assert!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(text, "a"), IndexRecordOption::WithFreqs)
+            ).is_ok()
+        );
+        assert!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(id, "a"), IndexRecordOption::Basic)
+            ).is_ok()
+        );
+        assert!(check(&searcher, phrase_query(text)).is_ok());
+        assert!(check(&searcher, phrase_query(positions_only)).is_ok());
+        assert!(check(&searcher, RangeQuery::new_u64(year, 1960..1970)).is_ok());
+
+        // term query on a stored, non-indexed field.
+        assert_eq!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(stored, "a"), IndexRecordOption::Basic)
+            ),
+            Err(QueryValidationError::FieldNotIndexed("stored".to_string()))
+        );
+        // freq-based scoring on a field without freqs.
+        assert_eq!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(id, "a"), IndexRecordOption::WithFreqs)
+            ),
+            Err(QueryValidationError::InsufficientIndexRecordOption(
+                "id".to_string(),
+                IndexRecordOption::WithFreqs,
+                IndexRecordOption::Basic
+            ))
+        );
+        // freq-based scoring on a field with positions, but without freqs.
+        assert_eq!(
+            check(
+                &searcher,
+                term_query(
+                    Term::from_field_text(positions_only, "a"),
+                    IndexRecordOption::WithFreqs
+                )
+            ),
+            Err(QueryValidationError::InsufficientIndexRecordOption(
+                "positions_only".to_string(),
+                IndexRecordOption::WithFreqs,
+                IndexRecordOption::WithPositions
+            ))
+        );
+        // phrase query on a field without positions.
+        assert_eq!(
+            check(&searcher, phrase_query(freqs_only)),
+            Err(QueryValidationError::InsufficientIndexRecordOption(
+                "freqs_only".to_string(),
+                IndexRecordOption::WithPositions,
+                IndexRecordOption::WithFreqs
+            ))
+        );
+        // range query with a value type different from the field type.
+        assert_eq!(
+            check(&searcher, RangeQuery::new_i64(year, -3..3)),
+            Err(QueryValidationError::IncompatibleFieldType(
+                "year".to_string(),
+                "i64".to_string()
+            ))
+        );
+        assert_eq!(
+            check(&searcher, RangeQuery::new_u64(stored, 0..3)),
+            Err(QueryValidationError::IncompatibleFieldType(
+                "stored".to_string(),
+                "u64".to_string()
+            ))
+        );
+        // term query on a facet field, with the text of a facet path.
+        assert_eq!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(category, "/a/b"), IndexRecordOption::Basic)
+            ),
+            Err(QueryValidationError::IncompatibleFieldType(
+                "category".to_string(),
+                "facet".to_string()
+            ))
+        );
+        let facet_term = Term::from_facet(category, &Facet::from("/a/b"));
+        assert!(check(&searcher, term_query(facet_term, IndexRecordOption::Basic)).is_ok());
+        // field not in the schema.
+        assert_eq!(
+            check(
+                &searcher,
+                term_query(Term::from_field_text(Field(42), "a"), IndexRecordOption::Basic)
+            ),
+            Err(QueryValidationError::FieldDoesNotExist(Field(42)))
+        );
+        // boolean queries report the error of their subqueries.
+        let subqueries: Vec<(Occur, Box<Query>)> = vec![
+            (
+                Occur::Should,
+                box term_query(Term::from_field_text(text, "a"), IndexRecordOption::WithFreqs),
+            ),
+            (Occur::MustNot, box phrase_query(freqs_only)),
+        ];
+        assert_eq!(
+            check(&searcher, BooleanQuery::from(subqueries)),
+            Err(QueryValidationError::InsufficientIndexRecordOption(
+                "freqs_only".to_string(),
+                IndexRecordOption::WithPositions,
+                IndexRecordOption::WithFreqs
+            ))
+        );
+    }
+}
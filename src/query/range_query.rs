@@ -1,7 +1,9 @@
-use schema::{Field, IndexRecordOption, Term};
+use schema::{value_bytes_to_string, Field, FieldType, IndexRecordOption, Schema, Term};
 use query::{Query, Scorer, Weight};
 use termdict::{TermDictionary, TermStreamer, TermStreamerBuilder};
 use core::SegmentReader;
+use core::SegmentMeta;
+use byteorder::{BigEndian, ByteOrder};
 use common::BitSet;
 use Result;
 use core::Searcher;
@@ -9,6 +11,10 @@ use query::BitSetDocSet;
 use query::ConstScorer;
 use std::collections::Bound;
 use std::collections::range::RangeArgument;
+use std::result;
+use std::fmt;
+use query::QueryValidationError;
+use query::query_validation::{get_field_entry, validate_indexed_field};
 
 fn map_bound<TFrom, Transform: Fn(TFrom) -> Vec<u8>>(
     bound: Bound<TFrom>,
@@ -22,6 +28,42 @@ fn map_bound<TFrom, Transform: Fn(TFrom) -> Vec<u8>>(
     }
 }
 
+/// Type of the values of the range.
+#[derive(Clone, Copy, Debug)]
+enum RangeValueType {
+    U64,
+    I64,
+    Str,
+}
+
+fn decode_int_bound(bound: &Bound<Vec<u8>>) -> Bound<u64> {
+    use self::Bound::*;
+    match *bound {
+        Excluded(ref term_val) => Excluded(BigEndian::read_u64(term_val)),
+        Included(ref term_val) => Included(BigEndian::read_u64(term_val)),
+        Unbounded => Unbounded,
+    }
+}
+
+impl RangeValueType {
+    fn is_compatible(&self, field_type: &FieldType) -> bool {
+        match (*self, field_type) {
+            (RangeValueType::U64, &FieldType::U64(_))
+            | (RangeValueType::I64, &FieldType::I64(_))
+            | (RangeValueType::Str, &FieldType::Str(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            RangeValueType::U64 => "u64",
+            RangeValueType::I64 => "i64",
+            RangeValueType::Str => "str",
+        }
+    }
+}
+
 /// `RangeQuery` match all documents that have at least one term within a defined range.
 ///
 /// Matched document will all get a constant `Score` of one.
@@ -55,7 +97,7 @@ fn map_bound<TFrom, Transform: Fn(TFrom) -> Vec<u8>>(
 /// #         for year in 1950u64..2017u64 {
 /// #             let num_docs_within_year = 10 + (year - 1950) * (year - 1950);
 /// #             for _ in 0..num_docs_within_year {
-/// #                 index_writer.add_document(doc!(year_field => year));
+/// #                 index_writer.add_document(doc!(year_field => year))?;
 /// #             }
 /// #         }
 /// #         index_writer.commit().unwrap();
@@ -79,9 +121,10 @@ fn map_bound<TFrom, Transform: Fn(TFrom) -> Vec<u8>>(
 /// #   run().unwrap()
 /// # }
 /// ```
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RangeQuery {
     field: Field,
+    value_type: RangeValueType,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
 }
@@ -95,6 +138,7 @@ impl RangeQuery {
         let make_term_val = |val: &i64| Term::from_field_i64(field, *val).value_bytes().to_owned();
         RangeQuery {
             field,
+            value_type: RangeValueType::I64,
             left_bound: map_bound(range.start(), &make_term_val),
             right_bound: map_bound(range.end(), &make_term_val),
         }
@@ -108,6 +152,7 @@ impl RangeQuery {
         let make_term_val = |val: &u64| Term::from_field_u64(field, *val).value_bytes().to_owned();
         RangeQuery {
             field,
+            value_type: RangeValueType::U64,
             left_bound: map_bound(range.start(), &make_term_val),
             right_bound: map_bound(range.end(), &make_term_val),
         }
@@ -121,26 +166,93 @@ impl RangeQuery {
         let make_term_val = |val: &&str| val.as_bytes().to_vec();
         RangeQuery {
             field,
+            value_type: RangeValueType::Str,
             left_bound: map_bound(range.start(), &make_term_val),
             right_bound: map_bound(range.end(), &make_term_val),
         }
     }
+
+    /// Create a new `RangeQuery` whose bounds are terms of the field.
+    ///
+    /// The type of the values of the range is the type of the field,
+    /// `field_type`. Fields that are neither `u64` nor `i64` fields are
+    /// considered as `Str` fields.
+    pub(crate) fn new_term_bounds(
+        field_type: &FieldType,
+        field: Field,
+        left_bound: Bound<Term>,
+        right_bound: Bound<Term>,
+    ) -> RangeQuery {
+        let value_type = match *field_type {
+            FieldType::U64(_) => RangeValueType::U64,
+            FieldType::I64(_) => RangeValueType::I64,
+            _ => RangeValueType::Str,
+        };
+        let make_term_val = |term: Term| term.value_bytes().to_owned();
+        RangeQuery {
+            field,
+            value_type,
+            left_bound: map_bound(left_bound, &make_term_val),
+            right_bound: map_bound(right_bound, &make_term_val),
+        }
+    }
 }
 
 impl Query for RangeQuery {
     fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        let int_bounds = match self.value_type {
+            RangeValueType::U64 | RangeValueType::I64 => Some((
+                decode_int_bound(&self.left_bound),
+                decode_int_bound(&self.right_bound),
+            )),
+            RangeValueType::Str => None,
+        };
         Ok(box RangeWeight {
             field: self.field,
             left_bound: self.left_bound.clone(),
             right_bound: self.right_bound.clone(),
+            int_bounds,
         })
     }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        let field_entry = get_field_entry(searcher.schema(), self.field)?;
+        if !self.value_type.is_compatible(field_entry.field_type()) {
+            return Err(QueryValidationError::IncompatibleFieldType(
+                field_entry.name().to_string(),
+                self.value_type.name().to_string(),
+            ));
+        }
+        validate_indexed_field(searcher.schema(), self.field, IndexRecordOption::Basic)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::collections::Bound::*;
+        let field_entry = schema.get_field_entry(self.field);
+        let field_type = field_entry.field_type();
+        write!(f, "Range({}:", field_entry.name())?;
+        match self.left_bound {
+            Included(ref term_val) => write!(f, "[{}", value_bytes_to_string(field_type, term_val))?,
+            Excluded(ref term_val) => write!(f, "({}", value_bytes_to_string(field_type, term_val))?,
+            Unbounded => write!(f, "(*")?,
+        }
+        write!(f, "..")?;
+        match self.right_bound {
+            Included(ref term_val) => write!(f, "{}]", value_bytes_to_string(field_type, term_val))?,
+            Excluded(ref term_val) => write!(f, "{})", value_bytes_to_string(field_type, term_val))?,
+            Unbounded => write!(f, "*)")?,
+        }
+        writeln!(f, ")")
+    }
 }
 
 pub struct RangeWeight {
     field: Field,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
+    // bounds of the range in the `u64` representation
+    // of the values, for `u64` and `i64` fields.
+    int_bounds: Option<(Bound<u64>, Bound<u64>)>,
 }
 
 impl RangeWeight {
@@ -165,6 +277,35 @@ impl RangeWeight {
 }
 
 impl Weight for RangeWeight {
+    fn can_match(&self, segment_meta: &SegmentMeta) -> bool {
+        use std::collections::Bound::*;
+        let (left_bound, right_bound) = match self.int_bounds {
+            Some(int_bounds) => int_bounds,
+            None => {
+                return true;
+            }
+        };
+        let (min_value, max_value) = match segment_meta.int_value_range(self.field) {
+            Some(int_value_range) => int_value_range,
+            None => {
+                // either the segment does not contain any value
+                // or it predates the recording of the value ranges.
+                return true;
+            }
+        };
+        let above_left_bound = match left_bound {
+            Included(left_val) => max_value >= left_val,
+            Excluded(left_val) => max_value > left_val,
+            Unbounded => true,
+        };
+        let below_right_bound = match right_bound {
+            Included(right_val) => min_value <= right_val,
+            Excluded(right_val) => min_value < right_val,
+            Unbounded => true,
+        };
+        above_left_bound && below_right_bound
+    }
+
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
         let max_doc = reader.max_doc();
         let mut doc_bitset = BitSet::with_max_value(max_doc);
@@ -178,7 +319,9 @@ impl Weight for RangeWeight {
                 .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
             while block_segment_postings.advance() {
                 for &doc in block_segment_postings.docs() {
-                    doc_bitset.insert(doc);
+                    if !reader.is_deleted(doc) {
+                        doc_bitset.insert(doc);
+                    }
                 }
             }
         }
@@ -191,10 +334,14 @@ impl Weight for RangeWeight {
 mod tests {
 
     use Index;
-    use schema::{Document, Field, SchemaBuilder, INT_INDEXED};
+    use schema::{Document, Field, IndexRecordOption, SchemaBuilder, Term, FAST, INT_INDEXED,
+                 TEXT};
     use collector::CountCollector;
     use std::collections::Bound;
-    use query::Query;
+    use std::collections::Bound::Excluded;
+    use std::ops::Range;
+    use query::{BooleanQuery, Occur, Query, TermQuery};
+    use indexer::NoMergePolicy;
     use Result;
     use super::RangeQuery;
 
@@ -211,7 +358,7 @@ mod tests {
                 for year in 1950u64..2017u64 {
                     let num_docs_within_year = 10 + (year - 1950) * (year - 1950);
                     for _ in 0..num_docs_within_year {
-                        index_writer.add_document(doc!(year_field => year));
+                        index_writer.add_document(doc!(year_field => year)).unwrap();
                     }
                 }
                 index_writer.commit().unwrap();
@@ -251,7 +398,7 @@ mod tests {
                         doc.add_i64(int_field, j as i64);
                     }
                 }
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
 
             index_writer.commit().unwrap();
@@ -284,4 +431,102 @@ mod tests {
         assert_eq!(count_multiples(RangeQuery::new_i64(int_field, 9..)), 91);
     }
 
+    #[test]
+    fn test_range_query_ignores_deleted_docs() {
+        let mut schema_builder = SchemaBuilder::new();
+        let year_field = schema_builder.add_u64_field("year", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 6_000_000).unwrap();
+            for year in 1960u64..1970u64 {
+                index_writer.add_document(doc!(year_field => year)).unwrap();
+            }
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_u64(year_field, 1965u64));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = RangeQuery::new_u64(year_field, 1962u64..1968u64);
+        let mut count_collector = CountCollector::default();
+        query.search(&*searcher, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 5);
+    }
+
+    #[test]
+    fn test_range_query_skips_segments() {
+        let mut schema_builder = SchemaBuilder::new();
+        let timestamp_field = schema_builder.add_u64_field("timestamp", INT_INDEXED | FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 6_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment_start in &[0u64, 1_000u64, 2_000u64] {
+                for timestamp in *segment_start..*segment_start + 100 {
+                    index_writer.add_document(doc!(
+                        timestamp_field => timestamp,
+                        text_field => "event"
+                    )).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+
+        let mut int_value_ranges: Vec<Option<(u64, u64)>> = index
+            .searchable_segment_metas()
+            .unwrap()
+            .iter()
+            .map(|segment_meta| segment_meta.int_value_range(timestamp_field))
+            .collect();
+        int_value_ranges.sort();
+        assert_eq!(
+            int_value_ranges,
+            vec![Some((0, 99)), Some((1_000, 1_099)), Some((2_000, 2_099))]
+        );
+
+        let num_open_inverted_indexes = |query: &Query, expected_count: usize| {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let mut count_collector = CountCollector::default();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            assert_eq!(count_collector.count(), expected_count);
+            assert_eq!(query.count(&*searcher).unwrap(), expected_count);
+            searcher
+                .segment_readers()
+                .iter()
+                .filter(|segment_reader| segment_reader.is_inverted_index_open(timestamp_field))
+                .count()
+        };
+
+        let range_query = RangeQuery::new_u64(timestamp_field, 1_050..1_150);
+        assert_eq!(num_open_inverted_indexes(&range_query, 50), 1);
+        let range_query = RangeQuery::new_u64(timestamp_field, 99..1_000);
+        assert_eq!(num_open_inverted_indexes(&range_query, 1), 1);
+        let range_query = RangeQuery::new_u64(timestamp_field, (Excluded(99), Excluded(1_000)));
+        assert_eq!(num_open_inverted_indexes(&range_query, 0), 0);
+        let range_query = RangeQuery::new_u64(timestamp_field, 50..2_050);
+        assert_eq!(num_open_inverted_indexes(&range_query, 2_000), 3);
+
+        let make_range_query = |range: Range<u64>| -> Box<Query> {
+            box RangeQuery::new_u64(timestamp_field, range)
+        };
+        let term_query: Box<Query> = box TermQuery::new(
+            Term::from_field_text(text_field, "event"),
+            IndexRecordOption::Basic,
+        );
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, make_range_query(2_000..2_010)),
+            (Occur::Must, term_query),
+        ]);
+        assert_eq!(num_open_inverted_indexes(&boolean_query, 10), 1);
+
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Should, make_range_query(0..10)),
+            (Occur::Should, make_range_query(2_000..2_010)),
+        ]);
+        assert_eq!(num_open_inverted_indexes(&boolean_query, 20), 2);
+    }
 }
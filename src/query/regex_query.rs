@@ -0,0 +1,355 @@
+use schema::{Field, FieldType, IndexRecordOption, Schema};
+use query::{Query, QueryParserError, Scorer, Weight};
+use termdict::{TermDictionary, TermStreamer, TermStreamerBuilder};
+use core::SegmentReader;
+use common::BitSet;
+use Result;
+use core::Searcher;
+use query::BitSetDocSet;
+use query::ConstScorer;
+use regex::bytes::Regex;
+use fst::Regex as RegexAutomaton;
+use postings::TermInfo;
+use std::result;
+use std::fmt;
+use query::QueryValidationError;
+use query::query_validation::{get_field_entry, validate_indexed_field};
+
+/// Returns the literal prefix of a pattern, that all of
+/// the terms it matches start with.
+///
+/// For instance, all of the terms matching `error_[0-9]+`
+/// start with `error_`. The prefix may be shorter than the
+/// actual literal prefix, down to an empty string.
+fn literal_prefix(pattern: &str) -> String {
+    // an alternation may not share the prefix of its first branch.
+    if pattern.contains('|') {
+        return String::new();
+    }
+    let mut prefix = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if "\\.+*?()[]{}^$".contains(c) {
+            break;
+        }
+        // the character is optional.
+        if let Some(&'?') | Some(&'*') | Some(&'{') = chars.peek() {
+            break;
+        }
+        prefix.push(c);
+    }
+    prefix
+}
+
+/// A Regex Query matches all of the documents
+/// containing a term that matches a regular expression.
+///
+/// The regular expression must match the whole term,
+/// e.g. `error_[0-9]+` matches `error_404` but not `my_error_404`.
+/// It uses the syntax of the [`regex`](https://docs.rs/regex) crate,
+/// and is matched against the terms as they were indexed,
+/// so it is typically used with fields using the `raw` tokenizer.
+///
+/// The regular expression is compiled into an automaton that drives
+/// the traversal of the term dictionary of each segment: the terms
+/// starting with a prefix that cannot match are never visited.
+/// Patterns that cannot be compiled into an automaton, e.g. patterns
+/// with word boundaries, are matched against the terms starting with
+/// the literal prefix of the pattern, if it has one.
+///
+/// All of the matching documents get a score of 1.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::collector::CountCollector;
+/// use tantivy::query::RegexQuery;
+/// use tantivy::schema::{SchemaBuilder, STRING};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let code = schema_builder.add_text_field("code", STRING);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(code => "error_404"))?;
+///     index_writer.add_document(doc!(code => "error_500"))?;
+///     index_writer.add_document(doc!(code => "error_unknown"))?;
+///     index_writer.add_document(doc!(code => "warning_12"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let query = RegexQuery::new(code, "error_[0-9]+")?;
+/// let mut count_collector = CountCollector::default();
+/// searcher.search(&query, &mut count_collector)?;
+/// assert_eq!(count_collector.count(), 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RegexQuery {
+    field: Field,
+    pattern: String,
+    regex: Regex,
+    prefix: String,
+}
+
+impl RegexQuery {
+    /// Creates a `RegexQuery` matching the terms of `field`
+    /// that match the regular expression `pattern`.
+    ///
+    /// # Errors
+    /// Returns `QueryParserError::InvalidRegex` if the pattern
+    /// is not a valid regular expression.
+    pub fn new(field: Field, pattern: &str) -> result::Result<RegexQuery, QueryParserError> {
+        let regex = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|err| QueryParserError::InvalidRegex(err.to_string()))?;
+        Ok(RegexQuery {
+            field,
+            pattern: pattern.to_string(),
+            regex,
+            prefix: literal_prefix(pattern),
+        })
+    }
+
+    /// Returns the regular expression of the query.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl Query for RegexQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(box RegexWeight {
+            field: self.field,
+            automaton: regex_automaton(&self.pattern),
+            regex: self.regex.clone(),
+            prefix: self.prefix.clone(),
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        let field_entry = get_field_entry(searcher.schema(), self.field)?;
+        if let FieldType::Str(_) = *field_entry.field_type() {
+            validate_indexed_field(searcher.schema(), self.field, IndexRecordOption::Basic)
+        } else {
+            Err(QueryValidationError::IncompatibleFieldType(
+                field_entry.name().to_string(),
+                "str".to_string(),
+            ))
+        }
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        let field_entry = schema.get_field_entry(self.field);
+        writeln!(f, "Regex({}:/{}/)", field_entry.name(), self.pattern)
+    }
+}
+
+/// Compiles the pattern into an automaton accepting the terms
+/// it matches, if the pattern is supported by the automata of `fst`.
+fn regex_automaton(pattern: &str) -> Option<RegexAutomaton> {
+    RegexAutomaton::new(pattern).ok()
+}
+
+pub struct RegexWeight {
+    field: Field,
+    automaton: Option<RegexAutomaton>,
+    regex: Regex,
+    prefix: String,
+}
+
+impl Weight for RegexWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        {
+            let mut add_term_docs = |term_info: &TermInfo| {
+                let mut block_segment_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+                while block_segment_postings.advance() {
+                    for &doc in block_segment_postings.docs() {
+                        if !reader.is_deleted(doc) {
+                            doc_bitset.insert(doc);
+                        }
+                    }
+                }
+            };
+            if let Some(ref automaton) = self.automaton {
+                let mut term_stream = term_dict.search(automaton).into_stream();
+                while term_stream.advance() {
+                    add_term_docs(term_stream.value());
+                }
+            } else {
+                let prefix = self.prefix.as_bytes();
+                let mut term_stream = term_dict.range().ge(prefix).into_stream();
+                while term_stream.advance() {
+                    if !term_stream.key().starts_with(prefix) {
+                        break;
+                    }
+                    if self.regex.is_match(term_stream.key()) {
+                        add_term_docs(term_stream.value());
+                    }
+                }
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(box ConstScorer::new(doc_bitset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, Term, STRING, TEXT};
+    use collector::CountCollector;
+    use core::Searcher;
+    use query::{Query, QueryDisplay, QueryParserError, QueryValidationError};
+    use indexer::NoMergePolicy;
+    use super::{literal_prefix, regex_automaton, RegexQuery};
+    use fst::Automaton;
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("error_[0-9]+"), "error_");
+        assert_eq!(literal_prefix("error"), "error");
+        assert_eq!(literal_prefix("errors?"), "error");
+        assert_eq!(literal_prefix("errors*"), "error");
+        assert_eq!(literal_prefix("errors{2}"), "error");
+        assert_eq!(literal_prefix("errors+"), "errors");
+        assert_eq!(literal_prefix("error\\.log"), "error");
+        assert_eq!(literal_prefix("error|warning"), "");
+        assert_eq!(literal_prefix("(?i)error"), "");
+        assert_eq!(literal_prefix(".*error"), "");
+    }
+
+    #[test]
+    fn test_regex_automaton() {
+        fn state_after<A: Automaton>(automaton: &A, key: &str) -> A::State {
+            key.bytes()
+                .fold(automaton.start(), |state, byte| automaton.accept(&state, byte))
+        }
+        let automaton = regex_automaton("error_[0-9]+").unwrap();
+        assert!(automaton.is_match(&state_after(&automaton, "error_404")));
+        assert!(!automaton.is_match(&state_after(&automaton, "error_")));
+        // the terms starting with `w` are pruned from the traversal.
+        assert!(!automaton.can_match(&state_after(&automaton, "w")));
+        assert!(automaton.can_match(&state_after(&automaton, "err")));
+        let automaton = regex_automaton(".*_2").unwrap();
+        assert!(automaton.can_match(&state_after(&automaton, "warn")));
+        // word boundaries are not supported by the automata.
+        assert!(regex_automaton("\\berror").is_none());
+    }
+
+    #[test]
+    fn test_regex_query() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            // the segments have different terms, hence different term ordinals.
+            let segments: [&[&str]; 3] = [
+                &["error_1", "error_12", "info_3", "error_x"],
+                &["aaa", "error_7", "warn_2", "error_"],
+                &["zzz_error_5", "error_99", "error_7"],
+            ];
+            for segment_codes in &segments {
+                for segment_code in segment_codes.iter() {
+                    index_writer
+                        .add_document(doc!(code => *segment_code, text => "error_1"))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let count = |searcher: &Searcher, pattern: &str| {
+            let query = RegexQuery::new(code, pattern).unwrap();
+            assert!(searcher.check_query(&query).is_ok());
+            let mut count_collector = CountCollector::default();
+            query.search(searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count(&*searcher, "error_[0-9]+"), 5);
+        assert_eq!(count(&*searcher, "error_(1|7)"), 3);
+        assert_eq!(count(&*searcher, ".*_2"), 1);
+        assert_eq!(count(&*searcher, "error_.*"), 7);
+        assert_eq!(count(&*searcher, ".*error.*"), 8);
+        assert_eq!(count(&*searcher, "error"), 0);
+        assert_eq!(count(&*searcher, "nomatch.*"), 0);
+        assert_eq!(count(&*searcher, "zzzz.*"), 0);
+        // matched without automaton.
+        assert_eq!(count(&*searcher, "\\berror_7"), 2);
+
+        // the pattern is matched against the indexed terms.
+        let query = RegexQuery::new(text, "error_[0-9]+").unwrap();
+        let mut count_collector = CountCollector::default();
+        query.search(&*searcher, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 0);
+    }
+
+    #[test]
+    fn test_regex_query_errors() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let num = schema_builder.add_u64_field("num", ::schema::INT_INDEXED);
+        let schema = schema_builder.build();
+        match RegexQuery::new(code, "error_[") {
+            Err(QueryParserError::InvalidRegex(_)) => {}
+            _ => panic!("The pattern should be invalid"),
+        }
+        let index = Index::create_in_ram(schema.clone());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(
+            searcher.check_query(&RegexQuery::new(num, "1.*").unwrap()),
+            Err(QueryValidationError::IncompatibleFieldType(
+                "num".to_string(),
+                "str".to_string()
+            ))
+        );
+        let query = RegexQuery::new(code, "error_[0-9]+").unwrap();
+        assert_eq!(query.pattern(), "error_[0-9]+");
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&query, &schema)),
+            "Regex(code:/error_[0-9]+/)\n"
+        );
+    }
+
+    #[test]
+    fn test_regex_query_ignores_deleted_docs() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for segment_code in &["error_1", "error_2", "info_3"] {
+                index_writer.add_document(doc!(code => *segment_code)).unwrap();
+            }
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(code, "error_1"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = RegexQuery::new(code, "error_[0-9]").unwrap();
+        let mut count_collector = CountCollector::default();
+        query.search(&*searcher, &mut count_collector).unwrap();
+        assert_eq!(count_collector.count(), 1);
+    }
+}
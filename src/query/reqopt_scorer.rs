@@ -40,6 +40,17 @@ where
             _phantom: PhantomData,
         }
     }
+
+    /// Consumes the scorer, and returns its required
+    /// and optional scorers.
+    ///
+    /// This is the two-`DocSet` analogue of
+    /// [`DocSetGroup::into_docsets`](../trait.DocSetGroup.html#tymethod.into_docsets).
+    /// The required scorer is positioned on the current document,
+    /// and the optional scorer on or after it, or exhausted.
+    pub fn into_docsets(self) -> (TReqScorer, TOptScorer) {
+        (self.req_scorer, self.opt_scorer)
+    }
 }
 
 impl<TReqScorer, TOptScorer, TScoreCombiner> DocSet
@@ -60,6 +71,10 @@ where
     fn size_hint(&self) -> u32 {
         self.req_scorer.size_hint()
     }
+
+    fn count_estimate(&self) -> u32 {
+        self.req_scorer.count_estimate()
+    }
 }
 
 impl<TReqScorer, TOptScorer, TScoreCombiner> Scorer
@@ -135,42 +150,42 @@ mod tests {
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 1);
-            assert_eq!(reqoptscorer.score(), 2f32);
+            assert_eq!(reqoptscorer.score(), 2.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 3);
-            assert_eq!(reqoptscorer.score(), 1f32);
+            assert_eq!(reqoptscorer.score(), 1.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 7);
-            assert_eq!(reqoptscorer.score(), 2f32);
+            assert_eq!(reqoptscorer.score(), 2.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 8);
-            assert_eq!(reqoptscorer.score(), 1f32);
+            assert_eq!(reqoptscorer.score(), 1.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 9);
-            assert_eq!(reqoptscorer.score(), 1f32);
+            assert_eq!(reqoptscorer.score(), 1.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 10);
-            assert_eq!(reqoptscorer.score(), 1f32);
+            assert_eq!(reqoptscorer.score(), 1.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 13);
-            assert_eq!(reqoptscorer.score(), 1f32);
+            assert_eq!(reqoptscorer.score(), 1.0);
         }
         {
             assert!(reqoptscorer.advance());
             assert_eq!(reqoptscorer.doc(), 15);
-            assert_eq!(reqoptscorer.score(), 2f32);
+            assert_eq!(reqoptscorer.score(), 2.0);
         }
         assert!(!reqoptscorer.advance());
     }
@@ -1,13 +1,50 @@
 use Score;
 use query::Scorer;
 
+/// Replaces a NaN score, that would otherwise silently poison
+/// the sums of the combiners and the ordering of the collected documents.
+///
+/// A NaN score is a bug (typically a division by zero in the scoring
+/// function). In debug mode, it triggers a panic naming its origin.
+/// In release mode, it is replaced by `0`.
+#[inline]
+pub(crate) fn sanitize_score(score: Score, origin: &str) -> Score {
+    if score.is_nan() {
+        nan_score(origin)
+    } else {
+        score
+    }
+}
+
+#[cold]
+fn nan_score(origin: &str) -> Score {
+    if cfg!(debug_assertions) {
+        panic!("NaN score returned by {}", origin);
+    }
+    0.0
+}
+
+/// Returns the score of a scorer, checking it is not NaN.
+///
+/// See `sanitize_score`. The panic names the scorer
+/// (see [`Scorer::name`](./trait.Scorer.html#method.name)).
+#[inline]
+pub(crate) fn checked_score<TScorer: Scorer>(scorer: &mut TScorer) -> Score {
+    let score = scorer.score();
+    if score.is_nan() {
+        nan_score(scorer.name())
+    } else {
+        score
+    }
+}
+
 /// The `ScoreCombiner` trait defines how to compute
 /// an overall score given a list of scores.
 pub trait ScoreCombiner: Default + Clone + Copy + 'static {
     /// Aggregates the score combiner with the given scorer.
     ///
     /// The `ScoreCombiner` may decide to call `.scorer.score()`
-    /// or not.
+    /// or not. Scores are expected not to be NaN (see `checked_score`).
     fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer);
 
     /// Clears the score combiner state back to its initial state.
@@ -31,7 +68,7 @@ impl ScoreCombiner for DoNothingCombiner {
     fn clear(&mut self) {}
 
     fn score(&self) -> Score {
-        1f32
+        1.0
     }
 }
 
@@ -43,11 +80,11 @@ pub struct SumCombiner {
 
 impl ScoreCombiner for SumCombiner {
     fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
-        self.score += scorer.score();
+        self.score += checked_score(scorer);
     }
 
     fn clear(&mut self) {
-        self.score = 0f32;
+        self.score = 0.0;
     }
 
     fn score(&self) -> Score {
@@ -65,12 +102,12 @@ pub struct SumWithCoordsCombiner {
 
 impl ScoreCombiner for SumWithCoordsCombiner {
     fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
-        self.score += scorer.score();
+        self.score += checked_score(scorer);
         self.num_fields += 1;
     }
 
     fn clear(&mut self) {
-        self.score = 0f32;
+        self.score = 0.0;
         self.num_fields = 0;
     }
 
@@ -78,3 +115,221 @@ impl ScoreCombiner for SumWithCoordsCombiner {
         self.score
     }
 }
+
+/// Combines the scores with a `TScoreCombiner`, and keeps the count
+/// of scorers which matched.
+///
+/// Unlike `SumWithCoordsCombiner`, the count is available, e.g. to
+/// enforce a minimum number of matching `Should` clauses.
+#[derive(Default, Clone, Copy)]
+pub struct CountingCombiner<TScoreCombiner> {
+    score_combiner: TScoreCombiner,
+    num_matches: usize,
+}
+
+impl<TScoreCombiner: ScoreCombiner> CountingCombiner<TScoreCombiner> {
+    /// Returns the number of scorers the combiner was updated with
+    /// since it was last cleared.
+    pub fn num_matches(&self) -> usize {
+        self.num_matches
+    }
+}
+
+impl<TScoreCombiner: ScoreCombiner> ScoreCombiner for CountingCombiner<TScoreCombiner> {
+    fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
+        self.score_combiner.update(scorer);
+        self.num_matches += 1;
+    }
+
+    fn clear(&mut self) {
+        self.score_combiner.clear();
+        self.num_matches = 0;
+    }
+
+    fn score(&self) -> Score {
+        self.score_combiner.score()
+    }
+}
+
+/// Takes the maximum of the scores of different scorers,
+/// plus the sum of the other scores multiplied by a tie breaker.
+///
+/// The scores are expected to be non-negative.
+#[derive(Default, Clone, Copy)]
+pub struct DisjunctionMaxCombiner {
+    tie_breaker: Score,
+    max: Score,
+    sum: Score,
+}
+
+impl DisjunctionMaxCombiner {
+    /// Creates a `DisjunctionMaxCombiner` with the given tie breaker.
+    ///
+    /// The default `DisjunctionMaxCombiner` has a tie breaker of `0`,
+    /// and only keeps the maximum score.
+    pub fn with_tie_breaker(tie_breaker: Score) -> DisjunctionMaxCombiner {
+        DisjunctionMaxCombiner {
+            tie_breaker,
+            max: 0.0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl ScoreCombiner for DisjunctionMaxCombiner {
+    fn update<TScorer: Scorer>(&mut self, scorer: &mut TScorer) {
+        let score = checked_score(scorer);
+        if score > self.max {
+            self.max = score;
+        }
+        self.sum += score;
+    }
+
+    /// Clears the scores, but keeps the tie breaker.
+    fn clear(&mut self) {
+        self.max = 0.0;
+        self.sum = 0.0;
+    }
+
+    fn score(&self) -> Score {
+        self.max + self.tie_breaker * (self.sum - self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use DocId;
+    use Score;
+    use docset::DocSet;
+    use query::Scorer;
+    use super::{CountingCombiner, DisjunctionMaxCombiner, DoNothingCombiner, ScoreCombiner,
+                SumCombiner, SumWithCoordsCombiner};
+
+    /// Scorer dividing by a null field norm.
+    struct NaNScorer {
+        field_norm: Score,
+    }
+
+    impl DocSet for NaNScorer {
+        fn advance(&mut self) -> bool {
+            false
+        }
+
+        fn doc(&self) -> DocId {
+            0
+        }
+
+        fn size_hint(&self) -> u32 {
+            0
+        }
+    }
+
+    impl Scorer for NaNScorer {
+        fn score(&mut self) -> Score {
+            self.field_norm / self.field_norm
+        }
+
+        fn name(&self) -> &'static str {
+            "NaNScorer"
+        }
+    }
+
+    fn combine<TScoreCombiner: ScoreCombiner>() -> Score {
+        let mut score_combiner = TScoreCombiner::default();
+        score_combiner.update(&mut NaNScorer { field_norm: 0.0 });
+        score_combiner.update(&mut NaNScorer { field_norm: 0.0 });
+        score_combiner.score()
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN score returned by NaNScorer")]
+    fn test_sum_combiner_nan() {
+        combine::<SumCombiner>();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN score returned by NaNScorer")]
+    fn test_boxed_scorer_nan() {
+        let mut scorer: Box<Scorer> = box NaNScorer { field_norm: 0.0 };
+        let mut score_combiner = DisjunctionMaxCombiner::default();
+        score_combiner.update(&mut scorer);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "NaN score returned by NaNScorer")]
+    fn test_sum_with_coords_combiner_nan() {
+        combine::<SumWithCoordsCombiner>();
+    }
+
+    /// Scorer returning a constant score.
+    struct ConstantScorer(Score);
+
+    impl DocSet for ConstantScorer {
+        fn advance(&mut self) -> bool {
+            false
+        }
+
+        fn doc(&self) -> DocId {
+            0
+        }
+
+        fn size_hint(&self) -> u32 {
+            0
+        }
+    }
+
+    impl Scorer for ConstantScorer {
+        fn score(&mut self) -> Score {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_disjunction_max_combiner() {
+        let mut score_combiner = DisjunctionMaxCombiner::with_tie_breaker(0.5);
+        score_combiner.update(&mut ConstantScorer(1.0));
+        assert_eq!(score_combiner.score(), 1.0);
+        score_combiner.update(&mut ConstantScorer(4.0));
+        score_combiner.update(&mut ConstantScorer(2.0));
+        assert_eq!(score_combiner.score(), 4.0 + 0.5 * (1.0 + 2.0));
+        score_combiner.clear();
+        score_combiner.update(&mut ConstantScorer(3.0));
+        assert_eq!(score_combiner.score(), 3.0);
+        score_combiner.update(&mut ConstantScorer(1.0));
+        assert_eq!(score_combiner.score(), 3.5);
+
+        let mut score_combiner = DisjunctionMaxCombiner::default();
+        score_combiner.update(&mut ConstantScorer(1.0));
+        score_combiner.update(&mut ConstantScorer(4.0));
+        assert_eq!(score_combiner.score(), 4.0);
+    }
+
+    #[test]
+    fn test_counting_combiner() {
+        let mut score_combiner = CountingCombiner::<SumCombiner>::default();
+        assert_eq!(score_combiner.num_matches(), 0);
+        score_combiner.update(&mut ConstantScorer(1.0));
+        score_combiner.update(&mut ConstantScorer(2.5));
+        assert_eq!(score_combiner.num_matches(), 2);
+        assert_eq!(score_combiner.score(), 3.5);
+        score_combiner.clear();
+        assert_eq!(score_combiner.num_matches(), 0);
+        assert_eq!(score_combiner.score(), 0.0);
+
+        let mut score_combiner = CountingCombiner::<DoNothingCombiner>::default();
+        score_combiner.update(&mut ConstantScorer(2.0));
+        assert_eq!(score_combiner.num_matches(), 1);
+        assert_eq!(score_combiner.score(), 1.0);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_combiner_nan_sanitized() {
+        assert_eq!(combine::<SumCombiner>(), 0.0);
+        assert_eq!(combine::<SumWithCoordsCombiner>(), 0.0);
+    }
+}
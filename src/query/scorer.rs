@@ -1,13 +1,21 @@
 use DocId;
 use Score;
 use collector::Collector;
-use docset::{DocSet, SkipResult};
+use docset::{DocSet, DocSetGroup, SkipResult};
 use common::BitSet;
-use std::ops::DerefMut;
+use std::ops::{Deref, DerefMut};
 use downcast;
+use query::TwoPhase;
+
+/// Number of `DocId`s fetched at once when collecting
+/// documents without scores.
+const COLLECT_BUFFER_LEN: usize = 128;
 
 /// Scored set of documents matching a query within a specific segment.
 ///
+/// Like a `Weight`, a `Scorer` is created for a single search,
+/// and is not required to be `Send` or `Sync`.
+///
 /// See [`Query`](./trait.Query.html).
 pub trait Scorer: downcast::Any + DocSet + 'static {
     /// Returns the score.
@@ -15,6 +23,20 @@ pub trait Scorer: downcast::Any + DocSet + 'static {
     /// This method will perform a bit of computation and is not cached.
     fn score(&mut self) -> Score;
 
+    /// Returns the name of the scorer, used to report
+    /// the scores it should not have returned, like NaN.
+    fn name(&self) -> &'static str {
+        "an unnamed scorer"
+    }
+
+    /// Returns the two-phase iteration of the scorer,
+    /// if it supports it.
+    ///
+    /// See [`TwoPhase`](./trait.TwoPhase.html).
+    fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+        None
+    }
+
     /// Consumes the complete `DocSet` and
     /// push the scored documents to the collector.
     fn collect(&mut self, collector: &mut Collector) {
@@ -22,6 +44,25 @@ pub trait Scorer: downcast::Any + DocSet + 'static {
             collector.collect(self.doc(), self.score());
         }
     }
+
+    /// Consumes the complete `DocSet` and
+    /// push the documents to the collector, without scoring them.
+    ///
+    /// Documents are fetched by batches using `.fill_buffer(...)`,
+    /// and pushed to the collector with a score of `1`.
+    /// This is used when the collector does not require scoring.
+    fn collect_doc_ids(&mut self, collector: &mut Collector) {
+        let mut buffer = [0u32; COLLECT_BUFFER_LEN];
+        loop {
+            let num_docs = self.fill_buffer(&mut buffer[..]);
+            for &doc in &buffer[..num_docs] {
+                collector.collect(doc, 1.0);
+            }
+            if num_docs < COLLECT_BUFFER_LEN {
+                return;
+            }
+        }
+    }
 }
 
 #[allow(missing_docs)]
@@ -34,10 +75,23 @@ impl Scorer for Box<Scorer> {
         self.deref_mut().score()
     }
 
+    fn name(&self) -> &'static str {
+        self.deref().name()
+    }
+
+    fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+        self.deref_mut().two_phase()
+    }
+
     fn collect(&mut self, collector: &mut Collector) {
         let scorer = self.deref_mut();
         scorer.collect(collector);
     }
+
+    fn collect_doc_ids(&mut self, collector: &mut Collector) {
+        let scorer = self.deref_mut();
+        scorer.collect_doc_ids(collector);
+    }
 }
 
 /// `EmptyScorer` is a dummy `Scorer` in which no document matches.
@@ -64,7 +118,11 @@ impl DocSet for EmptyScorer {
 
 impl Scorer for EmptyScorer {
     fn score(&mut self) -> Score {
-        0f32
+        0.0
+    }
+
+    fn name(&self) -> &'static str {
+        "EmptyScorer"
     }
 }
 
@@ -84,7 +142,7 @@ impl<TDocSet: DocSet> ConstScorer<TDocSet> {
     pub fn new(docset: TDocSet) -> ConstScorer<TDocSet> {
         ConstScorer {
             docset,
-            score: 1f32,
+            score: 1.0,
         }
     }
 
@@ -92,6 +150,11 @@ impl<TDocSet: DocSet> ConstScorer<TDocSet> {
     pub fn set_score(&mut self, score: Score) {
         self.score = score;
     }
+
+    /// Consumes the `ConstScorer`, and returns its `DocSet`.
+    pub fn into_inner(self) -> TDocSet {
+        self.docset
+    }
 }
 
 impl<TDocSet: DocSet> DocSet for ConstScorer<TDocSet> {
@@ -115,14 +178,31 @@ impl<TDocSet: DocSet> DocSet for ConstScorer<TDocSet> {
         self.docset.size_hint()
     }
 
+    fn count_estimate(&self) -> u32 {
+        self.docset.count_estimate()
+    }
+
     fn append_to_bitset(&mut self, bitset: &mut BitSet) {
         self.docset.append_to_bitset(bitset);
     }
 }
 
+impl<TDocSet, TDocSetGroup> DocSetGroup<TDocSet> for ConstScorer<TDocSetGroup>
+where
+    TDocSetGroup: DocSetGroup<TDocSet>,
+{
+    fn into_docsets(self) -> Vec<TDocSet> {
+        self.docset.into_docsets()
+    }
+}
+
 impl<TDocSet: DocSet + 'static> Scorer for ConstScorer<TDocSet> {
     fn score(&mut self) -> Score {
-        1f32
+        1.0
+    }
+
+    fn name(&self) -> &'static str {
+        "ConstScorer"
     }
 }
 
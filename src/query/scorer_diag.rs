@@ -0,0 +1,77 @@
+use schema::{Field, Term};
+
+/// Reason why (part of) a query cannot match any document
+/// of a segment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum SkipReason {
+    /// The segment does not hold any value for the field.
+    FieldAbsent(Field),
+    /// The term does not appear in the segment.
+    ///
+    /// `value_bytes` are the bytes of the term, without its field.
+    TermNotFound {
+        /// Field of the term.
+        field: Field,
+        /// Value bytes of the term.
+        value_bytes: Vec<u8>,
+    },
+}
+
+impl SkipReason {
+    /// Returns the `TermNotFound` reason of the given term.
+    pub fn term_not_found(term: &Term) -> SkipReason {
+        SkipReason::TermNotFound {
+            field: term.field(),
+            value_bytes: term.value_bytes().to_owned(),
+        }
+    }
+}
+
+/// Diagnostics about the creation of a `Scorer` for a segment,
+/// as returned by [`Weight::scorer_with_diag`](./trait.Weight.html#method.scorer_with_diag).
+///
+/// They help understanding why a segment contributed fewer hits
+/// than expected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ScorerDiag {
+    skip_reasons: Vec<SkipReason>,
+    estimated_num_candidates: u32,
+}
+
+impl ScorerDiag {
+    /// Creates a diagnostic with the given estimation of
+    /// the number of candidate documents, and no skip reason.
+    pub fn with_estimated_num_candidates(estimated_num_candidates: u32) -> ScorerDiag {
+        ScorerDiag {
+            skip_reasons: Vec::new(),
+            estimated_num_candidates,
+        }
+    }
+
+    /// Returns the reasons why the query, or some of its subqueries,
+    /// cannot match any document of the segment.
+    pub fn skip_reasons(&self) -> &[SkipReason] {
+        &self.skip_reasons
+    }
+
+    /// Returns an upper bound of the number of documents
+    /// the scorer will go through, deleted documents included.
+    pub fn estimated_num_candidates(&self) -> u32 {
+        self.estimated_num_candidates
+    }
+
+    /// Records a skip reason.
+    pub fn add_skip_reason(&mut self, skip_reason: SkipReason) {
+        self.skip_reasons.push(skip_reason);
+    }
+
+    /// Sets the estimation of the number of candidate documents.
+    pub fn set_estimated_num_candidates(&mut self, estimated_num_candidates: u32) {
+        self.estimated_num_candidates = estimated_num_candidates;
+    }
+
+    /// Appends the skip reasons of the diagnostic of a subquery.
+    pub fn merge_skip_reasons(&mut self, sub_diag: ScorerDiag) {
+        self.skip_reasons.extend(sub_diag.skip_reasons);
+    }
+}
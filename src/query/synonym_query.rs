@@ -0,0 +1,365 @@
+use schema::{Field, Term};
+use schema::{term_to_string, value_bytes_to_string, Schema};
+use schema::IndexRecordOption;
+use query::{EmptyScorer, Query, Scorer, Weight};
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use core::{SegmentReader, Searcher};
+use docset::DocSet;
+use postings::{Postings, SegmentPostings};
+use fastfield::FastFieldReader;
+use std::cmp;
+use std::fmt;
+use std::result;
+use DocId;
+use Result;
+use Score;
+
+/// `SynonymQuery` matches the documents containing any of several
+/// terms of a same field, while scoring them as if the terms
+/// were one single logical term.
+///
+/// The inverse document frequency is computed from the sum of
+/// the document frequencies of the terms, so that a rare synonym does
+/// not get a higher score than a frequent one. The term frequency
+/// of a document is the sum of the term frequencies of the terms it contains.
+///
+/// The score is then defined as in the [`TermQuery`](./struct.TermQuery.html).
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::schema::{SchemaBuilder, Term, TEXT};
+/// use tantivy::query::{Query, SynonymQuery};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(title => "a new tv"))?;
+///     index_writer.add_document(doc!(title => "the television set"))?;
+///     index_writer.add_document(doc!(title => "a radio"))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let query = SynonymQuery::new(
+///     title,
+///     vec![
+///         Term::from_field_text(title, "tv"),
+///         Term::from_field_text(title, "television"),
+///     ],
+/// );
+/// assert_eq!(query.count(&*searcher)?, 2);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SynonymQuery {
+    field: Field,
+    terms: Vec<Term>,
+}
+
+impl SynonymQuery {
+    /// Creates a new synonym query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `terms` is empty, or if one of the terms
+    /// does not belong to `field`.
+    pub fn new(field: Field, terms: Vec<Term>) -> SynonymQuery {
+        assert!(!terms.is_empty(), "A synonym query requires at least one term");
+        assert!(
+            terms.iter().all(|term| term.field() == field),
+            "All of the terms of a synonym query must belong to its field"
+        );
+        SynonymQuery { field, terms }
+    }
+
+    /// Returns the field of the query.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the terms of the query.
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+}
+
+impl Query for SynonymQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        let num_docs = searcher.num_docs();
+        let doc_freq = self.terms
+            .iter()
+            .map(|term| searcher.doc_freq(term))
+            .fold(0u32, |doc_freq, term_doc_freq| doc_freq.saturating_add(term_doc_freq));
+        // documents containing several of the terms are counted several times.
+        let doc_freq = cmp::min(u64::from(doc_freq), num_docs);
+        let index_record_option = if scoring_enabled {
+            IndexRecordOption::WithFreqs
+        } else {
+            IndexRecordOption::Basic
+        };
+        Ok(box SynonymWeight {
+            terms: self.terms.clone(),
+            idf: 1.0 + (num_docs as Score / (doc_freq as Score + 1.0)).ln(),
+            index_record_option,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        validate_indexed_field(searcher.schema(), self.field, IndexRecordOption::Basic)
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        let (first_term, other_terms) = self.terms
+            .split_first()
+            .expect("A synonym query has at least one term");
+        write!(f, "Synonym({}", term_to_string(schema, first_term))?;
+        let field_type = schema.get_field_entry(self.field).field_type();
+        for term in other_terms {
+            write!(f, " {}", value_bytes_to_string(field_type, term.value_bytes()))?;
+        }
+        writeln!(f, ")")
+    }
+}
+
+/// Weight associated to the `SynonymQuery` query.
+pub struct SynonymWeight {
+    terms: Vec<Term>,
+    idf: Score,
+    index_record_option: IndexRecordOption,
+}
+
+impl Weight for SynonymWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let field = self.terms[0].field();
+        let inverted_index = reader.inverted_index(field);
+        let postings: Vec<SegmentPostings> = self.terms
+            .iter()
+            .flat_map(|term| inverted_index.read_postings(term, self.index_record_option))
+            .collect();
+        if postings.is_empty() {
+            return Ok(box EmptyScorer);
+        }
+        let fieldnorm_reader_opt = reader.get_fieldnorms_reader(field);
+        let mut scorer = SynonymScorer::new(postings, self.idf, fieldnorm_reader_opt);
+        scorer.set_max_doc(reader.max_doc());
+        Ok(box scorer)
+    }
+}
+
+/// Scorer associated to the `SynonymQuery` query.
+///
+/// It goes through the union of the postings of the terms,
+/// summing the term frequencies of the terms found in each document.
+pub struct SynonymScorer {
+    // postings that are not exhausted yet.
+    postings: Vec<SegmentPostings>,
+    started: bool,
+    doc: DocId,
+    term_freq: u32,
+    idf: Score,
+    fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
+    // bound of the size hint.
+    max_doc: DocId,
+}
+
+impl SynonymScorer {
+    fn new(
+        postings: Vec<SegmentPostings>,
+        idf: Score,
+        fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
+    ) -> SynonymScorer {
+        let non_empty_postings = postings
+            .into_iter()
+            .filter_map(|mut postings| {
+                if postings.advance() {
+                    Some(postings)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        SynonymScorer {
+            postings: non_empty_postings,
+            started: false,
+            doc: 0,
+            term_freq: 0,
+            idf,
+            fieldnorm_reader_opt,
+            max_doc: DocId::max_value(),
+        }
+    }
+
+    /// Returns the sum of the term frequencies of the
+    /// terms in the current document.
+    pub fn term_freq(&self) -> u32 {
+        self.term_freq
+    }
+
+    /// Bounds the size hint of the scorer by the `max_doc` of the segment.
+    pub fn set_max_doc(&mut self, max_doc: DocId) {
+        self.max_doc = max_doc;
+    }
+}
+
+impl DocSet for SynonymScorer {
+    fn advance(&mut self) -> bool {
+        if self.started {
+            let doc = self.doc;
+            self.postings
+                .drain_filter(|postings| postings.doc() == doc && !postings.advance());
+        } else {
+            self.started = true;
+        }
+        let doc = match self.postings.iter().map(|postings| postings.doc()).min() {
+            Some(doc) => doc,
+            None => return false,
+        };
+        self.doc = doc;
+        self.term_freq = self.postings
+            .iter()
+            .filter(|postings| postings.doc() == doc)
+            .map(|postings| postings.term_freq())
+            .sum();
+        true
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        let size_hint = self.postings.iter().fold(0u32, |size_hint, postings| {
+            size_hint.saturating_add(postings.size_hint())
+        });
+        cmp::min(size_hint, self.max_doc)
+    }
+}
+
+impl Scorer for SynonymScorer {
+    fn score(&mut self) -> Score {
+        let tf = match self.fieldnorm_reader_opt {
+            Some(ref fieldnorm_reader) => {
+                let field_norm = fieldnorm_reader.get(self.doc);
+                self.term_freq as Score / field_norm as Score
+            }
+            None => self.term_freq as Score,
+        };
+        self.idf * tf.sqrt()
+    }
+
+    fn name(&self) -> &'static str {
+        "SynonymScorer"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::SynonymQuery;
+    use docset::DocSet;
+    use query::{BooleanQuery, Occur, Query, QueryDisplay, Scorer, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, TEXT};
+    use Index;
+    use DocId;
+    use Score;
+
+    fn doc_scores(index: &Index, query: &Query) -> Vec<(DocId, Score)> {
+        let searcher = index.searcher();
+        let weight = query.weight(&*searcher, true).unwrap();
+        let mut scorer = weight.scorer(searcher.segment_reader(0)).unwrap();
+        let mut doc_scores = Vec::new();
+        while scorer.advance() {
+            doc_scores.push((scorer.doc(), scorer.score()));
+        }
+        doc_scores
+    }
+
+    #[test]
+    fn test_synonym_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "tv tv")).unwrap();
+            index_writer.add_document(doc!(title => "television tv")).unwrap();
+            index_writer.add_document(doc!(title => "radio")).unwrap();
+            index_writer.add_document(doc!(title => "telly")).unwrap();
+            index_writer.add_document(doc!(title => "television")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let terms: Vec<Term> = ["tv", "television", "telly"]
+            .iter()
+            .map(|text| Term::from_field_text(title, text))
+            .collect();
+        let synonym_query = SynonymQuery::new(title, terms.clone());
+        let boolean_query = BooleanQuery::from(
+            terms
+                .iter()
+                .map(|term| {
+                    let term_query: Box<Query> =
+                        box TermQuery::new(term.clone(), IndexRecordOption::WithFreqs);
+                    (Occur::Should, term_query)
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let synonym_scores = doc_scores(&index, &synonym_query);
+        let boolean_scores = doc_scores(&index, &boolean_query);
+        let synonym_docs: Vec<DocId> = synonym_scores.iter().map(|&(doc, _)| doc).collect();
+        let boolean_docs: Vec<DocId> = boolean_scores.iter().map(|&(doc, _)| doc).collect();
+        assert_eq!(synonym_docs, boolean_docs);
+        assert_eq!(synonym_docs, vec![0, 1, 3, 4]);
+        assert_eq!(synonym_query.count(&*index.searcher()).unwrap(), 4);
+
+        // the idf is computed from the combined doc freq 2 + 2 + 1 = 5.
+        let idf = 1.0 + (5.0 as Score / 6.0).ln();
+        // every document has as many synonyms as tokens.
+        for &(_, score) in &synonym_scores {
+            assert!((score - idf).abs() < 0.0001);
+        }
+        // the rare synonym `telly` does not get a higher score
+        // than the frequent synonym `television`.
+        assert_eq!(synonym_scores[2].1, synonym_scores[3].1);
+        assert!(boolean_scores[2].1 > boolean_scores[3].1);
+    }
+
+    #[test]
+    fn test_synonym_query_missing_terms() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "radio")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let missing_query = SynonymQuery::new(title, vec![Term::from_field_text(title, "tv")]);
+        assert_eq!(missing_query.count(&*searcher).unwrap(), 0);
+        let query = SynonymQuery::new(
+            title,
+            vec![
+                Term::from_field_text(title, "tv"),
+                Term::from_field_text(title, "radio"),
+            ],
+        );
+        assert_eq!(query.count(&*searcher).unwrap(), 1);
+        assert_eq!(
+            QueryDisplay::new(&query, searcher.schema()).to_string(),
+            "Synonym(title:\"tv\" \"radio\")\n"
+        );
+    }
+}
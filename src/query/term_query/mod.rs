@@ -11,15 +11,17 @@ mod tests {
 
     use docset::DocSet;
     use postings::SegmentPostings;
-    use query::{Query, Scorer};
+    use query::{Query, QueryContext, Scorer};
+    use indexer::NoMergePolicy;
     use query::term_query::TermScorer;
     use query::TermQuery;
     use Index;
     use schema::*;
     use schema::IndexRecordOption;
     use fastfield::FastFieldReader;
+    use Score;
 
-    fn abs_diff(left: f32, right: f32) -> f32 {
+    fn abs_diff(left: Score, right: Score) -> Score {
         (right - left).abs()
     }
 
@@ -34,7 +36,7 @@ mod tests {
             let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
             {
                 let doc = doc!(text_field => "a");
-                index_writer.add_document(doc);
+                index_writer.add_document(doc).unwrap();
             }
             assert!(index_writer.commit().is_ok());
         }
@@ -53,6 +55,80 @@ mod tests {
         assert_eq!(term_scorer.score(), 0.30685282);
     }
 
+    #[test]
+    pub fn test_term_query_raw_field_ignores_value_length() {
+        let mut schema_builder = SchemaBuilder::default();
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(tag => "rust")).unwrap();
+            index_writer
+                .add_document(doc!(tag => "rust", tag => "a much longer tag value"))
+                .unwrap();
+            index_writer.add_document(doc!(tag => "search")).unwrap();
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert!(segment_reader.get_fieldnorms_reader(tag).is_none());
+        let term_query = TermQuery::new(
+            Term::from_field_text(tag, "rust"),
+            IndexRecordOption::WithFreqs,
+        );
+        let term_weight = term_query.weight(&searcher, true).unwrap();
+        let mut term_scorer = term_weight.scorer(segment_reader).unwrap();
+        assert!(term_scorer.advance());
+        let first_score = term_scorer.score();
+        assert!(term_scorer.advance());
+        assert_eq!(term_scorer.doc(), 1);
+        assert_eq!(term_scorer.score(), first_score);
+        assert!(!term_scorer.advance());
+    }
+
+    #[test]
+    pub fn test_term_query_explain() {
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b")).unwrap();
+            index_writer.add_document(doc!(text_field => "a a c")).unwrap();
+            index_writer.add_document(doc!(text_field => "c")).unwrap();
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let term_weight = term_query.weight(&searcher, true).unwrap();
+        let mut term_scorer = term_weight.scorer(searcher.segment_reader(0)).unwrap();
+        assert!(term_scorer.advance());
+        assert!(term_scorer.advance());
+        assert_eq!(term_scorer.doc(), 1);
+
+        let explanation = term_query.explain(&searcher, DocAddress(0, 1)).unwrap();
+        assert_eq!(explanation.value(), term_scorer.score());
+        assert!(explanation.description().starts_with("Term(text:\"a\")"));
+        assert_eq!(explanation.details().len(), 2);
+        // `a` appears in 2 of the 3 documents: its idf is 1.
+        let idf = &explanation.details()[0];
+        assert_eq!(idf.value(), 1.0);
+        assert_eq!(idf.details()[0].value(), 3.0);
+        assert_eq!(idf.details()[1].value(), 2.0);
+        let tf = &explanation.details()[1];
+        assert_eq!(tf.value(), 2.0 / 3.0);
+        assert_eq!(tf.details()[0].value(), 2.0);
+        assert_eq!(tf.details()[1].value(), 3.0);
+
+        assert!(term_query.explain(&searcher, DocAddress(0, 2)).is_err());
+    }
+
     #[test]
     pub fn test_term_scorer() {
         let left_fieldnorms = FastFieldReader::from(vec![10, 4]);
@@ -65,7 +141,54 @@ mod tests {
             postings: left,
         };
         left_scorer.advance();
-        assert!(abs_diff(left_scorer.score(), 0.15342641) < 0.001f32);
+        assert!(abs_diff(left_scorer.score(), 0.15342641) < 0.001);
     }
 
+    #[test]
+    pub fn test_term_scorer_with_context() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment_texts in &[["a a b", "a"], ["b", "a b a a"], ["a c", "c"]] {
+                for &text in segment_texts {
+                    index_writer
+                        .add_document(doc!(text_field => text, tag_field => "t"))
+                        .unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let mut context = QueryContext::new();
+        // the postings of the tag field, that has no term frequencies,
+        // reuse the buffers of the postings of the text field.
+        for &(field, text) in &[(text_field, "a"), (tag_field, "t")] {
+            let term_query = TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::WithFreqs,
+            );
+            let term_weight = term_query.weight(&searcher, true).unwrap();
+            for segment_reader in searcher.segment_readers() {
+                let mut expected_scorer = term_weight.scorer(segment_reader).unwrap();
+                let mut scorer = term_weight
+                    .scorer_with_context(segment_reader, &mut context)
+                    .unwrap();
+                while expected_scorer.advance() {
+                    assert!(scorer.advance());
+                    assert_eq!(scorer.doc(), expected_scorer.doc());
+                    assert_eq!(scorer.score(), expected_scorer.score());
+                }
+                assert!(!scorer.advance());
+            }
+        }
+        // a single `BlockSegmentPostings` is allocated for all of the segments.
+        assert_eq!(context.num_allocated_buffers(), 1);
+        assert_eq!(context.num_reused_buffers(), 5);
+    }
 }
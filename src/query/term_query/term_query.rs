@@ -5,6 +5,11 @@ use query::Query;
 use query::Weight;
 use schema::IndexRecordOption;
 use Searcher;
+use std::result;
+use query::QueryValidationError;
+use query::query_validation::validate_indexed_field;
+use schema::{term_to_string, FieldType, Schema};
+use std::fmt;
 
 /// A Term query matches all of the documents
 /// containing a specific term.
@@ -15,7 +20,7 @@ use Searcher;
 /// * `idf`        - inverse document frequency.
 /// * `term_freq`  - number of occurrences of the term in the field
 /// * `field norm` - number of tokens in the field.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TermQuery {
     term: Term,
     index_record_option: IndexRecordOption,
@@ -30,6 +35,11 @@ impl TermQuery {
         }
     }
 
+    /// Returns the term searched by the query.
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
     /// Returns a weight object.
     ///
     /// While `.weight(...)` returns a boxed trait object,
@@ -54,4 +64,28 @@ impl Query for TermQuery {
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
         Ok(box self.specialized_weight(searcher, scoring_enabled))
     }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        validate_indexed_field(
+            searcher.schema(),
+            self.term.field(),
+            self.index_record_option,
+        )?;
+        // the terms of facet fields are encoded facets, without any `/`:
+        // a term built from the text of a facet path would not match anything.
+        let field_entry = searcher.schema().get_field_entry(self.term.field());
+        if *field_entry.field_type() == FieldType::HierarchicalFacet
+            && self.term.value_bytes().first() == Some(&b'/')
+        {
+            return Err(QueryValidationError::IncompatibleFieldType(
+                field_entry.name().to_string(),
+                "facet".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Term({})", term_to_string(schema, &self.term))
+    }
 }
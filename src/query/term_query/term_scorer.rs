@@ -5,6 +5,7 @@ use postings::SegmentPostings;
 use query::Scorer;
 use postings::Postings;
 use fastfield::FastFieldReader;
+use query::Explanation;
 
 pub struct TermScorer {
     pub idf: Score,
@@ -16,6 +17,24 @@ impl TermScorer {
     pub fn postings(&self) -> &SegmentPostings {
         &self.postings
     }
+
+    // explains the term frequency part of the score of the current document.
+    pub(crate) fn explain_tf(&self) -> Explanation {
+        let term_freq = self.postings.term_freq() as Score;
+        match self.fieldnorm_reader_opt {
+            Some(ref fieldnorm_reader) => {
+                let field_norm = fieldnorm_reader.get(self.doc()) as Score;
+                let mut explanation = Explanation::new(
+                    "tf, computed as term_freq / fieldnorm",
+                    term_freq / field_norm,
+                );
+                explanation.add_const("term_freq, number of occurrences of the term", term_freq);
+                explanation.add_const("fieldnorm, number of tokens of the field", field_norm);
+                explanation
+            }
+            None => Explanation::new("tf, number of occurrences of the term", term_freq),
+        }
+    }
 }
 
 impl DocSet for TermScorer {
@@ -34,6 +53,10 @@ impl DocSet for TermScorer {
     fn skip_next(&mut self, target: DocId) -> SkipResult {
         self.postings.skip_next(target)
     }
+
+    fn fill_buffer(&mut self, buffer: &mut [DocId]) -> usize {
+        self.postings.fill_buffer(buffer)
+    }
 }
 
 impl Scorer for TermScorer {
@@ -42,10 +65,14 @@ impl Scorer for TermScorer {
         let tf = match self.fieldnorm_reader_opt {
             Some(ref fieldnorm_reader) => {
                 let field_norm = fieldnorm_reader.get(doc);
-                (self.postings.term_freq() as f32 / field_norm as f32)
+                (self.postings.term_freq() as Score / field_norm as Score)
             }
-            None => self.postings.term_freq() as f32,
+            None => self.postings.term_freq() as Score,
         };
         self.idf * tf.sqrt()
     }
+
+    fn name(&self) -> &'static str {
+        "TermScorer"
+    }
 }
@@ -2,14 +2,19 @@ use Term;
 use query::Weight;
 use core::SegmentReader;
 use query::Scorer;
-use docset::DocSet;
+use query::QueryContext;
+use query::{Explanation, ScorerDiag, SkipReason};
+use query::explanation::does_not_match;
+use docset::{DocSet, SkipResult};
 use postings::SegmentPostings;
-use schema::IndexRecordOption;
+use schema::{term_to_string, IndexRecordOption};
 use super::term_scorer::TermScorer;
 use Result;
+use Score;
+use DocId;
 
 pub struct TermWeight {
-    pub(crate) num_docs: u32,
+    pub(crate) num_docs: u64,
     pub(crate) doc_freq: u32,
     pub(crate) term: Term,
     pub(crate) index_record_option: IndexRecordOption,
@@ -21,6 +26,54 @@ impl Weight for TermWeight {
         Ok(box specialized_scorer)
     }
 
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        let specialized_scorer = self.specialized_scorer_with_context(reader, context)?;
+        Ok(box specialized_scorer)
+    }
+
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let scorer = self.specialized_scorer(reader)?;
+        let mut diag = ScorerDiag::with_estimated_num_candidates(scorer.size_hint());
+        let field = self.term.field();
+        if !reader.has_field(field) {
+            diag.add_skip_reason(SkipReason::FieldAbsent(field));
+        } else if scorer.size_hint() == 0 {
+            // the terms of the dictionary have at least one document.
+            diag.add_skip_reason(SkipReason::term_not_found(&self.term));
+        }
+        Ok((box scorer, diag))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.specialized_scorer(reader)?;
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new(
+            format!(
+                "Term({}), computed as idf * sqrt(tf)",
+                term_to_string(reader.schema(), &self.term)
+            ),
+            scorer.score(),
+        );
+        let mut idf_explanation = Explanation::new(
+            "idf, computed as 1 + ln(num_docs / (doc_freq + 1))",
+            scorer.idf,
+        );
+        idf_explanation.add_const("num_docs, number of documents", self.num_docs as Score);
+        idf_explanation.add_const(
+            "doc_freq, number of documents containing the term",
+            self.doc_freq as Score,
+        );
+        explanation.add_detail(idf_explanation);
+        explanation.add_detail(scorer.explain_tf());
+        Ok(explanation)
+    }
+
     fn count(&self, reader: &SegmentReader) -> Result<u32> {
         if reader.num_deleted_docs() == 0 {
             let field = self.term.field();
@@ -36,17 +89,41 @@ impl Weight for TermWeight {
 }
 
 impl TermWeight {
-    fn idf(&self) -> f32 {
-        1.0 + (self.num_docs as f32 / (self.doc_freq as f32 + 1.0)).ln()
+    fn idf(&self) -> Score {
+        1.0 + (self.num_docs as Score / (self.doc_freq as Score + 1.0)).ln()
     }
 
     /// If the field is not found, returns an empty `DocSet`.
     pub fn specialized_scorer(&self, reader: &SegmentReader) -> Result<TermScorer> {
+        self.specialized_scorer_opt_context(reader, None)
+    }
+
+    /// Same as [`.specialized_scorer(...)`](#method.specialized_scorer), except
+    /// that the buffers of the postings are taken from the `QueryContext`.
+    pub(crate) fn specialized_scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        context: &QueryContext,
+    ) -> Result<TermScorer> {
+        self.specialized_scorer_opt_context(reader, Some(context))
+    }
+
+    fn specialized_scorer_opt_context(
+        &self,
+        reader: &SegmentReader,
+        context_opt: Option<&QueryContext>,
+    ) -> Result<TermScorer> {
         let field = self.term.field();
         let inverted_index = reader.inverted_index(field);
         let fieldnorm_reader_opt = reader.get_fieldnorms_reader(field);
-        let postings_opt: Option<SegmentPostings> =
-            inverted_index.read_postings(&self.term, self.index_record_option);
+        let postings_opt: Option<SegmentPostings> = match context_opt {
+            Some(context) => inverted_index.read_postings_with_context(
+                &self.term,
+                self.index_record_option,
+                context,
+            ),
+            None => inverted_index.read_postings(&self.term, self.index_record_option),
+        };
         if let Some(segment_postings) = postings_opt {
             Ok(TermScorer {
                 idf: self.idf(),
@@ -55,7 +132,7 @@ impl TermWeight {
             })
         } else {
             Ok(TermScorer {
-                idf: 1f32,
+                idf: 1.0,
                 fieldnorm_reader_opt: None,
                 postings: SegmentPostings::empty(),
             })
@@ -0,0 +1,401 @@
+use schema::{Field, IndexRecordOption, Term};
+use schema::{value_bytes_to_string, Schema};
+use query::{EmptyScorer, Query, Scorer, Weight};
+use termdict::{TermStreamer, TermStreamerBuilder};
+use core::SegmentReader;
+use common::BitSet;
+use Result;
+use ErrorKind;
+use core::Searcher;
+use query::BitSetDocSet;
+use query::ConstScorer;
+use fst::Automaton;
+use std::result;
+use std::fmt;
+use query::QueryValidationError;
+use query::query_validation::{get_field_entry, validate_indexed_field};
+
+/// Returns the number of terms at the beginning of `terms`
+/// satisfying the predicate, assuming that the terms satisfying
+/// it all come before the others.
+fn partition_point<P: Fn(&[u8]) -> bool>(terms: &[Vec<u8>], predicate: P) -> usize {
+    let (mut start, mut stop) = (0, terms.len());
+    while start < stop {
+        let mid = start + (stop - start) / 2;
+        if predicate(&terms[mid]) {
+            start = mid + 1;
+        } else {
+            stop = mid;
+        }
+    }
+    start
+}
+
+/// State of a `TermSetAutomaton`: `terms[start..stop]` are the terms
+/// starting with the `depth` bytes accepted so far.
+#[derive(Clone, Copy, Debug)]
+struct TermSetState {
+    start: usize,
+    stop: usize,
+    depth: usize,
+}
+
+/// Automaton accepting exactly the keys of a sorted set of terms.
+///
+/// Searching the term dictionary with it streams the dictionary
+/// once, only visiting the prefixes of the terms of the set.
+struct TermSetAutomaton<'a> {
+    terms: &'a [Vec<u8>],
+}
+
+impl<'a> Automaton for TermSetAutomaton<'a> {
+    type State = TermSetState;
+
+    fn start(&self) -> TermSetState {
+        TermSetState {
+            start: 0,
+            stop: self.terms.len(),
+            depth: 0,
+        }
+    }
+
+    fn is_match(&self, state: &TermSetState) -> bool {
+        // the term equal to the prefix, if any, is the first one.
+        state.start < state.stop && self.terms[state.start].len() == state.depth
+    }
+
+    fn can_match(&self, state: &TermSetState) -> bool {
+        state.start < state.stop
+    }
+
+    fn accept(&self, state: &TermSetState, byte: u8) -> TermSetState {
+        let depth = state.depth;
+        let terms = &self.terms[state.start..state.stop];
+        let start = partition_point(terms, |term| term.len() == depth || term[depth] < byte);
+        let stop = partition_point(terms, |term| term.len() == depth || term[depth] <= byte);
+        TermSetState {
+            start: state.start + start,
+            stop: state.start + stop,
+            depth: depth + 1,
+        }
+    }
+}
+
+/// A Term Set Query matches all of the documents
+/// containing any of a set of terms of a same field,
+/// e.g. `id IN (17, 23, 42)`.
+///
+/// It is much cheaper than a `BooleanQuery` of `TermQuery`
+/// clauses when there are many terms: the term dictionary of each
+/// segment is streamed once to find all of the terms, and the
+/// documents are collected without creating a scorer per term.
+/// The terms that do not appear in a segment are simply skipped.
+///
+/// All of the matching documents get a score of 1.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::Index;
+/// use tantivy::query::{Query, TermSetQuery};
+/// use tantivy::schema::{SchemaBuilder, Term, INT_INDEXED};
+///
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::new();
+/// let id = schema_builder.add_u64_field("id", INT_INDEXED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     for i in 0..100u64 {
+///         index_writer.add_document(doc!(id => i))?;
+///     }
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let terms = vec![17u64, 23, 42, 1_000]
+///     .into_iter()
+///     .map(|val| Term::from_field_u64(id, val))
+///     .collect();
+/// let query = TermSetQuery::new(id, terms);
+/// assert_eq!(query.count(&*searcher)?, 3);
+/// # Ok(())
+/// # }
+/// # fn main() {
+/// #     run().unwrap()
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TermSetQuery {
+    field: Field,
+    terms: Vec<Term>,
+}
+
+impl TermSetQuery {
+    /// Creates a new term set query.
+    ///
+    /// The terms do not need to be sorted, and may contain duplicates.
+    /// A query without any term does not match any document.
+    ///
+    /// All of the terms must belong to `field`: otherwise, the query
+    /// does not pass validation, and building its weight fails.
+    pub fn new(field: Field, terms: Vec<Term>) -> TermSetQuery {
+        TermSetQuery { field, terms }
+    }
+
+    /// Returns the field of the query.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the terms of the query.
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    /// Checks that all of the terms belong to the field of the query.
+    fn validate_term_fields(&self, schema: &Schema) -> result::Result<(), QueryValidationError> {
+        if let Some(term) = self.terms.iter().find(|term| term.field() != self.field) {
+            let field_name = get_field_entry(schema, self.field)?.name();
+            let term_field_name = get_field_entry(schema, term.field())?.name();
+            return Err(QueryValidationError::InvalidQuery(format!(
+                "The term set query on the field '{}' has a term of the field '{}'",
+                field_name, term_field_name
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Query for TermSetQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        if let Err(validation_error) = self.validate_term_fields(searcher.schema()) {
+            bail!(ErrorKind::InvalidArgument(format!("{:?}", validation_error)));
+        }
+        let mut terms: Vec<Vec<u8>> = self.terms
+            .iter()
+            .map(|term| term.value_bytes().to_owned())
+            .collect();
+        terms.sort();
+        terms.dedup();
+        Ok(box TermSetWeight {
+            field: self.field,
+            terms,
+        })
+    }
+
+    fn validate(&self, searcher: &Searcher) -> result::Result<(), QueryValidationError> {
+        validate_indexed_field(searcher.schema(), self.field, IndexRecordOption::Basic)?;
+        self.validate_term_fields(searcher.schema())
+    }
+
+    fn fmt_tree(&self, schema: &Schema, _depth: usize, f: &mut fmt::Formatter) -> fmt::Result {
+        let field_entry = schema.get_field_entry(self.field);
+        write!(f, "TermSet({}:", field_entry.name())?;
+        for (ord, term) in self.terms.iter().enumerate() {
+            let separator = if ord == 0 { "" } else { " " };
+            let value = value_bytes_to_string(field_entry.field_type(), term.value_bytes());
+            write!(f, "{}{}", separator, value)?;
+        }
+        writeln!(f, ")")
+    }
+}
+
+/// Weight associated to the `TermSetQuery` query.
+pub struct TermSetWeight {
+    field: Field,
+    // sorted, without duplicates.
+    terms: Vec<Vec<u8>>,
+}
+
+impl Weight for TermSetWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        if self.terms.is_empty() {
+            return Ok(box EmptyScorer);
+        }
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+
+        let inverted_index = reader.inverted_index(self.field);
+        let automaton = TermSetAutomaton { terms: &self.terms };
+        let mut term_stream = inverted_index.terms().search(automaton).into_stream();
+        while term_stream.advance() {
+            let term_info = term_stream.value();
+            let mut block_segment_postings = inverted_index
+                .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+            while block_segment_postings.advance() {
+                for &doc in block_segment_postings.docs() {
+                    if !reader.is_deleted(doc) {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(box ConstScorer::new(doc_bitset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, Term, INT_INDEXED, STRING};
+    use query::{BooleanQuery, Occur, Query, QueryDisplay, QueryValidationError, TermQuery};
+    use schema::IndexRecordOption;
+    use indexer::NoMergePolicy;
+    use test_utils::SeededRng;
+    use fst::Automaton;
+    use super::{TermSetAutomaton, TermSetQuery};
+
+    #[test]
+    fn test_term_set_automaton() {
+        let terms: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"a".to_vec(),
+            b"ab".to_vec(),
+            b"abc".to_vec(),
+            b"b".to_vec(),
+            b"bcd".to_vec(),
+        ];
+        let automaton = TermSetAutomaton { terms: &terms };
+        let accepts = |key: &[u8]| {
+            let mut state = automaton.start();
+            for &byte in key {
+                if !automaton.can_match(&state) {
+                    return false;
+                }
+                state = automaton.accept(&state, byte);
+            }
+            automaton.is_match(&state)
+        };
+        for term in &terms {
+            assert!(accepts(term));
+        }
+        let other_keys: &[&[u8]] = &[b"aa", b"abcd", b"bc", b"c", b"\xFF", b"bcdd"];
+        for key in other_keys {
+            assert!(!accepts(key));
+        }
+        let no_terms: Vec<Vec<u8>> = vec![];
+        let automaton = TermSetAutomaton { terms: &no_terms };
+        assert!(!automaton.is_match(&automaton.start()));
+        assert!(!automaton.can_match(&automaton.start()));
+    }
+
+    #[test]
+    fn test_term_set_query_str() {
+        let mut schema_builder = SchemaBuilder::new();
+        let code = schema_builder.add_text_field("code", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            let segments: [&[&str]; 2] = [
+                &["a", "ab", "abc", "b", "b", "deleted"],
+                &["ab", "abcd", "bcd", "c", "zz", "deleted"],
+            ];
+            for segment_codes in &segments {
+                for segment_code in segment_codes.iter() {
+                    index_writer.add_document(doc!(code => *segment_code)).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+            index_writer.delete_term(Term::from_field_text(code, "deleted"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let count = |codes: &[&str]| {
+            let terms = codes
+                .iter()
+                .map(|text| Term::from_field_text(code, text))
+                .collect();
+            let query = TermSetQuery::new(code, terms);
+            assert!(searcher.check_query(&query).is_ok());
+            query.count(&*searcher).unwrap()
+        };
+        assert_eq!(count(&[]), 0);
+        assert_eq!(count(&["ab"]), 2);
+        assert_eq!(count(&["b", "ab", "missing", "ab"]), 4);
+        assert_eq!(count(&["a", "abcd", "bcd", "c"]), 4);
+        assert_eq!(count(&["zz", "abc"]), 2);
+        assert_eq!(count(&["deleted", "x", "bc"]), 0);
+
+        let query = TermSetQuery::new(
+            code,
+            vec![
+                Term::from_field_text(code, "b"),
+                Term::from_field_text(code, "a"),
+            ],
+        );
+        assert_eq!(
+            format!("{}", QueryDisplay::new(&query, &schema)),
+            "TermSet(code:\"b\" \"a\")\n"
+        );
+    }
+
+    #[test]
+    fn test_term_set_query_u64() {
+        let mut schema_builder = SchemaBuilder::new();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for i in 0..3_000u64 {
+                index_writer.add_document(doc!(id => i * 7)).unwrap();
+                if i % 1_000 == 999 {
+                    index_writer.commit().unwrap();
+                }
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let mut rng = SeededRng::new(42);
+        for _ in 0..20 {
+            let num_terms = rng.gen_range(1, 300) as usize;
+            let vals: Vec<u64> = (0..num_terms).map(|_| rng.gen_range(0, 25_000)).collect();
+            let terms: Vec<Term> = vals.iter().map(|&val| Term::from_field_u64(id, val)).collect();
+            let boolean_query = BooleanQuery::from(
+                terms
+                    .iter()
+                    .map(|term| {
+                        let term_query: Box<Query> =
+                            box TermQuery::new(term.clone(), IndexRecordOption::Basic);
+                        (Occur::Should, term_query)
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            let term_set_query = TermSetQuery::new(id, terms);
+            assert_eq!(
+                term_set_query.count(&*searcher).unwrap(),
+                boolean_query.count(&*searcher).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_term_set_query_field_mismatch() {
+        let mut schema_builder = SchemaBuilder::new();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED);
+        let other = schema_builder.add_u64_field("other", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermSetQuery::new(
+            id,
+            vec![Term::from_field_u64(id, 1), Term::from_field_u64(other, 1)],
+        );
+        assert_eq!(
+            searcher.check_query(&query),
+            Err(QueryValidationError::InvalidQuery(
+                "The term set query on the field 'id' has a term of the field 'other'".to_string()
+            ))
+        );
+        assert!(query.weight(&*searcher, false).is_err());
+    }
+}
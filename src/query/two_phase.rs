@@ -0,0 +1,357 @@
+use docset::{DocSet, SkipResult};
+use query::Scorer;
+use query::score_combiner::checked_score;
+use DocId;
+use Score;
+
+/// Two-phase iteration over the documents of a `Scorer`.
+///
+/// Some scorers, like the scorer of a `PhraseQuery`, first go through
+/// a cheap approximation of their documents (the documents containing
+/// all of the terms), and then run an expensive check on each candidate
+/// (the verification of the positions of the terms).
+///
+/// When such a scorer is intersected with other scorers,
+/// the [`TwoPhaseIntersection`](./struct.TwoPhaseIntersection.html)
+/// intersects the approximations first, and only runs the expensive
+/// checks on the documents of this cheaper intersection.
+///
+/// A `Scorer` exposes its `TwoPhase` implementation through
+/// [`Scorer::two_phase`](./trait.Scorer.html#method.two_phase).
+pub trait TwoPhase {
+    /// Returns the approximation of the documents of the scorer,
+    /// which is a superset of them.
+    ///
+    /// The scorer is positioned on the current document of the approximation.
+    fn approximation(&mut self) -> &mut DocSet;
+
+    /// Returns true iff the current document of the approximation
+    /// is a document of the scorer.
+    ///
+    /// It is called at most once per document.
+    fn matches(&mut self) -> bool;
+
+    /// Returns an estimate of the cost of a call to `matches`,
+    /// used to run the cheapest checks first.
+    fn match_cost(&self) -> f32;
+}
+
+// Skips the approximation of a scorer if it has one,
+// and the scorer itself otherwise.
+fn approximation_skip_next(scorer: &mut Box<Scorer>, target: DocId) -> SkipResult {
+    if let Some(two_phase) = scorer.two_phase() {
+        return two_phase.approximation().skip_next(target);
+    }
+    scorer.skip_next(target)
+}
+
+fn matches(scorer: &mut Box<Scorer>) -> bool {
+    scorer.two_phase().map(|two_phase| two_phase.matches()).unwrap_or(true)
+}
+
+/// Intersection of `Scorer`s, some of which support two-phase iteration.
+///
+/// The approximations of the scorers are intersected first.
+/// The `matches` checks of the scorers are then run on the documents
+/// of this intersection, by order of increasing `match_cost`,
+/// stopping at the first check that fails.
+///
+/// Scorers that do not support two-phase iteration are their own approximation.
+pub struct TwoPhaseIntersection {
+    // sorted by increasing `count_estimate`.
+    scorers: Vec<Box<Scorer>>,
+    // the ords of the two-phase scorers, sorted by increasing `match_cost`.
+    match_order: Vec<usize>,
+    finished: bool,
+    doc: DocId,
+}
+
+impl From<Vec<Box<Scorer>>> for TwoPhaseIntersection {
+    fn from(mut scorers: Vec<Box<Scorer>>) -> TwoPhaseIntersection {
+        assert!(scorers.len() >= 2);
+        scorers.sort_by_key(|scorer| scorer.count_estimate());
+        let mut match_costs: Vec<(usize, f32)> = scorers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(ord, scorer)| {
+                scorer
+                    .two_phase()
+                    .map(|two_phase| (ord, two_phase.match_cost()))
+            })
+            .collect();
+        match_costs.sort_by(|&(_, left), &(_, right)| {
+            left.partial_cmp(&right).expect("match costs are not NaN")
+        });
+        TwoPhaseIntersection {
+            scorers,
+            match_order: match_costs.into_iter().map(|(ord, _)| ord).collect(),
+            finished: false,
+            doc: 0u32,
+        }
+    }
+}
+
+impl TwoPhaseIntersection {
+    /// Returns true iff the intersection of the approximations
+    /// is positioned on a document of all of the scorers.
+    fn matches(&mut self) -> bool {
+        for &ord in &self.match_order {
+            if !matches(&mut self.scorers[ord]) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Advances to the next document of the intersection of the approximations.
+    fn advance_approximation(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        let mut candidate_doc = self.doc;
+        let mut candidate_ord = self.scorers.len();
+        'outer: loop {
+            for (ord, scorer) in self.scorers.iter_mut().enumerate() {
+                if ord != candidate_ord {
+                    match approximation_skip_next(scorer, candidate_doc) {
+                        SkipResult::Reached => {}
+                        SkipResult::OverStep => {
+                            candidate_doc = scorer.doc();
+                            candidate_ord = ord;
+                            continue 'outer;
+                        }
+                        SkipResult::End => {
+                            self.finished = true;
+                            return false;
+                        }
+                    }
+                }
+            }
+            self.doc = candidate_doc;
+            return true;
+        }
+    }
+
+    /// Skips the intersection of the approximations to `target`.
+    fn skip_approximation(&mut self, target: DocId) -> SkipResult {
+        let mut current_target: DocId = target;
+        let mut current_ord = self.scorers.len();
+        'outer: loop {
+            for (ord, scorer) in self.scorers.iter_mut().enumerate() {
+                if ord == current_ord {
+                    continue;
+                }
+                match approximation_skip_next(scorer, current_target) {
+                    SkipResult::End => {
+                        self.finished = true;
+                        return SkipResult::End;
+                    }
+                    SkipResult::OverStep => {
+                        current_target = scorer.doc();
+                        current_ord = ord;
+                        continue 'outer;
+                    }
+                    SkipResult::Reached => {}
+                }
+            }
+            self.doc = current_target;
+            if target == current_target {
+                return SkipResult::Reached;
+            } else {
+                return SkipResult::OverStep;
+            }
+        }
+    }
+}
+
+impl DocSet for TwoPhaseIntersection {
+    fn advance(&mut self) -> bool {
+        while self.advance_approximation() {
+            if self.matches() {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        match self.skip_approximation(target) {
+            SkipResult::End => SkipResult::End,
+            skip_result => {
+                if self.matches() {
+                    skip_result
+                } else if self.advance() {
+                    SkipResult::OverStep
+                } else {
+                    SkipResult::End
+                }
+            }
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .min()
+            .unwrap_or(0u32)
+    }
+
+    fn count_estimate(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.count_estimate())
+            .min()
+            .unwrap_or(0u32)
+    }
+}
+
+impl Scorer for TwoPhaseIntersection {
+    fn score(&mut self) -> Score {
+        self.scorers.iter_mut().map(checked_score).sum()
+    }
+
+    fn name(&self) -> &'static str {
+        "TwoPhaseIntersection"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{TwoPhase, TwoPhaseIntersection};
+    use DocId;
+    use Score;
+    use docset::{DocSet, SkipResult};
+    use postings::tests::test_skip_against_unoptimized;
+    use query::{ConstScorer, Intersection, Scorer, VecDocSet};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Two-phase scorer recording the documents it is asked to check.
+    struct ProbeScorer {
+        approximation: VecDocSet,
+        matching_docs: Vec<DocId>,
+        checked_docs: Rc<RefCell<Vec<DocId>>>,
+    }
+
+    impl ProbeScorer {
+        fn new(
+            approximation: Vec<DocId>,
+            matching_docs: Vec<DocId>,
+            checked_docs: Rc<RefCell<Vec<DocId>>>,
+        ) -> ProbeScorer {
+            ProbeScorer {
+                approximation: VecDocSet::from(approximation),
+                matching_docs,
+                checked_docs,
+            }
+        }
+    }
+
+    impl TwoPhase for ProbeScorer {
+        fn approximation(&mut self) -> &mut DocSet {
+            &mut self.approximation
+        }
+
+        fn matches(&mut self) -> bool {
+            let doc = self.approximation.doc();
+            self.checked_docs.borrow_mut().push(doc);
+            self.matching_docs.contains(&doc)
+        }
+
+        fn match_cost(&self) -> f32 {
+            1f32
+        }
+    }
+
+    impl DocSet for ProbeScorer {
+        fn advance(&mut self) -> bool {
+            while self.approximation.advance() {
+                if self.matches() {
+                    return true;
+                }
+            }
+            false
+        }
+
+        fn doc(&self) -> DocId {
+            self.approximation.doc()
+        }
+
+        fn size_hint(&self) -> u32 {
+            self.approximation.size_hint()
+        }
+    }
+
+    impl Scorer for ProbeScorer {
+        fn score(&mut self) -> Score {
+            1.0
+        }
+
+        fn two_phase(&mut self) -> Option<&mut TwoPhase> {
+            Some(self)
+        }
+    }
+
+    fn docs<TDocSet: DocSet>(mut docset: TDocSet) -> Vec<DocId> {
+        let mut docs = vec![];
+        while docset.advance() {
+            docs.push(docset.doc());
+        }
+        docs
+    }
+
+    #[test]
+    fn test_two_phase_intersection() {
+        let checked_docs = Rc::new(RefCell::new(vec![]));
+        let make_scorers = |checked_docs: &Rc<RefCell<Vec<DocId>>>| -> Vec<Box<Scorer>> {
+            vec![
+                box ConstScorer::new(VecDocSet::from(vec![1, 2, 4, 5, 8, 9, 12])),
+                box ProbeScorer::new(
+                    vec![0, 2, 3, 4, 5, 7, 9, 12, 13],
+                    vec![2, 5, 7, 12, 13],
+                    Rc::clone(checked_docs),
+                ),
+                box ConstScorer::new(VecDocSet::from(vec![2, 4, 5, 9, 10, 12])),
+            ]
+        };
+        let two_phase_intersection = TwoPhaseIntersection::from(make_scorers(&checked_docs));
+        assert_eq!(docs(two_phase_intersection), vec![2, 5, 12]);
+        // only the documents of the intersection of the approximations are checked.
+        assert_eq!(*checked_docs.borrow(), vec![2, 4, 5, 9, 12]);
+
+        let unoptimized_checked_docs = Rc::new(RefCell::new(vec![]));
+        let intersection = Intersection::from(make_scorers(&unoptimized_checked_docs));
+        assert_eq!(docs(intersection), vec![2, 5, 12]);
+        assert!(unoptimized_checked_docs.borrow().len() > checked_docs.borrow().len());
+    }
+
+    #[test]
+    fn test_two_phase_intersection_skip() {
+        let make_intersection = || {
+            let scorers: Vec<Box<Scorer>> = vec![
+                box ConstScorer::new(VecDocSet::from(vec![1, 2, 4, 5, 8, 9, 12])),
+                box ProbeScorer::new(
+                    vec![0, 2, 3, 4, 5, 7, 9, 12, 13],
+                    vec![2, 5, 7, 12, 13],
+                    Rc::new(RefCell::new(vec![])),
+                ),
+            ];
+            TwoPhaseIntersection::from(scorers)
+        };
+        let mut intersection = make_intersection();
+        assert_eq!(intersection.skip_next(3), SkipResult::OverStep);
+        assert_eq!(intersection.doc(), 5);
+        assert_eq!(intersection.skip_next(12), SkipResult::Reached);
+        assert_eq!(intersection.skip_next(13), SkipResult::End);
+        test_skip_against_unoptimized(
+            || box make_intersection(),
+            vec![0, 1, 2, 3, 4, 5, 6, 9, 10, 12, 13, 14],
+        );
+    }
+}
@@ -1,23 +1,83 @@
-use docset::{DocSet, SkipResult};
+use docset::{DocSet, DocSetGroup, SkipResult};
 use query::Scorer;
 use common::TinySet;
-use std::cmp::Ordering;
+use std::cmp::{self, Ordering};
 use DocId;
 use Score;
 use query::score_combiner::{DoNothingCombiner, ScoreCombiner};
+use query::query_context::{Pooled, QueryContext};
 
 const HORIZON_NUM_TINYBITSETS: usize = 64;
 const HORIZON: u32 = 64u32 * HORIZON_NUM_TINYBITSETS as u32;
 
-/// Creates a `DocSet` that iterator through the intersection of two `DocSet`s.
+/// Creates a `DocSet` that iterates through the union of several `Scorer`s.
+///
+/// All of the children must be of the same type. Children of
+/// different types can be combined by boxing them as `Box<Scorer>`.
+/// A `DocSet` that is not a `Scorer` can be wrapped into a `ConstScorer`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tantivy;
+/// use tantivy::{DocSet, Index};
+/// use tantivy::schema::{IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, STRING};
+/// use tantivy::query::{ConstScorer, Query, RangeQuery, Scorer, Union, Weight};
+///
+/// # fn main() { run().unwrap(); }
+/// # fn run() -> tantivy::Result<()> {
+/// let mut schema_builder = SchemaBuilder::default();
+/// let tag = schema_builder.add_text_field("tag", STRING);
+/// let year = schema_builder.add_u64_field("year", INT_INDEXED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// {
+///     let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+///     index_writer.add_document(doc!(tag => "rust", year => 1990u64))?;
+///     index_writer.add_document(doc!(tag => "java", year => 1995u64))?;
+///     index_writer.add_document(doc!(tag => "java", year => 2010u64))?;
+///     index_writer.commit()?;
+/// }
+/// index.load_searchers()?;
+/// let searcher = index.searcher();
+/// let segment_reader = searcher.segment_reader(0);
+///
+/// // The postings of the term `tag:rust`...
+/// let postings = segment_reader
+///     .inverted_index(tag)
+///     .read_postings(&Term::from_field_text(tag, "rust"), IndexRecordOption::Basic)
+///     .unwrap();
+/// // ... and the scorer of a range query.
+/// let range_scorer = RangeQuery::new_u64(year, 2000..2020)
+///     .weight(&searcher, false)?
+///     .scorer(segment_reader)?;
+///
+/// let mut union = Union::<Box<Scorer>>::from(vec![
+///     Box::new(ConstScorer::new(postings)) as Box<Scorer>,
+///     range_scorer,
+/// ]);
+/// let mut docs = vec![];
+/// while union.advance() {
+///     docs.push(union.doc());
+/// }
+/// assert_eq!(docs, vec![0, 2]);
+/// # Ok(())
+/// # }
+/// ```
 pub struct Union<TScorer, TScoreCombiner = DoNothingCombiner> {
-    docsets: Vec<TScorer>,
-    bitsets: Box<[TinySet; HORIZON_NUM_TINYBITSETS]>,
-    scores: Box<[TScoreCombiner; HORIZON as usize]>,
+    // docsets that are not exhausted yet, with their position
+    // in the `Vec` the union was built from.
+    docsets: Vec<(usize, TScorer)>,
+    exhausted_docsets: Vec<(usize, TScorer)>,
+    bitsets: Pooled<[TinySet; HORIZON_NUM_TINYBITSETS]>,
+    scores: Pooled<[TScoreCombiner; HORIZON as usize]>,
     cursor: usize,
     offset: DocId,
     doc: DocId,
-    score: Score,
+    // score combiner of the current document.
+    doc_score_combiner: TScoreCombiner,
+    // bound of the size hint.
+    max_doc: DocId,
 }
 
 impl<TScorer, TScoreCombiner> From<Vec<TScorer>> for Union<TScorer, TScoreCombiner>
@@ -26,37 +86,101 @@ where
     TScorer: Scorer,
 {
     fn from(docsets: Vec<TScorer>) -> Union<TScorer, TScoreCombiner> {
-        let non_empty_docsets: Vec<TScorer> = docsets
-            .into_iter()
-            .flat_map(
-                |mut docset| {
-                    if docset.advance() {
-                        Some(docset)
-                    } else {
-                        None
-                    }
-                },
-            )
-            .collect();
+        Union::build(
+            docsets,
+            Pooled::unpooled(Box::new([TinySet::empty(); HORIZON_NUM_TINYBITSETS])),
+            Pooled::unpooled(Box::new([TScoreCombiner::default(); HORIZON as usize])),
+        )
+    }
+}
+
+impl<TScorer, TScoreCombiner> Union<TScorer, TScoreCombiner>
+where
+    TScoreCombiner: ScoreCombiner,
+    TScorer: Scorer,
+{
+    /// Creates a `Union` whose buffers are taken from the given `QueryContext`.
+    ///
+    /// The buffers are given back to the context when the `Union` is dropped.
+    pub fn with_context(
+        docsets: Vec<TScorer>,
+        context: &QueryContext,
+    ) -> Union<TScorer, TScoreCombiner> {
+        Union::with_score_combiner(docsets, TScoreCombiner::default(), context)
+    }
+
+    /// Creates a `Union` whose scores are combined by copies of `score_combiner`,
+    /// instead of the default `TScoreCombiner`.
+    ///
+    /// This makes it possible to configure the score combiner.
+    pub(crate) fn with_score_combiner(
+        docsets: Vec<TScorer>,
+        score_combiner: TScoreCombiner,
+        context: &QueryContext,
+    ) -> Union<TScorer, TScoreCombiner> {
+        let bitsets = context.take_buffer(
+            || Box::new([TinySet::empty(); HORIZON_NUM_TINYBITSETS]),
+            |bitsets| {
+                for bitset in bitsets.iter_mut() {
+                    bitset.clear();
+                }
+            },
+        );
+        let scores = context.take_buffer(
+            || Box::new([score_combiner; HORIZON as usize]),
+            |scores| {
+                for buffered_score_combiner in scores.iter_mut() {
+                    *buffered_score_combiner = score_combiner;
+                }
+            },
+        );
+        Union::build(docsets, bitsets, scores)
+    }
+
+    fn build(
+        docsets: Vec<TScorer>,
+        bitsets: Pooled<[TinySet; HORIZON_NUM_TINYBITSETS]>,
+        scores: Pooled<[TScoreCombiner; HORIZON as usize]>,
+    ) -> Union<TScorer, TScoreCombiner> {
+        let mut non_empty_docsets = Vec::with_capacity(docsets.len());
+        let mut empty_docsets = Vec::new();
+        for (ord, mut docset) in docsets.into_iter().enumerate() {
+            if docset.advance() {
+                non_empty_docsets.push((ord, docset));
+            } else {
+                empty_docsets.push((ord, docset));
+            }
+        }
         Union {
             docsets: non_empty_docsets,
-            bitsets: Box::new([TinySet::empty(); HORIZON_NUM_TINYBITSETS]),
-            scores: Box::new([TScoreCombiner::default(); HORIZON as usize]),
+            exhausted_docsets: empty_docsets,
+            bitsets,
+            scores,
             cursor: HORIZON_NUM_TINYBITSETS,
             offset: 0,
             doc: 0,
-            score: 0f32,
+            doc_score_combiner: TScoreCombiner::default(),
+            max_doc: DocId::max_value(),
         }
     }
+
+    /// Bounds the size hint and the count estimate of the union
+    /// by the `max_doc` of the segment of its `DocSet`s.
+    ///
+    /// Otherwise, they are only bounded by `DocId::max_value()`.
+    pub fn set_max_doc(&mut self, max_doc: DocId) {
+        self.max_doc = max_doc;
+    }
 }
 
 fn refill<TScorer: Scorer, TScoreCombiner: ScoreCombiner>(
-    scorers: &mut Vec<TScorer>,
+    scorers: &mut Vec<(usize, TScorer)>,
+    exhausted_scorers: &mut Vec<(usize, TScorer)>,
     bitsets: &mut [TinySet; HORIZON_NUM_TINYBITSETS],
     score_combiner: &mut [TScoreCombiner; HORIZON as usize],
     min_doc: DocId,
 ) {
-    scorers.drain_filter(|scorer| {
+    let consumed_scorers = scorers.drain_filter(|&mut (_, ref mut scorer)| {
         let horizon = min_doc + HORIZON as u32;
         loop {
             let doc = scorer.doc();
@@ -73,15 +197,17 @@ fn refill<TScorer: Scorer, TScoreCombiner: ScoreCombiner>(
             }
         }
     });
+    exhausted_scorers.extend(consumed_scorers);
 }
 
 impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Union<TScorer, TScoreCombiner> {
     fn refill(&mut self) -> bool {
-        if let Some(min_doc) = self.docsets.iter_mut().map(|docset| docset.doc()).min() {
+        if let Some(min_doc) = self.docsets.iter().map(|&(_, ref docset)| docset.doc()).min() {
             self.offset = min_doc;
             self.cursor = 0;
             refill(
                 &mut self.docsets,
+                &mut self.exhausted_docsets,
                 &mut *self.bitsets,
                 &mut *self.scores,
                 min_doc,
@@ -92,13 +218,19 @@ impl<TScorer: Scorer, TScoreCombiner: ScoreCombiner> Union<TScorer, TScoreCombin
         }
     }
 
+    /// Returns the score combiner of the current document, updated
+    /// with all of the docsets containing it.
+    pub(crate) fn doc_score_combiner(&self) -> &TScoreCombiner {
+        &self.doc_score_combiner
+    }
+
     fn advance_buffered(&mut self) -> bool {
         while self.cursor < HORIZON_NUM_TINYBITSETS {
             if let Some(val) = self.bitsets[self.cursor].pop_lowest() {
                 let delta = val + (self.cursor as u32) * 64;
                 self.doc = self.offset + delta;
                 let score_combiner = &mut self.scores[delta as usize];
-                self.score = score_combiner.score();
+                self.doc_score_combiner = *score_combiner;
                 score_combiner.clear();
                 return true;
             } else {
@@ -196,14 +328,16 @@ where
 
             // The target is outside of the buffered horizon.
             // advance all docsets to a doc >= to the target.
-            self.docsets
-                .drain_filter(|docset| match docset.doc().cmp(&target) {
+            let consumed_docsets = self.docsets.drain_filter(|&mut (_, ref mut docset)| {
+                match docset.doc().cmp(&target) {
                     Ordering::Less => match docset.skip_next(target) {
                         SkipResult::End => true,
                         SkipResult::Reached | SkipResult::OverStep => false,
                     },
                     Ordering::Equal | Ordering::Greater => false,
-                });
+                }
+            });
+            self.exhausted_docsets.extend(consumed_docsets);
 
             // at this point all of the docsets
             // are positionned on a doc >= to the target.
@@ -225,8 +359,45 @@ where
         self.doc
     }
 
+    /// Sums the size hints of the children, the documents buffered
+    /// from them, and the current document, up to the `max_doc`
+    /// (see [`.set_max_doc(...)`](#method.set_max_doc)).
     fn size_hint(&self) -> u32 {
-        0u32
+        let num_buffered_docs = self.bitsets[self.cursor..HORIZON_NUM_TINYBITSETS]
+            .iter()
+            .map(|bitset| bitset.len())
+            .sum::<u32>();
+        let size_hint = self.docsets
+            .iter()
+            .fold(num_buffered_docs.saturating_add(1), |size_hint, &(_, ref docset)| {
+                size_hint.saturating_add(docset.size_hint())
+            });
+        cmp::min(size_hint, self.max_doc)
+    }
+
+    fn count_estimate(&self) -> u32 {
+        let count_estimate = self.docsets
+            .iter()
+            .fold(0u32, |count_estimate, &(_, ref docset)| {
+                count_estimate.saturating_add(docset.count_estimate())
+            });
+        cmp::min(count_estimate, self.max_doc)
+    }
+}
+
+/// The union reads its `DocSet`s ahead of its current document:
+/// they are positioned past it, or exhausted. Once the union is exhausted,
+/// all of them are exhausted.
+impl<TScorer, TScoreCombiner> DocSetGroup<TScorer> for Union<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    fn into_docsets(self) -> Vec<TScorer> {
+        let mut ord_docsets = self.docsets;
+        ord_docsets.extend(self.exhausted_docsets);
+        ord_docsets.sort_by_key(|&(ord, _)| ord);
+        ord_docsets.into_iter().map(|(_, docset)| docset).collect()
     }
 }
 
@@ -236,7 +407,11 @@ where
     TScorer: Scorer,
 {
     fn score(&mut self) -> Score {
-        self.score
+        self.doc_score_combiner.score()
+    }
+
+    fn name(&self) -> &'static str {
+        "Union"
     }
 }
 
@@ -249,11 +424,13 @@ mod tests {
     use DocId;
     use std::collections::BTreeSet;
     use super::HORIZON;
-    use docset::{DocSet, SkipResult};
+    use docset::{DocSet, DocSetGroup, SkipResult};
     use postings::tests::test_skip_against_unoptimized;
     use query::VecDocSet;
-    use query::ConstScorer;
+    use query::{BitSetDocSet, ConstScorer, Scorer};
     use query::score_combiner::DoNothingCombiner;
+    use common::BitSet;
+    use common::HasLen;
 
     fn aux_test_union(vals: Vec<Vec<u32>>) {
         let mut val_set: BTreeSet<u32> = BTreeSet::new();
@@ -305,6 +482,93 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_union_into_docsets() {
+        let make_docsets = || {
+            vec![
+                ConstScorer::new(VecDocSet::from(vec![1, 3, 5000])),
+                ConstScorer::new(VecDocSet::from(vec![])),
+                ConstScorer::new(VecDocSet::from(vec![2, 3])),
+            ]
+        };
+        let mut union = Union::<_, DoNothingCombiner>::from(make_docsets());
+        assert!(union.advance());
+        assert_eq!(union.doc(), 1);
+        // the docsets are read ahead of the union.
+        let mut docsets = union.into_docsets();
+        assert_eq!(docsets[0].doc(), 5000);
+        assert!(!docsets[2].advance());
+
+        let mut union = Union::<_, DoNothingCombiner>::from(make_docsets());
+        let mut docs = vec![];
+        while union.advance() {
+            docs.push(union.doc());
+        }
+        assert_eq!(docs, vec![1, 2, 3, 5000]);
+        // the docsets are given back in their original order, all exhausted.
+        let mut docsets: Vec<VecDocSet> = union
+            .into_docsets()
+            .into_iter()
+            .map(ConstScorer::into_inner)
+            .collect();
+        assert_eq!(
+            docsets.iter().map(|docset| docset.len()).collect::<Vec<_>>(),
+            vec![3, 0, 2]
+        );
+        for docset in &mut docsets {
+            assert!(!docset.advance());
+        }
+        // once rewound, they can be reused in another union.
+        for docset in &mut docsets {
+            docset.reset();
+        }
+        let mut union = Union::<_, DoNothingCombiner>::from(
+            docsets
+                .into_iter()
+                .map(ConstScorer::new)
+                .collect::<Vec<ConstScorer<VecDocSet>>>(),
+        );
+        let mut docs = vec![];
+        while union.advance() {
+            docs.push(union.doc());
+        }
+        assert_eq!(docs, vec![1, 2, 3, 5000]);
+    }
+
+    #[test]
+    fn test_union_boxed_scorers() {
+        let mut bitset = BitSet::with_max_value(10);
+        for &doc in &[2, 4, 9] {
+            bitset.insert(doc);
+        }
+        let scorers: Vec<Box<Scorer>> = vec![
+            box ConstScorer::new(VecDocSet::from(vec![1, 4, 7])),
+            box ConstScorer::new(BitSetDocSet::from(bitset)),
+        ];
+        let mut union = Union::<_, DoNothingCombiner>::from(scorers);
+        let mut docs = vec![];
+        while union.advance() {
+            docs.push(union.doc());
+        }
+        assert_eq!(docs, vec![1, 2, 4, 7, 9]);
+    }
+
+    #[test]
+    fn test_union_size_hint_clamped_to_max_doc() {
+        let make_union = || {
+            Union::<_, DoNothingCombiner>::from(vec![
+                ConstScorer::new(VecDocSet::from(vec![1, 2, 3, 4])),
+                ConstScorer::new(VecDocSet::from(vec![2, 3, 4, 5])),
+            ])
+        };
+        assert!(make_union().size_hint() > 6);
+        let mut union = make_union();
+        union.set_max_doc(6);
+        assert_eq!(union.size_hint(), 6);
+        assert_eq!(union.count_estimate(), 6);
+        assert_eq!(union.count(), 5);
+    }
+
     fn test_aux_union_skip(docs_list: &[Vec<DocId>], skip_targets: Vec<DocId>) {
         let mut btree_set = BTreeSet::new();
         for docs in docs_list {
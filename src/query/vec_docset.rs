@@ -26,6 +26,13 @@ impl From<Vec<DocId>> for VecDocSet {
     }
 }
 
+impl VecDocSet {
+    /// Rewinds the `VecDocSet`, as if it was just created.
+    pub fn reset(&mut self) {
+        self.cursor = Wrapping(usize::max_value());
+    }
+}
+
 impl DocSet for VecDocSet {
     fn advance(&mut self) -> bool {
         self.cursor += Wrapping(1);
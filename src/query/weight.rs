@@ -1,16 +1,81 @@
 use super::Scorer;
+use super::QueryContext;
+use super::ScorerDiag;
+use super::Explanation;
+use super::explanation::does_not_match;
+use docset::{DocSet, SkipResult};
+use DocId;
 use Result;
 use core::SegmentReader;
+use core::SegmentMeta;
 
 /// A Weight is the specialization of a Query
 /// for a given set of segments.
 ///
+/// A `Weight` is created for a single search, and is not
+/// required to be `Send` or `Sync`.
+///
 /// See [`Query`](./trait.Query.html).
 pub trait Weight {
     /// Returns the scorer for the given segment.
     /// See [`Query`](./trait.Query.html).
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>>;
 
+    /// Returns the scorer for the given segment, obtaining its
+    /// temporary buffers from the given `QueryContext`.
+    ///
+    /// Reusing the same context for the different segments of a search
+    /// makes it possible to reuse the buffers instead of allocating new ones.
+    ///
+    /// The default implementation ignores the context and calls
+    /// [`.scorer(...)`](#tymethod.scorer). Weights combining other
+    /// weights should pass the context down to them.
+    fn scorer_with_context(
+        &self,
+        reader: &SegmentReader,
+        _context: &mut QueryContext,
+    ) -> Result<Box<Scorer>> {
+        self.scorer(reader)
+    }
+
+    /// Returns the scorer for the given segment, together with
+    /// diagnostics explaining why it may not match as many documents
+    /// as expected.
+    ///
+    /// The default implementation calls [`.scorer(...)`](#tymethod.scorer),
+    /// and only reports the size hint of the scorer as its estimated
+    /// number of candidates.
+    fn scorer_with_diag(&self, reader: &SegmentReader) -> Result<(Box<Scorer>, ScorerDiag)> {
+        let scorer = self.scorer(reader)?;
+        let diag = ScorerDiag::with_estimated_num_candidates(scorer.size_hint());
+        Ok((scorer, diag))
+    }
+
+    /// Returns false if it is certain that no document of the segment
+    /// can match, judging from the statistics of its `SegmentMeta` only.
+    ///
+    /// This makes it possible to skip a segment without opening
+    /// any of its data structures.
+    /// The default implementation always returns true.
+    fn can_match(&self, _segment_meta: &SegmentMeta) -> bool {
+        true
+    }
+
+    /// Returns the explanation of the score of the document `doc`
+    /// of the given segment.
+    ///
+    /// Returns an error if the document does not match.
+    /// The default implementation only reports the score of the document,
+    /// weights with a more interesting scoring should detail it.
+    /// See [`Query::explain`](./trait.Query.html#method.explain).
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.scorer(reader)?;
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("Score", scorer.score()))
+    }
+
     /// Returns the number documents within the given `SegmentReader`.
     fn count(&self, reader: &SegmentReader) -> Result<u32> {
         Ok(self.scorer(reader)?.count())
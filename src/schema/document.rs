@@ -3,6 +3,10 @@ use itertools::Itertools;
 use common::VInt;
 use std::io::{self, Read, Write};
 use common::BinarySerializable;
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+use std::collections::BTreeSet;
 
 /// Tantivy's Document is the object that can
 /// be indexed and then searched for.
@@ -10,18 +14,33 @@ use common::BinarySerializable;
 /// Documents are fundamentally a collection of unordered couple `(field, value)`.
 /// In this list, one field may appear more than once.
 ///
+/// # Ordering guarantees
 ///
-
-/// Documents are really just a list of couple `(field, value)`.
-/// In this list, one field may appear more than once.
+/// The values of different fields may be interleaved freely. At indexing time,
+/// values are processed field by field, and the values of a given field are
+/// processed in the order they were added to the document. In particular, the positions
+/// of the tokens of a multivalued text field follow this insertion order,
+/// whatever the values of the other fields in between.
+///
+/// The stored fields are returned in the exact order they were added.
+///
+/// Adding the same value twice to a field indexes it twice.
+/// See [`dedup_field_values`](#method.dedup_field_values).
+///
+/// The facet labels are not part of the stored document.
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Document {
     field_values: Vec<FieldValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    facet_labels: Vec<(Field, Facet, String)>,
 }
 
 impl From<Vec<FieldValue>> for Document {
     fn from(field_values: Vec<FieldValue>) -> Self {
-        Document { field_values }
+        Document {
+            field_values,
+            facet_labels: Vec::new(),
+        }
     }
 }
 
@@ -59,6 +78,8 @@ impl Document {
     pub fn filter_fields<P: Fn(Field) -> bool>(&mut self, predicate: P) {
         self.field_values
             .retain(|field_value| predicate(field_value.field()));
+        self.facet_labels
+            .retain(|&(field, _, _)| predicate(field));
     }
 
     /// Adding a facet to the document.
@@ -71,6 +92,35 @@ impl Document {
         self.add(FieldValue::new(field, value));
     }
 
+    /// Adding a facet to the document, along with a display label.
+    ///
+    /// The label (e.g. `"Apple Inc."` for the facet `/brand/apple`) is recorded
+    /// in the segment, and can be retrieved via
+    /// [`FacetReader::label_from_ord`](../fastfield/struct.FacetReader.html#method.label_from_ord)
+    /// or in the results of the facet collectors.
+    ///
+    /// If the same facet is given different labels, the first label
+    /// indexed in the segment is kept.
+    pub fn add_facet_with_label<F>(&mut self, field: Field, path: F, label: &str)
+    where
+        Facet: From<F>,
+    {
+        let facet = Facet::from(path);
+        self.facet_labels
+            .push((field, facet.clone(), label.to_string()));
+        self.add(FieldValue::new(field, Value::Facet(facet)));
+    }
+
+    /// Returns the label associated to a facet of the document, if any.
+    pub fn facet_label(&self, field: Field, facet: &Facet) -> Option<&str> {
+        self.facet_labels
+            .iter()
+            .find(|&&(label_field, ref label_facet, _)| {
+                label_field == field && label_facet == facet
+            })
+            .map(|&(_, _, ref label)| label.as_str())
+    }
+
     /// Add a text field.
     pub fn add_text(&mut self, field: Field, text: &str) {
         let value = Value::Str(String::from(text));
@@ -97,8 +147,30 @@ impl Document {
         &self.field_values
     }
 
+    /// Sorts the field_values by field.
+    ///
+    /// The sort is stable: the values of a given field
+    /// keep their insertion order.
+    pub fn sort_by_field(&mut self) {
+        self.field_values
+            .sort_by_key(|field_value| field_value.field());
+    }
+
+    /// Removes the `(field, value)` couples that were already
+    /// added to the document.
+    ///
+    /// The first occurrence of each couple is kept, so that the
+    /// relative order of the remaining values is unchanged.
+    pub fn dedup_field_values(&mut self) {
+        let mut seen_field_values: BTreeSet<FieldValue> = BTreeSet::new();
+        self.field_values
+            .retain(|field_value| seen_field_values.insert(field_value.clone()));
+    }
+
     /// Sort and groups the field_values by field.
     ///
+    /// Within a group, values are in insertion order.
+    ///
     /// The result of this method is not cached and is
     /// computed on the fly when this method is called.
     pub fn get_sorted_field_values(&self) -> Vec<(Field, Vec<&FieldValue>)> {
@@ -128,6 +200,200 @@ impl Document {
             .find(|field_value| field_value.field() == field)
             .map(|field_value| field_value.value())
     }
+
+    /// Checks that the values of the document match the type
+    /// of their field in the schema.
+    ///
+    /// The text values of facet fields are parsed into facets.
+    /// If `skip_invalid_values` is true, the invalid values are removed
+    /// from the document instead of being reported, and the fields
+    /// of the removed values are returned, once per removed value.
+    pub(crate) fn validate(
+        &mut self,
+        schema: &Schema,
+        skip_invalid_values: bool,
+    ) -> Result<Vec<Field>, DocumentValidationError> {
+        let mut num_values = vec![0usize; schema.fields().len()];
+        let mut skipped_fields = Vec::new();
+        let field_values = mem::replace(&mut self.field_values, Vec::new());
+        for field_value in field_values {
+            match validate_field_value(schema, field_value, &mut num_values) {
+                Ok(field_value) => self.field_values.push(field_value),
+                Err(validation_error) => {
+                    if !skip_invalid_values {
+                        return Err(validation_error);
+                    }
+                    skipped_fields.push(validation_error.field);
+                }
+            }
+        }
+        Ok(skipped_fields)
+    }
+
+    /// Returns an object implementing `Display`, that
+    /// lists the values of the document field by field,
+    /// using the field names of the given schema.
+    ///
+    /// ```text
+    /// title: "Of Mice and Men"
+    /// year: 1937
+    /// ```
+    pub fn display<'a>(&'a self, schema: &'a Schema) -> DocumentDisplay<'a> {
+        DocumentDisplay {
+            document: self,
+            schema,
+        }
+    }
+}
+
+/// Error returned when a value of a document does not
+/// match the definition of its field in the schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentValidationError {
+    /// Field of the invalid value.
+    pub field: Field,
+    /// Name of the field of the invalid value,
+    /// or its debug representation if it is not in the schema.
+    pub field_name: String,
+    /// What the schema expects for this field.
+    pub expected: String,
+    /// What the document holds.
+    pub got: String,
+}
+
+impl DocumentValidationError {
+    fn new(schema: &Schema, field: Field, expected: &str, got: String) -> DocumentValidationError {
+        let field_name = schema
+            .fields()
+            .get(field.0 as usize)
+            .map(|field_entry| field_entry.name().to_string())
+            .unwrap_or_else(|| format!("{:?}", field));
+        DocumentValidationError {
+            field,
+            field_name,
+            expected: expected.to_string(),
+            got,
+        }
+    }
+}
+
+impl fmt::Display for DocumentValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid value for the field '{}': expected {}, got {}.",
+            self.field_name, self.expected, self.got
+        )
+    }
+}
+
+fn describe_value(value: &Value) -> String {
+    match *value {
+        Value::Str(ref text) => format!("the text {:?}", text),
+        Value::U64(val) => format!("the u64 {}", val),
+        Value::I64(val) => format!("the i64 {}", val),
+        Value::Facet(ref facet) => format!("the facet {}", facet),
+    }
+}
+
+// `num_values` counts the valid values of each field so far.
+fn validate_field_value(
+    schema: &Schema,
+    field_value: FieldValue,
+    num_values: &mut [usize],
+) -> Result<FieldValue, DocumentValidationError> {
+    let field = field_value.field();
+    if field.0 as usize >= num_values.len() {
+        return Err(DocumentValidationError::new(
+            schema,
+            field,
+            "a field of the schema",
+            "an unknown field".to_string(),
+        ));
+    }
+    let field_entry = schema.get_field_entry(field);
+    let parsed_facet_opt = match (field_entry.field_type(), field_value.value()) {
+        (&FieldType::Str(_), &Value::Str(_))
+        | (&FieldType::U64(_), &Value::U64(_))
+        | (&FieldType::I64(_), &Value::I64(_))
+        | (&FieldType::HierarchicalFacet, &Value::Facet(_)) => None,
+        (&FieldType::HierarchicalFacet, &Value::Str(ref path)) => match Facet::from_str(path) {
+            Ok(facet) => Some(facet),
+            Err(_) => {
+                return Err(DocumentValidationError::new(
+                    schema,
+                    field,
+                    "a facet path starting with `/`",
+                    describe_value(field_value.value()),
+                ));
+            }
+        },
+        (field_type, value) => {
+            let expected = match *field_type {
+                FieldType::Str(_) => "a text",
+                FieldType::U64(_) => "a u64",
+                FieldType::I64(_) => "an i64",
+                FieldType::HierarchicalFacet => "a facet",
+            };
+            return Err(DocumentValidationError::new(
+                schema,
+                field,
+                expected,
+                describe_value(value),
+            ));
+        }
+    };
+    let field_value = match parsed_facet_opt {
+        Some(facet) => FieldValue::new(field, Value::Facet(facet)),
+        None => field_value,
+    };
+    let is_single_valued = match *field_entry.field_type() {
+        FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
+            int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+        }
+        _ => false,
+    };
+    let field_num_values = &mut num_values[field.0 as usize];
+    if is_single_valued && *field_num_values > 0 {
+        return Err(DocumentValidationError::new(
+            schema,
+            field,
+            "a single value for a single valued fast field",
+            "several values".to_string(),
+        ));
+    }
+    *field_num_values += 1;
+    Ok(field_value)
+}
+
+/// Human readable representation of a `Document`,
+/// as returned by [`Document::display`](./struct.Document.html#method.display).
+pub struct DocumentDisplay<'a> {
+    document: &'a Document,
+    schema: &'a Schema,
+}
+
+impl<'a> fmt::Display for DocumentDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (field, field_values) in self.document.get_sorted_field_values() {
+            let values: Vec<String> = field_values
+                .into_iter()
+                .map(|field_value| match *field_value.value() {
+                    Value::Str(ref text) => format!("{:?}", text),
+                    Value::U64(val) => val.to_string(),
+                    Value::I64(val) => val.to_string(),
+                    Value::Facet(ref facet) => facet.to_string(),
+                })
+                .collect();
+            writeln!(
+                f,
+                "{}: {}",
+                self.schema.get_field_name(field),
+                values.join(", ")
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl BinarySerializable for Document {
@@ -163,4 +429,90 @@ mod tests {
         assert_eq!(doc.field_values().len(), 1);
     }
 
+    #[test]
+    fn test_doc_sort_by_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let year_field = schema_builder.add_i64_field("year", INT_STORED);
+        let mut doc = Document::default();
+        doc.add_i64(year_field, 1937);
+        doc.add_text(title_field, "Of Mice and Men");
+        doc.add_i64(year_field, 1936);
+        doc.add_text(title_field, "Mice");
+        let grouped: Vec<(Field, Vec<Value>)> = doc.get_sorted_field_values()
+            .into_iter()
+            .map(|(field, field_values)| {
+                let values = field_values
+                    .into_iter()
+                    .map(|field_value| field_value.value().clone())
+                    .collect();
+                (field, values)
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![
+                (
+                    title_field,
+                    vec![
+                        Value::Str("Of Mice and Men".to_string()),
+                        Value::Str("Mice".to_string()),
+                    ],
+                ),
+                (year_field, vec![Value::I64(1937), Value::I64(1936)]),
+            ]
+        );
+        doc.sort_by_field();
+        assert_eq!(
+            doc.field_values(),
+            &[
+                FieldValue::new(title_field, Value::Str("Of Mice and Men".to_string())),
+                FieldValue::new(title_field, Value::Str("Mice".to_string())),
+                FieldValue::new(year_field, Value::I64(1937)),
+                FieldValue::new(year_field, Value::I64(1936)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doc_dedup_field_values() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let subtitle_field = schema_builder.add_text_field("subtitle", TEXT);
+        let mut doc = Document::default();
+        doc.add_text(title_field, "b");
+        doc.add_text(title_field, "a");
+        doc.add_text(subtitle_field, "a");
+        doc.add_text(title_field, "b");
+        doc.add_text(title_field, "a");
+        doc.dedup_field_values();
+        assert_eq!(
+            doc.field_values(),
+            &[
+                FieldValue::new(title_field, Value::Str("b".to_string())),
+                FieldValue::new(title_field, Value::Str("a".to_string())),
+                FieldValue::new(subtitle_field, Value::Str("a".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doc_display() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let year_field = schema_builder.add_i64_field("year", INT_STORED);
+        let category_field = schema_builder.add_facet_field("category");
+        let schema = schema_builder.build();
+        let mut doc = Document::default();
+        doc.add_facet(category_field, "/novel/american");
+        doc.add_text(title_field, "Of Mice and Men");
+        doc.add_i64(year_field, 1937);
+        doc.add_text(title_field, "\"Mice\"");
+        assert_eq!(
+            doc.display(&schema).to_string(),
+            "title: \"Of Mice and Men\", \"\\\"Mice\\\"\"\n\
+             year: 1937\n\
+             category: /novel/american\n"
+        );
+    }
 }
@@ -1,11 +1,12 @@
 use std::fmt::{self, Debug, Display, Formatter};
-use std::str;
+use std::iter::FromIterator;
+use std::str::{self, FromStr};
 use std::io::{self, Read, Write};
 use regex::Regex;
 use std::borrow::Borrow;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Cow;
-use common::BinarySerializable;
+use common::{self, BinarySerializable};
 
 const SLASH_BYTE: u8 = b'/';
 const ESCAPE_BYTE: u8 = b'\\';
@@ -63,6 +64,10 @@ impl Facet {
     /// It is conceptually, if one of the steps of this path
     /// contains a `/` or a `\`, it should be escaped
     /// using an anti-slash `/`.
+    ///
+    /// Trailing slashes are ignored, so that `/a/` is the
+    /// same facet as `/a`, and both `/` and the empty string
+    /// are the root facet.
     pub fn from_text<T>(path: &T) -> Facet
     where
         T: ?Sized + AsRef<str>,
@@ -91,16 +96,34 @@ impl Facet {
         Facet(facet_bytes)
     }
 
+    /// Returns an iterator over the binary representations of the
+    /// ancestors of the facet, from the root facet to the facet itself.
+    ///
+    /// For instance, the prefixes of `/america/canada` are the
+    /// encoded bytes of `/`, `/america` and `/america/canada`.
+    /// The prefixes are computed lazily and do not allocate.
+    pub fn prefixes(&self) -> FacetPrefixes {
+        FacetPrefixes {
+            bytes: self.encoded_bytes(),
+            next_end_opt: Some(0),
+        }
+    }
+
     /// Accessor for the inner buffer of the `Facet`.
     pub(crate) fn inner_buffer_mut(&mut self) -> &mut Vec<u8> {
         &mut self.0
     }
 
     /// Returns `true` iff other is a subfacet of `self`.
+    ///
+    /// The root facet is a prefix of all of the other facets.
     #[allow(collapsible_if)]
     pub fn is_prefix_of(&self, other: &Facet) -> bool {
         let self_bytes: &[u8] = self.encoded_bytes();
         let other_bytes: &[u8] = other.encoded_bytes();
+        if self_bytes.is_empty() {
+            return !other_bytes.is_empty();
+        }
         if self_bytes.len() < other_bytes.len() {
             if other_bytes.starts_with(self_bytes) {
                 return other_bytes[self_bytes.len()] == 0u8;
@@ -110,6 +133,136 @@ impl Facet {
     }
 }
 
+/// Iterator over the prefixes of a facet, as returned by
+/// [`Facet::prefixes`](./struct.Facet.html#method.prefixes).
+pub struct FacetPrefixes<'a> {
+    bytes: &'a [u8],
+    // end of the next prefix to emit, or `None` once
+    // the facet itself has been emitted.
+    next_end_opt: Option<usize>,
+}
+
+impl<'a> Iterator for FacetPrefixes<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let end = self.next_end_opt?;
+        self.next_end_opt = if end == self.bytes.len() {
+            None
+        } else {
+            let next_end = self.bytes[end + 1..]
+                .iter()
+                .position(|&b| b == FACET_SEP_BYTE)
+                .map(|pos| end + 1 + pos)
+                .unwrap_or(self.bytes.len());
+            Some(next_end)
+        };
+        Some(&self.bytes[..end])
+    }
+}
+
+/// Error returned when parsing an invalid facet
+/// with [`str::parse`](https://doc.rust-lang.org/std/primitive.str.html#method.parse).
+#[derive(Debug, PartialEq, Eq)]
+pub enum FacetParseError {
+    /// The facet path does not start with a `/`.
+    MissingLeadingSlash(String),
+    /// The facet path ends with an escape character `\`
+    /// that does not escape anything.
+    DanglingEscape(String),
+}
+
+impl Display for FacetParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            FacetParseError::MissingLeadingSlash(ref path) => {
+                write!(f, "The facet {:?} does not start with a `/`.", path)
+            }
+            FacetParseError::DanglingEscape(ref path) => {
+                write!(f, "The facet {:?} ends with a dangling escape char.", path)
+            }
+        }
+    }
+}
+
+/// Parses a facet, checking that its text representation is valid.
+///
+/// Unlike `Facet::from`, which accepts any text, the path is required
+/// to start with a `/`, and must not end with a dangling `\`.
+impl FromStr for Facet {
+    type Err = FacetParseError;
+
+    fn from_str(path: &str) -> Result<Facet, FacetParseError> {
+        if !path.starts_with('/') {
+            return Err(FacetParseError::MissingLeadingSlash(path.to_string()));
+        }
+        let num_trailing_escapes = path.bytes()
+            .rev()
+            .take_while(|&b| b == ESCAPE_BYTE)
+            .count();
+        if num_trailing_escapes % 2 == 1 {
+            return Err(FacetParseError::DanglingEscape(path.to_string()));
+        }
+        Ok(Facet::from(path))
+    }
+}
+
+impl<T: Display> FromIterator<T> for Facet {
+    fn from_iter<Path: IntoIterator<Item = T>>(path: Path) -> Facet {
+        Facet::from_path(path)
+    }
+}
+
+impl From<Facet> for String {
+    fn from(facet: Facet) -> String {
+        facet.to_string()
+    }
+}
+
+/// Compares the facet with a text representation,
+/// as displayed by `Facet`.
+///
+/// The comparison does not allocate, and is strict: `/a/` and `a`
+/// are different from the facet `/a`, even though they are parsed as `/a`.
+impl PartialEq<str> for Facet {
+    fn eq(&self, text: &str) -> bool {
+        let mut text_bytes = text.as_bytes().iter();
+        for step in self.0.split(|&b| b == FACET_SEP_BYTE) {
+            if text_bytes.next() != Some(&SLASH_BYTE) {
+                return false;
+            }
+            for b in step {
+                if (*b == SLASH_BYTE || *b == ESCAPE_BYTE) && text_bytes.next() != Some(&ESCAPE_BYTE)
+                {
+                    return false;
+                }
+                if text_bytes.next() != Some(b) {
+                    return false;
+                }
+            }
+        }
+        text_bytes.next().is_none()
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Facet {
+    fn eq(&self, text: &&'a str) -> bool {
+        *self == **text
+    }
+}
+
+impl PartialEq<Facet> for str {
+    fn eq(&self, facet: &Facet) -> bool {
+        *facet == *self
+    }
+}
+
+impl<'a> PartialEq<Facet> for &'a str {
+    fn eq(&self, facet: &Facet) -> bool {
+        *facet == **self
+    }
+}
+
 impl Borrow<[u8]> for Facet {
     fn borrow(&self) -> &[u8] {
         self.encoded_bytes()
@@ -126,12 +279,23 @@ impl<'a, T: ?Sized + AsRef<str>> From<&'a T> for Facet {
         let path: &str = path_asref.as_ref();
         let mut facet_encoded = Vec::new();
         let mut state = State::Idle;
-        let path_bytes = path.as_bytes();
-        for &c in &path_bytes[1..] {
+        let mut path_bytes = path.as_bytes();
+        // the leading `/` is not encoded.
+        if path_bytes.first() == Some(&SLASH_BYTE) {
+            path_bytes = &path_bytes[1..];
+        }
+        // number of separators at the end of `facet_encoded`
+        // that are the result of trailing slashes.
+        let mut num_trailing_seps = 0;
+        for &c in path_bytes {
             match (state, c) {
-                (State::Idle, ESCAPE_BYTE) => state = State::Escaped,
+                (State::Idle, ESCAPE_BYTE) => {
+                    state = State::Escaped;
+                    num_trailing_seps = 0;
+                }
                 (State::Idle, SLASH_BYTE) => {
                     facet_encoded.push(FACET_SEP_BYTE);
+                    num_trailing_seps += 1;
                 }
                 (State::Escaped, any_char) => {
                     state = State::Idle;
@@ -139,9 +303,12 @@ impl<'a, T: ?Sized + AsRef<str>> From<&'a T> for Facet {
                 }
                 (State::Idle, other_char) => {
                     facet_encoded.push(other_char);
+                    num_trailing_seps = 0;
                 }
             }
         }
+        let encoded_len = facet_encoded.len() - num_trailing_seps;
+        facet_encoded.truncate(encoded_len);
         Facet(facet_encoded)
     }
 }
@@ -157,12 +324,16 @@ impl BinarySerializable for Facet {
     }
 }
 
+/// The steps that are not valid utf-8, which may only come from a
+/// corrupted term dictionary, are displayed as escaped byte strings.
 impl Display for Facet {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         for step in self.0.split(|&b| b == FACET_SEP_BYTE) {
             write!(f, "/")?;
-            let step_str = unsafe { str::from_utf8_unchecked(step) };
-            write!(f, "{}", escape_slashes(step_str))?;
+            match str::from_utf8(step) {
+                Ok(step_str) => write!(f, "{}", escape_slashes(step_str))?,
+                Err(_) => write!(f, "{}", common::escape_bytes(step))?,
+            }
         }
         Ok(())
     }
@@ -172,7 +343,7 @@ fn escape_slashes(s: &str) -> Cow<str> {
     lazy_static! {
         static ref SLASH_PTN: Regex = Regex::new(r"[\\/]").unwrap();
     }
-    SLASH_PTN.replace_all(s, "\\/")
+    SLASH_PTN.replace_all(s, "\\$0")
 }
 
 impl Serialize for Facet {
@@ -203,7 +374,9 @@ impl Debug for Facet {
 #[cfg(test)]
 mod tests {
 
-    use super::Facet;
+    use super::{Facet, FacetParseError};
+    use std::str;
+    use tokenizer::{FacetTokenizer, TokenStream, Tokenizer};
 
     #[test]
     fn test_root() {
@@ -212,6 +385,46 @@ mod tests {
         assert!(Facet::root().is_root());
     }
 
+    #[test]
+    fn test_root_from_text() {
+        assert_eq!(Facet::from_text("/"), Facet::root());
+        assert_eq!(Facet::from_text(""), Facet::root());
+        assert_eq!(Facet::from_text("//"), Facet::root());
+        assert!(Facet::from_text("/").is_root());
+        assert!(!Facet::from_text("/a").is_root());
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        assert_eq!(Facet::from_text("/a/"), Facet::from_text("/a"));
+        assert_eq!(Facet::from_text("/a//"), Facet::from_text("/a"));
+        assert_eq!(Facet::from_text("/a/b/"), Facet::from_path(vec!["a", "b"]));
+        assert_eq!(Facet::from_text("/a//b"), Facet::from_path(vec!["a", "", "b"]));
+    }
+
+    #[test]
+    fn test_escaped_trailing_slash() {
+        let facet = Facet::from_text("/a\\/");
+        assert_eq!(facet, Facet::from_path(vec!["a/"]));
+        assert_eq!(facet.to_string(), "/a\\/");
+    }
+
+    #[test]
+    fn test_is_prefix_of() {
+        let root = Facet::root();
+        let a = Facet::from_text("/a");
+        let ab = Facet::from_text("/a/b");
+        let abc = Facet::from_text("/abc");
+        assert!(root.is_prefix_of(&a));
+        assert!(root.is_prefix_of(&ab));
+        assert!(!root.is_prefix_of(&root));
+        assert!(a.is_prefix_of(&ab));
+        assert!(!a.is_prefix_of(&a));
+        assert!(!a.is_prefix_of(&abc));
+        assert!(!ab.is_prefix_of(&a));
+        assert!(!a.is_prefix_of(&root));
+    }
+
     #[test]
     fn test_facet_display() {
         {
@@ -226,6 +439,112 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_facet_display_escaped_backslash() {
+        let facet = Facet::from_path(vec!["a\\b", "c"]);
+        assert_eq!(facet.to_string(), "/a\\\\b/c");
+        assert_eq!(Facet::from_text(&facet.to_string()), facet);
+    }
+
+    #[test]
+    fn test_facet_prefixes() {
+        let prefixes: Vec<Facet> = Facet::from_text("/america/north_america/canada")
+            .prefixes()
+            .map(|prefix| Facet::from_encoded(prefix.to_owned()))
+            .collect();
+        assert_eq!(
+            prefixes,
+            vec![
+                Facet::root(),
+                Facet::from_text("/america"),
+                Facet::from_text("/america/north_america"),
+                Facet::from_text("/america/north_america/canada"),
+            ]
+        );
+        assert_eq!(Facet::root().prefixes().collect::<Vec<&[u8]>>(), vec![&b""[..]]);
+        let facet = Facet::from_path(vec!["a", "", "b"]);
+        assert_eq!(
+            facet.prefixes().collect::<Vec<&[u8]>>(),
+            vec![&b""[..], &b"a"[..], &b"a\0"[..], &b"a\0\0b"[..]]
+        );
+    }
+
+    #[test]
+    fn test_facet_prefixes_same_as_facet_tokenizer() {
+        let facets = [
+            Facet::root(),
+            Facet::from_text("/a"),
+            Facet::from_text("/électronique/tv_et_vidéo/led"),
+            Facet::from_path(vec!["a", "", "b"]),
+            Facet::from_path(vec!["sec/ond", "th\\ird"]),
+        ];
+        for facet in &facets {
+            let text = str::from_utf8(facet.encoded_bytes()).unwrap();
+            let mut tokens: Vec<Vec<u8>> = Vec::new();
+            FacetTokenizer
+                .token_stream(text)
+                .process(&mut |token| tokens.push(token.text.as_bytes().to_owned()));
+            let prefixes: Vec<Vec<u8>> = facet.prefixes().map(|prefix| prefix.to_owned()).collect();
+            assert_eq!(prefixes, tokens);
+        }
+    }
+
+    #[test]
+    fn test_facet_eq_str() {
+        assert_eq!(Facet::root(), "/");
+        assert_eq!(Facet::from_text("/a/b"), "/a/b");
+        assert_eq!("/a/b", Facet::from_text("/a/b"));
+        assert_ne!(Facet::from_text("/a/b"), "/a/b/");
+        assert_ne!(Facet::from_text("/a/b"), "a/b");
+        assert_ne!(Facet::from_text("/a/b"), "/a");
+        assert_ne!(Facet::from_text("/a"), "/a/b");
+        assert_ne!(Facet::root(), "");
+        let facet = Facet::from_path(vec!["sec/ond", "th\\ird"]);
+        assert_eq!(facet, "/sec\\/ond/th\\\\ird");
+        assert_ne!(facet, "/sec/ond/th\\\\ird");
+        assert_ne!(facet, "/sec\\/ond/th\\ird");
+        assert_eq!(facet, &facet.to_string()[..]);
+    }
+
+    #[test]
+    fn test_facet_from_str() {
+        assert_eq!("/".parse::<Facet>(), Ok(Facet::root()));
+        assert_eq!("/a/b".parse::<Facet>(), Ok(Facet::from_path(vec!["a", "b"])));
+        assert_eq!(
+            "/a\\/b".parse::<Facet>(),
+            Ok(Facet::from_path(vec!["a/b"]))
+        );
+        assert_eq!(
+            "a/b".parse::<Facet>(),
+            Err(FacetParseError::MissingLeadingSlash("a/b".to_string()))
+        );
+        assert_eq!(
+            "/a\\".parse::<Facet>(),
+            Err(FacetParseError::DanglingEscape("/a\\".to_string()))
+        );
+        assert_eq!(
+            "/a\\\\".parse::<Facet>(),
+            Ok(Facet::from_path(vec!["a\\"]))
+        );
+    }
+
+    #[test]
+    fn test_facet_conversions() {
+        let facet: Facet = vec!["a", "b"].into_iter().collect();
+        assert_eq!(facet, "/a/b");
+        let facet: Facet = (1..4).collect();
+        assert_eq!(facet, "/1/2/3");
+        assert_eq!(String::from(facet), "/1/2/3");
+        assert_eq!(facet!("/a/b"), Facet::from_path(vec!["a", "b"]));
+        assert_eq!(facet!(), Facet::root());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid facet")]
+    fn test_facet_macro_invalid() {
+        facet!("a/b");
+    }
+
     #[test]
     fn test_facet_debug() {
         let v = ["first", "second", "third"];
@@ -14,10 +14,14 @@ use schema::FieldType;
 /// - a field name
 /// - a field type, itself wrapping up options describing
 /// how the field should be indexed.
+/// - whether the field is the unique key of the schema.
+/// - the number of bits per term of its bloom filter, if any.
 #[derive(Clone, Debug)]
 pub struct FieldEntry {
     name: String,
     field_type: FieldType,
+    unique_key: bool,
+    bloom_filter: Option<usize>,
 }
 
 impl FieldEntry {
@@ -27,6 +31,8 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::Str(text_options),
+            unique_key: false,
+            bloom_filter: None,
         }
     }
 
@@ -36,6 +42,8 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::U64(field_type),
+            unique_key: false,
+            bloom_filter: None,
         }
     }
 
@@ -45,6 +53,8 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::I64(field_type),
+            unique_key: false,
+            bloom_filter: None,
         }
     }
 
@@ -53,6 +63,8 @@ impl FieldEntry {
         FieldEntry {
             name: field_name,
             field_type: FieldType::HierarchicalFacet,
+            unique_key: false,
+            bloom_filter: None,
         }
     }
 
@@ -66,6 +78,45 @@ impl FieldEntry {
         &self.field_type
     }
 
+    /// Returns true iff the field is the unique key of the schema.
+    ///
+    /// See [`SchemaBuilder::set_unique_key`](./struct.SchemaBuilder.html#method.set_unique_key).
+    pub fn is_unique_key(&self) -> bool {
+        self.unique_key
+    }
+
+    pub(crate) fn set_unique_key(&mut self, unique_key: bool) {
+        self.unique_key = unique_key;
+    }
+
+    /// Returns the number of bits per term of the bloom filter
+    /// of the field, or `None` if the field has no bloom filter.
+    ///
+    /// See [`SchemaBuilder::set_bloom_filter`](./struct.SchemaBuilder.html#method.set_bloom_filter).
+    pub fn bloom_filter(&self) -> Option<usize> {
+        self.bloom_filter
+    }
+
+    pub(crate) fn set_bloom_filter(&mut self, num_bits_per_term: usize) {
+        self.bloom_filter = Some(num_bits_per_term);
+    }
+
+    /// Returns true iff the field can be used as a unique key.
+    ///
+    /// Unique keys must be indexed `u64` or `i64` fields, or
+    /// text fields indexed with the `raw` tokenizer, so that
+    /// a key value maps to exactly one term.
+    pub fn is_valid_unique_key(&self) -> bool {
+        match self.field_type {
+            FieldType::Str(ref options) => options
+                .get_indexing_options()
+                .map(|indexing_options| indexing_options.tokenizer() == "raw")
+                .unwrap_or(false),
+            FieldType::U64(ref options) | FieldType::I64(ref options) => options.is_indexed(),
+            FieldType::HierarchicalFacet => false,
+        }
+    }
+
     /// Returns true iff the field is indexed
     pub fn is_indexed(&self) -> bool {
         match self.field_type {
@@ -75,6 +126,23 @@ impl FieldEntry {
         }
     }
 
+    /// Returns true iff field norms are recorded for the field.
+    ///
+    /// Field norms, the number of tokens of each document, are only meaningful
+    /// for tokenized text fields. Numeric fields, facets and text fields
+    /// indexed without field norms (see [`TextFieldIndexing::set_fieldnorms`](
+    /// struct.TextFieldIndexing.html#method.set_fieldnorms)), such as `STRING`,
+    /// behave as if all of their norms were 1.
+    pub fn has_fieldnorms(&self) -> bool {
+        match self.field_type {
+            FieldType::Str(ref options) => options
+                .get_indexing_options()
+                .map(|indexing_options| indexing_options.fieldnorms())
+                .unwrap_or(false),
+            FieldType::U64(_) | FieldType::I64(_) | FieldType::HierarchicalFacet => false,
+        }
+    }
+
     /// Returns true iff the field is a int (signed or unsigned) fast field
     pub fn is_int_fast(&self) -> bool {
         match self.field_type {
@@ -99,7 +167,9 @@ impl Serialize for FieldEntry {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("field_entry", 3)?;
+        let num_fields =
+            3 + if self.unique_key { 1 } else { 0 } + if self.bloom_filter.is_some() { 1 } else { 0 };
+        let mut s = serializer.serialize_struct("field_entry", num_fields)?;
         s.serialize_field("name", &self.name)?;
 
         match self.field_type {
@@ -119,6 +189,12 @@ impl Serialize for FieldEntry {
                 s.serialize_field("type", "hierarchical_facet")?;
             }
         }
+        if self.unique_key {
+            s.serialize_field("unique_key", &true)?;
+        }
+        if let Some(num_bits_per_term) = self.bloom_filter {
+            s.serialize_field("bloom_filter", &num_bits_per_term)?;
+        }
 
         s.end()
     }
@@ -129,15 +205,7 @@ impl<'de> Deserialize<'de> for FieldEntry {
     where
         D: Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
-        enum Field {
-            Name,
-            Type,
-            Options,
-        };
-
-        const FIELDS: &[&str] = &["name", "type", "options"];
+        const FIELDS: &[&str] = &["name", "type", "options", "unique_key", "bloom_filter"];
 
         struct FieldEntryVisitor;
 
@@ -155,15 +223,17 @@ impl<'de> Deserialize<'de> for FieldEntry {
                 let mut name = None;
                 let mut ty = None;
                 let mut field_type = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::Name => {
+                let mut unique_key = false;
+                let mut bloom_filter = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "name" => {
                             if name.is_some() {
                                 return Err(de::Error::duplicate_field("name"));
                             }
                             name = Some(map.next_value()?);
                         }
-                        Field::Type => {
+                        "type" => {
                             if ty.is_some() {
                                 return Err(de::Error::duplicate_field("type"));
                             }
@@ -172,7 +242,7 @@ impl<'de> Deserialize<'de> for FieldEntry {
                                 field_type = Some(FieldType::HierarchicalFacet);
                             }
                         }
-                        Field::Options => match ty {
+                        "options" => match ty {
                             None => {
                                 let msg = "The `type` field must be \
                                            specified before `options`";
@@ -188,6 +258,16 @@ impl<'de> Deserialize<'de> for FieldEntry {
                                 }
                             },
                         },
+                        "unique_key" => {
+                            unique_key = map.next_value()?;
+                        }
+                        "bloom_filter" => {
+                            bloom_filter = Some(map.next_value()?);
+                        }
+                        _ => {
+                            // fields written by a newer version are ignored.
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
                     }
                 }
 
@@ -195,7 +275,12 @@ impl<'de> Deserialize<'de> for FieldEntry {
                 ty.ok_or_else(|| de::Error::missing_field("ty"))?;
                 let field_type = field_type.ok_or_else(|| de::Error::missing_field("options"))?;
 
-                Ok(FieldEntry { name, field_type })
+                Ok(FieldEntry {
+                    name,
+                    field_type,
+                    unique_key,
+                    bloom_filter,
+                })
             }
         }
 
@@ -207,7 +292,7 @@ impl<'de> Deserialize<'de> for FieldEntry {
 mod tests {
 
     use super::*;
-    use schema::TEXT;
+    use schema::{TextFieldIndexing, INT_INDEXED, INT_STORED, STORED, STRING, TEXT};
     use serde_json;
 
     #[test]
@@ -237,5 +322,97 @@ mod tests {
             FieldType::Str(_) => assert!(true),
             _ => panic!("expected FieldType::Str"),
         }
+        assert!(!field_value.is_unique_key());
+    }
+
+    #[test]
+    fn test_json_serialization_unique_key() {
+        let mut field_value = FieldEntry::new_text(String::from("id"), STRING);
+        assert!(field_value.is_valid_unique_key());
+        field_value.set_unique_key(true);
+        let expected = r#"{
+  "name": "id",
+  "type": "text",
+  "options": {
+    "indexing": {
+      "record": "basic",
+      "tokenizer": "raw",
+      "fieldnorms": false
+    },
+    "stored": false
+  },
+  "unique_key": true
+}"#;
+        let field_value_json = serde_json::to_string_pretty(&field_value).unwrap();
+        assert_eq!(expected, &field_value_json);
+        let field_value: FieldEntry = serde_json::from_str(expected).unwrap();
+        assert!(field_value.is_unique_key());
+    }
+
+    #[test]
+    fn test_json_serialization_bloom_filter() {
+        let mut field_value = FieldEntry::new_u64(String::from("id"), INT_INDEXED);
+        field_value.set_bloom_filter(10);
+        let expected = r#"{
+  "name": "id",
+  "type": "u64",
+  "options": {
+    "indexed": true,
+    "stored": false
+  },
+  "bloom_filter": 10
+}"#;
+        let field_value_json = serde_json::to_string_pretty(&field_value).unwrap();
+        assert_eq!(expected, &field_value_json);
+        let field_value: FieldEntry = serde_json::from_str(expected).unwrap();
+        assert_eq!(field_value.bloom_filter(), Some(10));
+    }
+
+    #[test]
+    fn test_is_valid_unique_key() {
+        assert!(!FieldEntry::new_text(String::from("title"), TEXT).is_valid_unique_key());
+        assert!(!FieldEntry::new_text(String::from("title"), STORED).is_valid_unique_key());
+        assert!(FieldEntry::new_u64(String::from("id"), INT_INDEXED).is_valid_unique_key());
+        assert!(FieldEntry::new_i64(String::from("id"), INT_INDEXED).is_valid_unique_key());
+        assert!(!FieldEntry::new_u64(String::from("id"), INT_STORED).is_valid_unique_key());
+        assert!(!FieldEntry::new_facet(String::from("facet")).is_valid_unique_key());
+    }
+
+    #[test]
+    fn test_has_fieldnorms() {
+        assert!(FieldEntry::new_text(String::from("title"), TEXT).has_fieldnorms());
+        assert!(!FieldEntry::new_text(String::from("id"), STRING).has_fieldnorms());
+        assert!(!FieldEntry::new_text(String::from("title"), STORED).has_fieldnorms());
+        assert!(!FieldEntry::new_u64(String::from("id"), INT_INDEXED).has_fieldnorms());
+        let raw_with_fieldnorms = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("raw")
+                .set_fieldnorms(true),
+        );
+        assert!(FieldEntry::new_text(String::from("id"), raw_with_fieldnorms).has_fieldnorms());
+        let tokenized_without_fieldnorms = TextOptions::default()
+            .set_indexing_options(TextFieldIndexing::default().set_fieldnorms(false));
+        assert!(
+            !FieldEntry::new_text(String::from("title"), tokenized_without_fieldnorms)
+                .has_fieldnorms()
+        );
+    }
+
+    #[test]
+    fn test_json_deserialization_fieldnorms_default() {
+        // schemas serialized before the `fieldnorms` flag record field norms.
+        let json = r#"{
+  "name": "id",
+  "type": "text",
+  "options": {
+    "indexing": {
+      "record": "basic",
+      "tokenizer": "raw"
+    },
+    "stored": false
+  }
+}"#;
+        let field_entry: FieldEntry = serde_json::from_str(json).unwrap();
+        assert!(field_entry.has_fieldnorms());
     }
 }
@@ -12,6 +12,23 @@
 /// (See [`InvertedIndexReader.read_postings`](
 ///     ../struct.InvertedIndexReader.html#method.read_postings))
 ///
+/// # Modifying the option of a field
+///
+/// The option of the schema applies to the newly written segments,
+/// and the option each segment was written with is recorded in its
+/// `SegmentMeta`. Segments are always read with their own option.
+///
+/// When segments written with different options are merged, the
+/// merged segment gets the [intersection](#method.intersection) of these
+/// options and of the option of the schema, as frequencies and positions
+/// cannot be made up.
+///
+/// # Ordering
+///
+/// The options are only partially ordered by the information they record:
+/// `WithFreqs` and `WithPositions` each record something the other does not.
+/// The `Ord` implementation is an arbitrary total order, use
+/// [`covers`](#method.covers) to compare the recorded information.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Serialize, Deserialize)]
 pub enum IndexRecordOption {
     /// records only the `DocId`s
@@ -21,6 +38,15 @@ pub enum IndexRecordOption {
     /// The term frequency can help giving better scoring of the documents.
     #[serde(rename = "freq")]
     WithFreqs,
+    /// records the document ids and the positions of the occurences in the
+    /// document, but not the term frequencies.
+    ///
+    /// This is enough to run [PhraseQueries](../query/struct.PhraseQuery.html)
+    /// on fields that do not need frequency-based scoring: the term frequencies
+    /// are read as `1` by the scorers, which saves the space and the decoding
+    /// time of the frequency blocks.
+    #[serde(rename = "position_nofreq")]
+    WithPositions,
     /// records the document id, the term frequency and the positions of
     /// the occurences in the document.
     /// Positions are required to run [PhraseQueries](../query/struct.PhraseQuery.html).
@@ -31,25 +57,19 @@ pub enum IndexRecordOption {
 impl IndexRecordOption {
     /// Returns true iff the term frequency will be encoded.
     pub fn is_termfreq_enabled(&self) -> bool {
-        match *self {
-            IndexRecordOption::WithFreqsAndPositions | IndexRecordOption::WithFreqs => true,
-            _ => false,
-        }
+        self.has_freq()
     }
 
     /// Returns true iff the term positions within the document are stored as well.
     pub fn is_position_enabled(&self) -> bool {
-        match *self {
-            IndexRecordOption::WithFreqsAndPositions => true,
-            _ => false,
-        }
+        self.has_positions()
     }
 
     /// Returns true iff this option includes encoding
     /// term frequencies.
     pub fn has_freq(&self) -> bool {
         match *self {
-            IndexRecordOption::Basic => false,
+            IndexRecordOption::Basic | IndexRecordOption::WithPositions => false,
             IndexRecordOption::WithFreqs | IndexRecordOption::WithFreqsAndPositions => true,
         }
     }
@@ -59,7 +79,65 @@ impl IndexRecordOption {
     pub fn has_positions(&self) -> bool {
         match *self {
             IndexRecordOption::Basic | IndexRecordOption::WithFreqs => false,
-            IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithPositions | IndexRecordOption::WithFreqsAndPositions => true,
         }
     }
+
+    /// Returns true iff this option records all of the
+    /// information recorded by the `other` option.
+    pub fn covers(&self, other: IndexRecordOption) -> bool {
+        (self.has_freq() || !other.has_freq()) && (self.has_positions() || !other.has_positions())
+    }
+
+    /// Returns the option recording the information
+    /// recorded by both this option and the `other` option.
+    pub fn intersection(&self, other: IndexRecordOption) -> IndexRecordOption {
+        let has_freq = self.has_freq() && other.has_freq();
+        let has_positions = self.has_positions() && other.has_positions();
+        match (has_freq, has_positions) {
+            (false, false) => IndexRecordOption::Basic,
+            (true, false) => IndexRecordOption::WithFreqs,
+            (false, true) => IndexRecordOption::WithPositions,
+            (true, true) => IndexRecordOption::WithFreqsAndPositions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexRecordOption;
+
+    const RECORD_OPTIONS: [IndexRecordOption; 4] = [
+        IndexRecordOption::Basic,
+        IndexRecordOption::WithFreqs,
+        IndexRecordOption::WithPositions,
+        IndexRecordOption::WithFreqsAndPositions,
+    ];
+
+    #[test]
+    fn test_index_record_option_intersection() {
+        use self::IndexRecordOption::*;
+        assert_eq!(WithFreqs.intersection(WithPositions), Basic);
+        assert_eq!(WithFreqsAndPositions.intersection(WithPositions), WithPositions);
+        assert_eq!(WithFreqsAndPositions.intersection(WithFreqs), WithFreqs);
+        assert_eq!(WithPositions.intersection(Basic), Basic);
+        for &left in &RECORD_OPTIONS {
+            for &right in &RECORD_OPTIONS {
+                let intersection = left.intersection(right);
+                assert_eq!(intersection, right.intersection(left));
+                assert!(left.covers(intersection));
+                assert!(right.covers(intersection));
+                assert_eq!(left.covers(right), intersection == right);
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_record_option_serialization() {
+        use serde_json;
+        let json = serde_json::to_string(&IndexRecordOption::WithPositions).unwrap();
+        assert_eq!(json, "\"position_nofreq\"");
+        let deserialized: IndexRecordOption = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, IndexRecordOption::WithPositions);
+    }
 }
@@ -121,12 +121,13 @@ pub use self::schema::{Schema, SchemaBuilder};
 pub use self::value::Value;
 pub use self::schema::DocParsingError;
 
-pub use self::facet::Facet;
+pub use self::facet::{Facet, FacetParseError, FacetPrefixes};
 pub use self::facet::FACET_SEP_BYTE;
 
-pub use self::document::Document;
+pub use self::document::{Document, DocumentDisplay, DocumentValidationError};
 pub use self::field::Field;
 pub use self::term::Term;
+pub(crate) use self::term::{term_to_string, value_bytes_to_string};
 
 pub use self::field_type::FieldType;
 pub use self::field_entry::FieldEntry;
@@ -95,6 +95,63 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Declares `field` as the unique key of the schema.
+    ///
+    /// Documents sharing the same unique key value are considered
+    /// as different versions of the same document.
+    /// [`IndexWriter::upsert_document`](../struct.IndexWriter.html#method.upsert_document)
+    /// relies on it to replace the previous version of a document.
+    ///
+    /// A schema has at most one unique key. Calling this method
+    /// a second time replaces the previous unique key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is not an indexed `u64` or `i64` field, or
+    /// a text field indexed with the `raw` tokenizer (e.g. `STRING`).
+    pub fn set_unique_key(&mut self, field: Field) {
+        assert!(
+            self.fields[field.0 as usize].is_valid_unique_key(),
+            "The field {:?} cannot be used as a unique key. Unique keys must be indexed \
+             u64/i64 fields, or text fields indexed with the raw tokenizer.",
+            self.fields[field.0 as usize].name()
+        );
+        for (field_id, field_entry) in self.fields.iter_mut().enumerate() {
+            field_entry.set_unique_key(field_id == field.0 as usize);
+        }
+    }
+
+    /// Enables a bloom filter over the terms of `field`.
+    ///
+    /// A bloom filter is built for each segment, when it is
+    /// flushed or merged, and stored in its own segment component.
+    /// It is consulted before looking up a term in the term dictionary
+    /// of the segment, which is a cheap way to skip the segments that
+    /// do not contain the term.
+    /// This is typically useful for point lookups, by id for instance,
+    /// on an index with many segments.
+    ///
+    /// Bloom filters are disabled by default. `10` bits per term
+    /// result in a false positive rate of about 1%.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is not indexed, or if `num_bits_per_term`
+    /// is not within `[1, 32]`.
+    pub fn set_bloom_filter(&mut self, field: Field, num_bits_per_term: usize) {
+        let field_entry = &mut self.fields[field.0 as usize];
+        assert!(
+            field_entry.is_indexed(),
+            "The field {:?} is not indexed.",
+            field_entry.name()
+        );
+        assert!(
+            num_bits_per_term > 0 && num_bits_per_term <= 32,
+            "The number of bits per term of a bloom filter must be within [1, 32]"
+        );
+        field_entry.set_bloom_filter(num_bits_per_term);
+    }
+
     /// Adds a field entry to the schema in build.
     fn add_field(&mut self, field_entry: FieldEntry) -> Field {
         let field = Field(self.fields.len() as u32);
@@ -107,9 +164,14 @@ impl SchemaBuilder {
     /// Finalize the creation of a `Schema`
     /// This will consume your `SchemaBuilder`
     pub fn build(self) -> Schema {
+        let unique_key = self.fields
+            .iter()
+            .position(|field_entry| field_entry.is_unique_key())
+            .map(|field_id| Field(field_id as u32));
         Schema(Arc::new(InnerSchema {
             fields: self.fields,
             fields_map: self.fields_map,
+            unique_key,
         }))
     }
 }
@@ -126,6 +188,7 @@ impl Default for SchemaBuilder {
 struct InnerSchema {
     fields: Vec<FieldEntry>,
     fields_map: HashMap<String, Field>, // transient
+    unique_key: Option<Field>,            // transient
 }
 
 /// Tantivy has a very strict schema.
@@ -167,6 +230,13 @@ impl Schema {
         &self.0.fields
     }
 
+    /// Returns the unique key of the schema, if any.
+    ///
+    /// See [`SchemaBuilder::set_unique_key`](./struct.SchemaBuilder.html#method.set_unique_key).
+    pub fn unique_key(&self) -> Option<Field> {
+        self.0.unique_key
+    }
+
     /// Returns the field options associated with a given name.
     ///
     /// # Panics
@@ -351,7 +421,8 @@ mod tests {
     "options": {
       "indexing": {
         "record": "basic",
-        "tokenizer": "raw"
+        "tokenizer": "raw",
+        "fieldnorms": false
       },
       "stored": false
     }
@@ -385,6 +456,32 @@ mod tests {
         assert_eq!("author", fields.next().unwrap().name());
         assert_eq!("count", fields.next().unwrap().name());
         assert_eq!("popularity", fields.next().unwrap().name());
+        assert_eq!(schema.unique_key(), None);
+    }
+
+    #[test]
+    pub fn test_schema_unique_key() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", INT_INDEXED);
+        let author_field = schema_builder.add_text_field("author", STRING);
+        schema_builder.set_unique_key(id_field);
+        schema_builder.set_unique_key(author_field);
+        let schema = schema_builder.build();
+        assert_eq!(schema.unique_key(), Some(author_field));
+        assert!(!schema.get_field_entry(id_field).is_unique_key());
+        assert!(schema.get_field_entry(author_field).is_unique_key());
+
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        let schema: Schema = serde_json::from_str(&schema_json).unwrap();
+        assert_eq!(schema.unique_key(), Some(author_field));
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_schema_unique_key_tokenized_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        schema_builder.set_unique_key(title_field);
     }
 
     #[test]
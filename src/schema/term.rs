@@ -2,7 +2,7 @@ use std::fmt;
 
 use common;
 use byteorder::{BigEndian, ByteOrder};
-use super::Field;
+use super::{Facet, Field, FieldType, Schema};
 use std::str;
 
 /// Size (in bytes) of the buffer of a int field.
@@ -43,6 +43,18 @@ impl Term {
         term
     }
 
+    /// Builds a term given a facet field, and a facet.
+    ///
+    /// The term holds the encoded bytes of the facet, and matches the
+    /// documents associated to the facet or to one of its descendants.
+    pub fn from_facet(field: Field, facet: &Facet) -> Term {
+        let facet_bytes = facet.encoded_bytes();
+        let mut term = Term(Vec::with_capacity(4 + facet_bytes.len()));
+        term.set_field(field);
+        term.set_bytes(facet_bytes);
+        term
+    }
+
     /// Builds a term given a field, and a u64-value
     ///
     /// Assuming the term has a field id of 1, and a u64 value of 3234,
@@ -93,8 +105,13 @@ impl Term {
 
     /// Set the texts only, keeping the field untouched.
     pub fn set_text(&mut self, text: &str) {
+        self.set_bytes(text.as_bytes());
+    }
+
+    /// Set the value bytes only, keeping the field untouched.
+    pub(crate) fn set_bytes(&mut self, bytes: &[u8]) {
         self.0.resize(4, 0u8);
-        self.0.extend(text.as_bytes());
+        self.0.extend_from_slice(bytes);
     }
 }
 
@@ -171,6 +188,40 @@ impl fmt::Debug for Term {
     }
 }
 
+/// Returns a human readable representation of a term value,
+/// given the type of its field.
+///
+/// Bytes that cannot be interpreted according to the field type
+/// (e.g. text that is not valid utf-8) are displayed as an
+/// escaped byte string.
+pub(crate) fn value_bytes_to_string(field_type: &FieldType, value_bytes: &[u8]) -> String {
+    match *field_type {
+        FieldType::U64(_) if value_bytes.len() == 8 => {
+            BigEndian::read_u64(value_bytes).to_string()
+        }
+        FieldType::I64(_) if value_bytes.len() == 8 => {
+            common::u64_to_i64(BigEndian::read_u64(value_bytes)).to_string()
+        }
+        FieldType::Str(_) => match str::from_utf8(value_bytes) {
+            Ok(text) => format!("{:?}", text),
+            Err(_) => common::escape_bytes(value_bytes),
+        },
+        FieldType::HierarchicalFacet => Facet::from_encoded(value_bytes.to_owned()).to_string(),
+        _ => common::escape_bytes(value_bytes),
+    }
+}
+
+/// Returns a human readable representation of a term,
+/// as `field_name:value`.
+pub(crate) fn term_to_string(schema: &Schema, term: &Term) -> String {
+    let field_entry = schema.get_field_entry(term.field());
+    format!(
+        "{}:{}",
+        field_entry.name(),
+        value_bytes_to_string(field_entry.field_type(), term.value_bytes())
+    )
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -202,5 +253,88 @@ mod tests {
             assert_eq!(term.as_slice()[10], (933u64 / 256u64) as u8);
             assert_eq!(term.as_slice()[11], (983u64 % 256u64) as u8);
         }
+        {
+            let term = Term::from_facet(title_field, &Facet::from("/a/b"));
+            assert_eq!(term.field(), title_field);
+            assert_eq!(&term.as_slice()[4..], b"a\x00b");
+        }
+    }
+
+    #[test]
+    pub fn test_display_random_value_bytes() {
+        use super::term_to_string;
+        use query::{QueryDisplay, TermQuery};
+        use test_utils::SeededRng;
+        let mut schema_builder = SchemaBuilder::default();
+        let fields = vec![
+            schema_builder.add_text_field("title", STRING),
+            schema_builder.add_u64_field("count", INT_INDEXED),
+            schema_builder.add_i64_field("year", INT_INDEXED),
+            schema_builder.add_facet_field("category"),
+        ];
+        let schema = schema_builder.build();
+        let mut rng = SeededRng::new(7);
+        let mut values: Vec<Vec<u8>> = vec![vec![], vec![0u8], vec![255u8], vec![0u8, 255u8]];
+        for _ in 0..500 {
+            let len = rng.gen_range(0, 12) as usize;
+            values.push((0..len).map(|_| rng.gen_range(0, 256) as u8).collect());
+        }
+        for value in &values {
+            for &field in &fields {
+                let mut term_bytes = Term::from_field_text(field, "").as_slice().to_owned();
+                term_bytes.extend_from_slice(value);
+                let term = Term::wrap(term_bytes);
+                let field_name = schema.get_field_name(field);
+                assert!(term_to_string(&schema, &term).starts_with(field_name));
+                assert!(format!("{:?}", term).starts_with("Term("));
+                let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+                assert!(
+                    QueryDisplay::new(&term_query, &schema)
+                        .to_string()
+                        .starts_with("Term(")
+                );
+            }
+            assert!(Facet::from_encoded(value.clone()).to_string().starts_with('/'));
+        }
+        assert_eq!(
+            Facet::from_encoded(b"a\x00\xff/".to_vec()).to_string(),
+            r#"/a/b"\xff/""#
+        );
+        let mut facet_bytes = Term::from_field_text(fields[3], "").as_slice().to_owned();
+        facet_bytes.extend_from_slice(b"\xc3\x00b");
+        assert_eq!(
+            term_to_string(&schema, &Term::wrap(facet_bytes)),
+            r#"category:/b"\xc3"/b"#
+        );
+    }
+
+    #[test]
+    pub fn test_term_to_string() {
+        use super::term_to_string;
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", STRING);
+        let year_field = schema_builder.add_i64_field("year", INT_INDEXED);
+        let category_field = schema_builder.add_facet_field("category");
+        let schema = schema_builder.build();
+        assert_eq!(
+            term_to_string(&schema, &Term::from_field_text(title_field, "l'\"été\"")),
+            r#"title:"l'\"été\"""#
+        );
+        assert_eq!(
+            term_to_string(&schema, &Term::from_field_i64(year_field, -1_960)),
+            "year:-1960"
+        );
+        let mut facet_bytes = Term::from_field_text(category_field, "").as_slice().to_owned();
+        facet_bytes.extend_from_slice(Facet::from("/europe/france").encoded_bytes());
+        assert_eq!(
+            term_to_string(&schema, &Term::wrap(facet_bytes)),
+            "category:/europe/france"
+        );
+        let mut non_utf8_bytes = Term::from_field_text(title_field, "").as_slice().to_owned();
+        non_utf8_bytes.extend_from_slice(&[b'a', 0xff, b'"']);
+        assert_eq!(
+            term_to_string(&schema, &Term::wrap(non_utf8_bytes)),
+            r#"title:b"a\xff\"""#
+        );
     }
 }
@@ -47,10 +47,28 @@ impl Default for TextOptions {
 ///
 /// * record (See [`IndexRecordOption`](./enum.IndexRecordOption.html))
 /// * tokenizer
+/// * whether the value position offsets should be recorded
+/// * whether the field norms should be recorded
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct TextFieldIndexing {
     record: IndexRecordOption,
     tokenizer: Cow<'static, str>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    value_position_offsets: bool,
+    #[serde(default = "default_fieldnorms", skip_serializing_if = "is_true")]
+    fieldnorms: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
+}
+
+fn is_true(val: &bool) -> bool {
+    *val
+}
+
+fn default_fieldnorms() -> bool {
+    true
 }
 
 impl Default for TextFieldIndexing {
@@ -58,6 +76,8 @@ impl Default for TextFieldIndexing {
         TextFieldIndexing {
             tokenizer: Cow::Borrowed("default"),
             record: IndexRecordOption::Basic,
+            value_position_offsets: false,
+            fieldnorms: true,
         }
     }
 }
@@ -88,6 +108,42 @@ impl TextFieldIndexing {
     pub fn index_option(&self) -> IndexRecordOption {
         self.record
     }
+
+    /// Sets whether the position of the first token of each of the values
+    /// of a document should be recorded.
+    ///
+    /// The values of a multivalued text field are indexed as
+    /// one single stream of tokens. These offsets make it possible
+    /// to find which value a given position belongs to.
+    /// See [`SegmentReader::value_position_offsets`](
+    /// ../struct.SegmentReader.html#method.value_position_offsets).
+    pub fn set_value_position_offsets(
+        mut self,
+        value_position_offsets: bool,
+    ) -> TextFieldIndexing {
+        self.value_position_offsets = value_position_offsets;
+        self
+    }
+
+    /// Returns true iff the value position offsets are recorded.
+    pub fn value_position_offsets(&self) -> bool {
+        self.value_position_offsets
+    }
+
+    /// Sets whether the field norms, the number of tokens of each document,
+    /// should be recorded. They are recorded by default.
+    ///
+    /// Without field norms, the field scores as if all of its norms were 1,
+    /// which is the right choice for fields that are not tokenized.
+    pub fn set_fieldnorms(mut self, fieldnorms: bool) -> TextFieldIndexing {
+        self.fieldnorms = fieldnorms;
+        self
+    }
+
+    /// Returns true iff the field norms are recorded.
+    pub fn fieldnorms(&self) -> bool {
+        self.fieldnorms
+    }
 }
 
 /// The field will be untokenized and indexed
@@ -95,6 +151,8 @@ pub const STRING: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("raw"),
         record: IndexRecordOption::Basic,
+        value_position_offsets: false,
+        fieldnorms: false,
     }),
     stored: false,
 };
@@ -104,6 +162,8 @@ pub const TEXT: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("default"),
         record: IndexRecordOption::WithFreqsAndPositions,
+        value_position_offsets: false,
+        fieldnorms: true,
     }),
     stored: false,
 };
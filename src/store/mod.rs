@@ -17,6 +17,16 @@ the reader is smart enough to avoid decompressing
 the block a second time, but their is no real
 *uncompressed block* cache.
 
+The store starts with a header holding a magic number, the format version
+and the number of documents, and ends with a footer holding the offset
+of the skip list and the number of documents. Stores written by older
+versions of tantivy do not have a header.
+
+Since the format version 2, each document starts with the version of
+its encoding. Documents are therefore copied as is when merging segments
+if their encoding is the current one, and decoded then re-encoded
+otherwise.
+
 A typical use case for the store is, once
 the search result page has been computed, returning
 the actual content of the 10 best document.
@@ -38,6 +48,47 @@ mod writer;
 pub use self::reader::StoreReader;
 pub use self::writer::StoreWriter;
 
+use schema::Document;
+use common::BinarySerializable;
+use std::io;
+
+/// Marks the stores starting with a header.
+///
+/// Stores without a header start with the length of their
+/// first block, which is always much smaller.
+const STORE_MAGIC_NUMBER: u32 = 0xFFFF_FF01;
+pub(crate) const STORE_FORMAT_VERSION: u32 = 2;
+/// Format version of the stores without a header.
+const HEADERLESS_STORE_FORMAT_VERSION: u32 = 0;
+/// Magic number, format version and number of documents.
+const HEADER_NUM_BYTES: usize = 12;
+/// Number of documents recorded in the header when the
+/// store could not seek back to it.
+const UNKNOWN_DOC_COUNT: u32 = 0xFFFF_FFFF;
+
+/// Version of the encoding of the documents written in the store.
+const DOC_ENCODING_VERSION: u32 = 1;
+/// Version of the encoding of the documents of the stores
+/// written before the encoding was recorded.
+const LEGACY_DOC_ENCODING_VERSION: u32 = 0;
+
+/// Returns true iff the documents of a store with the given format version
+/// start with the version of their encoding, as a `VInt`.
+fn has_doc_encoding_versions(store_format_version: u32) -> bool {
+    store_format_version >= 2
+}
+
+/// Decodes a document encoded with the given version.
+fn decode_document(encoding_version: u32, mut doc_bytes: &[u8]) -> io::Result<Document> {
+    match encoding_version {
+        LEGACY_DOC_ENCODING_VERSION | DOC_ENCODING_VERSION => Document::deserialize(&mut doc_bytes),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown document encoding version {}", encoding_version),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -48,9 +99,17 @@ mod tests {
     use schema::TextOptions;
     use schema::FieldValue;
     use schema::Document;
-    use directory::{Directory, MmapDirectory, RAMDirectory, WritePtr};
+    use directory::{Directory, MmapDirectory, RAMDirectory, ReadOnlySource, WritePtr};
+    use directory::SeekableWrite;
+    use common::BinarySerializable;
+    use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+    use std::sync::{Arc, Mutex};
 
     fn write_lorem_ipsum_store(writer: WritePtr, num_docs: usize) -> Schema {
+        write_lorem_ipsum_docs(StoreWriter::new(writer).unwrap(), num_docs)
+    }
+
+    fn write_lorem_ipsum_docs(mut store_writer: StoreWriter, num_docs: usize) -> Schema {
         let mut schema_builder = SchemaBuilder::default();
         let field_body = schema_builder.add_text_field("body", TextOptions::default().set_stored());
         let field_title =
@@ -66,25 +125,22 @@ mod tests {
              proident, sunt in culpa qui officia deserunt mollit anim id est \
              laborum.",
         );
-        {
-            let mut store_writer = StoreWriter::new(writer);
-            for i in 0..num_docs {
-                let mut fields: Vec<FieldValue> = Vec::new();
-                {
-                    let field_value = FieldValue::new(field_body, From::from(lorem.clone()));
-                    fields.push(field_value);
-                }
-                {
-                    let title_text = format!("Doc {}", i);
-                    let field_value = FieldValue::new(field_title, From::from(title_text));
-                    fields.push(field_value);
-                }
-                //let fields_refs: Vec<&FieldValue> = fields.iter().collect();
-                let doc = Document::from(fields);
-                store_writer.store(&doc).unwrap();
+        for i in 0..num_docs {
+            let mut fields: Vec<FieldValue> = Vec::new();
+            {
+                let field_value = FieldValue::new(field_body, From::from(lorem.clone()));
+                fields.push(field_value);
             }
-            store_writer.close().unwrap();
+            {
+                let title_text = format!("Doc {}", i);
+                let field_value = FieldValue::new(field_title, From::from(title_text));
+                fields.push(field_value);
+            }
+            //let fields_refs: Vec<&FieldValue> = fields.iter().collect();
+            let doc = Document::from(fields);
+            store_writer.store(&doc).unwrap();
         }
+        store_writer.close().unwrap();
         schema
     }
 
@@ -105,6 +161,144 @@ mod tests {
         }
     }
 
+    fn check_lorem_ipsum_store(store_source: ReadOnlySource, schema: &Schema) {
+        let field_title = schema.get_field("title").unwrap();
+        assert_eq!(StoreReader::read_max_doc(&store_source).unwrap(), 1_000);
+        let store = StoreReader::from_source(store_source);
+        for i in 0..1_000 {
+            assert_eq!(
+                *store.get(i).unwrap().get_first(field_title).unwrap().text(),
+                format!("Doc {}", i)
+            );
+        }
+    }
+
+    fn read_header(store_source: &ReadOnlySource) -> (u32, u32, u32) {
+        let mut header = &store_source.as_slice()[..HEADER_NUM_BYTES];
+        (
+            u32::deserialize(&mut header).unwrap(),
+            u32::deserialize(&mut header).unwrap(),
+            u32::deserialize(&mut header).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_store_header() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_store(store_file, 1_000);
+        let store_source = directory.open_read(path).unwrap();
+        assert_eq!(
+            read_header(&store_source),
+            (STORE_MAGIC_NUMBER, STORE_FORMAT_VERSION, 1_000)
+        );
+        check_lorem_ipsum_store(store_source, &schema);
+    }
+
+    /// Writer refusing to seek, shared with the test to read its data back.
+    #[derive(Clone, Default)]
+    struct NonSeekableWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for NonSeekableWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for NonSeekableWriter {
+        fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+            Err(io::Error::new(io::ErrorKind::Other, "not seekable"))
+        }
+    }
+
+    #[test]
+    fn test_store_non_seekable_writer() {
+        let writer = NonSeekableWriter::default();
+        let boxed_writer: Box<SeekableWrite> = box writer.clone();
+        let schema = write_lorem_ipsum_store(BufWriter::new(boxed_writer), 1_000);
+        let data = writer.0.lock().unwrap().clone();
+        let store_source = ReadOnlySource::from(data);
+        assert_eq!(
+            read_header(&store_source),
+            (STORE_MAGIC_NUMBER, STORE_FORMAT_VERSION, UNKNOWN_DOC_COUNT)
+        );
+        check_lorem_ipsum_store(store_source, &schema);
+    }
+
+    #[test]
+    fn test_store_without_header() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_docs(StoreWriter::without_header(store_file), 1_000);
+        let store_source = directory.open_read(path).unwrap();
+        assert_eq!(StoreReader::read_format_version(&store_source), None);
+        check_lorem_ipsum_store(store_source, &schema);
+    }
+
+    #[test]
+    fn test_store_transcode_headerless_store() {
+        let mut directory = RAMDirectory::create();
+        let legacy_path = Path::new("legacy_store");
+        let legacy_file = directory.open_write(legacy_path).unwrap();
+        let schema = write_lorem_ipsum_docs(StoreWriter::without_header(legacy_file), 1_000);
+        let legacy_store = StoreReader::from_source(directory.open_read(legacy_path).unwrap());
+        assert_eq!(legacy_store.format_version(), HEADERLESS_STORE_FORMAT_VERSION);
+
+        let stacked_path = Path::new("stacked_store");
+        {
+            let stacked_file = directory.open_write(stacked_path).unwrap();
+            let mut store_writer = StoreWriter::new(stacked_file).unwrap();
+            store_writer.stack(&legacy_store).unwrap();
+            store_writer.close().unwrap();
+        }
+        let stacked_source = directory.open_read(stacked_path).unwrap();
+        assert_eq!(
+            read_header(&stacked_source),
+            (STORE_MAGIC_NUMBER, STORE_FORMAT_VERSION, 1_000)
+        );
+        let stacked_bytes = stacked_source.as_slice().to_vec();
+        check_lorem_ipsum_store(stacked_source.clone(), &schema);
+
+        // documents of the current encoding are copied as is.
+        let stacked_store = StoreReader::from_source(stacked_source);
+        let copied_path = Path::new("copied_store");
+        {
+            let copied_file = directory.open_write(copied_path).unwrap();
+            let mut store_writer = StoreWriter::new(copied_file).unwrap();
+            for doc_id in 0..1_000 {
+                store_writer.copy_doc(&stacked_store, doc_id).unwrap();
+            }
+            store_writer.close().unwrap();
+        }
+        let copied_source = directory.open_read(copied_path).unwrap();
+        assert_eq!(copied_source.as_slice(), &stacked_bytes[..]);
+        check_lorem_ipsum_store(copied_source, &schema);
+    }
+
+    #[test]
+    fn test_store_unknown_doc_encoding_version() {
+        assert!(decode_document(DOC_ENCODING_VERSION + 1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_store_header_footer_mismatch() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        write_lorem_ipsum_store(store_file, 1_000);
+        let mut data = directory.open_read(path).unwrap().as_slice().to_vec();
+        let footer_offset = data.len() - 4;
+        data.truncate(footer_offset);
+        999u32.serialize(&mut data).unwrap();
+        assert!(StoreReader::read_max_doc(&ReadOnlySource::from(data)).is_err());
+    }
+
     #[bench]
     fn bench_store_encode(b: &mut Bencher) {
         let mut directory = MmapDirectory::create_from_tempdir().unwrap();
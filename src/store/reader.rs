@@ -1,7 +1,7 @@
 use Result;
 
 use directory::ReadOnlySource;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use DocId;
 use schema::Document;
 use common::BinarySerializable;
@@ -10,6 +10,9 @@ use std::io::{self, Read};
 use common::VInt;
 use datastruct::SkipList;
 use lz4;
+use super::{HEADER_NUM_BYTES, STORE_MAGIC_NUMBER, UNKNOWN_DOC_COUNT};
+use super::{HEADERLESS_STORE_FORMAT_VERSION, LEGACY_DOC_ENCODING_VERSION};
+use super::{decode_document, has_doc_encoding_versions};
 
 /// Reads document off tantivy's [`Store`](./index.html)
 #[derive(Clone)]
@@ -19,11 +22,15 @@ pub struct StoreReader {
     current_block_offset: RefCell<usize>,
     current_block: RefCell<Vec<u8>>,
     max_doc: DocId,
+    format_version: u32,
+    num_decompressed_blocks: Cell<usize>,
 }
 
 impl StoreReader {
     /// Opens a store reader
     pub fn from_source(data: ReadOnlySource) -> StoreReader {
+        let format_version =
+            StoreReader::read_format_version(&data).unwrap_or(HEADERLESS_STORE_FORMAT_VERSION);
         let (data_source, offset_index_source, max_doc) = split_source(data);
         StoreReader {
             data: data_source,
@@ -31,14 +38,84 @@ impl StoreReader {
             current_block_offset: RefCell::new(usize::max_value()),
             current_block: RefCell::new(Vec::new()),
             max_doc,
+            format_version,
+            num_decompressed_blocks: Cell::new(0),
         }
     }
 
+    /// Returns the number of documents of the store.
+    pub(crate) fn max_doc(&self) -> DocId {
+        self.max_doc
+    }
+
+    /// Returns the number of blocks decompressed so far.
+    #[cfg(test)]
+    pub(crate) fn num_decompressed_blocks(&self) -> usize {
+        self.num_decompressed_blocks.get()
+    }
+
+    /// Returns the format version of the store.
+    pub(crate) fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    /// Returns the format version recorded in the header of the store,
+    /// or `None` if the store does not have a header.
+    pub(crate) fn read_format_version(data: &ReadOnlySource) -> Option<u32> {
+        let data = data.as_slice();
+        if data.len() < HEADER_NUM_BYTES {
+            return None;
+        }
+        let mut header = &data[..HEADER_NUM_BYTES];
+        let magic_number = u32::deserialize(&mut header).ok()?;
+        if magic_number != STORE_MAGIC_NUMBER {
+            return None;
+        }
+        u32::deserialize(&mut header).ok()
+    }
+
+    /// Reads the number of documents of a store from its footer,
+    /// without opening it.
+    ///
+    /// Returns an error if the footer is not consistent
+    /// with the length of the data, or with the header.
+    pub(crate) fn read_max_doc(data: &ReadOnlySource) -> io::Result<DocId> {
+        let data_len = data.len();
+        let footer_len = size_of::<u64>() + size_of::<u32>();
+        if data_len < footer_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "store is too short to contain a footer",
+            ));
+        }
+        let footer_offset = data_len - footer_len;
+        let mut footer = &data.as_slice()[footer_offset..];
+        let offset = u64::deserialize(&mut footer)?;
+        let max_doc = u32::deserialize(&mut footer)?;
+        if offset > footer_offset as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "store footer points beyond the end of the store",
+            ));
+        }
+        if let (_, Some(header_max_doc)) = read_header(data.as_slice(), offset as usize) {
+            if header_max_doc != max_doc {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "store header and footer disagree on the number of documents",
+                ));
+            }
+        }
+        Ok(max_doc)
+    }
+
     pub(crate) fn block_index(&self) -> SkipList<u64> {
         SkipList::from(self.offset_index_source.as_slice())
     }
 
-    fn block_offset(&self, doc_id: DocId) -> (DocId, u64) {
+    /// Returns the first doc id and the offset of the block
+    /// holding a document.
+    pub(crate) fn block_offset(&self, doc_id: DocId) -> (DocId, u64) {
         self.block_index()
             .seek(u64::from(doc_id) + 1)
             .map(|(doc, offset)| (doc as DocId, offset))
@@ -65,6 +142,8 @@ impl StoreReader {
             *self.current_block_offset.borrow_mut() = usize::max_value();
             lz4_decoder.read_to_end(&mut current_block_mut).map(|_| ())?;
             *self.current_block_offset.borrow_mut() = block_offset;
+            self.num_decompressed_blocks
+                .set(self.num_decompressed_blocks.get() + 1);
         }
         Ok(())
     }
@@ -77,17 +156,31 @@ impl StoreReader {
     /// It should not be called to score documents
     /// for instance.
     pub fn get(&self, doc_id: DocId) -> Result<Document> {
+        Ok(self.read_doc_bytes(doc_id, decode_document)?)
+    }
+
+    /// Calls `f` with the version of the encoding of a document,
+    /// and its encoded bytes.
+    pub(crate) fn read_doc_bytes<T, F>(&self, doc_id: DocId, f: F) -> io::Result<T>
+    where
+        F: FnOnce(u32, &[u8]) -> io::Result<T>,
+    {
         let (first_doc_id, block_offset) = self.block_offset(doc_id);
         self.read_block(block_offset as usize)?;
-        let current_block_mut = self.current_block.borrow_mut();
-        let mut cursor = &current_block_mut[..];
+        let current_block = self.current_block.borrow();
+        let mut cursor = &current_block[..];
         for _ in first_doc_id..doc_id {
             let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
             cursor = &cursor[doc_length..];
         }
         let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
         cursor = &cursor[..doc_length];
-        Ok(Document::deserialize(&mut cursor)?)
+        let encoding_version = if has_doc_encoding_versions(self.format_version) {
+            VInt::deserialize(&mut cursor)?.val() as u32
+        } else {
+            LEGACY_DOC_ENCODING_VERSION
+        };
+        f(encoding_version, cursor)
     }
 }
 
@@ -100,9 +193,36 @@ fn split_source(data: ReadOnlySource) -> (ReadOnlySource, ReadOnlySource, DocId)
     let offset = u64::deserialize(&mut serialized_offset_buf).unwrap();
     let offset = offset as usize;
     let max_doc = u32::deserialize(&mut serialized_offset_buf).unwrap();
+    let (header_len, _) = read_header(data.as_slice(), offset);
     (
-        data.slice(0, offset),
+        data.slice(header_len, offset),
         data.slice(offset, footer_offset),
         max_doc,
     )
 }
+
+/// Returns the length of the header of the store, and the number
+/// of documents it records if it is known.
+///
+/// Stores written before the header was introduced
+/// have a header length of 0.
+///
+/// `data_end` is the offset of the end of the document blocks.
+fn read_header(data: &[u8], data_end: usize) -> (usize, Option<DocId>) {
+    if data_end < HEADER_NUM_BYTES {
+        return (0, None);
+    }
+    let mut header = &data[..HEADER_NUM_BYTES];
+    let magic_number = u32::deserialize(&mut header).unwrap();
+    if magic_number != STORE_MAGIC_NUMBER {
+        // the first bytes are the length of the first block.
+        return (0, None);
+    }
+    let _format_version = u32::deserialize(&mut header).unwrap();
+    let max_doc = u32::deserialize(&mut header).unwrap();
+    if max_doc == UNKNOWN_DOC_COUNT {
+        (HEADER_NUM_BYTES, None)
+    } else {
+        (HEADER_NUM_BYTES, Some(max_doc))
+    }
+}
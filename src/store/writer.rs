@@ -1,8 +1,12 @@
 use directory::WritePtr;
 use DocId;
 use common::{BinarySerializable, VInt};
-use std::io::{self, Write};
+use std::io::{self, Seek, SeekFrom, Write};
 use super::StoreReader;
+use super::{HEADER_NUM_BYTES, STORE_FORMAT_VERSION, STORE_MAGIC_NUMBER, UNKNOWN_DOC_COUNT};
+use super::{decode_document, has_doc_encoding_versions, DOC_ENCODING_VERSION};
+#[cfg(test)]
+use super::HEADERLESS_STORE_FORMAT_VERSION;
 use lz4;
 use datastruct::SkipListBuilder;
 use common::CountingWriter;
@@ -18,7 +22,17 @@ const BLOCK_SIZE: usize = 16_384;
 ///
 /// The skip list index on the other hand, is build in memory.
 ///
+/// The store starts with a fixed-size header holding the format
+/// version and the number of documents. As the number of documents
+/// is only known once the store is closed, it is written by seeking
+/// back to the header. If the writer does not support seeking, the
+/// header keeps an unknown number of documents, and readers rely
+/// on the footer, which is always written.
+///
+/// Each document is prefixed by the version of its encoding.
 pub struct StoreWriter {
+    format_version: u32,
+    header_num_bytes: usize,
     doc: DocId,
     offset_index_writer: SkipListBuilder<u64>,
     writer: CountingWriter<WritePtr>,
@@ -31,8 +45,29 @@ impl StoreWriter {
     ///
     /// The store writer will writes blocks on disc as
     /// document are added.
-    pub fn new(writer: WritePtr) -> StoreWriter {
+    pub fn new(writer: WritePtr) -> io::Result<StoreWriter> {
+        let mut writer = CountingWriter::wrap(writer);
+        STORE_MAGIC_NUMBER.serialize(&mut writer)?;
+        STORE_FORMAT_VERSION.serialize(&mut writer)?;
+        UNKNOWN_DOC_COUNT.serialize(&mut writer)?;
+        Ok(StoreWriter {
+            format_version: STORE_FORMAT_VERSION,
+            header_num_bytes: HEADER_NUM_BYTES,
+            doc: 0,
+            offset_index_writer: SkipListBuilder::new(4),
+            writer,
+            intermediary_buffer: Vec::new(),
+            current_block: Vec::new(),
+        })
+    }
+
+    /// Creates a store writer writing stores as they were before
+    /// the header and the document encoding versions were introduced.
+    #[cfg(test)]
+    pub(crate) fn without_header(writer: WritePtr) -> StoreWriter {
         StoreWriter {
+            format_version: HEADERLESS_STORE_FORMAT_VERSION,
+            header_num_bytes: 0,
             doc: 0,
             offset_index_writer: SkipListBuilder::new(4),
             writer: CountingWriter::wrap(writer),
@@ -41,6 +76,12 @@ impl StoreWriter {
         }
     }
 
+    /// Offset of the end of the written data, relative
+    /// to the end of the header.
+    fn data_offset(&self) -> u64 {
+        (self.writer.written_bytes() - self.header_num_bytes) as u64
+    }
+
     /// Store a new document.
     ///
     /// The document id is implicitely the number of times
@@ -48,7 +89,37 @@ impl StoreWriter {
     ///
     pub fn store(&mut self, stored_document: &Document) -> io::Result<()> {
         self.intermediary_buffer.clear();
+        if has_doc_encoding_versions(self.format_version) {
+            VInt(u64::from(DOC_ENCODING_VERSION)).serialize(&mut self.intermediary_buffer)?;
+        }
         stored_document.serialize(&mut self.intermediary_buffer)?;
+        self.write_intermediary_buffer()
+    }
+
+    /// Copies a document of another store.
+    ///
+    /// The document is copied without being decoded if it
+    /// is encoded with the current version, and decoded then
+    /// re-encoded otherwise.
+    pub fn copy_doc(&mut self, store_reader: &StoreReader, doc_id: DocId) -> io::Result<()> {
+        store_reader.read_doc_bytes(doc_id, |encoding_version, doc_bytes| {
+            if encoding_version == DOC_ENCODING_VERSION
+                && has_doc_encoding_versions(self.format_version)
+            {
+                self.intermediary_buffer.clear();
+                VInt(u64::from(encoding_version)).serialize(&mut self.intermediary_buffer)?;
+                self.intermediary_buffer.extend_from_slice(doc_bytes);
+                self.write_intermediary_buffer()
+            } else {
+                let doc = decode_document(encoding_version, doc_bytes)?;
+                self.store(&doc)
+            }
+        })
+    }
+
+    /// Appends the encoded document of the intermediary buffer
+    /// to the current block.
+    fn write_intermediary_buffer(&mut self) -> io::Result<()> {
         let doc_num_bytes = self.intermediary_buffer.len();
         VInt(doc_num_bytes as u64).serialize(&mut self.current_block)?;
         self.current_block.write_all(&self.intermediary_buffer[..])?;
@@ -63,14 +134,23 @@ impl StoreWriter {
     /// This method is an optimization compared to iterating over the documents
     /// in the store and adding them one by one, as the store's data will
     /// not be decompressed and then recompressed.
+    ///
+    /// If the store reader has another format version, its documents
+    /// are copied one by one instead.
     pub fn stack(&mut self, store_reader: &StoreReader) -> io::Result<()> {
+        if store_reader.format_version() != self.format_version {
+            for doc_id in 0..store_reader.max_doc() {
+                self.copy_doc(store_reader, doc_id)?;
+            }
+            return Ok(());
+        }
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
-            self.offset_index_writer
-                .insert(u64::from(self.doc), &(self.writer.written_bytes() as u64))?;
+            let data_offset = self.data_offset();
+            self.offset_index_writer.insert(u64::from(self.doc), &data_offset)?;
         }
         let doc_offset = self.doc;
-        let start_offset = self.writer.written_bytes() as u64;
+        let start_offset = self.data_offset();
 
         // just bulk write all of the block of the given reader.
         self.writer.write_all(store_reader.block_data())?;
@@ -95,8 +175,8 @@ impl StoreWriter {
         }
         (self.intermediary_buffer.len() as u32).serialize(&mut self.writer)?;
         self.writer.write_all(&self.intermediary_buffer)?;
-        self.offset_index_writer
-            .insert(u64::from(self.doc), &(self.writer.written_bytes() as u64))?;
+        let data_offset = self.data_offset();
+        self.offset_index_writer.insert(u64::from(self.doc), &data_offset)?;
         self.current_block.clear();
         Ok(())
     }
@@ -104,7 +184,9 @@ impl StoreWriter {
     /// Finalized the store writer.
     ///
     /// Compress the last unfinished block if any,
-    /// and serializes the skip list index on disc.
+    /// serializes the skip list index on disc, and records
+    /// the number of documents in the header if the writer
+    /// supports seeking.
     pub fn close(mut self) -> io::Result<()> {
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
@@ -113,6 +195,12 @@ impl StoreWriter {
         self.offset_index_writer.write(&mut self.writer)?;
         header_offset.serialize(&mut self.writer)?;
         self.doc.serialize(&mut self.writer)?;
+        let doc_count_position = (HEADER_NUM_BYTES - 4) as u64;
+        let has_header = self.header_num_bytes > 0;
+        if has_header && self.writer.seek(SeekFrom::Start(doc_count_position)).is_ok() {
+            self.doc.serialize(&mut self.writer)?;
+            self.writer.seek(SeekFrom::End(0))?;
+        }
         self.writer.flush()
     }
 }
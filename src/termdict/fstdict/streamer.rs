@@ -1,26 +1,44 @@
-use fst::{IntoStreamer, Streamer};
+use fst::{Automaton, IntoStreamer, Streamer};
+use fst::automaton::AlwaysMatch;
 use fst::map::{Stream, StreamBuilder};
 use postings::TermInfo;
 use super::TermDictionaryImpl;
 use termdict::{TermDictionary, TermOrdinal, TermStreamer, TermStreamerBuilder};
 
 /// See [`TermStreamerBuilder`](./trait.TermStreamerBuilder.html)
-pub struct TermStreamerBuilderImpl<'a> {
+///
+/// The terms streamed are restricted to the ones accepted by
+/// the automaton `A`, if any (see `TermDictionaryImpl::search`).
+pub struct TermStreamerBuilderImpl<'a, A = AlwaysMatch>
+where
+    A: Automaton,
+{
     fst_map: &'a TermDictionaryImpl,
-    stream_builder: StreamBuilder<'a>,
+    stream_builder: StreamBuilder<'a, A>,
+    min_doc_freq: u32,
 }
 
-impl<'a> TermStreamerBuilderImpl<'a> {
-    pub(crate) fn new(fst_map: &'a TermDictionaryImpl, stream_builder: StreamBuilder<'a>) -> Self {
+impl<'a, A> TermStreamerBuilderImpl<'a, A>
+where
+    A: Automaton,
+{
+    pub(crate) fn new(
+        fst_map: &'a TermDictionaryImpl,
+        stream_builder: StreamBuilder<'a, A>,
+    ) -> Self {
         TermStreamerBuilderImpl {
             fst_map,
             stream_builder,
+            min_doc_freq: 0u32,
         }
     }
 }
 
-impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
-    type Streamer = TermStreamerImpl<'a>;
+impl<'a, A> TermStreamerBuilder for TermStreamerBuilderImpl<'a, A>
+where
+    A: Automaton,
+{
+    type Streamer = TermStreamerImpl<'a, A>;
 
     fn ge<T: AsRef<[u8]>>(mut self, bound: T) -> Self {
         self.stream_builder = self.stream_builder.ge(bound);
@@ -42,10 +60,16 @@ impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
         self
     }
 
+    fn doc_freq_ge(mut self, min_doc_freq: u32) -> Self {
+        self.min_doc_freq = min_doc_freq;
+        self
+    }
+
     fn into_stream(self) -> Self::Streamer {
         TermStreamerImpl {
             fst_map: self.fst_map,
             stream: self.stream_builder.into_stream(),
+            min_doc_freq: self.min_doc_freq,
             term_ord: 0u64,
             current_key: Vec::with_capacity(100),
             current_value: TermInfo::default(),
@@ -54,25 +78,35 @@ impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
 }
 
 /// See [`TermStreamer`](./trait.TermStreamer.html)
-pub struct TermStreamerImpl<'a> {
+pub struct TermStreamerImpl<'a, A = AlwaysMatch>
+where
+    A: Automaton,
+{
     fst_map: &'a TermDictionaryImpl,
-    stream: Stream<'a>,
+    stream: Stream<'a, A>,
+    min_doc_freq: u32,
     term_ord: TermOrdinal,
     current_key: Vec<u8>,
     current_value: TermInfo,
 }
 
-impl<'a> TermStreamer for TermStreamerImpl<'a> {
+impl<'a, A> TermStreamer for TermStreamerImpl<'a, A>
+where
+    A: Automaton,
+{
     fn advance(&mut self) -> bool {
-        if let Some((term, term_ord)) = self.stream.next() {
+        while let Some((term, term_ord)) = self.stream.next() {
+            let term_info = self.fst_map.term_info_from_ord(term_ord);
+            if term_info.doc_freq < self.min_doc_freq {
+                continue;
+            }
             self.current_key.clear();
             self.current_key.extend_from_slice(term);
             self.term_ord = term_ord;
-            self.current_value = self.fst_map.term_info_from_ord(term_ord);
-            true
-        } else {
-            false
+            self.current_value = term_info;
+            return true;
         }
+        false
     }
 
     fn term_ord(&self) -> TermOrdinal {
@@ -112,6 +112,28 @@ impl TermInfoStore {
         }
     }
 
+    /// Checks that the header of a term info store is consistent
+    /// with the length of its data.
+    pub fn check(data: &ReadOnlySource) -> io::Result<()> {
+        let buffer = data.as_slice();
+        if buffer.len() < 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "term info store is too short to contain a header",
+            ));
+        }
+        let len = Endianness::read_u64(&buffer[0..8]) as usize;
+        let num_terms = Endianness::read_u64(&buffer[8..16]) as usize;
+        let num_blocks = (num_terms + BLOCK_LEN - 1) / BLOCK_LEN;
+        if len != num_blocks * TermInfoBlockMeta::SIZE_IN_BYTES || 16 + len + 7 > buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "term info store header is not consistent with its length",
+            ));
+        }
+        Ok(())
+    }
+
     pub fn get(&self, term_ord: TermOrdinal) -> TermInfo {
         let block_id = (term_ord as usize) / BLOCK_LEN;
         let buffer = self.block_meta_source.as_slice();
@@ -1,12 +1,13 @@
 use std::io::{self, Write};
 use fst;
 use fst::raw::Fst;
+use fst::Automaton;
 use directory::ReadOnlySource;
 use common::BinarySerializable;
 use common::CountingWriter;
 use schema::FieldType;
 use postings::TermInfo;
-use termdict::{TermDictionary, TermDictionaryBuilder, TermOrdinal};
+use termdict::{check_key_len, TermDictionary, TermDictionaryBuilder, TermOrdinal};
 use super::{TermInfoStore, TermInfoStoreWriter, TermStreamerBuilderImpl, TermStreamerImpl};
 
 fn convert_fst_error(e: fst::Error) -> io::Error {
@@ -32,6 +33,7 @@ where
     ///
     /// Prefer using `.insert(key, value)`
     pub(crate) fn insert_key(&mut self, key: &[u8]) -> io::Result<()> {
+        check_key_len(key)?;
         self.fst_builder
             .insert(key, self.term_ord)
             .map_err(convert_fst_error)?;
@@ -81,16 +83,38 @@ where
     }
 }
 
-fn open_fst_index(source: ReadOnlySource) -> fst::Map {
-    let fst = match source {
-        ReadOnlySource::Anonymous(data) => {
-            Fst::from_shared_bytes(data.data, data.start, data.len).expect("FST data is corrupted")
-        }
-        ReadOnlySource::Mmap(mmap_readonly) => {
-            Fst::from_mmap(mmap_readonly).expect("FST data is corrupted")
-        }
+fn open_fst_index(source: ReadOnlySource) -> io::Result<fst::Map> {
+    let fst_result = match source {
+        ReadOnlySource::Anonymous(data) => Fst::from_shared_bytes(data.data, data.start, data.len),
+        ReadOnlySource::Mmap(mmap_readonly) => Fst::from_mmap(mmap_readonly),
     };
-    fst::Map::from(fst)
+    let fst = fst_result.map_err(convert_fst_error)?;
+    Ok(fst::Map::from(fst))
+}
+
+/// Splits the source of a term dictionary into
+/// its fst and its term info store.
+fn split_source(source: &ReadOnlySource) -> io::Result<(ReadOnlySource, ReadOnlySource)> {
+    let total_len = source.len();
+    if total_len < 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "term dictionary is too short to contain a footer",
+        ));
+    }
+    let length_offset = total_len - 8;
+    let mut split_len_buffer: &[u8] = &source.as_slice()[length_offset..];
+    let footer_size = u64::deserialize(&mut split_len_buffer)? as usize;
+    if footer_size > length_offset {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "term dictionary footer is longer than the dictionary",
+        ));
+    }
+    let split_len = length_offset - footer_size;
+    let fst_source = source.slice(0, split_len);
+    let values_source = source.slice(split_len, length_offset);
+    Ok((fst_source, values_source))
 }
 
 /// See [`TermDictionary`](./trait.TermDictionary.html)
@@ -99,27 +123,40 @@ pub struct TermDictionaryImpl {
     term_info_store: TermInfoStore,
 }
 
+impl TermDictionaryImpl {
+    /// Returns a range builder, to stream the terms accepted
+    /// by the automaton.
+    ///
+    /// The automaton drives the traversal of the dictionary:
+    /// the terms starting with a prefix the automaton cannot
+    /// match are never visited.
+    pub(crate) fn search<A: Automaton>(&self, automaton: A) -> TermStreamerBuilderImpl<A> {
+        TermStreamerBuilderImpl::new(self, self.fst_index.search(automaton))
+    }
+}
+
 impl<'a> TermDictionary<'a> for TermDictionaryImpl {
     type Streamer = TermStreamerImpl<'a>;
 
     type StreamBuilder = TermStreamerBuilderImpl<'a>;
 
     fn from_source(source: ReadOnlySource) -> Self {
-        let total_len = source.len();
-        let length_offset = total_len - 8;
-        let mut split_len_buffer: &[u8] = &source.as_slice()[length_offset..];
-        let footer_size = u64::deserialize(&mut split_len_buffer)
-            .expect("Deserializing 8 bytes should always work") as usize;
-        let split_len = length_offset - footer_size;
-        let fst_source = source.slice(0, split_len);
-        let values_source = source.slice(split_len, length_offset);
-        let fst_index = open_fst_index(fst_source);
+        let (fst_source, values_source) =
+            split_source(&source).expect("Term dictionary footer is corrupted");
+        let fst_index = open_fst_index(fst_source).expect("FST data is corrupted");
         TermDictionaryImpl {
             fst_index,
             term_info_store: TermInfoStore::open(&values_source),
         }
     }
 
+    fn check_source(source: &ReadOnlySource) -> io::Result<()> {
+        let (fst_source, values_source) = split_source(source)?;
+        TermInfoStore::check(&values_source)?;
+        open_fst_index(fst_source)?;
+        Ok(())
+    }
+
     fn empty(field_type: FieldType) -> Self {
         let term_dictionary_data: Vec<u8> =
             TermDictionaryBuilderImpl::new(Vec::<u8>::new(), field_type)
@@ -0,0 +1,223 @@
+use fst::Automaton;
+use std::cmp;
+use std::rc::Rc;
+use std::str;
+
+/// Returns the Levenshtein distance between two strings,
+/// counted in chars.
+pub(crate) fn edit_distance(left: &str, right: &str) -> usize {
+    let right_chars: Vec<char> = right.chars().collect();
+    let mut previous_row: Vec<usize> = (0..right_chars.len() + 1).collect();
+    for left_char in left.chars() {
+        previous_row = next_row(&previous_row, &right_chars, left_char);
+    }
+    previous_row[right_chars.len()]
+}
+
+// Given the edit distances between a prefix `p` of a string and
+// all of the prefixes of `chars`, returns the edit distances between
+// `p + c` and all of the prefixes of `chars`.
+fn next_row(previous_row: &[usize], chars: &[char], c: char) -> Vec<usize> {
+    let mut current_row = Vec::with_capacity(chars.len() + 1);
+    current_row.push(previous_row[0] + 1);
+    for (j, &right_char) in chars.iter().enumerate() {
+        let substitution_cost = if c == right_char { 0 } else { 1 };
+        let distance = cmp::min(
+            previous_row[j] + substitution_cost,
+            cmp::min(previous_row[j + 1] + 1, current_row[j] + 1),
+        );
+        current_row.push(distance);
+    }
+    current_row
+}
+
+// Returns the length of the utf-8 encoding of a char,
+// given its first byte, or 0 if the byte cannot start a char.
+fn utf8_len(first_byte: u8) -> usize {
+    match first_byte {
+        0x00...0x7F => 1,
+        0xC0...0xDF => 2,
+        0xE0...0xEF => 3,
+        0xF0...0xF7 => 4,
+        _ => 0,
+    }
+}
+
+/// State of a `LevenshteinAutomaton`.
+#[derive(Clone, Debug)]
+pub(crate) struct LevenshteinState {
+    // edit distances between the chars accepted so far
+    // and all of the prefixes of the target.
+    // Empty if the bytes accepted are not valid utf-8.
+    //
+    // They are shared by the states of the bytes of a same char,
+    // which only differ by their `char_bytes`.
+    distances: Rc<Vec<usize>>,
+    // bytes of the char being accepted.
+    char_bytes: [u8; 4],
+    num_char_bytes: usize,
+}
+
+impl LevenshteinState {
+    fn invalid() -> LevenshteinState {
+        LevenshteinState {
+            distances: Rc::new(Vec::new()),
+            char_bytes: [0u8; 4],
+            num_char_bytes: 0,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.distances.is_empty()
+    }
+}
+
+/// Automaton accepting the utf-8 strings within a maximum
+/// Levenshtein distance, counted in chars, of a target string.
+///
+/// It is meant to be run over a term dictionary (see
+/// `TermDictionaryImpl::search`): as soon as no string starting
+/// with the bytes accepted so far can be within the maximum distance,
+/// the automaton stops, and whole branches of the dictionary are skipped.
+pub(crate) struct LevenshteinAutomaton {
+    chars: Vec<char>,
+    max_distance: usize,
+}
+
+impl LevenshteinAutomaton {
+    /// Creates an automaton accepting the strings within
+    /// `max_distance` of `target`.
+    pub fn new(target: &str, max_distance: u8) -> LevenshteinAutomaton {
+        LevenshteinAutomaton {
+            chars: target.chars().collect(),
+            max_distance: max_distance as usize,
+        }
+    }
+}
+
+impl Automaton for LevenshteinAutomaton {
+    type State = LevenshteinState;
+
+    fn start(&self) -> LevenshteinState {
+        LevenshteinState {
+            distances: Rc::new((0..self.chars.len() + 1).collect()),
+            char_bytes: [0u8; 4],
+            num_char_bytes: 0,
+        }
+    }
+
+    fn is_match(&self, state: &LevenshteinState) -> bool {
+        state.is_valid() && state.num_char_bytes == 0
+            && state.distances[self.chars.len()] <= self.max_distance
+    }
+
+    fn can_match(&self, state: &LevenshteinState) -> bool {
+        // the distances can only increase with the chars to come.
+        state.is_valid() && state.distances.iter().any(|&distance| distance <= self.max_distance)
+    }
+
+    fn accept(&self, state: &LevenshteinState, byte: u8) -> LevenshteinState {
+        if !state.is_valid() {
+            return LevenshteinState::invalid();
+        }
+        let mut char_bytes = state.char_bytes;
+        char_bytes[state.num_char_bytes] = byte;
+        let num_char_bytes = state.num_char_bytes + 1;
+        let char_len = utf8_len(char_bytes[0]);
+        if char_len == 0 || (num_char_bytes > 1 && byte & 0xC0 != 0x80) {
+            return LevenshteinState::invalid();
+        }
+        if num_char_bytes < char_len {
+            return LevenshteinState {
+                distances: Rc::clone(&state.distances),
+                char_bytes,
+                num_char_bytes,
+            };
+        }
+        match str::from_utf8(&char_bytes[..char_len]) {
+            Ok(s) => {
+                let c = s.chars().next().expect("A non-empty str has a char");
+                LevenshteinState {
+                    distances: Rc::new(next_row(&state.distances, &self.chars, c)),
+                    char_bytes: [0u8; 4],
+                    num_char_bytes: 0,
+                }
+            }
+            Err(_) => LevenshteinState::invalid(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, LevenshteinAutomaton};
+    use fst::Automaton;
+    use std::rc::Rc;
+
+    fn accepts(automaton: &LevenshteinAutomaton, bytes: &[u8]) -> bool {
+        let mut state = automaton.start();
+        for &byte in bytes {
+            if !automaton.can_match(&state) {
+                return false;
+            }
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("title", "titel"), 2);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_levenshtein_automaton() {
+        let words = [
+            "", "a", "ab", "abc", "abcd", "acb", "bc", "xabc", "café", "cafe", "caf", "kafé",
+            "été", "ete", "et",
+        ];
+        for target in &words {
+            for max_distance in 0..3 {
+                let automaton = LevenshteinAutomaton::new(target, max_distance);
+                for word in &words {
+                    let expected = edit_distance(target, word) <= max_distance as usize;
+                    assert_eq!(
+                        accepts(&automaton, word.as_bytes()),
+                        expected,
+                        "{:?} {:?} {}",
+                        target,
+                        word,
+                        max_distance
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_levenshtein_automaton_rejects_invalid_utf8() {
+        let automaton = LevenshteinAutomaton::new("ab", 2);
+        assert!(accepts(&automaton, b"a"));
+        assert!(!accepts(&automaton, b"a\xFF"));
+        assert!(!accepts(&automaton, b"a\xC3"));
+        assert!(!accepts(&automaton, b"\x80b"));
+        assert!(!accepts(&automaton, b"\xC3b"));
+        assert!(accepts(&automaton, "aé".as_bytes()));
+    }
+
+    #[test]
+    fn test_levenshtein_automaton_shares_distances_within_a_char() {
+        let automaton = LevenshteinAutomaton::new("été", 1);
+        let start = automaton.start();
+        let first_byte_state = automaton.accept(&start, "é".as_bytes()[0]);
+        assert!(Rc::ptr_eq(&start.distances, &first_byte_state.distances));
+        let char_state = automaton.accept(&first_byte_state, "é".as_bytes()[1]);
+        assert!(!Rc::ptr_eq(&start.distances, &char_state.distances));
+        assert_eq!(*char_state.distances, vec![1, 0, 1, 2]);
+    }
+}
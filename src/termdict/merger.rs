@@ -39,6 +39,7 @@ impl<'a> Ord for HeapItem<'a> {
 pub struct TermMerger<'a> {
     heap: BinaryHeap<HeapItem<'a>>,
     current_streamers: Vec<HeapItem<'a>>,
+    min_doc_freq: u32,
 }
 
 impl<'a> TermMerger<'a> {
@@ -56,9 +57,23 @@ impl<'a> TermMerger<'a> {
                     segment_ord: ord,
                 })
                 .collect(),
+            min_doc_freq: 0u32,
         }
     }
 
+    /// Only returns the terms whose document frequency, summed
+    /// over all of the segments, is greater or equal to `min_doc_freq`.
+    ///
+    /// The document frequency includes the deleted documents.
+    ///
+    /// The streams must not be filtered with
+    /// [`TermStreamerBuilder::doc_freq_ge`](./trait.TermStreamerBuilder.html#tymethod.doc_freq_ge),
+    /// as a term can be frequent overall without being frequent in any segment.
+    pub fn doc_freq_ge(mut self, min_doc_freq: u32) -> TermMerger<'a> {
+        self.min_doc_freq = min_doc_freq;
+        self
+    }
+
     fn advance_segments(&mut self) {
         let streamers = &mut self.current_streamers;
         let heap = &mut self.heap;
@@ -72,8 +87,17 @@ impl<'a> TermMerger<'a> {
     /// Advance the term iterator to the next term.
     /// Returns true if there is indeed another term
     /// False if there is none.
-    #[allow(while_let_loop)]
     pub fn advance(&mut self) -> bool {
+        while self.advance_unfiltered() {
+            if self.min_doc_freq == 0 || self.doc_freq() >= self.min_doc_freq {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[allow(while_let_loop)]
+    fn advance_unfiltered(&mut self) -> bool {
         self.advance_segments();
         if let Some(head) = self.heap.pop() {
             self.current_streamers.push(head);
@@ -103,6 +127,19 @@ impl<'a> TermMerger<'a> {
         self.current_streamers[0].streamer.key()
     }
 
+    /// Returns the document frequency of the current term,
+    /// summed over all of the segments.
+    ///
+    /// This method may be called
+    /// iff advance() has been called before
+    /// and "true" was returned.
+    pub fn doc_freq(&self) -> u32 {
+        self.current_streamers
+            .iter()
+            .map(|heap_item| heap_item.streamer.value().doc_freq)
+            .sum()
+    }
+
     /// Returns the sorted list of segment ordinals
     /// that include the current term.
     ///
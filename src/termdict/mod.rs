@@ -50,11 +50,35 @@ term `stream`.
 use schema::{Field, FieldType, Term};
 use directory::ReadOnlySource;
 use postings::TermInfo;
+use std::io;
 
 /// Position of the term in the sorted list of terms.
 pub type TermOrdinal = u64;
 
+/// Maximum length of a term, in bytes.
+///
+/// Terms are serialized with their field, so that the text of a term
+/// is at most `MAX_TERM_LEN - 4` bytes long.
+pub const MAX_TERM_LEN: usize = u16::max_value() as usize - 1;
+
+/// Returns an error if the key is too long to be inserted in
+/// a term dictionary.
+pub(crate) fn check_key_len(key: &[u8]) -> io::Result<()> {
+    if key.len() > MAX_TERM_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Term of length {} exceeds the maximum term length ({}).",
+                key.len(),
+                MAX_TERM_LEN
+            ),
+        ));
+    }
+    Ok(())
+}
+
 pub use self::merger::TermMerger;
+pub use self::term_ordinal_mapping::TermOrdinalMapping;
 
 #[cfg(not(feature = "streamdict"))]
 mod fstdict;
@@ -69,6 +93,10 @@ pub use self::streamdict::{TermDictionaryBuilderImpl, TermDictionaryImpl, TermSt
                            TermStreamerImpl};
 
 mod merger;
+mod term_ordinal_mapping;
+mod levenshtein;
+
+pub(crate) use self::levenshtein::{edit_distance, LevenshteinAutomaton};
 use std::io;
 
 /// Dictionary associating sorted `&[u8]` to values
@@ -85,6 +113,12 @@ where
     /// Opens a `TermDictionary` given a data source.
     fn from_source(source: ReadOnlySource) -> Self;
 
+    /// Checks that the footer of a term dictionary is consistent
+    /// with the length of its data, without opening it.
+    ///
+    /// This is cheap, and detects most of the truncated dictionaries.
+    fn check_source(source: &ReadOnlySource) -> io::Result<()>;
+
     /// Returns the number of terms in the dictionary.
     /// Term ordinals range from 0 to `num_terms() - 1`.
     fn num_terms(&self) -> usize;
@@ -145,6 +179,10 @@ where
     /// Inserts a `(key, value)` pair in the term dictionary.
     ///
     /// *Keys have to be inserted in order.*
+    ///
+    /// Keys are arbitrary byte strings, that do not need to be valid utf-8.
+    /// The empty key is allowed, and comes before all of the other keys.
+    /// Keys longer than `MAX_TERM_LEN` are rejected.
     fn insert<K: AsRef<[u8]>>(&mut self, key: K, value: &TermInfo) -> io::Result<()>;
 
     /// Finalize writing the builder, and returns the underlying
@@ -189,6 +227,14 @@ pub trait TermStreamer: Sized {
     /// `V::default()`.
     fn value(&self) -> &TermInfo;
 
+    /// Returns the document frequency of the current term,
+    /// deleted documents included.
+    ///
+    /// This is a shortcut for `.value().doc_freq()`.
+    fn doc_freq(&self) -> u32 {
+        self.value().doc_freq()
+    }
+
     /// Return the next `(key, value)` pair.
     fn next(&mut self) -> Option<(&[u8], &TermInfo)> {
         if self.advance() {
@@ -217,6 +263,14 @@ pub trait TermStreamerBuilder {
     /// Limit the range to terms lesser or equal to the bound
     fn le<T: AsRef<[u8]>>(self, bound: T) -> Self;
 
+    /// Only stream the terms with a document frequency
+    /// greater or equal to `min_doc_freq`.
+    ///
+    /// The document frequency includes the deleted documents.
+    /// The other terms are skipped by the streamer itself,
+    /// without ever being returned.
+    fn doc_freq_ge(self, min_doc_freq: u32) -> Self;
+
     /// Creates the stream corresponding to the range
     /// of terms defined using the `TermStreamerBuilder`.
     fn into_stream(self) -> Self::Streamer;
@@ -227,14 +281,18 @@ mod tests {
     use super::{TermDictionaryBuilderImpl, TermDictionaryImpl, TermStreamerImpl};
     use directory::{Directory, RAMDirectory, ReadOnlySource};
     use std::path::PathBuf;
-    use schema::{Document, FieldType, SchemaBuilder, TEXT};
+    use schema::{Document, FieldType, IndexRecordOption, SchemaBuilder, TEXT};
     use core::Index;
+    use indexer::NoMergePolicy;
     use std::str;
     use termdict::TermStreamer;
     use termdict::TermStreamerBuilder;
     use termdict::TermDictionary;
     use termdict::TermDictionaryBuilder;
+    use termdict::MAX_TERM_LEN;
     use postings::TermInfo;
+    use docset::DocSet;
+    use test_utils::SeededRng;
 
     const BLOCK_SIZE: usize = 1_500;
 
@@ -247,6 +305,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_term_dictionary_key_too_long() {
+        let field_type = FieldType::Str(TEXT);
+        let mut term_dictionary_builder =
+            TermDictionaryBuilderImpl::new(vec![], field_type).unwrap();
+        let long_key = vec![b'a'; MAX_TERM_LEN + 1];
+        assert!(
+            term_dictionary_builder
+                .insert(&long_key[..MAX_TERM_LEN], &make_term_info(0u64))
+                .is_ok()
+        );
+        assert!(
+            term_dictionary_builder
+                .insert(&long_key, &make_term_info(1u64))
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_term_ordinals() {
         const COUNTRIES: [&'static str; 7] = [
@@ -336,7 +412,7 @@ mod tests {
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "a b d f");
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 index_writer.commit().unwrap();
             }
@@ -344,7 +420,7 @@ mod tests {
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "a b c d f");
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 index_writer.commit().unwrap();
             }
@@ -352,7 +428,7 @@ mod tests {
                 {
                     let mut doc = Document::default();
                     doc.add_text(text_field, "e f");
-                    index_writer.add_document(doc);
+                    index_writer.add_document(doc).unwrap();
                 }
                 index_writer.commit().unwrap();
             }
@@ -364,12 +440,90 @@ mod tests {
         let mut term_it = field_searcher.terms();
         let mut term_string = String::new();
         while term_it.advance() {
-            //let term = Term::from_bytes(term_it.key());
-            term_string.push_str(unsafe { str::from_utf8_unchecked(term_it.key()) });
+            term_string.push_str(str::from_utf8(term_it.key()).unwrap());
         }
         assert_eq!(&*term_string, "abcdef");
     }
 
+    #[test]
+    fn test_term_iterator_doc_freq_ge() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for text in &["a b c", "a c", "b c"] {
+                index_writer.add_document(doc!(text_field => *text)).unwrap();
+            }
+            index_writer.commit().unwrap();
+            for text in &["a b d", "a b", "b"] {
+                index_writer.add_document(doc!(text_field => *text)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let field_searcher = searcher.field(text_field);
+        let frequent_terms = |min_doc_freq: u32| {
+            let mut term_it = field_searcher.terms_with_doc_freq_ge(min_doc_freq);
+            let mut terms = vec![];
+            while term_it.advance() {
+                let term = str::from_utf8(term_it.key()).unwrap().to_string();
+                terms.push((term, term_it.doc_freq()));
+            }
+            terms
+        };
+        let pair = |term: &str, doc_freq: u32| (term.to_string(), doc_freq);
+        assert_eq!(
+            frequent_terms(0),
+            vec![pair("a", 4), pair("b", 5), pair("c", 3), pair("d", 1)]
+        );
+        assert_eq!(frequent_terms(2), vec![pair("a", 4), pair("b", 5), pair("c", 3)]);
+        // `a` appears in 2 documents of each segment.
+        assert_eq!(frequent_terms(4), vec![pair("a", 4), pair("b", 5)]);
+        assert_eq!(frequent_terms(5), vec![pair("b", 5)]);
+        assert!(frequent_terms(6).is_empty());
+    }
+
+    #[test]
+    fn test_term_info_doc_freq_matches_postings() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                let text = format!("a{} b{} common", i % 7, i % 11);
+                index_writer.add_document(doc!(text_field => text)).unwrap();
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let inverted_index = searcher.segment_reader(0).inverted_index(text_field);
+        let mut num_terms = 0;
+        let mut term_it = inverted_index.terms().stream();
+        while term_it.advance() {
+            let term_info = term_it.value().clone();
+            assert_eq!(term_it.doc_freq(), term_info.doc_freq());
+            let mut postings =
+                inverted_index.read_postings_from_terminfo(&term_info, IndexRecordOption::Basic);
+            let mut num_postings = 0u32;
+            while postings.advance() {
+                num_postings += 1;
+            }
+            assert_eq!(num_postings, term_info.doc_freq());
+            if term_it.key() == b"common" {
+                assert_eq!(term_info.doc_freq(), 1_000);
+            }
+            num_terms += 1;
+        }
+        assert_eq!(num_terms, 7 + 11 + 1);
+        assert_eq!(num_terms, inverted_index.terms().num_terms());
+    }
+
     #[test]
     fn test_term_dictionary_stream() {
         let ids: Vec<_> = (0u32..10_000u32)
@@ -531,6 +685,112 @@ mod tests {
         assert!(!stream.advance());
     }
 
+    #[test]
+    fn test_term_dictionary_random_byte_keys() {
+        let mut rng = SeededRng::new(42);
+        let mut keys: Vec<Vec<u8>> = vec![vec![], vec![0u8], vec![0u8, 0u8], vec![255u8]];
+        for _ in 0..2_000 {
+            let len = rng.gen_range(0, 8) as usize;
+            let mut key: Vec<u8> = (0..len).map(|_| rng.gen_range(0, 256) as u8).collect();
+            match rng.gen_range(0, 3) {
+                0 => key.insert(0, 0u8),
+                1 => key.insert(0, 255u8),
+                _ => {}
+            }
+            keys.push(key);
+        }
+        keys.sort();
+        keys.dedup();
+        let field_type = FieldType::Str(TEXT);
+        let buffer: Vec<u8> = {
+            let mut term_dictionary_builder =
+                TermDictionaryBuilderImpl::new(vec![], field_type).unwrap();
+            for (ord, key) in keys.iter().enumerate() {
+                term_dictionary_builder
+                    .insert(key, &make_term_info(ord as u64))
+                    .unwrap();
+            }
+            term_dictionary_builder.finish().unwrap()
+        };
+        let source = ReadOnlySource::from(buffer);
+        let term_dictionary: TermDictionaryImpl = TermDictionaryImpl::from_source(source);
+        assert_eq!(term_dictionary.num_terms(), keys.len());
+        for (ord, key) in keys.iter().enumerate() {
+            assert_eq!(term_dictionary.get(key), Some(make_term_info(ord as u64)));
+            assert_eq!(term_dictionary.term_ord(key), Some(ord as u64));
+            let mut bytes = vec![];
+            assert!(term_dictionary.ord_to_term(ord as u64, &mut bytes));
+            assert_eq!(&bytes, key);
+            let mut streamer = term_dictionary.range().ge(key).into_stream();
+            assert!(streamer.advance());
+            assert_eq!(streamer.key(), &key[..]);
+            assert_eq!(streamer.term_ord(), ord as u64);
+            let mut streamer = term_dictionary.range().gt(key).into_stream();
+            match keys.get(ord + 1) {
+                Some(next_key) => {
+                    assert!(streamer.advance());
+                    assert_eq!(streamer.key(), &next_key[..]);
+                }
+                None => assert!(!streamer.advance()),
+            }
+        }
+        assert!(term_dictionary.get(&[255u8; 10]).is_none());
+        let mut streamed_keys: Vec<Vec<u8>> = vec![];
+        let mut streamer = term_dictionary.stream();
+        while streamer.advance() {
+            streamed_keys.push(streamer.key().to_owned());
+        }
+        assert_eq!(streamed_keys, keys);
+    }
+
+    #[test]
+    fn test_term_dictionary_search_levenshtein() {
+        use termdict::LevenshteinAutomaton;
+        let mut keys: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abd".to_vec(),
+            b"ab\xFF".to_vec(),
+            b"acb".to_vec(),
+            b"bc".to_vec(),
+            b"xyz".to_vec(),
+            "ébc".as_bytes().to_vec(),
+        ];
+        keys.sort();
+        let field_type = FieldType::Str(TEXT);
+        let buffer: Vec<u8> = {
+            let mut term_dictionary_builder =
+                TermDictionaryBuilderImpl::new(vec![], field_type).unwrap();
+            for (ord, key) in keys.iter().enumerate() {
+                term_dictionary_builder
+                    .insert(key, &make_term_info(ord as u64))
+                    .unwrap();
+            }
+            term_dictionary_builder.finish().unwrap()
+        };
+        let source = ReadOnlySource::from(buffer);
+        let term_dictionary: TermDictionaryImpl = TermDictionaryImpl::from_source(source);
+        let search = |target: &str, max_distance: u8| -> Vec<String> {
+            let mut streamer = term_dictionary
+                .search(LevenshteinAutomaton::new(target, max_distance))
+                .into_stream();
+            let mut terms = vec![];
+            while streamer.advance() {
+                let ord = streamer.term_ord();
+                assert_eq!(&keys[ord as usize][..], streamer.key());
+                assert_eq!(streamer.value(), &make_term_info(ord));
+                terms.push(str::from_utf8(streamer.key()).unwrap().to_string());
+            }
+            terms
+        };
+        assert_eq!(search("abc", 0), vec!["abc"]);
+        // `ab\xFF` is not valid utf-8.
+        assert_eq!(search("abc", 1), vec!["abc", "abd", "bc", "ébc"]);
+        assert_eq!(search("abc", 2), vec!["abc", "abd", "acb", "bc", "ébc"]);
+        assert_eq!(search("", 0), vec![""]);
+        assert!(search("qwerty", 2).is_empty());
+    }
+
     #[test]
     fn test_stream_range_boundaries() {
         let field_type = FieldType::Str(TEXT);
@@ -584,6 +844,29 @@ mod tests {
             let range = term_dictionary.range().ge([0u8]).lt([5u8]).into_stream();
             assert_eq!(value_list(range), vec![0u32, 1u32, 2u32, 3u32, 4u32]);
         }
+        {
+            let range = term_dictionary.range().doc_freq_ge(7).into_stream();
+            assert_eq!(value_list(range), vec![7u32, 8u32, 9u32]);
+        }
+        {
+            let range = term_dictionary.range().doc_freq_ge(0).into_stream();
+            assert_eq!(value_list(range).len(), 10);
+        }
+        {
+            let range = term_dictionary.range().doc_freq_ge(10).into_stream();
+            assert!(value_list(range).is_empty());
+        }
+        {
+            let mut range = term_dictionary
+                .range()
+                .lt([8u8])
+                .doc_freq_ge(5)
+                .into_stream();
+            assert!(range.advance());
+            assert_eq!(range.key(), &[5u8]);
+            assert_eq!(range.term_ord(), 5);
+            assert_eq!(value_list(range), vec![6u32, 7u32]);
+        }
     }
 
 }
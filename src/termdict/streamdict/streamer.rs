@@ -1,6 +1,8 @@
 #![allow(should_implement_trait)]
 
 use std::cmp::max;
+use fst::Automaton;
+use fst::automaton::AlwaysMatch;
 use super::TermDictionaryImpl;
 use termdict::{TermStreamer, TermStreamerBuilder};
 use postings::TermInfo;
@@ -17,11 +19,31 @@ fn stream_before<'a>(
         cursor: stream_data,
         term_delta_decoder: TermDeltaDecoder::with_previous_term(prev_key),
         term_info_decoder: TermInfoDeltaDecoder::from_checkpoint(&checkpoint, has_positions),
+        min_doc_freq: 0u32,
+        automaton: AlwaysMatch,
     }
 }
 
+/// Returns true iff the automaton accepts the key.
+fn is_accepted<A: Automaton>(automaton: &A, key: &[u8]) -> bool {
+    let mut state = automaton.start();
+    for &byte in key {
+        if !automaton.can_match(&state) {
+            return false;
+        }
+        state = automaton.accept(&state, byte);
+    }
+    automaton.is_match(&state)
+}
+
 /// See [`TermStreamerBuilder`](./trait.TermStreamerBuilder.html)
-pub struct TermStreamerBuilderImpl<'a> {
+///
+/// The terms streamed are restricted to the ones accepted by
+/// the automaton `A`, if any (see `TermDictionaryImpl::search`).
+pub struct TermStreamerBuilderImpl<'a, A = AlwaysMatch>
+where
+    A: Automaton,
+{
     term_dictionary: &'a TermDictionaryImpl,
     origin: usize,
     offset_from: usize,
@@ -29,10 +51,15 @@ pub struct TermStreamerBuilderImpl<'a> {
     current_key: Vec<u8>,
     term_info: TermInfo,
     has_positions: bool,
+    min_doc_freq: u32,
+    automaton: A,
 }
 
-impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
-    type Streamer = TermStreamerImpl<'a>;
+impl<'a, A> TermStreamerBuilder for TermStreamerBuilderImpl<'a, A>
+where
+    A: Automaton,
+{
+    type Streamer = TermStreamerImpl<'a, A>;
 
     /// Limit the range to terms greater or equal to the bound
     fn ge<T: AsRef<[u8]>>(mut self, bound: T) -> Self {
@@ -94,6 +121,11 @@ impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
         self
     }
 
+    fn doc_freq_ge(mut self, min_doc_freq: u32) -> Self {
+        self.min_doc_freq = min_doc_freq;
+        self
+    }
+
     /// Build the streamer.
     fn into_stream(self) -> Self::Streamer {
         let data: &[u8] = self.term_dictionary.stream_data();
@@ -106,6 +138,8 @@ impl<'a> TermStreamerBuilder for TermStreamerBuilderImpl<'a> {
             cursor: &data[start..stop],
             term_delta_decoder,
             term_info_decoder,
+            min_doc_freq: self.min_doc_freq,
+            automaton: self.automaton,
         }
     }
 }
@@ -140,6 +174,19 @@ fn get_offset<'a, P: Fn(&[u8]) -> bool>(
 
 impl<'a> TermStreamerBuilderImpl<'a> {
     pub(crate) fn new(term_dictionary: &'a TermDictionaryImpl, has_positions: bool) -> Self {
+        TermStreamerBuilderImpl::with_automaton(term_dictionary, has_positions, AlwaysMatch)
+    }
+}
+
+impl<'a, A> TermStreamerBuilderImpl<'a, A>
+where
+    A: Automaton,
+{
+    pub(crate) fn with_automaton(
+        term_dictionary: &'a TermDictionaryImpl,
+        has_positions: bool,
+        automaton: A,
+    ) -> Self {
         let data = term_dictionary.stream_data();
         let origin = data.as_ptr() as usize;
         TermStreamerBuilderImpl {
@@ -150,28 +197,45 @@ impl<'a> TermStreamerBuilderImpl<'a> {
             offset_to: data.len(),
             current_key: Vec::with_capacity(300),
             has_positions,
+            min_doc_freq: 0u32,
+            automaton,
         }
     }
 }
 
 /// See [`TermStreamer`](./trait.TermStreamer.html)
-pub struct TermStreamerImpl<'a> {
+pub struct TermStreamerImpl<'a, A = AlwaysMatch>
+where
+    A: Automaton,
+{
     cursor: &'a [u8],
     term_delta_decoder: TermDeltaDecoder,
     term_info_decoder: TermInfoDeltaDecoder,
+    min_doc_freq: u32,
+    automaton: A,
 }
 
-impl<'a> TermStreamer for TermStreamerImpl<'a> {
+impl<'a, A> TermStreamer for TermStreamerImpl<'a, A>
+where
+    A: Automaton,
+{
     fn advance(&mut self) -> bool {
-        if self.cursor.is_empty() {
-            return false;
+        // terms are delta-encoded: the skipped terms still need to be decoded.
+        // Unlike the fst term dictionary, the terms that the automaton rejects
+        // are therefore all visited.
+        while !self.cursor.is_empty() {
+            let mut cursor: &[u8] = &self.cursor;
+            let code: u8 = cursor[0];
+            cursor = self.term_delta_decoder.decode(code, &cursor[1..]);
+            cursor = self.term_info_decoder.decode(code, cursor);
+            self.cursor = cursor;
+            if self.term_info_decoder.term_info().doc_freq >= self.min_doc_freq
+                && is_accepted(&self.automaton, self.term_delta_decoder.term())
+            {
+                return true;
+            }
         }
-        let mut cursor: &[u8] = &self.cursor;
-        let code: u8 = cursor[0];
-        cursor = self.term_delta_decoder.decode(code, &cursor[1..]);
-        cursor = self.term_info_decoder.decode(code, cursor);
-        self.cursor = cursor;
-        true
+        false
     }
 
     fn key(&self) -> &[u8] {
@@ -3,6 +3,7 @@
 use std::io::{self, Write};
 use super::CheckPoint;
 use fst;
+use fst::Automaton;
 
 use fst::raw::Fst;
 use directory::ReadOnlySource;
@@ -12,7 +13,7 @@ use postings::TermInfo;
 use schema::FieldType;
 use super::{DeltaTermInfo, TermDeltaEncoder, TermInfoDeltaEncoder};
 use fst::raw::Node;
-use termdict::{TermDictionary, TermDictionaryBuilder, TermStreamer};
+use termdict::{check_key_len, TermDictionary, TermDictionaryBuilder, TermStreamer};
 use super::{TermStreamerBuilderImpl, TermStreamerImpl};
 use termdict::TermStreamerBuilder;
 use std::mem::transmute;
@@ -91,6 +92,7 @@ where
     ///
     /// Prefer using `.insert(key, value)`
     pub(crate) fn insert_key(&mut self, key: &[u8]) -> io::Result<()> {
+        check_key_len(key)?;
         if self.len % INDEX_INTERVAL == 0 {
             self.add_index_entry();
         }
@@ -242,6 +244,14 @@ impl TermDictionaryImpl {
         (term, checkpoint)
     }
 
+    /// Returns a range builder, to stream the terms accepted
+    /// by the automaton.
+    ///
+    /// The terms are all decoded, and filtered by the automaton.
+    pub(crate) fn search<A: Automaton>(&self, automaton: A) -> TermStreamerBuilderImpl<A> {
+        TermStreamerBuilderImpl::with_automaton(self, self.has_positions, automaton)
+    }
+
     fn strictly_previous_key_checkpoint_offset(&self, key: &[u8]) -> (Vec<u8>, usize) {
         let fst_map = &self.fst_index;
         let fst = fst_map.as_fst();
@@ -322,6 +332,29 @@ impl<'a> TermDictionary<'a> for TermDictionaryImpl {
         }
     }
 
+    fn check_source(source: &ReadOnlySource) -> io::Result<()> {
+        // one byte for the positions flag, two addresses for the footer.
+        let total_len = source.len();
+        if total_len < 17 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "term dictionary is too short to contain a footer",
+            ));
+        }
+        let body_len = total_len - 17;
+        let mut footer_buffer: &[u8] = &source.as_slice()[1 + body_len..];
+        let fst_addr = u64::deserialize(&mut footer_buffer)? as usize;
+        let checkpoints_addr = u64::deserialize(&mut footer_buffer)? as usize;
+        if fst_addr < PADDING_SIZE || fst_addr > checkpoints_addr || checkpoints_addr > body_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "term dictionary footer is not consistent with its length",
+            ));
+        }
+        open_fst_index(source.slice(1 + fst_addr, 1 + checkpoints_addr))?;
+        Ok(())
+    }
+
     /// Lookups the value corresponding to the key.
     fn get<K: AsRef<[u8]>>(&self, target_key: K) -> Option<TermInfo> {
         let mut streamer = self.range().ge(&target_key).into_stream();
@@ -0,0 +1,166 @@
+use core::SegmentReader;
+use schema::Field;
+use termdict::{TermDictionary, TermMerger, TermOrdinal, TermStreamer};
+
+/// `TermOrdinalMapping` associates the terms of a field to
+/// global ordinals, shared by all of the segments of a `Searcher`.
+///
+/// Global ordinals are defined as the position of the term in
+/// the sorted list of the unique terms of all segments.
+///
+/// Each segment comes with a translation table from its
+/// segment local term ordinals to the global ordinals.
+/// Collectors can then accumulate values into a single
+/// global array, instead of merging term dictionaries
+/// at the end of the collection.
+///
+/// Building the mapping requires one pass over all of the
+/// term dictionaries. It is typically obtained via
+/// [`Searcher::facet_ordinal_mapping`](../struct.Searcher.html#method.facet_ordinal_mapping),
+/// which caches it for the lifetime of the searcher generation.
+pub struct TermOrdinalMapping {
+    field: Field,
+    // segment ord -> (segment term ord -> global term ord)
+    per_segment_global_ords: Vec<Vec<TermOrdinal>>,
+    // terms are stored contiguously.
+    // The global term ord `i` spans `term_bytes[term_offsets[i]..term_offsets[i+1]]`.
+    term_bytes: Vec<u8>,
+    term_offsets: Vec<usize>,
+}
+
+impl TermOrdinalMapping {
+    /// Builds the mapping of a field, for a given list of segments.
+    pub fn build(segment_readers: &[SegmentReader], field: Field) -> TermOrdinalMapping {
+        let inverted_indexes = segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(field))
+            .collect::<Vec<_>>();
+        let mut per_segment_global_ords: Vec<Vec<TermOrdinal>> = inverted_indexes
+            .iter()
+            .map(|inverted_index| vec![0; inverted_index.terms().num_terms()])
+            .collect();
+        let term_streams = inverted_indexes
+            .iter()
+            .map(|inverted_index| inverted_index.terms().stream())
+            .collect::<Vec<_>>();
+        let mut term_merger = TermMerger::new(term_streams);
+        let mut term_bytes = Vec::new();
+        let mut term_offsets = vec![0];
+        let mut global_ord: TermOrdinal = 0;
+        while term_merger.advance() {
+            for heap_item in term_merger.current_kvs() {
+                let segment_ord = heap_item.segment_ord;
+                let term_ord = heap_item.streamer.term_ord();
+                per_segment_global_ords[segment_ord][term_ord as usize] = global_ord;
+            }
+            term_bytes.extend_from_slice(term_merger.key());
+            term_offsets.push(term_bytes.len());
+            global_ord += 1;
+        }
+        TermOrdinalMapping {
+            field,
+            per_segment_global_ords,
+            term_bytes,
+            term_offsets,
+        }
+    }
+
+    /// Returns the field this mapping was built for.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the number of unique terms over all of the segments.
+    ///
+    /// Global term ordinals range from `0` to `num_terms() - 1`.
+    pub fn num_terms(&self) -> usize {
+        self.term_offsets.len() - 1
+    }
+
+    /// Returns the translation table of a segment, from its
+    /// local term ordinals to global term ordinals.
+    pub fn segment_mapping(&self, segment_ord: usize) -> &[TermOrdinal] {
+        &self.per_segment_global_ords[segment_ord]
+    }
+
+    /// Translates a segment local term ordinal into a global
+    /// term ordinal.
+    pub fn global_ord(&self, segment_ord: usize, term_ord: TermOrdinal) -> TermOrdinal {
+        self.per_segment_global_ords[segment_ord][term_ord as usize]
+    }
+
+    /// Returns the bytes of the term associated to a global term ordinal.
+    pub fn term(&self, global_ord: TermOrdinal) -> &[u8] {
+        let global_ord = global_ord as usize;
+        let start = self.term_offsets[global_ord];
+        let stop = self.term_offsets[global_ord + 1];
+        &self.term_bytes[start..stop]
+    }
+
+    /// Returns the first global term ordinal whose term
+    /// is greater or equal to `key`.
+    pub fn lower_bound(&self, key: &[u8]) -> TermOrdinal {
+        let (mut start, mut stop) = (0, self.num_terms());
+        while start < stop {
+            let mid = (start + stop) / 2;
+            if self.term(mid as TermOrdinal) < key {
+                start = mid + 1;
+            } else {
+                stop = mid;
+            }
+        }
+        start as TermOrdinal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use Index;
+    use schema::{SchemaBuilder, STRING};
+    use indexer::NoMergePolicy;
+    use super::TermOrdinalMapping;
+    use termdict::TermDictionary;
+
+    #[test]
+    fn test_term_ordinal_mapping() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for segment_terms in &[vec!["b", "d"], vec!["a", "d", "e"], vec!["c"]] {
+                for term in segment_terms {
+                    index_writer.add_document(doc!(text_field=>*term)).unwrap();
+                }
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_readers = searcher.segment_readers();
+        assert_eq!(segment_readers.len(), 3);
+        let mapping = TermOrdinalMapping::build(segment_readers, text_field);
+        assert_eq!(mapping.field(), text_field);
+        assert_eq!(mapping.num_terms(), 5);
+        let terms: Vec<&[u8]> = (0..5).map(|ord| mapping.term(ord)).collect();
+        assert_eq!(terms, vec![&b"a"[..], &b"b"[..], &b"c"[..], &b"d"[..], &b"e"[..]]);
+        for (segment_ord, segment_reader) in segment_readers.iter().enumerate() {
+            let inverted_index = segment_reader.inverted_index(text_field);
+            let term_dict = inverted_index.terms();
+            let segment_mapping = mapping.segment_mapping(segment_ord);
+            assert_eq!(segment_mapping.len(), term_dict.num_terms());
+            let mut term_buffer = vec![];
+            for (term_ord, &global_ord) in segment_mapping.iter().enumerate() {
+                assert!(term_dict.ord_to_term(term_ord as u64, &mut term_buffer));
+                assert_eq!(mapping.term(global_ord), &term_buffer[..]);
+                assert_eq!(mapping.global_ord(segment_ord, term_ord as u64), global_ord);
+            }
+        }
+        assert_eq!(mapping.lower_bound(b""), 0);
+        assert_eq!(mapping.lower_bound(b"b"), 1);
+        assert_eq!(mapping.lower_bound(b"bb"), 2);
+        assert_eq!(mapping.lower_bound(b"f"), 5);
+    }
+}
@@ -0,0 +1,389 @@
+/*!
+Deterministic generators of documents and indexes,
+shared by the benchmarks and the tests.
+
+All of the generators are seeded: the same seed always generates
+the same documents, so that benchmarks and tests are reproducible.
+
+The generated corpus has
+* a `text` field, whose tokens follow a zipfian distribution
+over a vocabulary of terms named `t0`, `t1`, ... by decreasing frequency,
+* a `uniform` fast `u64` field, uniformly distributed,
+* a `skewed` fast `u64` field, whose values follow a zipfian distribution,
+* a `category` facet field, whose facets are the leaves of a tree
+of configurable depth and fanout.
+
+```rust
+use tantivy::test_utils::CorpusGenerator;
+
+let mut corpus_generator = CorpusGenerator::new(42);
+corpus_generator.set_vocabulary_size(1_000);
+let index = corpus_generator.create_index_in_ram(100).unwrap();
+index.load_searchers().unwrap();
+assert_eq!(index.searcher().num_docs(), 100);
+```
+
+The [`oracle`](./oracle/index.html) module checks the evaluation
+of the queries against a naive in-memory engine, on random corpora
+and queries generated from the same kind of seeds.
+*/
+
+pub mod oracle;
+
+use Index;
+use Result;
+use schema::{Document, Facet, Field, Schema, SchemaBuilder, FAST, INT_INDEXED, STORED, TEXT};
+
+/// Seedable pseudo-random number generator (xorshift64*).
+///
+/// It is not meant to be cryptographically secure,
+/// only to be fast and deterministic.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> SeededRng {
+        // the state of a xorshift generator must not be 0.
+        let state = (seed ^ 0x9E37_79B9_7F4A_7C15).wrapping_mul(0xBF58_476D_1CE4_E5B9) | 1;
+        SeededRng { state }
+    }
+
+    /// Returns the next pseudo-random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a pseudo-random `f64` in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a pseudo-random `u64` in `[low, high)`.
+    ///
+    /// # Panics
+    /// Panics if `low >= high`.
+    pub fn gen_range(&mut self, low: u64, high: u64) -> u64 {
+        assert!(low < high, "empty range");
+        low + self.next_u64() % (high - low)
+    }
+}
+
+/// Zipfian distribution over the ranks `0..num_ranks`.
+///
+/// The probability of the rank `k` is proportional to `1 / (k + 1)^exponent`.
+#[derive(Clone, Debug)]
+pub struct ZipfDistribution {
+    // cumulative probabilities of the ranks.
+    cdf: Vec<f64>,
+}
+
+impl ZipfDistribution {
+    /// Creates a zipfian distribution.
+    ///
+    /// # Panics
+    /// Panics if `num_ranks` is `0`.
+    pub fn new(num_ranks: usize, exponent: f64) -> ZipfDistribution {
+        assert!(num_ranks > 0, "a zipfian distribution requires at least one rank");
+        let mut cdf = Vec::with_capacity(num_ranks);
+        let mut total = 0f64;
+        for rank in 0..num_ranks {
+            total += 1f64 / ((rank + 1) as f64).powf(exponent);
+            cdf.push(total);
+        }
+        for cumulated_probability in &mut cdf {
+            *cumulated_probability /= total;
+        }
+        ZipfDistribution { cdf }
+    }
+
+    /// Returns the number of ranks of the distribution.
+    pub fn num_ranks(&self) -> usize {
+        self.cdf.len()
+    }
+
+    /// Returns the probability of a rank.
+    pub fn probability(&self, rank: usize) -> f64 {
+        if rank == 0 {
+            self.cdf[0]
+        } else {
+            self.cdf[rank] - self.cdf[rank - 1]
+        }
+    }
+
+    /// Samples a rank.
+    pub fn sample(&self, rng: &mut SeededRng) -> usize {
+        let value = rng.next_f64();
+        let search_result = self.cdf.binary_search_by(|cumulated_probability| {
+            cumulated_probability
+                .partial_cmp(&value)
+                .expect("probabilities are not NaN")
+        });
+        match search_result {
+            Ok(rank) => rank + 1,
+            Err(rank) => rank,
+        }.min(self.cdf.len() - 1)
+    }
+}
+
+/// Returns the term of the vocabulary of the generated
+/// text with the given rank, `t0` being the most frequent.
+pub fn term_of_rank(rank: usize) -> String {
+    format!("t{}", rank)
+}
+
+/// Fields of the schema of the generated corpus.
+#[derive(Clone, Copy, Debug)]
+pub struct CorpusFields {
+    /// Text field, with zipfian distributed terms.
+    pub text: Field,
+    /// Fast `u64` field, uniformly distributed.
+    pub uniform: Field,
+    /// Fast `u64` field, with zipfian distributed values.
+    pub skewed: Field,
+    /// Facet field.
+    pub category: Field,
+}
+
+/// Generator of a corpus of documents.
+///
+/// See the [module documentation](./index.html).
+#[derive(Clone, Debug)]
+pub struct CorpusGenerator {
+    seed: u64,
+    vocabulary_size: usize,
+    zipf_exponent: f64,
+    num_tokens_per_doc: (usize, usize),
+    max_u64_value: u64,
+    facet_depth: usize,
+    facet_fanout: usize,
+    schema: Schema,
+    fields: CorpusFields,
+}
+
+impl CorpusGenerator {
+    /// Creates a generator with a given seed, and default settings:
+    /// * a vocabulary of `10_000` terms with a zipfian exponent of `1`,
+    /// * between `10` and `100` tokens per document,
+    /// * `u64` values below `1_000_000`,
+    /// * facets of depth `3` and fanout `5`.
+    pub fn new(seed: u64) -> CorpusGenerator {
+        let mut schema_builder = SchemaBuilder::default();
+        let fields = CorpusFields {
+            text: schema_builder.add_text_field("text", TEXT | STORED),
+            uniform: schema_builder.add_u64_field("uniform", INT_INDEXED | FAST),
+            skewed: schema_builder.add_u64_field("skewed", INT_INDEXED | FAST),
+            category: schema_builder.add_facet_field("category"),
+        };
+        CorpusGenerator {
+            seed,
+            vocabulary_size: 10_000,
+            zipf_exponent: 1f64,
+            num_tokens_per_doc: (10, 100),
+            max_u64_value: 1_000_000,
+            facet_depth: 3,
+            facet_fanout: 5,
+            schema: schema_builder.build(),
+            fields,
+        }
+    }
+
+    /// Sets the number of distinct terms of the text field.
+    pub fn set_vocabulary_size(&mut self, vocabulary_size: usize) {
+        self.vocabulary_size = vocabulary_size;
+    }
+
+    /// Sets the exponent of the zipfian distributions.
+    pub fn set_zipf_exponent(&mut self, zipf_exponent: f64) {
+        self.zipf_exponent = zipf_exponent;
+    }
+
+    /// Sets the bounds, inclusive, of the number of tokens of the text field.
+    pub fn set_num_tokens_per_doc(&mut self, min_num_tokens: usize, max_num_tokens: usize) {
+        assert!(min_num_tokens <= max_num_tokens);
+        self.num_tokens_per_doc = (min_num_tokens, max_num_tokens);
+    }
+
+    /// Sets the upper bound, exclusive, of the values of the `u64` fields.
+    pub fn set_max_u64_value(&mut self, max_u64_value: u64) {
+        self.max_u64_value = max_u64_value;
+    }
+
+    /// Sets the depth and the fanout of the tree of facets.
+    pub fn set_facet_tree(&mut self, facet_depth: usize, facet_fanout: usize) {
+        assert!(facet_depth > 0 && facet_fanout > 0);
+        self.facet_depth = facet_depth;
+        self.facet_fanout = facet_fanout;
+    }
+
+    /// Returns the schema of the corpus.
+    pub fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    /// Returns the fields of the corpus.
+    pub fn fields(&self) -> CorpusFields {
+        self.fields
+    }
+
+    /// Generates `num_docs` documents.
+    pub fn documents(&self, num_docs: usize) -> impl Iterator<Item = Document> {
+        let mut rng = SeededRng::new(self.seed);
+        let term_distribution = ZipfDistribution::new(self.vocabulary_size, self.zipf_exponent);
+        let value_distribution =
+            ZipfDistribution::new(self.max_u64_value as usize, self.zipf_exponent);
+        let generator = self.clone();
+        (0..num_docs)
+            .map(move |_| generator.document(&mut rng, &term_distribution, &value_distribution))
+    }
+
+    fn document(
+        &self,
+        rng: &mut SeededRng,
+        term_distribution: &ZipfDistribution,
+        value_distribution: &ZipfDistribution,
+    ) -> Document {
+        let (min_num_tokens, max_num_tokens) = self.num_tokens_per_doc;
+        let num_tokens = rng.gen_range(min_num_tokens as u64, max_num_tokens as u64 + 1);
+        let text = (0..num_tokens)
+            .map(|_| term_of_rank(term_distribution.sample(rng)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let facet_fanout = self.facet_fanout as u64;
+        let facet = Facet::from_path(
+            (0..self.facet_depth).map(|_| format!("f{}", rng.gen_range(0, facet_fanout))),
+        );
+        let mut doc = Document::default();
+        doc.add_text(self.fields.text, &text);
+        doc.add_u64(self.fields.uniform, rng.gen_range(0, self.max_u64_value));
+        doc.add_u64(self.fields.skewed, value_distribution.sample(rng) as u64);
+        doc.add_facet(self.fields.category, facet);
+        doc
+    }
+
+    /// Creates an index in RAM holding `num_docs` generated documents,
+    /// in a single segment.
+    pub fn create_index_in_ram(&self, num_docs: usize) -> Result<Index> {
+        let index = Index::create_in_ram(self.schema());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+            for doc in self.documents(num_docs) {
+                index_writer.add_document(doc)?;
+            }
+            index_writer.commit()?;
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{term_of_rank, CorpusGenerator, SeededRng, ZipfDistribution};
+    use collector::CountCollector;
+    use query::TermQuery;
+    use schema::{Facet, IndexRecordOption, Term, Value};
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_seeded_rng() {
+        let mut rng = SeededRng::new(1);
+        let values: Vec<u64> = (0..100).map(|_| rng.next_u64()).collect();
+        let mut same_seed_rng = SeededRng::new(1);
+        assert!(values.iter().all(|&value| value == same_seed_rng.next_u64()));
+        let mut other_seed_rng = SeededRng::new(2);
+        assert!(values.iter().any(|&value| value != other_seed_rng.next_u64()));
+        let mut rng = SeededRng::new(3);
+        let num_samples = 100_000;
+        let mean = (0..num_samples).map(|_| rng.next_f64()).sum::<f64>() / num_samples as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+        assert!((0..1_000).all(|_| rng.gen_range(10, 20) < 20));
+    }
+
+    #[test]
+    fn test_zipf_distribution() {
+        let zipf_distribution = ZipfDistribution::new(1_000, 1f64);
+        let total_probability: f64 = (0..1_000)
+            .map(|rank| zipf_distribution.probability(rank))
+            .sum();
+        assert!((total_probability - 1f64).abs() < 1e-9);
+        // the rank `k` is `k + 1` times less frequent than the rank `0`.
+        let ratio = zipf_distribution.probability(0) / zipf_distribution.probability(9);
+        assert!((ratio - 10f64).abs() < 1e-6);
+        let mut rng = SeededRng::new(4);
+        let num_samples = 100_000;
+        let mut counts = vec![0usize; 1_000];
+        for _ in 0..num_samples {
+            counts[zipf_distribution.sample(&mut rng)] += 1;
+        }
+        for rank in 0..5 {
+            let frequency = counts[rank] as f64 / num_samples as f64;
+            let probability = zipf_distribution.probability(rank);
+            assert!((frequency - probability).abs() < 0.1 * probability);
+        }
+    }
+
+    #[test]
+    fn test_corpus_generator() {
+        let mut corpus_generator = CorpusGenerator::new(5);
+        corpus_generator.set_vocabulary_size(100);
+        corpus_generator.set_num_tokens_per_doc(5, 10);
+        corpus_generator.set_max_u64_value(1_000);
+        corpus_generator.set_facet_tree(2, 3);
+        let fields = corpus_generator.fields();
+        let docs: Vec<_> = corpus_generator.documents(1_000).collect();
+        assert_eq!(docs.len(), 1_000);
+        // the documents only depend on the seed.
+        let same_docs: Vec<_> = corpus_generator.documents(1_000).collect();
+        assert_eq!(docs, same_docs);
+
+        let mut term_counts: HashMap<String, usize> = HashMap::new();
+        let mut facets = HashSet::new();
+        for doc in &docs {
+            let text = doc.get_first(fields.text).unwrap().text();
+            let num_tokens = text.split(' ').count();
+            assert!(num_tokens >= 5 && num_tokens <= 10);
+            for token in text.split(' ') {
+                *term_counts.entry(token.to_string()).or_insert(0) += 1;
+            }
+            assert!(doc.get_first(fields.uniform).unwrap().u64_value() < 1_000);
+            assert!(doc.get_first(fields.skewed).unwrap().u64_value() < 1_000);
+            let facet = match *doc.get_first(fields.category).unwrap() {
+                Value::Facet(ref facet) => facet.clone(),
+                _ => panic!("expected a facet"),
+            };
+            assert_eq!(facet.to_string().matches('/').count(), 2);
+            facets.insert(facet);
+        }
+        assert!(term_counts.len() <= 100);
+        assert_eq!(facets.len(), 9);
+        assert!(facets.contains(&Facet::from("/f0/f2")));
+        // the most frequent terms come first.
+        let count = |rank: usize| term_counts.get(&term_of_rank(rank)).cloned().unwrap_or(0);
+        assert!(count(0) > count(1));
+        assert!(count(1) > count(9));
+
+        let index = corpus_generator.create_index_in_ram(1_000).unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1_000);
+        let term_query = TermQuery::new(
+            Term::from_field_text(fields.text, &term_of_rank(0)),
+            IndexRecordOption::Basic,
+        );
+        let mut count_collector = CountCollector::default();
+        searcher.search(&term_query, &mut count_collector).unwrap();
+        let expected_count = docs.iter()
+            .filter(|doc| {
+                let text = doc.get_first(fields.text).unwrap().text();
+                text.split(' ').any(|token| token == "t0")
+            })
+            .count();
+        assert_eq!(count_collector.count(), expected_count);
+    }
+}
@@ -0,0 +1,854 @@
+/*!
+Naive in-memory search engine, used as an oracle to test the
+evaluation of the queries by differential testing.
+
+The oracle keeps the documents in memory, and evaluates the queries
+by brute force: a term matches the documents whose tokens contain it,
+a phrase the documents where its tokens are adjacent, and the boolean
+queries are evaluated with set algebra.
+
+[`check_seed`](./fn.check_seed.html) generates from a seed
+* a small random schema, with one to three text fields,
+* a random corpus, indexed with random deletes either in a single segment,
+or in several segments,
+* random query trees, made of terms, phrases, prefixes, regexes, ranges
+and `AllQuery` leaves,
+combined by `BooleanQuery`, `DisjunctionMaxQuery`, `ConstantScoreQuery`
+and `BoostQuery`.
+
+It then checks that, for each query, tantivy and the oracle agree on
+* the matching documents, whether they are scored or not, and their count,
+* the facet counts of the matching documents,
+* the scores of the documents, when the oracle is able to compute them, i.e.
+when the terms and the phrases are wrapped into a `ConstantScoreQuery`.
+
+A few fixed seeds run with the normal test run (see `tests/differential.rs`).
+A failure reports its seed, the layout of the index, and the failing query.
+It can be reproduced with
+
+```bash
+TANTIVY_ORACLE_SEEDS=<seed> cargo test --test differential
+```
+
+New queries are covered by adding a variant to
+[`OracleQuery`](./enum.OracleQuery.html), with its evaluation, its
+conversion to a tantivy `Query`, and its generation in `random_query`.
+*/
+
+use Index;
+use Result;
+use Score;
+use Searcher;
+use SegmentLocalId;
+use DocAddress;
+use DocId;
+use SegmentReader;
+use collector::{Collector, FacetCollector, TopCollector};
+use fastfield::FastFieldReader;
+use indexer::NoMergePolicy;
+use query::{AllQuery, BooleanQuery, BoostQuery, ConstantScoreQuery, DisjunctionMaxQuery,
+            FastFieldRangeQuery, Occur, PhraseQuery, PrefixQuery, Query, RangeQuery,
+            RegexQuery, TermQuery};
+use regex::Regex;
+use schema::{Document, Facet, Field, IndexRecordOption, Schema, SchemaBuilder, Term, FAST,
+             INT_INDEXED, TEXT};
+use std::collections::BTreeMap;
+use std::result;
+use super::SeededRng;
+
+/// Number of queries checked for each seed.
+const NUM_QUERIES_PER_SEED: usize = 50;
+
+/// Maximum depth of the generated query trees.
+const MAX_QUERY_DEPTH: usize = 3;
+
+/// Number of documents retrieved to check the top-k selection.
+const TOP_K: usize = 3;
+
+/// Fields of the generated schema.
+#[derive(Clone, Debug)]
+pub struct OracleFields {
+    /// Unique `u64` identifier of the documents, indexed and fast.
+    pub id: Field,
+    /// Text fields, indexed with positions.
+    pub texts: Vec<Field>,
+    /// `u64` field, indexed and fast.
+    pub num: Field,
+    /// Facet field.
+    pub category: Field,
+}
+
+/// Document of the oracle.
+#[derive(Clone, Debug)]
+pub struct OracleDoc {
+    /// Identifier of the document.
+    pub id: u64,
+    /// Tokens of each of the text fields.
+    pub texts: Vec<Vec<String>>,
+    /// Value of the `num` field.
+    pub num: u64,
+    /// Facet of the document, e.g. `/f0/f2`.
+    pub facet: Facet,
+}
+
+/// Operation of the generated indexing sequence.
+#[derive(Clone, Debug)]
+pub enum IndexOp {
+    /// Adds a document.
+    Add(OracleDoc),
+    /// Deletes the document with the given identifier, if it exists.
+    Delete(u64),
+    /// Commits, if the layout of the index has several segments.
+    Commit,
+}
+
+/// Layout of the generated index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// All of the documents are committed at once, in a single segment.
+    SingleSegment,
+    /// The documents are committed at random points,
+    /// in several segments that are not merged.
+    MultiSegment,
+}
+
+/// Query evaluated by the oracle, convertible into a tantivy `Query`.
+#[derive(Clone, Debug)]
+pub enum OracleQuery {
+    /// Term of the text field with the given ordinal.
+    Term(usize, String),
+    /// Phrase of the text field with the given ordinal.
+    Phrase(usize, Vec<String>),
+    /// Prefix of the terms of the text field with the given ordinal.
+    Prefix(usize, String),
+    /// Regex matching entirely the terms of the text field with the given ordinal.
+    Regex(usize, String),
+    /// Values of the `num` field in `[low, high)`,
+    /// searched with a `FastFieldRangeQuery` if `fast` is true,
+    /// and with a `RangeQuery` otherwise.
+    Range {
+        /// Inclusive lower bound.
+        low: u64,
+        /// Exclusive upper bound.
+        high: u64,
+        /// Searches the fast field rather than the terms.
+        fast: bool,
+    },
+    /// All of the documents.
+    All,
+    /// `BooleanQuery`.
+    Boolean(Vec<(Occur, OracleQuery)>),
+    /// `DisjunctionMaxQuery`, with its tie breaker.
+    DisjunctionMax(Vec<OracleQuery>, Score),
+    /// `ConstantScoreQuery`, with its score.
+    ConstantScore(Box<OracleQuery>, Score),
+    /// `BoostQuery`, with its boost.
+    Boost(Box<OracleQuery>, Score),
+}
+
+impl OracleQuery {
+    /// Returns true iff the document matches the query.
+    pub fn matches(&self, doc: &OracleDoc) -> bool {
+        match *self {
+            OracleQuery::Term(field_ord, ref word) => doc.texts[field_ord].contains(word),
+            OracleQuery::Phrase(field_ord, ref words) => doc.texts[field_ord]
+                .windows(words.len())
+                .any(|window| window == &words[..]),
+            OracleQuery::Prefix(field_ord, ref prefix) => doc.texts[field_ord]
+                .iter()
+                .any(|token| token.starts_with(prefix.as_str())),
+            OracleQuery::Regex(field_ord, ref pattern) => {
+                let regex = Regex::new(&format!("^(?:{})$", pattern)).expect("valid pattern");
+                doc.texts[field_ord].iter().any(|token| regex.is_match(token))
+            }
+            OracleQuery::Range { low, high, .. } => low <= doc.num && doc.num < high,
+            OracleQuery::All => true,
+            OracleQuery::Boolean(ref clauses) => {
+                let mut has_positive_clause = false;
+                let mut has_must_clause = false;
+                let mut matches_should_clause = false;
+                for &(occur, ref clause) in clauses {
+                    match occur {
+                        Occur::Must => {
+                            if !clause.matches(doc) {
+                                return false;
+                            }
+                            has_positive_clause = true;
+                            has_must_clause = true;
+                        }
+                        Occur::Should => {
+                            has_positive_clause = true;
+                            matches_should_clause |= clause.matches(doc);
+                        }
+                        Occur::MustNot => {
+                            if clause.matches(doc) {
+                                return false;
+                            }
+                        }
+                    }
+                }
+                if has_positive_clause {
+                    // the `Should` clauses are optional if there are `Must` clauses.
+                    has_must_clause || matches_should_clause
+                } else {
+                    // `MustNot` clauses alone match all of the other documents.
+                    !clauses.is_empty()
+                }
+            }
+            OracleQuery::DisjunctionMax(ref disjuncts, _) => {
+                disjuncts.iter().any(|disjunct| disjunct.matches(doc))
+            }
+            OracleQuery::ConstantScore(ref query, _) | OracleQuery::Boost(ref query, _) => {
+                query.matches(doc)
+            }
+        }
+    }
+
+    /// Returns the score of a document matching the query,
+    /// or `None` if the oracle cannot compute it.
+    ///
+    /// The scores of the terms and the phrases depend on the
+    /// statistics of the index, and are not computed: only the queries
+    /// wrapping them into a `ConstantScore` query have a score.
+    pub fn score(&self, doc: &OracleDoc) -> Option<Score> {
+        match *self {
+            OracleQuery::Term(..) | OracleQuery::Phrase(..) => None,
+            OracleQuery::Prefix(..)
+            | OracleQuery::Regex(..)
+            | OracleQuery::Range { .. }
+            | OracleQuery::All => Some(1.0),
+            OracleQuery::Boolean(ref clauses) => {
+                if clauses.len() == 1 && clauses[0].0 != Occur::MustNot {
+                    return clauses[0].1.score(doc);
+                }
+                let positive_clauses: Vec<&OracleQuery> = clauses
+                    .iter()
+                    .filter(|&&(occur, _)| occur != Occur::MustNot)
+                    .map(|&(_, ref clause)| clause)
+                    .collect();
+                if positive_clauses.is_empty() {
+                    // the documents that are not excluded all get the same score.
+                    return Some(1.0);
+                }
+                let mut score = 0.0;
+                for clause in positive_clauses {
+                    if clause.matches(doc) {
+                        score += clause.score(doc)?;
+                    }
+                }
+                Some(score)
+            }
+            OracleQuery::DisjunctionMax(ref disjuncts, tie_breaker) => {
+                let mut scores = Vec::new();
+                for disjunct in disjuncts {
+                    if disjunct.matches(doc) {
+                        scores.push(disjunct.score(doc)?);
+                    }
+                }
+                let max_score = scores.iter().cloned().fold(0.0, Score::max);
+                let sum_scores: Score = scores.iter().sum();
+                Some(max_score + tie_breaker * (sum_scores - max_score))
+            }
+            OracleQuery::ConstantScore(_, score) => Some(score),
+            OracleQuery::Boost(ref query, boost) => query.score(doc).map(|score| score * boost),
+        }
+    }
+
+    /// Converts the query into the equivalent tantivy `Query`.
+    pub fn to_query(&self, fields: &OracleFields) -> Box<Query> {
+        match *self {
+            OracleQuery::Term(field_ord, ref word) => box TermQuery::new(
+                Term::from_field_text(fields.texts[field_ord], word),
+                IndexRecordOption::WithFreqs,
+            ),
+            OracleQuery::Phrase(field_ord, ref words) => {
+                let terms: Vec<Term> = words
+                    .iter()
+                    .map(|word| Term::from_field_text(fields.texts[field_ord], word))
+                    .collect();
+                box PhraseQuery::from(terms)
+            }
+            OracleQuery::Prefix(field_ord, ref prefix) => {
+                box PrefixQuery::new(Term::from_field_text(fields.texts[field_ord], prefix))
+            }
+            OracleQuery::Regex(field_ord, ref pattern) => box RegexQuery::new(
+                fields.texts[field_ord],
+                pattern,
+            ).expect("valid pattern"),
+            OracleQuery::Range { low, high, fast } => {
+                if fast {
+                    box FastFieldRangeQuery::new_u64(fields.num, low..high)
+                } else {
+                    box RangeQuery::new_u64(fields.num, low..high)
+                }
+            }
+            OracleQuery::All => box AllQuery,
+            OracleQuery::Boolean(ref clauses) => {
+                let clauses: Vec<(Occur, Box<Query>)> = clauses
+                    .iter()
+                    .map(|&(occur, ref clause)| (occur, clause.to_query(fields)))
+                    .collect();
+                box BooleanQuery::from(clauses)
+            }
+            OracleQuery::DisjunctionMax(ref disjuncts, tie_breaker) => {
+                let disjuncts = disjuncts
+                    .iter()
+                    .map(|disjunct| disjunct.to_query(fields))
+                    .collect();
+                box DisjunctionMaxQuery::new(disjuncts, tie_breaker)
+            }
+            OracleQuery::ConstantScore(ref query, score) => {
+                let mut constant_score_query = ConstantScoreQuery::new(query.to_query(fields));
+                constant_score_query.set_score(score);
+                box constant_score_query
+            }
+            OracleQuery::Boost(ref query, boost) => {
+                box BoostQuery::new(query.to_query(fields), boost)
+            }
+        }
+    }
+}
+
+fn gen_bool(rng: &mut SeededRng, probability: f64) -> bool {
+    rng.next_f64() < probability
+}
+
+fn gen_index(rng: &mut SeededRng, len: usize) -> usize {
+    rng.gen_range(0, len as u64) as usize
+}
+
+/// Random corpus, and the sequence of operations indexing it.
+#[derive(Clone, Debug)]
+pub struct OracleCorpus {
+    schema: Schema,
+    fields: OracleFields,
+    vocabulary: Vec<String>,
+    max_num: u64,
+    ops: Vec<IndexOp>,
+}
+
+impl OracleCorpus {
+    /// Generates a schema, and a corpus of up to `60` documents
+    /// over a vocabulary of up to `6` words.
+    pub fn generate(rng: &mut SeededRng) -> OracleCorpus {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED | FAST);
+        let num_text_fields = rng.gen_range(1, 4);
+        let texts = (0..num_text_fields)
+            .map(|field_ord| schema_builder.add_text_field(&format!("text{}", field_ord), TEXT))
+            .collect();
+        let num = schema_builder.add_u64_field("num", INT_INDEXED | FAST);
+        let category = schema_builder.add_facet_field("category");
+        let fields = OracleFields {
+            id,
+            texts,
+            num,
+            category,
+        };
+        let vocabulary: Vec<String> = (0..rng.gen_range(2, 7))
+            .map(|rank| format!("w{}", rank))
+            .collect();
+        let max_num = rng.gen_range(1, 20);
+        let num_docs = rng.gen_range(1, 61);
+        let mut ops = Vec::new();
+        for doc_id in 0..num_docs {
+            let mut texts = Vec::new();
+            for _ in 0..num_text_fields {
+                let tokens: Vec<String> = (0..rng.gen_range(0, 7))
+                    .map(|_| vocabulary[gen_index(rng, vocabulary.len())].clone())
+                    .collect();
+                texts.push(tokens);
+            }
+            let num = rng.gen_range(0, max_num);
+            let facet_steps: Vec<String> =
+                (0..2).map(|_| format!("f{}", rng.gen_range(0, 3))).collect();
+            let doc = OracleDoc {
+                id: doc_id,
+                texts,
+                num,
+                facet: Facet::from_path(facet_steps),
+            };
+            ops.push(IndexOp::Add(doc));
+            if gen_bool(rng, 0.15) {
+                ops.push(IndexOp::Delete(rng.gen_range(0, doc_id + 1)));
+            }
+            if gen_bool(rng, 0.1) {
+                ops.push(IndexOp::Commit);
+            }
+        }
+        OracleCorpus {
+            schema: schema_builder.build(),
+            fields,
+            vocabulary,
+            max_num,
+            ops,
+        }
+    }
+
+    /// Returns the schema of the corpus.
+    pub fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    /// Returns the fields of the corpus.
+    pub fn fields(&self) -> &OracleFields {
+        &self.fields
+    }
+
+    /// Returns the operations indexing the corpus.
+    pub fn ops(&self) -> &[IndexOp] {
+        &self.ops[..]
+    }
+
+    /// Returns the documents that are not deleted, by identifier.
+    pub fn alive_docs(&self) -> BTreeMap<u64, &OracleDoc> {
+        let mut alive_docs = BTreeMap::new();
+        for op in &self.ops {
+            match *op {
+                IndexOp::Add(ref doc) => {
+                    alive_docs.insert(doc.id, doc);
+                }
+                IndexOp::Delete(id) => {
+                    alive_docs.remove(&id);
+                }
+                IndexOp::Commit => {}
+            }
+        }
+        alive_docs
+    }
+
+    fn document(&self, oracle_doc: &OracleDoc) -> Document {
+        let mut doc = Document::default();
+        doc.add_u64(self.fields.id, oracle_doc.id);
+        for (&field, tokens) in self.fields.texts.iter().zip(oracle_doc.texts.iter()) {
+            if !tokens.is_empty() {
+                doc.add_text(field, &tokens.join(" "));
+            }
+        }
+        doc.add_u64(self.fields.num, oracle_doc.num);
+        doc.add_facet(self.fields.category, oracle_doc.facet.clone());
+        doc
+    }
+
+    /// Creates an index in RAM, by running the operations of the corpus.
+    pub fn create_index(&self, layout: Layout) -> Result<Index> {
+        let index = Index::create_in_ram(self.schema());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+            index_writer.set_merge_policy(box NoMergePolicy::default());
+            for op in &self.ops {
+                match *op {
+                    IndexOp::Add(ref oracle_doc) => {
+                        index_writer.add_document(self.document(oracle_doc))?;
+                    }
+                    IndexOp::Delete(id) => {
+                        index_writer.delete_term(Term::from_field_u64(self.fields.id, id));
+                    }
+                    IndexOp::Commit => {
+                        if layout == Layout::MultiSegment {
+                            index_writer.commit()?;
+                        }
+                    }
+                }
+            }
+            index_writer.commit()?;
+        }
+        index.load_searchers()?;
+        Ok(index)
+    }
+
+    fn random_word(&self, rng: &mut SeededRng) -> String {
+        if gen_bool(rng, 0.1) {
+            // a word that is not in the index.
+            "missing".to_string()
+        } else {
+            self.vocabulary[gen_index(rng, self.vocabulary.len())].clone()
+        }
+    }
+
+    fn random_leaf(&self, rng: &mut SeededRng) -> OracleQuery {
+        let field_ord = gen_index(rng, self.fields.texts.len());
+        match rng.gen_range(0, 8) {
+            0 | 1 => OracleQuery::Term(field_ord, self.random_word(rng)),
+            2 | 3 => {
+                let num_words = rng.gen_range(2, 4);
+                let words = (0..num_words).map(|_| self.random_word(rng)).collect();
+                OracleQuery::Phrase(field_ord, words)
+            }
+            4 => {
+                let word = self.random_word(rng);
+                let prefix_len = rng.gen_range(0, word.len() as u64 + 1) as usize;
+                OracleQuery::Prefix(field_ord, word[..prefix_len].to_string())
+            }
+            5 => {
+                let patterns = ["w[0-2]", "w.", "(w1|w3)", ".*1", "w[^0]+", "m.*"];
+                let pattern = patterns[gen_index(rng, patterns.len())];
+                OracleQuery::Regex(field_ord, pattern.to_string())
+            }
+            6 => {
+                let low = rng.gen_range(0, self.max_num + 1);
+                let high = low + rng.gen_range(0, 6);
+                OracleQuery::Range {
+                    low,
+                    high,
+                    fast: gen_bool(rng, 0.5),
+                }
+            }
+            _ => OracleQuery::All,
+        }
+    }
+
+    /// Generates a random query tree, at most `max_depth` levels deep.
+    pub fn random_query(&self, rng: &mut SeededRng, max_depth: usize) -> OracleQuery {
+        if max_depth == 0 || gen_bool(rng, 0.3) {
+            return self.random_leaf(rng);
+        }
+        match rng.gen_range(0, 4) {
+            0 => {
+                let num_clauses = rng.gen_range(0, 5);
+                let clauses = (0..num_clauses)
+                    .map(|_| {
+                        let occur = [Occur::Must, Occur::Should, Occur::MustNot]
+                            [gen_index(rng, 3)];
+                        (occur, self.random_query(rng, max_depth - 1))
+                    })
+                    .collect();
+                OracleQuery::Boolean(clauses)
+            }
+            1 => {
+                let num_disjuncts = rng.gen_range(0, 4);
+                let disjuncts = (0..num_disjuncts)
+                    .map(|_| self.random_query(rng, max_depth - 1))
+                    .collect();
+                let tie_breaker = if gen_bool(rng, 0.5) { 0.0 } else { 0.5 };
+                OracleQuery::DisjunctionMax(disjuncts, tie_breaker)
+            }
+            2 => {
+                let score = if gen_bool(rng, 0.5) { 1.0 } else { 2.5 };
+                OracleQuery::ConstantScore(box self.random_query(rng, max_depth - 1), score)
+            }
+            _ => {
+                let boost = if gen_bool(rng, 0.5) { 0.5 } else { 2.0 };
+                OracleQuery::Boost(box self.random_query(rng, max_depth - 1), boost)
+            }
+        }
+    }
+}
+
+/// Collects the identifiers of the matching documents, without scoring them.
+struct IdCollector {
+    id_field: Field,
+    ff_reader: Option<FastFieldReader<u64>>,
+    ids: Vec<u64>,
+}
+
+impl Collector for IdCollector {
+    fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.ff_reader = Some(reader.fast_field_reader(self.id_field)?);
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let id = self.ff_reader.as_ref().unwrap().get(doc);
+        self.ids.push(id);
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+fn doc_id(searcher: &Searcher, id_field: Field, doc_address: DocAddress) -> Result<u64> {
+    let segment_reader = searcher.segment_reader(doc_address.segment_ord());
+    let ff_reader = segment_reader.fast_field_reader::<u64>(id_field)?;
+    Ok(ff_reader.get(doc_address.doc()))
+}
+
+fn is_close(left: Score, right: Score) -> bool {
+    (left - right).abs() <= 1e-4 * (1.0 + left.abs().max(right.abs()))
+}
+
+/// Returns the counts of the children of `facet`,
+/// among the facets of the given documents.
+fn oracle_facet_counts(docs: &[&OracleDoc], facet: &Facet) -> BTreeMap<String, u64> {
+    let prefix = if facet.is_root() {
+        String::new()
+    } else {
+        facet.to_string()
+    };
+    let mut facet_counts = BTreeMap::new();
+    for doc in docs {
+        let doc_facet = doc.facet.to_string();
+        if !doc_facet.starts_with(&format!("{}/", prefix)) {
+            continue;
+        }
+        let child_len = doc_facet[prefix.len() + 1..]
+            .find('/')
+            .map(|offset| prefix.len() + 1 + offset)
+            .unwrap_or(doc_facet.len());
+        *facet_counts
+            .entry(doc_facet[..child_len].to_string())
+            .or_insert(0) += 1;
+    }
+    facet_counts
+}
+
+fn check_facet_counts(
+    searcher: &Searcher,
+    corpus: &OracleCorpus,
+    query: &Query,
+    matching_docs: &[&OracleDoc],
+    facet: Facet,
+) -> result::Result<(), String> {
+    let mut facet_collector = FacetCollector::for_field(corpus.fields.category);
+    facet_collector.add_facet(facet.clone());
+    query
+        .search(searcher, &mut facet_collector)
+        .map_err(|err| format!("facet search failed: {:?}", err))?;
+    let facet_counts = facet_collector.harvest();
+    let counts: BTreeMap<String, u64> = facet_counts
+        .get(facet.clone())
+        .filter(|&(_, count)| count > 0)
+        .map(|(facet, count)| (facet.to_string(), count))
+        .collect();
+    let expected_counts = oracle_facet_counts(matching_docs, &facet);
+    if counts != expected_counts {
+        return Err(format!(
+            "facet counts of {}: expected {:?}, got {:?}",
+            facet, expected_counts, counts
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that tantivy and the oracle agree on the results of a query.
+///
+/// Returns a description of the first difference, if any.
+pub fn check_query(
+    searcher: &Searcher,
+    corpus: &OracleCorpus,
+    oracle_query: &OracleQuery,
+) -> result::Result<(), String> {
+    let id_field = corpus.fields.id;
+    let alive_docs = corpus.alive_docs();
+    let matching_docs: Vec<&OracleDoc> = alive_docs
+        .values()
+        .cloned()
+        .filter(|doc| oracle_query.matches(doc))
+        .collect();
+    let expected_ids: Vec<u64> = matching_docs.iter().map(|doc| doc.id).collect();
+    let query = oracle_query.to_query(&corpus.fields);
+    let error = |what: String| format!("{:?}: {}", oracle_query, what);
+
+    // matching documents, without scoring.
+    let mut id_collector = IdCollector {
+        id_field,
+        ff_reader: None,
+        ids: Vec::new(),
+    };
+    query
+        .search(searcher, &mut id_collector)
+        .map_err(|err| error(format!("search failed: {:?}", err)))?;
+    let mut ids = id_collector.ids;
+    ids.sort();
+    if ids != expected_ids {
+        return Err(error(format!(
+            "expected the documents {:?}, got {:?} without scoring",
+            expected_ids, ids
+        )));
+    }
+    let count = query
+        .count(searcher)
+        .map_err(|err| error(format!("count failed: {:?}", err)))?;
+    if count != expected_ids.len() {
+        return Err(error(format!(
+            "expected a count of {}, got {}",
+            expected_ids.len(),
+            count
+        )));
+    }
+
+    // matching documents and their scores.
+    let mut top_collector = TopCollector::with_limit(expected_ids.len().max(1));
+    query
+        .search(searcher, &mut top_collector)
+        .map_err(|err| error(format!("scored search failed: {:?}", err)))?;
+    let score_docs = top_collector.score_docs();
+    let mut scored_ids = Vec::with_capacity(score_docs.len());
+    for &(score, doc_address) in &score_docs {
+        let id = doc_id(searcher, id_field, doc_address)
+            .map_err(|err| error(format!("fast field failed: {:?}", err)))?;
+        // a deleted document is reported below, by the comparison of the documents.
+        let expected_score = alive_docs.get(&id).and_then(|doc| oracle_query.score(doc));
+        if let Some(expected_score) = expected_score {
+            if !is_close(score, expected_score) {
+                return Err(error(format!(
+                    "expected a score of {} for the document {}, got {}",
+                    expected_score, id, score
+                )));
+            }
+        }
+        scored_ids.push(id);
+    }
+    scored_ids.sort();
+    if scored_ids != expected_ids {
+        return Err(error(format!(
+            "expected the documents {:?}, got {:?} with scoring",
+            expected_ids, scored_ids
+        )));
+    }
+
+    // the top-k documents are the best of the scored documents.
+    let mut top_k_collector = TopCollector::with_limit(TOP_K);
+    query
+        .search(searcher, &mut top_k_collector)
+        .map_err(|err| error(format!("top-k search failed: {:?}", err)))?;
+    let top_k_scores: Vec<Score> = top_k_collector
+        .score_docs()
+        .into_iter()
+        .map(|(score, _)| score)
+        .collect();
+    let expected_top_k_scores: Vec<Score> = score_docs
+        .iter()
+        .take(TOP_K)
+        .map(|&(score, _)| score)
+        .collect();
+    let same_top_k_scores = top_k_scores.len() == expected_top_k_scores.len()
+        && top_k_scores
+            .iter()
+            .zip(expected_top_k_scores.iter())
+            .all(|(&score, &expected_score)| is_close(score, expected_score));
+    if !same_top_k_scores {
+        return Err(error(format!(
+            "expected the top-{} scores {:?}, got {:?}",
+            TOP_K, expected_top_k_scores, top_k_scores
+        )));
+    }
+
+    // facet counts.
+    check_facet_counts(searcher, corpus, &*query, &matching_docs, Facet::root())
+        .map_err(&error)?;
+    check_facet_counts(searcher, corpus, &*query, &matching_docs, Facet::from("/f0"))
+        .map_err(&error)?;
+    Ok(())
+}
+
+/// Generates a corpus and queries from a seed, and checks that tantivy
+/// and the oracle agree on the results of the queries, for all of the
+/// layouts of the index.
+///
+/// Returns a description of the first difference, if any.
+pub fn check_seed(seed: u64) -> result::Result<(), String> {
+    let mut rng = SeededRng::new(seed);
+    let corpus = OracleCorpus::generate(&mut rng);
+    let queries: Vec<OracleQuery> = (0..NUM_QUERIES_PER_SEED)
+        .map(|_| corpus.random_query(&mut rng, MAX_QUERY_DEPTH))
+        .collect();
+    for &layout in &[Layout::SingleSegment, Layout::MultiSegment] {
+        let index = corpus
+            .create_index(layout)
+            .map_err(|err| format!("seed {}: indexing failed: {:?}", seed, err))?;
+        let searcher = index.searcher();
+        for query in &queries {
+            check_query(&*searcher, &corpus, query)
+                .map_err(|what| format!("seed {}, {:?} layout: {}", seed, layout, what))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{check_seed, oracle_facet_counts, OracleCorpus, OracleDoc, OracleQuery};
+    use query::Occur;
+    use schema::Facet;
+    use test_utils::SeededRng;
+
+    fn doc(tokens: &str, num: u64) -> OracleDoc {
+        OracleDoc {
+            id: 0,
+            texts: vec![tokens.split(' ').map(|token| token.to_string()).collect()],
+            num,
+            facet: Facet::from("/f0/f1"),
+        }
+    }
+
+    fn term(word: &str) -> OracleQuery {
+        OracleQuery::Term(0, word.to_string())
+    }
+
+    #[test]
+    fn test_oracle_matches() {
+        let doc = doc("a b c", 5);
+        assert!(term("a").matches(&doc));
+        assert!(!term("d").matches(&doc));
+        let phrase = |words: &[&str]| {
+            OracleQuery::Phrase(0, words.iter().map(|word| word.to_string()).collect())
+        };
+        assert!(phrase(&["b", "c"]).matches(&doc));
+        assert!(!phrase(&["a", "c"]).matches(&doc));
+        assert!(OracleQuery::Prefix(0, "".to_string()).matches(&doc));
+        assert!(!OracleQuery::Prefix(0, "ab".to_string()).matches(&doc));
+        assert!(OracleQuery::Regex(0, "[a-b]".to_string()).matches(&doc));
+        assert!(!OracleQuery::Regex(0, "a|d".to_string()).matches(&OracleDoc {
+            texts: vec![vec!["ab".to_string()]],
+            ..doc.clone()
+        }));
+        let range = |low: u64, high: u64| OracleQuery::Range {
+            low,
+            high,
+            fast: false,
+        };
+        assert!(range(5, 6).matches(&doc));
+        assert!(!range(0, 5).matches(&doc));
+        let boolean = |clauses: Vec<(Occur, OracleQuery)>| OracleQuery::Boolean(clauses);
+        assert!(!boolean(vec![]).matches(&doc));
+        assert!(boolean(vec![(Occur::MustNot, term("d"))]).matches(&doc));
+        assert!(!boolean(vec![(Occur::MustNot, term("a"))]).matches(&doc));
+        assert!(boolean(vec![(Occur::Must, term("a")), (Occur::Should, term("d"))]).matches(&doc));
+        assert!(!boolean(vec![(Occur::Should, term("d"))]).matches(&doc));
+    }
+
+    #[test]
+    fn test_oracle_score() {
+        let doc = doc("a b c", 5);
+        let constant = |word: &str, score| OracleQuery::ConstantScore(box term(word), score);
+        assert_eq!(term("a").score(&doc), None);
+        assert_eq!(constant("a", 2.5).score(&doc), Some(2.5));
+        let query = OracleQuery::Boolean(vec![
+            (Occur::Must, constant("a", 1.0)),
+            (Occur::Should, constant("b", 2.0)),
+            (Occur::Should, constant("d", 4.0)),
+            (Occur::MustNot, term("d")),
+        ]);
+        assert_eq!(query.score(&doc), Some(3.0));
+        let query = OracleQuery::DisjunctionMax(vec![constant("a", 1.0), constant("b", 2.0)], 0.5);
+        assert_eq!(query.score(&doc), Some(2.5));
+        assert_eq!(OracleQuery::Boost(box query, 2.0).score(&doc), Some(5.0));
+    }
+
+    #[test]
+    fn test_oracle_facet_counts() {
+        let docs = vec![doc("a", 0), doc("a", 0)];
+        let docs: Vec<&OracleDoc> = docs.iter().collect();
+        let counts = oracle_facet_counts(&docs, &Facet::root());
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), vec![("/f0".to_string(), 2)]);
+        let counts = oracle_facet_counts(&docs, &Facet::from("/f0"));
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), vec![("/f0/f1".to_string(), 2)]);
+        assert!(oracle_facet_counts(&docs, &Facet::from("/f1")).is_empty());
+    }
+
+    #[test]
+    fn test_oracle_corpus_is_deterministic() {
+        let corpus = OracleCorpus::generate(&mut SeededRng::new(3));
+        let same_corpus = OracleCorpus::generate(&mut SeededRng::new(3));
+        assert_eq!(format!("{:?}", corpus), format!("{:?}", same_corpus));
+        assert!(!corpus.alive_docs().is_empty() || !corpus.ops().is_empty());
+    }
+
+    #[test]
+    fn test_check_seed() {
+        assert_eq!(check_seed(1), Ok(()));
+    }
+}
@@ -49,11 +49,16 @@ where
     }
 
     fn advance(&mut self) -> bool {
+        // position increments of the removed tokens are
+        // carried over to the next emitted token.
+        let mut removed_increments = 0;
         loop {
             if self.tail.advance() {
                 if self.predicate(self.tail.token()) {
+                    self.tail.token_mut().position_increment += removed_increments;
                     return true;
                 }
+                removed_increments += self.tail.token().position_increment;
             } else {
                 return false;
             }
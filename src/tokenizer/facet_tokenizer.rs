@@ -1,5 +1,4 @@
 use super::{Token, TokenStream, Tokenizer};
-use std::str;
 use schema::FACET_SEP_BYTE;
 
 /// The `FacetTokenizer` process a `Facet` binary representation
@@ -57,13 +56,11 @@ impl<'a> TokenStream for FacetTokenStream<'a> {
                     .position(|b| b == FACET_SEP_BYTE)
                     .map(|pos| cursor + 1 + pos)
                 {
-                    let facet_part =
-                        unsafe { str::from_utf8_unchecked(&bytes[cursor..next_sep_pos]) };
-                    self.token.text.push_str(facet_part);
+                    // the separator is an ASCII character, hence a char boundary.
+                    self.token.text.push_str(&self.text[cursor..next_sep_pos]);
                     self.state = State::UpToPosition(next_sep_pos);
                 } else {
-                    let facet_part = unsafe { str::from_utf8_unchecked(&bytes[cursor..]) };
-                    self.token.text.push_str(facet_part);
+                    self.token.text.push_str(&self.text[cursor..]);
                     self.state = State::Terminated;
                 }
                 true
@@ -99,7 +96,7 @@ mod tests {
                 tokens.push(format!("{}", facet));
             };
             FacetTokenizer
-                .token_stream(unsafe { str::from_utf8_unchecked(facet.encoded_bytes()) })
+                .token_stream(str::from_utf8(facet.encoded_bytes()).unwrap())
                 .process(&mut add_token);
         }
         assert_eq!(tokens.len(), 4);
@@ -119,7 +116,7 @@ mod tests {
                 tokens.push(format!("{}", facet));
             };
             FacetTokenizer
-                .token_stream(unsafe { str::from_utf8_unchecked(facet.encoded_bytes()) })
+                .token_stream(str::from_utf8(facet.encoded_bytes()).unwrap())
                 .process(&mut add_token);
         }
         assert_eq!(tokens.len(), 1);
@@ -24,6 +24,7 @@ impl<'a> Tokenizer<'a> for JapaneseTokenizer {
         let mut tokens = vec![];
         let mut offset_from;
         let mut offset_to = 0;
+        let mut previous_position = usize::max_value();
         for (pos, term) in tinysegmenter::tokenize(text).into_iter().enumerate() {
             offset_from = offset_to;
             offset_to = offset_from + term.len();
@@ -32,8 +33,10 @@ impl<'a> Tokenizer<'a> for JapaneseTokenizer {
                     offset_from,
                     offset_to,
                     position: pos,
+                    position_increment: pos.wrapping_sub(previous_position) as u32,
                     text: term,
                 });
+                previous_position = pos;
             }
         }
         JapaneseTokenizerStream {
@@ -55,6 +55,14 @@
 //! remove their inflection. This tokenizer is slower than the default one,
 //! but is recommended to improve recall.
 //!
+//! ## `<language code>_stem`
+//!
+//! When `tantivy` is compiled with the `multilingual` feature, a stemming
+//! tokenizer is also registered for each of the other [`Language`s](./enum.Language.html),
+//! under the name `<code>_stem` (e.g. `fr_stem`, `de_stem`, `es_stem`).
+//! Stop word lists are bundled for some of the languages,
+//! see [`StopWordFilter`](./struct.StopWordFilter.html).
+//!
 //!
 //! # Custom tokenizers
 //!
@@ -73,7 +81,7 @@
 //! let en_stem = SimpleTokenizer
 //!     .filter(RemoveLongFilter::limit(40))
 //!     .filter(LowerCaser)
-//!     .filter(Stemmer::new());
+//!     .filter(Stemmer::new(Language::English));
 //! # }
 //! ```
 //!
@@ -139,6 +147,10 @@ mod japanese_tokenizer;
 mod token_stream_chain;
 mod raw_tokenizer;
 mod alphanum_only;
+mod synonym_filter;
+mod nfc_normalizer;
+mod stop_word_filter;
+mod stop_words;
 
 pub use self::alphanum_only::AlphaNumOnlyFilter;
 pub use self::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
@@ -150,9 +162,20 @@ pub(crate) use self::token_stream_chain::TokenStreamChain;
 pub use self::japanese_tokenizer::JapaneseTokenizer;
 pub use self::remove_long::RemoveLongFilter;
 pub use self::lower_caser::LowerCaser;
-pub use self::stemmer::Stemmer;
+pub use self::stemmer::{Language, Stemmer};
+pub use self::stop_word_filter::StopWordFilter;
+pub use self::synonym_filter::SynonymFilter;
+pub use self::nfc_normalizer::NfcNormalizer;
 pub use self::facet_tokenizer::FacetTokenizer;
 
+use termdict::MAX_TERM_LEN;
+
+/// Maximum length of the indexed tokens, in bytes.
+///
+/// Longer tokens are dropped by the indexer, whatever the tokenizer
+/// of the field. See `IndexWriter::set_max_token_len`.
+pub const MAX_TOKEN_LEN: usize = MAX_TERM_LEN - 4;
+
 #[cfg(test)]
 mod test {
     use super::Token;
@@ -244,4 +267,58 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "multilingual")]
+    fn test_language_stem_tokenizers() {
+        use Index;
+        use schema::{IndexRecordOption, SchemaBuilder, TextFieldIndexing, TextOptions};
+        use query::QueryParser;
+        use collector::CountCollector;
+        use termdict::{TermDictionary, TermStreamer};
+
+        let text_options = |tokenizer: &str| {
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(tokenizer)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+        };
+        let mut schema_builder = SchemaBuilder::default();
+        let body_fr = schema_builder.add_text_field("body_fr", text_options("fr_stem"));
+        let body_en = schema_builder.add_text_field("body_en", text_options("en_stem"));
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            let text = "Nous mangeons des pommes";
+            index_writer.add_document(doc!(body_fr => text, body_en => text)).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let terms = |field| {
+            let inverted_index = searcher.segment_reader(0).inverted_index(field);
+            let mut terms = vec![];
+            let mut term_stream = inverted_index.terms().stream();
+            while term_stream.advance() {
+                terms.push(String::from_utf8(term_stream.key().to_vec()).unwrap());
+            }
+            terms
+        };
+        let fr_terms = terms(body_fr);
+        let en_terms = terms(body_en);
+        assert_ne!(fr_terms, en_terms);
+        let count = |field, query: &str| {
+            let query_parser = QueryParser::for_index(&index, vec![field]);
+            let query = query_parser.parse_query(query).unwrap();
+            let mut count_collector = CountCollector::default();
+            searcher.search(&*query, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        // "mangez" and "mangeons" are two inflections of the same french verb.
+        assert_eq!(count(body_fr, "mangez"), 1);
+        assert_eq!(count(body_en, "mangez"), 0);
+        assert_eq!(count(body_fr, "mangeons"), 1);
+        assert_eq!(count(body_en, "mangeons"), 1);
+    }
+
 }
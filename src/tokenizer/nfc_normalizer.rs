@@ -0,0 +1,86 @@
+use super::{Token, TokenFilter, TokenStream};
+use unicode_normalization::UnicodeNormalization;
+
+/// Token filter that applies the unicode NFC normalization to terms.
+///
+/// The same text may be encoded with precomposed characters
+/// (e.g. `é` as `U+00E9`) or with a base character followed by
+/// combining marks (`e` followed by `U+0301`). After normalization,
+/// both forms result in the same term.
+///
+/// The `QueryParser` applies the same normalization to its input
+/// by default (see `QueryParser::set_normalization`).
+///
+/// Note that this filter only sees the tokens emitted by the tokenizer.
+/// The `SimpleTokenizer` for instance splits text on combining marks,
+/// as they are not alphanumeric.
+#[derive(Clone)]
+pub struct NfcNormalizer;
+
+impl<TailTokenStream> TokenFilter<TailTokenStream> for NfcNormalizer
+where
+    TailTokenStream: TokenStream,
+{
+    type ResultTokenStream = NfcNormalizerTokenStream<TailTokenStream>;
+
+    fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
+        NfcNormalizerTokenStream::wrap(token_stream)
+    }
+}
+
+pub struct NfcNormalizerTokenStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    tail: TailTokenStream,
+}
+
+impl<TailTokenStream> TokenStream for NfcNormalizerTokenStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.tail.advance() {
+            let token = self.tail.token_mut();
+            if !token.text.is_ascii() {
+                token.text = token.text.nfc().collect();
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<TailTokenStream> NfcNormalizerTokenStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn wrap(tail: TailTokenStream) -> NfcNormalizerTokenStream<TailTokenStream> {
+        NfcNormalizerTokenStream { tail }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::NfcNormalizer;
+    use tokenizer::{RawTokenizer, TokenStream, Tokenizer};
+
+    #[test]
+    fn test_nfc_normalizer() {
+        let tokenizer = RawTokenizer.filter(NfcNormalizer);
+        let mut token_stream = tokenizer.token_stream("cafe\u{301}");
+        assert!(token_stream.advance());
+        assert_eq!(token_stream.token().text, "caf\u{e9}");
+        assert!(!token_stream.advance());
+    }
+}
@@ -17,6 +17,7 @@ impl<'a> Tokenizer<'a> for RawTokenizer {
             offset_from: 0,
             offset_to: text.len(),
             position: 0,
+            position_increment: 1,
             text: text.to_string(),
         };
         RawTokenStream {
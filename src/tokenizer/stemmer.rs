@@ -1,20 +1,167 @@
-use std::sync::Arc;
 use super::{Token, TokenFilter, TokenStream};
 use rust_stemmers::{self, Algorithm};
 
-/// `Stemmer` token filter. Currently only English is supported.
+/// Languages supported by the `Stemmer` and the `StopWordFilter`.
+///
+/// Only `English` is available by default. The other languages
+/// require the `multilingual` cargo feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    /// Danish
+    #[cfg(feature = "multilingual")]
+    Danish,
+    /// Dutch
+    #[cfg(feature = "multilingual")]
+    Dutch,
+    /// English
+    English,
+    /// Finnish
+    #[cfg(feature = "multilingual")]
+    Finnish,
+    /// French
+    #[cfg(feature = "multilingual")]
+    French,
+    /// German
+    #[cfg(feature = "multilingual")]
+    German,
+    /// Hungarian
+    #[cfg(feature = "multilingual")]
+    Hungarian,
+    /// Italian
+    #[cfg(feature = "multilingual")]
+    Italian,
+    /// Portuguese
+    #[cfg(feature = "multilingual")]
+    Portuguese,
+    /// Romanian
+    #[cfg(feature = "multilingual")]
+    Romanian,
+    /// Russian
+    #[cfg(feature = "multilingual")]
+    Russian,
+    /// Spanish
+    #[cfg(feature = "multilingual")]
+    Spanish,
+    /// Swedish
+    #[cfg(feature = "multilingual")]
+    Swedish,
+    /// Turkish
+    #[cfg(feature = "multilingual")]
+    Turkish,
+}
+
+impl Language {
+    /// Returns all of the available languages.
+    pub fn all() -> &'static [Language] {
+        #[cfg(not(feature = "multilingual"))]
+        static LANGUAGES: &'static [Language] = &[Language::English];
+        #[cfg(feature = "multilingual")]
+        static LANGUAGES: &'static [Language] = &[
+            Language::Danish,
+            Language::Dutch,
+            Language::English,
+            Language::Finnish,
+            Language::French,
+            Language::German,
+            Language::Hungarian,
+            Language::Italian,
+            Language::Portuguese,
+            Language::Romanian,
+            Language::Russian,
+            Language::Spanish,
+            Language::Swedish,
+            Language::Turkish,
+        ];
+        LANGUAGES
+    }
+
+    /// Returns the ISO 639-1 code of the language.
+    ///
+    /// The stemming tokenizer of a language is registered
+    /// in the `TokenizerManager` as `<code>_stem`. (e.g. `fr_stem`)
+    pub fn code(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "multilingual")]
+            Language::Danish => "da",
+            #[cfg(feature = "multilingual")]
+            Language::Dutch => "nl",
+            Language::English => "en",
+            #[cfg(feature = "multilingual")]
+            Language::Finnish => "fi",
+            #[cfg(feature = "multilingual")]
+            Language::French => "fr",
+            #[cfg(feature = "multilingual")]
+            Language::German => "de",
+            #[cfg(feature = "multilingual")]
+            Language::Hungarian => "hu",
+            #[cfg(feature = "multilingual")]
+            Language::Italian => "it",
+            #[cfg(feature = "multilingual")]
+            Language::Portuguese => "pt",
+            #[cfg(feature = "multilingual")]
+            Language::Romanian => "ro",
+            #[cfg(feature = "multilingual")]
+            Language::Russian => "ru",
+            #[cfg(feature = "multilingual")]
+            Language::Spanish => "es",
+            #[cfg(feature = "multilingual")]
+            Language::Swedish => "sv",
+            #[cfg(feature = "multilingual")]
+            Language::Turkish => "tr",
+        }
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match *self {
+            #[cfg(feature = "multilingual")]
+            Language::Danish => Algorithm::Danish,
+            #[cfg(feature = "multilingual")]
+            Language::Dutch => Algorithm::Dutch,
+            Language::English => Algorithm::English,
+            #[cfg(feature = "multilingual")]
+            Language::Finnish => Algorithm::Finnish,
+            #[cfg(feature = "multilingual")]
+            Language::French => Algorithm::French,
+            #[cfg(feature = "multilingual")]
+            Language::German => Algorithm::German,
+            #[cfg(feature = "multilingual")]
+            Language::Hungarian => Algorithm::Hungarian,
+            #[cfg(feature = "multilingual")]
+            Language::Italian => Algorithm::Italian,
+            #[cfg(feature = "multilingual")]
+            Language::Portuguese => Algorithm::Portuguese,
+            #[cfg(feature = "multilingual")]
+            Language::Romanian => Algorithm::Romanian,
+            #[cfg(feature = "multilingual")]
+            Language::Russian => Algorithm::Russian,
+            #[cfg(feature = "multilingual")]
+            Language::Spanish => Algorithm::Spanish,
+            #[cfg(feature = "multilingual")]
+            Language::Swedish => Algorithm::Swedish,
+            #[cfg(feature = "multilingual")]
+            Language::Turkish => Algorithm::Turkish,
+        }
+    }
+}
+
+/// `Stemmer` token filter, relying on the snowball stemming algorithms.
 /// Tokens are expected to be lowercased beforehands.
 #[derive(Clone)]
 pub struct Stemmer {
-    stemmer_algorithm: Arc<Algorithm>,
+    language: Language,
 }
 
 impl Stemmer {
-    /// Creates a new Stemmer `TokenFilter`.
-    pub fn new() -> Stemmer {
-        Stemmer {
-            stemmer_algorithm: Arc::new(Algorithm::English),
-        }
+    /// Creates a new Stemmer `TokenFilter` for a given language.
+    pub fn new(language: Language) -> Stemmer {
+        Stemmer { language }
+    }
+}
+
+impl Default for Stemmer {
+    /// Creates an English `Stemmer`.
+    fn default() -> Stemmer {
+        Stemmer::new(Language::English)
     }
 }
 
@@ -25,7 +172,7 @@ where
     type ResultTokenStream = StemmerTokenStream<TailTokenStream>;
 
     fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
-        let inner_stemmer = rust_stemmers::Stemmer::create(Algorithm::English);
+        let inner_stemmer = rust_stemmers::Stemmer::create(self.language.algorithm());
         StemmerTokenStream::wrap(inner_stemmer, token_stream)
     }
 }
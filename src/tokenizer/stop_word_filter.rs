@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use super::{Token, TokenFilter, TokenStream};
+use super::Language;
+use super::stop_words::stop_words;
+
+/// `StopWordFilter` removes the tokens that belong
+/// to a given list of stop words.
+///
+/// Tokens are compared to the stop words as is, so the filter
+/// is typically applied after the `LowerCaser`.
+#[derive(Clone)]
+pub struct StopWordFilter {
+    words: Arc<HashSet<String>>,
+}
+
+impl StopWordFilter {
+    /// Creates a `StopWordFilter` removing the given words.
+    pub fn remove<W: Into<String>>(words: Vec<W>) -> StopWordFilter {
+        StopWordFilter {
+            words: Arc::new(words.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Creates a `StopWordFilter` removing the bundled
+    /// stop words of a language.
+    ///
+    /// Returns `None` if no stop word list is bundled for the language.
+    pub fn for_language(language: Language) -> Option<StopWordFilter> {
+        stop_words(language).map(|words| StopWordFilter::remove(words.to_vec()))
+    }
+}
+
+impl<TailTokenStream> StopWordFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn predicate(&self, token: &Token) -> bool {
+        !self.words.contains(&token.text)
+    }
+
+    fn wrap(
+        words: Arc<HashSet<String>>,
+        tail: TailTokenStream,
+    ) -> StopWordFilterStream<TailTokenStream> {
+        StopWordFilterStream { words, tail }
+    }
+}
+
+impl<TailTokenStream> TokenFilter<TailTokenStream> for StopWordFilter
+where
+    TailTokenStream: TokenStream,
+{
+    type ResultTokenStream = StopWordFilterStream<TailTokenStream>;
+
+    fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
+        StopWordFilterStream::wrap(Arc::clone(&self.words), token_stream)
+    }
+}
+
+pub struct StopWordFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    words: Arc<HashSet<String>>,
+    tail: TailTokenStream,
+}
+
+impl<TailTokenStream> TokenStream for StopWordFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+
+    fn advance(&mut self) -> bool {
+        // position increments of the removed tokens are
+        // carried over to the next emitted token.
+        let mut removed_increments = 0;
+        loop {
+            if self.tail.advance() {
+                if self.predicate(self.tail.token()) {
+                    self.tail.token_mut().position_increment += removed_increments;
+                    return true;
+                }
+                removed_increments += self.tail.token().position_increment;
+            } else {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::StopWordFilter;
+    use tokenizer::{Language, LowerCaser, SimpleTokenizer, Token, TokenStream, Tokenizer};
+
+    fn token_texts(stop_word_filter: StopWordFilter, text: &str) -> Vec<String> {
+        let tokenizer = SimpleTokenizer.filter(LowerCaser).filter(stop_word_filter);
+        let mut texts = vec![];
+        tokenizer
+            .token_stream(text)
+            .process(&mut |token: &Token| texts.push(token.text.clone()));
+        texts
+    }
+
+    #[test]
+    fn test_stop_word_filter() {
+        let stop_word_filter = StopWordFilter::for_language(Language::English).unwrap();
+        assert_eq!(
+            token_texts(stop_word_filter, "The happy tax payer of the year"),
+            vec!["happy", "tax", "payer", "year"]
+        );
+    }
+
+    #[test]
+    fn test_stop_word_filter_custom_words() {
+        assert_eq!(
+            token_texts(StopWordFilter::remove(vec!["tax"]), "happy tax payer"),
+            vec!["happy", "payer"]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Stop word lists, derived from the lists of the snowball project.
+//!
+//! Words are lowercased, as the `StopWordFilter` is expected
+//! to be applied after the `LowerCaser`.
+
+use super::Language;
+
+const ENGLISH: &'static [&'static str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are",
+    "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but",
+    "by", "could", "did", "do", "does", "doing", "down", "during", "each", "few", "for", "from",
+    "further", "had", "has", "have", "having", "he", "her", "here", "hers", "herself", "him",
+    "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself", "me", "more",
+    "most", "my", "myself", "no", "nor", "not", "of", "off", "on", "once", "only", "or", "other",
+    "ought", "our", "ours", "ourselves", "out", "over", "own", "same", "she", "should", "so",
+    "some", "such", "than", "that", "the", "their", "theirs", "them", "themselves", "then",
+    "there", "these", "they", "this", "those", "through", "to", "too", "under", "until", "up",
+    "very", "was", "we", "were", "what", "when", "where", "which", "while", "who", "whom", "why",
+    "with", "would", "you", "your", "yours", "yourself", "yourselves",
+];
+
+#[cfg(feature = "multilingual")]
+const FRENCH: &'static [&'static str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et", "eux", "il",
+    "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "même", "mes", "moi", "mon", "ne",
+    "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se",
+    "ses", "son", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre",
+    "vous", "c", "d", "j", "l", "à", "m", "n", "s", "t", "y", "été", "étée", "étées", "étés",
+    "étant", "suis", "es", "est", "sommes", "êtes", "sont", "serai", "sera", "serons", "seront",
+    "étais", "était", "étions", "étiez", "étaient", "fut", "ai", "as", "avons", "avez", "ont",
+    "aurai", "aura", "aurons", "auront", "avais", "avait", "avions", "aviez", "avaient", "eu",
+];
+
+#[cfg(feature = "multilingual")]
+const GERMAN: &'static [&'static str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander",
+    "andere", "anderem", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei", "bin",
+    "bis", "bist", "da", "damit", "dann", "der", "den", "des", "dem", "die", "das", "dass",
+    "daß", "du", "durch", "ein", "eine", "einem", "einen", "einer", "eines", "er", "es", "für",
+    "hatte", "hatten", "hat", "hier", "ich", "ihr", "ihre", "im", "in", "ist", "ja", "kein",
+    "keine", "mich", "mir", "mit", "nach", "nicht", "noch", "nun", "nur", "ob", "oder", "ohne",
+    "sehr", "sein", "seine", "sich", "sie", "sind", "so", "über", "um", "und", "uns", "unter",
+    "vom", "von", "vor", "war", "waren", "was", "weil", "wenn", "wer", "wie", "wir", "wird",
+    "wo", "zu", "zum", "zur",
+];
+
+#[cfg(feature = "multilingual")]
+const SPANISH: &'static [&'static str] = &[
+    "de", "la", "que", "el", "en", "y", "a", "los", "del", "se", "las", "por", "un", "para",
+    "con", "no", "una", "su", "al", "lo", "como", "más", "pero", "sus", "le", "ya", "o", "este",
+    "sí", "porque", "esta", "entre", "cuando", "muy", "sin", "sobre", "también", "me", "hasta",
+    "hay", "donde", "quien", "desde", "todo", "nos", "durante", "todos", "uno", "les", "ni",
+    "contra", "otros", "ese", "eso", "ante", "ellos", "e", "esto", "mí", "antes", "algunos",
+    "qué", "unos", "yo", "otro", "otras", "otra", "él", "tanto", "esa", "estos", "mucho",
+    "quienes", "nada", "muchos", "cual", "poco", "ella", "estar", "estas", "algunas", "algo",
+    "nosotros", "mi", "mis", "tú", "te", "ti", "tu", "tus", "ellas", "vosotros", "es", "son",
+];
+
+#[cfg(feature = "multilingual")]
+const ITALIAN: &'static [&'static str] = &[
+    "ad", "al", "allo", "ai", "agli", "all", "alla", "alle", "con", "col", "coi", "da", "dal",
+    "dallo", "dai", "dagli", "dall", "dalla", "dalle", "di", "del", "dello", "dei", "degli",
+    "dell", "della", "delle", "in", "nel", "nello", "nei", "negli", "nell", "nella", "nelle",
+    "su", "sul", "sullo", "sui", "sugli", "sull", "sulla", "sulle", "per", "tra", "contro", "io",
+    "tu", "lui", "lei", "noi", "voi", "loro", "mio", "mia", "miei", "mie", "tuo", "tua", "tuoi",
+    "tue", "suo", "sua", "suoi", "sue", "il", "lo", "la", "i", "gli", "le", "un", "uno", "una",
+    "ma", "ed", "se", "perché", "anche", "come", "dov", "dove", "che", "chi", "cui", "non",
+    "più", "quale", "quanto", "quello", "questo", "si", "è", "e", "o", "sono", "era",
+];
+
+#[cfg(feature = "multilingual")]
+const PORTUGUESE: &'static [&'static str] = &[
+    "de", "a", "o", "que", "e", "do", "da", "em", "um", "para", "com", "não", "uma", "os", "no",
+    "se", "na", "por", "mais", "as", "dos", "como", "mas", "ao", "ele", "das", "à", "seu", "sua",
+    "ou", "quando", "muito", "nos", "já", "eu", "também", "só", "pelo", "pela", "até", "isso",
+    "ela", "entre", "depois", "sem", "mesmo", "aos", "seus", "quem", "nas", "me", "esse", "eles",
+    "você", "essa", "num", "nem", "suas", "meu", "às", "minha", "numa", "pelos", "elas", "qual",
+    "nós", "lhe", "deles", "essas", "esses", "pelas", "este", "dele", "tu", "te", "vocês", "vos",
+    "lhes", "meus", "minhas", "teu", "tua", "teus", "tuas", "nosso", "nossa", "nossos", "nossas",
+    "é", "são", "foi", "era",
+];
+
+#[cfg(feature = "multilingual")]
+const DUTCH: &'static [&'static str] = &[
+    "de", "en", "van", "ik", "te", "dat", "die", "in", "een", "hij", "het", "niet", "zijn", "is",
+    "was", "op", "aan", "met", "als", "voor", "had", "er", "maar", "om", "hem", "dan", "zou",
+    "of", "wat", "mijn", "men", "dit", "zo", "door", "over", "ze", "zich", "bij", "ook", "tot",
+    "je", "mij", "uit", "der", "daar", "haar", "naar", "heb", "hoe", "heeft", "hebben", "deze",
+    "u", "want", "nog", "zal", "me", "zij", "nu", "ge", "geen", "omdat", "iets", "worden",
+    "toch", "al", "waren", "veel", "meer", "doen", "toen", "moet", "ben", "zonder", "kan",
+    "hun", "dus", "alles", "onder", "ja", "eens", "hier", "wie", "werd", "altijd", "doch",
+    "wordt", "wezen", "kunnen", "ons", "zelf", "tegen", "na", "reeds", "wil", "kon", "niets",
+    "uw", "iemand", "geweest", "andere",
+];
+
+/// Returns the stop words of a language, or `None` if
+/// no stop word list is bundled for this language.
+pub(crate) fn stop_words(language: Language) -> Option<&'static [&'static str]> {
+    match language {
+        Language::English => Some(ENGLISH),
+        #[cfg(feature = "multilingual")]
+        Language::French => Some(FRENCH),
+        #[cfg(feature = "multilingual")]
+        Language::German => Some(GERMAN),
+        #[cfg(feature = "multilingual")]
+        Language::Spanish => Some(SPANISH),
+        #[cfg(feature = "multilingual")]
+        Language::Italian => Some(ITALIAN),
+        #[cfg(feature = "multilingual")]
+        Language::Portuguese => Some(PORTUGUESE),
+        #[cfg(feature = "multilingual")]
+        Language::Dutch => Some(DUTCH),
+        #[cfg(feature = "multilingual")]
+        _ => None,
+    }
+}
@@ -0,0 +1,225 @@
+use super::{Token, TokenFilter, TokenStream};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// `SynonymFilter` injects synonyms of the tokens at index time.
+///
+/// Each synonym is emitted right after the token it is derived from,
+/// with the same offsets and the same position (its `position_increment` is `0`).
+/// A `PhraseQuery` will therefore match through either the original
+/// token or any of its synonyms.
+///
+/// Only single-token synonyms are supported.
+/// The lookup is done on the token text as emitted by the previous
+/// filters, so the `SynonymFilter` is typically placed after the `LowerCaser`.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate tantivy;
+/// use tantivy::tokenizer::*;
+/// use std::collections::HashMap;
+///
+/// # fn main() {
+/// let mut synonyms = HashMap::new();
+/// synonyms.insert("nyc".to_string(), vec!["newyork".to_string()]);
+/// let tokenizer = SimpleTokenizer
+///     .filter(LowerCaser)
+///     .filter(SynonymFilter::new(synonyms));
+/// let mut token_stream = tokenizer.token_stream("I love NYC");
+/// let mut tokens = vec![];
+/// while let Some(token) = token_stream.next() {
+///     tokens.push((token.text.clone(), token.position));
+/// }
+/// assert_eq!(tokens, vec![
+///     ("i".to_string(), 0),
+///     ("love".to_string(), 1),
+///     ("nyc".to_string(), 2),
+///     ("newyork".to_string(), 2),
+/// ]);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SynonymFilter {
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl SynonymFilter {
+    /// Creates a `SynonymFilter` given a mapping from a token
+    /// to its list of synonyms.
+    pub fn new(synonyms: HashMap<String, Vec<String>>) -> SynonymFilter {
+        SynonymFilter {
+            synonyms: Arc::new(synonyms),
+        }
+    }
+}
+
+impl<TailTokenStream> TokenFilter<TailTokenStream> for SynonymFilter
+where
+    TailTokenStream: TokenStream,
+{
+    type ResultTokenStream = SynonymFilterStream<TailTokenStream>;
+
+    fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
+        SynonymFilterStream::wrap(Arc::clone(&self.synonyms), token_stream)
+    }
+}
+
+pub struct SynonymFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    synonyms: Arc<HashMap<String, Vec<String>>>,
+    tail: TailTokenStream,
+    // synonyms of the current token that remain to be emitted,
+    // in reverse order.
+    pending_synonyms: Vec<String>,
+    token: Token,
+}
+
+impl<TailTokenStream> SynonymFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn wrap(
+        synonyms: Arc<HashMap<String, Vec<String>>>,
+        tail: TailTokenStream,
+    ) -> SynonymFilterStream<TailTokenStream> {
+        SynonymFilterStream {
+            synonyms,
+            tail,
+            pending_synonyms: Vec::new(),
+            token: Token::default(),
+        }
+    }
+}
+
+impl<TailTokenStream> TokenStream for SynonymFilterStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+
+    fn advance(&mut self) -> bool {
+        if let Some(synonym) = self.pending_synonyms.pop() {
+            self.token.text = synonym;
+            self.token.position_increment = 0;
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        {
+            let tail_token = self.tail.token();
+            self.token.offset_from = tail_token.offset_from;
+            self.token.offset_to = tail_token.offset_to;
+            self.token.position = tail_token.position;
+            self.token.position_increment = tail_token.position_increment;
+            self.token.text.clear();
+            self.token.text.push_str(&tail_token.text);
+        }
+        if let Some(synonyms) = self.synonyms.get(&self.token.text) {
+            self.pending_synonyms
+                .extend(synonyms.iter().rev().cloned());
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::SynonymFilter;
+    use tokenizer::{LowerCaser, SimpleTokenizer, TokenStream, Tokenizer};
+    use std::collections::HashMap;
+    use Index;
+    use schema::{IndexRecordOption, SchemaBuilder, Term, TextFieldIndexing, TextOptions};
+    use query::PhraseQuery;
+    use collector::tests::TestCollector;
+
+    fn synonyms() -> HashMap<String, Vec<String>> {
+        let mut synonyms = HashMap::new();
+        synonyms.insert(
+            "nyc".to_string(),
+            vec!["newyork".to_string(), "bigapple".to_string()],
+        );
+        synonyms
+    }
+
+    #[test]
+    fn test_synonym_filter_token_stream() {
+        let tokenizer = SimpleTokenizer
+            .filter(LowerCaser)
+            .filter(SynonymFilter::new(synonyms()));
+        let mut token_stream = tokenizer.token_stream("to NYC today");
+        let mut tokens = vec![];
+        while let Some(token) = token_stream.next() {
+            tokens.push((
+                token.text.clone(),
+                token.position,
+                token.position_increment,
+                token.offset_from,
+                token.offset_to,
+            ));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("to".to_string(), 0, 1, 0, 2),
+                ("nyc".to_string(), 1, 1, 3, 6),
+                ("newyork".to_string(), 1, 0, 3, 6),
+                ("bigapple".to_string(), 1, 0, 3, 6),
+                ("today".to_string(), 2, 1, 7, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_synonym_filter_phrase_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("synonyms")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "synonyms",
+            SimpleTokenizer
+                .filter(LowerCaser)
+                .filter(SynonymFilter::new(synonyms())),
+        );
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field=>"I love NYC pizza")).unwrap();
+            index_writer.add_document(doc!(text_field=>"I love newyork pizza")).unwrap();
+            index_writer.add_document(doc!(text_field=>"NYC love")).unwrap();
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let phrase_docs = |texts: &[&str]| {
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            let mut test_collector = TestCollector::default();
+            searcher
+                .search(&PhraseQuery::from(terms), &mut test_collector)
+                .unwrap();
+            test_collector.docs()
+        };
+        assert_eq!(phrase_docs(&["love", "nyc"]), vec![0]);
+        assert_eq!(phrase_docs(&["love", "newyork"]), vec![0, 1]);
+        assert_eq!(phrase_docs(&["bigapple", "pizza"]), vec![0]);
+        assert_eq!(phrase_docs(&["newyork", "love"]), vec![2]);
+        assert!(phrase_docs(&["nyc", "newyork"]).is_empty());
+    }
+}
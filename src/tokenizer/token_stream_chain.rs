@@ -1,11 +1,14 @@
-use tokenizer::{Token, TokenStream};
+use tokenizer::{BoxedTokenizer, Token, TokenStream};
 
 pub(crate) struct TokenStreamChain<TTokenStream: TokenStream> {
     offsets: Vec<usize>,
     token_streams: Vec<TTokenStream>,
     position_shift: usize,
     stream_idx: usize,
+    // true until the first token of the current stream is reached.
+    at_stream_start: bool,
     token: Token,
+    start_positions: Vec<usize>,
 }
 
 impl<'a, TTokenStream> TokenStreamChain<TTokenStream>
@@ -19,11 +22,23 @@ where
         TokenStreamChain {
             offsets,
             stream_idx: 0,
+            at_stream_start: true,
             token_streams,
             position_shift: 0,
             token: Token::default(),
+            start_positions: vec![0],
         }
     }
+
+    /// Returns the position shift applied to each of the token streams
+    /// that have been reached so far.
+    ///
+    /// Once the chain has been consumed, it contains one position per stream.
+    /// All of the tokens of the `i`-th stream have a position greater
+    /// or equal to `start_positions()[i]`, and lower than `start_positions()[i + 1]`.
+    pub fn start_positions(&self) -> &[usize] {
+        &self.start_positions
+    }
 }
 
 impl<'a, TTokenStream> TokenStream for TokenStreamChain<TTokenStream>
@@ -36,15 +51,28 @@ where
             if token_stream.advance() {
                 let token = token_stream.token();
                 let offset_offset = self.offsets[self.stream_idx];
+                let position = token.position + self.position_shift;
                 self.token.offset_from = token.offset_from + offset_offset;
-                self.token.offset_from = token.offset_from + offset_offset;
-                self.token.position = token.position + self.position_shift;
+                self.token.offset_to = token.offset_to + offset_offset;
+                // the position increment of the first token of a stream
+                // accounts for the position gap between two streams.
+                self.token.position_increment = if self.at_stream_start {
+                    position.wrapping_sub(self.token.position) as u32
+                } else {
+                    token.position_increment
+                };
+                self.at_stream_start = false;
+                self.token.position = position;
                 self.token.text.clear();
                 self.token.text.push_str(token.text.as_str());
                 return true;
             } else {
                 self.stream_idx += 1;
+                self.at_stream_start = true;
                 self.position_shift = self.token.position + 2;
+                if self.stream_idx < self.token_streams.len() {
+                    self.start_positions.push(self.position_shift);
+                }
             }
         }
         false
@@ -66,3 +94,74 @@ where
         &mut self.token
     }
 }
+
+impl<'b> TokenStreamChain<Box<TokenStream + 'b>> {
+    /// Chains the token streams of each of the texts,
+    /// the same way `BoxedTokenizer::token_stream_texts` does.
+    pub fn from_texts(
+        tokenizer: &BoxedTokenizer,
+        texts: &'b [&'b str],
+    ) -> TokenStreamChain<Box<TokenStream + 'b>> {
+        let mut offsets = vec![];
+        let mut total_offset = 0;
+        for &text in texts {
+            offsets.push(total_offset);
+            total_offset += text.len();
+        }
+        let token_streams: Vec<_> = texts.iter().map(|text| tokenizer.token_stream(text)).collect();
+        TokenStreamChain::new(offsets, token_streams)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tokenizer::{SimpleTokenizer, Token, TokenStream, Tokenizer};
+    use super::TokenStreamChain;
+
+    #[test]
+    fn test_token_stream_chain_positions() {
+        let token_streams = vec![
+            SimpleTokenizer.token_stream("hello world"),
+            SimpleTokenizer.token_stream("happy tax"),
+        ];
+        let mut token_chain = TokenStreamChain::new(vec![0, 11], token_streams);
+        let mut tokens: Vec<(String, usize, u32, usize, usize)> = vec![];
+        {
+            let mut add_token = |token: &Token| {
+                tokens.push((
+                    token.text.clone(),
+                    token.position,
+                    token.position_increment,
+                    token.offset_from,
+                    token.offset_to,
+                ));
+            };
+            token_chain.process(&mut add_token);
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("hello".to_string(), 0, 1, 0, 5),
+                ("world".to_string(), 1, 1, 6, 11),
+                ("happy".to_string(), 3, 2, 11, 16),
+                ("tax".to_string(), 4, 1, 17, 20),
+            ]
+        );
+        assert_eq!(token_chain.start_positions(), &[0, 3]);
+    }
+
+    #[test]
+    fn test_token_stream_chain_start_positions() {
+        let token_streams = vec![
+            SimpleTokenizer.token_stream("a b"),
+            SimpleTokenizer.token_stream(""),
+            SimpleTokenizer.token_stream("c"),
+        ];
+        let mut token_chain = TokenStreamChain::new(vec![0, 3, 3], token_streams);
+        let mut positions = vec![];
+        token_chain.process(&mut |token: &Token| positions.push(token.position));
+        assert_eq!(positions, vec![0, 1, 3]);
+        assert_eq!(token_chain.start_positions(), &[0, 3, 3]);
+    }
+}
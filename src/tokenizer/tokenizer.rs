@@ -15,6 +15,18 @@ pub struct Token {
     pub offset_to: usize,
     /// Position, expressed in number of tokens.
     pub position: usize,
+    /// Difference between the position of this token
+    /// and the position of the previous token.
+    ///
+    /// It is `1` for regular tokens. Token filters injecting
+    /// additional tokens at the same position (e.g. synonyms)
+    /// emit them with a `position_increment` of `0`, and the same `position`
+    /// as the token they are derived from. Token filters removing tokens
+    /// (e.g. stop words) add their increments to the next token, so that
+    /// the gap is preserved.
+    ///
+    /// The positions indexed are computed from the position increments.
+    pub position_increment: u32,
     /// Actual text content of the token.
     pub text: String,
 }
@@ -25,6 +37,7 @@ impl Default for Token {
             offset_from: 0,
             offset_to: 0,
             position: usize::max_value(),
+            position_increment: 1,
             text: String::new(),
         }
     }
@@ -61,7 +74,7 @@ pub trait Tokenizer<'a>: Sized + Clone {
     /// let en_stem = SimpleTokenizer
     ///     .filter(RemoveLongFilter::limit(40))
     ///     .filter(LowerCaser)
-    ///     .filter(Stemmer::new());
+    ///     .filter(Stemmer::new(Language::English));
     /// # }
     /// ```
     ///
@@ -8,7 +8,7 @@ use tokenizer::SimpleTokenizer;
 use tokenizer::JapaneseTokenizer;
 use tokenizer::RemoveLongFilter;
 use tokenizer::LowerCaser;
-use tokenizer::Stemmer;
+use tokenizer::{Language, Stemmer};
 
 /// The tokenizer manager serves as a store for
 /// all of the pre-configured tokenizer pipelines.
@@ -22,6 +22,12 @@ use tokenizer::Stemmer;
 ///  * `en_stem` : Like `default`, but also applies stemming on the
 ///  resulting tokens. Stemming can improve the recall of your
 ///  search engine.
+///  * `<code>_stem` : Like `en_stem` for the other languages
+///  (e.g. `fr_stem`, `de_stem`), if tantivy is compiled with the
+///  `multilingual` feature. See `Language::code`.
+///
+/// Each `Index` has its own `TokenizerManager`, to which
+/// custom tokenizers can be registered.
 #[derive(Clone)]
 pub struct TokenizerManager {
     tokenizers: Arc<RwLock<HashMap<String, Box<BoxedTokenizer>>>>,
@@ -55,6 +61,7 @@ impl Default for TokenizerManager {
     /// the default pre-configured tokenizers of `tantivy`.
     /// - simple
     /// - en_stem
+    /// - `<code>_stem` for the other languages
     /// - ja
     fn default() -> TokenizerManager {
         let manager = TokenizerManager {
@@ -67,13 +74,15 @@ impl Default for TokenizerManager {
                 .filter(RemoveLongFilter::limit(40))
                 .filter(LowerCaser),
         );
-        manager.register(
-            "en_stem",
-            SimpleTokenizer
-                .filter(RemoveLongFilter::limit(40))
-                .filter(LowerCaser)
-                .filter(Stemmer::new()),
-        );
+        for &language in Language::all() {
+            manager.register(
+                &format!("{}_stem", language.code()),
+                SimpleTokenizer
+                    .filter(RemoveLongFilter::limit(40))
+                    .filter(LowerCaser)
+                    .filter(Stemmer::new(language)),
+            );
+        }
         manager.register("ja", JapaneseTokenizer.filter(RemoveLongFilter::limit(40)));
         manager
     }
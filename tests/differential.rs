@@ -0,0 +1,41 @@
+//! Differential tests of the evaluation of the queries: random corpora and
+//! queries are checked against the naive engine of `tantivy::test_utils::oracle`.
+//!
+//! The seeds can be overridden with a comma-separated list, e.g.
+//! `TANTIVY_ORACLE_SEEDS=3,17 cargo test --test differential`.
+
+extern crate tantivy;
+
+use std::env;
+use tantivy::test_utils::oracle::check_seed;
+
+/// Seeds checked by the normal test run.
+const SEEDS: [u64; 16] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 42, 1_337, 65_535, 123_456_789, 987_654_321, 4_294_967_296,
+];
+
+fn seeds() -> Vec<u64> {
+    match env::var("TANTIVY_ORACLE_SEEDS") {
+        Ok(seeds) => seeds
+            .split(',')
+            .map(|seed| {
+                seed.trim()
+                    .parse()
+                    .expect("TANTIVY_ORACLE_SEEDS must be a comma-separated list of u64")
+            })
+            .collect(),
+        Err(_) => SEEDS.to_vec(),
+    }
+}
+
+#[test]
+fn test_queries_agree_with_the_oracle() {
+    for seed in seeds() {
+        if let Err(err) = check_seed(seed) {
+            panic!(
+                "{}\nrerun with `TANTIVY_ORACLE_SEEDS={} cargo test --test differential`",
+                err, seed
+            );
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! End-to-end tests of the facets: indexing in several segments,
+//! deletes, merges, collection, queries, and reopening the index from disk.
+
+extern crate futures;
+#[macro_use]
+extern crate tantivy;
+extern crate tempdir;
+
+use futures::Future;
+use std::path::Path;
+use std::str;
+use tantivy::collector::{CountCollector, FacetCollector, FacetCounterWidth, FacetCounts,
+                         FastFacetCollector};
+use tantivy::merge_policy::NoMergePolicy;
+use tantivy::query::{AllQuery, Query, QueryParser, QueryParserError, TermQuery};
+use tantivy::schema::{Document, Facet, FacetParseError, Field, IndexRecordOption, Schema,
+                      SchemaBuilder, Term, Value, INT_INDEXED, INT_STORED, TEXT};
+use tantivy::tokenizer::{FacetTokenizer, TokenStream, Tokenizer};
+use tantivy::{DocAddress, Index, Searcher};
+use tempdir::TempDir;
+
+struct BookFields {
+    id: Field,
+    title: Field,
+    facet: Field,
+}
+
+fn book_schema() -> Schema {
+    let mut schema_builder = SchemaBuilder::default();
+    schema_builder.add_u64_field("id", INT_INDEXED | INT_STORED);
+    schema_builder.add_text_field("title", TEXT);
+    schema_builder.add_facet_field("facet");
+    schema_builder.build()
+}
+
+fn book_fields(schema: &Schema) -> BookFields {
+    BookFields {
+        id: schema.get_field("id").unwrap(),
+        title: schema.get_field("title").unwrap(),
+        facet: schema.get_field("facet").unwrap(),
+    }
+}
+
+/// Books `(id, title, facets)`, grouped by segment.
+///
+/// The book with the id 7 is added as JSON in the last segment.
+fn book_segments() -> Vec<Vec<(u64, &'static str, Vec<&'static str>)>> {
+    vec![
+        vec![
+            (
+                0,
+                "The Name of the Wind",
+                vec!["/category/fiction/fantasy", "/lang/en"],
+            ),
+            (1, "Dune", vec!["/lang/en", "/category/fiction/scifi"]),
+            // the facets of a document are not given in order.
+            (
+                2,
+                "La Vénus d'Ille",
+                vec![
+                    "/category/fiction/fantasy",
+                    "/lang/fr",
+                    "/category/fiction/horror",
+                ],
+            ),
+        ],
+        vec![
+            (
+                3,
+                "Cien años de soledad",
+                vec!["/lang/es", "/category/fiction/fantasy"],
+            ),
+            (4, "Don Quijote", vec!["/category/fiction/classic", "/lang/es"]),
+            (
+                5,
+                "Le Petit Prince",
+                vec!["/lang/fr", "/category/fiction/classic", "/lang/es"],
+            ),
+        ],
+        vec![
+            (
+                6,
+                "Die Verwandlung",
+                vec!["/lang/de", "/category/fiction/classic", "/lang/fr"],
+            ),
+        ],
+    ]
+}
+
+fn facet_label(id: u64, facet: &str) -> Option<&'static str> {
+    match (id, facet) {
+        (0, "/lang/en") => Some("English"),
+        (3, "/lang/es") => Some("Español"),
+        _ => None,
+    }
+}
+
+/// Indexes the books in three segments, in the given directory.
+fn create_book_index(index_path: &Path) -> (Index, BookFields) {
+    let index = Index::create(index_path, book_schema()).unwrap();
+    let fields = book_fields(&index.schema());
+    let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+    index_writer.set_merge_policy(Box::new(NoMergePolicy::default()));
+    let segments = book_segments();
+    let num_segments = segments.len();
+    for (segment_ord, segment_books) in segments.into_iter().enumerate() {
+        for (id, title, facets) in segment_books {
+            let mut doc = Document::new();
+            doc.add_u64(fields.id, id);
+            doc.add_text(fields.title, title);
+            for facet in facets {
+                match facet_label(id, facet) {
+                    Some(label) => doc.add_facet_with_label(fields.facet, facet, label),
+                    None => doc.add_facet(fields.facet, facet),
+                }
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        if segment_ord + 1 < num_segments {
+            index_writer.commit().unwrap();
+        }
+    }
+    let doc = index
+        .schema()
+        .parse_document(
+            r#"{
+                "id": 7,
+                "title": "Historia de España",
+                "facet": ["/category/history", "/lang/es"]
+            }"#,
+        )
+        .unwrap();
+    index_writer.add_document(doc).unwrap();
+    index_writer.commit().unwrap();
+    (index, fields)
+}
+
+fn to_vec(counts: &FacetCounts, facet: &str) -> Vec<(String, u64)> {
+    counts
+        .get(facet)
+        .map(|(facet, count)| (facet.to_string(), count))
+        .collect()
+}
+
+fn collect_facets(
+    searcher: &Searcher,
+    field: Field,
+    query: &Query,
+    facets: &[&str],
+) -> FacetCounts {
+    let mut facet_collector = FacetCollector::for_field(field);
+    for facet in facets {
+        facet_collector.add_facet(*facet);
+    }
+    searcher.search(query, &mut facet_collector).unwrap();
+    facet_collector.harvest()
+}
+
+/// Checks the counts of the children of `facet`,
+/// with both the `FacetCollector` and the `FastFacetCollector`.
+fn assert_facet_counts(
+    searcher: &Searcher,
+    field: Field,
+    query: &Query,
+    facet: &str,
+    expected: &[(&str, u64)],
+) {
+    let expected: Vec<(String, u64)> = expected
+        .iter()
+        .map(|&(facet, count)| (facet.to_string(), count))
+        .collect();
+    let facet_counts = collect_facets(searcher, field, query, &[facet]);
+    assert_eq!(to_vec(&facet_counts, facet), expected);
+
+    let mut fast_facet_collector = FastFacetCollector::for_field(searcher, field);
+    fast_facet_collector.add_facet(facet);
+    searcher.search(query, &mut fast_facet_collector).unwrap();
+    assert_eq!(to_vec(&fast_facet_collector.harvest(), facet), expected);
+}
+
+/// Checks that the facet ordinals of each document are sorted,
+/// and are those of its stored facets.
+fn assert_facet_ords_match_stored_facets(searcher: &Searcher, field: Field) {
+    let mut facet_ords = Vec::new();
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        let mut facet_reader = segment_reader.facet_reader(field).unwrap();
+        for doc in 0..segment_reader.max_doc() {
+            if segment_reader.is_deleted(doc) {
+                continue;
+            }
+            facet_reader.facet_ords(doc, &mut facet_ords);
+            assert!(facet_ords.windows(2).all(|ords| ords[0] <= ords[1]));
+            let mut facets: Vec<String> = Vec::new();
+            for &facet_ord in &facet_ords {
+                assert!((facet_ord as usize) < facet_reader.num_facets());
+                let mut facet = Facet::root();
+                facet_reader.facet_from_ord(facet_ord, &mut facet);
+                // only some of the segments have a label for `/lang/es`.
+                if let Some(label) = facet_reader.label_from_ord(facet_ord) {
+                    let expected_label = match facet.to_string().as_str() {
+                        "/lang/en" => "English",
+                        "/lang/es" => "Español",
+                        _ => panic!("Unexpected label {:?}", label),
+                    };
+                    assert_eq!(label, expected_label);
+                }
+                facets.push(facet.to_string());
+            }
+            let stored_doc = searcher
+                .doc(&DocAddress(segment_ord as u32, doc))
+                .unwrap();
+            let mut stored_facets: Vec<String> = stored_doc
+                .get_all(field)
+                .into_iter()
+                .map(|value| match *value {
+                    Value::Facet(ref facet) => facet.to_string(),
+                    _ => panic!("Expected a facet"),
+                })
+                .collect();
+            stored_facets.sort();
+            facets.sort();
+            assert_eq!(facets, stored_facets);
+        }
+    }
+}
+
+#[test]
+fn test_facet_counts_across_segments() {
+    let index_dir = TempDir::new("test_facet_counts_across_segments").unwrap();
+    let (index, fields) = create_book_index(index_dir.path());
+    index.load_searchers().unwrap();
+    let searcher = index.searcher();
+    assert_eq!(searcher.segment_readers().len(), 3);
+    assert_eq!(searcher.num_docs(), 8);
+    assert_facet_ords_match_stored_facets(&*searcher, fields.facet);
+
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        "/",
+        &[("/category", 8), ("/lang", 8)],
+    );
+    // "La Vénus d'Ille" is counted once, though it has two fiction facets.
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        "/category",
+        &[("/category/fiction", 7), ("/category/history", 1)],
+    );
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        "/category/fiction",
+        &[
+            ("/category/fiction/classic", 3),
+            ("/category/fiction/fantasy", 3),
+            ("/category/fiction/horror", 1),
+            ("/category/fiction/scifi", 1),
+        ],
+    );
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        "/lang",
+        &[("/lang/de", 1), ("/lang/en", 2), ("/lang/es", 4), ("/lang/fr", 3)],
+    );
+    // a leaf facet does not have any children to count.
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        "/lang/en",
+        &[],
+    );
+
+    // several facets at different depths.
+    let facet_counts = collect_facets(
+        &*searcher,
+        fields.facet,
+        &AllQuery,
+        &["/category/fiction", "/lang"],
+    );
+    assert_eq!(
+        to_vec(&facet_counts, "/"),
+        vec![
+            ("/category/fiction/classic".to_string(), 3),
+            ("/category/fiction/fantasy".to_string(), 3),
+            ("/category/fiction/horror".to_string(), 1),
+            ("/category/fiction/scifi".to_string(), 1),
+            ("/lang/de".to_string(), 1),
+            ("/lang/en".to_string(), 2),
+            ("/lang/es".to_string(), 4),
+            ("/lang/fr".to_string(), 3),
+        ]
+    );
+    assert_eq!(to_vec(&facet_counts, "/category").len(), 4);
+
+    // top k.
+    let top_langs: Vec<(String, u64)> = facet_counts
+        .top_k("/lang", 2)
+        .into_iter()
+        .map(|(facet, count)| (facet.to_string(), count))
+        .collect();
+    assert_eq!(
+        top_langs,
+        vec![("/lang/es".to_string(), 4), ("/lang/fr".to_string(), 3)]
+    );
+    assert_eq!(facet_counts.top_k("/lang", 10).len(), 4);
+    assert!(facet_counts.top_k("/lang", 0).is_empty());
+    assert!(facet_counts.top_k("/unknown", 3).is_empty());
+
+    // labels.
+    assert_eq!(facet_counts.label("/lang/en"), Some("English"));
+    assert_eq!(facet_counts.label("/lang/es"), Some("Español"));
+    assert_eq!(facet_counts.label("/lang/de"), None);
+    let lang_labels: Vec<(String, Option<&str>, u64)> = facet_counts
+        .get_with_labels("/lang")
+        .map(|(facet, label, count)| (facet.to_string(), label, count))
+        .collect();
+    assert_eq!(
+        lang_labels,
+        vec![
+            ("/lang/de".to_string(), None, 1),
+            ("/lang/en".to_string(), Some("English"), 2),
+            ("/lang/es".to_string(), Some("Español"), 4),
+            ("/lang/fr".to_string(), None, 3),
+        ]
+    );
+    let mut fast_facet_collector = FastFacetCollector::for_field(&*searcher, fields.facet);
+    fast_facet_collector.add_facet("/lang");
+    searcher.search(&AllQuery, &mut fast_facet_collector).unwrap();
+    let fast_facet_counts = fast_facet_collector.harvest();
+    assert_eq!(fast_facet_counts.label("/lang/en"), Some("English"));
+    assert_eq!(fast_facet_counts.label("/lang/es"), Some("Español"));
+
+    // 32 bits counters.
+    let mut facet_collector = FacetCollector::for_field(fields.facet);
+    facet_collector.set_counter_width(FacetCounterWidth::U32);
+    facet_collector.add_facet(Facet::from("/lang"));
+    searcher.search(&AllQuery, &mut facet_collector).unwrap();
+    assert_eq!(
+        to_vec(&facet_collector.harvest(), "/lang"),
+        to_vec(&facet_counts, "/lang")
+    );
+}
+
+fn assert_counts_after_deletes(searcher: &Searcher, fields: &BookFields) {
+    assert_eq!(searcher.num_docs(), 6);
+    assert_facet_ords_match_stored_facets(searcher, fields.facet);
+    assert_facet_counts(
+        searcher,
+        fields.facet,
+        &AllQuery,
+        "/category",
+        &[("/category/fiction", 6)],
+    );
+    assert_facet_counts(
+        searcher,
+        fields.facet,
+        &AllQuery,
+        "/category/fiction",
+        &[
+            ("/category/fiction/classic", 3),
+            ("/category/fiction/fantasy", 2),
+            ("/category/fiction/scifi", 1),
+        ],
+    );
+    assert_facet_counts(
+        searcher,
+        fields.facet,
+        &AllQuery,
+        "/lang",
+        &[("/lang/de", 1), ("/lang/en", 2), ("/lang/es", 3), ("/lang/fr", 2)],
+    );
+    let facet_counts = collect_facets(searcher, fields.facet, &AllQuery, &["/lang"]);
+    assert_eq!(facet_counts.label("/lang/en"), Some("English"));
+    assert_eq!(facet_counts.label("/lang/es"), Some("Español"));
+}
+
+#[test]
+fn test_facet_counts_with_deletes_and_merge() {
+    let index_dir = TempDir::new("test_facet_counts_with_deletes_and_merge").unwrap();
+    {
+        let (index, fields) = create_book_index(index_dir.path());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(Box::new(NoMergePolicy::default()));
+        // deletes "La Vénus d'Ille", and "Historia de España" through its facet.
+        index_writer.delete_term(Term::from_field_u64(fields.id, 2));
+        index_writer.delete_term(Term::from_facet(
+            fields.facet,
+            &Facet::from("/category/history"),
+        ));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().segment_readers().len(), 3);
+        assert_counts_after_deletes(&*index.searcher(), &fields);
+
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer.merge(&segment_ids).wait().unwrap();
+        index_writer.wait_merging_threads().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().segment_readers().len(), 1);
+        assert_counts_after_deletes(&*index.searcher(), &fields);
+    }
+    // the merged segment is read back from disk.
+    let index = Index::open(index_dir.path()).unwrap();
+    let fields = book_fields(&index.schema());
+    index.load_searchers().unwrap();
+    assert_counts_after_deletes(&*index.searcher(), &fields);
+}
+
+#[test]
+fn test_facet_queries() {
+    let index_dir = TempDir::new("test_facet_queries").unwrap();
+    let (index, fields) = create_book_index(index_dir.path());
+    index.load_searchers().unwrap();
+    let searcher = index.searcher();
+    let query_parser = QueryParser::for_index(&index, vec![fields.title]);
+    let count = |query: &Query| {
+        let mut count_collector = CountCollector::default();
+        searcher.search(query, &mut count_collector).unwrap();
+        count_collector.count()
+    };
+    let count_parsed = |query_str: &str| count(&*query_parser.parse_query(query_str).unwrap());
+
+    // a facet matches the documents of its descendants.
+    assert_eq!(count_parsed("facet:\"/category/fiction\""), 7);
+    assert_eq!(count_parsed("facet:\"/category/fiction/fantasy\""), 3);
+    assert_eq!(count_parsed("facet:\"/\""), 8);
+    assert_eq!(count_parsed("facet:\"/category/unknown\""), 0);
+    assert_eq!(
+        count_parsed("+facet:\"/category/fiction\" +facet:\"/lang/fr\""),
+        3
+    );
+    assert_eq!(count_parsed("+facet:\"/category\" -facet:\"/lang/es\""), 4);
+    assert_eq!(count_parsed("+title:dune +facet:\"/lang/en\""), 1);
+    assert_eq!(
+        query_parser.parse_query("facet:fiction").err(),
+        Some(QueryParserError::ExpectedFacet(
+            FacetParseError::MissingLeadingSlash("fiction".to_string())
+        ))
+    );
+    let es_query = TermQuery::new(
+        Term::from_facet(fields.facet, &Facet::from("/lang/es")),
+        IndexRecordOption::Basic,
+    );
+    assert_eq!(count(&es_query), 4);
+
+    // drilling down.
+    let fr_query = query_parser.parse_query("facet:\"/lang/fr\"").unwrap();
+    assert_facet_counts(
+        &*searcher,
+        fields.facet,
+        &*fr_query,
+        "/category/fiction",
+        &[
+            ("/category/fiction/classic", 2),
+            ("/category/fiction/fantasy", 1),
+            ("/category/fiction/horror", 1),
+        ],
+    );
+}
+
+#[test]
+fn test_facet_api() {
+    let facet = Facet::from("/category/fiction/fantasy");
+    assert_eq!(facet.to_string(), "/category/fiction/fantasy");
+    assert_eq!(facet, "/category/fiction/fantasy");
+    assert_eq!(Facet::from_text("/category/fiction/fantasy"), facet);
+    assert_eq!(
+        Facet::from_path(vec!["category", "fiction", "fantasy"]),
+        facet
+    );
+    assert_eq!("/category/fiction/fantasy".parse::<Facet>(), Ok(facet.clone()));
+    assert_eq!(
+        "category".parse::<Facet>(),
+        Err(FacetParseError::MissingLeadingSlash("category".to_string()))
+    );
+    assert_eq!(
+        "/category\\".parse::<Facet>(),
+        Err(FacetParseError::DanglingEscape("/category\\".to_string()))
+    );
+
+    // slashes within a step are escaped.
+    let escaped_facet = Facet::from_path(vec!["a/b", "c"]);
+    assert_eq!(escaped_facet.to_string(), "/a\\/b/c");
+    assert_eq!(Facet::from("/a\\/b/c"), escaped_facet);
+
+    let root = Facet::root();
+    assert!(root.is_root());
+    assert_eq!(root, "/");
+    assert_eq!(Facet::from("/"), root);
+    assert!(root.is_prefix_of(&facet));
+    assert!(Facet::from("/category").is_prefix_of(&facet));
+    assert!(!Facet::from("/cat").is_prefix_of(&facet));
+    assert!(!facet.is_prefix_of(&facet));
+
+    let prefixes: Vec<&[u8]> = facet.prefixes().collect();
+    assert_eq!(prefixes.len(), 4);
+    assert!(prefixes[0].is_empty());
+    assert_eq!(prefixes[3], facet.encoded_bytes());
+
+    // the facet tokenizer emits the same prefixes.
+    let mut tokens: Vec<Vec<u8>> = Vec::new();
+    let mut token_stream =
+        FacetTokenizer.token_stream(str::from_utf8(facet.encoded_bytes()).unwrap());
+    while token_stream.advance() {
+        tokens.push(token_stream.token().text.as_bytes().to_owned());
+    }
+    let prefixes: Vec<Vec<u8>> = prefixes.into_iter().map(|bytes| bytes.to_owned()).collect();
+    assert_eq!(tokens, prefixes);
+
+    let schema = book_schema();
+    let fields = book_fields(&schema);
+    let mut doc = doc!(fields.facet => Facet::from("/lang/fr"));
+    doc.add_facet_with_label(fields.facet, "/lang/en", "English");
+    assert_eq!(doc.facet_label(fields.facet, &Facet::from("/lang/en")), Some("English"));
+    assert_eq!(doc.facet_label(fields.facet, &Facet::from("/lang/fr")), None);
+    assert_eq!(doc.get_all(fields.facet).len(), 2);
+}